@@ -181,6 +181,28 @@ export const getCurrentApiUrl = () => {
 const MAX_RETRIES = 3;
 const RETRY_DELAY = 1000;
 
+// Tokens live for settings.session_timeout minutes; a 401 renews the token once through
+// /auth/refresh (accepted shortly after expiry) and replays the request. Concurrent 401s share one refresh.
+let refreshPromise: Promise<string | null> | null = null;
+
+const refreshToken = (): Promise<string | null> => {
+  if (!refreshPromise) {
+    refreshPromise = api
+      .post('/auth/refresh', null, { __skipAuthRefresh: true } as any)
+      .then(async (response) => {
+        const token = response.data?.data?.token;
+        if (!token) return null;
+        await saveToken(token);
+        return token as string;
+      })
+      .catch(() => null)
+      .finally(() => {
+        refreshPromise = null;
+      });
+  }
+  return refreshPromise;
+};
+
 // Request interceptor
 api.interceptors.request.use(
   async (config) => {
@@ -231,6 +253,21 @@ api.interceptors.response.use(
       }
     }
     
+    if (
+      error.response?.status === 401 &&
+      config &&
+      !config.__skipAuthRefresh &&
+      !config.__authRetried &&
+      !config.url?.includes('/auth/login')
+    ) {
+      config.__authRetried = true;
+      const token = await refreshToken();
+      if (token) {
+        config.headers.Authorization = `Bearer ${token}`;
+        return api(config);
+      }
+    }
+
     if (error.response?.status === 401) {
       try {
         // Clear auth data using unified auth storage
@@ -1,4 +1,5 @@
-use sqlx::{sqlite::{SqlitePool, SqlitePoolOptions}, Row};
+use sqlx::{sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous}, ConnectOptions, Row};
+use std::str::FromStr;
 use std::path::PathBuf;
 use tracing::{info, error, warn};
 use anyhow::Result;
@@ -10,6 +11,40 @@ pub struct Database {
 }
 
 impl Database {
+    // Pool tuning defaults, overridable through DB_IDLE_TIMEOUT_SECS / DB_MAX_LIFETIME_SECS (0 disables)
+    const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 600;
+    const DEFAULT_MAX_LIFETIME_SECS: u64 = 1800;
+    // DB_MAX_CONNECTIONS / DB_ACQUIRE_TIMEOUT_SECS / DB_BUSY_TIMEOUT_SECS
+    const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+    const DEFAULT_ACQUIRE_TIMEOUT_SECS: u64 = 30;
+    const DEFAULT_BUSY_TIMEOUT_SECS: u64 = 5;
+    // Attempts after the first for with_retry
+    const MAX_BUSY_RETRIES: u32 = 5;
+    // Statements slower than this are logged at warn with their SQL; SLOW_QUERY_MS overrides it
+    const DEFAULT_SLOW_QUERY_MS: u64 = 200;
+
+    fn slow_query_threshold() -> std::time::Duration {
+        let millis = match std::env::var("SLOW_QUERY_MS") {
+            Ok(value) => value.trim().parse::<u64>().unwrap_or_else(|_| {
+                warn!("Invalid SLOW_QUERY_MS={}, using {}ms", value, Self::DEFAULT_SLOW_QUERY_MS);
+                Self::DEFAULT_SLOW_QUERY_MS
+            }),
+            Err(_) => Self::DEFAULT_SLOW_QUERY_MS,
+        };
+        std::time::Duration::from_millis(millis)
+    }
+
+    fn env_duration_secs(name: &str, default: u64) -> Option<std::time::Duration> {
+        let secs = match std::env::var(name) {
+            Ok(value) => value.trim().parse::<u64>().unwrap_or_else(|_| {
+                warn!("Invalid {}={}, using {}s", name, value, default);
+                default
+            }),
+            Err(_) => default,
+        };
+        (secs > 0).then(|| std::time::Duration::from_secs(secs))
+    }
+
     pub async fn new() -> Result<Self> {
         // Use DATABASE_URL environment variable if set, otherwise use default path
         let database_url = std::env::var("DATABASE_URL")
@@ -27,7 +62,38 @@ impl Database {
         } else {
             database_url
         };
-        
+
+        Self::connect(&database_url).await
+    }
+
+    // Open (creating when missing), initialize and migrate the database at a sqlite: URL
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        Self::connect_with(database_url, Self::pool_options()).await
+    }
+
+    // Pool size and timeouts from DB_MAX_CONNECTIONS / DB_ACQUIRE_TIMEOUT_SECS / DB_IDLE_TIMEOUT_SECS /
+    // DB_MAX_LIFETIME_SECS
+    pub fn pool_options() -> SqlitePoolOptions {
+        let max_connections = match std::env::var("DB_MAX_CONNECTIONS") {
+            Ok(value) => value.trim().parse::<u32>().ok().filter(|max| *max > 0).unwrap_or_else(|| {
+                warn!("Invalid DB_MAX_CONNECTIONS={}, using {}", value, Self::DEFAULT_MAX_CONNECTIONS);
+                Self::DEFAULT_MAX_CONNECTIONS
+            }),
+            Err(_) => Self::DEFAULT_MAX_CONNECTIONS,
+        };
+        let acquire_timeout = Self::env_duration_secs("DB_ACQUIRE_TIMEOUT_SECS", Self::DEFAULT_ACQUIRE_TIMEOUT_SECS)
+            .unwrap_or(std::time::Duration::from_secs(Self::DEFAULT_ACQUIRE_TIMEOUT_SECS));
+        info!("Pool: {} connections, {}s acquire timeout", max_connections, acquire_timeout.as_secs());
+
+        SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .acquire_timeout(acquire_timeout)
+            .idle_timeout(Self::env_duration_secs("DB_IDLE_TIMEOUT_SECS", Self::DEFAULT_IDLE_TIMEOUT_SECS))
+            .max_lifetime(Self::env_duration_secs("DB_MAX_LIFETIME_SECS", Self::DEFAULT_MAX_LIFETIME_SECS))
+    }
+
+    // connect() with the pool sized by the caller instead of the environment
+    pub async fn connect_with(database_url: &str, pool_options: SqlitePoolOptions) -> Result<Self> {
         let db_path = if database_url.starts_with("sqlite:") {
             let path_str = database_url.strip_prefix("sqlite:").unwrap();
             if path_str == ":memory:" {
@@ -62,21 +128,33 @@ impl Database {
         }
         
         info!("Connecting to database pool...");
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .acquire_timeout(std::time::Duration::from_secs(30))
-            .connect(&database_url)
+        let slow_query_threshold = Self::slow_query_threshold();
+        info!("Logging queries slower than {}ms", slow_query_threshold.as_millis());
+
+        // SQLite optimizations (matching Node.js database.js), applied to every pooled connection rather
+        // than to whichever one a one-off PRAGMA happens to run on.
+        // WAL lets readers run alongside the single writer, so a bigger pool mostly buys concurrent reads;
+        // writers still take turns. busy_timeout makes a writer that finds the lock taken wait for it
+        // instead of failing at once with SQLITE_BUSY. Keep it below the acquire timeout so a stuck
+        // writer shows up as a busy error, not as an exhausted pool.
+        let busy_timeout = Self::env_duration_secs("DB_BUSY_TIMEOUT_SECS", Self::DEFAULT_BUSY_TIMEOUT_SECS).unwrap_or_default();
+        info!("Busy timeout: {}ms", busy_timeout.as_millis());
+        let connect_options = SqliteConnectOptions::from_str(database_url)?
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .foreign_keys(true)
+            .busy_timeout(busy_timeout)
+            .pragma("cache_size", "10000")
+            .pragma("temp_store", "MEMORY")
+            .pragma("mmap_size", "268435456")
+            // sqlx times every statement, dynamic SQL built by services included
+            .log_slow_statements(log::LevelFilter::Warn, slow_query_threshold);
+
+        let pool = pool_options
+            .connect_with(connect_options)
             .await?;
         info!("Database pool connected successfully");
 
-        // Apply SQLite optimizations (matching Node.js database.js)
-        sqlx::query("PRAGMA journal_mode = WAL").execute(&pool).await?;
-        sqlx::query("PRAGMA synchronous = NORMAL").execute(&pool).await?;
-        sqlx::query("PRAGMA cache_size = 10000").execute(&pool).await?;
-        sqlx::query("PRAGMA temp_store = MEMORY").execute(&pool).await?;
-        sqlx::query("PRAGMA mmap_size = 268435456").execute(&pool).await?;
-        sqlx::query("PRAGMA foreign_keys = ON").execute(&pool).await?;
-
         let db = Database { pool };
         
         // Initialize database if needed
@@ -89,6 +167,9 @@ impl Database {
             info!("Database already initialized");
         }
 
+        // Apply schema migrations added after the base schema
+        crate::migrations::run_pending(&db.pool).await?;
+
         Ok(db)
     }
 
@@ -267,14 +348,12 @@ impl Database {
         self.create_upload_schedules_table().await?;
         self.create_delegate_sales_table().await?;
         self.create_delegate_collections_table().await?;
+        self.create_delegate_commissions_table().await?;
 
         
         // Create indexes for better performance
         self.create_indexes().await?;
         
-        // Create triggers
-        self.create_triggers().await?;
-        
         // Insert default data
         self.insert_default_data().await?;
         
@@ -1442,6 +1521,11 @@ impl Database {
             "CREATE INDEX IF NOT EXISTS idx_sales_status ON sales(status)",
             "CREATE INDEX IF NOT EXISTS idx_sales_created_by ON sales(created_by)",
             "CREATE INDEX IF NOT EXISTS idx_sales_created_at ON sales(created_at)",
+            "CREATE INDEX IF NOT EXISTS idx_sale_items_sale_id ON sale_items(sale_id)",
+            "CREATE INDEX IF NOT EXISTS idx_sale_items_product_id ON sale_items(product_id)",
+            "CREATE INDEX IF NOT EXISTS idx_purchase_items_purchase_id ON purchase_items(purchase_id)",
+            "CREATE INDEX IF NOT EXISTS idx_debts_customer_id ON debts(customer_id)",
+            "CREATE INDEX IF NOT EXISTS idx_money_box_transactions_box_id ON money_box_transactions(box_id)",
         ];
 
         for index_sql in indexes {
@@ -1452,34 +1536,6 @@ impl Database {
         Ok(())
     }
 
-    async fn create_triggers(&self) -> Result<()> {
-        info!("Creating database triggers...");
-        
-        // Basic triggers for stock management
-        let triggers = vec![
-            // Trigger to update product stock when sale items are inserted
-            r#"
-            CREATE TRIGGER IF NOT EXISTS trigger_sale_item_insert
-            AFTER INSERT ON sale_items
-            WHEN NEW.product_id IS NOT NULL AND NEW.product_id > 0 AND NEW.product_id IN (SELECT id FROM products WHERE id > 0)
-            BEGIN
-                UPDATE products 
-                SET current_stock = current_stock - NEW.quantity,
-                    total_sold = total_sold + NEW.quantity,
-                    updated_at = CURRENT_TIMESTAMP
-                WHERE id = NEW.product_id;
-            END
-            "#,
-        ];
-
-        for trigger_sql in triggers {
-            sqlx::query(trigger_sql).execute(&self.pool).await?;
-        }
-
-        info!("Database triggers created successfully");
-        Ok(())
-    }
-
     async fn insert_default_data(&self) -> Result<()> {
         info!("Inserting default data...");
 
@@ -1614,6 +1670,9 @@ impl Database {
                 ("sales.add", "إضافة المبيعات", "إضافة المبيعات", "sales"),
                 ("sales.edit", "تعديل المبيعات", "تعديل المبيعات", "sales"),
                 ("sales.delete", "حذف المبيعات", "حذف المبيعات", "sales"),
+                ("sales.discount_override", "تجاوز حد الخصم", "الموافقة على خصم الفاتورة فوق الحد المسموح", "sales"),
+                ("sales.zero_price", "بيع بسعر صفر", "إضافة مواد مجانية بسعر صفر إلى الفاتورة", "sales"),
+                ("sales.credit_limit_override", "تجاوز الحد الائتماني", "البيع بالآجل لعميل تجاوز حده الائتماني", "sales"),
                 ("customers.manage", "إدارة العملاء", "عرض وإضافة وتعديل وحذف العملاء", "customers"),
                 ("customers.view", "عرض العملاء", "عرض العملاء", "customers"),
                 ("customers.add", "إضافة العملاء", "إضافة العملاء", "customers"),
@@ -1825,6 +1884,40 @@ impl Database {
         }
     }
 
+    // Run `attempt` (a whole transaction: begin, work, commit) again when SQLite reports the database busy
+    // or locked, backing off 50ms, 100ms, 200ms... between tries. busy_timeout already waits on the lock
+    // inside a statement; this covers the cases it cannot, such as a read transaction that needs to upgrade
+    // to a write after another writer committed. Any other error is returned straight away.
+    pub async fn with_retry<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            match attempt().await {
+                Err(err) if retries < Self::MAX_BUSY_RETRIES && Self::is_busy(&err) => {
+                    retries += 1;
+                    let backoff = std::time::Duration::from_millis(50 << (retries - 1));
+                    warn!("Database busy ({}), retry {}/{} in {}ms", err, retries, Self::MAX_BUSY_RETRIES, backoff.as_millis());
+                    tokio::time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // SQLITE_BUSY (5) and SQLITE_LOCKED (6), extended codes included
+    pub fn is_busy(err: &anyhow::Error) -> bool {
+        match err.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::Database(db_err)) => db_err
+                .code()
+                .and_then(|code| code.parse::<i32>().ok())
+                .is_some_and(|code| matches!(code & 0xFF, 5 | 6)),
+            _ => false,
+        }
+    }
+
     // Database optimization (equivalent to Node.js optimize-database.js)
     pub async fn optimize(&self) -> Result<()> {
         info!("Starting database optimization...");
@@ -1951,3 +2044,165 @@ impl<'a> Transaction<'a> {
         &mut self.tx
     }
 }
+
+// Fresh, fully migrated database in its own temp directory for tests; the directory goes away with the guard
+#[cfg(test)]
+pub struct TestDatabase {
+    pub db: Database,
+    _dir: tempfile::TempDir,
+}
+
+// Points HOME at one temp directory for the whole test run, so code writing under ~/.urcash
+// (backups, uploads, caches) never touches the real one
+#[cfg(test)]
+pub fn test_home() -> &'static std::path::Path {
+    static HOME: std::sync::OnceLock<tempfile::TempDir> = std::sync::OnceLock::new();
+    HOME.get_or_init(|| {
+        let dir = tempfile::tempdir().expect("temp home");
+        std::env::set_var("HOME", dir.path());
+        dir
+    }).path()
+}
+
+#[cfg(test)]
+impl TestDatabase {
+    pub async fn new() -> Self {
+        test_home();
+        let dir = tempfile::tempdir().expect("temp dir");
+        let url = format!("sqlite:{}", dir.path().join("test.sqlite").display());
+        let db = Database::connect(&url).await.expect("test database");
+        Self { db, _dir: dir }
+    }
+
+    pub fn file(&self) -> PathBuf {
+        self._dir.path().join("test.sqlite")
+    }
+
+    pub async fn with_pool(pool_options: SqlitePoolOptions) -> Self {
+        test_home();
+        let dir = tempfile::tempdir().expect("temp dir");
+        let url = format!("sqlite:{}", dir.path().join("test.sqlite").display());
+        let db = Database::connect_with(&url, pool_options).await.expect("test database");
+        Self { db, _dir: dir }
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn connect_initializes_and_migrates_a_new_file() {
+        let db = TestDatabase::new().await;
+        let admin: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE username = 'admin'")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(admin, 1);
+        assert!(db.health_check().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn statements_over_the_threshold_are_logged_as_slow() {
+        crate::test_support::sql_events();
+        let db = TestDatabase::new().await;
+
+        sqlx::query("SELECT 1 AS quick_marker").execute(&db.pool).await.unwrap();
+        let started = std::time::Instant::now();
+        sqlx::query("WITH RECURSIVE slow_marker(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM slow_marker WHERE i < 5000000) SELECT COUNT(*) FROM slow_marker")
+            .execute(&db.pool).await.unwrap();
+        assert!(started.elapsed() >= Database::slow_query_threshold());
+
+        let events = crate::test_support::sql_events();
+        let slow: Vec<_> = events.iter().filter(|event| event.fields.contains("slow_marker")).collect();
+        assert_eq!(slow.len(), 1, "{events:#?}");
+        assert_eq!(slow[0].level, tracing::Level::WARN);
+        assert!(slow[0].fields.contains("slow statement"));
+        assert!(!events.iter().any(|event| event.fields.contains("quick_marker")));
+    }
+
+    // Hold `count` connections at once, then run a query on every one of them concurrently
+    async fn query_on_held_connections(db: &Database, count: usize) -> Result<Vec<i64>> {
+        let mut held = Vec::new();
+        for _ in 0..count {
+            held.push(db.pool.acquire().await?);
+        }
+        let queries = held.iter_mut().enumerate().map(|(n, conn)| {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) + ? FROM users").bind(n as i64).fetch_one(&mut **conn)
+        });
+        Ok(futures::future::try_join_all(queries).await?)
+    }
+
+    #[tokio::test]
+    async fn a_bigger_pool_serves_more_concurrent_queries_than_the_default() {
+        let timeout = std::time::Duration::from_millis(500);
+
+        let small = TestDatabase::with_pool(Database::pool_options().max_connections(Database::DEFAULT_MAX_CONNECTIONS).acquire_timeout(timeout)).await;
+        let err = query_on_held_connections(&small, 8).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<sqlx::Error>(), Some(sqlx::Error::PoolTimedOut)), "{err}");
+
+        let large = TestDatabase::with_pool(Database::pool_options().max_connections(12).acquire_timeout(timeout)).await;
+        let results = query_on_held_connections(&large, 8).await.unwrap();
+        assert_eq!(results, (1..=8).collect::<Vec<i64>>());
+        assert!(large.pool.size() >= 8);
+
+        // Every pooled connection waits on a locked database instead of failing at once
+        let busy_timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout").fetch_one(&large.pool).await.unwrap();
+        assert_eq!(busy_timeout, 5000);
+    }
+
+    #[tokio::test]
+    async fn a_busy_write_is_retried_once_the_lock_is_released() {
+        let db = TestDatabase::new().await;
+        // A second connection that gives up on a locked database at once, like a writer whose busy_timeout ran out
+        let impatient = SqliteConnectOptions::new()
+            .filename(db._dir.path().join("test.sqlite"))
+            .busy_timeout(std::time::Duration::ZERO)
+            .connect().await.unwrap();
+        let impatient = tokio::sync::Mutex::new(impatient);
+        let mut blocker = db.pool.begin().await.unwrap();
+        sqlx::query("UPDATE settings SET company_name = company_name WHERE id = 1").execute(&mut *blocker).await.unwrap();
+        let blocker = tokio::sync::Mutex::new(Some(blocker));
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+
+        let written = db.with_retry(|| async {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let result = sqlx::query("INSERT INTO expenses (description, amount, category, date) VALUES ('كهرباء', 15000, 'utilities', '2026-10-01')")
+                .execute(&mut *impatient.lock().await)
+                .await;
+            // The other writer finishes while this one backs off
+            if let Some(blocker) = blocker.lock().await.take() {
+                blocker.commit().await.unwrap();
+            }
+            Ok(result?.rows_affected())
+        }).await;
+
+        assert_eq!(written.unwrap(), 1);
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn errors_other_than_busy_are_not_retried() {
+        let db = TestDatabase::new().await;
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+
+        let result = db.with_retry(|| async {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            sqlx::query("INSERT INTO users (id, username, password, name, role) VALUES (1, 'admin', 'x', 'x', 'admin')")
+                .execute(&db.pool)
+                .await?;
+            Ok(())
+        }).await;
+
+        let err = result.unwrap_err();
+        assert!(!Database::is_busy(&err));
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+}
@@ -1,9 +1,9 @@
 use axum::{
-    routing::{get, post},
+    routing::{get, patch, post},
     Router,
     http::Method,
     extract::State,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
     extract::Path,
 };
@@ -12,7 +12,8 @@ use tower_http::cors::{CorsLayer, Any, AllowOrigin};
 use tower_http::compression::CompressionLayer;
 use tower::{ServiceBuilder};
 use axum::extract::DefaultBodyLimit;
-use http::header::{CONTENT_TYPE, AUTHORIZATION, CACHE_CONTROL, HeaderValue};
+use http::header::{CONTENT_TYPE, AUTHORIZATION, CACHE_CONTROL, ETAG, IF_NONE_MATCH, HeaderValue};
+use http::{HeaderMap, StatusCode};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use serde_json::json;
 use std::time::Duration;
@@ -25,8 +26,16 @@ mod routes;
 mod utils;
 mod controllers;
 mod migrations;
+mod middleware;
+#[cfg(test)]
+mod test_support;
 
 use database::Database;
+use middleware::readiness_middleware::readiness_middleware;
+use middleware::permission_middleware::{RequirePermission, require_permission};
+use middleware::request_id_middleware::{request_id_middleware, REQUEST_ID_HEADER};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use services::{
     auth_service::AuthService, 
     cache_service::CacheService, 
@@ -58,6 +67,10 @@ use services::{
     installments_service::InstallmentsService,
     delegates_service::DelegatesService,
     stock_movements_service::StockMovementsService,
+    stock_holds_service::StockHoldsService,
+    units_service::UnitsService,
+    category_service::CategoryService,
+    sequence_service::SequenceService,
     money_boxes_service::MoneyBoxesService,
     device_service::DeviceService,
     mobile_live_data_service::MobileLiveDataService,
@@ -87,6 +100,12 @@ use routes::{
     employees_routes,
     stocks_routes,
     stock_movements_routes,
+    stock_holds_routes,
+    units_routes,
+    category_routes,
+    inventory_routes,
+    sequences_routes,
+    barcode_routes,
     money_boxes_routes,
     devices_routes,
     mobile_live_data_routes,
@@ -98,14 +117,37 @@ use routes::{
     customer_receipts_routes,
 }; 
 
-// Health check handler
-async fn health_check() -> impl IntoResponse {
-    Json(json!({
-        "status": "ok",
+// Health check handler: probes the database and answers 503 when it cannot be queried. The probe is
+// bounded so an exhausted pool reports unhealthy instead of hanging the check.
+async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
+    let database_ok = matches!(
+        tokio::time::timeout(std::time::Duration::from_secs(3), state.db.health_check()).await,
+        Ok(Ok(true))
+    );
+    let status = if database_ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (status, Json(json!({
+        "status": if database_ok { "ok" } else { "error" },
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "service": "rust-server",
-        "version": env!("CARGO_PKG_VERSION")
-    }))
+        "version": env!("CARGO_PKG_VERSION"),
+        "database": {
+            "status": if database_ok { "ok" } else { "unavailable" },
+            "pool_size": state.db.pool.size(),
+            "idle_connections": state.db.pool.num_idle(),
+            "closed": state.db.pool.is_closed()
+        }
+    })))
+}
+
+// Readiness: 200 once startup has finished and business routes accept traffic, 503 until then
+async fn ready_check(State(state): State<AppState>) -> impl IntoResponse {
+    let ready = state.ready.load(Ordering::Acquire);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(json!({
+        "success": ready,
+        "ready": ready
+    })))
 }
 
 // Status check handler (matches Node.js /api/status)
@@ -116,24 +158,202 @@ async fn status_check() -> impl IntoResponse {
     }))
 }
 
+    // If-None-Match names the current ETag (weak form or * included)
+    fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+        headers
+            .get(IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == etag
+            }))
+            .unwrap_or(false)
+    }
+
     // Settings handler (using settings service)
-    async fn settings_handler(State(state): State<AppState>) -> impl IntoResponse {
-        match state.settings_service.get_all_settings(&state.db).await {
-            Ok(settings) => Json(json!({
-                "success": true,
-                "data": settings
-            })),
+    async fn settings_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+        // Polled by every client; served from the cache until it expires or a settings write busts it
+        let settings = state.cache_service.get_or_compute(
+            "settings:all",
+            std::time::Duration::from_secs(60),
+            state.settings_service.get_all_settings_with_etag(&state.db),
+        ).await;
+        match settings {
+            Ok((settings, etag)) => {
+                if etag_matches(&headers, &etag) {
+                    return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+                }
+
+                (
+                    [(ETAG, etag)],
+                    Json(json!({
+                        "success": true,
+                        "data": settings
+                    })),
+                ).into_response()
+            }
             Err(err) => {
                 tracing::error!("Failed to get settings: {}", err);
                 Json(json!({
                     "success": false,
                     "error": "Failed to get settings",
                     "data": null
+                })).into_response()
+            }
+        }
+    }
+
+    // Partial settings update: only the keys sent are written
+    async fn update_settings_handler(
+        State(state): State<AppState>,
+        Json(patch): Json<serde_json::Map<String, serde_json::Value>>,
+    ) -> impl IntoResponse {
+        match state.settings_service.update_partial(&state.db, patch).await {
+            Ok(settings) => {
+                state.cache_service.invalidate("settings").await;
+                Json(json!({
+                    "success": true,
+                    "message": "تم تحديث الإعدادات بنجاح",
+                    "data": settings
+                }))
+            }
+            Err(err) => {
+                tracing::error!("Failed to update settings: {}", err);
+                Json(json!({
+                    "success": false,
+                    "message": err.to_string(),
+                    "data": null
                 }))
             }
         }
     }
 
+    // Settings of this install as a portable JSON document, for copying to another branch
+    async fn export_settings_handler(State(state): State<AppState>) -> impl IntoResponse {
+        match state.settings_service.export(&state.db).await {
+            Ok(export) => Json(json!({
+                "success": true,
+                "message": "تم تصدير الإعدادات بنجاح",
+                "data": export
+            })),
+            Err(err) => {
+                tracing::error!("Failed to export settings: {}", err);
+                Json(json!({
+                    "success": false,
+                    "message": err.to_string(),
+                    "data": null
+                }))
+            }
+        }
+    }
+
+    // Apply an exported settings document; company identity is kept unless ?overwrite_identity=true
+    async fn import_settings_handler(
+        State(state): State<AppState>,
+        axum::extract::Query(query): axum::extract::Query<models::ImportSettingsQuery>,
+        Json(payload): Json<serde_json::Value>,
+    ) -> Response {
+        match state.settings_service.import(&state.db, payload, query.overwrite_identity).await {
+            Ok(settings) => {
+                state.cache_service.invalidate("settings").await;
+                Json(json!({
+                    "success": true,
+                    "message": "تم استيراد الإعدادات بنجاح",
+                    "data": settings
+                })).into_response()
+            }
+            Err(err) => {
+                tracing::error!("Failed to import settings: {}", err);
+                (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": err.to_string(),
+                    "data": null
+                }))).into_response()
+            }
+        }
+    }
+
+    // Upload the company logo (multipart field "logo"), then point settings.logo_url at the stored file
+    async fn upload_logo_handler(State(state): State<AppState>, mut multipart: axum::extract::Multipart) -> Response {
+        let mut upload: Option<(Vec<u8>, String)> = None;
+        while let Ok(Some(field)) = multipart.next_field().await {
+            if field.name() == Some("logo") {
+                let filename = field.file_name().unwrap_or("logo").to_string();
+                if let Ok(data) = field.bytes().await {
+                    upload = Some((data.to_vec(), filename));
+                }
+            }
+        }
+
+        let Some((bytes, filename)) = upload else {
+            return (StatusCode::BAD_REQUEST, Json(json!({
+                "success": false,
+                "message": "لم يتم رفع أي صورة",
+                "data": null
+            }))).into_response();
+        };
+
+        let stored = match state.file_service.store_image(&bytes, &filename).await {
+            Ok(url) => url,
+            Err(err) => {
+                tracing::error!("Failed to store logo: {}", err);
+                let status = if err.downcast_ref::<services::file_service::InvalidImage>().is_some() {
+                    StatusCode::BAD_REQUEST
+                } else {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                };
+                return (status, Json(json!({
+                    "success": false,
+                    "message": err.to_string(),
+                    "data": null
+                }))).into_response();
+            }
+        };
+
+        let mut patch = serde_json::Map::new();
+        patch.insert("logo_url".to_string(), json!(stored));
+        match state.settings_service.update_partial(&state.db, patch).await {
+            Ok(settings) => {
+                state.cache_service.invalidate("settings").await;
+                Json(json!({
+                    "success": true,
+                    "message": "تم رفع الشعار بنجاح",
+                    "data": settings
+                })).into_response()
+            }
+            Err(err) => {
+                tracing::error!("Failed to save logo url: {}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+                    "success": false,
+                    "message": err.to_string(),
+                    "data": null
+                }))).into_response()
+            }
+        }
+    }
+
+    // Remove uploads no row references any more; ?dry_run=true only lists them
+    async fn cleanup_uploads_handler(
+        State(state): State<AppState>,
+        axum::extract::Query(query): axum::extract::Query<models::CleanupQuery>,
+    ) -> Response {
+        match state.file_service.garbage_collect(&state.db, query.dry_run).await {
+            Ok(report) => Json(json!({
+                "success": true,
+                "message": "تم تنظيف الملفات المرفوعة بنجاح",
+                "data": report
+            })).into_response(),
+            Err(err) => {
+                tracing::error!("Failed to clean up uploads: {}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+                    "success": false,
+                    "message": err.to_string(),
+                    "data": null
+                }))).into_response()
+            }
+        }
+    }
+
     // Backup scheduler status handler
     async fn backup_scheduler_status_handler() -> impl IntoResponse {
         Json(json!({
@@ -151,7 +371,13 @@ async fn status_check() -> impl IntoResponse {
 
 // Branch config handler (using device config service)
 async fn branch_config_handler(State(state): State<AppState>) -> impl IntoResponse {
-    match state.device_config_service.get_config() {
+    // The config file is read from disk; a short TTL still picks up edits made outside the server
+    let config = state.cache_service.get_or_compute(
+        "branch_config",
+        std::time::Duration::from_secs(30),
+        async { state.device_config_service.get_config() },
+    ).await;
+    match config {
         Ok(config) => Json(json!({
             "success": true,
             "data": {
@@ -182,35 +408,26 @@ async fn branch_config_handler(State(state): State<AppState>) -> impl IntoRespon
 }
 
 // Performance monitoring handler
-async fn performance_check() -> impl IntoResponse {
-    use sysinfo::System;
-    
-    let mut system = System::new_all();
-    system.refresh_all();
-    
-    let pid = std::process::id();
-    let memory_kb = system.process(sysinfo::Pid::from(pid as usize))
-        .map(|p| p.memory())
-        .unwrap_or(0);
-    let uptime = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
+// Process figures come from the shared sampler instead of a full system scan per request
+async fn performance_check(State(state): State<AppState>) -> impl IntoResponse {
+    let sample = state.performance_service.process_sample();
+
     Json(json!({
         "status": "ok",
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "memory": {
-            "rss": format!("{} MB", memory_kb / 1024),
+            "rss": format!("{} MB", sample.memory_bytes / (1024 * 1024)),
+            "rssBytes": sample.memory_bytes,
+            "virtual": format!("{} MB", sample.virtual_memory_bytes / (1024 * 1024)),
             "heapTotal": "N/A",
             "heapUsed": "N/A", 
             "external": "N/A"
         },
         "cpu": {
-            "user": "N/A",
-            "system": "N/A"
+            "usage": format!("{:.1}%", sample.cpu_usage),
+            "usagePercent": sample.cpu_usage
         },
-        "uptime": format!("{} seconds", uptime),
+        "uptime": format!("{} seconds", sample.uptime_secs),
         "platform": std::env::consts::OS,
         "nodeVersion": format!("Rust {}", env!("CARGO_PKG_VERSION"))
     }))
@@ -267,17 +484,28 @@ fn is_main_device() -> bool {
 
 #[tokio::main]
 async fn main() {
-    // Initialize logging
+    // Load environment variables
+    dotenv::dotenv().ok();
+
+    // Initialize logging (LOG_FORMAT=json switches console output to structured JSON)
+    let json_logs = std::env::var("LOG_FORMAT")
+        .map(|f| f.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+    let log_file_layer = LogService::logs_dir().map(|dir| {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .with_writer(tracing_appender::rolling::daily(dir, LogService::LOG_FILE_PREFIX))
+    });
+
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
         ))
-        .with(tracing_subscriber::fmt::layer())
+        .with(if json_logs { Some(tracing_subscriber::fmt::layer().json()) } else { None })
+        .with(if json_logs { None } else { Some(tracing_subscriber::fmt::layer()) })
+        .with(log_file_layer)
         .init();
 
-    // Load environment variables
-    dotenv::dotenv().ok();
-    
     let port = std::env::var("PORT").unwrap_or_else(|_| "39000".to_string());
     
     tracing::info!("🚀 Starting Rust Server...");
@@ -303,36 +531,220 @@ async fn main() {
     }
     
     // Initialize all services to match Node.js functionality
-    let auth_service = AuthService::new();
-    let cache_service = CacheService::new();
-    let license_service = LicenseService::new();
-    let device_config_service = DeviceConfigService::new()
-        .expect("Failed to initialize device config service");
-    let settings_service = SettingsService::new();
-    let permissions_service = PermissionsService::new();
-    let bills_service = BillsService::new();
-            // let cashbox_service = CashBoxService::new(); // Removed - using money boxes only
-    let cloud_backup_service = CloudBackupService::new(license_service.clone());
-    let customer_service = CustomerService::new();
-    let supplier_service = SupplierService::new();
-    let supplier_payment_receipt_service = SupplierPaymentReceiptService::new();
-    let product_service = ProductService::new();
-    let sale_service = SaleService::new();
-    let purchase_service = PurchaseService::new();
-    let inventory_service = InventoryService::new();
-    let report_service = ReportService::new();
-    let expense_service = ExpenseService::new();
-    let employee_service = EmployeeService::new();
-    let debt_service = DebtService::new();
-    let stock_service = StockService::new();
-    let notification_service = NotificationService::new();
-    let backup_service = BackupService::new();
-    let validation_service = ValidationService::new();
-    let barcode_service = BarcodeService::new();
-    let file_service = FileService::new();
-    
+    let app_state = AppState::new(db);
     tracing::info!("✅ All services initialized successfully");
 
+    let startup_state = app_state.clone();
+
+    let app = app_router(app_state);
+
+    let addr = format!("0.0.0.0:{}", port).parse::<SocketAddr>().unwrap();
+    
+    tracing::info!("🌐 Server starting on {}", addr);
+
+    // Start the server
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    tracing::info!("✅ Server is running! Press Ctrl+C to stop.");
+
+    // Startup work runs with the listener already bound, so /api/health and /api/ready answer
+    // meanwhile; business routes stay at 503 until it flips the ready flag.
+    tokio::spawn(async move {
+        // License verification on startup (equivalent to Node.js license check). An inactive license
+        // does not hold the server back: activation itself goes through the API.
+        tracing::info!("🔐 Verifying license...");
+        let verification = tokio::time::timeout(
+            Duration::from_secs(15),
+            startup_state.license_service.verify_license_offline_first(false),
+        ).await;
+        match verification {
+            Ok(Ok(license)) if license.success => tracing::info!("License verified"),
+            Ok(Ok(license)) => tracing::warn!("No active license: {}", license.message.unwrap_or_default()),
+            Ok(Err(e)) => tracing::warn!("License verification failed: {}", e),
+            Err(_) => tracing::warn!("License verification timed out"),
+        }
+        startup_state.license_service.start_reverification();
+
+        // Period report snapshots (frequency from settings.report_snapshot_frequency)
+        startup_state.reports_service.start_snapshot_scheduler(startup_state.db.clone());
+
+        // Recurring expense templates due today
+        startup_state.expense_service.start_recurring_scheduler(startup_state.db.clone());
+
+        // Daily check of products' stock against their movements
+        startup_state.inventory_service.start_drift_scheduler(startup_state.db.clone());
+
+        // Nightly VACUUM/ANALYZE/REINDEX when DB_OPTIMIZE_HOUR is set
+        startup_state.database_service.start_optimize_scheduler(startup_state.db.clone());
+
+        // Initialize backup scheduler (equivalent to Node.js backupScheduler.startScheduler)
+        tracing::info!("⏰ Initializing backup scheduler...");
+        // Add backup scheduler initialization here
+
+        // Network discovery: the main device announces itself on the LAN; a secondary device with
+        // auto_connect listens for it (up to connection_timeout) and points its config at it
+        if is_main_device() {
+            tracing::info!("🌐 Starting network discovery service...");
+            startup_state.device_config_service.start_discovery_broadcast();
+        } else if let Ok(config) = startup_state.device_config_service.get_config() {
+            if config.auto_connect {
+                let discovery_state = startup_state.clone();
+                tokio::spawn(async move {
+                    let timeout = Duration::from_millis(config.connection_timeout as u64);
+                    match discovery_state.device_service.discover_main(timeout).await {
+                        Ok(Some(main)) => {
+                            tracing::info!("🌐 Discovered main device at {}:{}", main.ip, main.port);
+                            if main.ip != config.ip {
+                                if let Err(e) = discovery_state.device_config_service.update_ip(main.ip) {
+                                    tracing::warn!("Failed to save discovered main device address: {}", e);
+                                }
+                            }
+                        }
+                        Ok(None) => tracing::warn!("No main device announced itself within {:?}", timeout),
+                        Err(e) => tracing::warn!("Network discovery failed: {}", e),
+                    }
+                });
+            }
+        }
+
+        startup_state.ready.store(true, Ordering::Release);
+        tracing::info!("🚦 Startup complete, accepting requests");
+    });
+
+    // Connect info gives handlers and the rate limiter the caller's address
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+}
+
+// Application state shared across all handlers - complete version matching Node.js functionality
+#[derive(Clone)]
+pub struct AppState {
+    pub db: Database,
+    pub auth_service: AuthService,
+    pub cache_service: CacheService,
+    pub license_service: LicenseService,
+    pub device_config_service: DeviceConfigService,
+    pub settings_service: SettingsService,
+    pub permissions_service: PermissionsService,
+    pub bills_service: BillsService,
+    // pub cashbox_service: CashBoxService, // Removed - using money boxes only
+    pub cloud_backup_service: CloudBackupService,
+    pub customer_service: CustomerService,
+    pub supplier_service: SupplierService,
+    pub supplier_payment_receipt_service: SupplierPaymentReceiptService,
+    pub product_service: ProductService,
+    pub sale_service: SaleService,
+    pub purchase_service: PurchaseService,
+    pub inventory_service: InventoryService,
+    pub report_service: ReportService,
+    pub reports_service: ReportsService,
+    pub expense_service: ExpenseService,
+    pub employee_service: EmployeeService,
+    pub debt_service: DebtService,
+    pub stock_service: StockService,
+    pub notification_service: NotificationService,
+    pub backup_service: BackupService,
+    pub validation_service: ValidationService,
+    pub barcode_service: BarcodeService,
+    pub file_service: FileService,
+    pub installments_service: InstallmentsService,
+    pub delegates_service: DelegatesService,
+    pub stock_movements_service: StockMovementsService,
+    pub stock_holds_service: StockHoldsService,
+    pub units_service: UnitsService,
+    pub category_service: CategoryService,
+    pub sequence_service: SequenceService,
+    pub money_boxes_service: MoneyBoxesService,
+    pub device_service: DeviceService,
+    pub mobile_live_data_service: MobileLiveDataService,
+    pub performance_service: PerformanceService,
+    pub database_service: DatabaseService,
+    pub log_service: LogService,
+    pub branch_config_service: BranchConfigService,
+    pub customer_receipts_service: CustomerReceiptsService,
+    // Set once startup has finished; see readiness_middleware
+    pub ready: Arc<AtomicBool>,
+}
+
+impl AppState {
+    pub fn new(db: Database) -> Self {
+        let auth_service = AuthService::new();
+        let cache_service = CacheService::new();
+        let license_service = LicenseService::new();
+        let device_config_service = DeviceConfigService::new()
+            .expect("Failed to initialize device config service");
+        let settings_service = SettingsService::new();
+        let permissions_service = PermissionsService::new();
+        let bills_service = BillsService::new();
+        // let cashbox_service = CashBoxService::new(); // Removed - using money boxes only
+        let cloud_backup_service = CloudBackupService::new(license_service.clone());
+        let customer_service = CustomerService::new();
+        let supplier_service = SupplierService::new();
+        let supplier_payment_receipt_service = SupplierPaymentReceiptService::new();
+        let product_service = ProductService::new();
+        let sale_service = SaleService::new();
+        let purchase_service = PurchaseService::new();
+        let inventory_service = InventoryService::new();
+        let report_service = ReportService::new();
+        let expense_service = ExpenseService::new();
+        let employee_service = EmployeeService::new();
+        let debt_service = DebtService::new();
+        let stock_service = StockService::new();
+        let notification_service = NotificationService::new();
+        let backup_service = BackupService::new();
+        let validation_service = ValidationService::new();
+        let barcode_service = BarcodeService::new();
+        let file_service = FileService::new();
+
+        AppState {
+            db,
+            auth_service,
+            cache_service,
+            license_service,
+            device_config_service,
+            settings_service,
+            permissions_service,
+            bills_service,
+            // cashbox_service, // Removed - using money boxes only
+            cloud_backup_service,
+            customer_service,
+            supplier_service,
+            supplier_payment_receipt_service,
+            product_service,
+            sale_service,
+            purchase_service,
+            inventory_service,
+            report_service,
+            reports_service: ReportsService::new(),
+            expense_service,
+            employee_service,
+            debt_service,
+            stock_service,
+            notification_service,
+            backup_service,
+            validation_service,
+            barcode_service,
+            file_service,
+            installments_service: InstallmentsService::new(),
+            delegates_service: DelegatesService::new(),
+            stock_movements_service: StockMovementsService::new(),
+            stock_holds_service: StockHoldsService::new(),
+            units_service: UnitsService::new(),
+            category_service: CategoryService::new(),
+            sequence_service: SequenceService::new(),
+            money_boxes_service: MoneyBoxesService::new(),
+            device_service: DeviceService::new(),
+            mobile_live_data_service: MobileLiveDataService::new(),
+            performance_service: PerformanceService::new(),
+            database_service: DatabaseService::new(),
+            log_service: LogService::new(),
+            branch_config_service: BranchConfigService::new(),
+            customer_receipts_service: CustomerReceiptsService::new(),
+            ready: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+// The whole API with its middleware stack, ready to serve
+fn app_router(app_state: AppState) -> Router {
     // CORS configuration - specific origins for credentials support
     let cors = CorsLayer::new()
         .allow_origin([
@@ -348,8 +760,11 @@ async fn main() {
             CONTENT_TYPE,
             AUTHORIZATION,
             CACHE_CONTROL,
+            IF_NONE_MATCH,
             http::header::HeaderName::from_static("x-requested-with"),
+            http::header::HeaderName::from_static(REQUEST_ID_HEADER),
         ])
+        .expose_headers([ETAG, http::header::HeaderName::from_static(REQUEST_ID_HEADER)])
         .allow_credentials(true);
 
     // Middleware stack matching Node.js setup
@@ -361,14 +776,25 @@ async fn main() {
         // Tracing/logging
         .layer(tower_http::trace::TraceLayer::new_for_http());
 
-    // Create complete router matching all Node.js routes
-    let app = Router::new()
+    // Complete router matching all Node.js routes
+    Router::new()
         // Health and status endpoints
         .route("/api/health", get(health_check))
+        .route("/api/ready", get(ready_check))
         .route("/api/status", get(status_check))
         .route("/api/performance", get(performance_check))
         .route("/api/settings", get(settings_handler))
+        .route("/api/settings", patch(update_settings_handler)
+            .route_layer(axum::middleware::from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
         .route("/api/settings/backup/scheduler-status", get(backup_scheduler_status_handler))
+        .route("/api/settings/export", get(export_settings_handler)
+            .route_layer(axum::middleware::from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+        .route("/api/settings/import", post(import_settings_handler)
+            .route_layer(axum::middleware::from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+        .route("/api/settings/logo", post(upload_logo_handler)
+            .route_layer(axum::middleware::from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+        .route("/api/uploads/cleanup", post(cleanup_uploads_handler)
+            .route_layer(axum::middleware::from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
         .route("/api/notifications", get(|| async { Json(json!({"notifications": []})) }))
 
         
@@ -395,6 +821,12 @@ async fn main() {
         .merge(employees_routes())
         .merge(stocks_routes())
         .merge(stock_movements_routes())
+        .merge(stock_holds_routes())
+        .merge(units_routes())
+        .merge(category_routes())
+        .merge(inventory_routes())
+        .merge(sequences_routes())
+        .merge(barcode_routes())
         .merge(money_boxes_routes())
         .merge(devices_routes())
         .merge(mobile_live_data_routes())
@@ -405,115 +837,114 @@ async fn main() {
         .merge(branch_config_routes())
         .merge(customer_receipts_routes())
         // Static file serving (equivalent to app.use('/uploads', express.static))
-        .nest_service("/uploads", tower_http::services::ServeDir::new("uploads"))
+        .nest_service("/uploads", tower_http::services::ServeDir::new(FileService::uploads_dir()))
         
-        .with_state(AppState {
-            db,
-            auth_service,
-            cache_service,
-            license_service,
-            device_config_service,
-            settings_service,
-            permissions_service,
-            bills_service,
-            // cashbox_service, // Removed - using money boxes only
-            cloud_backup_service,
-            customer_service,
-                    supplier_service,
-        supplier_payment_receipt_service,
-        product_service,
-            sale_service,
-            purchase_service,
-            inventory_service,
-            report_service,
-            expense_service,
-            employee_service,
-            debt_service,
-            stock_service,
-            notification_service,
-            backup_service,
-            validation_service,
-            barcode_service,
-            file_service,
-            installments_service: InstallmentsService::new(),
-            delegates_service: DelegatesService::new(),
-            stock_movements_service: StockMovementsService::new(),
-            money_boxes_service: MoneyBoxesService::new(),
-            device_service: DeviceService::new(),
-            mobile_live_data_service: MobileLiveDataService::new(),
-            performance_service: PerformanceService::new(),
-            database_service: DatabaseService::new(),
-            log_service: LogService::new(),
-            branch_config_service: BranchConfigService::new(),
-            customer_receipts_service: CustomerReceiptsService::new(),
-        })
+        .with_state(app_state.clone())
+        // Lets route-level middleware (e.g. RequirePermission) reach services without a state value
+        .layer(axum::middleware::from_fn_with_state(app_state.ready.clone(), readiness_middleware))
+        .layer(axum::Extension(app_state))
         .layer(cors)
-        .layer(middleware_stack);
+        .layer(middleware_stack)
+        // Outermost so the request span wraps tracing, handlers and service logs
+        .layer(axum::middleware::from_fn(request_id_middleware))
+}
 
-    let addr = format!("0.0.0.0:{}", port).parse::<SocketAddr>().unwrap();
-    
-    // License verification on startup (equivalent to Node.js license check)
-    tracing::info!("🔐 Verifying license...");
-    // Add license verification logic here similar to Node.js
-    
-    // Initialize backup scheduler (equivalent to Node.js backupScheduler.startScheduler)
-    tracing::info!("⏰ Initializing backup scheduler...");
-    // Add backup scheduler initialization here
-    
-    // Network discovery for main devices (equivalent to Node.js networkDiscoveryService)
-    if is_main_device() {
-        tracing::info!("🌐 Starting network discovery service...");
-        // Add network discovery service initialization here  
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestApp;
+
+    #[test]
+    fn if_none_match_accepts_listed_weak_and_wildcard_tags() {
+        let etag = "\"3f2a\"";
+        let matches = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+            etag_matches(&headers, etag)
+        };
+
+        assert!(matches("\"3f2a\""));
+        assert!(matches("\"old\", W/\"3f2a\""));
+        assert!(matches("*"));
+        assert!(!matches("\"old\""));
+        assert!(!etag_matches(&HeaderMap::new(), etag));
     }
 
-    tracing::info!("🌐 Server starting on {}", addr);
+    #[tokio::test]
+    async fn settings_can_be_read_by_anyone_but_patched_only_with_settings_manage() {
+        let app = TestApp::new().await;
+        app.add_user("cashier", "user", &[]).await;
+        app.add_user("manager", "manager", &["settings.manage"]).await;
+        let patch = serde_json::json!({ "company_name": "أسواق الرافدين" });
 
-    // Start the server
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    tracing::info!("✅ Server is running! Press Ctrl+C to stop.");
-    
-    axum::serve(listener, app).await.unwrap();
-}
+        let (status, _) = app.request(Method::GET, "/api/settings", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app.request(Method::PATCH, "/api/settings", None, Some(patch.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let cashier = app.login("cashier").await;
+        let (status, _) = app.request(Method::PATCH, "/api/settings", Some(&cashier), Some(patch.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
 
-// Application state shared across all handlers - complete version matching Node.js functionality
-#[derive(Clone)]
-pub struct AppState {
-    pub db: Database,
-    pub auth_service: AuthService,
-    pub cache_service: CacheService,
-    pub license_service: LicenseService,
-    pub device_config_service: DeviceConfigService,
-    pub settings_service: SettingsService,
-    pub permissions_service: PermissionsService,
-    pub bills_service: BillsService,
-    // pub cashbox_service: CashBoxService, // Removed - using money boxes only
-    pub cloud_backup_service: CloudBackupService,
-    pub customer_service: CustomerService,
-    pub supplier_service: SupplierService,
-    pub supplier_payment_receipt_service: SupplierPaymentReceiptService,
-    pub product_service: ProductService,
-    pub sale_service: SaleService,
-    pub purchase_service: PurchaseService,
-    pub inventory_service: InventoryService,
-    pub report_service: ReportService,
-    pub expense_service: ExpenseService,
-    pub employee_service: EmployeeService,
-    pub debt_service: DebtService,
-    pub stock_service: StockService,
-    pub notification_service: NotificationService,
-    pub backup_service: BackupService,
-    pub validation_service: ValidationService,
-    pub barcode_service: BarcodeService,
-    pub file_service: FileService,
-    pub installments_service: InstallmentsService,
-    pub delegates_service: DelegatesService,
-    pub stock_movements_service: StockMovementsService,
-    pub money_boxes_service: MoneyBoxesService,
-    pub device_service: DeviceService,
-    pub mobile_live_data_service: MobileLiveDataService,
-    pub performance_service: PerformanceService,
-    pub database_service: DatabaseService,
-    pub log_service: LogService,
-    pub branch_config_service: BranchConfigService,
-    pub customer_receipts_service: CustomerReceiptsService,
+        let manager = app.login("manager").await;
+        let (status, body) = app.request(Method::PATCH, "/api/settings", Some(&manager), Some(patch)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["company_name"], "أسواق الرافدين");
+    }
+
+    #[tokio::test]
+    async fn settings_write_replaces_the_cached_copy() {
+        let app = TestApp::new().await;
+        app.add_user("manager", "manager", &["settings.manage"]).await;
+        let manager = app.login("manager").await;
+
+        // The first read fills the cache
+        let (_, before) = app.request(Method::GET, "/api/settings", None, None).await;
+        assert_ne!(before["data"]["company_name"], "مكتبة دجلة");
+
+        let patch = serde_json::json!({ "company_name": "مكتبة دجلة" });
+        let (status, _) = app.request(Method::PATCH, "/api/settings", Some(&manager), Some(patch)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, after) = app.request(Method::GET, "/api/settings", None, None).await;
+        assert_eq!(after["data"]["company_name"], "مكتبة دجلة");
+    }
+
+    #[tokio::test]
+    async fn health_check_answers_503_once_the_pool_is_closed() {
+        let app = TestApp::new().await;
+
+        let (status, body) = app.request(Method::GET, "/api/health", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["database"]["status"], "ok");
+
+        app.state.db.pool.close().await;
+        let (status, body) = app.request(Method::GET, "/api/health", None, None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["database"]["status"], "unavailable");
+        assert_eq!(body["database"]["closed"], true);
+    }
+
+    #[tokio::test]
+    async fn business_routes_wait_for_the_ready_flag() {
+        let app = TestApp::new().await;
+        app.state.ready.store(false, Ordering::Release);
+
+        let (status, body) = app.request(Method::GET, "/api/settings", None, None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["success"], false);
+        let (status, body) = app.request(Method::GET, "/api/ready", None, None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["ready"], false);
+        let (status, _) = app.request(Method::GET, "/api/health", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        app.state.ready.store(true, Ordering::Release);
+        let (status, _) = app.request(Method::GET, "/api/settings", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = app.request(Method::GET, "/api/ready", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["ready"], true);
+    }
 }
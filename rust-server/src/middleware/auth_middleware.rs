@@ -55,7 +55,7 @@ pub async fn auth_middleware(
     };
 
     // Check if user is active
-    if user.is_active != 1 {
+    if !user.is_active() {
         warn!("Inactive user attempted to access protected route: {}", user.username);
         return Err(StatusCode::FORBIDDEN);
     }
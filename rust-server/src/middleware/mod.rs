@@ -2,4 +2,7 @@ pub mod auth_middleware;
 pub mod cors_middleware;
 pub mod error_middleware;
 pub mod logging_middleware;
+pub mod permission_middleware;
 pub mod rate_limit_middleware;
+pub mod readiness_middleware;
+pub mod request_id_middleware;
@@ -0,0 +1,121 @@
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::json;
+use tracing::{error, warn};
+
+use crate::AppState;
+
+// Permission required by a route, passed as the middleware state:
+// `delete(handler).route_layer(from_fn_with_state(RequirePermission("sales.delete"), require_permission))`
+#[derive(Clone, Copy, Debug)]
+pub struct RequirePermission(pub &'static str);
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({
+        "success": false,
+        "message": message
+    }))).into_response()
+}
+
+// Resolves the caller from the bearer token and checks user_permissions/role_permissions.
+// AppState is read from the request extensions (added as an Extension layer in main).
+pub async fn require_permission(
+    State(RequirePermission(permission)): State<RequirePermission>,
+    Extension(state): Extension<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.to_string());
+
+    let token = match token {
+        Some(token) => token,
+        None => return reject(StatusCode::UNAUTHORIZED, "يجب تسجيل الدخول أولاً"),
+    };
+
+    let user = match state.auth_service.get_user_from_token(&state.db, &token).await {
+        Ok(user) => user,
+        Err(e) => {
+            warn!("Permission check failed to resolve user: {}", e);
+            return reject(StatusCode::UNAUTHORIZED, "جلسة غير صالحة، يرجى تسجيل الدخول مجدداً");
+        }
+    };
+
+    let user_id = match user.id {
+        Some(id) if user.is_active() => id,
+        _ => return reject(StatusCode::FORBIDDEN, "الحساب غير مفعل"),
+    };
+
+    match state.permissions_service.has_permission(&state.db, user_id, permission).await {
+        Ok(true) => next.run(request).await,
+        Ok(false) => {
+            warn!("User {} denied: missing permission {}", user.username, permission);
+            reject(StatusCode::FORBIDDEN, "ليس لديك صلاحية لتنفيذ هذا الإجراء")
+        }
+        Err(e) => {
+            error!("Failed to check permission {} for user {}: {}", permission, user.username, e);
+            reject(StatusCode::FORBIDDEN, "ليس لديك صلاحية لتنفيذ هذا الإجراء")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use crate::test_support::TestApp;
+
+    async fn add_product(app: &TestApp) -> i64 {
+        sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price) VALUES ('Tea 500g', 'TEA-500', 3000, 3750, 3500)")
+            .execute(&app.db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    async fn product_exists(app: &TestApp, id: i64) -> bool {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM products WHERE id = ?)").bind(id).fetch_one(&app.db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn guarded_route_needs_a_session_and_the_permission() {
+        let app = TestApp::new().await;
+        app.add_user("cashier", "user", &["products.view"]).await;
+        app.add_user("storekeeper", "user", &["products.delete"]).await;
+        let id = add_product(&app).await;
+        let uri = format!("/api/products/{id}");
+
+        let (status, _) = app.request(Method::DELETE, &uri, None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = app.request(Method::DELETE, &uri, Some("not-a-token"), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let cashier = app.login("cashier").await;
+        let (status, body) = app.request(Method::DELETE, &uri, Some(&cashier), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["success"], false);
+        assert!(product_exists(&app, id).await);
+
+        let storekeeper = app.login("storekeeper").await;
+        let (status, _) = app.request(Method::DELETE, &uri, Some(&storekeeper), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!product_exists(&app, id).await);
+    }
+
+    #[tokio::test]
+    async fn admins_pass_through_role_permissions() {
+        let app = TestApp::new().await;
+        app.add_user("owner", "admin", &[]).await;
+        let id = add_product(&app).await;
+
+        let token = app.login("owner").await;
+        let (status, _) = app.request(Method::DELETE, &format!("/api/products/{id}"), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,35 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// Probes stay reachable while starting so the desktop shell can tell "up" from "ready"
+const ALWAYS_OPEN: &[&str] = &["/api/health", "/api/ready", "/api/status"];
+
+// Answers 503 on API routes until startup (license verification, schedulers) has finished and
+// flipped the flag. The listener is bound before that, so early requests would otherwise see
+// half-initialized state.
+pub async fn readiness_middleware(State(ready): State<Arc<AtomicBool>>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if ready.load(Ordering::Acquire) || !path.starts_with("/api/") || ALWAYS_OPEN.contains(&path) {
+        return next.run(request).await;
+    }
+
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [("Retry-After", "1")],
+        Json(json!({
+            "success": false,
+            "message": "الخادم قيد التشغيل، يرجى المحاولة بعد قليل"
+        })),
+    )
+        .into_response()
+}
@@ -0,0 +1,83 @@
+use axum::{
+    extract::Request,
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Reads X-Request-Id from the client (or generates one), runs the rest of the stack
+// inside a span carrying the id so every log line is correlated, and echoes it back.
+pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+    );
+
+    // A generated id is written into the request too, so handlers read the same id from the header
+    let header = HeaderValue::from_str(&request_id).ok();
+    if let Some(value) = header.clone() {
+        request.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Some(value) = header {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::HeaderMap, routing::get, Router};
+    use tower::Service;
+
+    // Echoes the id the handler sees in the body so it can be compared with the response header
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|headers: HeaderMap| async move {
+                headers.get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string()
+            }))
+            .layer(axum::middleware::from_fn(request_id_middleware))
+    }
+
+    async fn call(request: Request) -> (String, String) {
+        let response = app().call(request).await.unwrap();
+        let header = response.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (header, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn client_id_reaches_the_handler_and_is_echoed() {
+        let request = Request::builder().uri("/").header(REQUEST_ID_HEADER, "pos-7-000123").body(Body::empty()).unwrap();
+        assert_eq!(call(request).await, ("pos-7-000123".to_string(), "pos-7-000123".to_string()));
+    }
+
+    #[tokio::test]
+    async fn missing_or_oversized_id_is_replaced_by_a_generated_one() {
+        for incoming in [None, Some("x".repeat(200))] {
+            let mut builder = Request::builder().uri("/");
+            if let Some(ref id) = incoming {
+                builder = builder.header(REQUEST_ID_HEADER, id);
+            }
+            let (header, seen_by_handler) = call(builder.body(Body::empty()).unwrap()).await;
+            assert!(uuid::Uuid::parse_str(&header).is_ok());
+            assert_eq!(header, seen_by_handler);
+        }
+    }
+}
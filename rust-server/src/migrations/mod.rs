@@ -1 +1,441 @@
-// TODO: Implement migrations
+use anyhow::Result;
+use sqlx::SqlitePool;
+use tracing::info;
+
+// Schema change applied on top of the base tables created by Database::initialize_database.
+// Versions continue the numbering seeded into schema_migrations (001-023).
+pub struct Migration {
+    pub version: &'static str,
+    pub description: &'static str,
+    pub statements: &'static [&'static str],
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "024",
+        description: "Create exchange_rate_history table",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS exchange_rate_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                rate DECIMAL(10,4) NOT NULL CHECK(rate > 0),
+                effective_date DATE NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_exchange_rate_history_date ON exchange_rate_history(effective_date)",
+        ],
+    },
+    Migration {
+        version: "025",
+        description: "Add default_import_category setting",
+        statements: &[
+            "ALTER TABLE settings ADD COLUMN default_import_category TEXT DEFAULT NULL",
+        ],
+    },
+    Migration {
+        version: "026",
+        description: "Add anonymized_at to customers",
+        statements: &[
+            "ALTER TABLE customers ADD COLUMN anonymized_at DATETIME DEFAULT NULL",
+        ],
+    },
+    Migration {
+        version: "027",
+        description: "Add token_version to users for single-session enforcement",
+        statements: &[
+            "ALTER TABLE users ADD COLUMN token_version INTEGER NOT NULL DEFAULT 0",
+        ],
+    },
+    Migration {
+        version: "028",
+        description: "Unique sale barcodes and auto_generate_sale_barcode setting",
+        statements: &[
+            // Keep existing duplicates scannable but distinct before enforcing uniqueness
+            r#"
+            UPDATE sales SET barcode = barcode || '-' || id
+            WHERE barcode IS NOT NULL
+              AND id NOT IN (SELECT MIN(id) FROM sales WHERE barcode IS NOT NULL GROUP BY barcode)
+            "#,
+            "UPDATE sales SET barcode = NULL WHERE TRIM(barcode) = ''",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_sales_barcode_unique ON sales(barcode) WHERE barcode IS NOT NULL",
+            "ALTER TABLE settings ADD COLUMN auto_generate_sale_barcode INTEGER DEFAULT 1",
+        ],
+    },
+    Migration {
+        version: "029",
+        description: "Create stock_holds table for draft sale reservations",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS stock_holds (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                reference TEXT NOT NULL,
+                product_id INTEGER NOT NULL,
+                quantity INTEGER NOT NULL CHECK(quantity > 0),
+                status TEXT NOT NULL DEFAULT 'active' CHECK(status IN ('active', 'confirmed', 'released', 'expired')),
+                sale_id INTEGER,
+                expires_at DATETIME NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE CASCADE,
+                FOREIGN KEY (sale_id) REFERENCES sales(id) ON DELETE SET NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_stock_holds_product_status ON stock_holds(product_id, status)",
+            "CREATE INDEX IF NOT EXISTS idx_stock_holds_reference ON stock_holds(reference)",
+            "ALTER TABLE settings ADD COLUMN stock_hold_minutes INTEGER DEFAULT 30",
+        ],
+    },
+    Migration {
+        version: "030",
+        description: "One reports snapshot per type and period",
+        statements: &[
+            r#"
+            DELETE FROM reports WHERE id NOT IN (
+                SELECT MAX(id) FROM reports GROUP BY report_type, period_start, period_end
+            )
+            "#,
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_reports_period_unique ON reports(report_type, period_start, period_end)",
+        ],
+    },
+    Migration {
+        version: "031",
+        description: "Add report_snapshot_frequency setting",
+        statements: &[
+            "ALTER TABLE settings ADD COLUMN report_snapshot_frequency TEXT DEFAULT 'monthly'",
+        ],
+    },
+    Migration {
+        version: "032",
+        description: "Create units catalog seeded from existing product units",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS units (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                symbol TEXT,
+                base_unit_id INTEGER,
+                conversion_factor REAL NOT NULL DEFAULT 1 CHECK(conversion_factor > 0),
+                is_active INTEGER DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (base_unit_id) REFERENCES units(id) ON DELETE RESTRICT
+            )
+            "#,
+            "INSERT OR IGNORE INTO units (name) VALUES ('قطعة')",
+            "INSERT OR IGNORE INTO units (name) SELECT DISTINCT TRIM(unit) FROM products WHERE unit IS NOT NULL AND TRIM(unit) != ''",
+            "ALTER TABLE settings ADD COLUMN strict_units INTEGER DEFAULT 0",
+        ],
+    },
+    Migration {
+        version: "033",
+        description: "Create audit_logs table and invoice discount approval threshold",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_logs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER,
+                action TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id INTEGER,
+                details TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_audit_logs_entity ON audit_logs(entity_type, entity_id)",
+            "ALTER TABLE settings ADD COLUMN invoice_discount_threshold REAL DEFAULT 0",
+            "INSERT OR IGNORE INTO permissions (permission_id, name, description, category) VALUES ('sales.discount_override', 'تجاوز حد الخصم', 'الموافقة على خصم الفاتورة فوق الحد المسموح', 'sales')",
+            "INSERT OR IGNORE INTO role_permissions (role, permission_id) VALUES ('admin', 'sales.discount_override')",
+        ],
+    },
+    Migration {
+        version: "034",
+        description: "Add allow_zero_price_items setting and zero price permission",
+        statements: &[
+            "ALTER TABLE settings ADD COLUMN allow_zero_price_items INTEGER DEFAULT 0",
+            "INSERT OR IGNORE INTO permissions (permission_id, name, description, category) VALUES ('sales.zero_price', 'بيع بسعر صفر', 'إضافة مواد مجانية بسعر صفر إلى الفاتورة', 'sales')",
+            "INSERT OR IGNORE INTO role_permissions (role, permission_id) VALUES ('admin', 'sales.zero_price')",
+        ],
+    },
+    Migration {
+        version: "035",
+        description: "Create recurring_expenses templates",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS recurring_expenses (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                description TEXT NOT NULL,
+                amount REAL NOT NULL CHECK(amount > 0),
+                category TEXT NOT NULL,
+                interval TEXT NOT NULL DEFAULT 'monthly' CHECK(interval IN ('daily', 'weekly', 'monthly', 'yearly')),
+                next_run DATE NOT NULL,
+                money_box_id INTEGER NOT NULL,
+                is_active INTEGER DEFAULT 1,
+                last_run DATE,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (money_box_id) REFERENCES money_boxes(id) ON DELETE RESTRICT
+            )
+            "#,
+            "ALTER TABLE expenses ADD COLUMN recurring_expense_id INTEGER REFERENCES recurring_expenses(id) ON DELETE SET NULL",
+            // One generated expense per template and date, however often the generator runs
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_expenses_recurring_date ON expenses(recurring_expense_id, date) WHERE recurring_expense_id IS NOT NULL",
+        ],
+    },
+    Migration {
+        version: "036",
+        description: "Create customer_receipt_allocations for receipts covering several debts",
+        statements: &[
+            // debt_id is kept without a foreign key: settled debts are deleted like everywhere else
+            r#"
+            CREATE TABLE IF NOT EXISTS customer_receipt_allocations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                receipt_id INTEGER NOT NULL,
+                debt_id INTEGER NOT NULL,
+                sale_id INTEGER,
+                amount REAL NOT NULL CHECK(amount > 0),
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (receipt_id) REFERENCES customer_receipts(id) ON DELETE CASCADE,
+                FOREIGN KEY (sale_id) REFERENCES sales(id) ON DELETE SET NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_customer_receipt_allocations_receipt ON customer_receipt_allocations(receipt_id)",
+        ],
+    },
+    Migration {
+        version: "037",
+        description: "Create number_sequences for gap-free receipt numbering",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS number_sequences (
+                name TEXT PRIMARY KEY,
+                prefix TEXT NOT NULL DEFAULT '',
+                padding INTEGER NOT NULL DEFAULT 6 CHECK(padding BETWEEN 1 AND 12),
+                next_value INTEGER NOT NULL DEFAULT 1 CHECK(next_value > 0),
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            // Old customer receipts are CR + year/month + counter, so a shorter series cannot collide with them
+            "INSERT OR IGNORE INTO number_sequences (name, prefix, padding, next_value) VALUES ('customer_receipt', 'CR', 6, 1)",
+            // Continue after the highest SPRnnnnnn already issued
+            r#"
+            INSERT OR IGNORE INTO number_sequences (name, prefix, padding, next_value)
+            SELECT 'supplier_payment_receipt', 'SPR', 6,
+                   COALESCE(MAX(CAST(SUBSTR(receipt_number, 4) AS INTEGER)), 0) + 1
+            FROM supplier_payment_receipts WHERE receipt_number GLOB 'SPR[0-9]*'
+            "#,
+        ],
+    },
+    Migration {
+        version: "038",
+        description: "Add CSV export formatting settings",
+        statements: &[
+            "ALTER TABLE settings ADD COLUMN csv_delimiter TEXT DEFAULT ','",
+            "ALTER TABLE settings ADD COLUMN csv_decimal_separator TEXT DEFAULT '.'",
+            "ALTER TABLE settings ADD COLUMN csv_decimals INTEGER DEFAULT 2",
+            "ALTER TABLE settings ADD COLUMN csv_include_bom INTEGER DEFAULT 1",
+        ],
+    },
+    Migration {
+        version: "039",
+        description: "Drop the sale item stock trigger; sales move stock in their own transaction",
+        statements: &[
+            "DROP TRIGGER IF EXISTS trigger_sale_item_insert",
+        ],
+    },
+    Migration {
+        version: "040",
+        description: "Index the foreign keys used by sale, purchase, debt and money box lookups",
+        statements: &[
+            "CREATE INDEX IF NOT EXISTS idx_sale_items_sale_id ON sale_items(sale_id)",
+            "CREATE INDEX IF NOT EXISTS idx_sale_items_product_id ON sale_items(product_id)",
+            "CREATE INDEX IF NOT EXISTS idx_purchase_items_purchase_id ON purchase_items(purchase_id)",
+            "CREATE INDEX IF NOT EXISTS idx_debts_customer_id ON debts(customer_id)",
+            "CREATE INDEX IF NOT EXISTS idx_money_box_transactions_box_id ON money_box_transactions(box_id)",
+        ],
+    },
+    Migration {
+        version: "041",
+        description: "Credit sales to an employee for commission",
+        statements: &[
+            "ALTER TABLE sales ADD COLUMN employee_id INTEGER REFERENCES employees(id) ON DELETE SET NULL",
+            "CREATE INDEX IF NOT EXISTS idx_sales_employee_id ON sales(employee_id, invoice_date)",
+        ],
+    },
+    Migration {
+        version: "042",
+        description: "Create devices registry for secondary device heartbeats",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS devices (
+                device_id TEXT PRIMARY KEY,
+                name TEXT,
+                ip TEXT,
+                branch TEXT,
+                last_seen DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_devices_last_seen ON devices(last_seen)",
+        ],
+    },
+    Migration {
+        version: "043",
+        description: "Create pending_sync log of mobile changes and how they were resolved",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS pending_sync (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                table_name TEXT NOT NULL,
+                record_id INTEGER,
+                operation TEXT NOT NULL CHECK(operation IN ('insert', 'update', 'delete')),
+                payload TEXT NOT NULL,
+                client_updated_at DATETIME NOT NULL,
+                strategy TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'applied', 'rejected')),
+                reason TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                resolved_at DATETIME
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_pending_sync_status ON pending_sync(status, created_at)",
+        ],
+    },
+    Migration {
+        version: "044",
+        description: "Log deletions from synced tables so devices can pull them as tombstones",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS deletions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                table_name TEXT NOT NULL,
+                record_id INTEGER NOT NULL,
+                deleted_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_deletions_table_deleted_at ON deletions(table_name, deleted_at)",
+            // Triggers rather than service code: rows leave these tables through many paths
+            "CREATE TRIGGER IF NOT EXISTS trg_customers_tombstone AFTER DELETE ON customers BEGIN INSERT INTO deletions (table_name, record_id) VALUES ('customers', OLD.id); END",
+            "CREATE TRIGGER IF NOT EXISTS trg_suppliers_tombstone AFTER DELETE ON suppliers BEGIN INSERT INTO deletions (table_name, record_id) VALUES ('suppliers', OLD.id); END",
+            "CREATE TRIGGER IF NOT EXISTS trg_products_tombstone AFTER DELETE ON products BEGIN INSERT INTO deletions (table_name, record_id) VALUES ('products', OLD.id); END",
+        ],
+    },
+    Migration {
+        version: "045",
+        description: "Track how much of a cloud backup has reached the remote so uploads can resume",
+        statements: &[
+            "ALTER TABLE cloud_backups ADD COLUMN uploaded_bytes INTEGER NOT NULL DEFAULT 0",
+            "CREATE INDEX IF NOT EXISTS idx_cloud_backups_remote_backup_id ON cloud_backups(remote_backup_id)",
+        ],
+    },
+    Migration {
+        version: "046",
+        description: "Add permission to sell on credit past a customer's credit limit",
+        statements: &[
+            "INSERT OR IGNORE INTO permissions (permission_id, name, description, category) VALUES ('sales.credit_limit_override', 'تجاوز الحد الائتماني', 'البيع بالآجل لعميل تجاوز حده الائتماني', 'sales')",
+            "INSERT OR IGNORE INTO role_permissions (role, permission_id) VALUES ('admin', 'sales.credit_limit_override')",
+        ],
+    },
+    Migration {
+        version: "047",
+        description: "Record which purchase batches each sale drew its stock from",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS sale_batch_allocations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                sale_id INTEGER NOT NULL,
+                product_id INTEGER NOT NULL,
+                purchase_item_id INTEGER NOT NULL,
+                quantity INTEGER NOT NULL CHECK(quantity > 0),
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (sale_id) REFERENCES sales(id) ON DELETE CASCADE,
+                FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE RESTRICT,
+                FOREIGN KEY (purchase_item_id) REFERENCES purchase_items(id) ON DELETE CASCADE
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_sale_batch_allocations_purchase_item ON sale_batch_allocations(purchase_item_id)",
+            "CREATE INDEX IF NOT EXISTS idx_sale_batch_allocations_sale_product ON sale_batch_allocations(sale_id, product_id)",
+        ],
+    },
+    Migration {
+        version: "048",
+        description: "Remember client idempotency keys so a resubmitted request returns what it created",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS idempotency_keys (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scope TEXT NOT NULL,
+                idempotency_key TEXT NOT NULL,
+                entity_id INTEGER NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(scope, idempotency_key)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created_at ON idempotency_keys(created_at)",
+        ],
+    },
+];
+
+// Apply every migration not yet recorded in schema_migrations
+pub async fn run_pending(pool: &SqlitePool) -> Result<()> {
+    for migration in MIGRATIONS {
+        let applied = sqlx::query("SELECT id FROM schema_migrations WHERE version = ?")
+            .bind(migration.version)
+            .fetch_optional(pool)
+            .await?;
+
+        if applied.is_some() {
+            continue;
+        }
+
+        info!("Applying migration {}: {}", migration.version, migration.description);
+        let started = std::time::Instant::now();
+
+        let mut tx = pool.begin().await?;
+        for statement in migration.statements {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, description, executed_at, execution_time_ms, status) VALUES (?, ?, CURRENT_TIMESTAMP, ?, 'success')"
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .bind(started.elapsed().as_millis() as i64)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        info!("✅ Migration {} applied", migration.version);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    #[tokio::test]
+    async fn a_database_missing_the_foreign_key_indexes_gets_them_back() {
+        let db = TestDatabase::new().await;
+        let indexes = || async {
+            sqlx::query_scalar::<_, String>(
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND name IN ('idx_sale_items_sale_id', 'idx_purchase_items_purchase_id', 'idx_money_box_transactions_box_id') ORDER BY name"
+            )
+            .fetch_all(&db.pool).await.unwrap()
+        };
+        assert_eq!(indexes().await.len(), 3);
+
+        // A database from before migration 040
+        for index in ["idx_sale_items_sale_id", "idx_purchase_items_purchase_id", "idx_money_box_transactions_box_id"] {
+            sqlx::query(&format!("DROP INDEX {}", index)).execute(&db.pool).await.unwrap();
+        }
+        sqlx::query("DELETE FROM schema_migrations WHERE version = '040'").execute(&db.pool).await.unwrap();
+        assert!(indexes().await.is_empty());
+
+        run_pending(&db.pool).await.unwrap();
+        assert_eq!(indexes().await, ["idx_money_box_transactions_box_id", "idx_purchase_items_purchase_id", "idx_sale_items_sale_id"]);
+    }
+}
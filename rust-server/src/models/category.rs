@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::NaiveDateTime;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Category {
+    pub id: i64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CategoryWithCount {
+    pub id: i64,
+    pub name: String,
+    pub product_count: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteCategoryQuery {
+    pub reassign: Option<bool>, // move the category's products to no category instead of refusing
+}
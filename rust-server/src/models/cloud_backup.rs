@@ -18,7 +18,8 @@ pub struct CloudBackup {
     pub remote_backup_id: Option<String>,
     pub checksum: Option<String>,
     pub compression_ratio: Option<f64>,
-    pub encryption_key: Option<String>,
+    pub encryption_key: Option<String>,    #[serde(default)]
+    pub uploaded_bytes: i64, // bytes acknowledged by the remote; a failed upload resumes from here
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +29,17 @@ pub struct CreateCloudBackupRequest {
     pub description: Option<String>,
 }
 
+// A backup held on the remote server, as listed for restoring onto this machine
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RemoteBackup {
+    pub backup_id: String,
+    pub backup_name: String,
+    pub description: Option<String>,
+    pub size: i64,
+    pub checksum: Option<String>, // hex sha256 of the uploaded file
+    pub created_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CloudBackupResponse {
     pub success: bool,
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use chrono::{DateTime, Utc, NaiveDateTime};
+use chrono::{DateTime, Utc, NaiveDateTime, NaiveDate};
+use crate::utils::{CsvFormat, CsvRow};
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Customer {
@@ -18,6 +19,8 @@ pub struct Customer {
     pub representative_id: Option<i64>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    #[sqlx(default)]
+    pub anonymized_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -177,6 +180,34 @@ pub struct CustomerReceipt {
     pub created_by_name: String,
 }
 
+impl CsvRow for CustomerReceipt {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "ID", "رقم الإيصال", "تاريخ الإيصال", "المبلغ", "طريقة الدفع", "المرجع", "ملاحظات", "تاريخ الإنشاء",
+            "اسم العميل", "هاتف العميل", "بريد العميل", "رقم الفاتورة", "أنشئ بواسطة",
+        ]
+    }
+
+    fn to_record(&self, format: &CsvFormat) -> Vec<String> {
+        let text = |value: &Option<String>| format.text(value.as_deref().unwrap_or_default());
+        vec![
+            self.id.to_string(),
+            format.text(&self.receipt_number),
+            format.datetime(self.receipt_date),
+            format.number(self.amount),
+            format.text(&self.payment_method),
+            text(&self.reference_number),
+            text(&self.notes),
+            format.datetime(self.created_at),
+            format.text(&self.customer_name),
+            text(&self.customer_phone),
+            text(&self.customer_email),
+            text(&self.sale_invoice_no),
+            format.text(&self.created_by_name),
+        ]
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomerFinancialSummary {
     pub id: i64,
@@ -212,6 +243,51 @@ pub struct CustomerBalance {
     pub available_credit: f64,
 }
 
+// Outstanding debt split by days past due
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct AgingBuckets {
+    pub current: f64,
+    pub days_1_30: f64,
+    pub days_31_60: f64,
+    pub days_61_90: f64,
+    pub days_over_90: f64,
+    pub total: f64,
+}
+
+impl AgingBuckets {
+    pub fn add(&mut self, amount: f64, days_overdue: i64) {
+        match days_overdue {
+            d if d <= 0 => self.current += amount,
+            1..=30 => self.days_1_30 += amount,
+            31..=60 => self.days_31_60 += amount,
+            61..=90 => self.days_61_90 += amount,
+            _ => self.days_over_90 += amount,
+        }
+        self.total += amount;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerAging {
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub phone: Option<String>,
+    #[serde(flatten)]
+    pub buckets: AgingBuckets,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerAgingReport {
+    pub as_of_date: NaiveDate,
+    pub customers: Vec<CustomerAging>,
+    pub totals: AgingBuckets,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AgingReportQuery {
+    pub as_of: Option<NaiveDate>,
+}
+
 impl Customer {
     pub fn new(
         name: String,
@@ -240,6 +316,7 @@ impl Customer {
             representative_id,
             created_at: now,
             updated_at: now,
+            anonymized_at: None,
         }
     }
 
@@ -256,3 +333,58 @@ impl Customer {
         self.updated_at = Utc::now().naive_utc();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(id: i64, receipt_number: &str, amount: f64, notes: Option<&str>) -> CustomerReceipt {
+        let at = NaiveDate::from_ymd_opt(2026, 2, 9).unwrap().and_hms_opt(10, 30, 0).unwrap();
+        CustomerReceipt {
+            id,
+            receipt_number: receipt_number.to_string(),
+            customer_id: 12,
+            sale_id: None,
+            receipt_date: at,
+            amount,
+            payment_method: "cash".to_string(),
+            reference_number: None,
+            notes: notes.map(str::to_string),
+            created_at: at,
+            updated_at: at,
+            customer_name: "Sara Mahdi".to_string(),
+            customer_phone: Some("07701112233".to_string()),
+            customer_email: None,
+            sale_invoice_no: None,
+            created_by_name: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn receipts_read_back_field_for_field() {
+        let rows = [
+            receipt(1, "CR000001", 25000.0, Some("paid \"in full\"; thanks")),
+            receipt(2, "CR000002", 1250.755, None),
+        ];
+        let european = CsvFormat { delimiter: ';', decimal_separator: ',', ..CsvFormat::default() };
+        for (format, rounded) in [(CsvFormat::default(), "1250.76"), (european, "1250,76")] {
+            let mut csv = format.header(&CustomerReceipt::headers());
+            for row in &rows {
+                csv.push_str(&format.row(&row.to_record(&format)));
+            }
+            let csv = format.finish(csv);
+
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(format.delimiter as u8)
+                .from_reader(csv.trim_start_matches('\u{FEFF}').as_bytes());
+            assert_eq!(reader.headers().unwrap().iter().collect::<Vec<_>>(), CustomerReceipt::headers());
+            let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+            assert_eq!(records.len(), 2);
+            assert_eq!(&records[0][1], "CR000001");
+            assert_eq!(&records[0][2], "2026-02-09 10:30:00");
+            assert_eq!(&records[0][6], "paid \"in full\"; thanks");
+            assert_eq!(&records[1][3], rounded);
+            assert_eq!(&records[1][6], "");
+        }
+    }
+}
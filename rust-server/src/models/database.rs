@@ -45,6 +45,50 @@ pub struct FixMenuItemsResponse {
     pub message: String,
 }
 
+// Result of VACUUM/ANALYZE/REINDEX; sizes are the database file plus its WAL, in bytes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OptimizeReport {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub freed_bytes: i64, // negative when the file grew
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub in_use: u32,
+    pub max_connections: u32,
+    pub idle_timeout_secs: Option<u64>,
+    pub max_lifetime_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: String, // declared type as written in CREATE TABLE
+    pub nullable: bool,
+    pub default_value: Option<String>,
+    pub primary_key: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForeignKeySchema {
+    pub column: String,
+    pub references_table: String,
+    pub references_column: Option<String>, // None when the parent's primary key is implied
+    pub on_delete: String,
+    pub on_update: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    pub foreign_keys: Vec<ForeignKeySchema>,
+}
+
 // Database error messages (matching Node.js)
 pub const DATABASE_MESSAGES: &[(&str, &str)] = &[
     // Success Messages
@@ -53,11 +97,16 @@ pub const DATABASE_MESSAGES: &[(&str, &str)] = &[
     ("database_reset", "تم إعادة تعيين قاعدة البيانات بنجاح"),
     ("menu_items_fixed", "تم إصلاح عناصر القائمة بنجاح"),
     ("backups_fetched", "تم جلب قائمة النسخ الاحتياطية بنجاح"),
+    ("schema_fetched", "تم جلب بنية قاعدة البيانات بنجاح"),
+    ("pool_stats_fetched", "تم جلب حالة اتصالات قاعدة البيانات بنجاح"),
+    ("database_optimized", "تم تحسين قاعدة البيانات بنجاح"),
     
     // Error Messages
     ("backup_failed", "فشل في إنشاء نسخة احتياطية من قاعدة البيانات"),
     ("restore_failed", "فشل في استعادة قاعدة البيانات من النسخة الاحتياطية"),
     ("reset_failed", "فشل في إعادة تعيين قاعدة البيانات"),
+    ("optimize_failed", "فشل في تحسين قاعدة البيانات"),
+    ("optimize_in_progress", "تحسين قاعدة البيانات قيد التنفيذ بالفعل"),
     ("backup_not_found", "ملف النسخة الاحتياطية غير موجود"),
     ("database_busy", "قاعدة البيانات مشغولة حالياً. يرجى إغلاق جميع العمليات وإعادة المحاولة"),
     ("connection_failed", "فشل في الاتصال بقاعدة البيانات"),
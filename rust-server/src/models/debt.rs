@@ -137,3 +137,30 @@ pub struct RepayDebtResponse {
     pub excess_amount: f64,
     pub total_paid: f64,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApplyPaymentRequest {
+    pub customer_id: i64,
+    pub amount: f64,
+    pub payment_method: Option<String>,
+    pub money_box_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DebtAllocation {
+    pub debt_id: i64,
+    pub sale_id: i64,
+    pub invoice_no: String,
+    pub amount: f64,
+    pub remaining_amount: f64,
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApplyPaymentResult {
+    pub customer_id: i64,
+    pub total_paid: f64,
+    pub allocations: Vec<DebtAllocation>,
+    // Left over once every open debt is settled; kept as customer credit
+    pub unallocated: f64,
+}
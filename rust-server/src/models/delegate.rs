@@ -60,7 +60,7 @@ pub struct DelegateCommission {
     pub total_sales: f64,
     pub total_commission: f64,
     pub payment_amount: f64,
-    pub payment_date: NaiveDate,
+    pub payment_date: Option<NaiveDate>, // unset on calculated, not yet paid periods
     pub payment_method: Option<String>,
     pub notes: Option<String>,
     pub created_at: NaiveDateTime,
@@ -159,6 +159,15 @@ pub struct BulkAssignCustomersRequest {
 pub struct CommissionQuery {
     pub period_start: NaiveDate,
     pub period_end: NaiveDate,
+    pub recompute: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DelegateCommissionCalculation {
+    pub commission: DelegateCommission,
+    pub commission_type: String,
+    pub sales_count: Option<i64>, // None when an earlier calculation for the period was returned as-is
+    pub recalculated: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
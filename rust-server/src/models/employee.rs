@@ -73,6 +73,28 @@ pub struct CommissionCalculation {
     pub calculated_commission: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommissionReportQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+// What an employee earned on the sales credited to them. The period is the requested range
+// narrowed to the employee's commission window; rate is the percentage, or the amount per sale
+// for fixed commissions.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommissionReport {
+    pub employee_id: i64,
+    pub employee_name: String,
+    pub commission_type: String,
+    pub rate: f64,
+    pub period_start: Option<NaiveDate>,
+    pub period_end: Option<NaiveDate>,
+    pub sales_count: i64,
+    pub total_sales: f64,
+    pub commission_due: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmployeeListResponse {
     pub employees: Vec<Employee>,
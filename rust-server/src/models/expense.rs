@@ -59,6 +59,23 @@ pub struct ExpenseTotalByCategory {
     pub expense_count: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpenseCategoryTotal {
+    pub category: String,
+    pub count: i64,
+    pub total: f64,
+}
+
+// Per-category totals where spelling variants ("Rent", "rent ") are merged
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpenseCategoryBreakdown {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub categories: Vec<ExpenseCategoryTotal>,
+    pub count: i64,
+    pub total: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExpenseTotalByDateRange {
     pub total_amount: f64,
@@ -70,3 +87,48 @@ pub struct DateRangeQuery {
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
 }
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct RecurringExpense {
+    pub id: i64,
+    pub description: String,
+    pub amount: f64,
+    pub category: String,
+    pub interval: String, // daily, weekly, monthly, yearly
+    pub next_run: NaiveDate,
+    pub money_box_id: i64,
+    pub is_active: bool,
+    pub last_run: Option<NaiveDate>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRecurringExpenseRequest {
+    pub description: String,
+    pub amount: f64,
+    pub category: String,
+    pub interval: Option<String>,
+    pub next_run: NaiveDate,
+    pub money_box_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunRecurringRequest {
+    pub as_of: Option<NaiveDate>,
+}
+
+// A template that could not be generated on this run; it stays due for the next one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecurringExpenseFailure {
+    pub recurring_expense_id: i64,
+    pub due_date: NaiveDate,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecurringRunResult {
+    pub as_of: NaiveDate,
+    pub generated: Vec<Expense>,
+    pub failed: Vec<RecurringExpenseFailure>,
+}
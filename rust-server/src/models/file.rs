@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+// Outcome of an uploads cleanup; in a dry run `removed` and `freed_bytes` describe what would be deleted
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    pub scanned: usize,
+    pub referenced: usize,
+    pub too_recent: usize, // unreferenced but still inside the grace period
+    pub removed: usize,
+    pub freed_bytes: u64,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CleanupQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
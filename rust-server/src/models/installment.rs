@@ -56,6 +56,16 @@ pub struct InstallmentPaymentRequest {
     pub money_box_id: Option<i64>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GenerateScheduleRequest {
+    pub sale_id: i64,
+    pub customer_id: i64,
+    pub total_amount: f64,
+    pub num_installments: i64,
+    pub first_due_date: NaiveDate,
+    pub interval_days: Option<i64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateInstallmentPlanRequest {
     pub customer_id: i64,
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Inventory {
@@ -11,3 +11,113 @@ pub struct Inventory {
     pub available_quantity: i32,
     pub last_updated: NaiveDateTime,
 }
+
+// How stock on hand is priced in a valuation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostMethod {
+    AverageCost,
+    LastPurchasePrice,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockValuation {
+    pub stock_id: Option<i64>, // None for products not assigned to a warehouse
+    pub stock_name: Option<String>,
+    pub product_count: i64,
+    pub total_quantity: i64,
+    pub total_value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InventoryValuation {
+    pub method: CostMethod,
+    pub currency: String, // values are in the base currency, dollar-priced products converted
+    pub stocks: Vec<StockValuation>,
+    pub product_count: i64,
+    pub total_value: f64,
+    pub valued_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValuationQuery {
+    pub method: Option<CostMethod>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockCheckQuery {
+    pub days: Option<i64>,
+}
+
+// A product whose stored stock disagrees with the net of its recorded movements
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockDrift {
+    pub product_id: i64,
+    pub name: String,
+    pub sku: Option<String>,
+    pub current_stock: i64,
+    pub ledger_stock: i64,
+    pub drift: i64, // current_stock - ledger_stock
+}
+
+// Which side of a drift is taken as correct when reconciling
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileStrategy {
+    TrustLedger,
+    TrustCurrent,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReconcileRequest {
+    pub strategy: ReconcileStrategy,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReconcileResult {
+    pub product_id: i64,
+    pub strategy: ReconcileStrategy,
+    pub previous_stock: i64,
+    pub ledger_stock: i64,
+    pub current_stock: i64,
+}
+
+// One purchase line of a product, the unit batches are tracked in
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductBatch {
+    pub purchase_item_id: i64,
+    pub purchase_id: i64,
+    pub invoice_no: String,
+    pub invoice_date: NaiveDate,
+    pub batch_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub purchased_quantity: i64,
+    pub returned_quantity: i64,
+    pub sold_quantity: i64,
+    pub remaining_quantity: i64,
+}
+
+// One product to reorder; unit_price is the supplier's price when known, else the product's purchase price
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReorderLine {
+    pub product_id: i64,
+    pub name: String,
+    pub sku: String,
+    pub current_stock: i64,
+    pub reorder_point: i64,
+    pub max_stock: Option<i64>,
+    pub suggested_quantity: i64,
+    pub unit_price: f64,
+    pub lead_time: Option<i64>,
+    pub supplier_product_code: Option<String>,
+}
+
+// A draft purchase order for one supplier; supplier_id None groups products with no supplier link
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReorderSuggestion {
+    pub supplier_id: Option<i64>,
+    pub supplier_name: Option<String>,
+    pub lead_time: Option<i64>, // longest among the items
+    pub items: Vec<ReorderLine>,
+    pub estimated_total: f64,
+}
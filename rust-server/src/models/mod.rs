@@ -16,6 +16,10 @@ pub mod installment;
 pub mod sale;
 pub mod stock;
 pub mod stock_movement;
+pub mod stock_hold;
+pub mod unit;
+pub mod category;
+pub mod sequence;
 pub mod supplier;
 pub mod supplier_payment_receipt;
 pub mod product;
@@ -23,6 +27,7 @@ pub mod purchase;
 pub mod inventory;
 pub mod setting;
 pub mod device;
+pub mod file;
 
 
 pub mod receipt;
@@ -43,6 +48,7 @@ pub use installment::*;
 pub use sale::*;
 pub use stock::*;
 pub use stock_movement::*;
+pub use category::*;
 pub use supplier::*;
 pub use supplier_payment_receipt::*;
 pub use product::*;
@@ -50,6 +56,7 @@ pub use purchase::*;
 pub use inventory::*;
 pub use setting::*;
 pub use device::*;
+pub use file::*;
 
 
 pub use receipt::*;
@@ -48,6 +48,8 @@ pub struct ProductQuery {
     pub category: Option<String>,
     pub fields: Option<String>,
     pub format: Option<String>,
+    // Keyset paging: present (blank for the first page) switches to newest-first order and ignores page
+    pub after: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -119,6 +121,8 @@ pub struct ProductListResponse {
     pub page: i64,
     pub limit: i64,
     pub total_pages: i64,
+    // Set in keyset mode while more rows remain; pass it back as `after`
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -274,3 +278,39 @@ pub struct ImportResult {
     pub errors: Vec<String>,
     pub error_count: usize,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkPriceUpdateRequest {
+    pub product_ids: Option<Vec<i64>>,
+    pub category_id: Option<i64>,
+    pub all: Option<bool>,             // must be true to reprice every active product without a filter
+    pub price_field: Option<String>,   // selling_price (default), purchase_price, wholesale_price
+    pub adjustment_type: String,       // percentage, fixed, set
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkPriceUpdateQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceChangePreview {
+    pub product_id: i64,
+    pub name: String,
+    pub sku: String,
+    pub old_price: f64,
+    pub new_price: f64,
+    pub purchase_price: f64,
+    pub selling_price: f64,
+    pub violates_constraint: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkPriceUpdateResult {
+    pub dry_run: bool,
+    pub price_field: String,
+    pub changes: Vec<PriceChangePreview>,
+    pub violations: Vec<PriceChangePreview>,
+    pub updated_count: i64,
+}
@@ -51,6 +51,12 @@ pub struct CreatePurchaseRequest {
     pub invoice_date: NaiveDate,
     pub due_date: Option<NaiveDate>,
     pub items: Vec<CreatePurchaseItemRequest>,
+    // Totals the client displayed; checked against the items, never stored as sent
+    pub total_amount: Option<f64>,
+    pub discount_amount: Option<f64>,
+    pub tax_amount: Option<f64>,
+    pub net_amount: Option<f64>,
+    pub paid_amount: Option<f64>,
     pub payment_method: Option<String>,
     pub payment_status: Option<String>,
     pub status: Option<String>,
@@ -235,9 +241,24 @@ pub struct ReturnStats {
     pub returns: Vec<crate::models::bill::PurchaseReturn>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseQuery {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    pub supplier_id: Option<i64>,
+    pub payment_status: Option<String>,
+    pub status: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PurchaseListResponse {
     pub purchases: Vec<PurchaseWithDetails>,
+    pub total: i64,
+    pub page: i64,
+    pub limit: i64,
+    pub total_pages: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -750,3 +750,153 @@ pub struct CustomerDebtsDetailedReport {
     pub payment_status: String,
     pub days_overdue: i64,
 }
+
+// Period totals persisted in the reports table
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ReportSnapshot {
+    pub id: i64,
+    pub report_type: String,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub total_sales: f64,
+    pub total_purchases: f64,
+    pub total_expenses: f64,
+    pub net_profit: f64,
+    pub total_customers: i64,
+    pub total_suppliers: i64,
+    pub total_products: i64,
+    pub low_stock_products: i64,
+    pub out_of_stock_products: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotRequest {
+    pub report_type: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+// Profit figures for one date range
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeriodTotals {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub total_sales: f64,
+    pub cost_of_goods: f64,
+    pub gross_profit: f64,
+    pub total_purchases: f64,
+    pub total_expenses: f64,
+    pub net_profit: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricChange {
+    pub period_a: f64,
+    pub period_b: f64,
+    pub change: f64,
+    pub change_percent: Option<f64>, // None when period A is zero
+}
+
+impl MetricChange {
+    pub fn between(period_a: f64, period_b: f64) -> Self {
+        let change = period_b - period_a;
+        let change_percent = if period_a == 0.0 {
+            None
+        } else {
+            Some((change / period_a.abs() * 10000.0).round() / 100.0)
+        };
+        Self { period_a, period_b, change, change_percent }
+    }
+}
+
+// Period B measured against period A
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeriodComparison {
+    pub period_a: PeriodTotals,
+    pub period_b: PeriodTotals,
+    pub sales: MetricChange,
+    pub gross_profit: MetricChange,
+    pub expenses: MetricChange,
+    pub net_profit: MetricChange,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompareQuery {
+    pub a_start: NaiveDate,
+    pub a_end: NaiveDate,
+    pub b_start: NaiveDate,
+    pub b_end: NaiveDate,
+}
+
+// Live profit and loss for a date range
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfitAndLoss {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub gross_sales: f64,
+    pub returns: f64,
+    pub revenue: f64,
+    pub cost_of_goods: f64,
+    pub gross_profit: f64,
+    pub total_expenses: f64,
+    pub net_profit: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfitAndLossQuery {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TopProductsMetric {
+    Quantity,
+    Revenue,
+}
+
+// One best-seller row; manual items share a single row without product_id
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopProduct {
+    pub product_id: Option<i64>,
+    pub name: String,
+    pub sku: Option<String>,
+    pub total_quantity: i64,
+    pub total_revenue: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopProductsQuery {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub metric: Option<TopProductsMetric>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoneyBoxBalance {
+    pub id: i64,
+    pub name: String,
+    pub balance: f64,
+}
+
+// Balance-sheet style position: what the shop holds and is owed against what it owes.
+// Cash is read from the money box ledger as of the date; inventory and settlements are current.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub as_of: NaiveDate,
+    pub cash: f64,
+    pub money_boxes: Vec<MoneyBoxBalance>,
+    pub inventory_value: f64,
+    pub receivables: f64,
+    pub payables: f64,
+    pub total_assets: f64,
+    pub net: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceSnapshotQuery {
+    pub as_of: Option<NaiveDate>,
+}
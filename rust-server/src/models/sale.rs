@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use chrono::{NaiveDateTime, NaiveDate};
+use crate::utils::{CsvFormat, CsvRow};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Sale {
@@ -56,6 +57,42 @@ pub struct SaleWithDetails {
     pub total_items: i64,
 }
 
+impl CsvRow for SaleWithDetails {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "ID", "رقم الفاتورة", "تاريخ الفاتورة", "تاريخ الاستحقاق", "العميل", "المندوب", "عدد الأصناف",
+            "الإجمالي", "الخصم", "الضريبة", "الصافي", "المدفوع", "المتبقي", "طريقة الدفع", "حالة الدفع",
+            "الحالة", "الباركود", "ملاحظات", "أنشئ بواسطة", "تاريخ الإنشاء",
+        ]
+    }
+
+    fn to_record(&self, format: &CsvFormat) -> Vec<String> {
+        let text = |value: &Option<String>| format.text(value.as_deref().unwrap_or_default());
+        vec![
+            self.id.to_string(),
+            format.text(&self.invoice_no),
+            format.date(self.invoice_date),
+            self.due_date.map(|date| format.date(date)).unwrap_or_default(),
+            text(&self.customer_name),
+            text(&self.delegate_name),
+            self.total_items.to_string(),
+            format.number(self.total_amount),
+            format.number(self.discount_amount),
+            format.number(self.tax_amount),
+            format.number(self.net_amount),
+            format.number(self.paid_amount),
+            format.number(self.remaining_amount),
+            format.text(&self.payment_method),
+            format.text(&self.payment_status),
+            format.text(&self.status),
+            text(&self.barcode),
+            text(&self.notes),
+            text(&self.created_by_name),
+            format.datetime(self.created_at),
+        ]
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct SaleItem {
     pub id: i64,
@@ -96,6 +133,7 @@ pub struct SaleItemWithDetails {
 pub struct CreateSaleRequest {
     pub customer_id: Option<i64>,
     pub delegate_id: Option<i64>,
+    pub employee_id: Option<i64>, // employee credited with the sale for commission
     pub invoice_date: Option<NaiveDate>,
     pub due_date: Option<NaiveDate>,
     pub payment_method: Option<String>,
@@ -108,6 +146,11 @@ pub struct CreateSaleRequest {
     pub tax_amount: Option<f64>,
     pub is_anonymous: Option<bool>,
     pub barcode: Option<String>,
+    pub hold_reference: Option<String>, // stock hold placed by the draft being confirmed
+    pub override_credit_limit: Option<bool>, // sell on credit past the customer's limit; needs sales.credit_limit_override
+    pub idempotency_key: Option<String>, // client-chosen; a repeat within the window returns the first sale
+    #[serde(skip)]
+    pub requested_by: Option<i64>, // caller resolved from the bearer token by the route
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -137,6 +180,8 @@ pub struct UpdateSaleRequest {
     pub discount_amount: Option<f64>,
     pub tax_amount: Option<f64>,
     pub barcode: Option<String>,
+    #[serde(skip)]
+    pub requested_by: Option<i64>, // caller resolved from the bearer token by the route
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -149,6 +194,9 @@ pub struct SaleQuery {
     pub status: Option<String>,
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    pub format: Option<String>,
+    // Keyset paging: present (blank for the first page) ignores page and continues after the cursor
+    pub after: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -158,6 +206,8 @@ pub struct SaleListResponse {
     pub page: i64,
     pub limit: i64,
     pub total_pages: i64,
+    // Set in keyset mode while more rows remain; pass it back as `after`
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -266,6 +316,24 @@ pub struct SaleAmounts {
     pub payment_status: String,
 }
 
+// A problem found with one cart line; `line` is the item's index in the request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CartIssue {
+    pub line: usize,
+    pub product_id: Option<i64>,
+    pub issue: String, // invalid_item, product_not_found, product_inactive, price_changed, insufficient_stock
+    pub message: String,
+    pub requested_price: Option<f64>,
+    pub current_price: Option<f64>,
+    pub available_stock: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CartValidation {
+    pub valid: bool,
+    pub issues: Vec<CartIssue>,
+}
+
 impl Sale {
     pub fn is_paid(&self) -> bool {
         self.payment_status == "paid"
@@ -324,3 +392,40 @@ impl CreateSaleItemRequest {
         }
     }
 }
+
+// Which sales a bulk void covers; a date range or explicit ids is required so nothing is voided by accident
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaleFilter {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub customer_id: Option<i64>,
+    pub sale_ids: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoidSaleRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkVoidRequest {
+    #[serde(flatten)]
+    pub filter: SaleFilter,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkVoidOutcome {
+    pub sale_id: i64,
+    pub invoice_no: String,
+    pub status: String, // voided, skipped or failed
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkVoidResult {
+    pub voided: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub outcomes: Vec<BulkVoidOutcome>,
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::NaiveDateTime;
+
+// Counter behind a document numbering series, e.g. customer receipts "CR000042"
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct NumberSequence {
+    pub name: String,
+    pub prefix: String,
+    pub padding: i64,
+    pub next_value: i64,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateNumberSequenceRequest {
+    pub prefix: Option<String>,
+    pub padding: Option<i64>,
+    pub next_value: Option<i64>,
+}
@@ -10,3 +10,9 @@ pub struct Setting {
     pub description: Option<String>,
     pub updated_at: NaiveDateTime,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ImportSettingsQuery {
+    #[serde(default)]
+    pub overwrite_identity: bool,
+}
@@ -244,16 +244,67 @@ pub struct TransferProductRequest {
     pub notes: Option<String>,
 }
 
+// Why a product's quantity was corrected; decides the stock_movements type and which direction is allowed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentReason {
+    Count,     // physical count, either direction
+    Found,     // stock turned up that wasn't recorded
+    Damage,
+    Expiry,
+    Shrinkage, // loss or theft
+}
+
+impl AdjustmentReason {
+    pub fn movement_type(&self) -> &'static str {
+        match self {
+            AdjustmentReason::Damage => "damage",
+            AdjustmentReason::Expiry => "expiry",
+            _ => "adjustment",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AdjustmentReason::Count => "جرد",
+            AdjustmentReason::Found => "مخزون غير مسجل",
+            AdjustmentReason::Damage => "تالف",
+            AdjustmentReason::Expiry => "منتهي الصلاحية",
+            AdjustmentReason::Shrinkage => "فقدان",
+        }
+    }
+
+    pub fn allows(&self, delta: i64) -> bool {
+        match self {
+            AdjustmentReason::Count => true,
+            AdjustmentReason::Found => delta > 0,
+            _ => delta < 0,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AdjustStockRequest {
-    pub stock_id: i64,
     pub product_id: i64,
-    pub quantity: i64,
-    pub adjustment_type: String, // "add" or "subtract"
-    pub reason: String,
+    pub new_quantity: i64,
+    pub reason: AdjustmentReason,
     pub notes: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockAdjustment {
+    pub movement_id: i64,
+    pub stock_id: i64,
+    pub product_id: i64,
+    pub reason: AdjustmentReason,
+    pub movement_type: String,
+    pub previous_quantity: i64,
+    pub new_quantity: i64,
+    pub delta: i64,
+    pub unit_cost: f64,
+    pub total_value: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateStockProductRequest {
     pub quantity: Option<i64>,
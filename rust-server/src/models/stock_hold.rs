@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::NaiveDateTime;
+
+// Soft reservation placed by a draft sale; reduces available but not physical stock
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct StockHold {
+    pub id: i64,
+    pub reference: String,
+    pub product_id: i64,
+    pub quantity: i64,
+    pub status: String,
+    pub sale_id: Option<i64>,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockHoldItem {
+    pub product_id: i64,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateStockHoldRequest {
+    pub reference: Option<String>,
+    pub items: Vec<StockHoldItem>,
+    pub expires_in_minutes: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductAvailability {
+    pub product_id: i64,
+    pub current_stock: i64,
+    pub held_quantity: i64,
+    pub available_stock: i64,
+}
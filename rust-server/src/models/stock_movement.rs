@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use chrono::{NaiveDateTime, NaiveDate};
+use chrono::NaiveDateTime;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct StockMovement {
@@ -45,6 +45,24 @@ pub struct StockMovementWithDetails {
     pub created_by_name: Option<String>,
 }
 
+// One entry of a product's stock timeline, merged from stock_movements and inventory_movements
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockMovementWithContext {
+    pub source: String, // stock_movements | inventory_movements
+    pub id: i64,
+    pub movement_type: String,
+    pub quantity_change: i64, // signed effect on products.current_stock
+    pub running_balance: i64,
+    pub from_stock_name: Option<String>,
+    pub to_stock_name: Option<String>,
+    pub reference_type: Option<String>,
+    pub reference_id: Option<i64>,
+    pub reference_number: Option<String>,
+    pub notes: Option<String>,
+    pub created_by_name: Option<String>,
+    pub movement_date: NaiveDateTime,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateStockMovementRequest {
     pub movement_type: String,
@@ -110,15 +128,6 @@ pub struct ReverseStockMovementRequest {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProductStockHistoryQuery {
-    pub page: Option<i64>,
-    pub limit: Option<i64>,
-    pub movement_type: Option<String>,
-    pub from_date: Option<NaiveDate>,
-    pub to_date: Option<NaiveDate>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StockMovementsSummaryQuery {
     pub period: Option<i64>,
@@ -99,6 +99,51 @@ pub struct SupplierListResponse {
     pub total_pages: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkLinkProductsRequest {
+    pub product_ids: Vec<i64>,
+    pub default_price: Option<f64>,
+    pub lead_time: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkLinkProductsResult {
+    pub supplier_id: i64,
+    pub linked: Vec<i64>,
+    pub already_linked: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierStatementQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+// One ledger line: purchases increase the payable, payments decrease it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierStatementEntry {
+    pub entry_date: NaiveDate,
+    pub entry_type: String, // purchase, purchase_payment, receipt
+    pub reference_id: i64,
+    pub reference_no: String,
+    pub purchase_amount: f64,
+    pub payment_amount: f64,
+    pub balance: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierStatement {
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub opening_balance: f64,
+    pub total_purchases: f64,
+    pub total_payments: f64,
+    pub closing_balance: f64,
+    pub entries: Vec<SupplierStatementEntry>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SupplierSearchResponse {
     pub items: Vec<Supplier>,
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::NaiveDateTime;
+
+// Unit of measure; 1 of this unit = conversion_factor of base_unit_id (e.g. كرتون = 12 قطعة)
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Unit {
+    pub id: i64,
+    pub name: String,
+    pub symbol: Option<String>,
+    pub base_unit_id: Option<i64>,
+    pub conversion_factor: f64,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateUnitRequest {
+    pub name: String,
+    pub symbol: Option<String>,
+    pub base_unit_id: Option<i64>,
+    pub conversion_factor: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateUnitRequest {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub base_unit_id: Option<i64>,
+    pub conversion_factor: Option<f64>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnitConversionQuery {
+    pub from: String,
+    pub to: String,
+    pub quantity: f64,
+}
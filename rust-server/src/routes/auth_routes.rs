@@ -89,6 +89,39 @@ pub async fn profile_handler(
     }
 }
 
+// Refresh token handler: returns a new token for a valid or recently expired one
+pub async fn refresh_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let token = match headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+    {
+        Some(token) => token,
+        None => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<AuthResponse>::error("لا يوجد رمز توثيق".to_string())),
+            );
+        }
+    };
+
+    match state.auth_service.refresh_token(&state.db, token).await {
+        Ok(response) => {
+            info!("تم تجديد الجلسة للمستخدم: {}", response.user.username);
+            (StatusCode::OK, Json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            error!("فشل تجديد الجلسة: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<AuthResponse>::error("انتهت صلاحية الجلسة، يرجى تسجيل الدخول مجدداً".to_string())),
+            )
+        }
+    }
+}
+
 // Get current user handler
 pub async fn get_current_user_handler(
     State(state): State<AppState>,
@@ -264,6 +297,7 @@ pub fn auth_routes() -> Router<AppState> {
     Router::new()
         .route("/api/auth/login", post(login_handler))
         .route("/api/auth/register", post(register_handler))
+        .route("/api/auth/refresh", post(refresh_handler))
         .route("/api/auth/profile", get(profile_handler))
         .route("/api/auth/user", get(get_current_user_handler))
         .route("/api/auth/user/permissions", get(get_user_permissions_handler))
@@ -0,0 +1,75 @@
+use axum::{
+    routing::get,
+    Router,
+    extract::{State, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::AppState;
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct BarcodeImageQuery {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+// Render a product or sale barcode as a PNG for label printing
+async fn get_barcode_image(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    Query(query): Query<BarcodeImageQuery>,
+) -> impl IntoResponse {
+    let width = query.width.unwrap_or(300);
+    let height = query.height.unwrap_or(100);
+    match state.barcode_service.render_png(code.trim(), width, height) {
+        Ok(image) => (
+            StatusCode::OK,
+            [("Content-Type", "image/png"), ("Content-Disposition", "inline")],
+            image
+        ),
+        Err(err) => {
+            error!("Failed to render barcode {}: {}", code, err);
+            (
+                StatusCode::BAD_REQUEST,
+                [("Content-Type", "application/json"), ("Content-Disposition", "inline")],
+                serde_json::to_string(&json!({
+                    "success": false,
+                    "message": err.to_string()
+                })).unwrap().into_bytes()
+            )
+        }
+    }
+}
+
+pub fn barcode_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/barcode/:code/image", get(get_barcode_image))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestApp;
+    use axum::http::{Method, StatusCode};
+
+    const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+    #[tokio::test]
+    async fn known_code_renders_as_png_and_bad_check_digit_is_refused() {
+        let app = TestApp::new().await;
+
+        let (status, content_type, image) = app.request_raw(Method::GET, "/api/barcode/4006381333931/image?width=200&height=60", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some("image/png"));
+        assert_eq!(image[..8], PNG_SIGNATURE);
+        // IHDR follows the signature: width then height, big-endian
+        assert_eq!(u32::from_be_bytes(image[16..20].try_into().unwrap()), 200);
+        assert_eq!(u32::from_be_bytes(image[20..24].try_into().unwrap()), 60);
+
+        let (status, body) = app.request(Method::GET, "/api/barcode/4006381333932/image", None, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["success"], false);
+    }
+}
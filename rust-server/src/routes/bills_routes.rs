@@ -5,6 +5,8 @@ use crate::{
     },
 };
 use crate::AppState;
+use crate::utils::{ReceiptLanguage, ReceiptWidth};
+use serde::Deserialize;
 use sqlx::Row;
 use axum::{
     extract::{Path, Query, State},
@@ -107,6 +109,137 @@ pub async fn get_all_sale_bills(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct InvoicePdfQuery {
+    pub language: Option<String>,
+}
+
+// Printable PDF of a sale invoice (?language=ar|en|ar-en overrides the settings language)
+pub async fn get_sale_bill_pdf(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<InvoicePdfQuery>,
+) -> impl IntoResponse {
+    let language = match query.language.as_deref().map(ReceiptLanguage::parse).transpose() {
+        Ok(language) => language,
+        Err(e) => return (
+            StatusCode::BAD_REQUEST,
+            [
+                ("Content-Type", "application/json".to_string()),
+                ("Content-Disposition", "inline".to_string()),
+            ],
+            serde_json::to_string(&json!({
+                "success": false,
+                "error": e.to_string()
+            })).unwrap().into_bytes()
+        ),
+    };
+
+    match state.bills_service.render_invoice_pdf(&state.db, id, language).await {
+        Ok(pdf) => (
+            StatusCode::OK,
+            [
+                ("Content-Type", "application/pdf".to_string()),
+                ("Content-Disposition", format!("inline; filename=\"invoice-{}.pdf\"", id)),
+            ],
+            pdf
+        ),
+        Err(e) => {
+            error!("Error rendering PDF for sale {}: {}", id, e);
+            (
+                StatusCode::BAD_REQUEST,
+                [
+                    ("Content-Type", "application/json".to_string()),
+                    ("Content-Disposition", "inline".to_string()),
+                ],
+                serde_json::to_string(&json!({
+                    "success": false,
+                    "error": e.to_string()
+                })).unwrap().into_bytes()
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThermalReceiptQuery {
+    pub width: Option<String>,
+    pub language: Option<String>,
+}
+
+// ESC/POS receipt for a 58mm or 80mm thermal printer (?width=58|80&language=ar|en|ar-en)
+pub async fn get_sale_bill_escpos(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<ThermalReceiptQuery>,
+) -> impl IntoResponse {
+    let options = query.width.as_deref().map(ReceiptWidth::parse).transpose()
+        .and_then(|width| Ok((width, query.language.as_deref().map(ReceiptLanguage::parse).transpose()?)));
+    let (width, language) = match options {
+        Ok(options) => options,
+        Err(e) => return (
+            StatusCode::BAD_REQUEST,
+            [("Content-Type", "application/json")],
+            serde_json::to_string(&json!({
+                "success": false,
+                "error": e.to_string()
+            })).unwrap().into_bytes()
+        ),
+    };
+
+    match state.bills_service.render_thermal_receipt(&state.db, id, width, language).await {
+        Ok(receipt) => (
+            StatusCode::OK,
+            [("Content-Type", "application/octet-stream")],
+            receipt
+        ),
+        Err(e) => {
+            error!("Error rendering thermal receipt for sale {}: {}", id, e);
+            (
+                StatusCode::BAD_REQUEST,
+                [("Content-Type", "application/json")],
+                serde_json::to_string(&json!({
+                    "success": false,
+                    "error": e.to_string()
+                })).unwrap().into_bytes()
+            )
+        }
+    }
+}
+
+// QR image for a sale bill, when enabled in the bill settings
+pub async fn get_sale_bill_qr(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.bills_service.sale_qr_code(&state.db, id).await {
+        Ok(Some(image)) => (
+            StatusCode::OK,
+            [("Content-Type", "image/png")],
+            image
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            [("Content-Type", "application/json")],
+            serde_json::to_string(&json!({
+                "success": false,
+                "error": "QR code is disabled in bill settings"
+            })).unwrap().into_bytes()
+        ),
+        Err(e) => {
+            error!("Error generating QR for sale {}: {}", id, e);
+            (
+                StatusCode::BAD_REQUEST,
+                [("Content-Type", "application/json")],
+                serde_json::to_string(&json!({
+                    "success": false,
+                    "error": e.to_string()
+                })).unwrap().into_bytes()
+            )
+        }
+    }
+}
+
 pub async fn get_sale_bill_by_id(
     State(state): State<AppState>,
     Path(id): Path<i64>,
@@ -1166,6 +1299,9 @@ pub fn bills_routes() -> Router<AppState> {
         .route("/api/bills/sale/:id", get(get_sale_bill_by_id))
         .route("/api/bills/sale/number/:bill_number", get(get_bill_by_number))
         .route("/api/bills/sale/:id/payment", put(update_bill_payment_status))
+        .route("/api/bills/sale/:id/qr", get(get_sale_bill_qr))
+        .route("/api/bills/:id/pdf", get(get_sale_bill_pdf))
+        .route("/api/bills/:id/escpos", get(get_sale_bill_escpos))
         .route("/api/bills/sale/:id", delete(delete_bill))
         
         // Purchase Bills Routes
@@ -1,7 +1,7 @@
 use axum::{
     routing::{get, post, delete},
     Router,
-    extract::{State, Path},
+    extract::{State, Path, Query},
     response::IntoResponse,
     Json,
 };
@@ -21,6 +21,11 @@ pub struct InvalidateRequest {
     pub pattern: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ClearCacheQuery {
+    pub prefix: Option<String>,
+}
+
 // Get cache statistics
 async fn get_cache_stats(State(state): State<AppState>) -> impl IntoResponse {
     match state.cache_service.get_statistics().await {
@@ -72,8 +77,9 @@ async fn get_cache_memory(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
-// Flush all cache
+// Flush all cache, the license service's own cache included
 async fn flush_cache(State(state): State<AppState>) -> impl IntoResponse {
+    state.license_service.clear_license_cache().await;
     match state.cache_service.flush_all().await {
         Ok(result) => Json(json!({
             "success": true,
@@ -90,6 +96,20 @@ async fn flush_cache(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+// Clear the keys starting with `prefix`, or the whole cache when none is given
+async fn clear_cache(State(state): State<AppState>, Query(query): Query<ClearCacheQuery>) -> impl IntoResponse {
+    let prefix = query.prefix.as_deref().map(str::trim).unwrap_or("");
+    let cleared = state.cache_service.invalidate(prefix).await;
+    Json(json!({
+        "success": true,
+        "data": {
+            "prefix": prefix,
+            "cleared_keys": cleared
+        },
+        "message": "Cache cleared successfully"
+    }))
+}
+
 // Delete specific cache key
 async fn delete_cache_key(State(state): State<AppState>, Path(key): Path<String>) -> impl IntoResponse {
     match state.cache_service.delete_key(&key).await {
@@ -184,8 +204,68 @@ pub fn cache_routes() -> Router<AppState> {
         .route("/api/cache/keys", get(get_cache_keys))
         .route("/api/cache/memory", get(get_cache_memory))
         .route("/api/cache/flush", post(flush_cache))
+        .route("/api/cache/clear", post(clear_cache))
         .route("/api/cache/invalidate", post(invalidate_cache))
         .route("/api/cache/set", post(set_cache_key))
         .route("/api/cache/health", get(get_cache_health))
         .route("/api/cache/key/:key", get(get_cache_key_value).delete(delete_cache_key))
-}   
\ No newline at end of file
+}   
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestApp;
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn stats_count_a_hit_and_a_miss_and_flush_empties_the_store() {
+        let app = TestApp::new().await;
+        let stats = || app.request(Method::GET, "/api/cache/stats", None, None);
+
+        let (status, _) = app.request(Method::POST, "/api/cache/set", None, Some(json!({ "key": "report:daily", "value": { "total": 125000 } }))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, before) = stats().await;
+
+        app.request(Method::GET, "/api/cache/key/report:daily", None, None).await;
+        app.request(Method::GET, "/api/cache/key/report:weekly", None, None).await;
+        let (_, after) = stats().await;
+        let counter = |body: &serde_json::Value, name: &str| body["data"][name].as_u64().unwrap();
+        assert_eq!(counter(&after, "hits"), counter(&before, "hits") + 1);
+        assert_eq!(counter(&after, "misses"), counter(&before, "misses") + 1);
+        assert!(counter(&after, "entries") >= 1);
+        assert!(counter(&after, "memory_bytes") > 0);
+
+        let (status, flushed) = app.request(Method::POST, "/api/cache/flush", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(flushed["data"]["cleared_keys"].as_u64().unwrap() >= 1);
+        let (_, emptied) = stats().await;
+        assert_eq!(counter(&emptied, "entries"), 0);
+        assert_eq!(counter(&emptied, "memory_bytes"), 0);
+    }
+
+    #[tokio::test]
+    async fn stats_break_down_by_prefix_and_clear_drops_only_that_prefix() {
+        let app = TestApp::new().await;
+        for key in ["report:daily", "report:weekly", "product:17"] {
+            app.request(Method::POST, "/api/cache/set", None, Some(json!({ "key": key, "value": [1, 2, 3] }))).await;
+        }
+        for key in ["report:daily", "report:daily", "product:18"] {
+            app.request(Method::GET, &format!("/api/cache/key/{key}"), None, None).await;
+        }
+        let prefix = |body: &serde_json::Value, name: &str| {
+            body["data"]["prefixes"].as_array().unwrap().iter()
+                .find(|stats| stats["prefix"] == name)
+                .map(|stats| (stats["entries"].as_u64().unwrap(), stats["hits"].as_u64().unwrap(), stats["misses"].as_u64().unwrap(), stats["hit_rate"].as_f64().unwrap()))
+        };
+
+        let (_, stats) = app.request(Method::GET, "/api/cache/stats", None, None).await;
+        assert_eq!(prefix(&stats, "report"), Some((2, 2, 0, 1.0)));
+        assert_eq!(prefix(&stats, "product"), Some((1, 0, 1, 0.0)));
+
+        let (status, cleared) = app.request(Method::POST, "/api/cache/clear?prefix=report", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(cleared["data"]["cleared_keys"], 2);
+        let (_, stats) = app.request(Method::GET, "/api/cache/stats", None, None).await;
+        assert_eq!(prefix(&stats, "report").map(|(entries, ..)| entries), Some(0));
+        assert_eq!(prefix(&stats, "product").map(|(entries, ..)| entries), Some(1));
+    }
+}
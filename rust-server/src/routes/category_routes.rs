@@ -0,0 +1,170 @@
+use axum::{
+    routing::{get, delete},
+    Router,
+    extract::{State, Path, Query},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use crate::AppState;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
+use crate::models::{CategoryRequest, DeleteCategoryQuery};
+use tracing::{info, error};
+
+// All categories with how many products each holds
+async fn get_categories(State(state): State<AppState>) -> impl IntoResponse {
+    match state.category_service.list_with_counts(&state.db).await {
+        Ok(categories) => Json(json!({
+            "success": true,
+            "data": categories,
+            "message": "تم استرجاع الفئات بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get categories: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب الفئات"
+            }))
+        }
+    }
+}
+
+// Get category by ID
+async fn get_category(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.category_service.get_by_id(&state.db, id).await {
+        Ok(Some(category)) => Json(json!({
+            "success": true,
+            "data": category,
+            "message": "تم استرجاع الفئة بنجاح"
+        })),
+        Ok(None) => Json(json!({
+            "success": false,
+            "message": "الفئة غير موجودة"
+        })),
+        Err(err) => {
+            error!("Failed to get category {}: {}", id, err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب الفئة"
+            }))
+        }
+    }
+}
+
+// Create category
+async fn create_category(
+    State(state): State<AppState>,
+    Json(payload): Json<CategoryRequest>,
+) -> impl IntoResponse {
+    match state.category_service.create(&state.db, payload).await {
+        Ok(category) => {
+            info!("Category created successfully: {}", category.id);
+            Json(json!({
+                "success": true,
+                "data": category,
+                "message": "تم إنشاء الفئة بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to create category: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Rename category
+async fn update_category(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<CategoryRequest>,
+) -> impl IntoResponse {
+    match state.category_service.update(&state.db, id, payload).await {
+        Ok(category) => Json(json!({
+            "success": true,
+            "data": category,
+            "message": "تم تحديث الفئة بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to update category {}: {}", id, err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Delete category; refused while products use it unless ?reassign=true
+async fn delete_category(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<DeleteCategoryQuery>,
+) -> impl IntoResponse {
+    match state.category_service.delete(&state.db, id, query.reassign.unwrap_or(false)).await {
+        Ok(true) => Json(json!({
+            "success": true,
+            "message": "تم حذف الفئة بنجاح"
+        })),
+        Ok(false) => Json(json!({
+            "success": false,
+            "message": "الفئة غير موجودة"
+        })),
+        Err(err) => {
+            error!("Failed to delete category {}: {}", id, err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+pub fn category_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/categories", get(get_categories).post(create_category))
+        .route("/api/categories/:id", get(get_category).put(update_category))
+        .route("/api/categories/:id", delete(delete_category)
+            .route_layer(from_fn_with_state(RequirePermission("products.delete"), require_permission)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use crate::test_support::TestApp;
+
+    #[tokio::test]
+    async fn a_category_with_products_is_kept_unless_they_are_reassigned() {
+        let app = TestApp::new().await;
+        let pool = &app.db.pool;
+        app.add_user("stockkeeper", "manager", &["products.delete"]).await;
+        let token = app.login("stockkeeper").await;
+
+        let (status, created) = app.request(Method::POST, "/api/categories", Some(&token), Some(json!({ "name": "مشروبات" }))).await;
+        assert_eq!(status, StatusCode::OK, "{created}");
+        let category_id = created["data"]["id"].as_i64().unwrap();
+        let product_id = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, category_id) VALUES ('Pepsi 330ml', 'PEPSI-330', 400, 500, 450, ?)")
+            .bind(category_id).execute(pool).await.unwrap().last_insert_rowid();
+
+        let (_, listed) = app.request(Method::GET, "/api/categories", Some(&token), None).await;
+        let listed = listed["data"].as_array().unwrap().iter().find(|category| category["id"] == category_id).cloned().unwrap();
+        assert_eq!(listed["product_count"], 1);
+
+        let (_, blocked) = app.request(Method::DELETE, &format!("/api/categories/{category_id}"), Some(&token), None).await;
+        assert_eq!(blocked["success"], false, "{blocked}");
+        let (_, still_there) = app.request(Method::GET, &format!("/api/categories/{category_id}"), Some(&token), None).await;
+        assert_eq!(still_there["data"]["id"], category_id);
+
+        let (_, deleted) = app.request(Method::DELETE, &format!("/api/categories/{category_id}?reassign=true"), Some(&token), None).await;
+        assert_eq!(deleted["success"], true, "{deleted}");
+        let category: Option<i64> = sqlx::query_scalar("SELECT category_id FROM products WHERE id = ?").bind(product_id).fetch_one(pool).await.unwrap();
+        assert_eq!(category, None);
+    }
+}
@@ -1,8 +1,9 @@
 use crate::models::{
     CreateCloudBackupRequest, RestoreRequest, ApiResponse
 };
-use crate::services::CloudBackupService;
+use crate::services::{CloudBackupService, LicenseRequired};
 use crate::AppState;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
 use axum::{
     extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
@@ -10,8 +11,9 @@ use axum::{
     routing::{get, post},
     Router,
 };
+use axum::middleware::from_fn_with_state;
 use serde_json::json;
-use tracing::{info, warn, error};
+use tracing::{debug, info, warn, error};
 
 // ==================== CLOUD BACKUP OPERATIONS ====================
 
@@ -22,7 +24,9 @@ pub async fn create_cloud_backup(
 ) -> (StatusCode, Json<serde_json::Value>) {
     let user_id = 1; // TODO: Extract from JWT token
     
-    match state.cloud_backup_service.create_cloud_backup(&state.db, request).await {
+    let progress = |sent: u64, total: u64| debug!("Cloud backup upload: {}/{} bytes", sent, total);
+    
+    match state.cloud_backup_service.create_cloud_backup(&state.db, request, &progress).await {
         Ok(response) => {
             info!("Cloud backup created successfully for user: {}", user_id);
             (StatusCode::OK, Json(json!({
@@ -44,6 +48,34 @@ pub async fn create_cloud_backup(
     }
 }
 
+pub async fn resume_cloud_backup(
+    State(state): State<AppState>,
+    Path(remote_backup_id): Path<String>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let progress = |sent: u64, total: u64| debug!("Cloud backup upload: {}/{} bytes", sent, total);
+    
+    match state.cloud_backup_service.resume_cloud_backup(&state.db, &remote_backup_id, &progress).await {
+        Ok(response) => {
+            info!("Cloud backup upload resumed and completed: {}", remote_backup_id);
+            (StatusCode::OK, Json(json!({
+                "success": response.success,
+                "data": response.data,
+                "message": response.message
+            })))
+        }
+        Err(e) => {
+            error!("Error resuming cloud backup {}: {}", remote_backup_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "success": false,
+                    "error": format!("Error resuming cloud backup: {}", e)
+                })),
+            )
+        }
+    }
+}
+
 pub async fn get_user_backups(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -257,6 +289,66 @@ pub async fn restore_from_cloud_backup(
     }
 }
 
+pub async fn list_remote_backups(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.cloud_backup_service.list_remote().await {
+        Ok(backups) => (StatusCode::OK, Json(json!({
+            "success": true,
+            "data": backups
+        }))),
+        Err(e) => {
+            error!("Error listing remote backups: {}", e);
+            let status = if e.downcast_ref::<LicenseRequired>().is_some() {
+                StatusCode::FORBIDDEN
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (
+                status,
+                Json(json!({
+                    "success": false,
+                    "error": format!("Error listing remote backups: {}", e)
+                })),
+            )
+        }
+    }
+}
+
+pub async fn restore_from_remote(
+    State(state): State<AppState>,
+    Path(remote_backup_id): Path<String>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let restore = state.database_service.safe_operation(&state.db, "restore", || {
+        state.cloud_backup_service.restore_from_remote(&state.db, &remote_backup_id)
+    });
+    match restore.await {
+        Ok(result) => {
+            info!("Database restored from remote backup: {}", remote_backup_id);
+            (StatusCode::OK, Json(json!({
+                "success": true,
+                "data": result,
+                "message": "Database restored from cloud backup"
+            })))
+        }
+        Err(e) => {
+            error!("Error restoring from remote backup {}: {}", remote_backup_id, e);
+            let status = if e.downcast_ref::<LicenseRequired>().is_some() {
+                StatusCode::FORBIDDEN
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (
+                status,
+                Json(json!({
+                    "success": false,
+                    "error": format!("Error restoring from remote backup: {}", e)
+                })),
+            )
+        }
+    }
+}
+
 pub async fn check_database_accessibility(
     State(state): State<AppState>,
 ) -> (StatusCode, Json<serde_json::Value>) {
@@ -339,6 +431,9 @@ pub fn cloud_backup_routes() -> Router<AppState> {
         // Create a cloud backup and send it to remote server
         .route("/api/cloud-backup/create", post(create_cloud_backup))
         
+        // Continue an interrupted upload from the last acknowledged chunk
+        .route("/api/cloud-backup/resume/:remote_backup_id", post(resume_cloud_backup))
+        
         // Get user backups from remote server
         .route("/api/cloud-backup/user/:user_id", get(get_user_backups_with_id))
         .route("/api/cloud-backup/user", get(get_user_backups))
@@ -359,6 +454,12 @@ pub fn cloud_backup_routes() -> Router<AppState> {
         // Restore database from a cloud backup
         .route("/api/cloud-backup/restore/:backup_id", post(restore_from_cloud_backup))
         
+        // List backups held on the remote server and restore one over the live database
+        .route("/api/cloud-backup/remote", get(list_remote_backups)
+            .route_layer(from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+        .route("/api/cloud-backup/remote/:remote_backup_id/restore", post(restore_from_remote)
+            .route_layer(from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+        
         // Check database accessibility for restoration
         .route("/api/cloud-backup/check-accessibility", get(check_database_accessibility))
         
@@ -368,3 +469,26 @@ pub fn cloud_backup_routes() -> Router<AppState> {
         // Check for file locks on the database
         .route("/api/cloud-backup/check-locks", get(check_file_locks))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestApp;
+    use axum::http::{Method, StatusCode};
+
+    #[tokio::test]
+    async fn remote_backups_need_settings_manage() {
+        let app = TestApp::new().await;
+        app.add_user("cashier", "user", &["sales.create"]).await;
+        let cashier = app.login("cashier").await;
+
+        for (method, uri) in [
+            (Method::GET, "/api/cloud-backup/remote"),
+            (Method::POST, "/api/cloud-backup/remote/1/restore"),
+        ] {
+            let (status, _) = app.request(method.clone(), uri, None, None).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{uri}");
+            let (status, _) = app.request(method, uri, Some(&cashier), None).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+        }
+    }
+}
@@ -10,8 +10,9 @@ use tracing::{error, info};
 use crate::{
     models::ApiResponse,
     AppState,
+    utils::{CsvFormat, CsvFormatQuery},
     services::customer_receipts_service::{
-        CustomerReceiptQuery, CustomerReceiptsService, CreateCustomerReceiptRequest, UpdateCustomerReceiptRequest,
+        CustomerReceiptQuery, CustomerReceiptsService, CreateBulkReceiptRequest, CreateCustomerReceiptRequest, UpdateCustomerReceiptRequest,
     },
 };
 
@@ -96,6 +97,37 @@ async fn create_receipt(
     }
 }
 
+// Create one receipt paying several customer debts
+async fn create_bulk_receipt(
+    State(state): State<AppState>,
+    Json(request): Json<CreateBulkReceiptRequest>,
+) -> impl IntoResponse {
+    match state.customer_receipts_service.create_bulk(
+        &state.db,
+        request.customer_id,
+        request.amount,
+        request.allocations,
+        &request.payment_method,
+        request.money_box_id,
+    ).await {
+        Ok(result) => {
+            info!("Bulk customer receipt created successfully");
+            Json(json!({
+                "success": true,
+                "message": "تم إنشاء سند القبض بنجاح",
+                "data": result
+            }))
+        }
+        Err(err) => {
+            error!("Failed to create bulk customer receipt: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 // Update customer receipt
 async fn update_receipt(
     State(state): State<AppState>,
@@ -304,8 +336,13 @@ async fn get_customer_financial_summary(
 async fn export_receipts(
     State(state): State<AppState>,
     Query(query): Query<CustomerReceiptQuery>,
+    Query(format_query): Query<CsvFormatQuery>,
 ) -> impl IntoResponse {
-    match state.customer_receipts_service.export_to_csv(&state.db, &query).await {
+    let result = match CsvFormat::load(&state.db.pool, &format_query).await {
+        Ok(format) => state.customer_receipts_service.export_to_csv(&state.db, &query, &format).await,
+        Err(err) => Err(err),
+    };
+    match result {
         Ok(csv_content) => {
             info!("Customer receipts exported successfully");
             (
@@ -321,7 +358,8 @@ async fn export_receipts(
                 [("Content-Type", "application/json"), ("Content-Disposition", "inline")],
                 serde_json::to_string(&json!({
                     "success": false,
-                    "message": "حدث خطأ أثناء تصدير البيانات"
+                    "message": "حدث خطأ أثناء تصدير البيانات",
+                    "error": err.to_string()
                 })).unwrap()
             )
         }
@@ -360,6 +398,7 @@ pub fn customer_receipts_routes() -> axum::Router<AppState> {
     axum::Router::new()
         .route("/api/customer-receipts", axum::routing::get(get_all_receipts))
         .route("/api/customer-receipts", axum::routing::post(create_receipt))
+        .route("/api/customer-receipts/bulk", axum::routing::post(create_bulk_receipt))
         .route("/api/customer-receipts/statistics", axum::routing::get(get_statistics))
         .route("/api/customer-receipts/export", axum::routing::get(export_receipts))
         .route("/api/customer-receipts/export-pdf", axum::routing::get(export_receipts_pdf))
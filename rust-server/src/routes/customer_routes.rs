@@ -2,16 +2,20 @@ use axum::{
     routing::{get, post, put, delete},
     Router,
     extract::{State, Path, Query},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::AppState;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
 use crate::models::{
     Customer, CreateCustomerRequest, UpdateCustomerRequest, CustomerQuery, CustomerFilters,
-    CustomerListResponse, CustomerWithSales, CustomerDetails, ApiResponse
+    CustomerListResponse, CustomerWithSales, CustomerDetails, ApiResponse, AgingReportQuery
 };
+use crate::utils::InvalidContact;
 use tracing::{info, warn, error};
 
 // Get all customers
@@ -96,6 +100,98 @@ async fn get_customer_by_id(
     }
 }
 
+// Export all data held for a customer
+async fn export_customer_data(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.customer_service.export_data(&state.db, id).await {
+        Ok(data) => {
+            info!("تم تصدير بيانات العميل: {}", id);
+            Json(json!({
+                "success": true,
+                "data": data,
+                "message": "تم تصدير بيانات العميل بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to export customer data: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Outstanding debts grouped into aging buckets
+async fn get_customers_aging(
+    State(state): State<AppState>,
+    Query(query): Query<AgingReportQuery>,
+) -> impl IntoResponse {
+    let as_of = query.as_of.unwrap_or_else(|| chrono::Local::now().date_naive());
+
+    match state.customer_service.get_aging_report(&state.db, as_of).await {
+        Ok(report) => Json(json!({
+            "success": true,
+            "data": report,
+            "message": "تم استرجاع تقرير أعمار الديون بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get customers aging report: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "فشل استرجاع تقرير أعمار الديون"
+            }))
+        }
+    }
+}
+
+// Anonymize a customer's personal data (financial history is kept)
+async fn anonymize_customer(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.customer_service.anonymize(&state.db, id).await {
+        Ok(customer) => {
+            info!("تم إخفاء بيانات العميل: {}", id);
+            Json(json!({
+                "success": true,
+                "data": customer,
+                "message": "تم إخفاء البيانات الشخصية للعميل بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to anonymize customer: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Rebuild a customer's balance from debts and receipts
+async fn recompute_customer_balance(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.customer_service.recompute_balance(&state.db, id).await {
+        Ok(customer) => Json(json!({
+            "success": true,
+            "data": customer,
+            "message": "تم إعادة احتساب رصيد العميل بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to recompute customer balance: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 // Get customer details (optimized endpoint)
 async fn get_customer_details(
     State(state): State<AppState>,
@@ -163,19 +259,25 @@ async fn get_customer_with_sales(
 async fn create_customer(
     State(state): State<AppState>,
     Json(payload): Json<CreateCustomerRequest>,
-) -> impl IntoResponse {
+) -> Response {
     match state.customer_service.create(&state.db, payload).await {
         Ok(customer) => Json(json!({
             "success": true,
             "data": customer,
             "message": "customer_created"
-        })),
+        })).into_response(),
         Err(err) => {
             error!("Failed to create customer: {}", err);
+            if let Some(invalid) = err.downcast_ref::<InvalidContact>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": invalid.to_string()
+                }))).into_response();
+            }
             Json(json!({
                 "success": false,
                 "message": "Failed to create customer"
-            }))
+            })).into_response()
         }
     }
 }
@@ -185,23 +287,29 @@ async fn update_customer(
     State(state): State<AppState>,
     Path(id): Path<i64>,
     Json(payload): Json<UpdateCustomerRequest>,
-) -> impl IntoResponse {
+) -> Response {
     match state.customer_service.update(&state.db, id, payload).await {
         Ok(Some(customer)) => Json(json!({
             "success": true,
             "data": customer,
             "message": "customer_updated"
-        })),
+        })).into_response(),
         Ok(None) => Json(json!({
             "success": false,
             "message": "Customer not found"
-        })),
+        })).into_response(),
         Err(err) => {
             error!("Failed to update customer: {}", err);
+            if let Some(invalid) = err.downcast_ref::<InvalidContact>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": invalid.to_string()
+                }))).into_response();
+            }
             Json(json!({
                 "success": false,
                 "message": "Failed to update customer"
-            }))
+            })).into_response()
         }
     }
 }
@@ -263,8 +371,53 @@ pub fn customer_routes() -> Router<AppState> {
     Router::new()
         .route("/api/customers", get(get_all_customers).post(create_customer))
         .route("/api/customers/search", get(search_customers))
+        .route("/api/customers/aging", get(get_customers_aging))
         .route("/api/customers/cache/reload", post(reload_cache))
-        .route("/api/customers/:id", get(get_customer_by_id).put(update_customer).delete(delete_customer))
+        .route("/api/customers/:id", get(get_customer_by_id).put(update_customer))
+        .route("/api/customers/:id", delete(delete_customer)
+            .route_layer(from_fn_with_state(RequirePermission("customers.delete"), require_permission)))
         .route("/api/customers/:id/details", get(get_customer_details))
+        .route("/api/customers/:id/export", get(export_customer_data))
+        .route("/api/customers/:id/anonymize", post(anonymize_customer)
+            .route_layer(from_fn_with_state(RequirePermission("customers.delete"), require_permission)))
+        .route("/api/customers/:id/recompute-balance", post(recompute_customer_balance)
+            .route_layer(from_fn_with_state(RequirePermission("customers.manage"), require_permission)))
         .route("/api/customers/:id/sales", get(get_customer_with_sales))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use crate::test_support::TestApp;
+
+    #[tokio::test]
+    async fn customers_are_saved_with_a_canonical_phone_and_a_bad_email_is_refused() {
+        let app = TestApp::new().await;
+        app.add_user("cashier", "user", &[]).await;
+        let token = app.login("cashier").await;
+
+        let (status, created) = app.request(Method::POST, "/api/customers", Some(&token), Some(json!({
+            "name": "Zainab Kareem",
+            "phone": "0770 123-4567",
+            "email": " Zainab@Example.IQ "
+        }))).await;
+        assert_eq!(status, StatusCode::OK, "{created}");
+        let (phone, email): (String, String) = sqlx::query_as("SELECT phone, email FROM customers WHERE id = ?")
+            .bind(created["data"]["id"].as_i64().unwrap())
+            .fetch_one(&app.db.pool).await.unwrap();
+        assert_eq!((phone.as_str(), email.as_str()), ("+9647701234567", "zainab@example.iq"));
+
+        let (status, refused) = app.request(Method::POST, "/api/customers", Some(&token), Some(json!({
+            "name": "Mustafa Adel",
+            "phone": "07801112222",
+            "email": "mustafa@example"
+        }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(refused["success"], false);
+        assert!(refused["message"].as_str().unwrap().contains("البريد الإلكتروني غير صالح"), "{refused}");
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM customers WHERE name = 'Mustafa Adel'")
+            .fetch_one(&app.db.pool).await.unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -2,15 +2,19 @@ use axum::{
     routing::{get, post},
     Router,
     extract::{State, Path},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::AppState;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
 use crate::models::{
     CreateBackupRequest, RestoreBackupRequest, get_database_message
 };
+use crate::services::database_service::OptimizeInProgress;
 use tracing::{info, warn, error};
 
 // Create database backup
@@ -65,7 +69,10 @@ async fn restore_from_backup(
     State(state): State<AppState>,
     Path(backup_id): Path<String>,
 ) -> impl IntoResponse {
-    match state.database_service.restore_from_backup(&state.db, &backup_id).await {
+    let restore = state.database_service.safe_operation(&state.db, "restore", || {
+        state.database_service.restore_from_backup(&state.db, &backup_id)
+    });
+    match restore.await {
         Ok(result) => {
             info!("Database restored successfully from backup: {}", result.backup_path);
             Json(json!({
@@ -97,7 +104,10 @@ async fn restore_from_custom_backup(
         }));
     }
 
-    match state.database_service.restore_from_custom_backup(&state.db, &payload.backup_file).await {
+    let restore = state.database_service.safe_operation(&state.db, "restore", || {
+        state.database_service.restore_from_custom_backup(&state.db, &payload.backup_file)
+    });
+    match restore.await {
         Ok(result) => {
             info!("Database restored successfully from custom backup: {}", result.backup_path);
             Json(json!({
@@ -120,7 +130,10 @@ async fn restore_from_custom_backup(
 // Reset database
 async fn reset_database(State(state): State<AppState>) -> impl IntoResponse {
     // TODO: Add admin permission check and confirmation
-    match state.database_service.reset_database(&state.db).await {
+    let reset = state.database_service.safe_operation(&state.db, "reset", || {
+        state.database_service.reset_database(&state.db)
+    });
+    match reset.await {
         Ok(result) => {
             info!("Database reset successfully");
             Json(json!({
@@ -162,6 +175,60 @@ async fn fix_menu_items(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+// Table and column metadata for integrators and report builders
+async fn get_schema(State(state): State<AppState>) -> impl IntoResponse {
+    match state.database_service.schema(&state.db).await {
+        Ok(tables) => {
+            info!("Database schema fetched successfully");
+            Json(json!({
+                "success": true,
+                "data": tables,
+                "message": get_database_message("schema_fetched")
+            }))
+        },
+        Err(err) => {
+            error!("Failed to fetch database schema: {}", err);
+            let (_status_code, message) = state.database_service.handle_database_error(&err);
+            Json(json!({
+                "success": false,
+                "message": message
+            }))
+        }
+    }
+}
+
+// Connection pool size and usage, for diagnosing exhaustion during bulk operations
+async fn get_pool_stats(State(state): State<AppState>) -> impl IntoResponse {
+    Json(json!({
+        "success": true,
+        "data": state.database_service.pool_stats(&state.db),
+        "message": get_database_message("pool_stats_fetched")
+    }))
+}
+
+// VACUUM/ANALYZE/REINDEX on demand; answers once done with the file size before and after
+async fn optimize_database(State(state): State<AppState>) -> Response {
+    match state.database_service.optimize(&state.db).await {
+        Ok(report) => Json(json!({
+            "success": true,
+            "data": report,
+            "message": get_database_message("database_optimized")
+        })).into_response(),
+        Err(err) => {
+            error!("Failed to optimize database: {}", err);
+            let status = if err.downcast_ref::<OptimizeInProgress>().is_some() {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, Json(json!({
+                "success": false,
+                "message": if status == StatusCode::CONFLICT { err.to_string() } else { get_database_message("optimize_failed").to_string() }
+            }))).into_response()
+        }
+    }
+}
+
 pub fn database_routes() -> Router<AppState> {
     Router::new()
         .route("/api/database/backup", post(create_backup))
@@ -170,4 +237,60 @@ pub fn database_routes() -> Router<AppState> {
         .route("/api/database/restore-custom", post(restore_from_custom_backup))
         .route("/api/database/reset", post(reset_database))
         .route("/api/database/fix-menu-items", post(fix_menu_items))
-}
\ No newline at end of file
+        .route("/api/database/pool", get(get_pool_stats))
+        .route("/api/database/schema", get(get_schema)
+            .route_layer(from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+        .route("/api/database/optimize", post(optimize_database)
+            .route_layer(from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+}
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use crate::test_support::TestApp;
+
+    #[tokio::test]
+    async fn schema_is_limited_to_settings_managers() {
+        let app = TestApp::new().await;
+        app.add_user("clerk", "user", &["products.view"]).await;
+        app.add_user("owner", "admin", &[]).await;
+        let clerk = app.login("clerk").await;
+        let admin = app.login("owner").await;
+
+        let (status, _) = app.request(Method::GET, "/api/database/schema", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = app.request(Method::GET, "/api/database/schema", Some(&clerk), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = app.request(Method::GET, "/api/database/schema", Some(&admin), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let tables = body["data"].as_array().expect("table list");
+        assert!(tables.iter().any(|table| table["name"] == "sales"));
+    }
+
+    #[tokio::test]
+    async fn optimize_is_limited_to_settings_managers_and_reports_the_space_it_freed() {
+        let app = TestApp::new().await;
+        let pool = &app.db.pool;
+        app.add_user("clerk", "user", &["products.view"]).await;
+        app.add_user("owner", "admin", &[]).await;
+        // A few megabytes written and deleted again leave free pages for VACUUM to hand back
+        sqlx::query("CREATE TABLE scratch (payload BLOB)").execute(pool).await.unwrap();
+        for _ in 0..40 {
+            sqlx::query("INSERT INTO scratch (payload) VALUES (randomblob(100000))").execute(pool).await.unwrap();
+        }
+        sqlx::query("DELETE FROM scratch").execute(pool).await.unwrap();
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(pool).await.unwrap();
+
+        let clerk = app.login("clerk").await;
+        let (status, _) = app.request(Method::POST, "/api/database/optimize", Some(&clerk), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let admin = app.login("owner").await;
+        let (status, body) = app.request(Method::POST, "/api/database/optimize", Some(&admin), None).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let report = &body["data"];
+        let (before, after) = (report["size_before"].as_i64().unwrap(), report["size_after"].as_i64().unwrap());
+        assert!(before - after > 3_000_000, "{report}");
+        assert_eq!(report["freed_bytes"].as_i64().unwrap(), before - after);
+    }
+}
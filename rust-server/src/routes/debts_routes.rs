@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::AppState;
 use crate::models::{
-    DebtQuery, UpdateDebtRequest, RepayDebtRequest, RepayDebtLegacyRequest
+    DebtQuery, UpdateDebtRequest, RepayDebtRequest, RepayDebtLegacyRequest, ApplyPaymentRequest
 };
 use tracing::{info, warn, error};
 
@@ -221,6 +221,31 @@ async fn repay_debt(
     }
 }
 
+// Apply a lump-sum payment to a customer's debts, oldest first
+async fn apply_payment(
+    State(state): State<AppState>,
+    Json(payload): Json<ApplyPaymentRequest>,
+) -> impl IntoResponse {
+    let payment_method = payload.payment_method.as_deref().unwrap_or("cash");
+    match state.debt_service.apply_payment(&state.db, payload.customer_id, payload.amount, payment_method, payload.money_box_id).await {
+        Ok(result) => {
+            info!("Payment applied to debts of customer {}", payload.customer_id);
+            Json(json!({
+                "success": true,
+                "message": "تم تسديد الديون بنجاح",
+                "data": result
+            }))
+        },
+        Err(err) => {
+            error!("Failed to apply debt payment: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 // Legacy repay debt
 async fn repay_debt_legacy(
     State(state): State<AppState>,
@@ -256,4 +281,5 @@ pub fn debts_routes() -> Router<AppState> {
         .route("/api/debts/:id", delete(delete_debt))
         .route("/api/debts/:id/repay", post(repay_debt))
         .route("/api/debts/repay-legacy", post(repay_debt_legacy))
+        .route("/api/debts/apply-payment", post(apply_payment))
 }
\ No newline at end of file
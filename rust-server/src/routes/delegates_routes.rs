@@ -389,7 +389,7 @@ async fn calculate_delegate_commission(
     Path(id): Path<i64>,
     Query(query): Query<CommissionQuery>,
 ) -> impl IntoResponse {
-    match state.delegates_service.calculate_commission(&state.db, id, &query).await {
+    match state.delegates_service.calculate_commission(&state.db, id, query.period_start, query.period_end, query.recompute.unwrap_or(false)).await {
         Ok(result) => {
             info!("Commission calculated successfully for delegate ID: {}", id);
             Json(json!({
@@ -402,7 +402,8 @@ async fn calculate_delegate_commission(
             error!("Failed to calculate commission: {}", err);
             Json(json!({
                 "success": false,
-                "message": "فشل حساب العمولة"
+                "message": "فشل حساب العمولة",
+                "error": err.to_string()
             }))
         }
     }
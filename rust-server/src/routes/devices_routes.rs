@@ -1,11 +1,13 @@
 use axum::{
     routing::{get, post, delete, patch},
     Router,
-    extract::{State, Path, Query},
-    response::IntoResponse,
+    extract::{State, Path, Query, ConnectInfo},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use serde_json::json;
 use crate::AppState;
 
@@ -32,6 +34,22 @@ pub struct UpdateDeviceStatusRequest {
     pub notes: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HeartbeatRequest {
+    pub device_id: String,
+    pub name: Option<String>,
+    pub ip: Option<String>, // defaults to the address the request came from
+    pub branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActiveDevicesQuery {
+    pub within_seconds: Option<i64>,
+}
+
+// A device counts as online when it heartbeated within this many seconds
+const ACTIVE_DEVICE_WINDOW_SECS: i64 = 120;
+
 #[derive(Debug, Deserialize)]
 pub struct CashOperationRequest {
     pub amount: f64,
@@ -172,16 +190,102 @@ async fn remove_device(State(state): State<AppState>, Path(device_id): Path<Stri
     }
 }
 
+// Secondary device heartbeat
+async fn device_heartbeat(
+    State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(payload): Json<HeartbeatRequest>,
+) -> Response {
+    let device_id = payload.device_id.trim();
+    if device_id.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({"success": false, "message": "device_id is required"}))).into_response();
+    }
+
+    let ip = payload.ip.clone().or_else(|| connect_info.map(|ConnectInfo(addr)| addr.ip().to_string()));
+    match state.device_service.heartbeat(&state.db, device_id, ip.as_deref(), payload.name.as_deref(), payload.branch.as_deref()).await {
+        Ok(device) => Json(json!({"success": true, "data": device})).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to record device heartbeat: {}", err);
+            Json(json!({"success": false, "message": "Failed to record device heartbeat"})).into_response()
+        }
+    }
+}
+
+// Devices seen recently
+async fn get_active_devices(State(state): State<AppState>, Query(query): Query<ActiveDevicesQuery>) -> impl IntoResponse {
+    let within_seconds = query.within_seconds.unwrap_or(ACTIVE_DEVICE_WINDOW_SECS).max(0);
+    match state.device_service.list_active(&state.db, within_seconds).await {
+        Ok(devices) => Json(json!({"success": true, "data": devices})),
+        Err(err) => {
+            tracing::error!("Failed to get active devices: {}", err);
+            Json(json!({"success": false, "message": "Failed to get active devices"}))
+        }
+    }
+}
+
+// Listen for a main device on the LAN, waiting up to the configured connection_timeout
+async fn discover_main_device(State(state): State<AppState>) -> impl IntoResponse {
+    let timeout_ms = state.device_config_service.get_config().map(|config| config.connection_timeout).unwrap_or(10000);
+    match state.device_service.discover_main(std::time::Duration::from_millis(timeout_ms as u64)).await {
+        Ok(Some(main)) => Json(json!({"success": true, "data": main})),
+        Ok(None) => Json(json!({"success": false, "message": "No main device found on the network"})),
+        Err(err) => {
+            tracing::error!("Failed to discover main device: {}", err);
+            Json(json!({"success": false, "message": "Failed to discover main device"}))
+        }
+    }
+}
+
 pub fn devices_routes() -> Router<AppState> {
     Router::new()
         .route("/api/devices", get(get_devices).post(add_device))
         .route("/api/devices/statistics", get(get_device_statistics))
         .route("/api/devices/search", get(search_devices))
         .route("/api/devices/cash/summary", get(get_overall_cash_summary))
+        .route("/api/devices/heartbeat", post(device_heartbeat))
+        .route("/api/devices/active", get(get_active_devices))
+        .route("/api/devices/discover", get(discover_main_device))
         .route("/api/devices/:device_id", get(get_device_by_id).delete(remove_device))
         .route("/api/devices/:device_id/status", patch(update_device_status))
         .route("/api/devices/:device_id/cash/add", post(add_cash))
         .route("/api/devices/:device_id/cash/withdraw", post(withdraw_cash))
         .route("/api/devices/:device_id/cash/summary", get(get_device_cash_summary))
         .route("/api/devices/:device_id/transactions", get(get_device_transactions))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestApp;
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn a_heartbeating_device_is_active_until_it_goes_quiet() {
+        let app = TestApp::new().await;
+        let active = |within: u32| {
+            let app = &app;
+            async move {
+                let (_, body) = app.request(Method::GET, &format!("/api/devices/active?within_seconds={within}"), None, None).await;
+                body["data"].as_array().unwrap().iter().map(|device| device["device_id"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+            }
+        };
+
+        let (status, _) = app.request(Method::POST, "/api/devices/heartbeat", None, Some(json!({
+            "device_id": "POS-2", "name": "كاشير الطابق الثاني", "ip": "192.168.1.23", "branch": "secondary"
+        }))).await;
+        assert_eq!(status, StatusCode::OK);
+        // A later beat without a name keeps the one already known
+        let (_, beat) = app.request(Method::POST, "/api/devices/heartbeat", None, Some(json!({ "device_id": "POS-2", "ip": "192.168.1.24" }))).await;
+        assert_eq!(beat["data"]["name"], "كاشير الطابق الثاني");
+        assert_eq!(beat["data"]["ip"], "192.168.1.24");
+
+        assert_eq!(active(60).await, ["POS-2"]);
+
+        sqlx::query("UPDATE devices SET last_seen = datetime('now', '-300 seconds') WHERE device_id = 'POS-2'")
+            .execute(&app.db.pool).await.unwrap();
+        assert!(active(60).await.is_empty());
+        assert_eq!(active(600).await, ["POS-2"]);
+
+        let (status, _) = app.request(Method::POST, "/api/devices/heartbeat", None, Some(json!({ "device_id": "  " }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -8,7 +8,8 @@ use axum::{
 use serde_json::json;
 use crate::AppState;
 use crate::models::{
-    EmployeeQuery, CreateEmployeeRequest, UpdateEmployeeRequest, CalculateCommissionRequest
+    EmployeeQuery, CreateEmployeeRequest, UpdateEmployeeRequest, CalculateCommissionRequest,
+    CommissionReportQuery
 };
 use tracing::{info, warn, error};
 
@@ -207,6 +208,31 @@ async fn calculate_commission(
     }
 }
 
+// Commission earned on the sales credited to an employee
+async fn get_commission_report(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<CommissionReportQuery>,
+) -> impl IntoResponse {
+    match state.employee_service.commission_report(&state.db, id, query.start_date, query.end_date).await {
+        Ok(report) => {
+            info!("Commission report generated for employee {}", id);
+            Json(json!({
+                "success": true,
+                "message": "تم حساب تقرير العمولة بنجاح",
+                "data": report
+            }))
+        },
+        Err(err) => {
+            error!("Failed to generate commission report: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "فشل حساب تقرير العمولة"
+            }))
+        }
+    }
+}
+
 pub fn employees_routes() -> Router<AppState> {
     Router::new()
         .route("/api/employees", get(get_all_employees))
@@ -217,4 +243,5 @@ pub fn employees_routes() -> Router<AppState> {
         .route("/api/employees/dropdown/list", get(get_employees_dropdown))
         .route("/api/employees/commission/list", get(get_employees_commission_list))
         .route("/api/employees/commission/calculate", post(calculate_commission))
+        .route("/api/employees/:id/commission/report", get(get_commission_report))
 }
\ No newline at end of file
@@ -8,7 +8,8 @@ use axum::{
 use serde_json::json;
 use crate::AppState;
 use crate::models::{
-    ExpenseQuery, CreateExpenseRequest, UpdateExpenseRequest, DateRangeQuery
+    ExpenseQuery, CreateExpenseRequest, UpdateExpenseRequest, DateRangeQuery,
+    CreateRecurringExpenseRequest, RunRecurringRequest
 };
 use tracing::{info, warn, error};
 
@@ -290,6 +291,125 @@ async fn get_total_by_category(
     }
 }
 
+// Expense totals per normalized category
+async fn get_category_breakdown(
+    State(state): State<AppState>,
+    Query(query): Query<DateRangeQuery>,
+) -> impl IntoResponse {
+    let (start_date, end_date) = match (query.start_date, query.end_date) {
+        (Some(start_date), Some(end_date)) => (start_date, end_date),
+        _ => {
+            return Json(json!({
+                "success": false,
+                "message": "تاريخ البداية والنهاية مطلوبان"
+            }));
+        }
+    };
+
+    match state.expense_service.category_breakdown(&state.db, start_date, end_date).await {
+        Ok(breakdown) => {
+            info!("Expense category breakdown fetched successfully from {} to {}", start_date, end_date);
+            Json(json!({
+                "success": true,
+                "message": "تم جلب توزيع المصروفات حسب الفئة بنجاح",
+                "data": breakdown
+            }))
+        },
+        Err(err) => {
+            error!("Failed to fetch expense category breakdown: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Get recurring expense templates
+async fn get_recurring_expenses(State(state): State<AppState>) -> impl IntoResponse {
+    match state.expense_service.get_recurring(&state.db).await {
+        Ok(templates) => Json(json!({
+            "success": true,
+            "message": "تم جلب المصروفات المتكررة بنجاح",
+            "data": templates
+        })),
+        Err(err) => {
+            error!("Failed to fetch recurring expenses: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب المصروفات المتكررة"
+            }))
+        }
+    }
+}
+
+// Create recurring expense template
+async fn create_recurring_expense(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateRecurringExpenseRequest>,
+) -> impl IntoResponse {
+    match state.expense_service.create_recurring(&state.db, payload).await {
+        Ok(template) => Json(json!({
+            "success": true,
+            "message": "تم إنشاء المصروف المتكرر بنجاح",
+            "data": template
+        })),
+        Err(err) => {
+            error!("Failed to create recurring expense: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Stop a recurring expense template
+async fn deactivate_recurring_expense(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.expense_service.deactivate_recurring(&state.db, id).await {
+        Ok(true) => Json(json!({
+            "success": true,
+            "message": "تم إيقاف المصروف المتكرر بنجاح"
+        })),
+        Ok(false) => Json(json!({
+            "success": false,
+            "message": "المصروف المتكرر غير موجود"
+        })),
+        Err(err) => {
+            error!("Failed to deactivate recurring expense {}: {}", id, err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء إيقاف المصروف المتكرر"
+            }))
+        }
+    }
+}
+
+// Generate due recurring expenses now (defaults to today)
+async fn run_recurring_expenses(
+    State(state): State<AppState>,
+    Json(payload): Json<RunRecurringRequest>,
+) -> impl IntoResponse {
+    let as_of = payload.as_of.unwrap_or_else(|| chrono::Local::now().date_naive());
+    match state.expense_service.run_recurring(&state.db, as_of).await {
+        Ok(result) => Json(json!({
+            "success": true,
+            "message": "تم توليد المصروفات المتكررة المستحقة",
+            "data": result
+        })),
+        Err(err) => {
+            error!("Failed to run recurring expenses: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 // Get total by date range
 async fn get_total_by_date_range(
     State(state): State<AppState>,
@@ -336,5 +456,10 @@ pub fn expenses_routes() -> Router<AppState> {
         .route("/api/expenses/:id", put(update_expense))
         .route("/api/expenses/:id", delete(delete_expense))
         .route("/api/expenses/total-by-category", get(get_total_by_category))
+        .route("/api/expenses/category-breakdown", get(get_category_breakdown))
+        .route("/api/expenses/recurring", get(get_recurring_expenses))
+        .route("/api/expenses/recurring", post(create_recurring_expense))
+        .route("/api/expenses/recurring/run", post(run_recurring_expenses))
+        .route("/api/expenses/recurring/:id", delete(deactivate_recurring_expense))
         .route("/api/expenses/total-by-date-range", get(get_total_by_date_range))
 }
\ No newline at end of file
@@ -9,7 +9,8 @@ use serde_json::json;
 use crate::AppState;
 use crate::models::installment::{
     InstallmentQuery, CreateInstallmentRequest, UpdateInstallmentRequest, 
-    InstallmentPaymentRequest, CreateInstallmentPlanRequest
+    InstallmentPaymentRequest, CreateInstallmentPlanRequest, GenerateScheduleRequest,
+    PaymentRecordResponse, PaymentRecord
 };
 use tracing::{info, warn, error};
 
@@ -331,9 +332,26 @@ async fn record_payment(
         }));
     }
 
-    match state.installments_service.record_payment(&state.db, id, payload).await {
-        Ok(result) => {
+    match state.installments_service.record_payment(&state.db, id, payload.paid_amount, &payload.payment_method, payload.money_box_id).await {
+        Ok(installment) => {
             info!("Installment payment recorded successfully for ID: {}", id);
+            let result = PaymentRecordResponse {
+                receipt: json!({
+                    "customer_id": installment.customer_id,
+                    "sale_id": installment.sale_id,
+                    "amount": payload.paid_amount,
+                    "payment_method": payload.payment_method,
+                    "notes": payload.notes,
+                    "created_at": chrono::Utc::now()
+                }),
+                payment: PaymentRecord {
+                    paid_amount: payload.paid_amount,
+                    payment_method: payload.payment_method,
+                    notes: payload.notes,
+                    recorded_at: chrono::Utc::now().naive_utc(),
+                },
+                installment,
+            };
             Json(json!({
                 "success": true,
                 "message": "تم تسجيل الدفع بنجاح",
@@ -344,7 +362,7 @@ async fn record_payment(
             error!("Failed to record installment payment: {}", err);
             Json(json!({
                 "success": false,
-                "message": "حدث خطأ أثناء تسجيل الدفع"
+                "message": err.to_string()
             }))
         }
     }
@@ -396,6 +414,30 @@ async fn create_installment_plan(
     }
 }
 
+// Generate an evenly split installment schedule for an existing sale
+async fn generate_schedule(
+    State(state): State<AppState>,
+    Json(payload): Json<GenerateScheduleRequest>,
+) -> impl IntoResponse {
+    match state.installments_service.generate_schedule(&state.db, payload).await {
+        Ok(installments) => {
+            info!("Installment schedule generated successfully");
+            Json(json!({
+                "success": true,
+                "message": "تم إنشاء جدول الأقساط بنجاح",
+                "data": installments
+            }))
+        },
+        Err(err) => {
+            error!("Failed to generate installment schedule: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 pub fn installments_routes() -> Router<AppState> {
     Router::new()
         .route("/api/installments", get(get_all_installments))
@@ -411,4 +453,5 @@ pub fn installments_routes() -> Router<AppState> {
         .route("/api/installments/:id", delete(delete_installment))
         .route("/api/installments/:id/payment", post(record_payment))
         .route("/api/installments/plan", post(create_installment_plan))
+        .route("/api/installments/schedule", post(generate_schedule))
 }
\ No newline at end of file
@@ -0,0 +1,149 @@
+use axum::{
+    routing::{get, post},
+    Router,
+    extract::{State, Query, Path},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use crate::AppState;
+use crate::models::inventory::*;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
+use tracing::error;
+
+// Stock on hand valued per warehouse; average cost unless ?method=last_purchase_price
+async fn get_inventory_valuation(
+    State(state): State<AppState>,
+    Query(query): Query<ValuationQuery>,
+) -> impl IntoResponse {
+    let method = query.method.unwrap_or(CostMethod::AverageCost);
+    match state.inventory_service.valuation(&state.db, method).await {
+        Ok(valuation) => Json(json!({
+            "success": true,
+            "data": valuation,
+            "message": "تم حساب قيمة المخزون بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to value inventory: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء حساب قيمة المخزون"
+            }))
+        }
+    }
+}
+
+// Products whose last count is older than ?days= (default 90) or that were never counted
+async fn get_due_stock_check(
+    State(state): State<AppState>,
+    Query(query): Query<StockCheckQuery>,
+) -> impl IntoResponse {
+    match state.inventory_service.due_for_stock_check(&state.db, query.days.unwrap_or(90)).await {
+        Ok(products) => Json(json!({
+            "success": true,
+            "data": products,
+            "message": "تم جلب المنتجات المستحقة للجرد بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get products due for stock check: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Products whose stored stock differs from the net of their movements
+async fn get_stock_drift(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    match state.inventory_service.detect_drift(&state.db).await {
+        Ok(drifts) => Json(json!({
+            "success": true,
+            "data": drifts,
+            "message": "تم فحص فروقات المخزون بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to detect stock drift: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء فحص فروقات المخزون"
+            }))
+        }
+    }
+}
+
+async fn reconcile_stock(
+    State(state): State<AppState>,
+    Path(product_id): Path<i64>,
+    Json(payload): Json<ReconcileRequest>,
+) -> impl IntoResponse {
+    match state.inventory_service.reconcile(&state.db, product_id, payload.strategy).await {
+        Ok(result) => Json(json!({
+            "success": true,
+            "data": result,
+            "message": "تمت تسوية مخزون المنتج بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to reconcile stock of product {}: {}", product_id, err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Purchase batches of a product with remaining quantity and expiry, in the order sales draw from them
+async fn get_product_batches(
+    State(state): State<AppState>,
+    Path(product_id): Path<i64>,
+) -> impl IntoResponse {
+    match state.inventory_service.batches_for_product(&state.db, product_id).await {
+        Ok(batches) => Json(json!({
+            "success": true,
+            "data": batches,
+            "message": "تم جلب دفعات المنتج بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get batches of product {}: {}", product_id, err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب دفعات المنتج"
+            }))
+        }
+    }
+}
+
+// Draft purchase orders per supplier for products at or below their reorder point
+async fn get_reorder_suggestions(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    match state.inventory_service.reorder_suggestions(&state.db).await {
+        Ok(suggestions) => Json(json!({
+            "success": true,
+            "data": suggestions,
+            "message": "تم إعداد اقتراحات إعادة الطلب بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to build reorder suggestions: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء إعداد اقتراحات إعادة الطلب"
+            }))
+        }
+    }
+}
+
+pub fn inventory_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/inventory/valuation", get(get_inventory_valuation))
+        .route("/api/inventory/due-stock-check", get(get_due_stock_check))
+        .route("/api/inventory/reorder-suggestions", get(get_reorder_suggestions))
+        .route("/api/inventory/drift", get(get_stock_drift))
+        .route("/api/inventory/products/:product_id/batches", get(get_product_batches))
+        .route("/api/inventory/drift/:product_id/reconcile", post(reconcile_stock)
+            .route_layer(from_fn_with_state(RequirePermission("inventory.manage"), require_permission)))
+}
@@ -281,11 +281,13 @@ pub async fn cache_stats_handler() -> Result<Json<ApiResponse<Value>>, (StatusCo
     Ok(Json(ApiResponse::success(stats)))
 }
 
-// Notifications handler (placeholder)
-pub async fn notifications_handler() -> Result<Json<ApiResponse<Value>>, (StatusCode, Json<ApiResponse<String>>)> {
-    // For now, return empty notifications
+// Notifications handler: license expiry warnings
+pub async fn notifications_handler(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Value>>, (StatusCode, Json<ApiResponse<String>>)> {
+    // Expiry events from the latest background re-verification
     let notifications = serde_json::json!({
-        "notifications": [],
+        "notifications": state.license_service.license_events().await,
         "timestamp": chrono::Utc::now().to_rfc3339()
     });
     
@@ -1,13 +1,17 @@
 use axum::{
     routing::{get, post, put, delete},
+    middleware::from_fn_with_state,
     Router,
     extract::{State, Path, Query},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::AppState;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use crate::services::mobile_live_data_service::SYNCABLE_TABLES;
 
 #[derive(Debug, Deserialize)]
 pub struct CreateUserRequest {
@@ -37,6 +41,38 @@ pub struct SyncDataRequest {
     pub last_sync: Option<String>,
 }
 
+// How a queued mobile change is settled when the server copy changed since the device last saw it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    #[default]
+    LastWriteWins, // the newer updated_at wins
+    ServerWins,    // the server copy is kept and the change rejected
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncBatchRequest {
+    pub strategy: Option<ConflictStrategy>,
+    pub changes: Vec<SyncChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncChange {
+    pub table: String,
+    pub record_id: Option<i64>,
+    pub operation: String, // "insert", "update", "delete"
+    #[serde(default)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+    pub updated_at: chrono::NaiveDateTime,              // when the device made the change
+    pub base_updated_at: Option<chrono::NaiveDateTime>, // server updated_at the device last synced
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangesQuery {
+    pub since: String,          // RFC 3339 or "YYYY-MM-DD HH:MM:SS" (UTC)
+    pub tables: Option<String>, // comma separated, every syncable table when omitted
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateScheduleRequest {
     pub name: String,
@@ -107,6 +143,46 @@ async fn sync_data(State(state): State<AppState>, Path(data_type): Path<String>,
     }
 }
 
+// Apply a batch of queued offline changes; gated on settings.manage in the router
+async fn sync_batch(State(state): State<AppState>, Json(payload): Json<SyncBatchRequest>) -> impl IntoResponse {
+    match state.mobile_live_data_service.resolve_and_apply(&state.db, payload).await {
+        Ok(report) => Json(json!({"success": true, "data": report, "message": "Changes synced"})),
+        Err(err) => {
+            tracing::error!("Failed to apply sync batch: {}", err);
+            Json(json!({"success": false, "message": "Failed to apply sync batch"}))
+        }
+    }
+}
+
+// Rows created, updated or deleted since a timestamp, for devices pulling deltas; gated like sync-batch
+async fn get_changes(State(state): State<AppState>, Query(query): Query<ChangesQuery>) -> Response {
+    let since = match chrono::DateTime::parse_from_rfc3339(&query.since)
+        .map(|since| since.naive_utc())
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(&query.since, "%Y-%m-%d %H:%M:%S"))
+    {
+        Ok(since) => since,
+        Err(_) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"success": false, "message": "since must be an RFC 3339 timestamp"}))).into_response();
+        }
+    };
+
+    let tables: Vec<String> = match query.tables {
+        Some(ref tables) => tables.split(',').map(|table| table.trim().to_string()).filter(|table| !table.is_empty()).collect(),
+        None => SYNCABLE_TABLES.iter().map(|table| table.to_string()).collect(),
+    };
+    if let Some(table) = tables.iter().find(|table| !SYNCABLE_TABLES.contains(&table.as_str())) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"success": false, "message": format!("table {} cannot be synced", table)}))).into_response();
+    }
+
+    match state.mobile_live_data_service.changes_since(&state.db, since, tables).await {
+        Ok(changes) => Json(json!({"success": true, "data": changes})).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to get changes: {}", err);
+            Json(json!({"success": false, "message": "Failed to get changes"})).into_response()
+        }
+    }
+}
+
 // Test connection (admin only)
 async fn test_connection(State(state): State<AppState>) -> impl IntoResponse {
     match state.mobile_live_data_service.test_connection(&state.db).await {
@@ -201,10 +277,80 @@ pub fn mobile_live_data_routes() -> Router<AppState> {
         .route("/api/mobile-live-data/users", post(create_user).get(get_users))
         .route("/api/mobile-live-data/upload", post(upload_data))
         .route("/api/mobile-live-data/sync/:data_type", post(sync_data))
+        .route("/api/mobile-live-data/sync-batch", post(sync_batch)
+            .route_layer(from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+        .route("/api/mobile/changes", get(get_changes)
+            .route_layer(from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
         .route("/api/mobile-live-data/test-connection", get(test_connection))
         .route("/api/mobile-live-data/sync-status", get(get_sync_status))
         .route("/api/mobile-live-data/license-info", get(get_license_info))
         .route("/api/mobile-live-data/schedules", post(create_schedule).get(get_schedules))
         .route("/api/mobile-live-data/schedules/execute", post(execute_scheduled_uploads))
         .route("/api/mobile-live-data/auto-upload-settings", get(get_auto_upload_settings).post(save_auto_upload_settings))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestApp;
+    use axum::http::{Method, StatusCode};
+
+    #[tokio::test]
+    async fn changes_since_holds_rows_written_after_it_and_deleted_ids() {
+        let app = TestApp::new().await;
+        let pool = &app.db.pool;
+        // The seeded walk-in customer dates from before the device's last pull
+        sqlx::query("UPDATE customers SET updated_at = '2026-01-01 00:00:00'").execute(pool).await.unwrap();
+        sqlx::query("INSERT INTO customers (name, phone, updated_at) VALUES ('Before', '07700000001', '2026-10-01 08:00:00')")
+            .execute(pool).await.unwrap();
+        let after = sqlx::query("INSERT INTO customers (name, phone, updated_at) VALUES ('After', '07700000002', '2026-10-01 12:30:00')")
+            .execute(pool).await.unwrap()
+            .last_insert_rowid();
+        let removed = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, updated_at) VALUES ('Old cable', 'CBL-OLD', 1, 2, 2, '2026-09-01 00:00:00')")
+            .execute(pool).await.unwrap()
+            .last_insert_rowid();
+        sqlx::query("DELETE FROM products WHERE id = ?").bind(removed).execute(pool).await.unwrap();
+
+        let (status, _) = app.request(Method::GET, "/api/mobile/changes?since=2026-10-01T12:00:00Z&tables=customers", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        app.add_user("owner", "admin", &[]).await;
+        let owner = app.login("owner").await;
+
+        let (status, body) = app.request(Method::GET, "/api/mobile/changes?since=2026-10-01T12:00:00Z&tables=customers,products", Some(&owner), None).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let customers: Vec<i64> = body["data"]["changes"]["customers"].as_array().unwrap().iter().map(|row| row["id"].as_i64().unwrap()).collect();
+        assert_eq!(customers, [after]);
+        // Balances stay on the server
+        assert!(body["data"]["changes"]["customers"][0].get("current_balance").is_none());
+        assert!(body["data"]["changes"]["products"].as_array().unwrap().is_empty());
+        assert_eq!(body["data"]["deleted"]["products"], serde_json::json!([removed]));
+        assert!(body["data"]["changes"].get("suppliers").is_none());
+
+        let (status, _) = app.request(Method::GET, "/api/mobile/changes?since=2026-10-01T12:00:00Z&tables=users", Some(&owner), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = app.request(Method::GET, "/api/mobile/changes?since=yesterday", Some(&owner), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn sync_batch_needs_a_session_and_settings_manage() {
+        let app = TestApp::new().await;
+        app.add_user("cashier", "user", &["customers.view"]).await;
+        app.add_user("owner", "admin", &[]).await;
+        let batch = serde_json::json!({
+            "changes": [{ "table": "customers", "operation": "insert", "data": { "name": "Device customer" }, "updated_at": "2026-10-01T11:00:00" }]
+        });
+        let customers = || sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM customers WHERE name = 'Device customer'").fetch_one(&app.db.pool);
+
+        let (status, _) = app.request(Method::POST, "/api/mobile-live-data/sync-batch", None, Some(batch.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let cashier = app.login("cashier").await;
+        let (status, _) = app.request(Method::POST, "/api/mobile-live-data/sync-batch", Some(&cashier), Some(batch.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(customers().await.unwrap(), 0);
+
+        let owner = app.login("owner").await;
+        let (status, body) = app.request(Method::POST, "/api/mobile-live-data/sync-batch", Some(&owner), Some(batch)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["data"]["applied"], 1);
+        assert_eq!(customers().await.unwrap(), 1);
+    }
+}
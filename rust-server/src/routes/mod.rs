@@ -17,6 +17,12 @@ pub mod delegates_routes;
 pub mod employees_routes;
 pub mod stocks_routes;
 pub mod stock_movements_routes;
+pub mod stock_holds_routes;
+pub mod units_routes;
+pub mod category_routes;
+pub mod inventory_routes;
+pub mod sequences_routes;
+pub mod barcode_routes;
 pub mod money_boxes_routes;
 pub mod devices_routes;
 pub mod mobile_live_data_routes;
@@ -46,6 +52,12 @@ pub use delegates_routes::delegates_routes;
 pub use employees_routes::employees_routes;
 pub use stocks_routes::stocks_routes;
 pub use stock_movements_routes::stock_movements_routes;
+pub use stock_holds_routes::stock_holds_routes;
+pub use units_routes::units_routes;
+pub use category_routes::category_routes;
+pub use inventory_routes::inventory_routes;
+pub use sequences_routes::sequences_routes;
+pub use barcode_routes::barcode_routes;
 pub use money_boxes_routes::money_boxes_routes;
 pub use devices_routes::devices_routes;
 pub use mobile_live_data_routes::mobile_live_data_routes;
@@ -4,7 +4,7 @@ use axum::{
     extract::{State, Path, Query},
     response::IntoResponse,
     Json,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -15,10 +15,12 @@ use crate::{
     },
     services::money_boxes_service::{
         InternalCreateMoneyBoxRequest, InternalUpdateMoneyBoxRequest, InternalAddTransactionRequest,
-        InternalTransferRequest, InternalTransactionQuery
+        InternalTransferRequest, InternalTransactionQuery, ReconcileResult, ReopenMoneyBoxResult
     },
+    middleware::permission_middleware::{RequirePermission, require_permission},
     AppState,
 };
+use axum::middleware::from_fn_with_state;
 
 
 
@@ -233,6 +235,57 @@ async fn get_money_box_summary(
     }
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct ReconcileBalanceRequest {
+    pub correct: Option<bool>,
+}
+
+// Compare a box's stored amount with its transaction ledger, optionally correcting it
+async fn reconcile_balance(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    payload: Option<Json<ReconcileBalanceRequest>>,
+) -> Result<Json<ApiResponse<ReconcileResult>>, (StatusCode, Json<ApiResponse<String>>)> {
+    let correct = payload.and_then(|Json(payload)| payload.correct).unwrap_or(false);
+    match state.money_boxes_service.reconcile_box(&state.db, id, correct).await {
+        Ok(result) => Ok(Json(ApiResponse::success(result))),
+        Err(err) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("فشل في مطابقة رصيد صندوق المال: {}", err))),
+        )),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReopenMoneyBoxRequest {
+    pub reason: String,
+}
+
+// Undo a shift close made in error; the permission layer has already resolved the token
+async fn reopen_money_box(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<ReopenMoneyBoxRequest>,
+) -> Result<Json<ApiResponse<ReopenMoneyBoxResult>>, (StatusCode, Json<ApiResponse<String>>)> {
+    let token = headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let user = state.auth_service.get_user_from_token(&state.db, token).await.map_err(|_| (
+        StatusCode::UNAUTHORIZED,
+        Json(ApiResponse::error("جلسة غير صالحة، يرجى تسجيل الدخول مجدداً".to_string())),
+    ))?;
+
+    match state.money_boxes_service.reopen(&state.db, id, &payload.reason, user.id.unwrap_or_default(), user.is_admin()).await {
+        Ok(result) => Ok(Json(ApiResponse::success(result))),
+        Err(err) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("فشل في إعادة فتح صندوق المال: {}", err))),
+        )),
+    }
+}
+
 // Create money box
 async fn create_money_box(
     State(state): State<AppState>,
@@ -526,6 +579,9 @@ pub fn money_boxes_routes() -> Router<AppState> {
         .route("/api/money-boxes/transfer", post(transfer_between_money_boxes))
         .route("/api/money-boxes/:id", get(get_money_box_by_id).put(update_money_box).delete(delete_money_box))
         .route("/api/money-boxes/:id/summary", get(get_money_box_summary))
+        .route("/api/money-boxes/:id/reconcile-balance", post(reconcile_balance))
+        .route("/api/money-boxes/:id/reopen", post(reopen_money_box)
+            .route_layer(from_fn_with_state(RequirePermission("cashbox.manage"), require_permission)))
         .route("/api/money-boxes/:id/transactions", get(get_money_box_transactions).post(add_transaction))
         .route("/api/money-boxes/:id/transactions/date-range", get(get_transactions_by_date_range))
 }
\ No newline at end of file
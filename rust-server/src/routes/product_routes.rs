@@ -2,15 +2,20 @@ use axum::{
     routing::{get, post, put, delete},
     Router,
     extract::{State, Path, Query, Multipart},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 
 use serde_json::json;
 
 use crate::AppState;
+use crate::utils::{InvalidCursor, ItemLimitExceeded};
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
 use crate::models::{
-    ProductQuery, CreateProductRequest, UpdateProductRequest
+    ProductQuery, CreateProductRequest, UpdateProductRequest,
+    BulkPriceUpdateRequest, BulkPriceUpdateQuery
 };
 use serde::{Deserialize, Serialize};
 
@@ -18,13 +23,18 @@ use serde::{Deserialize, Serialize};
 pub struct UpdateProductStockRequest {
     pub quantity: i64,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct BarcodesRequest {
+    pub barcodes: Vec<String>,
+}
 use tracing::{info, warn, error};
 
 // Get all products
 async fn get_all_products(
     State(state): State<AppState>,
     Query(query): Query<ProductQuery>,
-) -> impl IntoResponse {
+) -> Response {
     match state.product_service.get_all(&state.db, &query).await {
         Ok(result) => {
             info!("Products fetched successfully: {} products found", result.items.len());
@@ -34,8 +44,9 @@ async fn get_all_products(
                 Json(json!({
                     "success": true,
                     "data": result.items,
+                    "next_cursor": result.next_cursor,
                     "message": "Products retrieved successfully"
-                }))
+                })).into_response()
             } else {
                 Json(json!({
                     "success": true,
@@ -48,18 +59,25 @@ async fn get_all_products(
                             "totalItems": result.total,
                             "itemsPerPage": result.limit,
                             "hasNextPage": result.page < result.total_pages,
-                            "hasPrevPage": result.page > 1
+                            "hasPrevPage": result.page > 1,
+                            "nextCursor": result.next_cursor
                         }
                     }
-                }))
+                })).into_response()
             }
         },
         Err(err) => {
             error!("Failed to fetch products: {}", err);
+            if let Some(invalid) = err.downcast_ref::<InvalidCursor>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": invalid.to_string()
+                }))).into_response();
+            }
             Json(json!({
                 "success": false,
                 "message": "حدث خطأ أثناء جلب المنتجات"
-            }))
+            })).into_response()
         }
     }
 }
@@ -132,7 +150,8 @@ async fn create_product(
             error!("Failed to create product: {}", err);
             Json(json!({
                 "success": false,
-                "message": "حدث خطأ أثناء إنشاء المنتج"
+                "message": "حدث خطأ أثناء إنشاء المنتج",
+                "error": err.to_string()
             }))
         }
     }
@@ -157,7 +176,8 @@ async fn update_product(
             error!("Failed to update product: {}", err);
             Json(json!({
                 "success": false,
-                "message": "حدث خطأ أثناء تحديث المنتج"
+                "message": "حدث خطأ أثناء تحديث المنتج",
+                "error": err.to_string()
             }))
         }
     }
@@ -276,6 +296,37 @@ async fn get_product_by_barcode(
     }
 }
 
+// Resolve a list of barcodes in one call; only found, active products come back, keyed by barcode
+async fn get_products_by_barcodes(
+    State(state): State<AppState>,
+    Json(payload): Json<BarcodesRequest>,
+) -> Response {
+    let requested = payload.barcodes.len();
+    match state.product_service.get_by_barcodes(&state.db, payload.barcodes).await {
+        Ok(products) => {
+            info!("Resolved {} of {} barcodes", products.len(), requested);
+            Json(json!({
+                "success": true,
+                "message": "تم جلب المنتجات بنجاح",
+                "data": products
+            })).into_response()
+        },
+        Err(err) => {
+            error!("Failed to fetch products by barcodes: {}", err);
+            if let Some(exceeded) = err.downcast_ref::<ItemLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string()
+                }))).into_response();
+            }
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب المنتجات"
+            })).into_response()
+        }
+    }
+}
+
 // Get products optimized for POS
 async fn get_products_for_pos(
     State(state): State<AppState>,
@@ -335,6 +386,36 @@ async fn update_product_stock(
     }
 }
 
+// Bulk price update (preview only when dry_run=true)
+async fn bulk_update_prices(
+    State(state): State<AppState>,
+    Query(query): Query<BulkPriceUpdateQuery>,
+    Json(payload): Json<BulkPriceUpdateRequest>,
+) -> impl IntoResponse {
+    let dry_run = query.dry_run.unwrap_or(false);
+    match state.product_service.bulk_update_prices(&state.db, &payload, dry_run).await {
+        Ok(result) => {
+            let message = if dry_run {
+                "تمت معاينة تحديث الأسعار بدون حفظ"
+            } else {
+                "تم تحديث الأسعار بنجاح"
+            };
+            Json(json!({
+                "success": true,
+                "message": message,
+                "data": result
+            }))
+        },
+        Err(err) => {
+            error!("Failed to bulk update prices: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 // Get expiring products
 async fn get_expiring_products(
     State(state): State<AppState>,
@@ -367,9 +448,10 @@ async fn get_expiring_products(
 async fn import_products(
     State(state): State<AppState>,
     mut multipart: Multipart,
-) -> impl IntoResponse {
+) -> Response {
     let mut file_content: Option<Vec<u8>> = None;
     let mut filename: Option<String> = None;
+    let mut category: Option<String> = None;
 
     // Parse multipart form data
     while let Some(field) = multipart.next_field().await.unwrap_or(None) {
@@ -379,6 +461,9 @@ async fn import_products(
                 if let Ok(data) = field.bytes().await {
                     file_content = Some(data.to_vec());
                 }
+            } else if field_name == "category" {
+                // Per-import override of the default_import_category setting
+                category = field.text().await.ok();
             }
         }
     }
@@ -389,7 +474,7 @@ async fn import_products(
             return Json(json!({
                 "success": false,
                 "message": "لم يتم رفع أي ملف أو الملف غير صالح"
-            }));
+            })).into_response();
         }
     };
 
@@ -403,11 +488,11 @@ async fn import_products(
         return Json(json!({
             "success": false,
             "message": "نوع الملف غير مدعوم. يرجى رفع ملف Excel (.xlsx/.xls) أو CSV."
-        }));
+        })).into_response();
     }
 
     // Process the file using the service layer
-    match state.product_service.import_products(&state.db, &file_content, &filename).await {
+    match state.product_service.import_products(&state.db, &file_content, &filename, category.as_deref()).await {
         Ok(result) => {
             info!("Products imported successfully: {} imported, {} failed", result.imported, result.failed);
             let mut message = format!("تم استيراد {} منتج بنجاح", result.imported);
@@ -431,14 +516,20 @@ async fn import_products(
                     "errors": result.errors,
                     "error_count": result.error_count
                 }
-            }))
+            })).into_response()
         },
         Err(err) => {
             error!("Failed to import products: {}", err);
+            if let Some(exceeded) = err.downcast_ref::<ItemLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string()
+                }))).into_response();
+            }
             Json(json!({
                 "success": false,
                 "message": format!("حدث خطأ أثناء استيراد المنتجات: {}", err)
-            }))
+            })).into_response()
         }
     }
 }
@@ -472,19 +563,72 @@ async fn get_product_movements(
     }
 }
 
+// Get product stock history with running balance
+async fn get_product_stock_history(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let current_stock = match state.product_service.get_by_id(&state.db, id).await {
+        Ok(Some(product)) => product.current_stock,
+        Ok(None) => {
+            return Json(json!({
+                "success": false,
+                "message": "المنتج غير موجود"
+            }));
+        },
+        Err(err) => {
+            error!("Failed to get product {}: {}", id, err);
+            return Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب المنتج"
+            }));
+        }
+    };
+
+    match state.stock_movements_service.product_history(&state.db, id).await {
+        Ok(history) => {
+            let final_balance = history.last().map(|m| m.running_balance).unwrap_or(0);
+            Json(json!({
+                "success": true,
+                "data": {
+                    "product_id": id,
+                    "current_stock": current_stock,
+                    "calculated_balance": final_balance,
+                    "difference": current_stock - final_balance,
+                    "reconciled": current_stock == final_balance,
+                    "movements": history
+                },
+                "message": "تم استرجاع سجل مخزون المنتج بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to get product stock history: {}", err);
+            Json(json!({
+                "success": false,
+                "message": format!("فشل في استرجاع سجل مخزون المنتج: {}", err)
+            }))
+        }
+    }
+}
+
 pub fn product_routes() -> Router<AppState> {
     Router::new()
         .route("/api/products", get(get_all_products))
         .route("/api/products", post(create_product))
         .route("/api/products/import", post(import_products))
+        .route("/api/products/bulk-price", post(bulk_update_prices)
+            .route_layer(from_fn_with_state(RequirePermission("products.edit"), require_permission)))
         .route("/api/products/search", get(search_products))
         .route("/api/products/low-stock", get(get_low_stock_products))
         .route("/api/products/expiring", get(get_expiring_products))
         .route("/api/products/pos", get(get_products_for_pos))
         .route("/api/products/barcode/:barcode", get(get_product_by_barcode))
+        .route("/api/products/by-barcodes", post(get_products_by_barcodes))
         .route("/api/products/:id", get(get_product_by_id))
         .route("/api/products/:id", put(update_product))
-        .route("/api/products/:id", delete(delete_product))
+        .route("/api/products/:id", delete(delete_product)
+            .route_layer(from_fn_with_state(RequirePermission("products.delete"), require_permission)))
         .route("/api/products/:id/stock", put(update_product_stock))
         .route("/api/products/:id/movements", get(get_product_movements))
+        .route("/api/products/:id/stock-history", get(get_product_stock_history))
 }
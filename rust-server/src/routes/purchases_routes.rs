@@ -2,29 +2,31 @@ use axum::{
     routing::{get, post, put, delete},
     Router,
     extract::{State, Path, Query},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use crate::AppState;
 use crate::models::{
-    CreatePurchaseRequest, UpdatePurchaseRequest, PurchaseReturnRequest
+    CreatePurchaseRequest, UpdatePurchaseRequest, PurchaseQuery, PurchaseReturnRequest
 };
+use crate::services::InconsistentAmounts;
+use crate::utils::ItemLimitExceeded;
 use tracing::{info, warn, error};
 
 // Get all purchases
 async fn get_all_purchases(
     State(state): State<AppState>,
+    Query(query): Query<PurchaseQuery>,
 ) -> impl IntoResponse {
-    match state.purchase_service.get_all(&state.db).await {
+    match state.purchase_service.get_all(&state.db, &query).await {
         Ok(purchases) => {
-            info!("Purchases fetched successfully: {} purchases found", purchases.len());
+            info!("Purchases fetched successfully: {} of {} purchases", purchases.purchases.len(), purchases.total);
             Json(json!({
                 "success": true,
                 "message": "تم جلب المشتريات بنجاح",
-                "data": {
-                    "purchases": purchases
-                }
+                "data": purchases
             }))
         },
         Err(err) => {
@@ -127,13 +129,13 @@ async fn get_supplier_purchases(
 async fn create_purchase(
     State(state): State<AppState>,
     Json(payload): Json<CreatePurchaseRequest>,
-) -> impl IntoResponse {
+) -> Response {
     // Validate required fields
     if payload.items.is_empty() {
         return Json(json!({
             "success": false,
             "message": "يجب إضافة منتج واحد على الأقل"
-        }));
+        })).into_response();
     }
 
     for item in &payload.items {
@@ -141,13 +143,13 @@ async fn create_purchase(
             return Json(json!({
                 "success": false,
                 "message": "الكمية يجب أن تكون أكبر من صفر"
-            }));
+            })).into_response();
         }
         if item.price < 0.0 {
             return Json(json!({
                 "success": false,
                 "message": "السعر يجب أن يكون أكبر من أو يساوي صفر"
-            }));
+            })).into_response();
         }
     }
 
@@ -160,10 +162,22 @@ async fn create_purchase(
                 "data": {
                     "purchase": purchase
                 }
-            }))
+            })).into_response()
         },
         Err(err) => {
             error!("Failed to create purchase: {}", err);
+            if let Some(exceeded) = err.downcast_ref::<ItemLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string()
+                }))).into_response();
+            }
+            if let Some(inconsistent) = err.downcast_ref::<InconsistentAmounts>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": inconsistent.to_string()
+                }))).into_response();
+            }
             
             // Handle specific duplicate errors
             let error_message = if err.to_string().contains("duplicate") || 
@@ -177,7 +191,7 @@ async fn create_purchase(
             Json(json!({
                 "success": false,
                 "message": error_message
-            }))
+            })).into_response()
         }
     }
 }
@@ -187,7 +201,7 @@ async fn update_purchase(
     State(state): State<AppState>,
     Path(id): Path<i64>,
     Json(payload): Json<UpdatePurchaseRequest>,
-) -> impl IntoResponse {
+) -> Response {
     match state.purchase_service.update(&state.db, id, payload, Some(1)).await {
         Ok(Some(purchase)) => {
             info!("Purchase updated successfully for ID: {}", id);
@@ -197,18 +211,24 @@ async fn update_purchase(
                 "data": {
                     "purchase": purchase
                 }
-            }))
+            })).into_response()
         },
         Ok(None) => Json(json!({
             "success": false,
             "message": "المشتريات غير موجودة"
-        })),
+        })).into_response(),
         Err(err) => {
             error!("Failed to update purchase: {}", err);
+            if let Some(exceeded) = err.downcast_ref::<ItemLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string()
+                }))).into_response();
+            }
             Json(json!({
                 "success": false,
                 "message": "حدث خطأ أثناء تحديث المشتريات"
-            }))
+            })).into_response()
         }
     }
 }
@@ -267,7 +287,7 @@ async fn process_purchase_return(
         }));
     }
 
-    match state.purchase_service.process_purchase_return(&state.db, id, payload.items, payload.reason, Some(1)).await {
+    match state.purchase_service.create_return(&state.db, id, payload.items, payload.reason, payload.refund_method, Some(1)).await {
         Ok(result) => {
             info!("Purchase return processed successfully for purchase ID: {}", id);
             Json(json!({
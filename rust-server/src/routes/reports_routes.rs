@@ -1,5 +1,5 @@
 use axum::{
-    routing::get,
+    routing::{get, post},
     Router,
     extract::{State, Query},
     response::IntoResponse,
@@ -7,7 +7,7 @@ use axum::{
 };
 use serde_json::json;
 use crate::AppState;
-use crate::models::report::ReportQuery;
+use crate::models::report::{ReportQuery, SnapshotRequest, CompareQuery, ProfitAndLossQuery, TopProductsQuery, TopProductsMetric, BalanceSnapshotQuery};
 use tracing::{info, warn, error};
 
 // Get dashboard summary
@@ -452,6 +452,128 @@ async fn get_customer_debts_detailed_report(
     }
 }
 
+// Recalculate a period and store it in the reports table
+async fn create_report_snapshot(
+    State(state): State<AppState>,
+    Json(payload): Json<SnapshotRequest>,
+) -> impl IntoResponse {
+    match state.reports_service.snapshot_period(&state.db, &payload.report_type, payload.start, payload.end).await {
+        Ok(snapshot) => {
+            info!("Report snapshot saved successfully");
+            Json(json!({
+                "success": true,
+                "message": "Report snapshot saved successfully",
+                "data": snapshot
+            }))
+        },
+        Err(err) => {
+            error!("Failed to save report snapshot: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Compare two date ranges (B against A)
+async fn compare_periods(
+    State(state): State<AppState>,
+    Query(query): Query<CompareQuery>,
+) -> impl IntoResponse {
+    match state.reports_service.compare_periods(&state.db, (query.a_start, query.a_end), (query.b_start, query.b_end)).await {
+        Ok(comparison) => {
+            info!("Period comparison fetched successfully");
+            Json(json!({
+                "success": true,
+                "message": "Period comparison fetched successfully",
+                "data": comparison
+            }))
+        },
+        Err(err) => {
+            error!("Failed to compare periods: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Live profit and loss for a date range
+async fn get_profit_and_loss(
+    State(state): State<AppState>,
+    Query(query): Query<ProfitAndLossQuery>,
+) -> impl IntoResponse {
+    match state.reports_service.profit_and_loss(&state.db, query.start_date, query.end_date).await {
+        Ok(report) => {
+            info!("Profit and loss calculated successfully");
+            Json(json!({
+                "success": true,
+                "message": "Profit and loss calculated successfully",
+                "data": report
+            }))
+        },
+        Err(err) => {
+            error!("Failed to calculate profit and loss: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Cash, inventory, receivables and payables as of a date
+async fn get_balance_snapshot(
+    State(state): State<AppState>,
+    Query(query): Query<BalanceSnapshotQuery>,
+) -> impl IntoResponse {
+    let as_of = query.as_of.unwrap_or_else(|| chrono::Local::now().date_naive());
+    match state.reports_service.balance_snapshot(&state.db, as_of).await {
+        Ok(snapshot) => {
+            info!("Balance snapshot calculated successfully");
+            Json(json!({
+                "success": true,
+                "message": "Balance snapshot calculated successfully",
+                "data": snapshot
+            }))
+        },
+        Err(err) => {
+            error!("Failed to calculate balance snapshot: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Best-selling products by quantity (default) or revenue
+async fn get_top_products(
+    State(state): State<AppState>,
+    Query(query): Query<TopProductsQuery>,
+) -> impl IntoResponse {
+    let metric = query.metric.unwrap_or(TopProductsMetric::Quantity);
+    match state.reports_service.top_products(&state.db, query.start_date, query.end_date, metric, query.limit.unwrap_or(10)).await {
+        Ok(products) => {
+            info!("Top products fetched successfully");
+            Json(json!({
+                "success": true,
+                "message": "Top products fetched successfully",
+                "data": products
+            }))
+        },
+        Err(err) => {
+            error!("Failed to fetch top products: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 pub fn reports_routes() -> Router<AppState> {
     Router::new()
         .route("/api/reports/dashboard", get(get_dashboard_summary))
@@ -470,4 +592,9 @@ pub fn reports_routes() -> Router<AppState> {
         .route("/api/reports/money-box", get(get_money_box_report))
         .route("/api/reports/expenses", get(get_expenses_report))
         .route("/api/reports/customer-debts", get(get_customer_debts_detailed_report))
+        .route("/api/reports/snapshot", post(create_report_snapshot))
+        .route("/api/reports/compare", get(compare_periods))
+        .route("/api/reports/profit-and-loss", get(get_profit_and_loss))
+        .route("/api/reports/top-products", get(get_top_products))
+        .route("/api/reports/balance", get(get_balance_snapshot))
 }
\ No newline at end of file
@@ -2,19 +2,43 @@ use axum::{
     routing::{get, post, put, delete},
     Router,
     extract::{State, Path, Query},
-    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    body::Body,
     Json,
 };
 use serde_json::json;
 use crate::AppState;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
 use crate::models::sale::*;
+use crate::services::CreditLimitExceeded;
+use crate::services::validation_service::FieldError;
+use crate::utils::{CsvFormat, CsvFormatQuery, InvalidCursor, ItemLimitExceeded};
 use tracing::{info, warn, error};
 
+// User id behind the bearer token, if any; sales routes stay usable without one
+async fn requesting_user(state: &AppState, headers: &HeaderMap) -> Option<i64> {
+    let token = headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))?;
+    state.auth_service.get_user_from_token(&state.db, token).await.ok().and_then(|user| user.id)
+}
+
+// 400 listing every invalid field at once so the form can mark them all
+fn invalid_sale_response(errors: Vec<FieldError>) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({
+        "success": false,
+        "message": "بيانات الفاتورة غير صالحة",
+        "errors": errors
+    }))).into_response()
+}
+
 // Get all sales
 async fn get_sales(
     State(state): State<AppState>,
     Query(query): Query<SaleQuery>,
-) -> impl IntoResponse {
+) -> Response {
     match state.sale_service.get_all(&state.db, &query).await {
         Ok(sales) => {
             info!("Sales fetched successfully");
@@ -22,14 +46,60 @@ async fn get_sales(
                 "success": true,
                 "message": "Sales fetched successfully",
                 "data": sales
-            }))
+            })).into_response()
         },
         Err(err) => {
             error!("Failed to get sales: {}", err);
+            if let Some(invalid) = err.downcast_ref::<InvalidCursor>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": invalid.to_string()
+                }))).into_response();
+            }
             Json(json!({
                 "success": false,
                 "message": "Failed to get sales"
+            })).into_response()
+        }
+    }
+}
+
+// Export the filtered sales list to CSV, streamed while the rows are read
+async fn export_sales(
+    State(state): State<AppState>,
+    Query(query): Query<SaleQuery>,
+    Query(format_query): Query<CsvFormatQuery>,
+) -> Response {
+    if !matches!(query.format.as_deref(), None | Some("csv")) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "success": false,
+                "message": "صيغة التصدير غير مدعومة"
             }))
+        ).into_response();
+    }
+
+    match CsvFormat::load(&state.db.pool, &format_query).await {
+        Ok(format) => {
+            info!("Streaming sales export");
+            let stream = state.sale_service.export_csv_stream(&state.db, &query, format);
+            (
+                StatusCode::OK,
+                [("Content-Type", "text/csv; charset=utf-8"), ("Content-Disposition", "attachment; filename=\"sales.csv\"")],
+                Body::from_stream(stream)
+            ).into_response()
+        }
+        Err(err) => {
+            error!("Failed to export sales: {}", err);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "success": false,
+                    "message": "حدث خطأ أثناء تصدير البيانات",
+                    "error": err.to_string()
+                }))
+            ).into_response()
         }
     }
 }
@@ -65,6 +135,37 @@ async fn get_sale_by_id(
     }
 }
 
+// Get sale by scanned barcode
+async fn get_sale_by_barcode(
+    State(state): State<AppState>,
+    Path(barcode): Path<String>,
+) -> impl IntoResponse {
+    match state.sale_service.get_by_barcode(&state.db, &barcode).await {
+        Ok(Some(sale)) => {
+            info!("Sale fetched by barcode: {}", barcode);
+            Json(json!({
+                "success": true,
+                "message": "Sale fetched successfully",
+                "data": sale
+            }))
+        },
+        Ok(None) => {
+            warn!("Sale not found for barcode: {}", barcode);
+            Json(json!({
+                "success": false,
+                "message": "لم يتم العثور على فاتورة بهذا الباركود"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to get sale by barcode: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "Failed to get sale"
+            }))
+        }
+    }
+}
+
 // Get customer sales
 async fn get_customer_sales(
     State(state): State<AppState>,
@@ -92,39 +193,21 @@ async fn get_customer_sales(
 // Create new sale
 async fn create_sale(
     State(state): State<AppState>,
-    Json(sale_data): Json<CreateSaleRequest>,
-) -> impl IntoResponse {
-    // Validate required fields
-    if sale_data.invoice_date.is_none() {
-        return Json(json!({
-            "success": false,
-            "message": "Invoice date is required"
-        }));
-    }
-
-    if sale_data.items.is_empty() {
-        return Json(json!({
-            "success": false,
-            "message": "Items are required"
-        }));
+    headers: HeaderMap,
+    Json(mut sale_data): Json<CreateSaleRequest>,
+) -> Response {
+    if let Err(errors) = state.validation_service.validate_create_sale(&sale_data) {
+        return invalid_sale_response(errors);
     }
 
-    // For anonymous sales, customer_id should be 999
-    if !sale_data.is_anonymous.unwrap_or(false) && sale_data.customer_id.is_none() {
-        return Json(json!({
-            "success": false,
-            "message": "Customer ID is required for non-anonymous sales"
-        }));
-    }
+    // Needed for zero-priced lines and discounts above the approval threshold
+    sale_data.requested_by = requesting_user(&state, &headers).await;
 
-    // Validate items
-    for item in &sale_data.items {
-        if item.quantity <= 0 || item.price <= 0.0 {
-            return Json(json!({
-                "success": false,
-                "message": "Invalid item data"
-            }));
-        }
+    // The Idempotency-Key header stands in for the body field
+    if sale_data.idempotency_key.is_none() {
+        sale_data.idempotency_key = headers.get("Idempotency-Key")
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_string);
     }
 
     match state.sale_service.create(&state.db, sale_data).await {
@@ -134,15 +217,45 @@ async fn create_sale(
                 "success": true,
                 "message": "Sale created successfully",
                 "data": sale
-            }))
+            })).into_response()
         },
         Err(err) => {
             error!("Failed to create sale: {}", err);
+            if let Some(exceeded) = err.downcast_ref::<ItemLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string()
+                }))).into_response();
+            }
+            if let Some(exceeded) = err.downcast_ref::<CreditLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string(),
+                    "data": {
+                        "credit_limit": exceeded.credit_limit,
+                        "current_balance": exceeded.current_balance,
+                        "debt_amount": exceeded.debt_amount,
+                        "overage": exceeded.overage()
+                    }
+                }))).into_response();
+            }
             
             // Handle specific duplicate errors
             let error_message = if err.to_string().contains("duplicate") || 
                                  err.to_string().contains("already exists") {
                 "تم إنشاء فاتورة مماثلة مسبقاً، يرجى التحقق من قائمة المبيعات"
+            } else if err.to_string().contains("held by draft sales") {
+                "الكمية المطلوبة غير متاحة لأنها محجوزة لفواتير مسودة أخرى"
+            } else if err.to_string().contains("Stock hold") {
+                "الحجز غير موجود أو انتهت صلاحيته"
+            } else if err.to_string().contains("sales.discount_override") {
+                "خصم الفاتورة يتجاوز الحد المسموح ويتطلب صلاحية تجاوز الخصم"
+            } else if err.to_string().contains("Zero price items are not allowed") {
+                "البيع بسعر صفر غير مفعل في الإعدادات"
+            } else if err.to_string().contains("sales.zero_price") {
+                "المواد بسعر صفر تتطلب صلاحية البيع بسعر صفر"
+            } else if err.to_string().contains("sales.credit_limit_override") {
+                "تجاوز الحد الائتماني يتطلب صلاحية تجاوز الحد الائتماني"
             } else {
                 "Failed to create sale"
             };
@@ -150,7 +263,38 @@ async fn create_sale(
             Json(json!({
                 "success": false,
                 "message": error_message
-            }))
+            })).into_response()
+        }
+    }
+}
+
+// Check a cart against current products, prices and stock before finalizing
+async fn validate_cart(
+    State(state): State<AppState>,
+    Json(cart): Json<CreateSaleRequest>,
+) -> Response {
+    match state.sale_service.validate_cart(&state.db, cart).await {
+        Ok(validation) => {
+            info!("Cart validated: {} issues", validation.issues.len());
+            Json(json!({
+                "success": true,
+                "message": "Cart validated successfully",
+                "data": validation
+            })).into_response()
+        },
+        Err(err) => {
+            error!("Failed to validate cart: {}", err);
+            if let Some(exceeded) = err.downcast_ref::<ItemLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string()
+                }))).into_response();
+            }
+            Json(json!({
+                "success": false,
+                "message": "Failed to validate cart",
+                "error": err.to_string()
+            })).into_response()
         }
     }
 }
@@ -159,27 +303,14 @@ async fn create_sale(
 async fn update_sale(
     State(state): State<AppState>,
     Path(id): Path<i64>,
-    Json(sale_data): Json<UpdateSaleRequest>,
-) -> impl IntoResponse {
-    // Validate required fields
-    if sale_data.customer_id.is_none() || sale_data.invoice_date.is_none() {
-        return Json(json!({
-            "success": false,
-            "message": "Customer ID and invoice date are required"
-        }));
+    headers: HeaderMap,
+    Json(mut sale_data): Json<UpdateSaleRequest>,
+) -> Response {
+    if let Err(errors) = state.validation_service.validate_update_sale(&sale_data) {
+        return invalid_sale_response(errors);
     }
 
-    // Validate items if provided
-    if let Some(ref items) = sale_data.items {
-        for item in items {
-            if item.quantity <= 0 || item.price <= 0.0 {
-                return Json(json!({
-                    "success": false,
-                    "message": "Invalid item data"
-                }));
-            }
-        }
-    }
+    sale_data.requested_by = requesting_user(&state, &headers).await;
 
     match state.sale_service.update(&state.db, id, sale_data).await {
         Ok(sale) => {
@@ -188,14 +319,21 @@ async fn update_sale(
                 "success": true,
                 "message": "Sale updated successfully",
                 "data": sale
-            }))
+            })).into_response()
         },
         Err(err) => {
             error!("Failed to update sale: {}", err);
+            if let Some(exceeded) = err.downcast_ref::<ItemLimitExceeded>() {
+                return (StatusCode::BAD_REQUEST, Json(json!({
+                    "success": false,
+                    "message": exceeded.to_string()
+                }))).into_response();
+            }
             Json(json!({
                 "success": false,
-                "message": "Failed to update sale"
-            }))
+                "message": "Failed to update sale",
+                "error": err.to_string()
+            })).into_response()
         }
     }
 }
@@ -321,14 +459,294 @@ async fn get_product_by_barcode(
     }
 }
 
+// Cancel one sale as a correction; the sale stays listed as cancelled
+async fn void_sale(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<VoidSaleRequest>,
+) -> Response {
+    let user_id = match requesting_user(&state, &headers).await {
+        Some(user_id) => user_id,
+        None => return (StatusCode::UNAUTHORIZED, Json(json!({
+            "success": false,
+            "message": "جلسة غير صالحة، يرجى تسجيل الدخول مجدداً"
+        }))).into_response(),
+    };
+
+    match state.sale_service.void(&state.db, id, &payload.reason, user_id).await {
+        Ok(sale) => Json(json!({
+            "success": true,
+            "message": "تم إلغاء الفاتورة بنجاح",
+            "data": sale
+        })).into_response(),
+        Err(err) => {
+            error!("Failed to void sale {}: {}", id, err);
+            (StatusCode::BAD_REQUEST, Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))).into_response()
+        }
+    }
+}
+
+// Void all sales of a day (or listed ids) entered by mistake; admins only, on top of the sales.delete layer
+async fn bulk_void_sales(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BulkVoidRequest>,
+) -> impl IntoResponse {
+    let token = headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let user = match state.auth_service.get_user_from_token(&state.db, token).await {
+        Ok(user) => user,
+        Err(_) => return (StatusCode::UNAUTHORIZED, Json(json!({
+            "success": false,
+            "message": "جلسة غير صالحة، يرجى تسجيل الدخول مجدداً"
+        }))),
+    };
+    if !user.is_admin() {
+        return (StatusCode::FORBIDDEN, Json(json!({
+            "success": false,
+            "message": "إلغاء الفواتير بالجملة متاح للمدير فقط"
+        })));
+    }
+    let user_id = user.id.unwrap_or_default();
+
+    match state.sale_service.bulk_void(&state.db, payload.filter, &payload.reason, user_id).await {
+        Ok(result) => (StatusCode::OK, Json(json!({
+            "success": true,
+            "message": format!("تم إلغاء {} فاتورة", result.voided),
+            "data": result
+        }))),
+        Err(err) => {
+            error!("Failed to bulk void sales: {}", err);
+            (StatusCode::BAD_REQUEST, Json(json!({
+                "success": false,
+                "message": err.to_string()
+            })))
+        }
+    }
+}
+
 pub fn sales_routes() -> Router<AppState> {
     Router::new()
         .route("/api/sales", get(get_sales))
+        .route("/api/sales/export", get(export_sales))
         .route("/api/sales/:id", get(get_sale_by_id))
         .route("/api/sales/customer/:customer_id", get(get_customer_sales))
+        .route("/api/sales/by-barcode/:barcode", get(get_sale_by_barcode))
         .route("/api/sales", post(create_sale))
+        .route("/api/sales/validate", post(validate_cart))
+        .route("/api/sales/bulk-void", post(bulk_void_sales)
+            .route_layer(from_fn_with_state(RequirePermission("sales.delete"), require_permission)))
         .route("/api/sales/:id", put(update_sale))
-        .route("/api/sales/:id", delete(delete_sale))
+        .route("/api/sales/:id", delete(delete_sale)
+            .route_layer(from_fn_with_state(RequirePermission("sales.delete"), require_permission)))
+        .route("/api/sales/:id/void", post(void_sale)
+            .route_layer(from_fn_with_state(RequirePermission("sales.delete"), require_permission)))
         .route("/api/sales/:id/return", post(process_sale_return))
         .route("/api/sales/pos/product/:barcode", get(get_product_by_barcode))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use crate::test_support::TestApp;
+
+    #[tokio::test]
+    async fn scanning_a_sales_barcode_finds_the_sale() {
+        let app = TestApp::new().await;
+        app.add_user("cashier", "user", &[]).await;
+        let token = app.login("cashier").await;
+
+        let (status, created) = app.request(Method::POST, "/api/sales", Some(&token), Some(json!({
+            "customer_id": 999,
+            "invoice_date": "2026-05-02",
+            "payment_method": "cash",
+            "paid_amount": 3500.0,
+            "items": [{ "name": "كيس خبز", "quantity": 7, "price": 500.0 }]
+        }))).await;
+        assert_eq!(status, StatusCode::OK, "{created}");
+        let sale_id = created["data"]["id"].as_i64().unwrap();
+        let barcode = created["data"]["barcode"].as_str().expect("barcode assigned on creation").to_string();
+
+        let (status, found) = app.request(Method::GET, &format!("/api/sales/by-barcode/{barcode}"), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(found["success"], true);
+        assert_eq!(found["data"]["id"], sale_id);
+
+        let (_, missing) = app.request(Method::GET, "/api/sales/by-barcode/2999999999990", Some(&token), None).await;
+        assert_eq!(missing["success"], false);
+    }
+
+    #[tokio::test]
+    async fn bulk_void_needs_an_admin_and_puts_stock_and_debts_back() {
+        let app = TestApp::new().await;
+        let pool = &app.db.pool;
+        app.add_user("cashier", "user", &[]).await;
+        app.add_user("supervisor", "manager", &["sales.delete"]).await;
+        app.add_user("owner", "admin", &[]).await;
+        let cashier = app.login("cashier").await;
+        let product_id = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock) VALUES ('Tea 500g', 'TEA-500', 3000, 4000, 3500, 1, 20)")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone) VALUES ('Abbas Kadhim', '07809990000')")
+            .execute(pool).await.unwrap().last_insert_rowid();
+
+        let sale = |invoice_date: &'static str, quantity: i64, paid: f64| {
+            let body = json!({
+                "customer_id": customer_id,
+                "invoice_date": invoice_date,
+                "due_date": "2026-06-30",
+                "payment_method": "cash",
+                "paid_amount": paid,
+                "items": [{ "product_id": product_id, "quantity": quantity, "price": 4000.0 }]
+            });
+            let (app, token) = (&app, &cashier);
+            async move {
+                let (status, created) = app.request(Method::POST, "/api/sales", Some(token), Some(body)).await;
+                assert_eq!(status, StatusCode::OK, "{created}");
+                created["data"]["id"].as_i64().unwrap()
+            }
+        };
+        let cash = sale("2026-05-10", 2, 8000.0).await;
+        let credit = sale("2026-05-10", 3, 0.0).await;
+        let next_day = sale("2026-05-11", 1, 4000.0).await;
+        let stock = || sqlx::query_scalar::<_, i64>("SELECT current_stock FROM products WHERE id = ?").bind(product_id).fetch_one(pool);
+        assert_eq!(stock().await.unwrap(), 14);
+
+        let request = json!({ "start_date": "2026-05-10", "end_date": "2026-05-10", "reason": "فواتير تجريبية" });
+        let (status, _) = app.request(Method::POST, "/api/sales/bulk-void", Some(&cashier), Some(request.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Holding sales.delete is not enough without being an admin
+        let supervisor = app.login("supervisor").await;
+        let (status, _) = app.request(Method::POST, "/api/sales/bulk-void", Some(&supervisor), Some(request.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(stock().await.unwrap(), 14);
+
+        let owner = app.login("owner").await;
+        let (status, body) = app.request(Method::POST, "/api/sales/bulk-void", Some(&owner), Some(request)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["data"]["voided"], 2);
+
+        assert_eq!(stock().await.unwrap(), 19);
+        let statuses: Vec<(i64, String)> = sqlx::query_as("SELECT id, status FROM sales ORDER BY id").fetch_all(pool).await.unwrap();
+        assert_eq!(statuses, vec![(cash, "cancelled".to_string()), (credit, "cancelled".to_string()), (next_day, "completed".to_string())]);
+        let open_debts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM debts WHERE sale_id = ?").bind(credit).fetch_one(pool).await.unwrap();
+        assert_eq!(open_debts, 0);
+        let balance: f64 = sqlx::query_scalar("SELECT CAST(COALESCE(current_balance, 0) AS REAL) FROM customers WHERE id = ?")
+            .bind(customer_id).fetch_one(pool).await.unwrap();
+        assert_eq!(balance, 0.0);
+    }
+
+    #[tokio::test]
+    async fn an_oversized_cart_is_refused_with_400_and_nothing_written() {
+        let app = TestApp::new().await;
+        app.add_user("cashier", "user", &[]).await;
+        let token = app.login("cashier").await;
+        let lines = crate::utils::SALE_ITEMS_LIMIT.max() + 1;
+        let cart = json!({
+            "customer_id": 999,
+            "invoice_date": "2026-05-02",
+            "payment_method": "cash",
+            "paid_amount": 250.0 * lines as f64,
+            "items": vec![json!({ "name": "علكة", "quantity": 1, "price": 250.0 }); lines]
+        });
+
+        for uri in ["/api/sales", "/api/sales/validate"] {
+            let (status, body) = app.request(Method::POST, uri, Some(&token), Some(cart.clone())).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}: {body}");
+            assert_eq!(body["success"], false);
+            assert!(body["message"].as_str().unwrap().contains(&lines.to_string()));
+        }
+
+        let sales: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales").fetch_one(&app.db.pool).await.unwrap();
+        assert_eq!(sales, 0);
+    }
+
+    #[tokio::test]
+    async fn credit_sales_past_the_limit_are_refused_unless_an_approver_overrides() {
+        let app = TestApp::new().await;
+        let pool = &app.db.pool;
+        app.add_user("cashier", "user", &[]).await;
+        app.add_user("supervisor", "manager", &["sales.credit_limit_override"]).await;
+        let cashier = app.login("cashier").await;
+        let supervisor = app.login("supervisor").await;
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone, credit_limit) VALUES ('Haider Salim', '07701239999', 10000)")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let credit_sale = |price: f64, override_limit: bool| json!({
+            "customer_id": customer_id,
+            "invoice_date": "2026-10-15",
+            "due_date": "2026-11-15",
+            "payment_method": "cash",
+            "payment_status": "unpaid",
+            "paid_amount": 0.0,
+            "override_credit_limit": override_limit,
+            "items": [{ "name": "كارتون زيت", "quantity": 1, "price": price }]
+        });
+        let debts = || sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM debts WHERE customer_id = ?").bind(customer_id).fetch_one(pool);
+
+        let (status, body) = app.request(Method::POST, "/api/sales", Some(&cashier), Some(credit_sale(8000.0, false))).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(debts().await.unwrap(), 1);
+
+        let (status, refused) = app.request(Method::POST, "/api/sales", Some(&cashier), Some(credit_sale(4000.0, false))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{refused}");
+        assert_eq!(refused["data"]["credit_limit"], 10000.0);
+        assert_eq!(refused["data"]["current_balance"], 8000.0);
+        assert_eq!(refused["data"]["overage"], 2000.0);
+        assert!(refused["message"].as_str().unwrap().contains("Haider Salim"), "{refused}");
+
+        // Asking to override without the permission changes nothing
+        let (_, denied) = app.request(Method::POST, "/api/sales", Some(&cashier), Some(credit_sale(4000.0, true))).await;
+        assert_eq!(denied["success"], false, "{denied}");
+        assert_eq!(debts().await.unwrap(), 1);
+
+        let (status, approved) = app.request(Method::POST, "/api/sales", Some(&supervisor), Some(credit_sale(4000.0, true))).await;
+        assert_eq!(status, StatusCode::OK, "{approved}");
+        assert_eq!(debts().await.unwrap(), 2);
+        let overrides: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_logs WHERE action = 'credit_limit_override' AND entity_id = ?")
+            .bind(approved["data"]["id"].as_i64().unwrap())
+            .fetch_one(pool).await.unwrap();
+        assert_eq!(overrides, 1);
+    }
+
+    #[tokio::test]
+    async fn posting_the_same_idempotency_key_twice_creates_one_sale() {
+        let app = TestApp::new().await;
+        let pool = &app.db.pool;
+        app.add_user("cashier", "user", &[]).await;
+        let token = app.login("cashier").await;
+        let product_id = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock) VALUES ('Dates 1kg', 'DATES-1KG', 2000, 3000, 2500, 1, 10)")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let sale = |key: &str| json!({
+            "customer_id": 999,
+            "invoice_date": "2026-10-15",
+            "payment_method": "cash",
+            "paid_amount": 6000.0,
+            "idempotency_key": key,
+            "items": [{ "product_id": product_id, "quantity": 2, "price": 3000.0 }]
+        });
+
+        let (status, first) = app.request(Method::POST, "/api/sales", Some(&token), Some(sale("pos-1-7f3a"))).await;
+        assert_eq!(status, StatusCode::OK, "{first}");
+        let (status, repeat) = app.request(Method::POST, "/api/sales", Some(&token), Some(sale("pos-1-7f3a"))).await;
+        assert_eq!(status, StatusCode::OK, "{repeat}");
+        assert_eq!(repeat["data"]["id"], first["data"]["id"]);
+
+        let count = || sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sales").fetch_one(pool);
+        let stock = || sqlx::query_scalar::<_, i64>("SELECT current_stock FROM products WHERE id = ?").bind(product_id).fetch_one(pool);
+        assert_eq!(count().await.unwrap(), 1);
+        assert_eq!(stock().await.unwrap(), 8);
+
+        // A new key is a new sale
+        let (status, other) = app.request(Method::POST, "/api/sales", Some(&token), Some(sale("pos-1-91bc"))).await;
+        assert_eq!(status, StatusCode::OK, "{other}");
+        assert_ne!(other["data"]["id"], first["data"]["id"]);
+        assert_eq!(count().await.unwrap(), 2);
+        assert_eq!(stock().await.unwrap(), 6);
+    }
+}
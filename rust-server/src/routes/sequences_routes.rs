@@ -0,0 +1,63 @@
+use axum::{
+    routing::{get, put},
+    Router,
+    extract::{State, Path},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use crate::AppState;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
+use crate::models::sequence::*;
+use tracing::{info, error};
+
+// Get all numbering sequences
+async fn get_sequences(State(state): State<AppState>) -> impl IntoResponse {
+    match state.sequence_service.get_all(&state.db).await {
+        Ok(sequences) => Json(json!({
+            "success": true,
+            "data": sequences,
+            "message": "تم استرجاع تسلسلات الترقيم بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get number sequences: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب تسلسلات الترقيم"
+            }))
+        }
+    }
+}
+
+// Update a numbering sequence's prefix, padding or next value
+async fn update_sequence(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateNumberSequenceRequest>,
+) -> impl IntoResponse {
+    match state.sequence_service.update(&state.db, &name, payload).await {
+        Ok(sequence) => {
+            info!("Number sequence {} updated", name);
+            Json(json!({
+                "success": true,
+                "data": sequence,
+                "message": "تم تحديث تسلسل الترقيم بنجاح"
+            }))
+        }
+        Err(err) => {
+            error!("Failed to update number sequence {}: {}", name, err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+pub fn sequences_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/sequences", get(get_sequences))
+        .route("/api/sequences/:name", put(update_sequence)
+            .route_layer(from_fn_with_state(RequirePermission("settings.manage"), require_permission)))
+}
@@ -0,0 +1,116 @@
+use axum::{
+    routing::{get, post},
+    Router,
+    extract::{State, Path},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use crate::AppState;
+use crate::models::stock_hold::*;
+use tracing::{info, error};
+
+// Place a hold for a draft sale
+async fn create_stock_hold(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateStockHoldRequest>,
+) -> impl IntoResponse {
+    match state.stock_holds_service.create(&state.db, payload).await {
+        Ok(holds) => {
+            info!("Stock hold created with {} items", holds.len());
+            Json(json!({
+                "success": true,
+                "data": {
+                    "reference": holds.first().map(|h| h.reference.clone()),
+                    "holds": holds
+                },
+                "message": "تم حجز الكميات بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to create stock hold: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Get holds placed under a draft reference
+async fn get_stock_hold(
+    State(state): State<AppState>,
+    Path(reference): Path<String>,
+) -> impl IntoResponse {
+    match state.stock_holds_service.get_by_reference(&state.db, &reference).await {
+        Ok(holds) if holds.is_empty() => Json(json!({
+            "success": false,
+            "message": "الحجز غير موجود"
+        })),
+        Ok(holds) => Json(json!({
+            "success": true,
+            "data": holds,
+            "message": "تم استرجاع الحجز بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get stock hold {}: {}", reference, err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب الحجز"
+            }))
+        }
+    }
+}
+
+// Cancel a draft and release its held quantities
+async fn release_stock_hold(
+    State(state): State<AppState>,
+    Path(reference): Path<String>,
+) -> impl IntoResponse {
+    match state.stock_holds_service.release(&state.db, &reference).await {
+        Ok(released) => {
+            info!("Released {} holds for {}", released, reference);
+            Json(json!({
+                "success": true,
+                "data": { "released": released },
+                "message": "تم إلغاء الحجز بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to release stock hold {}: {}", reference, err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء إلغاء الحجز"
+            }))
+        }
+    }
+}
+
+// Physical stock, held quantity and what is left to sell
+async fn get_product_availability(
+    State(state): State<AppState>,
+    Path(product_id): Path<i64>,
+) -> impl IntoResponse {
+    match state.stock_holds_service.availability(&state.db, product_id).await {
+        Ok(availability) => Json(json!({
+            "success": true,
+            "data": availability,
+            "message": "تم استرجاع الكمية المتاحة بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get availability for product {}: {}", product_id, err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+pub fn stock_holds_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/stock-holds", post(create_stock_hold))
+        .route("/api/stock-holds/:reference", get(get_stock_hold))
+        .route("/api/stock-holds/:reference/release", post(release_stock_hold))
+        .route("/api/stock-holds/availability/:product_id", get(get_product_availability))
+}
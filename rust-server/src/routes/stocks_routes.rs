@@ -2,6 +2,7 @@ use axum::{
     routing::{get, post, put, delete},
     Router,
     extract::{State, Path, Query},
+    http::HeaderMap,
     response::IntoResponse,
     Json,
 };
@@ -9,8 +10,18 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::AppState;
 use crate::models::stock::*;
+use crate::middleware::permission_middleware::{RequirePermission, require_permission};
+use axum::middleware::from_fn_with_state;
 use tracing::{info, warn, error};
 
+// User id behind the bearer token, if any, to attribute movements
+async fn requesting_user(state: &AppState, headers: &HeaderMap) -> Option<i64> {
+    let token = headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))?;
+    state.auth_service.get_user_from_token(&state.db, token).await.ok().and_then(|user| user.id)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddProductToStockRequestFrontend {
     #[serde(deserialize_with = "deserialize_string_to_i64")]
@@ -314,6 +325,30 @@ async fn add_product_to_stock(
     }
 }
 
+// Correct a product's quantity in a stock to the counted amount, with a reason
+async fn adjust_stock(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<AdjustStockRequest>,
+) -> impl IntoResponse {
+    let created_by = requesting_user(&state, &headers).await;
+    match state.stock_service.adjust(&state.db, id, payload, created_by).await {
+        Ok(adjustment) => Json(json!({
+            "success": true,
+            "data": adjustment,
+            "message": "تم تعديل كمية المنتج بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to adjust stock: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 pub fn stocks_routes() -> Router<AppState> {
     Router::new()
         .route("/api/stocks", get(get_stocks))
@@ -325,4 +360,6 @@ pub fn stocks_routes() -> Router<AppState> {
         .route("/api/stocks/:id/movements", get(get_stock_movements))
         .route("/api/stocks/:id/stats", get(get_stock_stats))
         .route("/api/stocks/:id/products", post(add_product_to_stock))
+        .route("/api/stocks/:id/adjust", post(adjust_stock)
+            .route_layer(from_fn_with_state(RequirePermission("inventory.edit"), require_permission)))
 }
\ No newline at end of file
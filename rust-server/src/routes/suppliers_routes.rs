@@ -2,12 +2,14 @@ use axum::{
     routing::{get, post, put, delete},
     Router,
     extract::{State, Path, Query},
+    http::StatusCode,
     response::IntoResponse,
     Json,
 };
 use serde_json::json;
 use crate::AppState;
 use crate::models::supplier::*;
+use crate::utils::{CsvFormat, CsvFormatQuery};
 use tracing::{info, warn, error};
 
 // Get all suppliers
@@ -245,6 +247,100 @@ async fn delete_supplier(
     }
 }
 
+// Export supplier statement as CSV using the configured separators and date format
+async fn export_supplier_statement(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<SupplierStatementQuery>,
+    Query(format_query): Query<CsvFormatQuery>,
+) -> impl IntoResponse {
+    let result = match CsvFormat::load(&state.db.pool, &format_query).await {
+        Ok(format) => state.supplier_service.export_statement_csv(&state.db, id, query.start_date, query.end_date, &format).await,
+        Err(err) => Err(err),
+    };
+    let (status, message) = match result {
+        Ok(Some(csv_content)) => {
+            info!("Supplier statement exported successfully");
+            return (
+                StatusCode::OK,
+                [("Content-Type", "text/csv; charset=utf-8"), ("Content-Disposition", "attachment; filename=\"supplier_statement.csv\"")],
+                csv_content
+            );
+        },
+        Ok(None) => (StatusCode::NOT_FOUND, "المورد غير موجود".to_string()),
+        Err(err) => {
+            error!("Failed to export supplier statement: {}", err);
+            (StatusCode::BAD_REQUEST, err.to_string())
+        }
+    };
+    (
+        status,
+        [("Content-Type", "application/json"), ("Content-Disposition", "inline")],
+        serde_json::to_string(&json!({
+            "success": false,
+            "message": message
+        })).unwrap()
+    )
+}
+
+// Get supplier statement (purchases, payments and running balance)
+async fn get_supplier_statement(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<SupplierStatementQuery>,
+) -> impl IntoResponse {
+    match state.supplier_service.get_statement(&state.db, id, query.start_date, query.end_date).await {
+        Ok(Some(statement)) => {
+            info!("Supplier statement retrieved successfully");
+            Json(json!({
+                "success": true,
+                "data": statement,
+                "message": "Supplier statement retrieved successfully"
+            }))
+        },
+        Ok(None) => {
+            warn!("Supplier not found: {}", id);
+            Json(json!({
+                "success": false,
+                "message": "المورد غير موجود"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to get supplier statement: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب كشف حساب المورد",
+                "error": err.to_string()
+            }))
+        }
+    }
+}
+
+// Link many products to a supplier
+async fn bulk_link_supplier_products(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<BulkLinkProductsRequest>,
+) -> impl IntoResponse {
+    match state.supplier_service.bulk_link_products(&state.db, id, payload.product_ids, payload.default_price, payload.lead_time).await {
+        Ok(result) => {
+            info!("Products linked to supplier successfully");
+            Json(json!({
+                "success": true,
+                "data": result,
+                "message": "تم ربط المنتجات بالمورد بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to link products to supplier: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
 pub fn suppliers_routes() -> Router<AppState> {
     Router::new()
         .route("/api/suppliers", get(get_suppliers))
@@ -252,6 +348,9 @@ pub fn suppliers_routes() -> Router<AppState> {
         .route("/api/suppliers", post(create_supplier))
         .route("/api/suppliers/:id", get(get_supplier_by_id))
         .route("/api/suppliers/:id/products", get(get_supplier_with_products))
+        .route("/api/suppliers/:id/statement", get(get_supplier_statement))
+        .route("/api/suppliers/:id/statement/export", get(export_supplier_statement))
+        .route("/api/suppliers/:id/products/bulk", post(bulk_link_supplier_products))
         .route("/api/suppliers/:id", put(update_supplier))
         .route("/api/suppliers/:id", delete(delete_supplier))
 }
\ No newline at end of file
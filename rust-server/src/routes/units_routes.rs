@@ -0,0 +1,154 @@
+use axum::{
+    routing::get,
+    Router,
+    extract::{State, Path, Query},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use crate::AppState;
+use crate::models::unit::*;
+use tracing::{info, error};
+
+// Get all units
+async fn get_units(State(state): State<AppState>) -> impl IntoResponse {
+    match state.units_service.get_all(&state.db).await {
+        Ok(units) => Json(json!({
+            "success": true,
+            "data": units,
+            "message": "تم استرجاع الوحدات بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to get units: {}", err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب الوحدات"
+            }))
+        }
+    }
+}
+
+// Get unit by ID
+async fn get_unit(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.units_service.get_by_id(&state.db, id).await {
+        Ok(Some(unit)) => Json(json!({
+            "success": true,
+            "data": unit,
+            "message": "تم استرجاع الوحدة بنجاح"
+        })),
+        Ok(None) => Json(json!({
+            "success": false,
+            "message": "الوحدة غير موجودة"
+        })),
+        Err(err) => {
+            error!("Failed to get unit {}: {}", id, err);
+            Json(json!({
+                "success": false,
+                "message": "حدث خطأ أثناء جلب الوحدة"
+            }))
+        }
+    }
+}
+
+// Create unit
+async fn create_unit(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateUnitRequest>,
+) -> impl IntoResponse {
+    match state.units_service.create(&state.db, payload).await {
+        Ok(unit) => {
+            info!("Unit created successfully: {}", unit.id);
+            Json(json!({
+                "success": true,
+                "data": unit,
+                "message": "تم إنشاء الوحدة بنجاح"
+            }))
+        },
+        Err(err) => {
+            error!("Failed to create unit: {}", err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Update unit
+async fn update_unit(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateUnitRequest>,
+) -> impl IntoResponse {
+    match state.units_service.update(&state.db, id, payload).await {
+        Ok(unit) => Json(json!({
+            "success": true,
+            "data": unit,
+            "message": "تم تحديث الوحدة بنجاح"
+        })),
+        Err(err) => {
+            error!("Failed to update unit {}: {}", id, err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Delete unit
+async fn delete_unit(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.units_service.delete(&state.db, id).await {
+        Ok(true) => Json(json!({
+            "success": true,
+            "message": "تم حذف الوحدة بنجاح"
+        })),
+        Ok(false) => Json(json!({
+            "success": false,
+            "message": "الوحدة غير موجودة"
+        })),
+        Err(err) => {
+            error!("Failed to delete unit {}: {}", id, err);
+            Json(json!({
+                "success": false,
+                "message": err.to_string()
+            }))
+        }
+    }
+}
+
+// Convert a quantity between related units
+async fn convert_units(
+    State(state): State<AppState>,
+    Query(query): Query<UnitConversionQuery>,
+) -> impl IntoResponse {
+    match state.units_service.convert(&state.db, &query.from, &query.to, query.quantity).await {
+        Ok(result) => Json(json!({
+            "success": true,
+            "data": {
+                "from": query.from,
+                "to": query.to,
+                "quantity": query.quantity,
+                "result": result
+            },
+            "message": "تم التحويل بنجاح"
+        })),
+        Err(err) => Json(json!({
+            "success": false,
+            "message": err.to_string()
+        })),
+    }
+}
+
+pub fn units_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/units", get(get_units).post(create_unit))
+        .route("/api/units/convert", get(convert_units))
+        .route("/api/units/:id", get(get_unit).put(update_unit).delete(delete_unit))
+}
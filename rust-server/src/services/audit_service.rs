@@ -0,0 +1,25 @@
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::SqliteConnection;
+
+// Append-only record of sensitive actions (approvals, overrides) and who performed them
+pub struct AuditService;
+
+impl AuditService {
+    // Runs on the caller's connection so the entry commits or rolls back with the action itself
+    pub async fn record(conn: &mut SqliteConnection, user_id: Option<i64>, action: &str, entity_type: &str, entity_id: Option<i64>, details: Value) -> Result<()> {
+        sqlx::query(r#"
+            INSERT INTO audit_logs (user_id, action, entity_type, entity_id, details, created_at)
+            VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+        "#)
+        .bind(user_id)
+        .bind(action)
+        .bind(entity_type)
+        .bind(entity_id)
+        .bind(details.to_string())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
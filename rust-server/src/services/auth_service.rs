@@ -8,15 +8,22 @@ use std::collections::HashMap;
 use tracing::{info, warn};
 use anyhow::Result;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     sub: String, // user_id
     username: String,
     role: String,
     exp: usize, // expiration time
     iat: usize, // issued at
+    #[serde(default)]
+    ver: i64, // users.token_version at issue time
 }
 
+// Fallback token lifetime when settings.session_timeout is missing or invalid
+const DEFAULT_SESSION_MINUTES: i64 = 24 * 60;
+// How long after expiry a token may still be exchanged through refresh_token
+const REFRESH_GRACE_SECONDS: u64 = 15 * 60;
+
 impl Claims {
     pub fn get_user_id(&self) -> Result<i64> {
         self.sub.parse::<i64>().map_err(|e| anyhow::anyhow!("Invalid user ID: {}", e))
@@ -84,7 +91,7 @@ impl AuthService {
         // Get the created user
         let user = self.get_user_by_id(db, user_id).await?
             .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created user"))?;
-        let token = self.generate_token(&user)?;
+        let token = self.generate_token(db, &user).await?;
 
         Ok(ApiResponse::success(AuthResponse { token, user }))
     }
@@ -121,8 +128,18 @@ impl AuthService {
         // Update last login
         self.update_last_login(db, user.id.unwrap_or(0)).await?;
 
+        // Single-session mode: bump the token version so earlier tokens stop validating
+        let (_, allow_multiple_sessions) = self.session_settings(db).await?;
+        if !allow_multiple_sessions {
+            sqlx::query("UPDATE users SET token_version = token_version + 1 WHERE id = ?")
+                .bind(user.id.unwrap_or(0))
+                .execute(&db.pool)
+                .await?;
+            info!("Previous sessions invalidated for user {}", user.username);
+        }
+
         // Generate token
-        let token = self.generate_token(&user)?;
+        let token = self.generate_token(db, &user).await?;
 
         Ok(ApiResponse::success(AuthResponse { token, user }))
     }
@@ -130,6 +147,10 @@ impl AuthService {
     pub async fn get_user_from_token(&self, db: &Database, token: &str) -> Result<User> {
         let claims = self.verify_token(token)?;
         let user_id: i64 = claims.sub.parse()?;
+
+        if claims.ver != self.token_version(db, user_id).await? {
+            return Err(anyhow::anyhow!("Session has been replaced by a newer login"));
+        }
         
         match self.get_user_by_id(db, user_id).await? {
             Some(user) => Ok(user),
@@ -137,6 +158,59 @@ impl AuthService {
         }
     }
 
+    // Exchange a valid (or recently expired, within the grace window) token for a fresh one
+    pub async fn refresh_token(&self, db: &Database, token: &str) -> Result<AuthResponse> {
+        let mut validation = Validation::default();
+        validation.leeway = REFRESH_GRACE_SECONDS;
+        let claims = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.jwt_secret.as_ref()),
+            &validation,
+        )?.claims;
+
+        let user_id = claims.get_user_id()?;
+        if claims.ver != self.token_version(db, user_id).await? {
+            return Err(anyhow::anyhow!("Session has been replaced by a newer login"));
+        }
+
+        let user = self.get_user_by_id(db, user_id).await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
+        if !user.is_active() {
+            return Err(anyhow::anyhow!("Account is deactivated"));
+        }
+
+        let token = self.generate_token(db, &user).await?;
+        Ok(AuthResponse { token, user })
+    }
+
+    // (session lifetime in minutes, allow_multiple_sessions) from the settings row
+    async fn session_settings(&self, db: &Database) -> Result<(i64, bool)> {
+        let row = sqlx::query("SELECT session_timeout, allow_multiple_sessions FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?;
+
+        Ok(match row {
+            Some(row) => (
+                row.get::<Option<i64>, _>("session_timeout")
+                    .filter(|minutes| *minutes > 0)
+                    .unwrap_or(DEFAULT_SESSION_MINUTES),
+                row.get::<Option<i64>, _>("allow_multiple_sessions").unwrap_or(1) == 1,
+            ),
+            None => (DEFAULT_SESSION_MINUTES, true),
+        })
+    }
+
+    async fn token_version(&self, db: &Database, user_id: i64) -> Result<i64> {
+        let version = sqlx::query("SELECT token_version FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get::<i64, _>("token_version"))
+            .unwrap_or(0);
+
+        Ok(version)
+    }
+
     pub fn verify_token(&self, token: &str) -> Result<Claims> {
         let token_data = decode::<Claims>(
             token,
@@ -234,9 +308,10 @@ impl AuthService {
         Ok(user)
     }
 
-    fn generate_token(&self, user: &User) -> Result<String> {
+    async fn generate_token(&self, db: &Database, user: &User) -> Result<String> {
+        let (session_minutes, _) = self.session_settings(db).await?;
         let now = chrono::Utc::now();
-        let exp = (now + chrono::Duration::hours(24)).timestamp() as usize;
+        let exp = (now + chrono::Duration::minutes(session_minutes)).timestamp() as usize;
         let iat = now.timestamp() as usize;
 
         let claims = Claims {
@@ -245,6 +320,7 @@ impl AuthService {
             role: user.role.clone().unwrap_or_else(|| "user".to_string()),
             exp,
             iat,
+            ver: self.token_version(db, user.id.unwrap_or(0)).await?,
         };
 
         let token = encode(
@@ -411,3 +487,80 @@ impl AuthService {
         Ok(result.rows_affected() > 0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    async fn add_cashier(db: &Database) -> i64 {
+        sqlx::query("INSERT INTO users (username, password, name, role) VALUES ('noor', ?, 'Noor Jasim', 'user')")
+            .bind(hash("counter-2", 4).unwrap())
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    async fn login(service: &AuthService, db: &Database) -> String {
+        let response = service.login(db, LoginRequest { username: "noor".into(), password: "counter-2".into() }).await.unwrap();
+        response.data.expect("login succeeds").token
+    }
+
+    // Token for the user that expired `seconds_ago`
+    fn expired_token(service: &AuthService, user_id: i64, seconds_ago: i64) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            username: "noor".into(),
+            role: "user".into(),
+            exp: (now - seconds_ago) as usize,
+            iat: (now - seconds_ago - 1800) as usize,
+            ver: 0,
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(service.jwt_secret.as_ref())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn token_lifetime_follows_session_timeout() {
+        let db = TestDatabase::new().await;
+        let service = AuthService::new();
+        add_cashier(&db).await;
+
+        sqlx::query("UPDATE settings SET session_timeout = 45 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let claims = service.verify_token(&login(&service, &db).await).unwrap();
+        assert_eq!(claims.exp - claims.iat, 45 * 60);
+
+        // Unset or nonsensical values keep the long-standing 24 hour tokens
+        sqlx::query("UPDATE settings SET session_timeout = 0 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let claims = service.verify_token(&login(&service, &db).await).unwrap();
+        assert_eq!(claims.exp - claims.iat, 24 * 3600);
+    }
+
+    #[tokio::test]
+    async fn recently_expired_tokens_can_be_refreshed_within_the_grace_window() {
+        let db = TestDatabase::new().await;
+        let service = AuthService::new();
+        let user_id = add_cashier(&db).await;
+
+        let stale = expired_token(&service, user_id, 5 * 60);
+        assert!(service.get_user_from_token(&db, &stale).await.is_err());
+        let refreshed = service.refresh_token(&db, &stale).await.unwrap();
+        assert_eq!(service.get_user_from_token(&db, &refreshed.token).await.unwrap().id, Some(user_id));
+
+        let too_old = expired_token(&service, user_id, 20 * 60);
+        assert!(service.refresh_token(&db, &too_old).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn single_session_mode_retires_earlier_tokens() {
+        let db = TestDatabase::new().await;
+        let service = AuthService::new();
+        add_cashier(&db).await;
+        sqlx::query("UPDATE settings SET allow_multiple_sessions = 0 WHERE id = 1").execute(&db.pool).await.unwrap();
+
+        let first = login(&service, &db).await;
+        let second = login(&service, &db).await;
+        assert!(service.get_user_from_token(&db, &first).await.is_err());
+        assert!(service.refresh_token(&db, &first).await.is_err());
+        assert!(service.get_user_from_token(&db, &second).await.is_ok());
+    }
+}
@@ -1,10 +1,149 @@
 use anyhow::Result;
+use barcoders::sym::code128::Code128;
+use barcoders::sym::ean13::EAN13;
 
 #[derive(Clone)]
 pub struct BarcodeService;
 
 impl BarcodeService {
+    // "21" keeps product codes apart from sale barcodes, which start with "20" (see SaleService::sale_barcode)
+    const PRODUCT_PREFIX: &'static str = "21";
+
+    // Blank modules on each side so scanners can find the first bar
+    const QUIET_ZONE: usize = 10;
+    const MAX_CODE128_LEN: usize = 48;
+
     pub fn new() -> Self {
         Self
     }
+
+    // EAN-13 for a product: in-store prefix + 10-digit zero-padded id + check digit
+    pub fn generate(&self, product_id: i64) -> String {
+        let body = format!("{}{:010}", Self::PRODUCT_PREFIX, product_id.rem_euclid(10_000_000_000));
+        format!("{}{}", body, Self::check_digit(&body))
+    }
+
+    // Thirteen digits whose last one matches the EAN-13 check digit of the first twelve
+    pub fn validate(&self, code: &str) -> bool {
+        if code.len() != 13 || !code.chars().all(|c| c.is_ascii_digit()) {
+            return false;
+        }
+        Self::check_digit(&code[..12]).to_string() == code[12..]
+    }
+
+    // Grayscale PNG of the code: EAN-13 for valid 13-digit codes, Code128 (set B) for other printable text.
+    // The image is at least one pixel per module wide; bars are scaled to fill and centered in `width`.
+    pub fn render_png(&self, code: &str, width: u32, height: u32) -> Result<Vec<u8>> {
+        let (width, height, pixels) = self.render_gray(code, width, height)?;
+        Self::encode_png(width, height, &pixels)
+    }
+
+    // Same bars as render_png as raw 8-bit grayscale rows (0 = bar), for printers that take bitmaps
+    pub fn render_gray(&self, code: &str, width: u32, height: u32) -> Result<(usize, usize, Vec<u8>)> {
+        let modules = self.encode(code)?;
+
+        let total = modules.len() + Self::QUIET_ZONE * 2;
+        let width = (width as usize).clamp(total, 4000);
+        let height = height.clamp(10, 1000) as usize;
+        let scale = width / total;
+        let left = (width - total * scale) / 2 + Self::QUIET_ZONE * scale;
+
+        let mut row = vec![255u8; width];
+        for (i, module) in modules.iter().enumerate() {
+            if *module == 1 {
+                let start = left + i * scale;
+                row[start..start + scale].fill(0);
+            }
+        }
+        Ok((width, height, row.repeat(height)))
+    }
+
+    // Grayscale PNG of a QR code; `scale` pixels per module with the standard 4-module quiet zone
+    pub fn render_qr_png(&self, data: &str, scale: u32) -> Result<Vec<u8>> {
+        let qr = qrcode::QrCode::with_error_correction_level(data.as_bytes(), qrcode::EcLevel::M)
+            .map_err(|e| anyhow::anyhow!("تعذر إنشاء رمز QR: {}", e))?;
+        let modules = qr.to_colors();
+        let qr_width = qr.width();
+
+        let scale = scale.clamp(1, 20) as usize;
+        let size = (qr_width + 8) * scale;
+        let mut pixels = vec![255u8; size * size];
+        for (i, color) in modules.iter().enumerate() {
+            if *color == qrcode::Color::Dark {
+                let (x, y) = ((i % qr_width + 4) * scale, (i / qr_width + 4) * scale);
+                for row in y..y + scale {
+                    pixels[row * size + x..row * size + x + scale].fill(0);
+                }
+            }
+        }
+
+        Self::encode_png(size, size, &pixels)
+    }
+
+    fn encode_png(width: usize, height: usize, pixels: &[u8]) -> Result<Vec<u8>> {
+        let mut png_bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_bytes, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(pixels)?;
+
+        Ok(png_bytes)
+    }
+
+    // Bar pattern (1 = bar, 0 = space) for the symbology the code fits
+    fn encode(&self, code: &str) -> Result<Vec<u8>> {
+        if code.len() == 13 && code.chars().all(|c| c.is_ascii_digit()) {
+            if !self.validate(code) {
+                return Err(anyhow::anyhow!("رقم التحقق في الباركود غير صحيح"));
+            }
+            return EAN13::new(code)
+                .map(|ean| ean.encode())
+                .map_err(|e| anyhow::anyhow!("باركود غير صالح: {}", e));
+        }
+
+        if code.is_empty() || code.len() > Self::MAX_CODE128_LEN || !code.chars().all(|c| (' '..='~').contains(&c)) {
+            return Err(anyhow::anyhow!("الباركود يجب أن يكون نصاً من 1 إلى {} حرفاً لاتينياً", Self::MAX_CODE128_LEN));
+        }
+        // 'Ɓ' selects character set B, which covers all printable ASCII
+        Code128::new(format!("Ɓ{}", code))
+            .map(|code128| code128.encode())
+            .map_err(|e| anyhow::anyhow!("باركود غير صالح: {}", e))
+    }
+
+    // Weights alternate 1 and 3 from the left of the 12-digit body
+    pub fn check_digit(body: &str) -> u32 {
+        let sum: u32 = body.chars()
+            .filter_map(|c| c.to_digit(10))
+            .enumerate()
+            .map(|(i, d)| if i % 2 == 0 { d } else { d * 3 })
+            .sum();
+        (10 - sum % 10) % 10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digit_matches_published_ean13_codes() {
+        assert_eq!(BarcodeService::check_digit("400638133393"), 1);
+        assert_eq!(BarcodeService::check_digit("590123412345"), 7);
+        assert_eq!(BarcodeService::check_digit("978020137962"), 4);
+
+        let barcodes = BarcodeService::new();
+        assert!(barcodes.validate("4006381333931"));
+        assert!(!barcodes.validate("4006381333932"));
+        assert!(!barcodes.validate("400638133393"));
+        assert!(!barcodes.validate("40063813339A1"));
+    }
+
+    #[test]
+    fn generated_codes_are_valid_and_carry_the_product_id() {
+        let barcodes = BarcodeService::new();
+        let code = barcodes.generate(4521);
+        assert_eq!(&code[..12], "210000004521");
+        assert!(barcodes.validate(&code));
+        assert_ne!(code, barcodes.generate(4522));
+    }
 }
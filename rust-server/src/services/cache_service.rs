@@ -1,8 +1,11 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::RwLock;
 use std::time::{Duration, Instant};
 use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
 
 #[derive(Debug, Clone)]
 pub struct CacheEntry<T> {
@@ -10,106 +13,226 @@ pub struct CacheEntry<T> {
     pub expires_at: Option<Instant>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct CacheStats {
     pub hits: u64,
     pub misses: u64,
     pub sets: u64,
     pub deletes: u64,
+    pub entries: usize,
+    pub memory_bytes: usize, // keys, values and per-entry bookkeeping; allocator overhead not included
+    pub prefixes: Vec<PrefixStats>,
+}
+
+// Keys are grouped by the part before the first ':' ("settings:all" → "settings")
+#[derive(Debug, Serialize)]
+pub struct PrefixStats {
+    pub prefix: String,
+    pub entries: usize,
+    pub memory_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+}
+
+// Counters are atomics so reads and stats never wait on the cache lock for bookkeeping
+#[derive(Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    sets: AtomicU64,
+    deletes: AtomicU64,
+    // (hits, misses) per key prefix; the lock is held only to bump a pair
+    by_prefix: std::sync::Mutex<HashMap<String, (u64, u64)>>,
+}
+
+impl CacheCounters {
+    fn record(&self, key: &str, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let mut by_prefix = self.by_prefix.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let pair = match by_prefix.get_mut(key_prefix(key)) {
+            Some(pair) => pair,
+            None => by_prefix.entry(key_prefix(key).to_string()).or_default(),
+        };
+        if hit { pair.0 += 1 } else { pair.1 += 1 }
+    }
+}
+
+fn key_prefix(key: &str) -> &str {
+    key.split(':').next().unwrap_or(key)
+}
+
+fn entry_size(key: &String, entry: &CacheEntry<String>) -> usize {
+    key.capacity() + entry.data.capacity() + std::mem::size_of::<(String, CacheEntry<String>)>()
+}
+
+fn hit_rate(hits: u64, misses: u64) -> f64 {
+    if hits + misses > 0 { hits as f64 / (hits + misses) as f64 } else { 0.0 }
 }
 
 #[derive(Clone)]
 pub struct CacheService {
     cache: Arc<RwLock<HashMap<String, CacheEntry<String>>>>,
-    stats: Arc<RwLock<CacheStats>>,
+    counters: Arc<CacheCounters>,
 }
 
 impl CacheService {
     pub fn new() -> Self {
         Self {
             cache: Arc::new(RwLock::new(HashMap::new())),
-            stats: Arc::new(RwLock::new(CacheStats {
-                hits: 0,
-                misses: 0,
-                sets: 0,
-                deletes: 0,
-            })),
+            counters: Arc::new(CacheCounters::default()),
         }
     }
 
     pub async fn get(&self, key: &str) -> Option<String> {
-        let mut cache = self.cache.write().await;
-        let mut stats = self.stats.write().await;
-
-        if let Some(entry) = cache.get(key) {
-            // Check if entry has expired
-            if let Some(expires_at) = entry.expires_at {
-                if Instant::now() > expires_at {
-                    cache.remove(key);
-                    stats.misses += 1;
+        {
+            let cache = self.cache.read().await;
+            match cache.get(key) {
+                Some(entry) if entry.expires_at.is_none_or(|expires_at| Instant::now() <= expires_at) => {
+                    self.counters.record(key, true);
+                    return Some(entry.data.clone());
+                }
+                Some(_) => {}
+                None => {
+                    self.counters.record(key, false);
                     return None;
                 }
             }
-            stats.hits += 1;
-            Some(entry.data.clone())
-        } else {
-            stats.misses += 1;
-            None
         }
+
+        // Expired: drop it, unless another writer refreshed it in the meantime
+        let mut cache = self.cache.write().await;
+        if cache.get(key).and_then(|entry| entry.expires_at).is_some_and(|expires_at| Instant::now() > expires_at) {
+            cache.remove(key);
+        }
+        self.counters.record(key, false);
+        None
     }
 
     pub async fn set(&self, key: String, value: String, ttl: Option<Duration>) -> Result<()> {
         let mut cache = self.cache.write().await;
-        let mut stats = self.stats.write().await;
 
-        let expires_at = ttl.map(|duration| Instant::now() + duration);
+        // Expired entries are otherwise only dropped when read; sweep them on every write
+        let now = Instant::now();
+        cache.retain(|_, entry| entry.expires_at.is_none_or(|expires_at| expires_at > now));
+
+        let expires_at = ttl.map(|duration| now + duration);
         
         cache.insert(key, CacheEntry {
             data: value,
             expires_at,
         });
         
-        stats.sets += 1;
+        self.counters.sets.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
 
     pub async fn delete(&self, key: &str) -> Result<()> {
         let mut cache = self.cache.write().await;
-        let mut stats = self.stats.write().await;
 
         if cache.remove(key).is_some() {
-            stats.deletes += 1;
+            self.counters.deletes.fetch_add(1, Ordering::Relaxed);
         }
         
         Ok(())
     }
 
+    // Cached value for `key`, or the result of `compute` kept for `ttl`. Values are stored as JSON so any
+    // serializable type can share the store; a failed `compute` is returned and not cached.
+    pub async fn get_or_compute<T, F>(&self, key: &str, ttl: Duration, compute: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T>>,
+    {
+        if let Some(cached) = self.get(key).await {
+            if let Ok(value) = serde_json::from_str(&cached) {
+                return Ok(value);
+            }
+        }
+
+        let value = compute.await?;
+        self.set(key.to_string(), serde_json::to_string(&value)?, Some(ttl)).await?;
+        Ok(value)
+    }
+
+    // Drop every key starting with `prefix`, e.g. "settings" after a settings write; returns how many went
+    pub async fn invalidate(&self, prefix: &str) -> usize {
+        let mut cache = self.cache.write().await;
+
+        let before = cache.len();
+        cache.retain(|key, _| !key.starts_with(prefix));
+        let removed = before - cache.len();
+        self.counters.deletes.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
     pub async fn clear(&self) -> Result<()> {
         let mut cache = self.cache.write().await;
         cache.clear();
         Ok(())
     }
 
-    pub async fn get_stats(&self) -> CacheStats {
-        let stats = self.stats.read().await;
+    pub async fn stats(&self) -> CacheStats {
+        let cache = self.cache.read().await;
+
+        // Prefixes seen by lookups or present in the store, whichever way they showed up
+        let mut prefixes: HashMap<String, PrefixStats> = HashMap::new();
+        let by_prefix = self.counters.by_prefix.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (prefix, &(hits, misses)) in by_prefix.iter() {
+            prefixes.insert(prefix.clone(), PrefixStats {
+                prefix: prefix.clone(),
+                entries: 0,
+                memory_bytes: 0,
+                hits,
+                misses,
+                hit_rate: hit_rate(hits, misses),
+            });
+        }
+        drop(by_prefix);
+
+        let mut memory_bytes = 0;
+        for (key, entry) in cache.iter() {
+            let size = entry_size(key, entry);
+            memory_bytes += size;
+            let prefix = key_prefix(key);
+            let stats = prefixes.entry(prefix.to_string()).or_insert_with(|| PrefixStats {
+                prefix: prefix.to_string(),
+                entries: 0,
+                memory_bytes: 0,
+                hits: 0,
+                misses: 0,
+                hit_rate: 0.0,
+            });
+            stats.entries += 1;
+            stats.memory_bytes += size;
+        }
+        let mut prefixes: Vec<PrefixStats> = prefixes.into_values().collect();
+        prefixes.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+
         CacheStats {
-            hits: stats.hits,
-            misses: stats.misses,
-            sets: stats.sets,
-            deletes: stats.deletes,
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            sets: self.counters.sets.load(Ordering::Relaxed),
+            deletes: self.counters.deletes.load(Ordering::Relaxed),
+            entries: cache.len(),
+            memory_bytes,
+            prefixes,
         }
     }
 
     pub async fn get_statistics(&self) -> Result<serde_json::Value> {
-        let stats = self.get_stats().await;
+        let stats = self.stats().await;
         Ok(serde_json::json!({
             "hits": stats.hits,
             "misses": stats.misses,
             "sets": stats.sets,
             "deletes": stats.deletes,
-            "hit_rate": if stats.hits + stats.misses > 0 {
-                stats.hits as f64 / (stats.hits + stats.misses) as f64
-            } else { 0.0 }
+            "entries": stats.entries,
+            "memory_bytes": stats.memory_bytes,
+            "hit_rate": hit_rate(stats.hits, stats.misses),
+            "prefixes": stats.prefixes
         }))
     }
 
@@ -119,11 +242,11 @@ impl CacheService {
     }
 
     pub async fn get_memory_usage(&self) -> Result<serde_json::Value> {
-        let cache = self.cache.read().await;
+        let stats = self.stats().await;
         Ok(serde_json::json!({
-            "used_memory": cache.len() * 100, // Rough estimation
+            "used_memory": stats.memory_bytes,
             "max_memory": 1000000, // Mock value
-            "usage_percentage": (cache.len() as f64 / 10000.0) * 100.0
+            "usage_percentage": (stats.memory_bytes as f64 / 1000000.0) * 100.0
         }))
     }
 
@@ -131,6 +254,7 @@ impl CacheService {
         let mut cache = self.cache.write().await;
         let count = cache.len();
         cache.clear();
+        self.counters.deletes.fetch_add(count as u64, Ordering::Relaxed);
         Ok(serde_json::json!({
             "cleared_keys": count
         }))
@@ -179,14 +303,58 @@ impl CacheService {
     }
 
     pub async fn get_health_status(&self) -> Result<serde_json::Value> {
-        let cache = self.cache.read().await;
-        let stats = self.get_stats().await;
+        let stats = self.stats().await;
         Ok(serde_json::json!({
             "status": "healthy",
-            "entries": cache.len(),
+            "entries": stats.entries,
             "hits": stats.hits,
             "misses": stats.misses,
             "uptime": 0
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn computed_values_are_reused_until_the_ttl_runs_out() {
+        let cache = CacheService::new();
+        let runs = AtomicUsize::new(0);
+        let load = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Ok(vec!["IQD".to_string(), "USD".to_string()])
+        };
+        let ttl = Duration::from_millis(50);
+
+        let first: Vec<String> = cache.get_or_compute("settings:currencies", ttl, load()).await.unwrap();
+        let second: Vec<String> = cache.get_or_compute("settings:currencies", ttl, load()).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        let _: Vec<String> = cache.get_or_compute("settings:currencies", ttl, load()).await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        // A failed computation is handed back and nothing is stored
+        let failed: Result<i64> = cache.get_or_compute("branch:config", ttl, async { Err(anyhow::anyhow!("db down")) }).await;
+        assert!(failed.is_err());
+        assert!(cache.get("branch:config").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn invalidate_drops_only_keys_under_the_prefix() {
+        let cache = CacheService::new();
+        for key in ["settings:all", "settings:bill", "branch:config"] {
+            cache.set(key.to_string(), "{}".to_string(), None).await.unwrap();
+        }
+
+        assert_eq!(cache.invalidate("settings").await, 2);
+        assert!(cache.get("settings:all").await.is_none());
+        assert!(cache.get("settings:bill").await.is_none());
+        assert_eq!(cache.get("branch:config").await.as_deref(), Some("{}"));
+        assert_eq!(cache.invalidate("settings").await, 0);
+    }
+}
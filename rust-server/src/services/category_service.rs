@@ -0,0 +1,116 @@
+use anyhow::Result;
+use tracing::info;
+use crate::database::Database;
+use crate::models::category::*;
+
+#[derive(Clone)]
+pub struct CategoryService;
+
+impl CategoryService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub async fn list_with_counts(&self, db: &Database) -> Result<Vec<CategoryWithCount>> {
+        let categories = sqlx::query_as::<_, CategoryWithCount>(r#"
+            SELECT c.id, c.name, COUNT(p.id) as product_count, c.created_at, c.updated_at
+            FROM categories c
+            LEFT JOIN products p ON p.category_id = c.id
+            GROUP BY c.id
+            ORDER BY c.name
+        "#)
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(categories)
+    }
+
+    pub async fn get_by_id(&self, db: &Database, id: i64) -> Result<Option<Category>> {
+        let category = sqlx::query_as::<_, Category>("SELECT id, name, created_at, updated_at FROM categories WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&db.pool)
+            .await?;
+
+        Ok(category)
+    }
+
+    // Names are unique regardless of case, so imports matching by name find one category
+    async fn validate_name(&self, db: &Database, name: &str, id: Option<i64>) -> Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("اسم الفئة مطلوب"));
+        }
+        let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM categories WHERE LOWER(name) = LOWER(?)")
+            .bind(name)
+            .fetch_optional(&db.pool)
+            .await?;
+        if existing.is_some_and(|existing| Some(existing) != id) {
+            return Err(anyhow::anyhow!("الفئة موجودة مسبقاً"));
+        }
+        Ok(name.to_string())
+    }
+
+    pub async fn create(&self, db: &Database, payload: CategoryRequest) -> Result<Category> {
+        let name = self.validate_name(db, &payload.name, None).await?;
+
+        let id = sqlx::query("INSERT INTO categories (name, created_at, updated_at) VALUES (?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)")
+            .bind(&name)
+            .execute(&db.pool)
+            .await?
+            .last_insert_rowid();
+
+        info!("Category created: {}", name);
+        self.get_by_id(db, id).await?.ok_or_else(|| anyhow::anyhow!("فشل في إنشاء الفئة"))
+    }
+
+    pub async fn update(&self, db: &Database, id: i64, payload: CategoryRequest) -> Result<Category> {
+        if self.get_by_id(db, id).await?.is_none() {
+            return Err(anyhow::anyhow!("الفئة غير موجودة"));
+        }
+        let name = self.validate_name(db, &payload.name, Some(id)).await?;
+
+        sqlx::query("UPDATE categories SET name = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&name)
+            .bind(id)
+            .execute(&db.pool)
+            .await?;
+
+        self.get_by_id(db, id).await?.ok_or_else(|| anyhow::anyhow!("الفئة غير موجودة"))
+    }
+
+    // A category still holding products is kept unless `reassign`, which leaves those products uncategorized
+    pub async fn delete(&self, db: &Database, id: i64, reassign: bool) -> Result<bool> {
+        let mut tx = db.pool.begin().await?;
+
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM categories WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if exists.is_none() {
+            return Ok(false);
+        }
+
+        let products: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM products WHERE category_id = ?")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if products > 0 {
+            if !reassign {
+                return Err(anyhow::anyhow!("لا يمكن حذف فئة مرتبطة بـ {} منتج", products));
+            }
+            sqlx::query("UPDATE products SET category_id = NULL, updated_at = CURRENT_TIMESTAMP WHERE category_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query("DELETE FROM categories WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        info!("Category {} deleted ({} products left uncategorized)", id, products);
+        Ok(true)
+    }
+}
@@ -1,7 +1,8 @@
 use crate::models::{
     CloudBackup, CreateCloudBackupRequest, BackupStats, ServerHealth, LicenseInfo,
-    DatabaseFileInfo, FileLockInfo, RestoreRequest, ApiResponse
+    DatabaseFileInfo, FileLockInfo, RestoreRequest, ApiResponse, RemoteBackup, RestoreBackupResponse
 };
+use crate::services::database_service::DatabaseService;
 use crate::database::Database;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
@@ -12,7 +13,75 @@ use tracing::{info, warn, error};
 use uuid::Uuid;
 use sqlx::Row;
 use reqwest::Client;
+use sha2::{Digest, Sha256};
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+const DEFAULT_UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Called with (bytes uploaded so far, total bytes) after every acknowledged chunk.
+pub type UploadProgress = dyn Fn(u64, u64) + Send + Sync;
+
+// Chunk size for cloud backup uploads; smaller chunks lose less work when a flaky link drops
+fn upload_chunk_size() -> usize {
+    match std::env::var("CLOUD_BACKUP_CHUNK_SIZE") {
+        Ok(value) => match value.trim().parse::<usize>() {
+            Ok(size) if size > 0 => size,
+            _ => {
+                warn!("Invalid CLOUD_BACKUP_CHUNK_SIZE={}, using {}", value, DEFAULT_UPLOAD_CHUNK_SIZE);
+                DEFAULT_UPLOAD_CHUNK_SIZE
+            }
+        },
+        Err(_) => DEFAULT_UPLOAD_CHUNK_SIZE,
+    }
+}
+
+// No valid license for a remote backup operation; routes answer 403 for it
+#[derive(Debug)]
+pub struct LicenseRequired(pub String);
+
+impl std::fmt::Display for LicenseRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for LicenseRequired {}
+
+// Parse the remote `/api/user-backup/user/:id` listing; entries without a backup id are skipped
+pub fn parse_remote_backups(response: &serde_json::Value) -> Vec<RemoteBackup> {
+    let Some(entries) = response.get("backups").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+
+    entries.iter()
+        .filter_map(|entry| {
+            let backup_id = entry.get("backupId").and_then(|v| v.as_str())?.to_string();
+            Some(RemoteBackup {
+                backup_name: entry.get("backupName")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Unknown")
+                    .to_string(),
+                description: entry.get("description").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                size: entry.get("size").and_then(|v| v.as_i64()).unwrap_or(0),
+                checksum: entry.get("checksum")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.trim_start_matches("sha256:").to_lowercase()),
+                created_at: entry.get("createdAt")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                    .map(|dt| dt.with_timezone(&Utc)),
+                backup_id,
+            })
+        })
+        .collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
 
 #[derive(Clone)]
 pub struct CloudBackupService {
@@ -41,6 +110,12 @@ impl CloudBackupService {
         }
     }
 
+    #[cfg(test)]
+    fn with_remote_server(mut self, url: String) -> Self {
+        self.remote_server_url = url;
+        self
+    }
+
     async fn get_license_identifiers(&self) -> Result<(String, String)> {
         // Get user ID from license service
         match self.license_service.verify_license_and_key().await {
@@ -65,6 +140,14 @@ impl CloudBackupService {
         }
     }
 
+    // Remote listing and restore are licensed features
+    async fn require_license(&self) -> Result<String> {
+        self.get_license_identifiers()
+            .await
+            .map(|(user_id, _)| user_id)
+            .map_err(|e| LicenseRequired(format!("A valid license is required for cloud backups: {}", e)).into())
+    }
+
     async fn send_multipart_request(&self, url: &str, file_path: &str, metadata: serde_json::Value) -> Result<serde_json::Value> {
         let file = tokio::fs::File::open(file_path).await?;
         let file_metadata = tokio::fs::metadata(file_path).await?;
@@ -142,10 +225,33 @@ impl CloudBackupService {
         }
     }
 
+    async fn send_post_request(&self, url: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        let mut request = self.http_client
+            .post(url)
+            .header("Accept", "application/json")
+            .json(body);
+        
+        // Add API key if present
+        if let Some(ref api_key) = self.api_key {
+            request = request.header("x-api-key", api_key);
+        }
+        
+        let response = request.send().await?;
+        
+        if response.status().is_success() {
+            let response_data: serde_json::Value = response.json().await?;
+            Ok(response_data)
+        } else {
+            let error_text = response.text().await?;
+            Err(anyhow::anyhow!("Remote server error: {}", error_text))
+        }
+    }
+
     pub async fn create_cloud_backup(
         &self,
         db: &Database,
         request: CreateCloudBackupRequest,
+        progress: &UploadProgress,
     ) -> Result<ApiResponse<CloudBackup>> {
         let backup_name = request.backup_name.clone().unwrap_or_else(|| {
             format!("Backup_{}", chrono::Utc::now().format("%Y%m%d_%H%M%S"))
@@ -181,92 +287,234 @@ impl CloudBackupService {
             return Err(anyhow::anyhow!("Database file not found at: {}", db_path));
         }
         
-        // Prepare metadata for remote server
-        let mut metadata = serde_json::Map::new();
-        metadata.insert("userId".to_string(), serde_json::Value::String(actual_user_id.to_string()));
-        metadata.insert("deviceId".to_string(), serde_json::Value::String(device_id));
-        if let Some(ref name) = request.backup_name {
-            metadata.insert("backupName".to_string(), serde_json::Value::String(name.clone()));
+        // Snapshot the database first: a resumed upload must send the same bytes as the first attempt
+        let backup_dir = std::env::var("BACKUP_DIR")
+            .unwrap_or_else(|_| "~/.urcash/backups".to_string());
+        let backup_dir = shellexpand::tilde(&backup_dir).to_string();
+        fs::create_dir_all(&backup_dir)?;
+        
+        let backup_uuid = Uuid::new_v4().to_string();
+        let backup_filename = format!("backup_{}_{}.sqlite", backup_uuid, chrono::Utc::now().format("%Y%m%d_%H%M%S"));
+        let backup_path = format!("{}/{}", backup_dir, backup_filename);
+        fs::copy(&db_path, &backup_path)?;
+        
+        let snapshot = fs::read(&backup_path)?;
+        let file_size = snapshot.len() as i64;
+        let checksum = sha256_hex(&snapshot);
+        drop(snapshot);
+        
+        // For local database, we'll use a numeric user ID (1) since we're storing locally
+        let local_user_id = 1;
+        
+        let backup_id = sqlx::query(
+            r#"
+            INSERT INTO cloud_backups (
+                user_id, backup_name, description, file_path, file_size,
+                backup_type, status, created_at, updated_at, checksum, uploaded_bytes
+            ) VALUES (?, ?, ?, ?, ?, 'manual', 'uploading', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, 0)
+            "#
+        )
+        .bind(local_user_id)
+        .bind(&backup_name)
+        .bind(&request.description)
+        .bind(&backup_path)
+        .bind(file_size)
+        .bind(&checksum)
+        .execute(&db.pool)
+        .await?
+        .last_insert_rowid();
+        
+        info!("Starting chunked upload to remote server: {}", self.remote_server_url);
+        
+        // Open an upload session; the remote hands back the id every chunk is sent against
+        let init_url = format!("{}/api/user-backup/upload/init", self.remote_server_url);
+        let init_body = json!({
+            "userId": actual_user_id,
+            "deviceId": device_id,
+            "backupName": backup_name,
+            "description": request.description,
+            "fileSize": file_size,
+            "checksum": checksum,
+        });
+        let remote_backup_id = match self.send_post_request(&init_url, &init_body).await {
+            Ok(response_data) => response_data.get("backupId")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .ok_or_else(|| anyhow::anyhow!("Remote server did not return a backup id")),
+            Err(e) => Err(e),
+        };
+        let remote_backup_id = match remote_backup_id {
+            Ok(id) => id,
+            Err(e) => {
+                error!("Failed to start upload on remote server: {}", e);
+                self.mark_upload_failed(db, backup_id).await;
+                return Err(anyhow::anyhow!("Failed to create cloud backup: {}", e));
+            }
+        };
+        
+        sqlx::query("UPDATE cloud_backups SET remote_backup_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&remote_backup_id)
+            .bind(backup_id)
+            .execute(&db.pool)
+            .await?;
+        
+        if let Err(e) = self.upload_chunks(db, backup_id, &remote_backup_id, &backup_path, 0, progress).await {
+            error!("Failed to upload to remote server: {}", e);
+            return Err(anyhow::anyhow!("Failed to create cloud backup: {}", e));
         }
-        if let Some(ref desc) = request.description {
-            metadata.insert("description".to_string(), serde_json::Value::String(desc.clone()));
+        
+        let backup = self.get_backup_by_id(db, backup_id).await?;
+        
+        info!("Cloud backup created successfully: {}", backup_id);
+        
+        Ok(ApiResponse::success(backup))
+    }
+    
+    /// Continue an interrupted upload from the last offset the remote acknowledged.
+    pub async fn resume_cloud_backup(
+        &self,
+        db: &Database,
+        remote_backup_id: &str,
+        progress: &UploadProgress,
+    ) -> Result<ApiResponse<CloudBackup>> {
+        let backup = self.get_backup_by_remote_id(db, remote_backup_id).await?;
+        
+        if backup.status == "completed" {
+            return Ok(ApiResponse::success(backup));
         }
         
-        info!("Uploading backup to remote server: {}", self.remote_server_url);
+        if !Path::new(&backup.file_path).exists() {
+            return Err(anyhow::anyhow!("Backup file not found at: {}", backup.file_path));
+        }
         
-        // Upload to remote server
-        let remote_url = format!("{}/api/user-backup/create", self.remote_server_url);
-        let remote_response = self.send_multipart_request(&remote_url, &db_path, serde_json::Value::Object(metadata)).await;
+        info!(
+            "Resuming cloud backup {} from byte {} of {}",
+            remote_backup_id, backup.uploaded_bytes, backup.file_size
+        );
         
-        match remote_response {
-            Ok(response_data) => {
-                info!("Cloud backup uploaded to remote server successfully");
-                
-                // Create local backup record
-                let backup_dir = std::env::var("BACKUP_DIR")
-                    .unwrap_or_else(|_| "~/.urcash/backups".to_string());
-                let backup_dir = shellexpand::tilde(&backup_dir).to_string();
-                fs::create_dir_all(&backup_dir)?;
-                
-                // Generate backup file path
-                let backup_id = Uuid::new_v4().to_string();
-                let backup_filename = format!("backup_{}_{}.sqlite", backup_id, chrono::Utc::now().format("%Y%m%d_%H%M%S"));
-                let backup_path = format!("{}/{}", backup_dir, backup_filename);
-                
-                // Copy the database file locally
-                fs::copy(&db_path, &backup_path)?;
-                
-                // Get file size
-                let metadata = fs::metadata(&backup_path)?;
-                let file_size = metadata.len() as i64;
-                
-                // Calculate checksum (simplified for now)
-                let checksum = Some(format!("sha256_{}", backup_id));
-                
-                // Get remote backup ID from response
-                let remote_backup_id = response_data.get("backupId")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-                
-                // Insert backup record into database
-                let query = r#"
-                    INSERT INTO cloud_backups (
-                        user_id, backup_name, description, file_path, file_size,
-                        backup_type, status, created_at, updated_at, checksum, remote_backup_id
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, ?)
-                "#;
-                
-                // For local database, we'll use a numeric user ID (1) since we're storing locally
-                let local_user_id = 1;
-                
-                let backup_id = sqlx::query(query)
-                    .bind(local_user_id)
-                    .bind(&backup_name)
-                    .bind(&request.description)
-                    .bind(&backup_path)
-                    .bind(file_size)
-                    .bind("manual")
-                    .bind("completed")
-                    .bind(&checksum)
-                    .bind(&remote_backup_id)
-                    .execute(&db.pool)
-                    .await?
-                    .last_insert_rowid();
-                
-                // Get the created backup
-                let backup = self.get_backup_by_id(db, backup_id).await?;
-                
-                info!("Cloud backup created successfully: {}", backup_id);
-                
-                Ok(ApiResponse::success(backup))
+        sqlx::query("UPDATE cloud_backups SET status = 'uploading', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(backup.id)
+            .execute(&db.pool)
+            .await?;
+        
+        self.upload_chunks(db, backup.id, remote_backup_id, &backup.file_path, backup.uploaded_bytes as u64, progress)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to resume cloud backup: {}", e))?;
+        
+        let backup = self.get_backup_by_id(db, backup.id).await?;
+        Ok(ApiResponse::success(backup))
+    }
+    
+    // Send the file from `offset` onwards one chunk at a time, recording each acknowledged chunk so a
+    // dropped connection costs at most one chunk. Any failure leaves the row 'failed' for a later resume.
+    async fn upload_chunks(
+        &self,
+        db: &Database,
+        backup_id: i64,
+        remote_backup_id: &str,
+        file_path: &str,
+        offset: u64,
+        progress: &UploadProgress,
+    ) -> Result<()> {
+        let result = self.send_chunks(db, backup_id, remote_backup_id, file_path, offset, progress).await;
+        
+        match result {
+            Ok(()) => {
+                sqlx::query(
+                    "UPDATE cloud_backups SET status = 'completed', uploaded_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+                )
+                .bind(backup_id)
+                .execute(&db.pool)
+                .await?;
+                Ok(())
             }
             Err(e) => {
-                error!("Failed to upload to remote server: {}", e);
-                Err(anyhow::anyhow!("Failed to create cloud backup: {}", e))
+                self.mark_upload_failed(db, backup_id).await;
+                Err(e)
             }
         }
     }
     
+    async fn send_chunks(
+        &self,
+        db: &Database,
+        backup_id: i64,
+        remote_backup_id: &str,
+        file_path: &str,
+        mut offset: u64,
+        progress: &UploadProgress,
+    ) -> Result<()> {
+        let mut file = tokio::fs::File::open(file_path).await?;
+        let total = file.metadata().await?.len();
+        if offset > total {
+            return Err(anyhow::anyhow!("Stored upload offset {} is past the end of the {} byte backup", offset, total));
+        }
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        
+        let chunk_size = upload_chunk_size();
+        let chunk_url = format!("{}/api/user-backup/upload/{}/chunk", self.remote_server_url, remote_backup_id);
+        progress(offset, total);
+        
+        while offset < total {
+            let mut chunk = Vec::with_capacity(chunk_size);
+            (&mut file).take(chunk_size as u64).read_to_end(&mut chunk).await?;
+            if chunk.is_empty() {
+                return Err(anyhow::anyhow!("Backup file shrank to {} bytes during upload", offset));
+            }
+            let chunk_len = chunk.len() as u64;
+            
+            let chunk_part = reqwest::multipart::Part::bytes(chunk)
+                .file_name("database.sqlite")
+                .mime_str("application/octet-stream")?;
+            let form = reqwest::multipart::Form::new()
+                .text("offset", offset.to_string())
+                .text("totalSize", total.to_string())
+                .part("chunk", chunk_part);
+            
+            let mut request = self.http_client
+                .post(&chunk_url)
+                .header("Accept", "application/json")
+                .header("User-Agent", "Urcash-CloudBackup/1.0")
+                .multipart(form);
+            if let Some(ref api_key) = self.api_key {
+                request = request.header("x-api-key", api_key);
+            }
+            
+            let response = request.send().await?;
+            if !response.status().is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(anyhow::anyhow!("Remote server rejected chunk at byte {}: {}", offset, error_text));
+            }
+            
+            offset += chunk_len;
+            sqlx::query("UPDATE cloud_backups SET uploaded_bytes = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(offset as i64)
+                .bind(backup_id)
+                .execute(&db.pool)
+                .await?;
+            progress(offset, total);
+        }
+        
+        let checksum: Option<String> = sqlx::query_scalar("SELECT checksum FROM cloud_backups WHERE id = ?")
+            .bind(backup_id)
+            .fetch_one(&db.pool)
+            .await?;
+        let complete_url = format!("{}/api/user-backup/upload/{}/complete", self.remote_server_url, remote_backup_id);
+        self.send_post_request(&complete_url, &json!({ "totalSize": total, "checksum": checksum })).await?;
+        
+        Ok(())
+    }
+    
+    async fn mark_upload_failed(&self, db: &Database, backup_id: i64) {
+        if let Err(e) = sqlx::query("UPDATE cloud_backups SET status = 'failed', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(backup_id)
+            .execute(&db.pool)
+            .await
+        {
+            warn!("Failed to mark cloud backup {} as failed: {}", backup_id, e);
+        }
+    }
+    
     pub async fn get_user_backups(
         &self,
         db: &Database,
@@ -319,6 +567,9 @@ impl CloudBackupService {
                                 checksum: None,
                                 compression_ratio: None,
                                 encryption_key: None,
+                                uploaded_bytes: backup_obj.get("size")
+                                    .and_then(|v| v.as_i64())
+                                    .unwrap_or(0),
                             };
                             backups.push(backup);
                         }
@@ -360,6 +611,7 @@ impl CloudBackupService {
                         checksum: row.get("checksum"),
                         compression_ratio: row.get("compression_ratio"),
                         encryption_key: row.get("encryption_key"),
+                        uploaded_bytes: row.get("uploaded_bytes"),
                     };
                     backups.push(backup);
                 }
@@ -369,6 +621,68 @@ impl CloudBackupService {
         }
     }
     
+    /// Backups this license holder has on the remote server, newest first as the remote returns them.
+    pub async fn list_remote(&self) -> Result<Vec<RemoteBackup>> {
+        let user_id = self.require_license().await?;
+        self.remote_backups_of(&user_id).await
+    }
+    
+    async fn remote_backups_of(&self, user_id: &str) -> Result<Vec<RemoteBackup>> {
+        let remote_url = format!("{}/api/user-backup/user/{}", self.remote_server_url, user_id);
+        let response_data = self.send_get_request(&remote_url).await?;
+        
+        Ok(parse_remote_backups(&response_data))
+    }
+    
+    /// Download a remote backup, check it against the checksum the remote recorded at upload and swap it
+    /// in as the live database. Callers close over this with `DatabaseService::safe_operation`.
+    pub async fn restore_from_remote(&self, db: &Database, remote_backup_id: &str) -> Result<RestoreBackupResponse> {
+        let remote = self.list_remote().await?
+            .into_iter()
+            .find(|backup| backup.backup_id == remote_backup_id)
+            .ok_or_else(|| anyhow::anyhow!("Remote backup not found: {}", remote_backup_id))?;
+        let expected_checksum = remote.checksum
+            .ok_or_else(|| anyhow::anyhow!("Remote backup {} has no checksum to verify against", remote_backup_id))?;
+        
+        info!("Downloading remote backup {} for restore", remote_backup_id);
+        
+        let download_url = format!("{}/api/user-backup/download/{}", self.remote_server_url, remote_backup_id);
+        let mut request = self.http_client.get(&download_url);
+        if let Some(ref api_key) = self.api_key {
+            request = request.header("x-api-key", api_key);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to download backup: {}", error_text));
+        }
+        let file_data = response.bytes().await?;
+        
+        let actual_checksum = sha256_hex(&file_data);
+        if actual_checksum != expected_checksum {
+            error!(
+                "Checksum mismatch for remote backup {}: expected {}, got {}",
+                remote_backup_id, expected_checksum, actual_checksum
+            );
+            return Err(anyhow::anyhow!("Downloaded backup is corrupted (checksum mismatch)"));
+        }
+        
+        let backup_dir = std::env::var("BACKUP_DIR")
+            .unwrap_or_else(|_| "~/.urcash/backups".to_string());
+        let backup_dir = shellexpand::tilde(&backup_dir).to_string();
+        fs::create_dir_all(&backup_dir)?;
+        // The id comes from the remote; keep only filename-safe characters
+        let file_stem: String = remote_backup_id.chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        let download_path = format!("{}/remote_{}.sqlite", backup_dir, file_stem);
+        fs::write(&download_path, &file_data)?;
+        
+        info!("Remote backup {} verified ({} bytes), restoring", remote_backup_id, file_data.len());
+        
+        DatabaseService::new().restore_from_custom_backup(db, &download_path).await
+    }
+    
     pub async fn get_backup_stats(
         &self,
         db: &Database,
@@ -517,6 +831,7 @@ impl CloudBackupService {
                 checksum: None,
                 compression_ratio: None,
                 encryption_key: None,
+                uploaded_bytes: file_data.len() as i64,
             };
             
             Ok(ApiResponse::success(backup))
@@ -636,6 +951,7 @@ impl CloudBackupService {
             checksum: row.get("checksum"),
             compression_ratio: row.get("compression_ratio"),
             encryption_key: row.get("encryption_key"),
+            uploaded_bytes: row.get("uploaded_bytes"),
         })
     }
     
@@ -662,6 +978,143 @@ impl CloudBackupService {
             checksum: row.get("checksum"),
             compression_ratio: row.get("compression_ratio"),
             encryption_key: row.get("encryption_key"),
+            uploaded_bytes: row.get("uploaded_bytes"),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::services::license_service::LicenseService;
+    use axum::{extract::{Multipart, State}, http::StatusCode, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    // What the mock remote received: the reassembled upload, every chunk offset tried and the completion
+    #[derive(Default)]
+    struct Remote {
+        received: Vec<u8>,
+        attempts: Vec<u64>,
+        dropped_once: bool,
+        completed: Option<serde_json::Value>,
+    }
+
+    // Stands in for the backup server; the first attempt at the second chunk fails like a dropped link
+    async fn mock_remote() -> (String, Arc<Mutex<Remote>>) {
+        let remote = Arc::new(Mutex::new(Remote::default()));
+        let app = Router::new()
+            .route("/api/user-backup/upload/:id/chunk", post(|State(remote): State<Arc<Mutex<Remote>>>, mut form: Multipart| async move {
+                let (mut offset, mut chunk) = (0, Vec::new());
+                while let Some(field) = form.next_field().await.unwrap() {
+                    match field.name() {
+                        Some("offset") => offset = field.text().await.unwrap().parse::<u64>().unwrap(),
+                        Some("chunk") => chunk = field.bytes().await.unwrap().to_vec(),
+                        _ => {}
+                    }
+                }
+                let mut remote = remote.lock().unwrap();
+                remote.attempts.push(offset);
+                if offset > 0 && !remote.dropped_once {
+                    remote.dropped_once = true;
+                    return StatusCode::BAD_GATEWAY;
+                }
+                assert_eq!(offset, remote.received.len() as u64);
+                remote.received.extend_from_slice(&chunk);
+                StatusCode::OK
+            }))
+            .route("/api/user-backup/upload/:id/complete", post(|State(remote): State<Arc<Mutex<Remote>>>, Json(body): Json<serde_json::Value>| async move {
+                remote.lock().unwrap().completed = Some(body);
+                Json(json!({ "success": true }))
+            }))
+            .with_state(remote.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, remote)
+    }
+
+    #[tokio::test]
+    async fn a_dropped_chunk_fails_the_upload_and_resume_continues_from_the_stored_offset() {
+        let db = TestDatabase::new().await;
+        let (url, remote) = mock_remote().await;
+        let service = CloudBackupService::new(LicenseService::new()).with_remote_server(url);
+
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("snapshot.sqlite");
+        let data: Vec<u8> = (0..DEFAULT_UPLOAD_CHUNK_SIZE * 5 / 2).map(|i| (i % 251) as u8).collect();
+        fs::write(&file_path, &data).unwrap();
+        sqlx::query(
+            "INSERT INTO cloud_backups (user_id, backup_name, file_path, file_size, status, remote_backup_id, checksum, uploaded_bytes) VALUES (1, 'Nightly', ?, ?, 'uploading', 'rb-77', ?, 0)"
+        )
+        .bind(file_path.to_str().unwrap())
+        .bind(data.len() as i64)
+        .bind(sha256_hex(&data))
+        .execute(&db.pool).await.unwrap();
+        let state = || async {
+            sqlx::query_as::<_, (String, i64)>("SELECT status, uploaded_bytes FROM cloud_backups WHERE remote_backup_id = 'rb-77'")
+                .fetch_one(&db.pool).await.unwrap()
+        };
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let progress = {
+            let reported = reported.clone();
+            move |done: u64, total: u64| reported.lock().unwrap().push((done, total))
+        };
+
+        assert!(service.resume_cloud_backup(&db, "rb-77", &progress).await.is_err());
+        assert_eq!(state().await, ("failed".to_string(), DEFAULT_UPLOAD_CHUNK_SIZE as i64));
+
+        let backup = service.resume_cloud_backup(&db, "rb-77", &progress).await.unwrap().data.unwrap();
+        assert_eq!(backup.status, "completed");
+        assert_eq!(state().await, ("completed".to_string(), data.len() as i64));
+
+        let chunk = DEFAULT_UPLOAD_CHUNK_SIZE as u64;
+        let remote = remote.lock().unwrap();
+        assert_eq!(remote.attempts, [0, chunk, chunk, 2 * chunk]);
+        assert!(remote.received == data);
+        assert_eq!(remote.completed.as_ref().unwrap()["checksum"], sha256_hex(&data));
+        let total = data.len() as u64;
+        assert_eq!(reported.lock().unwrap().last(), Some(&(total, total)));
+        assert!(reported.lock().unwrap().contains(&(chunk, total)));
+    }
+
+    #[tokio::test]
+    async fn remote_listing_is_parsed_and_needs_a_license() {
+        let app = Router::new().route("/api/user-backup/user/:user_id", axum::routing::get(|axum::extract::Path(user_id): axum::extract::Path<String>| async move {
+            assert_eq!(user_id, "u-451");
+            Json(json!({
+                "success": true,
+                "backups": [
+                    {
+                        "backupId": "rb-2",
+                        "backupName": "Before year end",
+                        "description": "قبل الجرد",
+                        "size": 5242880,
+                        "checksum": "sha256:9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08",
+                        "createdAt": "2026-09-30T21:15:00Z"
+                    },
+                    { "backupName": "half-written entry" },
+                    { "backupId": "rb-1", "size": 1024 }
+                ]
+            }))
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let service = CloudBackupService::new(LicenseService::new()).with_remote_server(url);
+
+        let backups = service.remote_backups_of("u-451").await.unwrap();
+        let ids: Vec<&str> = backups.iter().map(|backup| backup.backup_id.as_str()).collect();
+        assert_eq!(ids, ["rb-2", "rb-1"]);
+        assert_eq!(backups[0].backup_name, "Before year end");
+        assert_eq!(backups[0].size, 5242880);
+        assert_eq!(backups[0].checksum.as_deref(), Some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"));
+        assert_eq!(backups[0].created_at.unwrap().to_rfc3339(), "2026-09-30T21:15:00+00:00");
+        assert_eq!((backups[1].backup_name.as_str(), backups[1].checksum.as_ref(), backups[1].created_at), ("Unknown", None, None));
+
+        // Without an activated license on this machine the listing is refused before calling out
+        let err = service.list_remote().await.unwrap_err();
+        assert!(err.downcast_ref::<LicenseRequired>().is_some(), "{err}");
+    }
+}
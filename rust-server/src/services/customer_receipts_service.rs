@@ -1,8 +1,10 @@
 use crate::models::customer::CustomerReceipt;
 use crate::models::ApiResponse;
 use crate::database::Database;
+use crate::services::SequenceService;
+use crate::utils::CsvFormat;
 use anyhow::Result;
-use chrono::{NaiveDateTime, Utc, Datelike};
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use sqlx::Row;
@@ -20,6 +22,36 @@ pub struct CreateCustomerReceiptRequest {
     pub money_box_id: Option<i64>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptAllocation {
+    pub debt_id: i64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBulkReceiptRequest {
+    pub customer_id: i64,
+    pub amount: f64,
+    // Omitted: the amount settles the oldest debts first
+    pub allocations: Option<Vec<ReceiptAllocation>>,
+    pub payment_method: String,
+    pub money_box_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppliedReceiptAllocation {
+    pub debt_id: i64,
+    pub sale_id: i64,
+    pub amount: f64,
+    pub remaining: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkReceiptResult {
+    pub receipt: Value,
+    pub allocations: Vec<AppliedReceiptAllocation>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateCustomerReceiptRequest {
     pub customer_id: Option<i64>,
@@ -168,9 +200,6 @@ impl CustomerReceiptsService {
     }
 
     pub async fn create_receipt(&self, db: &Database, request: CreateCustomerReceiptRequest, user_id: i64) -> Result<Value> {
-        // Generate receipt number
-        let receipt_number = self.generate_receipt_number(db).await?;
-        
         // Parse receipt date or use current date
         let receipt_date = if let Some(date_str) = request.receipt_date {
             NaiveDateTime::parse_from_str(&format!("{} 00:00:00", date_str), "%Y-%m-%d %H:%M:%S")
@@ -181,6 +210,9 @@ impl CustomerReceiptsService {
 
         let now = Utc::now().naive_utc();
 
+        let mut tx = db.pool.begin().await?;
+        let receipt_number = SequenceService::next(&mut tx, SequenceService::CUSTOMER_RECEIPT).await?;
+
         let receipt_id = sqlx::query(
             "INSERT INTO customer_receipts (
                 receipt_no, customer_id, sale_id, receipt_date, amount, 
@@ -199,10 +231,18 @@ impl CustomerReceiptsService {
         .bind(now)
         .bind(now)
         .bind(request.money_box_id)
-        .execute(&db.pool)
+        .execute(&mut *tx)
         .await?
         .last_insert_rowid();
 
+        // Not allocated to any debt, so it stands as credit against what the customer owes
+        sqlx::query("UPDATE customers SET current_balance = COALESCE(current_balance, 0) - ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(request.amount)
+            .bind(request.customer_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
         // Add money to the selected money box if specified
         if let Some(money_box_id) = request.money_box_id {
             // Import the money box service
@@ -230,6 +270,195 @@ impl CustomerReceiptsService {
         })
     }
 
+    // One receipt paying several debts of the same customer, either as allocated by the caller or oldest-first
+    pub async fn create_bulk(
+        &self,
+        db: &Database,
+        customer_id: i64,
+        amount: f64,
+        allocations: Option<Vec<ReceiptAllocation>>,
+        payment_method: &str,
+        money_box_id: Option<i64>,
+    ) -> Result<BulkReceiptResult> {
+        if amount <= 0.0 {
+            return Err(anyhow::anyhow!("مبلغ الدفع يجب أن يكون أكبر من صفر"));
+        }
+        if !["cash", "card", "bank_transfer", "check"].contains(&payment_method) {
+            return Err(anyhow::anyhow!("طريقة الدفع غير صحيحة"));
+        }
+
+        let mut tx = db.pool.begin().await?;
+        let receipt_number = SequenceService::next(&mut tx, SequenceService::CUSTOMER_RECEIPT).await?;
+
+        // (debt_id, sale_id, remaining, invoice_no), oldest first
+        let open_debts: Vec<(i64, i64, f64, String)> = sqlx::query(r#"
+            SELECT d.id, d.sale_id, d.amount, s.invoice_no
+            FROM debts d
+            JOIN sales s ON s.id = d.sale_id
+            WHERE d.customer_id = ? AND d.amount > 0 AND d.status != 'paid'
+            ORDER BY d.due_date ASC, d.created_at ASC, d.id ASC
+        "#)
+        .bind(customer_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.get("id"), row.get("sale_id"), row.get("amount"), row.get("invoice_no")))
+        .collect();
+
+        if open_debts.is_empty() {
+            return Err(anyhow::anyhow!("لا توجد ديون مستحقة على هذا العميل"));
+        }
+        let total_outstanding: f64 = open_debts.iter().map(|(_, _, remaining, _)| remaining).sum();
+        if amount > total_outstanding + 0.005 {
+            return Err(anyhow::anyhow!("مبلغ الدفع ({:.2}) أكبر من إجمالي الديون المستحقة ({:.2})", amount, total_outstanding));
+        }
+
+        let mut plan: Vec<(i64, i64, f64, f64, String)> = Vec::new();
+        match allocations {
+            Some(allocations) if !allocations.is_empty() => {
+                let mut allocated = 0.0;
+                for allocation in &allocations {
+                    if allocation.amount <= 0.0 {
+                        return Err(anyhow::anyhow!("مبلغ التخصيص يجب أن يكون أكبر من صفر"));
+                    }
+                    if plan.iter().any(|(debt_id, ..)| *debt_id == allocation.debt_id) {
+                        return Err(anyhow::anyhow!("الدين {} مكرر في التخصيص", allocation.debt_id));
+                    }
+                    let (debt_id, sale_id, remaining, invoice_no) = open_debts.iter()
+                        .find(|(debt_id, ..)| *debt_id == allocation.debt_id)
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("الدين {} غير موجود أو مسدد لهذا العميل", allocation.debt_id))?;
+                    if allocation.amount > remaining + 0.005 {
+                        return Err(anyhow::anyhow!("مبلغ التخصيص ({:.2}) أكبر من المتبقي من الدين {} ({:.2})", allocation.amount, debt_id, remaining));
+                    }
+                    allocated += allocation.amount;
+                    plan.push((debt_id, sale_id, allocation.amount.min(remaining), remaining, invoice_no));
+                }
+                if (allocated - amount).abs() > 0.005 {
+                    return Err(anyhow::anyhow!("مجموع التخصيصات ({:.2}) لا يساوي مبلغ الدفع ({:.2})", allocated, amount));
+                }
+            }
+            _ => {
+                let mut left = amount;
+                for (debt_id, sale_id, remaining, invoice_no) in open_debts {
+                    if left <= 0.005 {
+                        break;
+                    }
+                    let part = left.min(remaining);
+                    left -= part;
+                    plan.push((debt_id, sale_id, part, remaining, invoice_no));
+                }
+            }
+        }
+
+        let mut applied = Vec::with_capacity(plan.len());
+        for (debt_id, sale_id, part, remaining, _) in &plan {
+            let new_remaining = remaining - part;
+            if new_remaining <= 0.005 {
+                sqlx::query("DELETE FROM debts WHERE id = ?")
+                    .bind(debt_id)
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query("UPDATE debts SET amount = ?, status = 'partial', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(new_remaining)
+                    .bind(debt_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            // Same bookkeeping as DebtService::repay_debt: the sale carries the paid amount
+            let sale = sqlx::query("SELECT net_amount, paid_amount FROM sales WHERE id = ?")
+                .bind(sale_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            let net_amount: f64 = sale.get("net_amount");
+            let sale_paid = (sale.get::<f64, _>("paid_amount") + part).min(net_amount);
+            let sale_status = if sale_paid >= net_amount - 0.005 { "paid" } else { "partial" };
+            sqlx::query("UPDATE sales SET paid_amount = ?, payment_status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(sale_paid)
+                .bind(sale_status)
+                .bind(sale_id)
+                .execute(&mut *tx)
+                .await?;
+
+            applied.push(AppliedReceiptAllocation {
+                debt_id: *debt_id,
+                sale_id: *sale_id,
+                amount: *part,
+                remaining: new_remaining.max(0.0),
+            });
+        }
+
+        let invoices: Vec<&str> = plan.iter().map(|(.., invoice_no)| invoice_no.as_str()).collect();
+        let receipt_id = sqlx::query(
+            "INSERT INTO customer_receipts (
+                receipt_no, customer_id, sale_id, receipt_date, amount,
+                payment_method, notes, created_at, updated_at, money_box_id
+            ) VALUES (?, ?, NULL, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?)"
+        )
+        .bind(&receipt_number)
+        .bind(customer_id)
+        .bind(Utc::now().naive_utc())
+        .bind(amount)
+        .bind(payment_method)
+        .bind(format!("سداد الفواتير: {}", invoices.join("، ")))
+        .bind(money_box_id)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        for allocation in &applied {
+            sqlx::query("INSERT INTO customer_receipt_allocations (receipt_id, debt_id, sale_id, amount) VALUES (?, ?, ?, ?)")
+                .bind(receipt_id)
+                .bind(allocation.debt_id)
+                .bind(allocation.sale_id)
+                .bind(allocation.amount)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query("UPDATE customers SET current_balance = COALESCE(current_balance, 0) - ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(amount)
+            .bind(customer_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if let Some(box_id) = money_box_id {
+            let balance: f64 = sqlx::query("SELECT amount FROM money_boxes WHERE id = ?")
+                .bind(box_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .map(|row| row.get("amount"))
+                .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+
+            sqlx::query("UPDATE money_boxes SET amount = amount + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(amount)
+                .bind(box_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(r#"
+                INSERT INTO money_box_transactions (box_id, type, amount, balance_after, notes, created_at)
+                VALUES (?, 'deposit', ?, ?, ?, CURRENT_TIMESTAMP)
+            "#)
+            .bind(box_id)
+            .bind(amount)
+            .bind(balance + amount)
+            .bind(format!("إيصال دفع عميل - {}", receipt_number))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        info!("Customer receipt {} settled {} debts for customer {}", receipt_number, applied.len(), customer_id);
+
+        let receipt = self.get_receipt_by_id(db, receipt_id).await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created receipt"))?;
+
+        Ok(BulkReceiptResult { receipt, allocations: applied })
+    }
+
     pub async fn update_receipt(&self, db: &Database, id: i64, _request: UpdateCustomerReceiptRequest) -> Result<Option<Value>> {
         // Check if receipt exists
         let existing = sqlx::query("SELECT id FROM customer_receipts WHERE id = ?")
@@ -306,24 +535,6 @@ impl CustomerReceiptsService {
         }))
     }
 
-    async fn generate_receipt_number(&self, db: &Database) -> Result<String> {
-        let year = Utc::now().year();
-        let month = Utc::now().month();
-        
-        let prefix = format!("CR{}{:02}", year, month);
-        
-        let count: i64 = sqlx::query(
-            "SELECT COUNT(*) FROM customer_receipts WHERE receipt_no LIKE ?"
-        )
-        .bind(format!("{}%", prefix))
-        .fetch_one(&db.pool)
-        .await?
-        .get(0);
-
-        let receipt_number = format!("{}{:04}", prefix, count + 1);
-        Ok(receipt_number)
-    }
-
     // Get customer bills (unpaid sales)
     pub async fn get_customer_bills(&self, db: &Database, customer_id: i64) -> Result<Vec<Value>> {
         let rows = sqlx::query(
@@ -469,7 +680,7 @@ impl CustomerReceiptsService {
     }
 
     // Export customer receipts to CSV
-    pub async fn export_to_csv(&self, db: &Database, query: &CustomerReceiptQuery) -> Result<String> {
+    pub async fn export_to_csv(&self, db: &Database, query: &CustomerReceiptQuery, format: &CsvFormat) -> Result<String> {
         let rows = sqlx::query(
             r#"
             SELECT 
@@ -499,58 +710,36 @@ impl CustomerReceiptsService {
         .fetch_all(&db.pool)
         .await?;
 
-        let mut csv_content = String::new();
-        
-        // Add CSV header
-        csv_content.push_str("ID,رقم الإيصال,تاريخ الإيصال,المبلغ,طريقة الدفع,المرجع,ملاحظات,تاريخ الإنشاء,تاريخ التحديث,اسم العميل,هاتف العميل,بريد العميل,أنشئ بواسطة,صندوق المال,رقم الفاتورة\n");
-        
+        let mut csv_content = format.header(&[
+            "ID", "رقم الإيصال", "تاريخ الإيصال", "المبلغ", "طريقة الدفع", "المرجع", "ملاحظات", "تاريخ الإنشاء",
+            "تاريخ التحديث", "اسم العميل", "هاتف العميل", "بريد العميل", "أنشئ بواسطة", "صندوق المال", "رقم الفاتورة",
+        ]);
+
         // Add data rows
         for row in rows {
-            let id = row.get::<i64, _>("id");
-            let receipt_no = row.get::<String, _>("receipt_no");
-            let receipt_date = row.get::<String, _>("receipt_date");
-            let amount = row.get::<f64, _>("amount");
-            let payment_method = row.get::<String, _>("payment_method");
-            let reference_no = row.get::<Option<String>, _>("reference_no").unwrap_or_default();
-            let notes = row.get::<Option<String>, _>("notes").unwrap_or_default();
-            let created_at = row.get::<String, _>("created_at");
-            let updated_at = row.get::<String, _>("updated_at");
-            let customer_name = row.get::<Option<String>, _>("customer_name").unwrap_or_default();
-            let customer_phone = row.get::<Option<String>, _>("customer_phone").unwrap_or_default();
-            let customer_email = row.get::<Option<String>, _>("customer_email").unwrap_or_default();
-            let created_by_name = row.get::<Option<String>, _>("created_by_name").unwrap_or_default();
-            let money_box_name = row.get::<Option<String>, _>("money_box_name").unwrap_or_default();
-            let sale_invoice_no = row.get::<Option<String>, _>("sale_invoice_no").unwrap_or_default();
-            
-            // Escape CSV fields that contain commas or quotes
-            let escape_csv_field = |field: &str| {
-                if field.contains(',') || field.contains('"') || field.contains('\n') {
-                    format!("\"{}\"", field.replace("\"", "\"\""))
-                } else {
-                    field.to_string()
-                }
-            };
-            
-            csv_content.push_str(&format!("{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
-                id,
-                escape_csv_field(&receipt_no),
-                escape_csv_field(&receipt_date),
-                amount,
-                escape_csv_field(&payment_method),
-                escape_csv_field(&reference_no),
-                escape_csv_field(&notes),
-                escape_csv_field(&created_at),
-                escape_csv_field(&updated_at),
-                escape_csv_field(&customer_name),
-                escape_csv_field(&customer_phone),
-                escape_csv_field(&customer_email),
-                escape_csv_field(&created_by_name),
-                escape_csv_field(&money_box_name),
-                escape_csv_field(&sale_invoice_no)
-            ));
+            let text = |column: &str| format.text(&row.get::<Option<String>, _>(column).unwrap_or_default());
+            let date = |column: &str| format.date_text(&row.get::<Option<String>, _>(column).unwrap_or_default());
+
+            csv_content.push_str(&format.row(&[
+                row.get::<i64, _>("id").to_string(),
+                text("receipt_no"),
+                date("receipt_date"),
+                format.number(row.get::<f64, _>("amount")),
+                text("payment_method"),
+                text("reference_no"),
+                text("notes"),
+                date("created_at"),
+                date("updated_at"),
+                text("customer_name"),
+                text("customer_phone"),
+                text("customer_email"),
+                text("created_by_name"),
+                text("money_box_name"),
+                text("sale_invoice_no"),
+            ]));
         }
-        
-        Ok(csv_content)
+
+        Ok(format.finish(csv_content))
     }
 
     // Export customer receipts to PDF
@@ -717,3 +906,82 @@ impl CustomerReceiptsService {
         Ok(pdf_content.into_bytes())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    #[tokio::test]
+    async fn one_receipt_pays_three_debts_oldest_first() {
+        let db = TestDatabase::new().await;
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone) VALUES ('Hussein Ali', '07701239876')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        // Inserted out of due order so the allocation has to sort them
+        let mut debt_ids = Vec::new();
+        for (invoice_no, due_date, amount) in [("BR-2", "2026-02-10", 20000.0), ("BR-1", "2026-01-10", 10000.0), ("BR-3", "2026-03-10", 30000.0)] {
+            let sale_id = sqlx::query("INSERT INTO sales (customer_id, invoice_no, invoice_date, total_amount, net_amount, paid_amount, payment_status) VALUES (?, ?, '2026-01-01', ?, ?, 0.0, 'unpaid')")
+                .bind(customer_id).bind(invoice_no).bind(amount).bind(amount)
+                .execute(&db.pool).await.unwrap()
+                .last_insert_rowid();
+            let debt_id = sqlx::query("INSERT INTO debts (customer_id, sale_id, amount, due_date, status) VALUES (?, ?, ?, ?, 'unpaid')")
+                .bind(customer_id).bind(sale_id).bind(amount).bind(due_date)
+                .execute(&db.pool).await.unwrap()
+                .last_insert_rowid();
+            debt_ids.push(debt_id);
+        }
+
+        let result = CustomerReceiptsService::new()
+            .create_bulk(&db, customer_id, 45000.0, None, "cash", Some(1))
+            .await
+            .unwrap();
+
+        let applied: Vec<(i64, f64, f64)> = result.allocations.iter().map(|a| (a.debt_id, a.amount, a.remaining)).collect();
+        assert_eq!(applied, vec![(debt_ids[1], 10000.0, 0.0), (debt_ids[0], 20000.0, 0.0), (debt_ids[2], 15000.0, 15000.0)]);
+        assert_eq!(result.receipt["amount"], 45000.0);
+
+        let receipts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM customer_receipts").fetch_one(&db.pool).await.unwrap();
+        let linked: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM customer_receipt_allocations").fetch_one(&db.pool).await.unwrap();
+        assert_eq!((receipts, linked), (1, 3));
+
+        let open: Vec<(i64, String)> = sqlx::query_as("SELECT id, status FROM debts ORDER BY id").fetch_all(&db.pool).await.unwrap();
+        assert_eq!(open, vec![(debt_ids[2], "partial".to_string())]);
+        let statuses: Vec<String> = sqlx::query_scalar("SELECT payment_status FROM sales ORDER BY invoice_no").fetch_all(&db.pool).await.unwrap();
+        assert_eq!(statuses, vec!["paid", "paid", "partial"]);
+
+        let deposit: f64 = sqlx::query_scalar("SELECT CAST(amount AS REAL) FROM money_box_transactions WHERE box_id = 1 AND type = 'deposit'")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(deposit, 45000.0);
+    }
+
+    #[tokio::test]
+    async fn concurrent_receipts_take_consecutive_numbers() {
+        let db = TestDatabase::new().await;
+        let service = CustomerReceiptsService::new();
+        let user_id: i64 = sqlx::query_scalar("SELECT id FROM users ORDER BY id LIMIT 1").fetch_one(&db.pool).await.unwrap();
+        let receipt = |amount: f64| CreateCustomerReceiptRequest {
+            customer_id: 999,
+            sale_id: None,
+            receipt_date: Some("2026-05-10".to_string()),
+            amount,
+            payment_method: "cash".to_string(),
+            reference_number: None,
+            notes: None,
+            money_box_id: None,
+        };
+
+        let created = futures::future::join_all((1..=20).map(|i| service.create_receipt(&db, receipt(i as f64 * 1000.0), user_id))).await;
+        let mut numbers: Vec<String> = created.into_iter()
+            .map(|receipt| receipt.unwrap()["receipt_number"].as_str().unwrap().to_string())
+            .collect();
+        numbers.sort();
+        let expected: Vec<String> = (1..=20).map(|n| format!("CR{:06}", n)).collect();
+        assert_eq!(numbers, expected);
+
+        // A failed bulk payment rolls its claimed number back, so the series stays gap-free
+        assert!(service.create_bulk(&db, 999, 500.0, None, "cash", None).await.is_err());
+        let next = service.create_receipt(&db, receipt(500.0), user_id).await.unwrap();
+        assert_eq!(next["receipt_number"], "CR000021");
+    }
+}
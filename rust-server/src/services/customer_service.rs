@@ -4,12 +4,14 @@ use crate::models::{
     Customer, CreateCustomerRequest, UpdateCustomerRequest, CustomerQuery, CustomerFilters,
     CustomerListResponse, CustomerWithSales, CustomerDetails, CustomerInstallment,
     CustomerBill, CustomerReceipt, CustomerFinancialSummary, CustomerSale, CustomerSaleItem,
-    ApiResponse, PaginatedResponse, CustomerSaleDebt
+    ApiResponse, PaginatedResponse, CustomerSaleDebt, AgingBuckets, CustomerAging, CustomerAgingReport
 };
 use sqlx::{Row, SqlitePool};
 use tracing::{info, warn, error};
-use chrono::{Utc, DateTime};
+use chrono::{Utc, DateTime, NaiveDate};
 use crate::models::PaginationInfo;
+use crate::utils::{normalize_email, normalize_phone, sqlite_rows_to_json};
+use serde_json::{json, Value};
 
 #[derive(Clone)]
 pub struct CustomerService;
@@ -147,12 +149,12 @@ impl CustomerService {
 
     // Create new customer
     pub async fn create(&self, db: &Database, data: CreateCustomerRequest) -> Result<Customer> {
-        // Convert empty email strings to null
-        let email = if data.email.as_deref() == Some("") {
-            None
-        } else {
-            data.email
-        };
+        // Blank email/phone become null; the rest are stored in canonical form
+        let email = data.email.as_deref().map(normalize_email).transpose()?.flatten();
+        let phone = data.phone.as_deref().map(normalize_phone).transpose()?.flatten();
+        if let Some(ref phone) = phone {
+            self.warn_duplicate_phone(db, phone, None).await?;
+        }
 
         // Convert boolean values to integers for SQLite compatibility
         let is_active = data.is_active.unwrap_or(true) as i64;
@@ -167,7 +169,7 @@ impl CustomerService {
         )
         .bind(&data.name)
         .bind(email)
-        .bind(&data.phone)
+        .bind(&phone)
         .bind(&data.address)
         .bind(data.credit_limit.unwrap_or(1000000.0))
         .bind(data.customer_type.unwrap_or_else(|| "retail".to_string()))
@@ -184,14 +186,30 @@ impl CustomerService {
         })
     }
 
+    // Same number under another customer usually means the customer is being entered twice
+    async fn warn_duplicate_phone(&self, db: &Database, phone: &str, exclude_id: Option<i64>) -> Result<()> {
+        let existing = sqlx::query("SELECT id, name FROM customers WHERE phone = ? AND id != ? LIMIT 1")
+            .bind(phone)
+            .bind(exclude_id.unwrap_or(0))
+            .fetch_optional(&db.pool)
+            .await?;
+        if let Some(row) = existing {
+            warn!(
+                "Customer phone {} is already used by customer {} ({})",
+                phone, row.get::<i64, _>("id"), row.get::<String, _>("name")
+            );
+        }
+        Ok(())
+    }
+
     // Update customer
     pub async fn update(&self, db: &Database, id: i64, data: UpdateCustomerRequest) -> Result<Option<Customer>> {
-        // Convert empty email strings to null
-        let email = if data.email.as_deref() == Some("") {
-            None
-        } else {
-            data.email
-        };
+        // Blank email becomes null and a blank phone keeps the current one
+        let email = data.email.as_deref().map(normalize_email).transpose()?.flatten();
+        let phone = data.phone.as_deref().map(normalize_phone).transpose()?.flatten();
+        if let Some(ref phone) = phone {
+            self.warn_duplicate_phone(db, phone, Some(id)).await?;
+        }
 
         // Convert boolean values to integers for SQLite compatibility
         let is_active = data.is_active.map(|b| b as i64);
@@ -215,7 +233,7 @@ impl CustomerService {
         )
         .bind(&data.name)
         .bind(email)
-        .bind(&data.phone)
+        .bind(&phone)
         .bind(&data.address)
         .bind(data.credit_limit)
         .bind(&data.customer_type)
@@ -520,7 +538,183 @@ impl CustomerService {
         }))
     }
 
+    // Export everything stored about one customer as a single JSON document
+    pub async fn export_data(&self, db: &Database, customer_id: i64) -> Result<Value> {
+        let customer = self.get_by_id(db, customer_id).await?
+            .ok_or_else(|| anyhow::anyhow!("العميل غير موجود"))?;
+
+        let sales = sqlx::query("SELECT * FROM sales WHERE customer_id = ? ORDER BY created_at ASC")
+            .bind(customer_id)
+            .fetch_all(&db.pool)
+            .await?;
+
+        let sale_items = sqlx::query(r#"
+            SELECT si.*
+            FROM sale_items si
+            JOIN sales s ON si.sale_id = s.id
+            WHERE s.customer_id = ?
+            ORDER BY si.sale_id ASC, si.id ASC
+        "#)
+        .bind(customer_id)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let receipts = sqlx::query("SELECT * FROM customer_receipts WHERE customer_id = ? ORDER BY receipt_date ASC, id ASC")
+            .bind(customer_id)
+            .fetch_all(&db.pool)
+            .await?;
+
+        let debts = sqlx::query("SELECT * FROM debts WHERE customer_id = ? ORDER BY due_date ASC, id ASC")
+            .bind(customer_id)
+            .fetch_all(&db.pool)
+            .await?;
+
+        let installments = sqlx::query("SELECT * FROM installments WHERE customer_id = ? ORDER BY due_date ASC, id ASC")
+            .bind(customer_id)
+            .fetch_all(&db.pool)
+            .await?;
+
+        let delegate_sales = sqlx::query(r#"
+            SELECT ds.*, r.name as delegate_name
+            FROM delegate_sales ds
+            LEFT JOIN representatives r ON ds.delegate_id = r.id
+            WHERE ds.customer_id = ?
+            ORDER BY ds.created_at ASC
+        "#)
+        .bind(customer_id)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let delegate_collections = sqlx::query(r#"
+            SELECT dc.*, r.name as delegate_name
+            FROM delegate_collections dc
+            LEFT JOIN representatives r ON dc.delegate_id = r.id
+            WHERE dc.customer_id = ?
+            ORDER BY dc.collection_date ASC
+        "#)
+        .bind(customer_id)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let representative = match customer.representative_id {
+            Some(representative_id) => sqlx::query("SELECT id, name, phone, email FROM representatives WHERE id = ?")
+                .bind(representative_id)
+                .fetch_optional(&db.pool)
+                .await?
+                .map(|row| crate::utils::sqlite_row_to_json(&row)),
+            None => None,
+        };
+
+        info!("Customer data exported: customer_id={}", customer_id);
+
+        Ok(json!({
+            "exported_at": Utc::now().to_rfc3339(),
+            "customer": customer,
+            "sales": sqlite_rows_to_json(&sales),
+            "sale_items": sqlite_rows_to_json(&sale_items),
+            "receipts": sqlite_rows_to_json(&receipts),
+            "debts": sqlite_rows_to_json(&debts),
+            "installments": sqlite_rows_to_json(&installments),
+            "delegates": {
+                "representative": representative,
+                "delegate_sales": sqlite_rows_to_json(&delegate_sales),
+                "delegate_collections": sqlite_rows_to_json(&delegate_collections),
+            }
+        }))
+    }
+
+    // Erase a customer's personal data while keeping sales, debts and receipts intact.
+    // The row stays (financial rows reference it) but is renamed, stripped and deactivated.
+    pub async fn anonymize(&self, db: &Database, customer_id: i64) -> Result<Customer> {
+        if customer_id == 999 {
+            return Err(anyhow::anyhow!("لا يمكن إخفاء بيانات العميل النقدي"));
+        }
+
+        let customer = self.get_by_id(db, customer_id).await?
+            .ok_or_else(|| anyhow::anyhow!("العميل غير موجود"))?;
+        if customer.anonymized_at.is_some() {
+            return Err(anyhow::anyhow!("تم إخفاء بيانات هذا العميل مسبقاً"));
+        }
+
+        let mut tx = db.pool.begin().await?;
+
+        sqlx::query(r#"
+            UPDATE customers
+            SET name = ?,
+                email = NULL,
+                phone = NULL,
+                address = NULL,
+                tax_number = NULL,
+                is_active = 0,
+                anonymized_at = CURRENT_TIMESTAMP,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+        "#)
+        .bind(format!("عميل محذوف #{}", customer_id))
+        .bind(customer_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Representatives linked to this customer keep their own details, only the link is dropped
+        sqlx::query("UPDATE representatives SET customer_id = NULL WHERE customer_id = ?")
+            .bind(customer_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        info!("Customer {} anonymized", customer_id);
+
+        self.get_by_id(db, customer_id).await?
+            .ok_or_else(|| anyhow::anyhow!("العميل غير موجود"))
+    }
+
     // Update customer balance (add or subtract amount)
+    // Bucket each customer's open debts by days past the sale due date
+    pub async fn get_aging_report(&self, db: &Database, as_of_date: NaiveDate) -> Result<CustomerAgingReport> {
+        let rows = sqlx::query(r#"
+            SELECT c.id as customer_id, c.name as customer_name, c.phone,
+                   d.amount, COALESCE(s.due_date, d.due_date) as due_date
+            FROM debts d
+            JOIN sales s ON d.sale_id = s.id
+            JOIN customers c ON d.customer_id = c.id
+            WHERE d.status != 'paid' AND d.amount > 0
+              AND s.status != 'cancelled'
+              AND s.invoice_date <= ?
+            ORDER BY c.name, due_date
+        "#)
+        .bind(as_of_date)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let mut customers: Vec<CustomerAging> = Vec::new();
+        let mut totals = AgingBuckets::default();
+        for row in rows {
+            let customer_id: i64 = row.get("customer_id");
+            let amount: f64 = row.get("amount");
+            let due_date: NaiveDate = row.get("due_date");
+            let days_overdue = (as_of_date - due_date).num_days();
+
+            if customers.last().map(|c| c.customer_id) != Some(customer_id) {
+                customers.push(CustomerAging {
+                    customer_id,
+                    customer_name: row.get("customer_name"),
+                    phone: row.get("phone"),
+                    buckets: AgingBuckets::default(),
+                });
+            }
+            if let Some(customer) = customers.last_mut() {
+                customer.buckets.add(amount, days_overdue);
+            }
+            totals.add(amount, days_overdue);
+        }
+
+        Ok(CustomerAgingReport {
+            as_of_date,
+            customers,
+            totals,
+        })
+    }
+
     pub async fn update_balance(&self, db: &Database, customer_id: i64, amount: f64, operation: &str) -> Result<Option<Customer>> {
         info!("updateBalance called: customerId={}, amount={}, operation={}", customer_id, amount, operation);
 
@@ -560,4 +754,220 @@ impl CustomerService {
             Ok(None)
         }
     }
+
+    // Rebuild current_balance from source rows: open debts less the unallocated part of receipts.
+    // For repairing balances that drifted before the running updates were in place.
+    pub async fn recompute_balance(&self, db: &Database, customer_id: i64) -> Result<Customer> {
+        let mut tx = db.pool.begin().await?;
+
+        let previous: f64 = sqlx::query("SELECT COALESCE(current_balance, 0) AS balance FROM customers WHERE id = ?")
+            .bind(customer_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| row.get("balance"))
+            .ok_or_else(|| anyhow::anyhow!("العميل غير موجود"))?;
+
+        let open_debts: f64 = sqlx::query(
+            "SELECT COALESCE(SUM(amount), 0.0) AS total FROM debts WHERE customer_id = ? AND status != 'paid'"
+        )
+        .bind(customer_id)
+        .fetch_one(&mut *tx)
+        .await?
+        .get("total");
+
+        let unallocated_receipts: f64 = sqlx::query(r#"
+            SELECT COALESCE(SUM(r.amount - COALESCE(a.allocated, 0)), 0.0) AS total
+            FROM customer_receipts r
+            LEFT JOIN (
+                SELECT receipt_id, SUM(amount) AS allocated
+                FROM customer_receipt_allocations
+                GROUP BY receipt_id
+            ) a ON a.receipt_id = r.id
+            WHERE r.customer_id = ?
+        "#)
+        .bind(customer_id)
+        .fetch_one(&mut *tx)
+        .await?
+        .get("total");
+
+        let balance = open_debts - unallocated_receipts;
+        sqlx::query("UPDATE customers SET current_balance = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(balance)
+            .bind(customer_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        if (previous - balance).abs() > 0.005 {
+            warn!("Customer {} balance corrected from {} to {}", customer_id, previous, balance);
+        }
+
+        self.get_by_id(db, customer_id).await?
+            .ok_or_else(|| anyhow::anyhow!("العميل غير موجود"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::models::AgingBuckets;
+    use std::collections::HashMap;
+    use crate::models::sale::CreateSaleRequest;
+    use crate::services::SaleService;
+
+    async fn add_customer(db: &Database, name: &str, phone: &str) -> i64 {
+        sqlx::query("INSERT INTO customers (name, phone, email, address) VALUES (?, ?, ?, 'Karrada, Baghdad')")
+            .bind(name)
+            .bind(phone)
+            .bind(format!("{}@example.iq", phone))
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    // Unpaid sale of free-text items, so no product stock is involved
+    async fn credit_sale(db: &Database, customer_id: i64, price: f64) -> i64 {
+        let request: CreateSaleRequest = serde_json::from_value(json!({
+            "customer_id": customer_id,
+            "payment_method": "cash",
+            "payment_status": "unpaid",
+            "paid_amount": 0,
+            "due_date": "2026-11-30",
+            "items": [{ "name": "خدمة توصيل", "quantity": 1, "price": price }]
+        })).unwrap();
+        SaleService::new().create(db, request).await.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn export_collects_only_this_customers_records() {
+        let db = TestDatabase::new().await;
+        let zainab = add_customer(&db, "Zainab Kareem", "07712345678").await;
+        let other = add_customer(&db, "Omar Saad", "07898765432").await;
+        let sale_id = credit_sale(&db, zainab, 45000.0).await;
+        credit_sale(&db, other, 12000.0).await;
+        sqlx::query("INSERT INTO customer_receipts (customer_id, sale_id, receipt_no, receipt_date, amount) VALUES (?, ?, 'RC-TEST-1', '2026-10-01', 15000)")
+            .bind(zainab).bind(sale_id).execute(&db.pool).await.unwrap();
+
+        let export = CustomerService::new().export_data(&db, zainab).await.unwrap();
+        assert_eq!(export["customer"]["name"], "Zainab Kareem");
+        assert_eq!(export["sales"].as_array().unwrap().len(), 1);
+        assert_eq!(export["sales"][0]["id"], sale_id);
+        assert_eq!(export["sale_items"][0]["product_name"], "خدمة توصيل");
+        assert_eq!(export["receipts"][0]["amount"], 15000.0);
+        assert_eq!(export["debts"].as_array().unwrap().len(), 1);
+        assert!(export["exported_at"].is_string());
+
+        assert!(CustomerService::new().export_data(&db, other + 100).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn anonymize_strips_personal_data_but_keeps_financials() {
+        let db = TestDatabase::new().await;
+        let service = CustomerService::new();
+        let hussein = add_customer(&db, "Hussein Ali", "07501112233").await;
+        let sale_id = credit_sale(&db, hussein, 80000.0).await;
+
+        let customer = service.anonymize(&db, hussein).await.unwrap();
+        assert_eq!(customer.name, format!("عميل محذوف #{hussein}"));
+        assert!(customer.phone.is_none() && customer.email.is_none() && customer.address.is_none());
+        assert!(customer.anonymized_at.is_some());
+
+        let (sale_customer, net_amount): (i64, f64) = sqlx::query_as("SELECT customer_id, net_amount FROM sales WHERE id = ?")
+            .bind(sale_id).fetch_one(&db.pool).await.unwrap();
+        assert_eq!((sale_customer, net_amount), (hussein, 80000.0));
+        let debt: f64 = sqlx::query_scalar("SELECT amount FROM debts WHERE sale_id = ?").bind(sale_id).fetch_one(&db.pool).await.unwrap();
+        assert_eq!(debt, 80000.0);
+
+        // Neither twice nor the walk-in cash customer
+        assert!(service.anonymize(&db, hussein).await.is_err());
+        assert!(service.anonymize(&db, 999).await.is_err());
+    }
+
+    // Invoice plus its debt row; debts.due_date is only the fallback when the sale has none
+    async fn debt(db: &Database, customer_id: i64, invoice_no: &str, sale_due: Option<&str>, amount: f64, status: &str) {
+        let sale_id = sqlx::query("INSERT INTO sales (customer_id, invoice_no, invoice_date, due_date, total_amount, net_amount) VALUES (?, ?, '2026-03-01', ?, ?, ?)")
+            .bind(customer_id)
+            .bind(invoice_no)
+            .bind(sale_due)
+            .bind(amount)
+            .bind(amount)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        sqlx::query("INSERT INTO debts (customer_id, sale_id, amount, due_date, status) VALUES (?, ?, ?, '2026-09-25', ?)")
+            .bind(customer_id)
+            .bind(sale_id)
+            .bind(amount)
+            .bind(status)
+            .execute(&db.pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn aging_report_buckets_open_debts_by_days_overdue() {
+        let db = TestDatabase::new().await;
+        let layla = add_customer(&db, "Layla Hassan", "07801231234").await;
+        let ahmed = add_customer(&db, "Ahmed Fadhil", "07907654321").await;
+
+        debt(&db, layla, "AG-1", Some("2026-10-15"), 100.0, "unpaid").await; // not yet due
+        debt(&db, layla, "AG-2", Some("2026-09-30"), 200.0, "unpaid").await; // due today
+        debt(&db, layla, "AG-3", Some("2026-09-01"), 300.0, "partial").await; // 29 days
+        debt(&db, layla, "AG-4", Some("2026-08-01"), 400.0, "pending").await; // 60 days
+        debt(&db, ahmed, "AG-5", Some("2026-07-02"), 500.0, "unpaid").await; // 90 days
+        debt(&db, ahmed, "AG-6", Some("2026-01-10"), 600.0, "unpaid").await; // 263 days
+        debt(&db, ahmed, "AG-7", None, 700.0, "unpaid").await; // falls back to the debt's date, 5 days
+        debt(&db, ahmed, "AG-8", Some("2026-01-10"), 800.0, "paid").await;
+
+        let as_of = NaiveDate::from_ymd_opt(2026, 9, 30).unwrap();
+        let report = CustomerService::new().get_aging_report(&db, as_of).await.unwrap();
+
+        let by_name: HashMap<&str, &AgingBuckets> = report.customers.iter()
+            .map(|customer| (customer.customer_name.as_str(), &customer.buckets))
+            .collect();
+        let buckets = |b: &AgingBuckets| (b.current, b.days_1_30, b.days_31_60, b.days_61_90, b.days_over_90, b.total);
+        assert_eq!(buckets(by_name["Layla Hassan"]), (300.0, 300.0, 400.0, 0.0, 0.0, 1000.0));
+        assert_eq!(buckets(by_name["Ahmed Fadhil"]), (0.0, 700.0, 0.0, 500.0, 600.0, 1800.0));
+        assert_eq!(buckets(&report.totals), (300.0, 1000.0, 400.0, 500.0, 600.0, 2800.0));
+    }
+
+    #[tokio::test]
+    async fn balance_tracks_open_debts_less_receipts_and_recompute_agrees() {
+        use crate::services::{CustomerReceiptsService, DebtService};
+        use crate::services::customer_receipts_service::CreateCustomerReceiptRequest;
+        let db = TestDatabase::new().await;
+        let customers = CustomerService::new();
+        let ali = add_customer(&db, "Ali Jaber", "07711112222").await;
+        let balance = || async { customers.get_by_id(&db, ali).await.unwrap().unwrap().current_balance };
+        let from_source_rows = || async {
+            let open_debts: f64 = sqlx::query_scalar("SELECT CAST(COALESCE(SUM(amount), 0) AS REAL) FROM debts WHERE customer_id = ? AND status != 'paid'")
+                .bind(ali).fetch_one(&db.pool).await.unwrap();
+            let receipts: f64 = sqlx::query_scalar("SELECT CAST(COALESCE(SUM(amount), 0) AS REAL) FROM customer_receipts WHERE customer_id = ?")
+                .bind(ali).fetch_one(&db.pool).await.unwrap();
+            open_debts - receipts
+        };
+
+        credit_sale(&db, ali, 50000.0).await;
+        credit_sale(&db, ali, 20000.0).await;
+        assert_eq!(balance().await, 70000.0);
+
+        // Settles the first debt and leaves 10000 on the second
+        DebtService::new().apply_payment(&db, ali, 60000.0, "cash", None).await.unwrap();
+        assert_eq!(balance().await, 10000.0);
+
+        CustomerReceiptsService::new().create_receipt(&db, CreateCustomerReceiptRequest {
+            customer_id: ali,
+            sale_id: None,
+            receipt_date: Some("2026-10-15".to_string()),
+            amount: 4000.0,
+            payment_method: "cash".to_string(),
+            reference_number: None,
+            notes: Some("دفعة مقدمة".to_string()),
+            money_box_id: None,
+        }, 1).await.unwrap();
+        assert_eq!(balance().await, 6000.0);
+        assert_eq!(from_source_rows().await, 6000.0);
+
+        // A drifted balance is rebuilt from the same rows
+        sqlx::query("UPDATE customers SET current_balance = 123456 WHERE id = ?").bind(ali).execute(&db.pool).await.unwrap();
+        let recomputed = customers.recompute_balance(&db, ali).await.unwrap();
+        assert_eq!(recomputed.current_balance, 6000.0);
+    }
 }
@@ -2,16 +2,37 @@ use anyhow::Result;
 use crate::database::Database;
 use crate::models::{
     BackupInfo, CreateBackupResponse, RestoreBackupResponse, DatabaseResetResponse,
-    FixMenuItemsResponse, get_database_message
+    FixMenuItemsResponse, TableSchema, ColumnSchema, ForeignKeySchema, PoolStats, OptimizeReport,
+    get_database_message
 };
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use chrono::{Utc, DateTime};
 use tracing::{info, warn, error};
 use dirs;
 use sqlx::Row;
 
 const MAX_BACKUPS: usize = 5;
+// Pre-operation snapshots live in their own folder so regular backup rotation never removes them
+const MAX_RECOVERY_BACKUPS: usize = 10;
+
+// Set while an optimize runs, so the endpoint and the nightly run never overlap
+static OPTIMIZE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// Optimize requested while another one is still running; routes downcast to answer 409
+#[derive(Debug)]
+pub struct OptimizeInProgress;
+
+impl fmt::Display for OptimizeInProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(get_database_message("optimize_in_progress"))
+    }
+}
+
+impl std::error::Error for OptimizeInProgress {}
 
 #[derive(Clone)]
 pub struct DatabaseService;
@@ -93,6 +114,93 @@ impl DatabaseService {
         Ok(true)
     }
 
+    // Recovery snapshot directory (~/.urcash/backups/recovery)
+    fn get_recovery_dir(&self) -> Result<PathBuf> {
+        let recovery_dir = self.get_backup_dir()?.join("recovery");
+        if !recovery_dir.exists() {
+            fs::create_dir_all(&recovery_dir)?;
+        }
+        Ok(recovery_dir)
+    }
+
+    // Auto-backup before risky operations is on unless SAFE_OPERATION_BACKUPS=false
+    fn safe_operation_backups_enabled(&self) -> bool {
+        std::env::var("SAFE_OPERATION_BACKUPS")
+            .map(|v| !matches!(v.trim().to_lowercase().as_str(), "false" | "0" | "off"))
+            .unwrap_or(true)
+    }
+
+    // Snapshot the live database into the recovery folder, pruning the oldest snapshots
+    async fn create_recovery_backup(&self, db: &Database, operation: &str) -> Result<PathBuf> {
+        let recovery_dir = self.get_recovery_dir()?;
+        let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S-%3fZ").to_string();
+        let backup_path = recovery_dir.join(format!("pre-{}-{}.db", operation, timestamp));
+
+        // VACUUM INTO gives a consistent copy even with pending WAL frames
+        sqlx::query("VACUUM INTO ?")
+            .bind(backup_path.to_string_lossy().to_string())
+            .execute(&db.pool)
+            .await?;
+
+        let mut snapshots: Vec<PathBuf> = fs::read_dir(&recovery_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("db"))
+            .collect();
+        if snapshots.len() > MAX_RECOVERY_BACKUPS {
+            snapshots.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+            for old in &snapshots[..snapshots.len() - MAX_RECOVERY_BACKUPS] {
+                if let Err(e) = fs::remove_file(old) {
+                    warn!("Failed to delete old recovery backup {:?}: {}", old, e);
+                }
+            }
+        }
+
+        Ok(backup_path)
+    }
+
+    // Put a recovery snapshot back in place of the database file
+    fn restore_recovery_backup(&self, backup_path: &Path) -> Result<()> {
+        let db_path = self.get_database_path()?;
+        for suffix in ["-wal", "-shm"] {
+            let sidecar = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+            if sidecar.exists() {
+                fs::remove_file(&sidecar)?;
+            }
+        }
+        fs::copy(backup_path, &db_path)?;
+        Ok(())
+    }
+
+    // Run a destructive operation behind a named recovery backup. The snapshot is always kept;
+    // if the operation fails after closing the pool (file swap/delete), the snapshot is copied back.
+    pub async fn safe_operation<T, F, Fut>(&self, db: &Database, name: &str, operation: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        if !self.safe_operation_backups_enabled() {
+            return operation().await;
+        }
+
+        let backup_path = self.create_recovery_backup(db, name).await
+            .map_err(|e| anyhow::anyhow!("Failed to create recovery backup before {}: {}", name, e))?;
+        info!("Recovery backup created before {}: {:?}", name, backup_path);
+
+        match operation().await {
+            Ok(result) => Ok(result),
+            Err(err) => {
+                error!("Operation {} failed, recovery backup kept at {:?}: {}", name, backup_path, err);
+                if db.pool.is_closed() {
+                    match self.restore_recovery_backup(&backup_path) {
+                        Ok(_) => info!("Database rolled back to recovery backup {:?}", backup_path),
+                        Err(e) => error!("Failed to roll back to recovery backup {:?}: {}", backup_path, e),
+                    }
+                }
+                Err(err)
+            }
+        }
+    }
+
     // Get existing backups
     async fn get_existing_backups(&self) -> Result<Vec<BackupInfo>> {
         let backup_dir = self.get_backup_dir()?;
@@ -329,6 +437,148 @@ impl DatabaseService {
     }
 
     // Helper method to handle database errors (matching Node.js error handling)
+    // Connection pool usage; in_use counts connections currently checked out
+    pub fn pool_stats(&self, db: &Database) -> PoolStats {
+        let options = db.pool.options();
+        let size = db.pool.size();
+        let idle = db.pool.num_idle();
+
+        PoolStats {
+            size,
+            idle,
+            in_use: size.saturating_sub(idle as u32),
+            max_connections: options.get_max_connections(),
+            idle_timeout_secs: options.get_idle_timeout().map(|timeout| timeout.as_secs()),
+            max_lifetime_secs: options.get_max_lifetime().map(|lifetime| lifetime.as_secs()),
+        }
+    }
+
+    // Bytes on disk for the open database: the main file plus its WAL
+    async fn database_size(db: &Database) -> Result<u64> {
+        let file: String = sqlx::query("SELECT file FROM pragma_database_list WHERE name = 'main'")
+            .fetch_one(&db.pool)
+            .await?
+            .get("file");
+        let size = |path: String| fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        Ok(size(file.clone()) + size(format!("{}-wal", file)))
+    }
+
+    // Run Database::optimize on its own task and report the size change. The task is not tied to the
+    // request, so a client that gives up cannot cancel VACUUM half way. In WAL mode readers keep working
+    // from their snapshot throughout; writers wait on the busy timeout while VACUUM holds the write lock.
+    pub async fn optimize(&self, db: &Database) -> Result<OptimizeReport> {
+        if OPTIMIZE_RUNNING.swap(true, Ordering::AcqRel) {
+            return Err(OptimizeInProgress.into());
+        }
+
+        let db = db.clone();
+        let task = tokio::spawn(async move {
+            let result = async {
+                let size_before = Self::database_size(&db).await?;
+                let started = Instant::now();
+                db.optimize().await?;
+                // VACUUM writes the rebuilt file through the WAL; the checkpoint folds it back so the size shows
+                sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&db.pool).await?;
+                let size_after = Self::database_size(&db).await?;
+
+                Ok::<_, anyhow::Error>(OptimizeReport {
+                    size_before,
+                    size_after,
+                    freed_bytes: size_before as i64 - size_after as i64,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                })
+            }.await;
+            OPTIMIZE_RUNNING.store(false, Ordering::Release);
+            result
+        });
+
+        let report = task.await??;
+        info!(
+            "Database optimized in {}ms: {} -> {} bytes",
+            report.duration_ms, report.size_before, report.size_after
+        );
+        Ok(report)
+    }
+
+    // Nightly optimize at DB_OPTIMIZE_HOUR (0-23, local time); unset leaves maintenance to startup and the endpoint
+    pub fn start_optimize_scheduler(&self, db: Database) {
+        let hour = match std::env::var("DB_OPTIMIZE_HOUR").ok().and_then(|hour| hour.trim().parse::<u32>().ok()) {
+            Some(hour) if hour < 24 => hour,
+            Some(_) | None => return,
+        };
+        info!("Nightly database optimize scheduled at {:02}:00", hour);
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(5 * 60));
+            let mut last_run: Option<chrono::NaiveDate> = None;
+            loop {
+                interval.tick().await;
+                let now = chrono::Local::now();
+                let today = now.date_naive();
+                if chrono::Timelike::hour(&now) != hour || last_run == Some(today) {
+                    continue;
+                }
+                last_run = Some(today);
+                if let Err(err) = service.optimize(&db).await {
+                    error!("Nightly database optimize failed: {}", err);
+                }
+            }
+        });
+    }
+
+    // Tables with their columns and foreign keys, read from sqlite_master and the table PRAGMAs
+    pub async fn schema(&self, db: &Database) -> Result<Vec<TableSchema>> {
+        let tables: Vec<String> = sqlx::query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+        )
+        .fetch_all(&db.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.get("name"))
+        .collect();
+
+        let mut schema = Vec::with_capacity(tables.len());
+        for table in tables {
+            // PRAGMA arguments can't be bound; names come from sqlite_master and are quoted
+            let quoted = format!("\"{}\"", table.replace('"', "\"\""));
+
+            let columns = sqlx::query(&format!("PRAGMA table_info({})", quoted))
+                .fetch_all(&db.pool)
+                .await?
+                .into_iter()
+                .map(|row| ColumnSchema {
+                    name: row.get("name"),
+                    data_type: row.get("type"),
+                    nullable: row.get::<i64, _>("notnull") == 0 && row.get::<i64, _>("pk") == 0,
+                    default_value: row.get("dflt_value"),
+                    primary_key: row.get::<i64, _>("pk") > 0,
+                })
+                .collect();
+
+            let foreign_keys = sqlx::query(&format!("PRAGMA foreign_key_list({})", quoted))
+                .fetch_all(&db.pool)
+                .await?
+                .into_iter()
+                .map(|row| ForeignKeySchema {
+                    column: row.get("from"),
+                    references_table: row.get("table"),
+                    references_column: row.get("to"),
+                    on_delete: row.get("on_delete"),
+                    on_update: row.get("on_update"),
+                })
+                .collect();
+
+            schema.push(TableSchema {
+                name: table,
+                columns,
+                foreign_keys,
+            });
+        }
+
+        Ok(schema)
+    }
+
     pub fn handle_database_error(&self, error: &anyhow::Error) -> (u16, String) {
         let error_message = error.to_string();
         
@@ -351,4 +601,96 @@ impl DatabaseService {
         // Default database error handling
         (500, get_database_message("backup_failed").to_string())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    fn snapshots(prefix: &str) -> Vec<PathBuf> {
+        let dir = DatabaseService::new().get_recovery_dir().unwrap();
+        fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with(prefix))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn risky_operations_leave_a_recovery_snapshot() {
+        let db = TestDatabase::new().await;
+        let service = DatabaseService::new();
+        sqlx::query("INSERT INTO customers (name, phone) VALUES ('Ali Hassan', '07711112222')").execute(&db.pool).await.unwrap();
+
+        let wiped = service.safe_operation(&db, "wipe", || async {
+            Ok(sqlx::query("DELETE FROM customers WHERE name = 'Ali Hassan'").execute(&db.pool).await?.rows_affected())
+        }).await.unwrap();
+        assert_eq!(wiped, 1);
+
+        // The snapshot still holds the row the operation removed
+        let taken = snapshots("pre-wipe-");
+        assert_eq!(taken.len(), 1);
+        let copy = sqlx::SqlitePool::connect(&format!("sqlite:{}", taken[0].display())).await.unwrap();
+        let kept: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM customers WHERE name = 'Ali Hassan'").fetch_one(&copy).await.unwrap();
+        assert_eq!(kept, 1);
+        copy.close().await;
+
+        // A failing operation reports its error and keeps its snapshot
+        let failed: Result<()> = service.safe_operation(&db, "broken", || async { Err(anyhow::anyhow!("disk full")) }).await;
+        assert_eq!(failed.unwrap_err().to_string(), "disk full");
+        assert_eq!(snapshots("pre-broken-").len(), 1);
+
+        for _ in 0..MAX_RECOVERY_BACKUPS {
+            service.safe_operation(&db, "rotate", || async { Ok(()) }).await.unwrap();
+        }
+        assert_eq!(snapshots("pre-").len(), MAX_RECOVERY_BACKUPS);
+    }
+
+    #[tokio::test]
+    async fn schema_reports_product_columns_and_foreign_keys() {
+        let db = TestDatabase::new().await;
+        let schema = DatabaseService::new().schema(&db).await.unwrap();
+        assert!(schema.iter().all(|table| !table.name.starts_with("sqlite_")));
+
+        let products = schema.iter().find(|table| table.name == "products").expect("products table");
+        let column = |name: &str| products.columns.iter().find(|c| c.name == name).unwrap_or_else(|| panic!("no column {name}"));
+        let described = |name: &str| {
+            let c = column(name);
+            (c.data_type.as_str(), c.nullable, c.primary_key)
+        };
+        assert_eq!(described("id"), ("INTEGER", false, true));
+        assert_eq!(described("name"), ("TEXT", false, false));
+        assert_eq!(described("selling_price"), ("REAL", false, false));
+        assert_eq!(described("current_stock"), ("INTEGER", false, false));
+        assert_eq!(described("description"), ("TEXT", true, false));
+        assert_eq!(column("current_stock").default_value.as_deref(), Some("0"));
+
+        let category = products.foreign_keys.iter().find(|fk| fk.column == "category_id").expect("category foreign key");
+        assert_eq!(category.references_table, "categories");
+        assert_eq!(category.references_column.as_deref(), Some("id"));
+        assert_eq!(category.on_delete, "SET NULL");
+    }
+
+    #[tokio::test]
+    async fn pool_stats_count_a_connection_held_by_a_transaction() {
+        let db = TestDatabase::new().await;
+        let service = DatabaseService::new();
+
+        let tx = db.pool.begin().await.unwrap();
+        let held = service.pool_stats(&db);
+        assert!(held.in_use >= 1);
+        assert_eq!(held.in_use, held.size - held.idle as u32);
+
+        // The pool takes the connection back on a background task, so give it a moment
+        tx.rollback().await.unwrap();
+        let mut released = service.pool_stats(&db);
+        for _ in 0..50 {
+            if released.in_use < held.in_use {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            released = service.pool_stats(&db);
+        }
+        assert!(released.in_use < held.in_use);
+        assert!(released.max_connections >= released.size);
+    }
+}
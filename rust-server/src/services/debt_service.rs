@@ -1,9 +1,10 @@
 use anyhow::Result;
 use crate::database::Database;
+use crate::services::SequenceService;
 use crate::models::{
     Debt, DebtDetail, CustomerWithDebts, CustomerDebtInfo, DebtQuery, UpdateDebtRequest,
     RepayDebtRequest, RepayDebtLegacyRequest, DebtStats, DebtListResponse, PaginationInfo,
-    AppliedPayment, RepayDebtResponse, DebtPaginationInfo
+    AppliedPayment, RepayDebtResponse, DebtPaginationInfo, ApplyPaymentResult, DebtAllocation
 };
 use sqlx::{Row, SqlitePool};
 use tracing::{info, warn, error};
@@ -628,6 +629,171 @@ impl DebtService {
         })
     }
 
+    // Spread a lump-sum payment over the customer's open debts, earliest due date first
+    pub async fn apply_payment(
+        &self,
+        db: &Database,
+        customer_id: i64,
+        amount: f64,
+        payment_method: &str,
+        money_box_id: Option<i64>,
+    ) -> Result<ApplyPaymentResult> {
+        if amount <= 0.0 {
+            return Err(anyhow::anyhow!("مبلغ الدفع يجب أن يكون أكبر من صفر"));
+        }
+        if !["cash", "card", "bank_transfer", "check"].contains(&payment_method) {
+            return Err(anyhow::anyhow!("طريقة الدفع غير صحيحة"));
+        }
+
+        let mut tx = db.pool.begin().await?;
+
+        let customer = sqlx::query("SELECT COALESCE(current_balance, 0) AS current_balance, credit_limit FROM customers WHERE id = ?")
+            .bind(customer_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("العميل غير موجود"))?;
+
+        // Any excess leaves the customer in credit, which may not go past their credit limit
+        let current_balance: f64 = customer.get("current_balance");
+        if let Some(credit_limit) = customer.get::<Option<f64>, _>("credit_limit") {
+            if current_balance - amount < -credit_limit - 0.005 {
+                return Err(anyhow::anyhow!(
+                    "مبلغ الدفع ({:.2}) يتجاوز المستحق على العميل ({:.2}) بأكثر من حد الائتمان ({:.2})",
+                    amount, current_balance.max(0.0), credit_limit
+                ));
+            }
+        }
+
+        let debts = sqlx::query(r#"
+            SELECT d.id, d.sale_id, d.amount, s.invoice_no, s.net_amount, s.paid_amount
+            FROM debts d
+            JOIN sales s ON s.id = d.sale_id
+            WHERE d.customer_id = ? AND d.amount > 0 AND d.status != 'paid'
+            ORDER BY d.due_date ASC, d.id ASC
+        "#)
+        .bind(customer_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut left = amount;
+        let mut allocations = Vec::new();
+        for debt in debts {
+            if left <= 0.005 {
+                break;
+            }
+            let debt_id: i64 = debt.get("id");
+            let sale_id: i64 = debt.get("sale_id");
+            let debt_amount: f64 = debt.get("amount");
+            let net_amount: f64 = debt.get("net_amount");
+            let sale_paid: f64 = debt.get("paid_amount");
+
+            let part = left.min(debt_amount);
+            left -= part;
+            let remaining_amount = (debt_amount - part).max(0.0);
+            let status = if remaining_amount <= 0.005 { "paid" } else { "partial" };
+
+            let new_sale_paid = (sale_paid + part).min(net_amount);
+            sqlx::query("UPDATE sales SET paid_amount = ?, remaining_amount = ?, payment_status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(new_sale_paid)
+                .bind(net_amount - new_sale_paid)
+                .bind(if new_sale_paid >= net_amount - 0.005 { "paid" } else { "partial" })
+                .bind(sale_id)
+                .execute(&mut *tx)
+                .await?;
+
+            // Settled debts are removed, as in repay_debt
+            if status == "paid" {
+                sqlx::query("DELETE FROM debts WHERE id = ?")
+                    .bind(debt_id)
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query("UPDATE debts SET amount = ?, status = 'partial', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(remaining_amount)
+                    .bind(debt_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            allocations.push(DebtAllocation {
+                debt_id,
+                sale_id,
+                invoice_no: debt.get("invoice_no"),
+                amount: part,
+                remaining_amount,
+                status: status.to_string(),
+            });
+        }
+
+        let unallocated = if left > 0.005 { left } else { 0.0 };
+
+        // The whole payment comes off what the customer owes; any excess leaves them in credit
+        sqlx::query("UPDATE customers SET current_balance = COALESCE(current_balance, 0) - ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(amount)
+            .bind(customer_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Record the excess as an unallocated receipt so the credit survives a balance recompute
+        if unallocated > 0.0 {
+            let receipt_number = SequenceService::next(&mut tx, SequenceService::CUSTOMER_RECEIPT).await?;
+            sqlx::query(
+                "INSERT INTO customer_receipts (
+                    receipt_no, customer_id, sale_id, receipt_date, amount,
+                    payment_method, notes, created_at, updated_at, money_box_id
+                ) VALUES (?, ?, NULL, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?)"
+            )
+            .bind(&receipt_number)
+            .bind(customer_id)
+            .bind(Utc::now().naive_utc())
+            .bind(unallocated)
+            .bind(payment_method)
+            .bind("رصيد دائن من تسديد الديون")
+            .bind(money_box_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        match money_box_id {
+            Some(box_id) => {
+                let balance: f64 = sqlx::query("SELECT amount FROM money_boxes WHERE id = ?")
+                    .bind(box_id)
+                    .fetch_optional(&mut *tx)
+                    .await?
+                    .map(|row| row.get("amount"))
+                    .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+
+                sqlx::query("UPDATE money_boxes SET amount = amount + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(amount)
+                    .bind(box_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query(r#"
+                    INSERT INTO money_box_transactions (box_id, type, amount, balance_after, notes, created_at)
+                    VALUES (?, 'deposit', ?, ?, ?, CURRENT_TIMESTAMP)
+                "#)
+                .bind(box_id)
+                .bind(amount)
+                .bind(balance + amount)
+                .bind(format!("تسديد ديون العميل رقم {}", customer_id))
+                .execute(&mut *tx)
+                .await?;
+            }
+            None => warn!("Debt payment of {} for customer {} recorded without a money box", amount, customer_id),
+        }
+
+        tx.commit().await?;
+        info!("Applied payment of {} to {} debts of customer {} ({} unallocated)", amount, allocations.len(), customer_id, unallocated);
+
+        Ok(ApplyPaymentResult {
+            customer_id,
+            total_paid: amount,
+            allocations,
+            unallocated,
+        })
+    }
+
     // Legacy repay debt method
     pub async fn repay_debt_legacy(&self, db: &Database, payload: RepayDebtLegacyRequest) -> Result<Value> {
         // This is a simplified legacy implementation
@@ -638,3 +804,89 @@ impl DebtService {
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::models::CreateSaleRequest;
+    use crate::services::SaleService;
+    use serde_json::json;
+
+    async fn sale_on_credit(db: &Database, customer_id: i64, due_date: &str) -> i64 {
+        let request: CreateSaleRequest = serde_json::from_value(json!({
+            "customer_id": customer_id,
+            "invoice_date": "2026-06-01",
+            "due_date": due_date,
+            "payment_method": "cash",
+            "payment_status": "unpaid",
+            "paid_amount": 0,
+            "items": [{ "name": "صيانة مولدة", "quantity": 1, "price": 40000.0 }]
+        })).unwrap();
+        SaleService::new().create(db, request).await.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn lump_sum_settles_the_oldest_debt_and_half_the_next() {
+        let db = TestDatabase::new().await;
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone) VALUES ('Mustafa Jabbar', '07735550101')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        let later = sale_on_credit(&db, customer_id, "2026-08-01").await;
+        let older = sale_on_credit(&db, customer_id, "2026-07-01").await;
+        let untouched = sale_on_credit(&db, customer_id, "2026-09-01").await;
+
+        let result = DebtService::new().apply_payment(&db, customer_id, 60000.0, "cash", Some(1)).await.unwrap();
+
+        let applied: Vec<(i64, f64, f64, &str)> = result.allocations.iter()
+            .map(|a| (a.sale_id, a.amount, a.remaining_amount, a.status.as_str()))
+            .collect();
+        assert_eq!(applied, vec![(older, 40000.0, 0.0, "paid"), (later, 20000.0, 20000.0, "partial")]);
+        assert_eq!((result.total_paid, result.unallocated), (60000.0, 0.0));
+
+        let sale = |id: i64| {
+            sqlx::query_as::<_, (f64, String, f64)>("SELECT CAST(paid_amount AS REAL), payment_status, CAST(remaining_amount AS REAL) FROM sales WHERE id = ?")
+                .bind(id)
+                .fetch_one(&db.pool)
+        };
+        assert_eq!(sale(older).await.unwrap(), (40000.0, "paid".to_string(), 0.0));
+        assert_eq!(sale(later).await.unwrap(), (20000.0, "partial".to_string(), 20000.0));
+        let (paid, status, _) = sale(untouched).await.unwrap();
+        assert_eq!((paid, status.as_str()), (0.0, "unpaid"));
+
+        let open_debts: Vec<i64> = sqlx::query_scalar("SELECT sale_id FROM debts ORDER BY due_date").fetch_all(&db.pool).await.unwrap();
+        assert_eq!(open_debts, vec![later, untouched]);
+        let deposits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM money_box_transactions WHERE box_id = 1 AND type = 'deposit'")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(deposits, 1);
+    }
+
+    #[tokio::test]
+    async fn overpaying_past_the_credit_limit_is_refused_before_anything_moves() {
+        let db = TestDatabase::new().await;
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone) VALUES ('Zainab Kareem', '07735550102')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        let sale_id = sale_on_credit(&db, customer_id, "2026-07-01").await;
+        // No credit allowed from here on: the customer may settle what is owed but not pay ahead
+        sqlx::query("UPDATE customers SET credit_limit = 0 WHERE id = ?")
+            .bind(customer_id)
+            .execute(&db.pool).await.unwrap();
+
+        let err = DebtService::new().apply_payment(&db, customer_id, 50000.0, "cash", Some(1)).await.unwrap_err();
+        assert!(err.to_string().contains("حد الائتمان"), "{err}");
+
+        let debt: f64 = sqlx::query_scalar("SELECT amount FROM debts WHERE sale_id = ?")
+            .bind(sale_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(debt, 40000.0);
+        let balance: f64 = sqlx::query_scalar("SELECT current_balance FROM customers WHERE id = ?")
+            .bind(customer_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(balance, 40000.0);
+
+        // Paying exactly what is owed is still fine
+        let result = DebtService::new().apply_payment(&db, customer_id, 40000.0, "cash", Some(1)).await.unwrap();
+        assert_eq!((result.total_paid, result.unallocated), (40000.0, 0.0));
+    }
+}
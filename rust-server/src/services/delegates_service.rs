@@ -7,7 +7,7 @@ use crate::models::delegate::{
     BulkAssignCustomersRequest, CommissionQuery, PayCommissionRequest, DelegateListResponse,
     DelegateDashboard, DelegateAnalytics, CommissionHistoryQuery, DashboardQuery,
     TopDelegatesQuery, PerformanceQuery, SetTargetsRequest, DelegateCustomersQuery,
-    CreateCommissionPaymentRequest, GeneratePerformanceReportRequest
+    CreateCommissionPaymentRequest, GeneratePerformanceReportRequest, DelegateCommissionCalculation
 };
 use crate::models::PaginationInfo;
 use sqlx::{Row, SqlitePool};
@@ -440,32 +440,108 @@ impl DelegatesService {
         Ok(())
     }
 
-    // Calculate commission
-    pub async fn calculate_commission(&self, db: &Database, delegate_id: i64, query: &CommissionQuery) -> Result<Value> {
-        let sql = r#"
-            SELECT 
-                COALESCE(SUM(ds.total_amount), 0) as total_sales,
-                COALESCE(SUM(ds.commission_amount), 0) as total_commission,
+    // Period totals and commission from the representative's settings:
+    // percentage of sales, or a fixed amount per sale
+    async fn commission_totals(&self, db: &Database, delegate_id: i64, period_start: NaiveDate, period_end: NaiveDate) -> Result<(f64, f64, i64, String)> {
+        let delegate = sqlx::query("SELECT commission_type, commission_rate, commission_amount FROM representatives WHERE id = ?")
+            .bind(delegate_id)
+            .fetch_optional(&db.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("المندوب غير موجود"))?;
+        let commission_type: String = delegate.get::<Option<String>, _>("commission_type").unwrap_or_else(|| "percentage".to_string());
+        let commission_rate: f64 = delegate.get::<Option<f64>, _>("commission_rate").unwrap_or(0.0);
+        let commission_amount: f64 = delegate.get::<Option<f64>, _>("commission_amount").unwrap_or(0.0);
+
+        let result = sqlx::query(r#"
+            SELECT
+                COALESCE(SUM(ds.total_amount), 0.0) as total_sales,
                 COUNT(ds.id) as sales_count
             FROM delegate_sales ds
-            WHERE ds.delegate_id = ? 
+            WHERE ds.delegate_id = ?
                 AND DATE(ds.created_at) BETWEEN ? AND ?
-        "#;
+        "#)
+        .bind(delegate_id)
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_one(&db.pool)
+        .await?;
+        let total_sales: f64 = result.get("total_sales");
+        let sales_count: i64 = result.get("sales_count");
 
-        let result = sqlx::query(sql)
-            .bind(delegate_id)
-            .bind(query.period_start)
-            .bind(query.period_end)
+        let total_commission = if commission_type == "fixed" {
+            commission_amount * sales_count as f64
+        } else {
+            total_sales * commission_rate / 100.0
+        };
+
+        Ok((total_sales, (total_commission * 100.0).round() / 100.0, sales_count, commission_type))
+    }
+
+    // Calculate and store a period's commission; an already calculated period is returned unless recompute is set
+    pub async fn calculate_commission(&self, db: &Database, delegate_id: i64, period_start: NaiveDate, period_end: NaiveDate, recompute: bool) -> Result<DelegateCommissionCalculation> {
+        if period_start > period_end {
+            return Err(anyhow::anyhow!("تاريخ البداية يجب أن يكون قبل تاريخ النهاية"));
+        }
+
+        let (total_sales, total_commission, sales_count, commission_type) =
+            self.commission_totals(db, delegate_id, period_start, period_end).await?;
+
+        // pay_commission may have opened the period row with zero placeholders
+        let existing = sqlx::query_as::<_, DelegateCommission>(
+            "SELECT * FROM delegate_commissions WHERE delegate_id = ? AND period_start = ? AND period_end = ? ORDER BY id LIMIT 1"
+        )
+        .bind(delegate_id)
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_optional(&db.pool)
+        .await?;
+
+        let id = match existing {
+            Some(row) if !recompute && (row.total_sales != 0.0 || row.total_commission != 0.0) => {
+                return Ok(DelegateCommissionCalculation {
+                    commission: row,
+                    commission_type,
+                    sales_count: None,
+                    recalculated: false,
+                });
+            }
+            Some(row) => {
+                sqlx::query("UPDATE delegate_commissions SET total_sales = ?, total_commission = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(total_sales)
+                    .bind(total_commission)
+                    .bind(row.id)
+                    .execute(&db.pool)
+                    .await?;
+                row.id
+            }
+            None => {
+                sqlx::query(r#"
+                    INSERT INTO delegate_commissions (delegate_id, period_start, period_end, total_sales, total_commission, created_at, updated_at)
+                    VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                "#)
+                .bind(delegate_id)
+                .bind(period_start)
+                .bind(period_end)
+                .bind(total_sales)
+                .bind(total_commission)
+                .execute(&db.pool)
+                .await?
+                .last_insert_rowid()
+            }
+        };
+
+        let commission = sqlx::query_as::<_, DelegateCommission>("SELECT * FROM delegate_commissions WHERE id = ?")
+            .bind(id)
             .fetch_one(&db.pool)
             .await?;
+        info!("Commission for delegate {} ({} - {}): {}", delegate_id, period_start, period_end, total_commission);
 
-        Ok(serde_json::json!({
-            "total_sales": result.get::<f64, _>("total_sales"),
-            "total_commission": result.get::<f64, _>("total_commission"),
-            "sales_count": result.get::<i64, _>("sales_count"),
-            "period_start": query.period_start,
-            "period_end": query.period_end
-        }))
+        Ok(DelegateCommissionCalculation {
+            commission,
+            commission_type,
+            sales_count: Some(sales_count),
+            recalculated: true,
+        })
     }
 
     // Get delegate dashboard
@@ -633,11 +709,19 @@ impl DelegatesService {
     }
 
     pub async fn get_commission_report(&self, db: &Database, delegate_id: i64) -> Result<Value> {
-        let query = CommissionQuery {
-            period_start: chrono::Utc::now().date_naive() - chrono::Duration::days(30),
-            period_end: chrono::Utc::now().date_naive(),
-        };
-        self.calculate_commission(db, delegate_id, &query).await
+        // Rolling 30-day preview; not stored since the window moves daily
+        let period_start = chrono::Utc::now().date_naive() - chrono::Duration::days(30);
+        let period_end = chrono::Utc::now().date_naive();
+        let (total_sales, total_commission, sales_count, _) =
+            self.commission_totals(db, delegate_id, period_start, period_end).await?;
+
+        Ok(serde_json::json!({
+            "total_sales": total_sales,
+            "total_commission": total_commission,
+            "sales_count": sales_count,
+            "period_start": period_start,
+            "period_end": period_end
+        }))
     }
 
     pub async fn get_performance(&self, db: &Database, delegate_id: i64) -> Result<Value> {
@@ -751,7 +835,7 @@ impl DelegatesService {
                 "total_sales": row.get::<f64, _>("total_sales"),
                 "total_commission": row.get::<f64, _>("total_commission"),
                 "payment_amount": row.get::<f64, _>("payment_amount"),
-                "payment_date": row.get::<NaiveDate, _>("payment_date"),
+                "payment_date": row.get::<Option<NaiveDate>, _>("payment_date"),
                 "payment_method": row.get::<Option<String>, _>("payment_method"),
                 "notes": row.get::<Option<String>, _>("notes"),
                 "created_at": row.get::<NaiveDateTime, _>("created_at"),
@@ -860,4 +944,84 @@ impl DelegatesService {
             "message": "تم إنشاء تقارير الأداء بنجاح"
         }))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    async fn representative(db: &Database, name: &str, commission_type: &str, rate: f64, amount: f64) -> i64 {
+        sqlx::query("INSERT INTO representatives (name, commission_type, commission_rate, commission_amount) VALUES (?, ?, ?, ?)")
+            .bind(name)
+            .bind(commission_type)
+            .bind(rate)
+            .bind(amount)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    // Delegate sale on `day` backed by a real invoice row
+    async fn delegate_sale(db: &Database, delegate_id: i64, total: f64, day: &str) {
+        let sale_id = sqlx::query("INSERT INTO sales (customer_id, invoice_no, invoice_date, total_amount, net_amount) VALUES (999, ?, ?, ?, ?)")
+            .bind(format!("DL-{delegate_id}-{day}-{total}"))
+            .bind(day)
+            .bind(total)
+            .bind(total)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        sqlx::query("INSERT INTO delegate_sales (delegate_id, customer_id, sale_id, total_amount, created_at) VALUES (?, 999, ?, ?, ?)")
+            .bind(delegate_id)
+            .bind(sale_id)
+            .bind(total)
+            .bind(format!("{day} 10:30:00"))
+            .execute(&db.pool).await.unwrap();
+    }
+
+    fn may() -> (NaiveDate, NaiveDate) {
+        (NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(), NaiveDate::from_ymd_opt(2026, 5, 31).unwrap())
+    }
+
+    #[tokio::test]
+    async fn percentage_commission_is_a_share_of_period_sales() {
+        let db = TestDatabase::new().await;
+        let karim = representative(&db, "Karim", "percentage", 2.5, 0.0).await;
+        delegate_sale(&db, karim, 12000.0, "2026-05-03").await;
+        delegate_sale(&db, karim, 8000.0, "2026-05-31").await;
+        delegate_sale(&db, karim, 50000.0, "2026-06-01").await;
+
+        let (start, end) = may();
+        let result = DelegatesService::new().calculate_commission(&db, karim, start, end, false).await.unwrap();
+        assert_eq!(result.commission_type, "percentage");
+        assert_eq!(result.sales_count, Some(2));
+        assert_eq!((result.commission.total_sales, result.commission.total_commission), (20000.0, 500.0));
+        assert!(result.recalculated);
+    }
+
+    #[tokio::test]
+    async fn fixed_commission_pays_per_sale_and_is_kept_unless_recomputed() {
+        let db = TestDatabase::new().await;
+        let service = DelegatesService::new();
+        let huda = representative(&db, "Huda", "fixed", 0.0, 750.0).await;
+        delegate_sale(&db, huda, 3000.0, "2026-05-10").await;
+        delegate_sale(&db, huda, 4000.0, "2026-05-11").await;
+        delegate_sale(&db, huda, 9000.0, "2026-05-20").await;
+
+        let (start, end) = may();
+        let first = service.calculate_commission(&db, huda, start, end, false).await.unwrap();
+        assert_eq!((first.commission.total_sales, first.commission.total_commission), (16000.0, 2250.0));
+
+        // A late sale doesn't change the stored period until asked to
+        delegate_sale(&db, huda, 1000.0, "2026-05-28").await;
+        let again = service.calculate_commission(&db, huda, start, end, false).await.unwrap();
+        assert!(!again.recalculated);
+        assert_eq!((again.commission.id, again.commission.total_commission), (first.commission.id, 2250.0));
+
+        let recomputed = service.calculate_commission(&db, huda, start, end, true).await.unwrap();
+        assert_eq!(recomputed.commission.id, first.commission.id);
+        assert_eq!(recomputed.commission.total_commission, 3000.0);
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM delegate_commissions WHERE delegate_id = ?")
+            .bind(huda)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(rows, 1);
+    }
+}
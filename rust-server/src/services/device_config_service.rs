@@ -26,6 +26,55 @@ impl Default for AppConfig {
     }
 }
 
+// LAN discovery: the main device broadcasts a small JSON announcement on DISCOVERY_PORT every
+// DISCOVERY_INTERVAL_SECS and secondary devices listen for it to find where the server runs
+const DISCOVERY_SERVICE: &str = "urcash";
+const DEFAULT_DISCOVERY_PORT: u16 = 39001;
+const DEFAULT_DISCOVERY_INTERVAL_SECS: u64 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiscoveryAnnouncement {
+    pub ip: String,
+    pub port: u16,
+    pub branch: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiscoveryPacket {
+    service: String,
+    #[serde(flatten)]
+    announcement: DiscoveryAnnouncement,
+}
+
+impl DiscoveryAnnouncement {
+    pub fn to_packet(&self) -> Vec<u8> {
+        serde_json::to_vec(&DiscoveryPacket {
+            service: DISCOVERY_SERVICE.to_string(),
+            announcement: self.clone(),
+        })
+        .unwrap_or_default()
+    }
+
+    // Anything that isn't one of our announcements (other apps share broadcast ports) is None
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        let packet: DiscoveryPacket = serde_json::from_slice(packet).ok()?;
+        if packet.service != DISCOVERY_SERVICE || packet.announcement.ip.is_empty() || packet.announcement.port == 0 {
+            return None;
+        }
+        Some(packet.announcement)
+    }
+}
+
+pub fn discovery_port() -> u16 {
+    match std::env::var("DISCOVERY_PORT") {
+        Ok(value) => value.trim().parse::<u16>().unwrap_or_else(|_| {
+            warn!("Invalid DISCOVERY_PORT={}, using {}", value, DEFAULT_DISCOVERY_PORT);
+            DEFAULT_DISCOVERY_PORT
+        }),
+        Err(_) => DEFAULT_DISCOVERY_PORT,
+    }
+}
+
 #[derive(Clone)]
 pub struct DeviceConfigService {
     app_data_dir: PathBuf,
@@ -122,6 +171,66 @@ impl DeviceConfigService {
         "127.0.0.1".to_string()
     }
 
+    // What this device announces: the configured address unless it only points at this machine,
+    // in which case the LAN address secondaries can actually reach
+    pub fn discovery_announcement(&self) -> Result<DiscoveryAnnouncement> {
+        let config = self.get_config()?;
+        let ip = if config.ip.is_empty() || config.ip == "localhost" || config.ip.starts_with("127.") {
+            self.get_local_ip()
+        } else {
+            config.ip
+        };
+        Ok(DiscoveryAnnouncement {
+            ip,
+            port: config.port,
+            branch: config.branch,
+        })
+    }
+
+    // Broadcast the announcement on the LAN until the process exits. The config is re-read every
+    // round so IP or port changes made through the API are announced without a restart.
+    pub fn start_discovery_broadcast(&self) {
+        let service = self.clone();
+        let port = discovery_port();
+        let interval_secs = match std::env::var("DISCOVERY_INTERVAL_SECS") {
+            Ok(value) => value.trim().parse::<u64>().unwrap_or_else(|_| {
+                warn!("Invalid DISCOVERY_INTERVAL_SECS={}, using {}", value, DEFAULT_DISCOVERY_INTERVAL_SECS);
+                DEFAULT_DISCOVERY_INTERVAL_SECS
+            }),
+            Err(_) => DEFAULT_DISCOVERY_INTERVAL_SECS,
+        }.max(1);
+
+        tokio::spawn(async move {
+            let socket = match tokio::net::UdpSocket::bind("0.0.0.0:0").await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    error!("Network discovery disabled, could not open a UDP socket: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = socket.set_broadcast(true) {
+                error!("Network discovery disabled, broadcast not permitted: {}", e);
+                return;
+            }
+            info!("Announcing this device on UDP port {} every {}s", port, interval_secs);
+
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                let announcement = match service.discovery_announcement() {
+                    Ok(announcement) => announcement,
+                    Err(e) => {
+                        warn!("Skipping discovery announcement, config unreadable: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = socket.send_to(&announcement.to_packet(), ("255.255.255.255", port)).await {
+                    warn!("Discovery broadcast failed: {}", e);
+                }
+            }
+        });
+    }
+
     pub fn update_ip(&self, new_ip: String) -> Result<()> {
         let mut config = self.get_config()?;
         config.ip = new_ip;
@@ -143,3 +252,31 @@ impl Default for DeviceConfigService {
         Self::new().expect("Failed to initialize DeviceConfigService")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn announcement_packets_parse_into_ip_port_and_branch() {
+        let packet = br#"{"service":"urcash","ip":"192.168.1.10","port":39000,"branch":"main"}"#;
+        assert_eq!(
+            DiscoveryAnnouncement::parse(packet),
+            Some(DiscoveryAnnouncement { ip: "192.168.1.10".to_string(), port: 39000, branch: "main".to_string() })
+        );
+
+        let announcement = DiscoveryAnnouncement { ip: "10.0.0.4".to_string(), port: 40100, branch: "main".to_string() };
+        assert_eq!(DiscoveryAnnouncement::parse(&announcement.to_packet()), Some(announcement));
+
+        // Other services on the port, half-filled announcements and noise are ignored
+        for packet in [
+            &br#"{"service":"printer","ip":"192.168.1.10","port":39000,"branch":"main"}"#[..],
+            br#"{"service":"urcash","ip":"","port":39000,"branch":"main"}"#,
+            br#"{"service":"urcash","ip":"192.168.1.10","port":0,"branch":"main"}"#,
+            br#"{"service":"urcash","ip":"192.168.1.10"}"#,
+            b"M-SEARCH * HTTP/1.1",
+        ] {
+            assert_eq!(DiscoveryAnnouncement::parse(packet), None, "{}", String::from_utf8_lossy(packet));
+        }
+    }
+}
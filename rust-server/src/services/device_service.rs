@@ -1,7 +1,10 @@
 use crate::database::Database;
+use crate::utils::{sqlite_row_to_json, sqlite_rows_to_json};
 use sqlx::Result;
 use serde_json::Value;
 use crate::routes::devices_routes::*;
+use crate::services::device_config_service::{discovery_port, DiscoveryAnnouncement};
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct DeviceService;
@@ -58,4 +61,65 @@ impl DeviceService {
     pub async fn remove(&self, _db: &Database, _device_id: &str) -> Result<()> {
         Ok(())
     }
-}
\ No newline at end of file
+
+    // Record that a secondary device is alive. Name, IP and branch keep their last known value
+    // when a heartbeat leaves them out.
+    pub async fn heartbeat(&self, db: &Database, device_id: &str, ip: Option<&str>, name: Option<&str>, branch: Option<&str>) -> Result<Value> {
+        let row = sqlx::query(r#"
+            INSERT INTO devices (device_id, name, ip, branch, last_seen)
+            VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(device_id) DO UPDATE SET
+                name = COALESCE(excluded.name, devices.name),
+                ip = COALESCE(excluded.ip, devices.ip),
+                branch = COALESCE(excluded.branch, devices.branch),
+                last_seen = CURRENT_TIMESTAMP
+            RETURNING device_id, name, ip, branch, last_seen
+        "#)
+        .bind(device_id)
+        .bind(name)
+        .bind(ip)
+        .bind(branch)
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(sqlite_row_to_json(&row))
+    }
+
+    // Devices whose last heartbeat is at most within_seconds old, most recent first
+    pub async fn list_active(&self, db: &Database, within_seconds: i64) -> Result<Value> {
+        let rows = sqlx::query(r#"
+            SELECT device_id, name, ip, branch, last_seen
+            FROM devices
+            WHERE last_seen >= datetime('now', '-' || ? || ' seconds')
+            ORDER BY last_seen DESC
+        "#)
+        .bind(within_seconds)
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(Value::Array(sqlite_rows_to_json(&rows)))
+    }
+
+    // Listen for a main device's broadcast announcement for up to `timeout`. Packets from other
+    // sources on the port are skipped; None when nothing was heard in time.
+    pub async fn discover_main(&self, timeout: Duration) -> std::io::Result<Option<DiscoveryAnnouncement>> {
+        let socket = tokio::net::UdpSocket::bind(("0.0.0.0", discovery_port())).await?;
+        let mut buffer = [0u8; 1024];
+
+        let listen = async {
+            loop {
+                let (len, from) = socket.recv_from(&mut buffer).await?;
+                match DiscoveryAnnouncement::parse(&buffer[..len]) {
+                    Some(announcement) if announcement.branch == "main" => return Ok(announcement),
+                    _ => tracing::debug!("Ignoring discovery packet from {}", from),
+                }
+            }
+        };
+
+        match tokio::time::timeout(timeout, listen).await {
+            Ok(Ok(announcement)) => Ok(Some(announcement)),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Ok(None),
+        }
+    }
+}
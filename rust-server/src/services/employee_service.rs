@@ -2,7 +2,7 @@ use anyhow::Result;
 use crate::database::Database;
 use crate::models::{
     Employee, EmployeeQuery, CreateEmployeeRequest, UpdateEmployeeRequest, 
-    CalculateCommissionRequest, CommissionCalculation, EmployeeListResponse, 
+    CalculateCommissionRequest, CommissionCalculation, CommissionReport, EmployeeListResponse, 
     EmployeeDropdown, EmployeeWithCommission
 };
 use crate::models::PaginationInfo;
@@ -376,4 +376,136 @@ impl EmployeeService {
             "calculation": calculation
         }))
     }
+
+    // Commission earned on the non-cancelled sales credited to the employee between start_date and
+    // end_date, counting only the days inside their commission window. Percentage commissions apply to
+    // the sales' net total; fixed commissions pay commission_amount per sale.
+    pub async fn commission_report(
+        &self,
+        db: &Database,
+        employee_id: i64,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> Result<CommissionReport> {
+        let employee = self.get_by_id(db, employee_id).await?
+            .ok_or_else(|| anyhow::anyhow!("Employee not found"))?;
+
+        let period_start = start_date.into_iter().chain(employee.commission_start_date).max();
+        let period_end = end_date.into_iter().chain(employee.commission_end_date).min();
+
+        let (sales_count, total_sales) = match (period_start, period_end) {
+            (Some(start), Some(end)) if start > end => (0, 0.0),
+            _ => {
+                let row = sqlx::query(r#"
+                    SELECT COUNT(*) as sales_count, COALESCE(SUM(net_amount), 0.0) as total_sales
+                    FROM sales
+                    WHERE employee_id = ?
+                      AND status != 'cancelled'
+                      AND (? IS NULL OR invoice_date >= ?)
+                      AND (? IS NULL OR invoice_date <= ?)
+                "#)
+                .bind(employee_id)
+                .bind(period_start)
+                .bind(period_start)
+                .bind(period_end)
+                .bind(period_end)
+                .fetch_one(&db.pool)
+                .await?;
+                (row.get::<i64, _>("sales_count"), row.get::<f64, _>("total_sales"))
+            }
+        };
+
+        let (rate, commission_due) = if employee.commission_type == "fixed" {
+            (employee.commission_amount, employee.commission_amount * sales_count as f64)
+        } else {
+            (employee.commission_rate, total_sales * employee.commission_rate / 100.0)
+        };
+
+        Ok(CommissionReport {
+            employee_id,
+            employee_name: employee.name,
+            commission_type: employee.commission_type,
+            rate,
+            period_start,
+            period_end,
+            sales_count,
+            total_sales,
+            commission_due,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::services::SaleService;
+
+    async fn credit_sale(db: &Database, employee_id: i64, date: &str, amount: f64) -> i64 {
+        let sale = serde_json::from_value(serde_json::json!({
+            "customer_id": 999,
+            "employee_id": employee_id,
+            "invoice_date": date,
+            "payment_method": "cash",
+            "paid_amount": amount,
+            "items": [{ "name": "هاتف", "quantity": 1, "price": amount }]
+        })).unwrap();
+        SaleService::new().create(db, sale).await.unwrap().id
+    }
+
+    fn day(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn percentage_commission_counts_only_sales_inside_the_window() {
+        let db = TestDatabase::new().await;
+        let service = EmployeeService::new();
+        let employee_id = sqlx::query(
+            "INSERT INTO employees (name, commission_rate, commission_type, commission_start_date, commission_end_date) VALUES ('Zainab', 5, 'percentage', '2026-03-01', '2026-03-31')"
+        )
+        .execute(&db.pool).await.unwrap()
+        .last_insert_rowid();
+        credit_sale(&db, employee_id, "2026-02-27", 90000.0).await;
+        credit_sale(&db, employee_id, "2026-03-05", 100000.0).await;
+        credit_sale(&db, employee_id, "2026-03-20", 40000.0).await;
+        credit_sale(&db, employee_id, "2026-04-02", 70000.0).await;
+        let cancelled = credit_sale(&db, employee_id, "2026-03-10", 50000.0).await;
+        sqlx::query("UPDATE sales SET status = 'cancelled' WHERE id = ?").bind(cancelled).execute(&db.pool).await.unwrap();
+
+        let report = service.commission_report(&db, employee_id, None, None).await.unwrap();
+        assert_eq!((report.period_start, report.period_end), (Some(day("2026-03-01")), Some(day("2026-03-31"))));
+        assert_eq!((report.sales_count, report.total_sales), (2, 140000.0));
+        assert_eq!((report.rate, report.commission_due), (5.0, 7000.0));
+
+        // A requested range is narrowed to the window, not widened past it
+        let report = service.commission_report(&db, employee_id, Some(day("2026-03-15")), Some(day("2026-04-30"))).await.unwrap();
+        assert_eq!((report.period_start, report.period_end), (Some(day("2026-03-15")), Some(day("2026-03-31"))));
+        assert_eq!((report.sales_count, report.commission_due), (1, 2000.0));
+
+        let report = service.commission_report(&db, employee_id, Some(day("2026-04-01")), None).await.unwrap();
+        assert_eq!((report.sales_count, report.commission_due), (0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn fixed_amount_is_earned_on_each_sale_credited_to_the_employee() {
+        let db = TestDatabase::new().await;
+        let service = EmployeeService::new();
+        let employee_id = sqlx::query("INSERT INTO employees (name, commission_type, commission_amount) VALUES ('Hassan', 'fixed', 2500)")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        let colleague = sqlx::query("INSERT INTO employees (name, commission_type, commission_amount) VALUES ('Noor', 'fixed', 2500)")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        credit_sale(&db, employee_id, "2026-03-02", 15000.0).await;
+        credit_sale(&db, employee_id, "2026-03-03", 320000.0).await;
+        credit_sale(&db, colleague, "2026-03-03", 45000.0).await;
+
+        let report = service.commission_report(&db, employee_id, None, None).await.unwrap();
+        assert_eq!(report.commission_type, "fixed");
+        assert_eq!((report.sales_count, report.total_sales), (2, 335000.0));
+        assert_eq!((report.rate, report.commission_due), (2500.0, 5000.0));
+
+        assert!(service.commission_report(&db, 9999, None, None).await.is_err());
+    }
 }
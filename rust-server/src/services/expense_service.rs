@@ -3,7 +3,8 @@ use crate::database::Database;
 use crate::models::{
     Expense, ExpenseQuery, CreateExpenseRequest, UpdateExpenseRequest, 
     ExpenseListResponse, ExpenseTotalByCategory, 
-    ExpenseTotalByDateRange, DateRangeQuery
+    ExpenseTotalByDateRange, DateRangeQuery, ExpenseCategoryTotal, ExpenseCategoryBreakdown,
+    RecurringExpense, CreateRecurringExpenseRequest, RecurringExpenseFailure, RecurringRunResult
 };
 use crate::models::PaginationInfo;
 use sqlx::{Row, SqlitePool};
@@ -15,6 +16,11 @@ use serde_json::Value;
 pub struct ExpenseService;
 
 impl ExpenseService {
+    pub const RECURRING_INTERVALS: &'static [&'static str] = &["daily", "weekly", "monthly", "yearly"];
+    // A template far behind (e.g. the app was closed for months) catches up at most this many runs at once
+    const MAX_RECURRING_CATCH_UP: usize = 24;
+    const RECURRING_CHECK_INTERVAL_SECS: u64 = 3600;
+
     pub fn new() -> Self {
         Self
     }
@@ -527,6 +533,61 @@ impl ExpenseService {
         Ok(totals)
     }
 
+    // Category key used for grouping: trimmed, single-spaced, lowercase
+    fn normalize_category(category: &str) -> String {
+        category.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+    }
+
+    // Totals per category for a period, largest first, with a grand total
+    pub async fn category_breakdown(&self, db: &Database, start_date: NaiveDate, end_date: NaiveDate) -> Result<ExpenseCategoryBreakdown> {
+        if start_date > end_date {
+            return Err(anyhow::anyhow!("تاريخ البداية يجب أن يكون قبل تاريخ النهاية"));
+        }
+
+        let rows = sqlx::query(r#"
+            SELECT category, COUNT(id) as expense_count, COALESCE(SUM(amount), 0.0) as total_amount
+            FROM expenses
+            WHERE date BETWEEN ? AND ?
+            GROUP BY category
+            ORDER BY expense_count DESC
+        "#)
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_all(&db.pool)
+        .await?;
+
+        // The most used spelling of a category names the merged group
+        let mut categories: Vec<(String, ExpenseCategoryTotal)> = Vec::new();
+        for row in rows {
+            let raw: String = row.get("category");
+            let key = Self::normalize_category(&raw);
+            let count: i64 = row.get("expense_count");
+            let total: f64 = row.get("total_amount");
+            match categories.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, group)) => {
+                    group.count += count;
+                    group.total += total;
+                }
+                None => categories.push((key, ExpenseCategoryTotal {
+                    category: raw.split_whitespace().collect::<Vec<_>>().join(" "),
+                    count,
+                    total,
+                })),
+            }
+        }
+
+        let mut categories: Vec<ExpenseCategoryTotal> = categories.into_iter().map(|(_, group)| group).collect();
+        categories.sort_by(|a, b| b.total.partial_cmp(&a.total).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(ExpenseCategoryBreakdown {
+            start_date,
+            end_date,
+            count: categories.iter().map(|group| group.count).sum(),
+            total: categories.iter().map(|group| group.total).sum(),
+            categories,
+        })
+    }
+
     // Get total by date range
     pub async fn get_total_by_date_range(&self, db: &Database, start_date: &str, end_date: &str) -> Result<ExpenseTotalByDateRange> {
         let query = r#"
@@ -548,4 +609,309 @@ impl ExpenseService {
             expense_count: result.get::<Option<i64>, _>("expense_count").unwrap_or(0),
         })
     }
+
+    pub async fn get_recurring(&self, db: &Database) -> Result<Vec<RecurringExpense>> {
+        let templates = sqlx::query_as::<_, RecurringExpense>("SELECT * FROM recurring_expenses ORDER BY next_run, id")
+            .fetch_all(&db.pool)
+            .await?;
+
+        Ok(templates)
+    }
+
+    pub async fn create_recurring(&self, db: &Database, payload: CreateRecurringExpenseRequest) -> Result<RecurringExpense> {
+        if payload.description.trim().is_empty() {
+            return Err(anyhow::anyhow!("وصف المصروف مطلوب"));
+        }
+        if payload.amount <= 0.0 {
+            return Err(anyhow::anyhow!("المبلغ يجب أن يكون أكبر من صفر"));
+        }
+        if payload.category.trim().is_empty() {
+            return Err(anyhow::anyhow!("فئة المصروف مطلوبة"));
+        }
+        let interval = payload.interval.as_deref().unwrap_or("monthly");
+        if !Self::RECURRING_INTERVALS.contains(&interval) {
+            return Err(anyhow::anyhow!("دورية المصروف يجب أن تكون daily أو weekly أو monthly أو yearly"));
+        }
+        let money_box = sqlx::query("SELECT id FROM money_boxes WHERE id = ?")
+            .bind(payload.money_box_id)
+            .fetch_optional(&db.pool)
+            .await?;
+        if money_box.is_none() {
+            return Err(anyhow::anyhow!("صندوق المال غير موجود"));
+        }
+
+        let id = sqlx::query(r#"
+            INSERT INTO recurring_expenses (description, amount, category, interval, next_run, money_box_id, is_active, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        "#)
+        .bind(payload.description.trim())
+        .bind(payload.amount)
+        .bind(payload.category.trim())
+        .bind(interval)
+        .bind(payload.next_run)
+        .bind(payload.money_box_id)
+        .execute(&db.pool)
+        .await?
+        .last_insert_rowid();
+
+        info!("Recurring expense {} created ({}, next run {})", id, interval, payload.next_run);
+        let template = sqlx::query_as::<_, RecurringExpense>("SELECT * FROM recurring_expenses WHERE id = ?")
+            .bind(id)
+            .fetch_one(&db.pool)
+            .await?;
+
+        Ok(template)
+    }
+
+    // Stop generating; already generated expenses are kept
+    pub async fn deactivate_recurring(&self, db: &Database, id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE recurring_expenses SET is_active = 0, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(id)
+            .execute(&db.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn advance_recurring(date: NaiveDate, interval: &str) -> Option<NaiveDate> {
+        match interval {
+            "daily" => date.checked_add_days(chrono::Days::new(1)),
+            "weekly" => date.checked_add_days(chrono::Days::new(7)),
+            "yearly" => date.checked_add_months(chrono::Months::new(12)),
+            _ => date.checked_add_months(chrono::Months::new(1)),
+        }
+    }
+
+    // Turn every template due on or before as_of into expenses, paid from its money box.
+    // Each occurrence commits on its own; the (recurring_expense_id, date) unique index and the
+    // next_run compare-and-set keep repeated or concurrent runs from generating twice.
+    pub async fn run_recurring(&self, db: &Database, as_of: NaiveDate) -> Result<RecurringRunResult> {
+        let due = sqlx::query_as::<_, RecurringExpense>(
+            "SELECT * FROM recurring_expenses WHERE is_active = 1 AND next_run <= ? ORDER BY next_run, id"
+        )
+        .bind(as_of)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let mut generated = Vec::new();
+        let mut failed = Vec::new();
+        for template in due {
+            let mut run_date = template.next_run;
+            for _ in 0..Self::MAX_RECURRING_CATCH_UP {
+                if run_date > as_of {
+                    break;
+                }
+                let next_run = match Self::advance_recurring(run_date, &template.interval) {
+                    Some(next_run) => next_run,
+                    None => break,
+                };
+
+                match self.generate_recurring(db, &template, run_date, next_run).await {
+                    Ok(Some(expense)) => generated.push(expense),
+                    Ok(None) => {},
+                    Err(err) => {
+                        warn!("Recurring expense {} not generated for {}: {}", template.id, run_date, err);
+                        failed.push(RecurringExpenseFailure {
+                            recurring_expense_id: template.id,
+                            due_date: run_date,
+                            reason: err.to_string(),
+                        });
+                        break;
+                    }
+                }
+                run_date = next_run;
+            }
+        }
+
+        if !generated.is_empty() {
+            info!("Generated {} recurring expenses as of {}", generated.len(), as_of);
+        }
+        Ok(RecurringRunResult {
+            as_of,
+            generated,
+            failed,
+        })
+    }
+
+    // One occurrence; None when another run already advanced the template past run_date
+    async fn generate_recurring(&self, db: &Database, template: &RecurringExpense, run_date: NaiveDate, next_run: NaiveDate) -> Result<Option<Expense>> {
+        let mut tx = db.pool.begin().await?;
+
+        let advanced = sqlx::query(r#"
+            UPDATE recurring_expenses SET next_run = ?, last_run = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND next_run = ? AND is_active = 1
+        "#)
+        .bind(next_run)
+        .bind(run_date)
+        .bind(template.id)
+        .bind(run_date)
+        .execute(&mut *tx)
+        .await?;
+        if advanced.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let inserted = sqlx::query(r#"
+            INSERT OR IGNORE INTO expenses (description, amount, category, date, money_box_id, recurring_expense_id, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        "#)
+        .bind(&template.description)
+        .bind(template.amount)
+        .bind(&template.category)
+        .bind(run_date)
+        .bind(template.money_box_id)
+        .bind(template.id)
+        .execute(&mut *tx)
+        .await?;
+        if inserted.rows_affected() == 0 {
+            // Generated before (e.g. next_run was moved back by hand); only advance the schedule
+            tx.commit().await?;
+            return Ok(None);
+        }
+        let expense_id = inserted.last_insert_rowid();
+
+        let money_box = sqlx::query("SELECT name, amount FROM money_boxes WHERE id = ?")
+            .bind(template.money_box_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+        let current_balance: f64 = money_box.get("amount");
+        if current_balance < template.amount {
+            return Err(anyhow::anyhow!(
+                "الرصيد غير كافٍ في {}. المطلوب: {}، المتوفر: {}",
+                money_box.get::<String, _>("name"),
+                template.amount,
+                current_balance
+            ));
+        }
+
+        sqlx::query("UPDATE money_boxes SET amount = amount - ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(template.amount)
+            .bind(template.money_box_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(r#"
+            INSERT INTO money_box_transactions
+            (box_id, type, amount, balance_after, notes, created_at)
+            VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+        "#)
+        .bind(template.money_box_id)
+        .bind("withdraw")
+        .bind(template.amount)
+        .bind(current_balance - template.amount)
+        .bind(format!("مصروف متكرر: {}", template.description))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_by_id(db, expense_id).await
+    }
+
+    // Background task that generates due recurring expenses every hour
+    pub fn start_recurring_scheduler(&self, db: Database) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(Self::RECURRING_CHECK_INTERVAL_SECS));
+            loop {
+                interval.tick().await;
+                let today = chrono::Local::now().date_naive();
+                if let Err(err) = service.run_recurring(&db, today).await {
+                    error!("Recurring expense scheduler failed: {}", err);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    #[tokio::test]
+    async fn category_spellings_merge_into_one_group() {
+        let db = TestDatabase::new().await;
+        let expenses = [
+            ("Rent", 500000.0, "2026-03-01"),
+            ("rent ", 250000.0, "2026-03-15"),
+            ("Rent", 100000.0, "2026-03-20"),
+            ("Utilities", 60000.0, "2026-03-05"),
+            ("Rent", 999999.0, "2026-04-01"),
+        ];
+        for (category, amount, date) in expenses {
+            sqlx::query("INSERT INTO expenses (description, amount, category, date) VALUES ('مصروف', ?, ?, ?)")
+                .bind(amount)
+                .bind(category)
+                .bind(date)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        }
+
+        let march = |day: u32| NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+        let breakdown = ExpenseService::new().category_breakdown(&db, march(1), march(31)).await.unwrap();
+        let groups: Vec<(&str, i64, f64)> = breakdown.categories.iter()
+            .map(|group| (group.category.as_str(), group.count, group.total))
+            .collect();
+        assert_eq!(groups, vec![("Rent", 3, 850000.0), ("Utilities", 1, 60000.0)]);
+        assert_eq!((breakdown.count, breakdown.total), (4, 910000.0));
+
+        assert!(ExpenseService::new().category_breakdown(&db, march(31), march(1)).await.is_err());
+    }
+
+    fn monthly(description: &str, next_run: NaiveDate) -> CreateRecurringExpenseRequest {
+        CreateRecurringExpenseRequest {
+            description: description.to_string(),
+            amount: 300000.0,
+            category: "Rent".to_string(),
+            interval: Some("monthly".to_string()),
+            next_run,
+            money_box_id: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn due_templates_generate_once_and_advance() {
+        let db = TestDatabase::new().await;
+        let service = ExpenseService::new();
+        sqlx::query("UPDATE money_boxes SET amount = 1000000.5 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let today = NaiveDate::from_ymd_opt(2026, 5, 1).unwrap();
+        let due = service.create_recurring(&db, monthly("إيجار المحل", today)).await.unwrap();
+        let later = service.create_recurring(&db, monthly("إيجار المخزن", NaiveDate::from_ymd_opt(2026, 5, 15).unwrap())).await.unwrap();
+
+        let run = service.run_recurring(&db, today).await.unwrap();
+        assert!(run.failed.is_empty());
+        assert_eq!(run.generated.len(), 1);
+        assert_eq!(run.generated[0].description, "إيجار المحل");
+        assert_eq!(run.generated[0].date, today);
+
+        let next_run = |id: i64| {
+            let pool = db.pool.clone();
+            async move {
+                sqlx::query_scalar::<_, NaiveDate>("SELECT next_run FROM recurring_expenses WHERE id = ?")
+                    .bind(id)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(next_run(due.id).await, NaiveDate::from_ymd_opt(2026, 6, 1).unwrap());
+        assert_eq!(next_run(later.id).await, NaiveDate::from_ymd_opt(2026, 5, 15).unwrap());
+
+        // A second run the same day finds nothing due
+        let again = service.run_recurring(&db, today).await.unwrap();
+        assert!(again.generated.is_empty());
+        let expenses: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM expenses").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(expenses, 1);
+
+        let (balance, withdrawals): (f64, i64) = sqlx::query_as(
+            "SELECT (SELECT amount FROM money_boxes WHERE id = 1), (SELECT COUNT(*) FROM money_box_transactions WHERE box_id = 1 AND type = 'withdraw')"
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!((balance, withdrawals), (700000.5, 1));
+    }
 }
@@ -1,10 +1,264 @@
 use anyhow::Result;
+use image::{ImageFormat, imageops::FilterType};
+use sqlx::Row;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::database::Database;
+use crate::models::CleanupReport;
+
+// Columns holding /uploads URLs; a stored file is kept while any row names it. Add new columns here.
+const UPLOAD_REFERENCE_COLUMNS: &[(&str, &str)] = &[
+    ("settings", "logo_url"),
+];
+
+// Upload that is not an accepted image or is too large; routes downcast to answer 400 with the message
+#[derive(Debug)]
+pub struct InvalidImage(pub String);
+
+impl fmt::Display for InvalidImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidImage {}
 
 #[derive(Clone)]
 pub struct FileService;
 
 impl FileService {
+    // Largest image accepted before decoding
+    pub const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+    // Longest side kept for stored images; larger ones are scaled down keeping the aspect ratio
+    pub const MAX_IMAGE_DIMENSION: u32 = 1024;
+
+    // Unreferenced files younger than this are kept, covering an upload whose row is not saved yet
+    pub const UPLOAD_GC_GRACE_HOURS: u64 = 24;
+
     pub fn new() -> Self {
         Self
     }
+
+    // ~/.urcash/uploads, served under /uploads; falls back to ./uploads without a home directory
+    pub fn uploads_dir() -> PathBuf {
+        dirs::home_dir()
+            .map(|home| home.join(".urcash").join("uploads"))
+            .unwrap_or_else(|| PathBuf::from("uploads"))
+    }
+
+    // Format from the leading bytes only, so a renamed file cannot pass as an image
+    fn detect_image_format(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(ImageFormat::WebP)
+        } else {
+            None
+        }
+    }
+
+    // Validate and store an uploaded PNG/JPEG/WebP image, scaled down to MAX_IMAGE_DIMENSION when larger.
+    // The stored name keeps a sanitized stem of `filename` plus a random suffix; returns its /uploads URL.
+    pub async fn store_image(&self, bytes: &[u8], filename: &str) -> Result<String> {
+        if bytes.is_empty() {
+            return Err(InvalidImage("الملف فارغ".to_string()).into());
+        }
+        if bytes.len() > Self::MAX_IMAGE_BYTES {
+            return Err(InvalidImage(format!(
+                "حجم الصورة يتجاوز الحد المسموح ({} ميغابايت)",
+                Self::MAX_IMAGE_BYTES / (1024 * 1024)
+            )).into());
+        }
+        let format = Self::detect_image_format(bytes)
+            .ok_or_else(|| InvalidImage("نوع الملف غير مدعوم، يرجى رفع صورة PNG أو JPEG أو WebP".to_string()))?;
+
+        // Decoding proves the file is a readable image; only oversized ones are re-encoded
+        let original = bytes.to_vec();
+        let stored = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+            let image = image::load_from_memory_with_format(&original, format)
+                .map_err(|_| InvalidImage("ملف الصورة تالف أو غير صالح".to_string()))?;
+            if image.width().max(image.height()) <= Self::MAX_IMAGE_DIMENSION {
+                return Ok(original);
+            }
+            let resized = image.resize(Self::MAX_IMAGE_DIMENSION, Self::MAX_IMAGE_DIMENSION, FilterType::Lanczos3);
+            let mut encoded = Cursor::new(Vec::new());
+            match format {
+                // The JPEG encoder has no alpha channel
+                ImageFormat::Jpeg => resized.to_rgb8().write_to(&mut encoded, format)?,
+                _ => resized.write_to(&mut encoded, format)?,
+            }
+            Ok(encoded.into_inner())
+        }).await??;
+
+        let stem: String = std::path::Path::new(filename)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+            .take(40)
+            .collect();
+        let stem = if stem.is_empty() { "image".to_string() } else { stem };
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let name = format!("{}-{}.{}", stem, &suffix[..12], format.extensions_str()[0]);
+
+        let dir = Self::uploads_dir();
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(dir.join(&name), &stored).await?;
+
+        info!("Stored image {} ({} bytes)", name, stored.len());
+        Ok(format!("/uploads/{}", name))
+    }
+
+    // File names referenced from UPLOAD_REFERENCE_COLUMNS; values may be "/uploads/x.png", a full URL or a bare name
+    async fn referenced_uploads(db: &Database) -> Result<HashSet<String>> {
+        let mut names = HashSet::new();
+        for (table, column) in UPLOAD_REFERENCE_COLUMNS {
+            let sql = format!("SELECT {column} AS path FROM {table} WHERE {column} IS NOT NULL AND {column} != ''");
+            for row in sqlx::query(&sql).fetch_all(&db.pool).await? {
+                let path: String = row.get("path");
+                if let Some(name) = path.trim().rsplit('/').next().filter(|name| !name.is_empty()) {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    // Delete files in the uploads directory that no row references and that are older than the grace period.
+    // References are read before anything is removed, so a failed query deletes nothing; a dry run only reports.
+    pub async fn garbage_collect(&self, db: &Database, dry_run: bool) -> Result<CleanupReport> {
+        let referenced = Self::referenced_uploads(db).await?;
+        let grace = Duration::from_secs(Self::UPLOAD_GC_GRACE_HOURS * 3600);
+        let mut report = CleanupReport {
+            dry_run,
+            scanned: 0,
+            referenced: 0,
+            too_recent: 0,
+            removed: 0,
+            freed_bytes: 0,
+            files: Vec::new(),
+        };
+
+        let dir = Self::uploads_dir();
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+            Err(err) => return Err(err.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !metadata.is_file() || name.starts_with('.') {
+                continue;
+            }
+            report.scanned += 1;
+
+            if referenced.contains(&name) {
+                report.referenced += 1;
+                continue;
+            }
+            let age = metadata.modified().ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .unwrap_or_default();
+            if age < grace {
+                report.too_recent += 1;
+                continue;
+            }
+
+            if !dry_run {
+                if let Err(err) = tokio::fs::remove_file(entry.path()).await {
+                    warn!("Failed to remove orphaned upload {}: {}", name, err);
+                    continue;
+                }
+            }
+            report.removed += 1;
+            report.freed_bytes += metadata.len();
+            report.files.push(name);
+        }
+
+        info!(
+            "Uploads cleanup{}: {} scanned, {} referenced, {} removed ({} bytes)",
+            if dry_run { " (dry run)" } else { "" },
+            report.scanned, report.referenced, report.removed, report.freed_bytes
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image::RgbImage::from_pixel(width, height, image::Rgb([200, 30, 60]))
+            .write_to(&mut bytes, ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[tokio::test]
+    async fn a_png_is_stored_under_uploads_and_scaled_down_when_large() {
+        crate::database::test_home();
+        let service = FileService::new();
+
+        let small = png(64, 32);
+        let url = service.store_image(&small, "Shop Logo (final).png").await.unwrap();
+        let name = url.strip_prefix("/uploads/").unwrap();
+        assert!(name.starts_with("ShopLogofinal-") && name.ends_with(".png"), "{url}");
+        assert_eq!(std::fs::read(FileService::uploads_dir().join(name)).unwrap(), small);
+
+        let url = service.store_image(&png(2048, 512), "banner.png").await.unwrap();
+        let stored = image::open(FileService::uploads_dir().join(url.strip_prefix("/uploads/").unwrap())).unwrap();
+        assert_eq!((stored.width(), stored.height()), (FileService::MAX_IMAGE_DIMENSION, 256));
+    }
+
+    #[tokio::test]
+    async fn a_file_that_is_not_an_image_is_rejected() {
+        let service = FileService::new();
+
+        for bytes in [b"%PDF-1.7 invoice".to_vec(), b"\x89PNG\r\n\x1a\n but truncated".to_vec(), Vec::new()] {
+            let err = service.store_image(&bytes, "logo.png").await.unwrap_err();
+            assert!(err.downcast_ref::<InvalidImage>().is_some(), "{err}");
+        }
+        let oversized = vec![0u8; FileService::MAX_IMAGE_BYTES + 1];
+        assert!(service.store_image(&oversized, "logo.png").await.unwrap_err().downcast_ref::<InvalidImage>().is_some());
+    }
+
+    #[tokio::test]
+    async fn only_old_unreferenced_uploads_are_collected_and_dry_run_keeps_them() {
+        let db = crate::database::TestDatabase::new().await;
+        let service = FileService::new();
+        let dir = FileService::uploads_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let two_days_ago = SystemTime::now() - Duration::from_secs(48 * 3600);
+        for (name, aged) in [("gc-logo.png", true), ("gc-orphan.png", true), ("gc-fresh.png", false)] {
+            std::fs::write(dir.join(name), b"not really a png").unwrap();
+            if aged {
+                std::fs::File::options().write(true).open(dir.join(name)).unwrap().set_modified(two_days_ago).unwrap();
+            }
+        }
+        sqlx::query("UPDATE settings SET logo_url = 'http://localhost:39000/uploads/gc-logo.png' WHERE id = 1")
+            .execute(&db.pool).await.unwrap();
+
+        let preview = service.garbage_collect(&db, true).await.unwrap();
+        assert_eq!(preview.files, ["gc-orphan.png"]);
+        assert_eq!(preview.freed_bytes, 16);
+        assert!(dir.join("gc-orphan.png").exists());
+
+        let report = service.garbage_collect(&db, false).await.unwrap();
+        assert_eq!(report.files, ["gc-orphan.png"]);
+        assert!(!dir.join("gc-orphan.png").exists());
+        assert!(dir.join("gc-logo.png").exists());
+        assert!(dir.join("gc-fresh.png").exists());
+    }
 }
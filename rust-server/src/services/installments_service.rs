@@ -4,7 +4,7 @@ use crate::models::{
     Installment, InstallmentQuery, CreateInstallmentRequest, UpdateInstallmentRequest, 
     InstallmentPaymentRequest, CreateInstallmentPlanRequest, InstallmentListResponse,
     InstallmentWithDetails, InstallmentGroupedBySale, InstallmentSummary, 
-    InstallmentPlan, InstallmentPlanResponse, PaymentRecordResponse, PaymentRecord
+    InstallmentPlan, InstallmentPlanResponse, GenerateScheduleRequest
 };
 use sqlx::{Row, SqlitePool};
 use tracing::{info, warn, error};
@@ -378,74 +378,120 @@ impl InstallmentsService {
         }))
     }
 
-    // Record payment for an installment
-    pub async fn record_payment(&self, db: &Database, id: i64, payload: InstallmentPaymentRequest) -> Result<PaymentRecordResponse> {
-        // Validate required fields
-        if payload.paid_amount <= 0.0 {
+    // Record a payment against an installment; the money box and the sale's debt move with it
+    pub async fn record_payment(
+        &self,
+        db: &Database,
+        installment_id: i64,
+        amount: f64,
+        payment_method: &str,
+        money_box_id: Option<i64>,
+    ) -> Result<InstallmentWithDetails> {
+        if amount <= 0.0 {
             return Err(anyhow::anyhow!("مبلغ الدفع يجب أن يكون أكبر من صفر"));
         }
+        if !["cash", "card", "bank_transfer", "check"].contains(&payment_method) {
+            return Err(anyhow::anyhow!("طريقة الدفع غير صحيحة"));
+        }
 
-        // Get current installment
-        let installment = self.get_by_id(db, id).await?;
-        if installment.is_none() {
-            return Err(anyhow::anyhow!("القسط غير موجود"));
+        let mut tx = db.pool.begin().await?;
+
+        let installment = sqlx::query("SELECT sale_id, amount, paid_amount FROM installments WHERE id = ?")
+            .bind(installment_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("القسط غير موجود"))?;
+        let sale_id: i64 = installment.get("sale_id");
+        let installment_amount: f64 = installment.get("amount");
+        let paid_amount: f64 = installment.get("paid_amount");
+
+        let remaining = installment_amount - paid_amount;
+        if amount > remaining + 0.005 {
+            return Err(anyhow::anyhow!("مبلغ الدفع ({:.2}) أكبر من المتبقي من القسط ({:.2})", amount, remaining.max(0.0)));
         }
-        let installment = installment.unwrap();
 
-        let new_paid_amount = installment.paid_amount + payload.paid_amount;
-        let total_amount = installment.amount;
-        
-        // Determine new payment status
-        let new_payment_status = if new_paid_amount >= total_amount {
-            "paid".to_string()
-        } else if new_paid_amount > 0.0 {
-            "partial".to_string()
-        } else {
-            "unpaid".to_string()
-        };
+        let new_paid_amount = (paid_amount + amount).min(installment_amount);
+        let new_payment_status = if new_paid_amount >= installment_amount - 0.005 { "paid" } else { "partial" };
 
-        // Update installment
-        let changes = sqlx::query(r#"
-            UPDATE installments 
-            SET paid_amount = ?, payment_status = ?, paid_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+        sqlx::query(r#"
+            UPDATE installments
+            SET paid_amount = ?, payment_status = ?, payment_method = ?, paid_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
             WHERE id = ?
         "#)
         .bind(new_paid_amount)
-        .bind(&new_payment_status)
-        .bind(id)
-        .execute(&db.pool)
+        .bind(new_payment_status)
+        .bind(payment_method)
+        .bind(installment_id)
+        .execute(&mut *tx)
         .await?;
 
-        if changes.rows_affected() == 0 {
-            return Err(anyhow::anyhow!("فشل في تسجيل الدفع"));
+        // Same bookkeeping as DebtService::repay_debt: the sale carries the paid amount, the debt row the remainder
+        let sale = sqlx::query("SELECT net_amount, paid_amount FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if let Some(sale) = sale {
+            let net_amount: f64 = sale.get("net_amount");
+            let sale_paid = (sale.get::<f64, _>("paid_amount") + amount).min(net_amount);
+            let sale_status = if sale_paid >= net_amount { "paid" } else { "partial" };
+
+            sqlx::query("UPDATE sales SET paid_amount = ?, payment_status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(sale_paid)
+                .bind(sale_status)
+                .bind(sale_id)
+                .execute(&mut *tx)
+                .await?;
+
+            if sale_status == "paid" {
+                sqlx::query("DELETE FROM debts WHERE sale_id = ?")
+                    .bind(sale_id)
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query(r#"
+                    UPDATE debts SET amount = MAX(amount - ?, 0), status = 'partial', updated_at = CURRENT_TIMESTAMP
+                    WHERE sale_id = ?
+                "#)
+                .bind(amount)
+                .bind(sale_id)
+                .execute(&mut *tx)
+                .await?;
+            }
         }
 
-        // Get the updated installment
-        let updated_installment = self.get_by_id(db, id).await?.unwrap();
-
-        // Create a simple receipt record (simplified version)
-        let receipt = serde_json::json!({
-            "id": 1,
-            "customer_id": installment.customer_id,
-            "sale_id": installment.sale_id,
-            "amount": payload.paid_amount,
-            "payment_method": payload.payment_method,
-            "notes": payload.notes,
-            "created_at": chrono::Utc::now()
-        });
-
-        let payment_record = PaymentRecord {
-            paid_amount: payload.paid_amount,
-            payment_method: payload.payment_method,
-            notes: payload.notes,
-            recorded_at: chrono::Utc::now().naive_utc(),
-        };
+        match money_box_id {
+            Some(box_id) => {
+                let balance: f64 = sqlx::query("SELECT amount FROM money_boxes WHERE id = ?")
+                    .bind(box_id)
+                    .fetch_optional(&mut *tx)
+                    .await?
+                    .map(|row| row.get("amount"))
+                    .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+
+                sqlx::query("UPDATE money_boxes SET amount = amount + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(amount)
+                    .bind(box_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query(r#"
+                    INSERT INTO money_box_transactions (box_id, type, amount, balance_after, notes, created_at)
+                    VALUES (?, 'installment_payment', ?, ?, ?, CURRENT_TIMESTAMP)
+                "#)
+                .bind(box_id)
+                .bind(amount)
+                .bind(balance + amount)
+                .bind(format!("دفعة قسط رقم {}", installment_id))
+                .execute(&mut *tx)
+                .await?;
+            }
+            None => warn!("Installment {} payment of {} recorded without a money box", installment_id, amount),
+        }
 
-        Ok(PaymentRecordResponse {
-            installment: updated_installment,
-            receipt,
-            payment: payment_record,
-        })
+        tx.commit().await?;
+
+        self.get_by_id(db, installment_id).await?
+            .ok_or_else(|| anyhow::anyhow!("القسط غير موجود"))
     }
 
     // Get installments grouped by sale
@@ -603,6 +649,76 @@ impl InstallmentsService {
         })
     }
 
+    pub const DEFAULT_INTERVAL_DAYS: i64 = 30;
+
+    // Split total_amount evenly over N installments; amounts are in whole cents and the
+    // last installment absorbs the remainder so the rows always sum to the total
+    pub async fn generate_schedule(&self, db: &Database, request: GenerateScheduleRequest) -> Result<Vec<Installment>> {
+        let GenerateScheduleRequest { sale_id, customer_id, total_amount, num_installments, first_due_date, interval_days } = request;
+        let interval_days = interval_days.unwrap_or(Self::DEFAULT_INTERVAL_DAYS);
+        if num_installments <= 0 {
+            return Err(anyhow::anyhow!("عدد الأقساط يجب أن يكون أكبر من صفر"));
+        }
+        if total_amount <= 0.0 {
+            return Err(anyhow::anyhow!("المبلغ الإجمالي يجب أن يكون أكبر من صفر"));
+        }
+        if interval_days <= 0 {
+            return Err(anyhow::anyhow!("الفترة بين الأقساط يجب أن تكون أكبر من صفر"));
+        }
+
+        let total_cents = (total_amount * 100.0).round() as i64;
+        let base_cents = total_cents / num_installments;
+        if base_cents <= 0 {
+            return Err(anyhow::anyhow!("المبلغ الإجمالي صغير جداً لهذا العدد من الأقساط"));
+        }
+
+        let mut tx = db.pool.begin().await?;
+
+        let sale = sqlx::query("SELECT id FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if sale.is_none() {
+            return Err(anyhow::anyhow!("الفاتورة غير موجودة"));
+        }
+
+        let mut ids = Vec::new();
+        for index in 0..num_installments {
+            let cents = if index == num_installments - 1 {
+                total_cents - base_cents * (num_installments - 1)
+            } else {
+                base_cents
+            };
+            let due_date = first_due_date + chrono::Duration::days(interval_days * index);
+
+            let id = sqlx::query(r#"
+                INSERT INTO installments (sale_id, customer_id, due_date, amount, payment_status, created_at, updated_at)
+                VALUES (?, ?, ?, ?, 'unpaid', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            "#)
+            .bind(sale_id)
+            .bind(customer_id)
+            .bind(due_date)
+            .bind(cents as f64 / 100.0)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+            ids.push(id);
+        }
+
+        tx.commit().await?;
+        info!("Generated {} installments for sale {}", num_installments, sale_id);
+
+        let installments = sqlx::query_as::<_, Installment>(
+            "SELECT * FROM installments WHERE sale_id = ? AND id >= ? ORDER BY due_date, id"
+        )
+        .bind(sale_id)
+        .bind(ids[0])
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(installments)
+    }
+
     // Create installment plan
     pub async fn create_installment_plan(&self, db: &Database, payload: CreateInstallmentPlanRequest) -> Result<InstallmentPlanResponse> {
         // Validate required fields
@@ -748,7 +864,7 @@ impl InstallmentsService {
     }
 
     pub async fn record_installment_payment(&self, db: &Database, id: i64, payload: InstallmentPaymentRequest) -> Result<Value> {
-        let result = self.record_payment(db, id, payload).await?;
+        let result = self.record_payment(db, id, payload.paid_amount, &payload.payment_method, payload.money_box_id).await?;
         Ok(serde_json::json!(result))
     }
 
@@ -794,4 +910,138 @@ impl Default for InstallmentQuery {
             end_date: None,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    async fn sale_for(db: &Database, customer_id: i64, invoice_no: &str) -> i64 {
+        sqlx::query("INSERT INTO sales (customer_id, invoice_no, invoice_date, total_amount, net_amount) VALUES (?, ?, '2026-02-14', 100, 100)")
+            .bind(customer_id)
+            .bind(invoice_no)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    // Monthly (30-day) schedule starting 2026-03-01
+    fn schedule(sale_id: i64, customer_id: i64, total_amount: f64, num_installments: i64) -> GenerateScheduleRequest {
+        GenerateScheduleRequest {
+            sale_id,
+            customer_id,
+            total_amount,
+            num_installments,
+            first_due_date: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+            interval_days: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn uneven_split_puts_the_remainder_on_the_last_installment() {
+        let db = TestDatabase::new().await;
+        let customer_id = sqlx::query("INSERT INTO customers (name) VALUES ('Mustafa Jabbar')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        let sale_id = sale_for(&db, customer_id, "INS-100").await;
+
+        let schedule = InstallmentsService::new()
+            .generate_schedule(&db, schedule(sale_id, customer_id, 100.0, 3))
+            .await.unwrap();
+
+        let amounts: Vec<f64> = schedule.iter().map(|i| i.amount).collect();
+        assert_eq!(amounts, vec![33.33, 33.33, 33.34]);
+        let stored_cents: i64 = sqlx::query_scalar("SELECT CAST(ROUND(SUM(amount) * 100) AS INTEGER) FROM installments WHERE sale_id = ?")
+            .bind(sale_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(stored_cents, 10_000);
+
+        let due: Vec<String> = schedule.iter().map(|i| i.due_date.to_string()).collect();
+        assert_eq!(due, vec!["2026-03-01", "2026-03-31", "2026-04-30"]);
+        assert!(schedule.iter().all(|i| i.payment_status == "unpaid" && i.customer_id == Some(customer_id)));
+    }
+
+    #[tokio::test]
+    async fn schedule_rejects_bad_counts_and_missing_sales() {
+        let db = TestDatabase::new().await;
+        let service = InstallmentsService::new();
+        let sale_id = sale_for(&db, 999, "INS-200").await;
+
+        assert!(service.generate_schedule(&db, schedule(sale_id, 999, 100.0, 0)).await.is_err());
+        assert!(service.generate_schedule(&db, schedule(sale_id, 999, 100.0, -2)).await.is_err());
+        // Fewer cents than installments
+        assert!(service.generate_schedule(&db, schedule(sale_id, 999, 0.02, 3)).await.is_err());
+        assert!(service.generate_schedule(&db, schedule(sale_id + 50, 999, 100.0, 3)).await.is_err());
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM installments").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(rows, 0);
+    }
+
+    // Credit sale of 100 owed in two installments of 50, with the debt row the sale would carry
+    async fn two_part_plan(db: &Database) -> (i64, Vec<i64>) {
+        let customer_id = sqlx::query("INSERT INTO customers (name) VALUES ('Rana Sabah')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        let sale_id = sale_for(db, customer_id, "INS-300").await;
+        sqlx::query("INSERT INTO debts (customer_id, sale_id, amount, due_date) VALUES (?, ?, 100, '2026-04-01')")
+            .bind(customer_id)
+            .bind(sale_id)
+            .execute(&db.pool).await.unwrap();
+        let request = GenerateScheduleRequest { interval_days: Some(31), ..schedule(sale_id, customer_id, 100.0, 2) };
+        let schedule = InstallmentsService::new().generate_schedule(db, request).await.unwrap();
+        (sale_id, schedule.iter().map(|i| i.id).collect())
+    }
+
+    async fn box_amount(db: &Database, box_id: i64) -> f64 {
+        sqlx::query_scalar("SELECT amount FROM money_boxes WHERE id = ?")
+            .bind(box_id)
+            .fetch_one(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn partial_then_full_payment_flips_the_installment_to_paid() {
+        let db = TestDatabase::new().await;
+        let service = InstallmentsService::new();
+        let (sale_id, installments) = two_part_plan(&db).await;
+        let box_before = box_amount(&db, 2).await;
+
+        let installment = service.record_payment(&db, installments[0], 20.0, "cash", Some(2)).await.unwrap();
+        assert_eq!((installment.paid_amount, installment.payment_status.as_str()), (20.0, "partial"));
+        assert!(installment.paid_at.is_some());
+        let debt: f64 = sqlx::query_scalar("SELECT amount FROM debts WHERE sale_id = ?")
+            .bind(sale_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(debt, 80.0);
+
+        // Only 30 is left on this installment
+        assert!(service.record_payment(&db, installments[0], 30.5, "cash", Some(2)).await.is_err());
+        let installment = service.record_payment(&db, installments[0], 30.0, "card", Some(2)).await.unwrap();
+        assert_eq!((installment.paid_amount, installment.payment_status.as_str()), (50.0, "paid"));
+        assert_eq!(installment.payment_method.as_deref(), Some("card"));
+
+        assert_eq!(box_amount(&db, 2).await - box_before, 50.0);
+        let postings: Vec<(f64, f64)> = sqlx::query_as(
+            "SELECT amount, balance_after FROM money_box_transactions WHERE box_id = 2 AND type = 'installment_payment' ORDER BY id"
+        )
+        .fetch_all(&db.pool).await.unwrap();
+        assert_eq!(postings, vec![(20.0, box_before + 20.0), (30.0, box_before + 50.0)]);
+    }
+
+    #[tokio::test]
+    async fn paying_the_last_installment_settles_the_sale_and_its_debt() {
+        let db = TestDatabase::new().await;
+        let service = InstallmentsService::new();
+        let (sale_id, installments) = two_part_plan(&db).await;
+
+        service.record_payment(&db, installments[0], 50.0, "cash", None).await.unwrap();
+        service.record_payment(&db, installments[1], 50.0, "bank_transfer", None).await.unwrap();
+
+        let (paid, status): (f64, String) = sqlx::query_as("SELECT paid_amount, payment_status FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!((paid, status.as_str()), (100.0, "paid"));
+        let debts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM debts WHERE sale_id = ?")
+            .bind(sale_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(debts, 0);
+    }
+}
@@ -1,10 +1,641 @@
 use anyhow::Result;
+use sqlx::Row;
+use crate::database::Database;
+use crate::models::inventory::*;
+use crate::models::ProductWithDetails;
+use crate::services::{AuditService, ProductService};
+use crate::utils::CurrencyConverter;
+use tracing::{error, info, warn};
+
+// Net quantity each product's movements account for. stock_movements carry direction in their
+// stock columns (into a stock adds, out of one removes, between two stocks nets to zero);
+// inventory_movements in its type, with adjustments signed.
+const LEDGER_STOCK_SQL: &str = r#"
+    SELECT product_id, SUM(qty) AS qty FROM (
+        SELECT product_id,
+               CASE
+                   WHEN to_stock_id IS NOT NULL AND from_stock_id IS NULL THEN quantity
+                   WHEN from_stock_id IS NOT NULL AND to_stock_id IS NULL THEN -quantity
+                   ELSE 0
+               END AS qty
+        FROM stock_movements
+        UNION ALL
+        SELECT product_id,
+               CASE movement_type
+                   WHEN 'in' THEN quantity
+                   WHEN 'out' THEN -quantity
+                   WHEN 'adjustment' THEN quantity
+                   ELSE 0
+               END AS qty
+        FROM inventory_movements
+    )
+    GROUP BY product_id
+"#;
+
+// Purchase lines of one product with what is left of each, in FEFO order: earliest expiry first,
+// undated batches last, ties by receipt. Cancelled purchases hold no stock.
+const PRODUCT_BATCHES_SQL: &str = r#"
+    SELECT pi.id AS purchase_item_id, pi.purchase_id, p.invoice_no, p.invoice_date,
+           pi.batch_number, pi.expiry_date, pi.quantity AS purchased_quantity,
+           COALESCE(pi.returned_quantity, 0) AS returned_quantity,
+           COALESCE(a.sold, 0) AS sold_quantity,
+           pi.quantity - COALESCE(pi.returned_quantity, 0) - COALESCE(a.sold, 0) AS remaining_quantity
+    FROM purchase_items pi
+    JOIN purchases p ON p.id = pi.purchase_id
+    LEFT JOIN (
+        SELECT purchase_item_id, SUM(quantity) AS sold
+        FROM sale_batch_allocations
+        GROUP BY purchase_item_id
+    ) a ON a.purchase_item_id = pi.id
+    WHERE pi.product_id = ? AND p.status != 'cancelled'
+    ORDER BY pi.expiry_date IS NULL, pi.expiry_date, p.invoice_date, pi.id
+"#;
 
 #[derive(Clone)]
 pub struct InventoryService;
 
 impl InventoryService {
+    const DRIFT_CHECK_INTERVAL_SECS: u64 = 24 * 3600;
+    // Reorder quantity for products without a max_stock to fill up to
+    const DEFAULT_REORDER_QUANTITY: i64 = 10;
+
     pub fn new() -> Self {
         Self
     }
+
+    // Value of stock on hand per warehouse, in the base currency: dollar-priced products are converted
+    // at the current exchange rate. Both methods fall back to purchase_price for products never received
+    // through a purchase; negative stock values negative.
+    pub async fn valuation(&self, db: &Database, method: CostMethod) -> Result<InventoryValuation> {
+        let unit_cost = match method {
+            CostMethod::AverageCost => "COALESCE(NULLIF(p.average_cost, 0), p.purchase_price, 0)",
+            CostMethod::LastPurchasePrice => "COALESCE(NULLIF(p.last_purchase_price, 0), p.purchase_price, 0)",
+        };
+
+        let rows = sqlx::query(&format!(r#"
+            SELECT
+                st.id as stock_id,
+                st.name as stock_name,
+                COUNT(p.id) as product_count,
+                COALESCE(SUM(p.current_stock), 0) as total_quantity,
+                CAST(COALESCE(SUM(CASE WHEN p.is_dolar = 1 THEN 0 ELSE p.current_stock * {cost} END), 0) AS REAL) as base_value,
+                CAST(COALESCE(SUM(CASE WHEN p.is_dolar = 1 THEN p.current_stock * {cost} ELSE 0 END), 0) AS REAL) as foreign_value
+            FROM products p
+            LEFT JOIN stocks st ON p.stock_id = st.id
+            WHERE p.is_active = 1
+            GROUP BY st.id
+            ORDER BY st.id IS NULL, st.name
+        "#, cost = unit_cost))
+        .fetch_all(&db.pool)
+        .await?;
+
+        let converter = CurrencyConverter::load(&db.pool).await?;
+        let stocks = rows.into_iter().map(|row| {
+            let foreign_value = converter.convert(
+                row.get("foreign_value"), CurrencyConverter::FOREIGN_CURRENCY, CurrencyConverter::BASE_CURRENCY, None,
+            )?;
+            Ok(StockValuation {
+                stock_id: row.get("stock_id"),
+                stock_name: row.get("stock_name"),
+                product_count: row.get("product_count"),
+                total_quantity: row.get("total_quantity"),
+                total_value: ((row.get::<f64, _>("base_value") + foreign_value) * 100.0).round() / 100.0,
+            })
+        }).collect::<Result<Vec<StockValuation>>>()?;
+
+        Ok(InventoryValuation {
+            method,
+            currency: CurrencyConverter::BASE_CURRENCY.to_string(),
+            product_count: stocks.iter().map(|stock| stock.product_count).sum(),
+            total_value: (stocks.iter().fold(0.0, |total, stock| total + stock.total_value) * 100.0).round() / 100.0,
+            stocks,
+            valued_at: chrono::Local::now().naive_local(),
+        })
+    }
+
+    // Active products not counted within `interval_days` (never-counted first, then oldest count), the
+    // work list for cyclic counting
+    pub async fn due_for_stock_check(&self, db: &Database, interval_days: i64) -> Result<Vec<ProductWithDetails>> {
+        if interval_days < 1 {
+            return Err(anyhow::anyhow!("عدد الأيام يجب أن يكون أكبر من صفر"));
+        }
+
+        let rows = sqlx::query(r#"
+            SELECT p.*, c.name as category_name, s.name as stock_name
+            FROM products p
+            LEFT JOIN categories c ON p.category_id = c.id
+            LEFT JOIN stocks s ON p.stock_id = s.id
+            WHERE p.is_active = 1
+              AND (p.last_stock_check IS NULL OR p.last_stock_check < datetime('now', '-' || ? || ' days'))
+            ORDER BY p.last_stock_check IS NOT NULL, p.last_stock_check ASC, p.name ASC
+        "#)
+        .bind(interval_days)
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.iter().map(ProductService::map_product_row).collect())
+    }
+
+    // Products whose current_stock differs from the net of their movements. Stock entered directly on
+    // the product (opening quantities, manual edits) has no movement behind it and shows up here too.
+    pub async fn detect_drift(&self, db: &Database) -> Result<Vec<StockDrift>> {
+        let rows = sqlx::query(&format!(r#"
+            SELECT p.id, p.name, p.sku, p.current_stock, COALESCE(l.qty, 0) AS ledger_stock
+            FROM products p
+            LEFT JOIN ({}) l ON l.product_id = p.id
+            WHERE p.current_stock != COALESCE(l.qty, 0)
+            ORDER BY ABS(p.current_stock - COALESCE(l.qty, 0)) DESC, p.id
+        "#, LEDGER_STOCK_SQL))
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| {
+            let current_stock: i64 = row.get("current_stock");
+            let ledger_stock: i64 = row.get("ledger_stock");
+            StockDrift {
+                product_id: row.get("id"),
+                name: row.get("name"),
+                sku: row.get("sku"),
+                current_stock,
+                ledger_stock,
+                drift: current_stock - ledger_stock,
+            }
+        }).collect())
+    }
+
+    // Bring one product's stock and ledger back in line. TrustLedger sets current_stock to the ledger net;
+    // TrustCurrent keeps current_stock and writes an adjustment movement for the difference.
+    pub async fn reconcile(&self, db: &Database, product_id: i64, strategy: ReconcileStrategy) -> Result<ReconcileResult> {
+        let mut tx = db.pool.begin().await?;
+
+        let previous_stock: i64 = sqlx::query_scalar("SELECT current_stock FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("المنتج غير موجود"))?;
+        let ledger_stock: i64 = sqlx::query_scalar(&format!("SELECT COALESCE(SUM(qty), 0) FROM ({}) WHERE product_id = ?", LEDGER_STOCK_SQL))
+            .bind(product_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let delta = previous_stock - ledger_stock;
+        if delta == 0 {
+            return Err(anyhow::anyhow!("مخزون المنتج مطابق لسجل الحركات"));
+        }
+
+        let current_stock = match strategy {
+            ReconcileStrategy::TrustLedger => {
+                sqlx::query("UPDATE products SET current_stock = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(ledger_stock)
+                    .bind(product_id)
+                    .execute(&mut *tx)
+                    .await?;
+                ledger_stock
+            }
+            ReconcileStrategy::TrustCurrent => {
+                sqlx::query(r#"
+                    INSERT INTO inventory_movements (product_id, movement_type, quantity, reference_type, notes)
+                    VALUES (?, 'adjustment', ?, 'reconciliation', ?)
+                "#)
+                .bind(product_id)
+                .bind(delta)
+                .bind(format!("تسوية فرق المخزون: {}", delta))
+                .execute(&mut *tx)
+                .await?;
+                previous_stock
+            }
+        };
+
+        AuditService::record(&mut tx, None, "stock_reconcile", "product", Some(product_id), serde_json::json!({
+            "strategy": strategy,
+            "previous_stock": previous_stock,
+            "ledger_stock": ledger_stock,
+            "drift": delta,
+        })).await?;
+
+        tx.commit().await?;
+        info!("Reconciled stock of product {} ({:?}): stock {} -> {}, ledger {}", product_id, strategy, previous_stock, current_stock, ledger_stock);
+
+        Ok(ReconcileResult {
+            product_id,
+            strategy,
+            previous_stock,
+            ledger_stock,
+            current_stock,
+        })
+    }
+
+    // Daily drift check; only reports, corrections stay a deliberate reconcile per product
+    pub fn start_drift_scheduler(&self, db: Database) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(Self::DRIFT_CHECK_INTERVAL_SECS));
+            loop {
+                interval.tick().await;
+                match service.detect_drift(&db).await {
+                    Ok(drifts) if drifts.is_empty() => info!("Stock drift check: all products match their movements"),
+                    Ok(drifts) => warn!("Stock drift check: {} products differ from their movements", drifts.len()),
+                    Err(err) => error!("Stock drift check failed: {}", err),
+                }
+            }
+        });
+    }
+
+    pub async fn batches_for_product(&self, db: &Database, product_id: i64) -> Result<Vec<ProductBatch>> {
+        let rows = sqlx::query(PRODUCT_BATCHES_SQL)
+            .bind(product_id)
+            .fetch_all(&db.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| ProductBatch {
+            purchase_item_id: row.get("purchase_item_id"),
+            purchase_id: row.get("purchase_id"),
+            invoice_no: row.get("invoice_no"),
+            invoice_date: row.get("invoice_date"),
+            batch_number: row.get("batch_number"),
+            expiry_date: row.get("expiry_date"),
+            purchased_quantity: row.get("purchased_quantity"),
+            returned_quantity: row.get("returned_quantity"),
+            sold_quantity: row.get("sold_quantity"),
+            remaining_quantity: row.get("remaining_quantity"),
+        }).collect())
+    }
+
+    // Draw sold quantity from the product's batches, earliest expiry first. Whatever the batches can't
+    // cover (stock entered without a purchase) is sold unallocated.
+    pub async fn take_from_batches(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        sale_id: i64,
+        product_id: i64,
+        quantity: i64,
+    ) -> Result<()> {
+        let batches: Vec<(i64, i64)> = sqlx::query(PRODUCT_BATCHES_SQL)
+            .bind(product_id)
+            .fetch_all(&mut **tx)
+            .await?
+            .into_iter()
+            .map(|row| (row.get("purchase_item_id"), row.get("remaining_quantity")))
+            .collect();
+
+        let mut left = quantity;
+        for (purchase_item_id, remaining) in batches {
+            if left <= 0 {
+                break;
+            }
+            if remaining <= 0 {
+                continue;
+            }
+            let part = left.min(remaining);
+            sqlx::query("INSERT INTO sale_batch_allocations (sale_id, product_id, purchase_item_id, quantity) VALUES (?, ?, ?, ?)")
+                .bind(sale_id)
+                .bind(product_id)
+                .bind(purchase_item_id)
+                .bind(part)
+                .execute(&mut **tx)
+                .await?;
+            left -= part;
+        }
+        Ok(())
+    }
+
+    // Give quantity back to the batches a sale drew from, latest expiry first so the earliest-expiring
+    // stock stays sold
+    pub async fn return_to_batches(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        sale_id: i64,
+        product_id: i64,
+        quantity: i64,
+    ) -> Result<()> {
+        let allocations: Vec<(i64, i64)> = sqlx::query(r#"
+            SELECT a.id, a.quantity
+            FROM sale_batch_allocations a
+            JOIN purchase_items pi ON pi.id = a.purchase_item_id
+            WHERE a.sale_id = ? AND a.product_id = ?
+            ORDER BY pi.expiry_date IS NULL DESC, pi.expiry_date DESC, a.id DESC
+        "#)
+        .bind(sale_id)
+        .bind(product_id)
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.get("id"), row.get("quantity")))
+        .collect();
+
+        let mut left = quantity;
+        for (allocation_id, allocated) in allocations {
+            if left <= 0 {
+                break;
+            }
+            if allocated <= left {
+                sqlx::query("DELETE FROM sale_batch_allocations WHERE id = ?")
+                    .bind(allocation_id)
+                    .execute(&mut **tx)
+                    .await?;
+                left -= allocated;
+            } else {
+                sqlx::query("UPDATE sale_batch_allocations SET quantity = quantity - ? WHERE id = ?")
+                    .bind(left)
+                    .bind(allocation_id)
+                    .execute(&mut **tx)
+                    .await?;
+                left = 0;
+            }
+        }
+        Ok(())
+    }
+
+    // Products at or below their reorder point (min_stock when none is set), grouped into one draft order
+    // per supplier: the preferred one, else the quickest to deliver. Suppliers with the longest lead
+    // time come first since their orders are the most urgent; unassigned products come last.
+    pub async fn reorder_suggestions(&self, db: &Database) -> Result<Vec<ReorderSuggestion>> {
+        let rows = sqlx::query(r#"
+            SELECT p.id, p.name, p.sku, p.current_stock, p.max_stock,
+                   COALESCE(NULLIF(p.reorder_point, 0), p.min_stock, 0) AS reorder_point,
+                   CAST(COALESCE(ps.supplier_price, p.purchase_price, 0) AS REAL) AS unit_price,
+                   ps.supplier_id, s.name AS supplier_name, ps.lead_time, ps.supplier_product_code
+            FROM products p
+            LEFT JOIN product_suppliers ps ON ps.id = (
+                SELECT x.id
+                FROM product_suppliers x
+                JOIN suppliers sx ON sx.id = x.supplier_id AND COALESCE(sx.is_active, 1) = 1
+                WHERE x.product_id = p.id
+                ORDER BY x.is_preferred DESC, x.lead_time IS NULL, x.lead_time, x.id
+                LIMIT 1
+            )
+            LEFT JOIN suppliers s ON s.id = ps.supplier_id
+            WHERE p.is_active = 1
+              AND COALESCE(NULLIF(p.reorder_point, 0), p.min_stock, 0) > 0
+              AND p.current_stock <= COALESCE(NULLIF(p.reorder_point, 0), p.min_stock, 0)
+            ORDER BY ps.lead_time IS NULL, ps.lead_time DESC, p.current_stock - COALESCE(NULLIF(p.reorder_point, 0), p.min_stock, 0), p.name
+        "#)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let mut groups: Vec<ReorderSuggestion> = Vec::new();
+        for row in rows {
+            let current_stock: i64 = row.get("current_stock");
+            let reorder_point: i64 = row.get("reorder_point");
+            let max_stock: Option<i64> = row.get("max_stock");
+            let suggested_quantity = match max_stock {
+                Some(max_stock) if max_stock > current_stock => max_stock - current_stock,
+                _ => Self::DEFAULT_REORDER_QUANTITY.max(reorder_point - current_stock),
+            };
+            let supplier_id: Option<i64> = row.get("supplier_id");
+            let line = ReorderLine {
+                product_id: row.get("id"),
+                name: row.get("name"),
+                sku: row.get("sku"),
+                current_stock,
+                reorder_point,
+                max_stock,
+                suggested_quantity,
+                unit_price: row.get("unit_price"),
+                lead_time: row.get("lead_time"),
+                supplier_product_code: row.get("supplier_product_code"),
+            };
+
+            match groups.iter_mut().find(|group| group.supplier_id == supplier_id) {
+                Some(group) => group.items.push(line),
+                None => groups.push(ReorderSuggestion {
+                    supplier_id,
+                    supplier_name: row.get("supplier_name"),
+                    lead_time: None,
+                    items: vec![line],
+                    estimated_total: 0.0,
+                }),
+            }
+        }
+
+        for group in &mut groups {
+            group.lead_time = group.items.iter().filter_map(|item| item.lead_time).max();
+            let total: f64 = group.items.iter().map(|item| item.unit_price * item.suggested_quantity as f64).sum();
+            group.estimated_total = (total * 100.0).round() / 100.0;
+        }
+        groups.sort_by_key(|group| (group.supplier_id.is_none(), std::cmp::Reverse(group.lead_time)));
+
+        Ok(groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    // (sku, stock_id, on hand, average_cost, last_purchase_price, priced in dollars)
+    type ShelfRow = (&'static str, Option<i64>, i64, f64, f64, bool);
+
+    const SHELF: &[ShelfRow] = &[
+        ("DRILL", Some(1), 10, 12.0, 15.0, false),
+        ("SAW", Some(2), 4, 0.0, 25.0, false),   // never costed: falls back to purchase_price 20
+        ("CLAMP", Some(2), -2, 5.0, 6.0, false), // oversold, counts against the warehouse
+        ("LASER", None, 3, 2.0, 3.0, true),
+    ];
+
+    async fn seed_shelf(db: &Database) {
+        sqlx::query("INSERT INTO stocks (id, name, code, address) VALUES (2, 'Branch Zayouna', 'BR-2', 'Zayouna')")
+            .execute(&db.pool).await.unwrap();
+        for &(sku, stock_id, on_hand, average_cost, last_price, is_dolar) in SHELF {
+            sqlx::query(r#"
+                INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock, average_cost, last_purchase_price, is_dolar)
+                VALUES (?, ?, 20, 40, 30, ?, ?, ?, ?, ?)
+            "#)
+            .bind(sku)
+            .bind(sku)
+            .bind(stock_id)
+            .bind(on_hand)
+            .bind(average_cost)
+            .bind(last_price)
+            .bind(is_dolar)
+            .execute(&db.pool).await.unwrap();
+        }
+        sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock, is_active) VALUES ('Retired', 'OLD', 20, 40, 30, 1, 100, 0)")
+            .execute(&db.pool).await.unwrap();
+        sqlx::query("UPDATE settings SET exchange_rate = 1500 WHERE id = 1").execute(&db.pool).await.unwrap();
+    }
+
+    fn per_stock(valuation: &InventoryValuation) -> Vec<(Option<i64>, i64, i64, f64)> {
+        valuation.stocks.iter()
+            .map(|stock| (stock.stock_id, stock.product_count, stock.total_quantity, stock.total_value))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn valuation_by_average_cost_and_last_purchase_price() {
+        let db = TestDatabase::new().await;
+        seed_shelf(&db).await;
+        let service = InventoryService::new();
+
+        let average = service.valuation(&db, CostMethod::AverageCost).await.unwrap();
+        let last = service.valuation(&db, CostMethod::LastPurchasePrice).await.unwrap();
+
+        // Warehouses by name, unassigned products last; the dollar-priced laser is converted at 1500
+        assert_eq!(per_stock(&average), vec![
+            (Some(2), 2, 2, 4.0 * 20.0 - 2.0 * 5.0),
+            (Some(1), 1, 10, 120.0),
+            (None, 1, 3, 3.0 * 2.0 * 1500.0),
+        ]);
+        assert_eq!(per_stock(&last), vec![
+            (Some(2), 2, 2, 4.0 * 25.0 - 2.0 * 6.0),
+            (Some(1), 1, 10, 150.0),
+            (None, 1, 3, 3.0 * 3.0 * 1500.0),
+        ]);
+
+        assert_eq!((average.product_count, average.total_value), (4, 70.0 + 120.0 + 9000.0));
+        assert_eq!((last.product_count, last.total_value), (4, 88.0 + 150.0 + 13500.0));
+        assert_eq!(average.currency, "IQD");
+        assert_eq!(last.method, CostMethod::LastPurchasePrice);
+    }
+
+    #[tokio::test]
+    async fn stock_check_list_holds_unchecked_and_overdue_products() {
+        let db = TestDatabase::new().await;
+        // (sku, days since last count, active)
+        let counts: &[(&str, Option<i64>, bool)] = &[
+            ("FRESH", Some(30), true),
+            ("STALE", Some(200), true),
+            ("NEVER", None, true),
+            ("EDGE", Some(89), true),
+            ("GONE", None, false),
+        ];
+        for &(sku, days_ago, active) in counts {
+            sqlx::query(r#"
+                INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, is_active, last_stock_check)
+                VALUES (?, ?, 20, 40, 30, ?, CASE WHEN ? IS NULL THEN NULL ELSE datetime('now', '-' || ? || ' days') END)
+            "#)
+            .bind(sku)
+            .bind(sku)
+            .bind(active)
+            .bind(days_ago)
+            .bind(days_ago)
+            .execute(&db.pool).await.unwrap();
+        }
+
+        let due = InventoryService::new().due_for_stock_check(&db, 90).await.unwrap();
+        let skus: Vec<&str> = due.iter().map(|product| product.sku.as_str()).collect();
+        // Never-counted first, then the longest overdue
+        assert_eq!(skus, vec!["NEVER", "STALE"]);
+
+        let due = InventoryService::new().due_for_stock_check(&db, 20).await.unwrap();
+        assert_eq!(due.len(), 4);
+        assert!(InventoryService::new().due_for_stock_check(&db, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_deliberate_drift_is_detected_and_each_strategy_corrects_it() {
+        let db = TestDatabase::new().await;
+        let service = InventoryService::new();
+        let mut ids = Vec::new();
+        for (sku, current_stock) in [("RICE-5KG", 10), ("SUGAR-1KG", 10), ("OIL-1L", 5)] {
+            ids.push(sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock) VALUES (?, ?, 1000, 1500, 1250, 1, ?)")
+                .bind(sku).bind(sku).bind(current_stock)
+                .execute(&db.pool).await.unwrap().last_insert_rowid());
+        }
+        let (rice, sugar, oil) = (ids[0], ids[1], ids[2]);
+        for (product_id, movement_type, quantity) in [(rice, "in", 10), (sugar, "in", 12), (sugar, "out", 4), (oil, "in", 5)] {
+            sqlx::query("INSERT INTO inventory_movements (product_id, movement_type, quantity) VALUES (?, ?, ?)")
+                .bind(product_id).bind(movement_type).bind(quantity)
+                .execute(&db.pool).await.unwrap();
+        }
+        // A manual edit that skipped the ledger
+        sqlx::query("UPDATE products SET current_stock = 7 WHERE id = ?").bind(oil).execute(&db.pool).await.unwrap();
+
+        let drifts: Vec<(i64, i64, i64)> = service.detect_drift(&db).await.unwrap().iter()
+            .map(|drift| (drift.product_id, drift.ledger_stock, drift.drift))
+            .collect();
+        assert_eq!(drifts, [(sugar, 8, 2), (oil, 5, 2)]);
+
+        let fixed = service.reconcile(&db, sugar, ReconcileStrategy::TrustLedger).await.unwrap();
+        assert_eq!((fixed.previous_stock, fixed.current_stock), (10, 8));
+        let kept = service.reconcile(&db, oil, ReconcileStrategy::TrustCurrent).await.unwrap();
+        assert_eq!((kept.previous_stock, kept.current_stock), (7, 7));
+        let adjustment: i64 = sqlx::query_scalar("SELECT quantity FROM inventory_movements WHERE product_id = ? AND movement_type = 'adjustment'")
+            .bind(oil).fetch_one(&db.pool).await.unwrap();
+        assert_eq!(adjustment, 2);
+
+        assert!(service.detect_drift(&db).await.unwrap().is_empty());
+        assert!(service.reconcile(&db, rice, ReconcileStrategy::TrustLedger).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn sales_draw_from_the_earliest_expiring_batch_first() {
+        use crate::models::sale::CreateSaleRequest;
+        use crate::services::SaleService;
+        let db = TestDatabase::new().await;
+        let pool = &db.pool;
+        let supplier_id = sqlx::query("INSERT INTO suppliers (name, contact_person, phone) VALUES ('Al-Rafidain Dairy', 'Saad', '07705550000')")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let yogurt = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock) VALUES ('Yogurt 1kg', 'YOG-1KG', 1000, 1500, 1250, 1, 30)")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        // The later-expiring batch arrives first, so receipt order alone would pick the wrong one
+        let mut batches = Vec::new();
+        for (invoice_no, invoice_date, batch, expiry, quantity) in [
+            ("PUR-1", "2026-10-01", "LOT-B", "2026-12-31", 20),
+            ("PUR-2", "2026-10-05", "LOT-A", "2026-11-15", 10),
+        ] {
+            let purchase_id = sqlx::query("INSERT INTO purchases (supplier_id, invoice_no, invoice_date, total_amount, net_amount) VALUES (?, ?, ?, ?, ?)")
+                .bind(supplier_id).bind(invoice_no).bind(invoice_date).bind(quantity * 1000).bind(quantity * 1000)
+                .execute(pool).await.unwrap().last_insert_rowid();
+            batches.push(sqlx::query("INSERT INTO purchase_items (purchase_id, product_id, quantity, price, total, batch_number, expiry_date) VALUES (?, ?, ?, 1000, ?, ?, ?)")
+                .bind(purchase_id).bind(yogurt).bind(quantity).bind(quantity * 1000).bind(batch).bind(expiry)
+                .execute(pool).await.unwrap().last_insert_rowid());
+        }
+        let (late, early) = (batches[0], batches[1]);
+
+        let sale: CreateSaleRequest = serde_json::from_value(serde_json::json!({
+            "customer_id": 999,
+            "invoice_date": "2026-10-15",
+            "payment_method": "cash",
+            "paid_amount": 22500.0,
+            "items": [{ "product_id": yogurt, "quantity": 15, "price": 1500.0 }]
+        })).unwrap();
+        SaleService::new().create(&db, sale).await.unwrap();
+
+        let remaining: Vec<(i64, Option<String>, i64, i64)> = InventoryService::new().batches_for_product(&db, yogurt).await.unwrap().iter()
+            .map(|batch| (batch.purchase_item_id, batch.batch_number.clone(), batch.sold_quantity, batch.remaining_quantity))
+            .collect();
+        assert_eq!(remaining, [
+            (early, Some("LOT-A".to_string()), 10, 0),
+            (late, Some("LOT-B".to_string()), 5, 15),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn products_below_reorder_point_are_grouped_under_their_preferred_supplier() {
+        let db = TestDatabase::new().await;
+        let pool = &db.pool;
+        let mut suppliers = Vec::new();
+        for name in ["Baghdad Wholesale", "Kirkuk Dairy"] {
+            suppliers.push(sqlx::query("INSERT INTO suppliers (name, contact_person, phone) VALUES (?, 'Office', '07700000000')")
+                .bind(name).execute(pool).await.unwrap().last_insert_rowid());
+        }
+        let (wholesale, dairy) = (suppliers[0], suppliers[1]);
+        let add_product = |sku: &'static str, current_stock: i64, reorder_point: i64, max_stock: Option<i64>| {
+            sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, current_stock, reorder_point, max_stock) VALUES (?, ?, 1000, 1500, 1250, ?, ?, ?)")
+                .bind(sku).bind(sku).bind(current_stock).bind(reorder_point).bind(max_stock)
+                .execute(pool)
+        };
+        let milk = add_product("MILK-1L", 3, 10, Some(40)).await.unwrap().last_insert_rowid();
+        let cheese = add_product("CHEESE-500G", 2, 5, None).await.unwrap().last_insert_rowid();
+        add_product("BUTTER-250G", 50, 10, Some(60)).await.unwrap();
+        // The faster supplier is not the preferred one
+        for (supplier_id, price, lead_time, preferred) in [(wholesale, 1100.0, 1, 0), (dairy, 900.0, 3, 1)] {
+            sqlx::query("INSERT INTO product_suppliers (product_id, supplier_id, supplier_price, lead_time, is_preferred) VALUES (?, ?, ?, ?, ?)")
+                .bind(milk).bind(supplier_id).bind(price).bind(lead_time).bind(preferred)
+                .execute(pool).await.unwrap();
+        }
+
+        let groups = InventoryService::new().reorder_suggestions(&db).await.unwrap();
+        // (supplier_id, [(product_id, suggested_quantity)], estimated_total) per group
+        type GroupSummary = (Option<i64>, Vec<(i64, i64)>, f64);
+        let summary: Vec<GroupSummary> = groups.iter()
+            .map(|group| (group.supplier_id, group.items.iter().map(|item| (item.product_id, item.suggested_quantity)).collect(), group.estimated_total))
+            .collect();
+        assert_eq!(summary, [
+            (Some(dairy), vec![(milk, 37)], 33300.0),
+            (None, vec![(cheese, 10)], 10000.0),
+        ]);
+        assert_eq!(groups[0].supplier_name.as_deref(), Some("Kirkuk Dairy"));
+        assert_eq!(groups[0].lead_time, Some(3));
+    }
 }
@@ -15,11 +15,21 @@ use aes::Aes256;
 use cbc::{Decryptor, cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit}};
 use sha2::{Sha256, Digest};
 use md5::Md5;
+use rsa::{RsaPublicKey, pkcs1::DecodeRsaPublicKey, pkcs1v15::{Signature, VerifyingKey}, pkcs8::DecodePublicKey, signature::Verifier};
+use base64::Engine;
 
 use crate::database::Database;
 
 const API_URL: &str = "https://urcash.up.railway.app/api";
 const CACHE_TTL: u64 = 5 * 60; // 5 minutes
+// Background re-verification, overridable through LICENSE_CHECK_INTERVAL_SECS / LICENSE_EXPIRY_WARNING_DAYS
+const DEFAULT_LICENSE_CHECK_INTERVAL_SECS: u64 = 6 * 60 * 60;
+const DEFAULT_EXPIRY_WARNING_DAYS: i64 = 7;
+// License server requests: per-attempt timeout, attempts and first backoff delay (doubled each retry),
+// overridable through LICENSE_HTTP_TIMEOUT_SECS / LICENSE_HTTP_RETRIES
+const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_HTTP_RETRIES: u64 = 3;
+const RETRY_BACKOFF_MS: u64 = 500;
 
 // License directory path
 fn get_license_dir() -> String {
@@ -31,6 +41,18 @@ fn get_license_dir() -> String {
 pub struct LicenseService {
     client: Client,
     cache: Arc<RwLock<HashMap<String, LicenseCacheEntry>>>,
+    // Outcome of the latest background check, served by /api/license/notifications
+    events: Arc<RwLock<Vec<LicenseEvent>>>,
+}
+
+// "license.expiring" ahead of the expiry date, "license.expired" once it has passed
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseEvent {
+    pub event: String,
+    pub message: String,
+    pub expires_at: Option<chrono::DateTime<Utc>>,
+    pub days_until_expiry: Option<i64>,
+    pub timestamp: chrono::DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -144,6 +166,9 @@ pub struct DecryptedLicense {
     pub fingerprint: String,
     pub variation_used: usize,
     pub key_method: Option<String>,
+    // Exact JSON text of `license_data.data` as issued; the signature covers these bytes
+    #[serde(default)]
+    pub signed_payload: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -160,10 +185,52 @@ pub struct LicenseFilesResponse {
 
 impl LicenseService {
     pub fn new() -> Self {
+        // A hung connection to the license server must not block activation indefinitely
+        let timeout_secs = env_number("LICENSE_HTTP_TIMEOUT_SECS", DEFAULT_HTTP_TIMEOUT_SECS).max(1);
+        let client = Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(timeout_secs.min(DEFAULT_HTTP_TIMEOUT_SECS)))
+            .timeout(std::time::Duration::from_secs(timeout_secs))
+            .build()
+            .expect("Failed to create HTTP client");
+
         Self {
-            client: Client::new(),
+            client,
             cache: Arc::new(RwLock::new(HashMap::new())),
+            events: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    // Re-verify the license (offline-first) every LICENSE_CHECK_INTERVAL_SECS, refreshing the cached status
+    // and replacing the expiry events the UI polls for
+    pub fn start_reverification(&self) {
+        let service = self.clone();
+        let interval_secs = env_number("LICENSE_CHECK_INTERVAL_SECS", DEFAULT_LICENSE_CHECK_INTERVAL_SECS).max(60);
+        let warning_days = env_number("LICENSE_EXPIRY_WARNING_DAYS", DEFAULT_EXPIRY_WARNING_DAYS as u64) as i64;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                // Dropping the cache first makes this a fresh local check; the server is only asked when
+                // there is no usable local license
+                match service.verify_license_offline_first(true).await {
+                    Ok(license) => service.record_expiry(&license, Utc::now(), warning_days).await,
+                    Err(err) => warn!("Background license verification failed: {}", err),
+                }
+            }
+        });
+    }
+
+    // Replace the expiry events with the one a check result calls for, logging it as a warning
+    async fn record_expiry(&self, license: &LicenseResponse, now: chrono::DateTime<Utc>, warning_days: i64) {
+        let event = expiry_event(license.expires_at, license.expired.unwrap_or(false), now, warning_days);
+        if let Some(ref event) = event {
+            warn!("{}: {}", event.event, event.message);
         }
+        *self.events.write().await = event.into_iter().collect();
+    }
+
+    pub async fn license_events(&self) -> Vec<LicenseEvent> {
+        self.events.read().await.clone()
     }
 
     // Generate device fingerprint
@@ -235,25 +302,41 @@ impl LicenseService {
 
     // Send GET request
     async fn send_get_request(&self, url: &str) -> Result<Value> {
-        let response = self.client
-            .get(url)
-            .send()
-            .await?;
-
-        let result: Value = response.json().await?;
-        Ok(result)
+        self.send_with_retry(url, true, || self.client.get(url)).await
     }
 
-    // Send POST request
+    // Send POST request. Activation consumes codes, so a POST is only retried when the connection
+    // was never made, never after a timeout that may have reached the server.
     async fn send_post_request(&self, url: &str, data: &Value) -> Result<Value> {
-        let response = self.client
-            .post(url)
-            .json(data)
-            .send()
-            .await?;
-
-        let result: Value = response.json().await?;
-        Ok(result)
+        self.send_with_retry(url, false, || self.client.post(url).json(data)).await
+    }
+
+    // Retry transient network failures with exponential backoff, then give up with an offline message
+    async fn send_with_retry<F>(&self, url: &str, idempotent: bool, build: F) -> Result<Value>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let attempts = env_number("LICENSE_HTTP_RETRIES", DEFAULT_HTTP_RETRIES).max(1);
+        let mut backoff = std::time::Duration::from_millis(RETRY_BACKOFF_MS);
+
+        for attempt in 1..=attempts {
+            let error = match build().send().await {
+                Ok(response) if idempotent && response.status().is_server_error() => {
+                    format!("server responded {}", response.status())
+                }
+                Ok(response) => return Ok(response.json().await?),
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => e.to_string(),
+                Err(e) => return Err(e.into()),
+            };
+
+            warn!("License server request {} failed (attempt {}/{}): {}", url, attempt, attempts, error);
+            if attempt < attempts {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        Err(anyhow::anyhow!("تعذر الاتصال بخادم التراخيص، يرجى التحقق من اتصال الإنترنت والمحاولة مرة أخرى"))
     }
 
     // Cache management
@@ -510,6 +593,24 @@ impl LicenseService {
 
         info!("🔍 يتم التشفير باستخدام الترخيص: {}...", &current_fingerprint[..50.min(current_fingerprint.len())]);
 
+        // The fingerprint that decrypted the license last time is tried first, so a hardware probe that
+        // comes back "unknown" on this run doesn't send us through every variation below
+        let cached_fingerprint = self.read_cached_fingerprint();
+        if let Some(ref fingerprint) = cached_fingerprint {
+            info!("🔍 Trying cached fingerprint: {}...", &fingerprint[..50.min(fingerprint.len())]);
+            let result = match self.try_decrypt_with_sha256(&iv, &encrypted_data, fingerprint, 0).await {
+                Ok(result) => Some(result),
+                Err(_) => self.try_decrypt_with_md5(&iv, &encrypted_data, fingerprint, 0).await.ok(),
+            };
+            if let Some(result) = result {
+                if let Ok(cached_json) = serde_json::to_string(&result) {
+                    self.set_cache(&cache_key, &cached_json, 2 * 60).await;
+                }
+                return Ok(result);
+            }
+            warn!("Cached fingerprint no longer decrypts the license, regenerating variations");
+        }
+
         // Try multiple fingerprint variations to find the one that works
         // Focus on stable components without MAC addresses
         let fingerprint_variations = vec![
@@ -552,11 +653,16 @@ impl LicenseService {
             );
 
             // Try SHA-256 key derivation first
+            if cached_fingerprint.as_deref() == Some(fingerprint.as_str()) {
+                continue;
+            }
+
             if let Ok(result) = self.try_decrypt_with_sha256(&iv, &encrypted_data, fingerprint, i + 1).await {
                 // Cache the successful decryption
                 if let Ok(cached_json) = serde_json::to_string(&result) {
                     self.set_cache(&cache_key, &cached_json, 2 * 60).await; // 2 minutes for decryption cache
                 }
+                self.write_cached_fingerprint(fingerprint);
                 return Ok(result);
             }
 
@@ -566,6 +672,7 @@ impl LicenseService {
                 if let Ok(cached_json) = serde_json::to_string(&result) {
                     self.set_cache(&cache_key, &cached_json, 2 * 60).await; // 2 minutes for decryption cache
                 }
+                self.write_cached_fingerprint(fingerprint);
                 return Ok(result);
             }
         }
@@ -643,6 +750,15 @@ impl LicenseService {
         let license_data: LicenseData = serde_json::from_value(parsed_value.clone())
             .map_err(|e| anyhow::anyhow!("Failed to parse license data structure: {}. Raw value: {:?}", e, parsed_value))?;
 
+        // Keep the signed object byte-for-byte; re-serializing would reorder keys and break the signature
+        #[derive(Deserialize)]
+        struct SignedPayload {
+            data: Box<serde_json::value::RawValue>,
+        }
+        let signed_payload = serde_json::from_str::<SignedPayload>(&decrypted_str)
+            .map(|payload| payload.data.get().to_string())
+            .map_err(|e| anyhow::anyhow!("Failed to read signed license data: {}", e))?;
+
         Ok(DecryptedLicense {
             success: true,
             license_data,
@@ -650,9 +766,45 @@ impl LicenseService {
             fingerprint: fingerprint.to_string(),
             variation_used: variation,
             key_method: key_method.map(|s| s.to_string()),
+            signed_payload,
         })
     }
 
+    /// Check an RSA PKCS#1 v1.5 / SHA-256 signature (base64 or hex) over the license's `data` JSON text
+    /// against the PEM public key written beside it. Any parse failure counts as an invalid signature.
+    pub fn verify_signature(&self, license_info: &str, signature: &str, public_key_pem: &str) -> bool {
+        let public_key = match RsaPublicKey::from_public_key_pem(public_key_pem)
+            .or_else(|_| RsaPublicKey::from_pkcs1_pem(public_key_pem))
+        {
+            Ok(key) => key,
+            Err(e) => {
+                warn!("License public key could not be read: {}", e);
+                return false;
+            }
+        };
+
+        let signature = signature.trim();
+        let signature_bytes = if signature.len().is_multiple_of(2) && signature.chars().all(|c| c.is_ascii_hexdigit()) {
+            hex::decode(signature).ok()
+        } else {
+            base64::engine::general_purpose::STANDARD.decode(signature).ok()
+        };
+        let Some(signature) = signature_bytes.and_then(|bytes| Signature::try_from(bytes.as_slice()).ok()) else {
+            warn!("License signature is not valid base64 or hex");
+            return false;
+        };
+
+        VerifyingKey::<Sha256>::new(public_key)
+            .verify(license_info.as_bytes(), &signature)
+            .is_ok()
+    }
+
+    // Read the public key saved with the license
+    fn read_public_key(&self) -> Result<String> {
+        let public_key_path = format!("{}/public.pem", get_license_dir());
+        Ok(fs::read_to_string(public_key_path)?)
+    }
+
     // Ensure license directory exists
     fn ensure_license_directory(&self) -> Result<()> {
         let license_dir = get_license_dir();
@@ -663,6 +815,24 @@ impl LicenseService {
         Ok(())
     }
 
+    // Fingerprint variation that last decrypted the license, kept across restarts
+    fn read_cached_fingerprint(&self) -> Option<String> {
+        let cache_path = format!("{}/fingerprint.cache", get_license_dir());
+        fs::read_to_string(cache_path)
+            .ok()
+            .map(|content| content.trim().to_string())
+            .filter(|fingerprint| !fingerprint.is_empty())
+    }
+
+    fn write_cached_fingerprint(&self, fingerprint: &str) {
+        let cache_path = format!("{}/fingerprint.cache", get_license_dir());
+        let written = self.ensure_license_directory().and_then(|_| fs::write(&cache_path, fingerprint).map_err(Into::into));
+        match written {
+            Ok(_) => info!("Cached working fingerprint to {}", cache_path),
+            Err(e) => warn!("Failed to cache fingerprint to {}: {}", cache_path, e),
+        }
+    }
+
     // Check if license files exist locally
     fn license_files_exist(&self) -> bool {
         let license_dir = get_license_dir();
@@ -823,6 +993,31 @@ impl LicenseService {
             Ok(decrypted_license) => {
                 let license_info = &decrypted_license.license_data.data;
                 
+                // A license that decrypts but whose signature doesn't match public.pem has been altered
+                let signature_valid = self.read_public_key()
+                    .map(|public_key| self.verify_signature(
+                        &decrypted_license.signed_payload,
+                        &decrypted_license.license_data.signature,
+                        &public_key,
+                    ))
+                    .unwrap_or(false);
+                if !signature_valid {
+                    warn!("❌ Local license signature does not match public.pem");
+                    let result = LicenseResponse {
+                        success: false,
+                        message: Some("توقيع الترخيص المحلي غير صالح".to_string()),
+                        error: Some("Invalid license signature".to_string()),
+                        source: Some("local".to_string()),
+                        offline: Some(true),
+                        ..Default::default()
+                    };
+                    
+                    if let Ok(cached_json) = serde_json::to_string(&result) {
+                        self.set_cache(&cache_key, &cached_json, 30).await; // 30 seconds for errors
+                    }
+                    return Ok(result);
+                }
+                
                 // Check if license is expired
                 let now = chrono::Utc::now();
                 if let Some(expires_at_str) = &license_info.expires_at {
@@ -863,7 +1058,7 @@ impl LicenseService {
                     activated_at: None, // This would need to be extracted from individual features
                     expires_at,
                     feature_licenses: Some(serde_json::to_value(&license_info.features).unwrap_or_default()),
-                    feature_expiration_status: None, // This would need to be computed
+                    feature_expiration_status: Some(feature_expiration_status(&license_info.features, now)),
                     signature: Some(decrypted_license.license_data.signature.clone()),
                     message: Some("تم التحقق من الترخيص محلياً".to_string()),
                     source: Some("local".to_string()),
@@ -1143,3 +1338,206 @@ impl Default for LicenseService {
         Self::new()
     }
 }
+
+fn env_number(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(value) => value.trim().parse::<u64>().unwrap_or_else(|_| {
+            warn!("Invalid {}={}, using {}", name, value, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+// Expiry event for a license expiring at `expires_at`, if it is expired or within `warning_days` of it
+pub fn expiry_event(expires_at: Option<chrono::DateTime<Utc>>, expired: bool, now: chrono::DateTime<Utc>, warning_days: i64) -> Option<LicenseEvent> {
+    let days_until_expiry = expires_at.map(|expires_at| (expires_at - now).num_days());
+    let expired = expired || expires_at.is_some_and(|expires_at| expires_at <= now);
+    let (event, message) = if expired {
+        ("license.expired", "انتهت صلاحية الترخيص، يرجى التجديد".to_string())
+    } else {
+        match days_until_expiry {
+            Some(days) if days <= warning_days => (
+                "license.expiring",
+                format!("ينتهي الترخيص خلال {} يوم، يرجى التجديد قبل انقطاع الخدمة", days),
+            ),
+            _ => return None,
+        }
+    };
+    Some(LicenseEvent {
+        event: event.to_string(),
+        message,
+        expires_at,
+        days_until_expiry,
+        timestamp: now,
+    })
+}
+
+// Per-feature `{ active, expires_at, days_remaining }`; features without an expiry are perpetual.
+// An expiry date that doesn't parse is treated as expired rather than silently granting the feature.
+pub fn feature_expiration_status(features: &HashMap<String, FeatureInfo>, now: chrono::DateTime<Utc>) -> Value {
+    let status: serde_json::Map<String, Value> = features.iter()
+        .map(|(name, feature)| {
+            let entry = match feature.expires_at.as_deref() {
+                None => serde_json::json!({
+                    "active": true,
+                    "expires_at": null,
+                    "days_remaining": null,
+                }),
+                Some(expires_at) => {
+                    let parsed = chrono::DateTime::parse_from_rfc3339(expires_at)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .ok();
+                    serde_json::json!({
+                        "active": parsed.is_some_and(|expires_at| expires_at > now),
+                        "expires_at": expires_at,
+                        "days_remaining": parsed.map(|expires_at| (expires_at - now).num_days().max(0)).unwrap_or(0),
+                    })
+                }
+            };
+            (name.clone(), entry)
+        })
+        .collect();
+    Value::Object(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn checked(expires_at: chrono::DateTime<Utc>) -> LicenseResponse {
+        LicenseResponse { success: true, expires_at: Some(expires_at), ..Default::default() }
+    }
+
+    // "iv_hex:data_hex" as the license server issues it, keyed by SHA-256 of the fingerprint
+    fn encrypt_license(payload: &Value, fingerprint: &str) -> String {
+        use cbc::cipher::BlockEncryptMut;
+        let key = Sha256::digest(fingerprint.as_bytes());
+        let iv = [7u8; 16];
+        let plain = payload.to_string().into_bytes();
+        let mut buffer = vec![0u8; plain.len() + 16];
+        buffer[..plain.len()].copy_from_slice(&plain);
+        let encrypted = cbc::Encryptor::<Aes256>::new_from_slices(&key, &iv).unwrap()
+            .encrypt_padded_mut::<Pkcs7>(&mut buffer, plain.len()).unwrap();
+        format!("{}:{}", hex::encode(iv), hex::encode(encrypted))
+    }
+
+    #[tokio::test]
+    async fn a_check_inside_the_warning_window_raises_expiring_and_later_expired() {
+        let service = LicenseService::new();
+        let now = Utc::now();
+        let events = || async { service.license_events().await.into_iter().map(|event| (event.event, event.days_until_expiry)).collect::<Vec<_>>() };
+
+        service.record_expiry(&checked(now + Duration::days(30)), now, 7).await;
+        assert!(events().await.is_empty());
+
+        service.record_expiry(&checked(now + Duration::days(5) + Duration::hours(1)), now, 7).await;
+        assert_eq!(events().await, vec![("license.expiring".to_string(), Some(5))]);
+
+        // Each check replaces the previous events rather than piling them up
+        service.record_expiry(&checked(now - Duration::days(1)), now, 7).await;
+        assert_eq!(events().await, vec![("license.expired".to_string(), Some(-1))]);
+
+        service.record_expiry(&checked(now + Duration::days(365)), now, 7).await;
+        assert!(events().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn the_cached_fingerprint_is_tried_first_and_decrypts_the_license() {
+        crate::database::test_home();
+        let service = LicenseService::new();
+        // A fingerprint none of the regenerated variations would produce, as when a probe came back different
+        let cached = "machine-from-an-earlier-run-serial-C02XK1";
+        let license = encrypt_license(&serde_json::json!({
+            "data": { "device_id": "device-1", "type": "premium", "features": {}, "expires_at": null, "userId": "u-7" },
+            "signature": "c2lnbmF0dXJl"
+        }), cached);
+
+        assert!(service.decrypt_license(&license, "fingerprint-with-unknown-serial").await.is_err());
+
+        service.write_cached_fingerprint(cached);
+        assert_eq!(service.read_cached_fingerprint().as_deref(), Some(cached));
+        let decrypted = service.decrypt_license(&license, "fingerprint-with-unknown-serial").await.unwrap();
+        assert_eq!(decrypted.fingerprint, cached);
+        assert_eq!(decrypted.variation_used, 0);
+        assert_eq!(decrypted.license_data.data.user_id.as_deref(), Some("u-7"));
+    }
+
+    #[tokio::test]
+    async fn a_hung_license_server_is_retried_for_gets_and_gives_up_offline() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().fallback(move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                axum::Json(serde_json::json!({ "success": true }))
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/license/check", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let service = LicenseService {
+            client: Client::builder().timeout(std::time::Duration::from_millis(200)).build().unwrap(),
+            ..LicenseService::new()
+        };
+
+        let started = std::time::Instant::now();
+        let err = service.send_get_request(&url).await.unwrap_err();
+        assert_eq!(hits.load(Ordering::SeqCst), DEFAULT_HTTP_RETRIES as usize);
+        assert!(err.to_string().contains("تعذر الاتصال بخادم التراخيص"), "{err}");
+        // Each attempt is cut off by the client timeout instead of waiting on the server
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        // A POST that timed out may have reached the server, so it is not sent again
+        hits.store(0, Ordering::SeqCst);
+        let err = service.send_post_request(&url, &serde_json::json!({ "code": "ABC" })).await.unwrap_err();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert!(err.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout()), "{err}");
+    }
+
+    #[test]
+    fn a_valid_signature_verifies_and_a_mutated_payload_does_not() {
+        use rsa::{pkcs1v15::SigningKey, pkcs8::EncodePublicKey, signature::{SignatureEncoding, Signer}, RsaPrivateKey};
+        let service = LicenseService::new();
+        // Small key so key generation stays quick in debug builds; verification does not depend on the size
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        let public_pem = private_key.to_public_key().to_public_key_pem(rsa::pkcs8::LineEnding::LF).unwrap();
+        let payload = r#"{"device_id":"device-1","type":"premium","features":{},"expires_at":null,"userId":"u-7"}"#;
+        let signature = SigningKey::<Sha256>::new(private_key).sign(payload.as_bytes()).to_vec();
+        let base64_signature = base64::engine::general_purpose::STANDARD.encode(&signature);
+
+        assert!(service.verify_signature(payload, &base64_signature, &public_pem));
+        assert!(service.verify_signature(payload, &hex::encode(&signature), &public_pem));
+
+        let tampered = payload.replace("premium", "enterprise");
+        assert!(!service.verify_signature(&tampered, &base64_signature, &public_pem));
+        assert!(!service.verify_signature(payload, "not a signature", &public_pem));
+        assert!(!service.verify_signature(payload, &base64_signature, "-----BEGIN PUBLIC KEY-----\nbroken\n-----END PUBLIC KEY-----"));
+    }
+
+    #[test]
+    fn feature_status_marks_expired_active_and_perpetual_features() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-15T12:00:00Z").unwrap().with_timezone(&Utc);
+        let feature = |expires_at: Option<&str>| FeatureInfo {
+            activated_at: Some("2026-01-01T00:00:00Z".to_string()),
+            activation_code: None,
+            expires_at: expires_at.map(str::to_string),
+            type_: "subscription".to_string(),
+        };
+        let features = HashMap::from([
+            ("reports".to_string(), feature(Some("2026-10-01T00:00:00Z"))),
+            ("mobile_live_data".to_string(), feature(Some("2026-10-25T18:00:00Z"))),
+            ("pos".to_string(), feature(None)),
+        ]);
+
+        assert_eq!(feature_expiration_status(&features, now), serde_json::json!({
+            "reports": { "active": false, "expires_at": "2026-10-01T00:00:00Z", "days_remaining": 0 },
+            "mobile_live_data": { "active": true, "expires_at": "2026-10-25T18:00:00Z", "days_remaining": 10 },
+            "pos": { "active": true, "expires_at": null, "days_remaining": null },
+        }));
+    }
+}
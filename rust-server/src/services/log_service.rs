@@ -1,6 +1,9 @@
 use crate::database::Database;
 use sqlx::Result;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
 use crate::routes::logs_routes::*;
 
 #[derive(Clone)]
@@ -10,18 +13,108 @@ pub struct LogsResult {
     pub logs: Vec<Value>,
 }
 
+// A single parsed line from the JSON log files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: Option<String>,
+    pub message: Option<String>,
+    pub fields: Value,
+}
+
 impl LogService {
+    // Prefix used by the daily rotating file appender (server.log.YYYY-MM-DD)
+    pub const LOG_FILE_PREFIX: &'static str = "server.log";
+
     pub fn new() -> Self {
         Self
     }
 
+    pub fn logs_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".urcash").join("logs"))
+    }
+
+    // Parse one line written by the JSON fmt layer; non-JSON lines are skipped
+    pub fn parse_log_line(line: &str) -> Option<LogEntry> {
+        let value: Value = serde_json::from_str(line.trim()).ok()?;
+        let timestamp = value.get("timestamp")
+            .and_then(|t| t.as_str())
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())?
+            .with_timezone(&Utc);
+        let level = value.get("level")?.as_str()?.to_uppercase();
+        let fields = value.get("fields").cloned().unwrap_or(Value::Null);
+
+        Some(LogEntry {
+            timestamp,
+            level,
+            target: value.get("target").and_then(|t| t.as_str()).map(|t| t.to_string()),
+            message: fields.get("message").and_then(|m| m.as_str()).map(|m| m.to_string()),
+            fields,
+        })
+    }
+
+    // Read the rotated log files and return the newest entries matching the filters
+    pub async fn query_logs(&self, level: Option<&str>, since: Option<DateTime<Utc>>, limit: usize) -> anyhow::Result<Vec<LogEntry>> {
+        match Self::logs_dir() {
+            Some(dir) if dir.exists() => Self::read_logs(&dir, level, since, limit).await,
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    async fn read_logs(dir: &std::path::Path, level: Option<&str>, since: Option<DateTime<Utc>>, limit: usize) -> anyhow::Result<Vec<LogEntry>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(Self::LOG_FILE_PREFIX) {
+                files.push(entry.path());
+            }
+        }
+        // Daily rotation suffixes are ISO dates, so a name sort is chronological
+        files.sort();
+
+        let level = level.map(|l| l.to_uppercase());
+        let mut entries = Vec::new();
+        for file in files.iter().rev() {
+            let contents = tokio::fs::read_to_string(file).await?;
+            for entry in contents.lines().rev().filter_map(Self::parse_log_line) {
+                if let Some(since) = since {
+                    if entry.timestamp < since {
+                        continue;
+                    }
+                }
+                if let Some(ref level) = level {
+                    if &entry.level != level {
+                        continue;
+                    }
+                }
+                entries.push(entry);
+                if entries.len() >= limit {
+                    return Ok(entries);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
     pub async fn get_log_files(&self) -> Result<Value> {
         Ok(serde_json::json!([]))
     }
 
-    pub async fn get_recent_logs(&self, _query: &LogQuery) -> Result<LogsResult> {
+    pub async fn get_recent_logs(&self, query: &LogQuery) -> anyhow::Result<LogsResult> {
+        let since = Utc::now() - chrono::Duration::hours(query.hours.unwrap_or(24) as i64);
+        let limit = query.limit.unwrap_or(100).max(0) as usize;
+        let entries = self.query_logs(query.level.as_deref(), Some(since), limit).await?;
+
         Ok(LogsResult {
-            logs: vec![]
+            logs: entries.into_iter()
+                .map(|entry| serde_json::to_value(entry).unwrap_or(Value::Null))
+                .collect()
         })
     }
 
@@ -52,4 +145,56 @@ impl LogService {
     pub async fn clear_old_logs(&self, _days: u32) -> Result<u32> {
         Ok(0)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // Write events through the same JSON fmt layer main() installs for the log files
+    fn write_json_log(path: &std::path::Path, events: impl FnOnce()) {
+        let file = Arc::new(std::fs::File::create(path).unwrap());
+        let subscriber = tracing_subscriber::fmt().json().with_writer(file).finish();
+        tracing::subscriber::with_default(subscriber, events);
+    }
+
+    #[test]
+    fn parses_lines_written_by_the_json_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.log.2026-10-15");
+        write_json_log(&path, || tracing::warn!(sale_id = 42, "stock below minimum"));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let entry = LogService::parse_log_line(contents.lines().next().unwrap()).unwrap();
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.message.as_deref(), Some("stock below minimum"));
+        assert_eq!(entry.fields["sale_id"], 42);
+        assert!(LogService::parse_log_line("plain text line").is_none());
+    }
+
+    #[tokio::test]
+    async fn reads_newest_entries_first_filtered_by_level_and_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        write_json_log(&dir.path().join("server.log.2026-10-14"), || {
+            tracing::error!("yesterday error");
+            tracing::info!("yesterday info");
+        });
+        write_json_log(&dir.path().join("server.log.2026-10-15"), || {
+            tracing::error!("today first error");
+            tracing::error!("today second error");
+        });
+        std::fs::write(dir.path().join("unrelated.txt"), "{}").unwrap();
+
+        let errors = LogService::read_logs(dir.path(), Some("error"), None, 10).await.unwrap();
+        let messages: Vec<_> = errors.iter().filter_map(|entry| entry.message.as_deref()).collect();
+        assert_eq!(messages, ["today second error", "today first error", "yesterday error"]);
+
+        let limited = LogService::read_logs(dir.path(), None, None, 1).await.unwrap();
+        assert_eq!(limited.len(), 1);
+        assert!(LogService::read_logs(dir.path(), None, None, 0).await.unwrap().is_empty());
+
+        let future = Utc::now() + chrono::Duration::hours(1);
+        assert!(LogService::read_logs(dir.path(), None, Some(future), 10).await.unwrap().is_empty());
+    }
+}
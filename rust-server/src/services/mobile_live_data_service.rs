@@ -1,8 +1,35 @@
 use crate::database::Database;
-use sqlx::Result;
-use serde_json::Value;
+use crate::utils::sqlite_rows_to_json;
+use sqlx::{Acquire, Result, Row};
+use serde_json::{json, Value};
+use chrono::NaiveDateTime;
 use crate::routes::mobile_live_data_routes::*;
 
+// Tables devices may change offline or pull deltas of; each has an updated_at the conflict check
+// and changes_since rely on, and a deletion trigger feeding the deletions log
+pub const SYNCABLE_TABLES: &[&str] = &["customers", "suppliers", "products"];
+// Managed by the server, never taken from a change
+const PROTECTED_COLUMNS: &[&str] = &["id", "created_at", "updated_at"];
+// Running totals kept by the debt and stock ledgers; a change carrying one is rejected
+const LEDGER_COLUMNS: &[&str] = &["current_balance", "current_stock", "average_cost", "total_purchased", "total_sold"];
+// What changes_since hands a device per table; cost prices and customer balances never leave the server
+const SYNC_COLUMNS: &[(&str, &[&str])] = &[
+    ("customers", &[
+        "id", "name", "email", "phone", "address", "is_active", "customer_type", "tax_number", "due_date",
+        "representative_id", "created_at", "updated_at",
+    ]),
+    ("suppliers", &[
+        "id", "name", "contact_person", "phone", "email", "address", "tax_number", "notes", "is_active",
+        "created_at", "updated_at",
+    ]),
+    ("products", &[
+        "id", "name", "scientific_name", "description", "supported", "sku", "barcode", "selling_price",
+        "wholesale_price", "company_name", "current_stock", "min_stock", "max_stock", "unit", "units_per_box",
+        "is_dolar", "expiry_date", "is_active", "reorder_point", "category_id", "stock_id", "location_in_stock",
+        "shelf_number", "rack_number", "bin_number", "created_at", "updated_at",
+    ]),
+];
+
 #[derive(Clone)]
 pub struct MobileLiveDataService;
 
@@ -58,4 +85,346 @@ impl MobileLiveDataService {
     pub async fn save_auto_upload_settings(&self, _db: &Database, _payload: AutoUploadSettings) -> Result<Value> {
         Ok(serde_json::json!({}))
     }
-}
\ No newline at end of file
+
+    // Apply queued offline changes in one transaction. A change conflicts when the server row was
+    // updated after the device's base_updated_at (its own updated_at when absent): server_wins then
+    // rejects it, last_write_wins applies it only if the device's edit is the newer one. Each change
+    // runs in a savepoint so a failing one is rejected without undoing the rest, and every change is
+    // logged in pending_sync with its outcome.
+    pub async fn resolve_and_apply(&self, db: &Database, batch: SyncBatchRequest) -> Result<Value> {
+        let strategy = batch.strategy.unwrap_or_default();
+        let strategy_name = match strategy {
+            ConflictStrategy::LastWriteWins => "last_write_wins",
+            ConflictStrategy::ServerWins => "server_wins",
+        };
+
+        let mut tx = db.pool.begin().await?;
+        let mut results = Vec::new();
+        let (mut applied, mut rejected) = (0, 0);
+
+        for (index, change) in batch.changes.iter().enumerate() {
+            let mut savepoint = (&mut *tx).begin().await?;
+            let outcome = match Self::apply_change(&mut savepoint, change, strategy).await {
+                Ok(Ok(record_id)) => {
+                    savepoint.commit().await?;
+                    Ok(record_id)
+                }
+                Ok(Err(reason)) => {
+                    savepoint.rollback().await?;
+                    Err(reason)
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    Err(e.to_string())
+                }
+            };
+
+            let (status, record_id, reason) = match outcome {
+                Ok(record_id) => {
+                    applied += 1;
+                    ("applied", record_id, None)
+                }
+                Err(reason) => {
+                    rejected += 1;
+                    ("rejected", change.record_id, Some(reason))
+                }
+            };
+
+            sqlx::query(r#"
+                INSERT INTO pending_sync (
+                    table_name, record_id, operation, payload, client_updated_at,
+                    strategy, status, reason, resolved_at
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+            "#)
+            .bind(&change.table)
+            .bind(record_id)
+            .bind(&change.operation)
+            .bind(Value::Object(change.data.clone()).to_string())
+            .bind(change.updated_at)
+            .bind(strategy_name)
+            .bind(status)
+            .bind(&reason)
+            .execute(&mut *tx)
+            .await?;
+
+            results.push(json!({
+                "index": index,
+                "table": change.table,
+                "record_id": record_id,
+                "operation": change.operation,
+                "status": status,
+                "reason": reason,
+            }));
+        }
+
+        tx.commit().await?;
+        tracing::info!("Sync batch ({}): {} applied, {} rejected", strategy_name, applied, rejected);
+
+        Ok(json!({
+            "strategy": strategy_name,
+            "applied": applied,
+            "rejected": rejected,
+            "results": results,
+        }))
+    }
+
+    // Rows of each requested table updated after `since` and the ids deleted after it. Tables outside
+    // SYNCABLE_TABLES are skipped. server_time is read first and is what the device passes as `since`
+    // next time, so nothing written during the pull is missed (a row may come twice, ids dedupe it).
+    pub async fn changes_since(&self, db: &Database, since: NaiveDateTime, tables: Vec<String>) -> Result<Value> {
+        let server_time: String = sqlx::query("SELECT CURRENT_TIMESTAMP as now")
+            .fetch_one(&db.pool)
+            .await?
+            .get("now");
+
+        let mut changes = serde_json::Map::new();
+        let mut deleted = serde_json::Map::new();
+        for (table, columns) in SYNC_COLUMNS.iter().filter(|(table, _)| tables.iter().any(|wanted| wanted == *table)) {
+            // datetime() on both sides: updated_at is written both as "YYYY-MM-DD HH:MM:SS" and ISO 8601
+            let rows = sqlx::query(&format!(
+                "SELECT {} FROM {} WHERE datetime(updated_at) > datetime(?) ORDER BY updated_at, id",
+                columns.join(", "),
+                table
+            ))
+            .bind(since)
+            .fetch_all(&db.pool)
+            .await?;
+            changes.insert(table.to_string(), Value::Array(sqlite_rows_to_json(&rows)));
+
+            let ids: Vec<i64> = sqlx::query(
+                "SELECT DISTINCT record_id FROM deletions WHERE table_name = ? AND deleted_at > datetime(?) ORDER BY record_id"
+            )
+            .bind(*table)
+            .bind(since)
+            .fetch_all(&db.pool)
+            .await?
+            .iter()
+            .map(|row| row.get("record_id"))
+            .collect();
+            deleted.insert(table.to_string(), json!(ids));
+        }
+
+        Ok(json!({
+            "since": since,
+            "server_time": server_time,
+            "changes": changes,
+            "deleted": deleted,
+        }))
+    }
+
+    // Ok(Ok(record id)) when applied, Ok(Err(reason)) when the change is rejected
+    async fn apply_change(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        change: &SyncChange,
+        strategy: ConflictStrategy,
+    ) -> Result<std::result::Result<Option<i64>, String>> {
+        let table = match SYNCABLE_TABLES.iter().find(|table| **table == change.table) {
+            Some(table) => *table,
+            None => return Ok(Err(format!("table {} cannot be synced", change.table))),
+        };
+
+        let known_columns: Vec<String> = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&mut **tx)
+            .await?
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect();
+        let mut columns = Vec::new();
+        for column in change.data.keys() {
+            if PROTECTED_COLUMNS.contains(&column.as_str()) {
+                continue;
+            }
+            if LEDGER_COLUMNS.contains(&column.as_str()) {
+                return Ok(Err(format!("column {} is kept by the server", column)));
+            }
+            if !known_columns.contains(column) {
+                return Ok(Err(format!("unknown column {}", column)));
+            }
+            columns.push(column.as_str());
+        }
+
+        if change.operation == "insert" {
+            if columns.is_empty() {
+                return Ok(Err("nothing to insert".to_string()));
+            }
+            let sql = format!(
+                "INSERT INTO {} ({}, updated_at) VALUES ({}, ?)",
+                table,
+                columns.join(", "),
+                vec!["?"; columns.len()].join(", ")
+            );
+            let mut query = sqlx::query(&sql);
+            for column in &columns {
+                query = bind_json(query, &change.data[*column]);
+            }
+            let id = query.bind(change.updated_at).execute(&mut **tx).await?.last_insert_rowid();
+            return Ok(Ok(Some(id)));
+        }
+
+        let record_id = match change.record_id {
+            Some(record_id) => record_id,
+            None => return Ok(Err("record_id is required".to_string())),
+        };
+        let server_updated_at: Option<NaiveDateTime> = match sqlx::query(&format!("SELECT updated_at FROM {} WHERE id = ?", table))
+            .bind(record_id)
+            .fetch_optional(&mut **tx)
+            .await?
+        {
+            Some(row) => row.get("updated_at"),
+            None => return Ok(Err("record not found on the server".to_string())),
+        };
+
+        let base = change.base_updated_at.unwrap_or(change.updated_at);
+        if let Some(server_updated_at) = server_updated_at {
+            if server_updated_at > base {
+                let keep_server = match strategy {
+                    ConflictStrategy::ServerWins => true,
+                    ConflictStrategy::LastWriteWins => server_updated_at >= change.updated_at,
+                };
+                if keep_server {
+                    return Ok(Err(format!("conflict: server copy changed at {}", server_updated_at)));
+                }
+            }
+        }
+
+        match change.operation.as_str() {
+            "update" => {
+                if columns.is_empty() {
+                    return Ok(Err("nothing to update".to_string()));
+                }
+                let assignments: Vec<String> = columns.iter().map(|column| format!("{} = ?", column)).collect();
+                let sql = format!("UPDATE {} SET {}, updated_at = ? WHERE id = ?", table, assignments.join(", "));
+                let mut query = sqlx::query(&sql);
+                for column in &columns {
+                    query = bind_json(query, &change.data[*column]);
+                }
+                query.bind(change.updated_at).bind(record_id).execute(&mut **tx).await?;
+            }
+            "delete" => {
+                sqlx::query(&format!("DELETE FROM {} WHERE id = ?", table))
+                    .bind(record_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            other => return Ok(Err(format!("unsupported operation {}", other))),
+        }
+
+        Ok(Ok(Some(record_id)))
+    }
+}
+
+fn bind_json<'q>(
+    query: sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+    value: &Value,
+) -> sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(flag) => query.bind(*flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => query.bind(integer),
+            None => query.bind(number.as_f64()),
+        },
+        Value::String(text) => query.bind(text.clone()),
+        other => query.bind(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    // A customer last changed on the server at 10:00, after a device synced it at 09:00
+    async fn customer_changed_at_ten(db: &Database) -> i64 {
+        sqlx::query("INSERT INTO customers (name, phone, updated_at) VALUES ('Ahmed Salman', '07701112233', '2026-10-01 10:00:00')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    fn phone_edit(customer_id: i64, phone: &str, edited_at: &str) -> Value {
+        json!({
+            "table": "customers",
+            "record_id": customer_id,
+            "operation": "update",
+            "data": { "phone": phone },
+            "updated_at": edited_at,
+            "base_updated_at": "2026-10-01T09:00:00"
+        })
+    }
+
+    async fn apply(db: &Database, strategy: &str, changes: Vec<Value>) -> Value {
+        let batch: SyncBatchRequest = serde_json::from_value(json!({ "strategy": strategy, "changes": changes })).unwrap();
+        MobileLiveDataService::new().resolve_and_apply(db, batch).await.unwrap()
+    }
+
+    async fn phone(db: &Database, customer_id: i64) -> String {
+        sqlx::query_scalar("SELECT phone FROM customers WHERE id = ?").bind(customer_id).fetch_one(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn server_wins_keeps_the_server_copy_and_logs_the_rejection() {
+        let db = TestDatabase::new().await;
+        let customer_id = customer_changed_at_ten(&db).await;
+
+        let report = apply(&db, "server_wins", vec![phone_edit(customer_id, "07809998877", "2026-10-01T11:00:00")]).await;
+        assert_eq!((report["applied"].as_i64(), report["rejected"].as_i64()), (Some(0), Some(1)));
+        assert!(report["results"][0]["reason"].as_str().unwrap().starts_with("conflict"));
+        assert_eq!(phone(&db, customer_id).await, "07701112233");
+
+        let (status, strategy): (String, String) = sqlx::query_as("SELECT status, strategy FROM pending_sync WHERE record_id = ?")
+            .bind(customer_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!((status.as_str(), strategy.as_str()), ("rejected", "server_wins"));
+    }
+
+    #[tokio::test]
+    async fn last_write_wins_takes_the_newer_edit_and_refuses_the_older_one() {
+        let db = TestDatabase::new().await;
+        let customer_id = customer_changed_at_ten(&db).await;
+
+        // Made at 09:30 on the device, older than the server's 10:00 change
+        let report = apply(&db, "last_write_wins", vec![phone_edit(customer_id, "07800000001", "2026-10-01T09:30:00")]).await;
+        assert_eq!(report["results"][0]["status"], "rejected");
+        assert_eq!(phone(&db, customer_id).await, "07701112233");
+
+        // A bad change in the same batch is rejected on its own without undoing the newer edit
+        let report = apply(&db, "last_write_wins", vec![
+            json!({ "table": "users", "record_id": 1, "operation": "update", "data": { "role": "admin" }, "updated_at": "2026-10-01T11:00:00" }),
+            phone_edit(customer_id, "07809998877", "2026-10-01T11:00:00"),
+        ]).await;
+        let statuses: Vec<&str> = report["results"].as_array().unwrap().iter().map(|result| result["status"].as_str().unwrap()).collect();
+        assert_eq!(statuses, ["rejected", "applied"]);
+        assert_eq!(phone(&db, customer_id).await, "07809998877");
+        let updated_at: String = sqlx::query_scalar("SELECT updated_at FROM customers WHERE id = ?")
+            .bind(customer_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert!(updated_at.starts_with("2026-10-01") && updated_at.contains("11:00:00"), "{updated_at}");
+    }
+
+    #[tokio::test]
+    async fn ledger_totals_never_come_from_a_sync() {
+        let db = TestDatabase::new().await;
+        let customer_id = customer_changed_at_ten(&db).await;
+
+        let report = apply(&db, "last_write_wins", vec![json!({
+            "table": "customers",
+            "record_id": customer_id,
+            "operation": "update",
+            "data": { "phone": "07809998877", "current_balance": -500000 },
+            "updated_at": "2026-10-01T11:00:00"
+        })]).await;
+        assert_eq!(report["results"][0]["status"], "rejected");
+        assert_eq!(report["results"][0]["reason"], "column current_balance is kept by the server");
+        assert_eq!(phone(&db, customer_id).await, "07701112233");
+
+        let report = apply(&db, "last_write_wins", vec![json!({
+            "table": "products",
+            "operation": "insert",
+            "data": { "name": "Cable", "sku": "CBL-1", "purchase_price": 1, "selling_price": 2, "wholesale_price": 2, "current_stock": 500 },
+            "updated_at": "2026-10-01T11:00:00"
+        })]).await;
+        assert_eq!(report["rejected"], 1);
+        let products: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM products WHERE sku = 'CBL-1'").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(products, 0);
+    }
+}
@@ -28,6 +28,9 @@ pub mod file_service;
 pub mod installments_service;
 pub mod delegates_service;
 pub mod stock_movements_service;
+pub mod stock_holds_service;
+pub mod units_service;
+pub mod category_service;
 pub mod money_boxes_service;
 pub mod device_service;
 pub mod mobile_live_data_service;
@@ -36,6 +39,8 @@ pub mod database_service;
 pub mod log_service;
 pub mod branch_config_service;
 pub mod customer_receipts_service;
+pub mod audit_service;
+pub mod sequence_service;
 
 pub use auth_service::AuthService;
 pub use cache_service::CacheService;
@@ -43,15 +48,15 @@ pub use license_service::LicenseService;
 pub use device_config_service::DeviceConfigService;
 pub use bills_service::BillsService;
 // pub use cashbox_service::CashBoxService; // Removed - using money boxes only
-pub use cloud_backup_service::CloudBackupService;
+pub use cloud_backup_service::{CloudBackupService, LicenseRequired};
 pub use settings_service::SettingsService;
 pub use permissions_service::PermissionsService;
 pub use customer_service::CustomerService;
 pub use supplier_service::SupplierService;
 pub use supplier_payment_receipt_service::SupplierPaymentReceiptService;
 pub use product_service::ProductService;
-pub use sale_service::SaleService;
-pub use purchase_service::PurchaseService;
+pub use sale_service::{CreditLimitExceeded, SaleService};
+pub use purchase_service::{InconsistentAmounts, PurchaseService};
 pub use inventory_service::InventoryService;
 pub use report_service::ReportService;
 pub use reports_service::ReportsService;
@@ -75,3 +80,5 @@ pub use database_service::DatabaseService;
 pub use log_service::LogService;
 pub use branch_config_service::BranchConfigService;
 pub use customer_receipts_service::CustomerReceiptsService;
+pub use audit_service::AuditService;
+pub use sequence_service::SequenceService;
@@ -1,7 +1,7 @@
 use crate::database::Database;
 use sqlx::{Row};
 use serde_json::{Value, json};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use crate::routes::money_boxes_routes::MoneyBoxQuery;
 use anyhow::Result;
 
@@ -54,6 +54,30 @@ pub struct InternalReconcileMoneyBoxRequest {
     pub notes: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ReconcileResult {
+    pub box_id: i64,
+    pub stored_amount: f64,
+    pub computed_balance: f64,
+    pub difference: f64, // stored - computed
+    pub transaction_count: i64,
+    // Transactions whose balance_after disagrees with the running total
+    pub inconsistent_transactions: Vec<i64>,
+    // Types the ledger replay does not know the direction of; they are left out of the total
+    pub unknown_types: Vec<String>,
+    pub drifted: bool,
+    pub corrected: bool,
+    pub adjustment_transaction_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReopenMoneyBoxResult {
+    pub box_id: i64,
+    pub amount: f64,
+    pub voided_closing_transaction_id: i64,
+    pub compensating_transaction_id: i64,
+}
+
 #[derive(Clone)]
 pub struct MoneyBoxesService;
 
@@ -881,6 +905,189 @@ impl MoneyBoxesService {
         }))
     }
 
+    // Transaction types that add to / take from a box, as written across the services
+    const CREDIT_TYPES: &'static [&'static str] = &[
+        "deposit", "transfer_in", "cash_deposit", "transfer_from", "transfer_from_cash_box", "transfer_from_daily_box",
+        "transfer_from_money_box", "expense_reversal", "customer_receipt", "sale", "purchase_return", "installment_payment",
+        "cash_box_reopen",
+    ];
+    const DEBIT_TYPES: &'static [&'static str] = &[
+        "withdraw", "withdrawal", "transfer_out", "transfer_to_cashier", "transfer_to_money_box", "transfer_to_bank",
+        "cash_box_closing", "expense", "expense_update", "purchase", "supplier_payment",
+    ];
+    // Audit entry written when the stored amount is corrected; it does not move the ledger itself
+    const CORRECTION_TYPE: &'static str = "balance_correction";
+
+    // Replay the box's transactions in order and compare the result with the stored amount.
+    // The opening balance is taken from the first transaction, since initial balances have no entry of their own.
+    // A correction is only applied when every transaction type is known and each balance_after matches the replay.
+    pub async fn reconcile_box(&self, db: &Database, box_id: i64, correct: bool) -> Result<ReconcileResult> {
+        let mut tx = db.pool.begin().await?;
+
+        let stored_amount: f64 = sqlx::query("SELECT CAST(amount AS REAL) as amount FROM money_boxes WHERE id = ?")
+            .bind(box_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| row.get("amount"))
+            .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+
+        let rows = sqlx::query(r#"
+            SELECT id, type, CAST(amount AS REAL) as amount, CAST(balance_after AS REAL) as balance_after
+            FROM money_box_transactions
+            WHERE box_id = ? AND type != ?
+            ORDER BY created_at ASC, id ASC
+        "#)
+        .bind(box_id)
+        .bind(Self::CORRECTION_TYPE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut running: Option<f64> = None;
+        let mut inconsistent_transactions = Vec::new();
+        let mut unknown_types: Vec<String> = Vec::new();
+        for row in &rows {
+            let transaction_type: String = row.get("type");
+            let amount: f64 = row.get("amount");
+            let balance_after: Option<f64> = row.get("balance_after");
+
+            let signed = if Self::CREDIT_TYPES.contains(&transaction_type.as_str()) {
+                amount
+            } else if Self::DEBIT_TYPES.contains(&transaction_type.as_str()) {
+                -amount
+            } else {
+                if !unknown_types.contains(&transaction_type) {
+                    unknown_types.push(transaction_type);
+                }
+                0.0
+            };
+
+            let opening = running.unwrap_or_else(|| balance_after.map_or(0.0, |after| after - signed));
+            let balance = opening + signed;
+            if balance_after.is_some_and(|after| (after - balance).abs() >= 0.01) {
+                inconsistent_transactions.push(row.get::<i64, _>("id"));
+            }
+            running = Some(balance);
+        }
+
+        // Without any transactions there is nothing to check the stored amount against
+        let computed_balance = running.unwrap_or(stored_amount);
+        let difference = stored_amount - computed_balance;
+        let drifted = difference.abs() >= 0.01;
+
+        // A ledger with unknown types or broken running balances cannot be trusted to correct against; report only
+        let ledger_trusted = unknown_types.is_empty() && inconsistent_transactions.is_empty();
+        let mut adjustment_transaction_id = None;
+        if drifted && correct && ledger_trusted {
+            sqlx::query("UPDATE money_boxes SET amount = ?, updated_at = datetime('now') WHERE id = ?")
+                .bind(computed_balance)
+                .bind(box_id)
+                .execute(&mut *tx)
+                .await?;
+
+            let id = sqlx::query(r#"
+                INSERT INTO money_box_transactions (box_id, type, amount, balance_after, notes, created_at)
+                VALUES (?, ?, ?, ?, ?, datetime('now'))
+            "#)
+            .bind(box_id)
+            .bind(Self::CORRECTION_TYPE)
+            .bind(difference.abs())
+            .bind(computed_balance)
+            .bind(format!("تصحيح رصيد الصندوق من {:.2} إلى {:.2} حسب سجل الحركات", stored_amount, computed_balance))
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+            adjustment_transaction_id = Some(id);
+        }
+
+        tx.commit().await?;
+
+        Ok(ReconcileResult {
+            box_id,
+            stored_amount,
+            computed_balance,
+            difference,
+            transaction_count: rows.len() as i64,
+            inconsistent_transactions,
+            unknown_types,
+            drifted,
+            corrected: adjustment_transaction_id.is_some(),
+            adjustment_transaction_id,
+        })
+    }
+
+    // Undo a shift close made in error. A box counts as closed while its latest closing/reopen entry is a
+    // 'cash_box_closing'; reopening puts that amount back with a 'cash_box_reopen' entry and logs the reason.
+    // A close from before today's business day can only be undone by an admin.
+    pub async fn reopen(&self, db: &Database, box_id: i64, reason: &str, user_id: i64, is_admin: bool) -> Result<ReopenMoneyBoxResult> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(anyhow::anyhow!("سبب إعادة فتح الصندوق مطلوب"));
+        }
+
+        let mut tx = db.pool.begin().await?;
+
+        let balance: f64 = sqlx::query("SELECT CAST(amount AS REAL) as amount FROM money_boxes WHERE id = ?")
+            .bind(box_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| row.get("amount"))
+            .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+
+        let closing = sqlx::query(r#"
+            SELECT id, type, CAST(amount AS REAL) as amount, created_at,
+                   date(created_at, 'localtime') < date('now', 'localtime') as closed_before_today
+            FROM money_box_transactions
+            WHERE box_id = ? AND type IN ('cash_box_closing', 'cash_box_reopen')
+            ORDER BY id DESC LIMIT 1
+        "#)
+        .bind(box_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|row| row.get::<String, _>("type") == "cash_box_closing")
+        .ok_or_else(|| anyhow::anyhow!("الصندوق غير مغلق"))?;
+
+        if closing.get::<Option<bool>, _>("closed_before_today").unwrap_or(false) && !is_admin {
+            return Err(anyhow::anyhow!("لا يمكن إعادة فتح صندوق أغلق قبل يوم العمل الحالي إلا بواسطة المدير"));
+        }
+
+        let closing_id: i64 = closing.get("id");
+        let amount: f64 = closing.get("amount");
+        let balance_after = balance + amount;
+        sqlx::query("UPDATE money_boxes SET amount = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(balance_after)
+            .bind(box_id)
+            .execute(&mut *tx)
+            .await?;
+        let compensating_id = sqlx::query(r#"
+            INSERT INTO money_box_transactions (box_id, type, amount, balance_after, notes, created_by, created_at)
+            VALUES (?, 'cash_box_reopen', ?, ?, ?, ?, CURRENT_TIMESTAMP)
+        "#)
+        .bind(box_id)
+        .bind(amount)
+        .bind(balance_after)
+        .bind(format!("إلغاء قيد الإغلاق رقم {}: {}", closing_id, reason))
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        crate::services::AuditService::record(&mut tx, Some(user_id), "money_box_reopen", "money_box", Some(box_id), json!({
+            "reason": reason,
+            "closed_at": closing.get::<Option<String>, _>("created_at"),
+            "voided_closing_transaction_id": closing_id,
+            "compensating_transaction_id": compensating_id,
+        })).await?;
+
+        tx.commit().await?;
+
+        Ok(ReopenMoneyBoxResult {
+            box_id,
+            amount: balance_after,
+            voided_closing_transaction_id: closing_id,
+            compensating_transaction_id: compensating_id,
+        })
+    }
+
     // Get money boxes dropdown
     pub async fn get_money_boxes_dropdown(&self, db: &Database) -> Result<Value> {
         let rows = sqlx::query(
@@ -906,4 +1113,113 @@ impl MoneyBoxesService {
 
         Ok(json!(dropdown_data))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    async fn ledger(db: &Database, box_id: i64, entries: &[(&str, f64, f64)]) {
+        for (minute, (transaction_type, amount, balance_after)) in entries.iter().enumerate() {
+            sqlx::query("INSERT INTO money_box_transactions (box_id, type, amount, balance_after, created_at) VALUES (?, ?, ?, ?, ?)")
+                .bind(box_id)
+                .bind(transaction_type)
+                .bind(amount)
+                .bind(balance_after)
+                .bind(format!("2026-02-01 09:{:02}:00", minute))
+                .execute(&db.pool).await.unwrap();
+        }
+    }
+
+    async fn stored_amount(db: &Database, box_id: i64) -> f64 {
+        sqlx::query_scalar("SELECT CAST(amount AS REAL) FROM money_boxes WHERE id = ?").bind(box_id).fetch_one(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn drifted_balance_is_reported_then_corrected_on_request() {
+        let db = TestDatabase::new().await;
+        let service = MoneyBoxesService::new();
+        ledger(&db, 1, &[("deposit", 500.0, 500.0), ("withdraw", 120.0, 380.0), ("deposit", 70.0, 450.0)]).await;
+        sqlx::query("UPDATE money_boxes SET amount = 475 WHERE id = 1").execute(&db.pool).await.unwrap();
+
+        let report = service.reconcile_box(&db, 1, false).await.unwrap();
+        assert!(report.drifted && !report.corrected);
+        assert_eq!((report.stored_amount, report.computed_balance, report.difference), (475.0, 450.0, 25.0));
+        assert_eq!(stored_amount(&db, 1).await, 475.0);
+
+        let fixed = service.reconcile_box(&db, 1, true).await.unwrap();
+        assert!(fixed.corrected);
+        assert_eq!(stored_amount(&db, 1).await, 450.0);
+        let adjustment: (String, f64) = sqlx::query_as("SELECT type, CAST(amount AS REAL) FROM money_box_transactions WHERE id = ?")
+            .bind(fixed.adjustment_transaction_id.unwrap())
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(adjustment, ("balance_correction".to_string(), 25.0));
+
+        // The correction entry itself is left out of the next check
+        assert!(!service.reconcile_box(&db, 1, true).await.unwrap().drifted);
+    }
+
+    #[tokio::test]
+    async fn correction_is_refused_when_the_ledger_is_inconsistent() {
+        let db = TestDatabase::new().await;
+        let service = MoneyBoxesService::new();
+        // The second entry claims 300 after withdrawing 120 from 500
+        ledger(&db, 2, &[("deposit", 500.0, 500.0), ("withdraw", 120.0, 300.0)]).await;
+        sqlx::query("UPDATE money_boxes SET amount = 300 WHERE id = 2").execute(&db.pool).await.unwrap();
+
+        let result = service.reconcile_box(&db, 2, true).await.unwrap();
+        assert!(result.drifted);
+        assert!(!result.corrected);
+        assert_eq!(result.inconsistent_transactions.len(), 1);
+        assert_eq!(stored_amount(&db, 2).await, 300.0);
+        let corrections: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM money_box_transactions WHERE type = 'balance_correction'")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(corrections, 0);
+    }
+
+    // Box 1 holding 530 before a shift close at `closed_at` moved 500 of it out
+    async fn closed_at(db: &Database, closed_at: &str) {
+        sqlx::query("UPDATE money_boxes SET amount = 30 WHERE id = 1").execute(&db.pool).await.unwrap();
+        sqlx::query("INSERT INTO money_box_transactions (box_id, type, amount, balance_after, created_at) VALUES (1, 'cash_box_closing', 500, 30, ?)")
+            .bind(closed_at)
+            .execute(&db.pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reopen_puts_the_closing_back_and_logs_the_reason() {
+        let db = TestDatabase::new().await;
+        let service = MoneyBoxesService::new();
+        let now: String = sqlx::query_scalar("SELECT datetime('now')").fetch_one(&db.pool).await.unwrap();
+        closed_at(&db, &now).await;
+
+        let result = service.reopen(&db, 1, "أغلق بالخطأ قبل نهاية الوردية", 1, false).await.unwrap();
+        assert_eq!(result.amount, 530.0);
+        assert_eq!(stored_amount(&db, 1).await, 530.0);
+        let compensating: (String, f64, f64) = sqlx::query_as("SELECT type, CAST(amount AS REAL), CAST(balance_after AS REAL) FROM money_box_transactions WHERE id = ?")
+            .bind(result.compensating_transaction_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(compensating, ("cash_box_reopen".to_string(), 500.0, 530.0));
+
+        let (action, details): (String, String) = sqlx::query_as("SELECT action, details FROM audit_logs WHERE entity_type = 'money_box' AND entity_id = 1")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(action, "money_box_reopen");
+        assert!(details.contains("أغلق بالخطأ قبل نهاية الوردية"));
+
+        // Already open again, and the ledger still replays to the stored amount
+        assert!(service.reopen(&db, 1, "again", 1, false).await.is_err());
+        assert!(!service.reconcile_box(&db, 1, false).await.unwrap().drifted);
+    }
+
+    #[tokio::test]
+    async fn only_an_admin_reopens_a_box_closed_on_an_earlier_day() {
+        let db = TestDatabase::new().await;
+        let service = MoneyBoxesService::new();
+        let yesterday: String = sqlx::query_scalar("SELECT datetime('now', '-1 day')").fetch_one(&db.pool).await.unwrap();
+        closed_at(&db, &yesterday).await;
+
+        assert!(service.reopen(&db, 1, "late correction", 1, false).await.is_err());
+        assert_eq!(stored_amount(&db, 1).await, 30.0);
+
+        assert_eq!(service.reopen(&db, 1, "late correction", 1, true).await.unwrap().amount, 530.0);
+    }
+}
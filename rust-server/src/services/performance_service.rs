@@ -1,13 +1,69 @@
 use crate::database::Database;
 use sqlx::Result;
+use serde::Serialize;
 use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, System};
+
+// Samples closer together than this are served from the previous one; CPU usage is measured
+// between two refreshes, so a shorter gap would only add noise.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Resource usage of the server process itself
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ProcessSample {
+    pub memory_bytes: u64,
+    pub virtual_memory_bytes: u64,
+    // Percent of one core since the previous sample; 0 on the first one
+    pub cpu_usage: f32,
+    pub uptime_secs: u64,
+}
+
+struct ProcessSampler {
+    system: System,
+    pid: Pid,
+    last: Option<(Instant, ProcessSample)>,
+}
 
 #[derive(Clone)]
-pub struct PerformanceService;
+pub struct PerformanceService {
+    sampler: Arc<Mutex<ProcessSampler>>,
+}
 
 impl PerformanceService {
     pub fn new() -> Self {
-        Self
+        Self {
+            sampler: Arc::new(Mutex::new(ProcessSampler {
+                system: System::new(),
+                pid: Pid::from_u32(std::process::id()),
+                last: None,
+            })),
+        }
+    }
+
+    // Refreshes only this process rather than the whole process table, at most once per SAMPLE_INTERVAL
+    pub fn process_sample(&self) -> ProcessSample {
+        let mut sampler = self.sampler.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((taken_at, sample)) = sampler.last {
+            if taken_at.elapsed() < SAMPLE_INTERVAL {
+                return sample;
+            }
+        }
+
+        let pid = sampler.pid;
+        sampler.system.refresh_process(pid);
+        let sample = match sampler.system.process(pid) {
+            Some(process) => ProcessSample {
+                memory_bytes: process.memory(),
+                virtual_memory_bytes: process.virtual_memory(),
+                cpu_usage: process.cpu_usage(),
+                uptime_secs: process.run_time(),
+            },
+            None => ProcessSample { memory_bytes: 0, virtual_memory_bytes: 0, cpu_usage: 0.0, uptime_secs: 0 },
+        };
+        sampler.last = Some((Instant::now(), sample));
+        sample
     }
 
     pub async fn get_overview(&self, _db: &Database) -> Result<Value> {
@@ -41,4 +97,27 @@ impl PerformanceService {
     pub async fn health_check(&self, _db: &Database) -> Result<Value> {
         Ok(serde_json::json!({}))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_this_process_and_reuses_a_fresh_sample() {
+        let service = PerformanceService::new();
+
+        let first = service.process_sample();
+        assert!(first.memory_bytes > 0);
+        assert!(first.virtual_memory_bytes >= first.memory_bytes);
+
+        // A second read inside the interval is the cached sample, not a new refresh
+        let cached = service.process_sample();
+        assert_eq!(cached.memory_bytes, first.memory_bytes);
+        assert_eq!(cached.cpu_usage, first.cpu_usage);
+
+        std::thread::sleep(SAMPLE_INTERVAL + Duration::from_millis(50));
+        service.process_sample();
+        let taken_at = service.sampler.lock().unwrap().last.unwrap().0;
+        assert!(taken_at.elapsed() < SAMPLE_INTERVAL);
+    }
+}
@@ -3,13 +3,17 @@ use crate::database::Database;
 use crate::models::{
     Product, ProductQuery, CreateProductRequest, UpdateProductRequest, 
     ProductListResponse, ProductWithDetails, ProductSearchResponse, 
-    UpdateStockRequest, LowStockProduct, ImportResult
+    UpdateStockRequest, LowStockProduct, ImportResult,
+    BulkPriceUpdateRequest, PriceChangePreview, BulkPriceUpdateResult
 };
-use crate::utils::generate_unique_sku;
+use crate::utils::{generate_unique_sku, PageCursor, BARCODE_LOOKUP_LIMIT, IMPORT_ROWS_LIMIT};
+use crate::services::units_service::UnitsService;
+use crate::services::BarcodeService;
 use sqlx::{Row, SqlitePool};
 use tracing::{info, warn, error};
 use chrono::{Utc, DateTime, NaiveDate, NaiveDateTime};
 use serde_json::Value;
+use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct ProductService;
@@ -24,6 +28,11 @@ impl ProductService {
         let page = query.page.unwrap_or(1);
         let limit = query.limit.unwrap_or(1000);
         let offset = (page - 1) * limit;
+        let keyset = query.after.is_some();
+        let after = match query.after.as_deref() {
+            Some(raw) => PageCursor::decode(raw)?,
+            None => None,
+        };
 
         let mut where_conditions = vec!["1=1".to_string()];
         let mut query_params: Vec<String> = vec![];
@@ -64,30 +73,54 @@ impl ProductService {
             .await?
             .get("total");
 
+        // Keyset mode walks (created_at, id) newest first and reads one extra row to know if more remain;
+        // the total above still counts every match so the UI can show it
+        let (order_by, page_limit, page_offset) = if keyset {
+            if let Some(ref cursor) = after {
+                where_conditions.push(PageCursor::condition("p"));
+                query_params.push(cursor.created_at.clone());
+                query_params.push(cursor.created_at.clone());
+                query_params.push(cursor.id.to_string());
+            }
+            ("p.created_at DESC, p.id DESC", limit + 1, 0)
+        } else {
+            ("p.name ASC", limit, offset)
+        };
+        let where_clause = where_conditions.join(" AND ");
+
         // Get products
         let query_str = format!(
             r#"
             SELECT 
-                p.*, c.name as category_name, s.name as stock_name
+                p.*, c.name as category_name, s.name as stock_name,
+                CAST(p.created_at AS TEXT) as cursor_created_at
             FROM products p
             LEFT JOIN categories c ON p.category_id = c.id
             LEFT JOIN stocks s ON p.stock_id = s.id
             WHERE {}
-            ORDER BY p.name ASC
+            ORDER BY {}
             LIMIT ? OFFSET ?
             "#,
-            where_clause
+            where_clause, order_by
         );
 
         let mut query_builder = sqlx::query(&query_str);
         for param in &query_params {
             query_builder = query_builder.bind(param);
         }
-        query_builder = query_builder.bind(limit).bind(offset);
+        query_builder = query_builder.bind(page_limit).bind(page_offset);
+
+        let mut rows = query_builder.fetch_all(&db.pool).await?;
+        let mut next_cursor = None;
+        if keyset && rows.len() as i64 > limit {
+            rows.truncate(limit.max(0) as usize);
+            next_cursor = rows.last().map(|row| PageCursor {
+                created_at: row.get("cursor_created_at"),
+                id: row.get("id"),
+            }.encode());
+        }
 
-        let items = query_builder
-            .fetch_all(&db.pool)
-            .await?
+        let items = rows
             .into_iter()
             .map(|row| ProductWithDetails {
                 id: row.get("id"),
@@ -138,6 +171,7 @@ impl ProductService {
             page,
             limit,
             total_pages,
+            next_cursor,
         })
     }
 
@@ -217,6 +251,8 @@ impl ProductService {
             return Err(anyhow::anyhow!("سعر البيع يجب أن يكون أكبر من أو يساوي سعر الشراء"));
         }
 
+        UnitsService::ensure_allowed(db, payload.unit.as_deref().unwrap_or("قطعة")).await?;
+
         // Generate unique SKU if not provided
         let sku = if let Some(provided_sku) = &payload.sku {
             // Check if provided SKU already exists
@@ -233,8 +269,13 @@ impl ProductService {
             generate_unique_sku(&payload.name, &db.pool).await?
         };
 
+        let barcode = match payload.barcode.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+            Some(barcode) => Some(barcode.to_string()),
+            None => Self::auto_barcode(db).await?,
+        };
+
         // Check if barcode already exists
-        if let Some(ref barcode) = payload.barcode {
+        if let Some(ref barcode) = barcode {
             let existing_barcode = sqlx::query("SELECT id FROM products WHERE barcode = ?")
                 .bind(barcode)
                 .fetch_optional(&db.pool)
@@ -275,7 +316,7 @@ impl ProductService {
         .bind(&payload.description)
         .bind(payload.supported.unwrap_or(true))
         .bind(&sku)
-        .bind(&payload.barcode)
+        .bind(&barcode)
         .bind(payload.purchase_price)
         .bind(payload.selling_price)
         .bind(payload.wholesale_price)
@@ -319,6 +360,10 @@ impl ProductService {
             return Err(anyhow::anyhow!("المنتج غير موجود"));
         }
 
+        if let Some(ref unit) = payload.unit {
+            UnitsService::ensure_allowed(db, unit).await?;
+        }
+
         // Build update query dynamically
         let mut update_fields = Vec::new();
         let mut query_params: Vec<String> = vec![];
@@ -615,7 +660,83 @@ impl ProductService {
         Ok(products)
     }
 
+    // Generated EAN-13 for a new product when settings.auto_generate_barcode is on
+    async fn auto_barcode(db: &Database) -> Result<Option<String>> {
+        const MAX_ATTEMPTS: i64 = 20;
+
+        let enabled: i64 = sqlx::query("SELECT COALESCE(auto_generate_barcode, 1) as enabled FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get("enabled"))
+            .unwrap_or(1);
+        if enabled == 0 {
+            return Ok(None);
+        }
+
+        // Seed with the id the product is about to get; skip ahead past codes already taken
+        let next_id: i64 = sqlx::query(r#"
+            SELECT COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'products'), (SELECT MAX(id) FROM products), 0) + 1 as next_id
+        "#)
+        .fetch_one(&db.pool)
+        .await?
+        .get("next_id");
+
+        let barcodes = BarcodeService::new();
+        for attempt in 0..MAX_ATTEMPTS {
+            let code = barcodes.generate(next_id + attempt);
+            let taken = sqlx::query("SELECT id FROM products WHERE barcode = ?")
+                .bind(&code)
+                .fetch_optional(&db.pool)
+                .await?;
+            if taken.is_none() {
+                return Ok(Some(code));
+            }
+        }
+
+        Err(anyhow::anyhow!("تعذر توليد باركود فريد للمنتج"))
+    }
+
     // Get product by barcode
+    // Resolve many barcodes at once (cart restore, label printing). Keys are the barcodes as sent;
+    // missing or inactive products are simply absent. Barcodes compare case-insensitively like the column.
+    pub async fn get_by_barcodes(&self, db: &Database, barcodes: Vec<String>) -> Result<HashMap<String, ProductWithDetails>> {
+        // Well under SQLite's bound-parameter limit
+        const CHUNK_SIZE: usize = 500;
+
+        let mut requested: HashMap<String, String> = HashMap::new();
+        for barcode in barcodes {
+            let barcode = barcode.trim();
+            if !barcode.is_empty() {
+                requested.entry(barcode.to_lowercase()).or_insert_with(|| barcode.to_string());
+            }
+        }
+        BARCODE_LOOKUP_LIMIT.check(requested.len())?;
+
+        let keys: Vec<&String> = requested.values().collect();
+        let mut products = HashMap::with_capacity(keys.len());
+        for chunk in keys.chunks(CHUNK_SIZE) {
+            let sql = format!(r#"
+                SELECT p.*, c.name as category_name, s.name as stock_name
+                FROM products p
+                LEFT JOIN categories c ON p.category_id = c.id
+                LEFT JOIN stocks s ON p.stock_id = s.id
+                WHERE p.barcode IN ({}) AND p.is_active = 1
+            "#, vec!["?"; chunk.len()].join(", "));
+            let mut query = sqlx::query(&sql);
+            for barcode in chunk {
+                query = query.bind(*barcode);
+            }
+            for row in query.fetch_all(&db.pool).await? {
+                let product = Self::map_product_row(&row);
+                if let Some(requested_as) = product.barcode.as_ref().and_then(|barcode| requested.get(&barcode.to_lowercase())) {
+                    products.insert(requested_as.clone(), product);
+                }
+            }
+        }
+
+        Ok(products)
+    }
+
     pub async fn get_by_barcode(&self, db: &Database, barcode: &str) -> Result<Option<ProductWithDetails>> {
         let result = sqlx::query(r#"
             SELECT 
@@ -836,11 +957,12 @@ impl ProductService {
         })
     }
 
-    // Update product stock
+    // Update product stock; a manual correction also counts as a stock check for cyclic counting
     pub async fn update_stock(&self, db: &Database, id: i64, quantity: i64) -> Result<bool> {
         let changes = sqlx::query(r#"
             UPDATE products 
             SET current_stock = current_stock + ?,
+                last_stock_check = CURRENT_TIMESTAMP,
                 updated_at = CURRENT_TIMESTAMP
             WHERE id = ?
         "#)
@@ -852,8 +974,145 @@ impl ProductService {
         Ok(changes.rows_affected() > 0)
     }
 
+    // Bulk adjust a price field; with dry_run the changes are only previewed.
+    // Rows that would break selling_price >= purchase_price are reported and never written.
+    // Without product_ids or category_id the caller has to pass all: true to reprice the whole catalogue.
+    pub async fn bulk_update_prices(&self, db: &Database, payload: &BulkPriceUpdateRequest, dry_run: bool) -> Result<BulkPriceUpdateResult> {
+        let price_field = payload.price_field.clone().unwrap_or_else(|| "selling_price".to_string());
+        if !matches!(price_field.as_str(), "selling_price" | "purchase_price" | "wholesale_price") {
+            return Err(anyhow::anyhow!("حقل السعر غير صالح: {}", price_field));
+        }
+        if !matches!(payload.adjustment_type.as_str(), "percentage" | "fixed" | "set") {
+            return Err(anyhow::anyhow!("نوع التعديل غير صالح: {}", payload.adjustment_type));
+        }
+        if payload.product_ids.is_none() && payload.category_id.is_none() && payload.all != Some(true) {
+            return Err(anyhow::anyhow!("حدد المنتجات أو الفئة، أو أرسل all: true لتعديل أسعار جميع المنتجات"));
+        }
+
+        let mut conditions = vec!["is_active = 1"];
+        let mut params: Vec<i64> = Vec::new();
+
+        let placeholders;
+        if let Some(ref ids) = payload.product_ids {
+            if ids.is_empty() {
+                return Err(anyhow::anyhow!("يجب تحديد منتج واحد على الأقل"));
+            }
+            placeholders = format!("id IN ({})", vec!["?"; ids.len()].join(", "));
+            conditions.push(&placeholders);
+            params.extend(ids.iter().copied());
+        }
+        if let Some(category_id) = payload.category_id {
+            conditions.push("category_id = ?");
+            params.push(category_id);
+        }
+
+        let sql = format!(
+            "SELECT id, name, sku, purchase_price, selling_price, wholesale_price FROM products WHERE {} ORDER BY id",
+            conditions.join(" AND ")
+        );
+        let mut query_builder = sqlx::query(&sql);
+        for param in &params {
+            query_builder = query_builder.bind(param);
+        }
+        // Read and write in one transaction so a price edited in between is not overwritten from a stale read
+        let mut tx = db.pool.begin().await?;
+        let rows = query_builder.fetch_all(&mut *tx).await?;
+
+        let mut changes = Vec::new();
+        let mut violations = Vec::new();
+        for row in rows {
+            let purchase_price: f64 = row.get("purchase_price");
+            let selling_price: f64 = row.get("selling_price");
+            let old_price: f64 = row.get(price_field.as_str());
+
+            let new_price = match payload.adjustment_type.as_str() {
+                "percentage" => old_price * (1.0 + payload.value / 100.0),
+                "fixed" => old_price + payload.value,
+                _ => payload.value,
+            };
+            let new_price = (new_price * 100.0).round() / 100.0;
+
+            let (new_purchase, new_selling) = match price_field.as_str() {
+                "purchase_price" => (new_price, selling_price),
+                "selling_price" => (purchase_price, new_price),
+                _ => (purchase_price, selling_price),
+            };
+
+            let preview = PriceChangePreview {
+                product_id: row.get("id"),
+                name: row.get("name"),
+                sku: row.get("sku"),
+                old_price,
+                new_price,
+                purchase_price: new_purchase,
+                selling_price: new_selling,
+                violates_constraint: new_price < 0.0 || new_selling < new_purchase,
+            };
+
+            if preview.violates_constraint {
+                violations.push(preview.clone());
+            }
+            changes.push(preview);
+        }
+
+        let mut updated_count = 0;
+        if !dry_run {
+            let update_sql = format!(
+                "UPDATE products SET {} = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                price_field
+            );
+            for change in changes.iter().filter(|c| !c.violates_constraint && c.new_price != c.old_price) {
+                let result = sqlx::query(&update_sql)
+                    .bind(change.new_price)
+                    .bind(change.product_id)
+                    .execute(&mut *tx)
+                    .await?;
+                updated_count += result.rows_affected() as i64;
+            }
+            tx.commit().await?;
+            info!("Bulk price update applied to {} products ({} skipped)", updated_count, violations.len());
+        }
+
+        Ok(BulkPriceUpdateResult {
+            dry_run,
+            price_field,
+            changes,
+            violations,
+            updated_count,
+        })
+    }
+
+    // Look up a category by name for imports, creating it when it doesn't exist yet
+    async fn resolve_import_category(&self, db: &Database, name: &str, cache: &mut HashMap<String, i64>) -> Result<i64> {
+        if let Some(id) = cache.get(name) {
+            return Ok(*id);
+        }
+
+        let existing = sqlx::query("SELECT id FROM categories WHERE name = ? LIMIT 1")
+            .bind(name)
+            .fetch_optional(&db.pool)
+            .await?;
+
+        let id = match existing {
+            Some(row) => row.get::<i64, _>("id"),
+            None => {
+                let result = sqlx::query("INSERT INTO categories (name) VALUES (?)")
+                    .bind(name)
+                    .execute(&db.pool)
+                    .await?;
+                info!("Created category \"{}\" for product import", name);
+                result.last_insert_rowid()
+            }
+        };
+
+        cache.insert(name.to_string(), id);
+        Ok(id)
+    }
+
     // Import products from Excel/CSV file
-    pub async fn import_products(&self, db: &Database, file_content: &[u8], filename: &str) -> Result<ImportResult> {
+    // Rows without a category column value fall back to `category_override`, then to the
+    // default_import_category setting; missing categories are created on the fly.
+    pub async fn import_products(&self, db: &Database, file_content: &[u8], filename: &str, category_override: Option<&str>) -> Result<ImportResult> {
         use calamine::{open_workbook, DataType, Xlsx, Reader};
         use std::io::Cursor;
         use chrono::NaiveDate;
@@ -873,6 +1132,18 @@ impl ProductService {
             None => return Err(anyhow::anyhow!("No default main stock found. Please create a main stock first."))
         };
 
+        // Resolve the import-wide fallback category
+        let default_category = match category_override.map(|c| c.trim()).filter(|c| !c.is_empty()) {
+            Some(name) => Some(name.to_string()),
+            None => sqlx::query("SELECT default_import_category FROM settings WHERE id = 1")
+                .fetch_optional(&db.pool)
+                .await?
+                .and_then(|row| row.get::<Option<String>, _>("default_import_category"))
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty()),
+        };
+        let mut category_ids: HashMap<String, i64> = HashMap::new();
+
         // Determine file type and process accordingly
         if filename.to_lowercase().ends_with(".xlsx") || filename.to_lowercase().ends_with(".xls") {
             // Process Excel file using temporary file
@@ -907,6 +1178,7 @@ impl ProductService {
                 if rows.is_empty() {
                     return Err(anyhow::anyhow!("Excel file is empty"));
                 }
+                IMPORT_ROWS_LIMIT.check(rows.len() - 1)?;
 
                 // Use first row as headers
                 let headers = &rows[0];
@@ -933,6 +1205,11 @@ impl ProductService {
                         .contains(&h.to_lowercase().trim())
                 });
 
+                let category_index = headers.iter().position(|h| {
+                    ["category", "category_name", "الفئة", "التصنيف"]
+                        .contains(&h.to_lowercase().trim())
+                });
+
                 // Process data rows
                 for (row_index, row) in data_rows.iter().enumerate() {
                     total += 1;
@@ -1013,6 +1290,16 @@ impl ProductService {
                         continue;
                     }
 
+                    // Resolve category: row value first, then the import default
+                    let row_category = category_index
+                        .and_then(|idx| row.get(idx))
+                        .map(|c| c.trim().to_string())
+                        .filter(|c| !c.is_empty());
+                    let category_id = match row_category.or_else(|| default_category.clone()) {
+                        Some(name) => Some(self.resolve_import_category(db, &name, &mut category_ids).await?),
+                        None => None,
+                    };
+
                     // Generate unique SKU
                     let sku = generate_unique_sku(&product_name, &db.pool).await?;
 
@@ -1022,8 +1309,8 @@ impl ProductService {
                             name, description, supported, sku, company_name,
                             purchase_price, selling_price, wholesale_price,
                             current_stock, min_stock, unit, units_per_box,
-                            is_dolar, expiry_date, stock_id
-                        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                            is_dolar, expiry_date, stock_id, category_id
+                        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                     "#)
                     .bind(&product_name)
                     .bind("") // description
@@ -1040,6 +1327,7 @@ impl ProductService {
                     .bind(false) // is_dolar
                     .bind(expiry_date)
                     .bind(default_stock_id)
+                    .bind(category_id)
                     .execute(&db.pool)
                     .await?;
 
@@ -1084,13 +1372,20 @@ impl ProductService {
                     .contains(&h.as_str())
             });
 
+            let category_index = headers.iter().position(|h| {
+                ["category", "category_name", "الفئة", "التصنيف"]
+                    .contains(&h.as_str())
+            });
+
+            // Read every record up front so an oversized file is refused before anything is written
+            let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+            IMPORT_ROWS_LIMIT.check(records.len())?;
+
             // Process data rows
-            for (row_index, result) in reader.records().enumerate() {
+            for (row_index, row) in records.into_iter().enumerate() {
                 total += 1;
                 let actual_row_index = row_index + 2; // +1 for 0-based index, +1 for header
 
-                let row = result?;
-
                 // Extract product name
                 let product_name = if product_name_index < row.len() {
                     row[product_name_index].trim().to_string()
@@ -1166,6 +1461,16 @@ impl ProductService {
                     continue;
                 }
 
+                // Resolve category: row value first, then the import default
+                let row_category = category_index
+                    .and_then(|idx| row.get(idx))
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty());
+                let category_id = match row_category.or_else(|| default_category.clone()) {
+                    Some(name) => Some(self.resolve_import_category(db, &name, &mut category_ids).await?),
+                    None => None,
+                };
+
                 // Generate unique SKU
                 let sku = generate_unique_sku(&product_name, &db.pool).await?;
 
@@ -1175,8 +1480,8 @@ impl ProductService {
                         name, description, supported, sku, company_name,
                         purchase_price, selling_price, wholesale_price,
                         current_stock, min_stock, unit, units_per_box,
-                        is_dolar, expiry_date, stock_id
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        is_dolar, expiry_date, stock_id, category_id
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                 "#)
                 .bind(&product_name)
                 .bind("") // description
@@ -1193,6 +1498,7 @@ impl ProductService {
                 .bind(false) // is_dolar
                 .bind(expiry_date)
                 .bind(default_stock_id)
+                .bind(category_id)
                 .execute(&db.pool)
                 .await?;
 
@@ -1291,4 +1597,223 @@ impl ProductService {
 
         Ok(products)
     }
+
+    // Row from a `p.*, category_name, stock_name` select, for services listing products outside this one
+    pub fn map_product_row(row: &sqlx::sqlite::SqliteRow) -> ProductWithDetails {
+        ProductWithDetails {
+            id: row.get("id"),
+            name: row.get("name"),
+            scientific_name: row.get("scientific_name"),
+            description: row.get("description"),
+            supported: row.get("supported"),
+            sku: row.get("sku"),
+            barcode: row.get("barcode"),
+            purchase_price: row.get("purchase_price"),
+            selling_price: row.get("selling_price"),
+            wholesale_price: row.get("wholesale_price"),
+            company_name: row.get("company_name"),
+            current_stock: row.get("current_stock"),
+            min_stock: row.get("min_stock"),
+            max_stock: row.get("max_stock"),
+            total_sold: row.get("total_sold"),
+            total_purchased: row.get("total_purchased"),
+            unit: row.get("unit"),
+            units_per_box: row.get("units_per_box"),
+            is_dolar: row.get("is_dolar"),
+            expiry_date: row.get("expiry_date"),
+            is_active: row.get("is_active"),
+            last_purchase_date: row.get("last_purchase_date"),
+            last_purchase_price: row.get("last_purchase_price"),
+            average_cost: row.get("average_cost"),
+            reorder_point: row.get("reorder_point"),
+            category_id: row.get("category_id"),
+            stock_id: row.get("stock_id"),
+            location_in_stock: row.get("location_in_stock"),
+            shelf_number: row.get("shelf_number"),
+            rack_number: row.get("rack_number"),
+            bin_number: row.get("bin_number"),
+            last_stock_check: row.get("last_stock_check"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            supplier_name: None,
+            category_name: row.get("category_name"),
+            stock_name: row.get("stock_name"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    async fn add_product(db: &Database, sku: &str, purchase: f64, selling: f64) -> i64 {
+        sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price) VALUES (?, ?, ?, ?, ?)")
+            .bind(format!("منتج {sku}"))
+            .bind(sku)
+            .bind(purchase)
+            .bind(selling)
+            .bind(selling)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    async fn selling_price(db: &Database, id: i64) -> f64 {
+        sqlx::query_scalar("SELECT selling_price FROM products WHERE id = ?").bind(id).fetch_one(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn dry_run_previews_and_apply_skips_rows_below_cost() {
+        let db = TestDatabase::new().await;
+        let rice = add_product(&db, "RICE-5KG", 8000.0, 10000.0).await;
+        let oil = add_product(&db, "OIL-1L", 2400.0, 2500.0).await;
+        let payload = BulkPriceUpdateRequest {
+            product_ids: Some(vec![rice, oil]),
+            category_id: None,
+            all: None,
+            price_field: None,
+            adjustment_type: "percentage".to_string(),
+            value: -10.0,
+        };
+
+        let preview = ProductService::new().bulk_update_prices(&db, &payload, true).await.unwrap();
+        assert_eq!(preview.changes.len(), 2);
+        assert_eq!(preview.changes[0].new_price, 9000.0);
+        // 2250 would sell oil under its 2400 cost
+        assert_eq!(preview.violations.len(), 1);
+        assert_eq!(preview.violations[0].product_id, oil);
+        assert_eq!(preview.updated_count, 0);
+        assert_eq!(selling_price(&db, rice).await, 10000.0);
+
+        let applied = ProductService::new().bulk_update_prices(&db, &payload, false).await.unwrap();
+        assert_eq!(applied.updated_count, 1);
+        assert_eq!(selling_price(&db, rice).await, 9000.0);
+        assert_eq!(selling_price(&db, oil).await, 2500.0);
+
+        // No filter reprices nothing unless the whole catalogue is asked for
+        let everything = BulkPriceUpdateRequest { product_ids: None, ..payload };
+        assert!(ProductService::new().bulk_update_prices(&db, &everything, false).await.is_err());
+        assert_eq!(selling_price(&db, rice).await, 9000.0);
+        let everything = BulkPriceUpdateRequest { all: Some(true), ..everything };
+        assert_eq!(ProductService::new().bulk_update_prices(&db, &everything, true).await.unwrap().changes.len(), 2);
+    }
+
+    async fn category_of(db: &Database, name: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT c.name FROM products p LEFT JOIN categories c ON c.id = p.category_id WHERE p.name = ?")
+            .bind(name)
+            .fetch_one(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn import_falls_back_to_the_override_then_the_default_category() {
+        let db = TestDatabase::new().await;
+        sqlx::query("UPDATE settings SET default_import_category = 'عام' WHERE id = 1").execute(&db.pool).await.unwrap();
+        let service = ProductService::new();
+
+        let csv = "name,price,category\nPepsi 330ml,750,مشروبات\nTissue box,1250,\nNapkins,500,\n";
+        let result = service.import_products(&db, csv.as_bytes(), "items.csv", None).await.unwrap();
+        assert_eq!(result.imported, 3);
+        assert_eq!(category_of(&db, "Pepsi 330ml").await.as_deref(), Some("مشروبات"));
+        assert_eq!(category_of(&db, "Tissue box").await.as_deref(), Some("عام"));
+        assert_eq!(category_of(&db, "Napkins").await.as_deref(), Some("عام"));
+
+        let csv = "name,price\nLantern,18000\n";
+        service.import_products(&db, csv.as_bytes(), "season.csv", Some(" موسمي ")).await.unwrap();
+        assert_eq!(category_of(&db, "Lantern").await.as_deref(), Some("موسمي"));
+
+        // The fallback category is created for the first row and reused by the next
+        let general: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM categories WHERE name = 'عام'").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(general, 1);
+    }
+
+    #[tokio::test]
+    async fn generated_barcode_skips_a_code_already_in_use() {
+        let db = TestDatabase::new().await;
+        let service = ProductService::new();
+        let new_product = |sku: &str| serde_json::from_value::<CreateProductRequest>(serde_json::json!({
+            "name": format!("Biscuit {sku}"),
+            "sku": sku,
+            "purchase_price": 500.0,
+            "selling_price": 750.0,
+            "wholesale_price": 650.0
+        })).unwrap();
+
+        // A hand-entered barcode already holds the code the next product would get
+        let barcodes = BarcodeService::new();
+        let squatter = add_product(&db, "MANUAL-1", 100.0, 200.0).await;
+        sqlx::query("UPDATE products SET barcode = ? WHERE id = ?")
+            .bind(barcodes.generate(squatter + 1))
+            .bind(squatter)
+            .execute(&db.pool).await.unwrap();
+
+        let product = service.create(&db, new_product("BSC-1")).await.unwrap();
+        let barcode = product.barcode.clone().unwrap();
+        assert_eq!(barcode, barcodes.generate(squatter + 2));
+        assert!(barcodes.validate(&barcode));
+
+        sqlx::query("UPDATE settings SET auto_generate_barcode = 0 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let plain = service.create(&db, new_product("BSC-2")).await.unwrap();
+        assert_eq!(plain.barcode, None);
+    }
+
+    #[tokio::test]
+    async fn barcode_batch_returns_only_found_active_products() {
+        let db = TestDatabase::new().await;
+        let mut ids = Vec::new();
+        for (sku, barcode, is_active) in [("TEA", "6291041500213", 1), ("SOAP", "6281031259087", 1), ("OLD-GUM", "6223000111222", 0)] {
+            let id = add_product(&db, sku, 500.0, 750.0).await;
+            sqlx::query("UPDATE products SET barcode = ?, is_active = ? WHERE id = ?")
+                .bind(barcode).bind(is_active).bind(id)
+                .execute(&db.pool).await.unwrap();
+            ids.push(id);
+        }
+
+        // More than one chunk's worth of unknown codes around the real ones
+        let mut barcodes: Vec<String> = (0..700).map(|n| format!("200000{:07}", n)).collect();
+        barcodes.insert(350, "6281031259087".to_string());
+        barcodes.extend(["6291041500213", " 6291041500213 ", "6223000111222", ""].map(String::from));
+
+        let found = ProductService::new().get_by_barcodes(&db, barcodes).await.unwrap();
+        let mut resolved: Vec<(&str, i64)> = found.iter().map(|(barcode, product)| (barcode.as_str(), product.id)).collect();
+        resolved.sort();
+        assert_eq!(resolved, [("6281031259087", ids[1]), ("6291041500213", ids[0])]);
+    }
+
+    #[tokio::test]
+    async fn cursor_pages_cover_every_product_once() {
+        let db = TestDatabase::new().await;
+        // Batches of rows sharing a created_at, so the id tie-break decides their order
+        for n in 0..23 {
+            let id = add_product(&db, &format!("SKU-{n:02}"), 100.0, 150.0).await;
+            sqlx::query("UPDATE products SET created_at = ? WHERE id = ?")
+                .bind(format!("2026-10-{:02} 09:00:00", 1 + n / 4))
+                .bind(id)
+                .execute(&db.pool).await.unwrap();
+        }
+        let expected: Vec<i64> = sqlx::query_scalar("SELECT id FROM products ORDER BY created_at DESC, id DESC")
+            .fetch_all(&db.pool).await.unwrap();
+        let service = ProductService::new();
+
+        let mut seen = Vec::new();
+        let mut after = String::new();
+        loop {
+            let query: ProductQuery = serde_json::from_value(serde_json::json!({ "limit": 5, "after": after })).unwrap();
+            let page = service.get_all(&db, &query).await.unwrap();
+            assert!(page.items.len() <= 5);
+            seen.extend(page.items.iter().map(|product| product.id));
+            match page.next_cursor {
+                Some(cursor) => after = cursor,
+                None => break,
+            }
+            // A product added mid-walk is newer than every cursor, so it neither shifts nor repeats rows
+            if seen.len() == 10 {
+                let late = add_product(&db, "LATE-ARRIVAL", 100.0, 150.0).await;
+                sqlx::query("UPDATE products SET created_at = '2026-11-01 09:00:00' WHERE id = ?").bind(late).execute(&db.pool).await.unwrap();
+            }
+        }
+        assert_eq!(seen, expected);
+
+        let bad: ProductQuery = serde_json::from_value(serde_json::json!({ "limit": 5, "after": "not-a-cursor!" })).unwrap();
+        assert!(service.get_all(&db, &bad).await.unwrap_err().downcast_ref::<crate::utils::InvalidCursor>().is_some());
+    }
 }
@@ -1,8 +1,8 @@
 use anyhow::Result;
 use crate::database::Database;
 use crate::models::{
-    Purchase, PurchaseItem, CreatePurchaseRequest, UpdatePurchaseRequest, 
-    PurchaseWithDetails, PurchaseListResponse, PurchaseReturnRequest,
+    Purchase, PurchaseItem, CreatePurchaseRequest, CreatePurchaseItemRequest, UpdatePurchaseRequest, 
+    PurchaseWithDetails, PurchaseListResponse, PurchaseQuery, PurchaseReturnRequest,
     PurchaseWithReturns, PurchaseReturnResponse,
     PurchaseWarning, CreditStatus
 };
@@ -12,6 +12,22 @@ use tracing::{info, warn, error};
 use chrono::{Utc, DateTime, NaiveDate, NaiveDateTime};
 use serde_json::Value;
 use std::collections::HashMap;
+use crate::utils::PURCHASE_ITEMS_LIMIT;
+
+// Tolerance when comparing money amounts computed in floating point
+const AMOUNT_EPSILON: f64 = 0.01;
+
+// Payload totals or paid amount that don't agree with the items; routes answer 400 for it
+#[derive(Debug)]
+pub struct InconsistentAmounts(pub String);
+
+impl std::fmt::Display for InconsistentAmounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InconsistentAmounts {}
 
 #[derive(Clone)]
 pub struct PurchaseService;
@@ -33,10 +49,14 @@ impl PurchaseService {
 
     // Helper function to check supplier credit limit
     async fn check_supplier_credit_limit(&self, db: &Database, supplier_id: i64, purchase_amount: f64) -> Result<(Vec<PurchaseWarning>, CreditStatus)> {
+        // suppliers carry no balance or limit columns: what is owed is the sum of open purchases,
+        // and no supplier has a limit until the table grows one
         let supplier = sqlx::query(r#"
-            SELECT id, name, credit_limit, current_balance 
-            FROM suppliers 
-            WHERE id = ?
+            SELECT s.id, s.name, CAST(NULL AS REAL) as credit_limit,
+                   COALESCE((SELECT SUM(p.remaining_amount) FROM purchases p
+                             WHERE p.supplier_id = s.id AND p.status != 'cancelled'), 0.0) as current_balance
+            FROM suppliers s
+            WHERE s.id = ?
         "#)
         .bind(supplier_id)
         .fetch_one(&db.pool)
@@ -93,10 +113,32 @@ impl PurchaseService {
         Ok((warnings, credit_status))
     }
 
-    // Get all purchases
-    pub async fn get_all(&self, db: &Database) -> Result<Vec<PurchaseWithDetails>> {
-        // First get all purchases without items, including return information
-        let purchases = sqlx::query(r#"
+    // Get all purchases with supplier and item data, filtered and paginated like sales
+    pub async fn get_all(&self, db: &Database, query: &PurchaseQuery) -> Result<PurchaseListResponse> {
+        let page = query.page.unwrap_or(1);
+        let limit = query.limit.unwrap_or(50);
+        let offset = (page - 1) * limit;
+
+        let (where_clause, params) = Self::purchase_filters(query);
+
+        // Get total count for pagination
+        let count_query = format!(
+            "SELECT COUNT(*) as total FROM purchases p {}",
+            where_clause
+        );
+
+        let mut count_query_builder = sqlx::query(&count_query);
+        for param in &params {
+            count_query_builder = count_query_builder.bind(param.as_str());
+        }
+        let total: i64 = count_query_builder
+            .fetch_one(&db.pool)
+            .await?
+            .get("total");
+
+        // Returns are summed in subqueries so joining the items does not multiply them
+        let purchases_query = format!(
+            r#"
             SELECT 
                 p.*,
                 s.name as supplier_name,
@@ -104,61 +146,59 @@ impl PurchaseService {
                 s.phone as supplier_phone,
                 s.email as supplier_email,
                 s.address as supplier_address,
-                COALESCE(SUM(pr.total_amount), 0) as total_returned_amount,
-                COUNT(pr.id) as return_count,
-                MAX(pr.return_date) as last_return_date
+                (SELECT COALESCE(SUM(pr.total_amount), 0.0) FROM purchase_returns pr WHERE pr.purchase_id = p.id) as total_returned_amount,
+                (SELECT COUNT(*) FROM purchase_returns pr WHERE pr.purchase_id = p.id) as return_count,
+                (SELECT date(MAX(pr.return_date)) FROM purchase_returns pr WHERE pr.purchase_id = p.id) as last_return_date,
+                json_group_array(
+                    json_object(
+                        'id', pi.id,
+                        'purchase_id', pi.purchase_id,
+                        'product_id', pi.product_id,
+                        'stock_id', pi.stock_id,
+                        'quantity', pi.quantity,
+                        'price', pi.price,
+                        'discount_percent', pi.discount_percent,
+                        'tax_percent', pi.tax_percent,
+                        'total', pi.total,
+                        'returned_quantity', pi.returned_quantity,
+                        'expiry_date', date(pi.expiry_date),
+                        'batch_number', pi.batch_number,
+                        'notes', pi.notes,
+                        'created_at', strftime('%Y-%m-%dT%H:%M:%S', pi.created_at),
+                        'updated_at', strftime('%Y-%m-%dT%H:%M:%S', pi.updated_at),
+                        'product_name', pr_product.name,
+                        'product_sku', pr_product.sku
+                    )
+                ) FILTER (WHERE pi.id IS NOT NULL) as items
             FROM purchases p
             LEFT JOIN suppliers s ON p.supplier_id = s.id
-            LEFT JOIN purchase_returns pr ON p.id = pr.purchase_id
-            GROUP BY p.id, p.supplier_id, p.invoice_no, p.invoice_date, p.due_date, p.total_amount, 
-                     p.discount_amount, p.tax_amount, p.net_amount, p.paid_amount, p.remaining_amount,
-                     p.payment_method, p.payment_status, p.status, p.notes, p.created_by, p.created_at, 
-                     p.updated_at, p.money_box_id, s.name, s.contact_person, s.phone, s.email, s.address
+            LEFT JOIN purchase_items pi ON p.id = pi.purchase_id
+            LEFT JOIN products pr_product ON pi.product_id = pr_product.id
+            {}
+            GROUP BY p.id
             ORDER BY p.created_at DESC
-        "#)
-        .fetch_all(&db.pool)
-        .await?;
-
-        // Then fetch items for each purchase
-        let mut result = Vec::new();
-        for row in purchases {
-            let purchase_id: i64 = row.get("id");
-            
-            // Get purchase items
-            let items = sqlx::query(r#"
-                SELECT pi.*, p.name as product_name, p.sku as product_sku
-                FROM purchase_items pi
-                LEFT JOIN products p ON pi.product_id = p.id
-                WHERE pi.purchase_id = ?
-            "#)
-            .bind(purchase_id)
-            .fetch_all(&db.pool)
-            .await?;
+            LIMIT ? OFFSET ?
+            "#,
+            where_clause
+        );
 
-            // Process items
-            let purchase_items = items.into_iter().map(|item_row| {
-                crate::models::PurchaseItemWithDetails {
-                    id: item_row.get("id"),
-                    purchase_id: item_row.get("purchase_id"),
-                    product_id: item_row.get("product_id"),
-                    stock_id: item_row.get("stock_id"),
-                    quantity: item_row.get("quantity"),
-                    price: item_row.get("price"),
-                    discount_percent: item_row.get("discount_percent"),
-                    tax_percent: item_row.get("tax_percent"),
-                    total: item_row.get("total"),
-                    returned_quantity: item_row.get("returned_quantity"),
-                    expiry_date: item_row.get("expiry_date"),
-                    batch_number: item_row.get("batch_number"),
-                    notes: item_row.get("notes"),
-                    created_at: item_row.get("created_at"),
-                    updated_at: item_row.get("updated_at"),
-                    product_name: item_row.get("product_name"),
-                    product_sku: item_row.get("product_sku"),
-                }
-            }).collect();
+        let mut query_builder = sqlx::query(&purchases_query);
+        for param in &params {
+            query_builder = query_builder.bind(param.as_str());
+        }
+        let rows = query_builder.bind(limit).bind(offset).fetch_all(&db.pool).await?;
+
+        let purchases = rows.iter().map(|row| {
+            let items_json: Option<String> = row.get("items");
+            let items = items_json
+                .and_then(|json| {
+                    serde_json::from_str(&json)
+                        .map_err(|e| warn!("Failed to parse items for purchase {}: {}", row.get::<i64, _>("id"), e))
+                        .ok()
+                })
+                .unwrap_or_default();
 
-            let purchase = PurchaseWithDetails {
+            PurchaseWithDetails {
                 id: row.get("id"),
                 supplier_id: row.get("supplier_id"),
                 invoice_no: row.get("invoice_no"),
@@ -186,15 +226,54 @@ impl PurchaseService {
                 total_returned_amount: row.get("total_returned_amount"),
                 return_count: row.get("return_count"),
                 last_return_date: row.get("last_return_date"),
-                items: purchase_items,
+                items,
                 warnings: None,
                 credit_status: None,
-            };
+            }
+        }).collect();
+
+        Ok(PurchaseListResponse {
+            purchases,
+            total,
+            page,
+            limit,
+            total_pages: (total + limit - 1) / limit,
+        })
+    }
 
-            result.push(purchase);
+    // WHERE clause shared by the count and the data query so both see the same purchases
+    fn purchase_filters(query: &PurchaseQuery) -> (String, Vec<String>) {
+        let mut conditions = Vec::new();
+        let mut params: Vec<String> = Vec::new();
+
+        if let Some(supplier_id) = query.supplier_id {
+            conditions.push("p.supplier_id = ?");
+            params.push(supplier_id.to_string());
+        }
+        if let Some(ref payment_status) = query.payment_status {
+            conditions.push("p.payment_status = ?");
+            params.push(payment_status.clone());
+        }
+        if let Some(ref status) = query.status {
+            conditions.push("p.status = ?");
+            params.push(status.clone());
+        }
+        if let Some(ref start_date) = query.start_date {
+            conditions.push("p.invoice_date >= ?");
+            params.push(start_date.to_string());
+        }
+        if let Some(ref end_date) = query.end_date {
+            conditions.push("p.invoice_date <= ?");
+            params.push(end_date.to_string());
         }
 
-        Ok(result)
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        (where_clause, params)
     }
 
     // Get purchase by ID
@@ -206,9 +285,9 @@ impl PurchaseService {
                    s.phone as supplier_phone,
                    s.email as supplier_email,
                    s.address as supplier_address,
-                   COALESCE(SUM(pr.total_amount), 0) as total_returned_amount,
+                   COALESCE(SUM(pr.total_amount), 0.0) as total_returned_amount,
                    COUNT(pr.id) as return_count,
-                   MAX(pr.return_date) as last_return_date
+                   date(MAX(pr.return_date)) as last_return_date
             FROM purchases p
             LEFT JOIN suppliers s ON p.supplier_id = s.id
             LEFT JOIN purchase_returns pr ON p.id = pr.purchase_id
@@ -397,6 +476,7 @@ impl PurchaseService {
         if purchase.items.is_empty() {
             return Err(anyhow::anyhow!("At least one item is required"));
         }
+        PURCHASE_ITEMS_LIMIT.check(purchase.items.len())?;
 
         // Validate each item
         for item in &purchase.items {
@@ -442,6 +522,14 @@ impl PurchaseService {
         }
 
         let net_amount = total_amount - discount_amount + tax_amount;
+        Self::check_payload_amounts(&purchase, total_amount, discount_amount, tax_amount, net_amount)?;
+
+        // remaining_amount is generated from net_amount - paid_amount, so only paid_amount is written
+        let paid_amount = purchase.paid_amount.unwrap_or(0.0);
+        if paid_amount < 0.0 || paid_amount - net_amount > AMOUNT_EPSILON {
+            return Err(InconsistentAmounts(format!("المبلغ المدفوع يجب أن يكون بين 0 و {:.2}", net_amount)).into());
+        }
+        let paid_amount = paid_amount.min(net_amount);
 
         // Check credit limit
         let (warnings, credit_status) = self.check_supplier_credit_limit(db, purchase.supplier_id, net_amount).await?;
@@ -454,59 +542,69 @@ impl PurchaseService {
             .unwrap_or(&format!("PUR-{}-{}", timestamp, random_suffix))
             .to_string();
 
-        // Use database transaction to ensure consistency
-        let result = sqlx::query(r#"
-            INSERT INTO purchases (
-                supplier_id, invoice_no, invoice_date, due_date,
-                total_amount, discount_amount, tax_amount, net_amount,
-                paid_amount, payment_method, payment_status, status,
-                notes, created_by, money_box_id
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#)
-        .bind(purchase.supplier_id)
-        .bind(&invoice_no)
-        .bind(purchase.invoice_date)
-        .bind(purchase.due_date)
-        .bind(total_amount)
-        .bind(discount_amount)
-        .bind(tax_amount)
-        .bind(net_amount)
-        .bind(purchase.payment_status.as_deref().unwrap_or("unpaid"))
-        .bind(purchase.payment_method.as_deref().unwrap_or("cash"))
-        .bind(purchase.payment_status.as_deref().unwrap_or("unpaid"))
-        .bind(purchase.status.as_deref().unwrap_or("completed"))
-        .bind(&purchase.notes)
-        .bind(user_id)
-        .bind(purchase.money_box_id)
-        .execute(&db.pool)
-        .await?;
+        // Use database transaction to ensure consistency, started over if another writer holds the database
+        let purchase = &purchase;
+        let invoice_no = &invoice_no;
+        let purchase_id = db.with_retry(move || async move {
+            let mut tx = db.pool.begin().await?;
+            let result = sqlx::query(r#"
+                INSERT INTO purchases (
+                    supplier_id, invoice_no, invoice_date, due_date,
+                    total_amount, discount_amount, tax_amount, net_amount,
+                    paid_amount, payment_method, payment_status, status,
+                    notes, created_by, money_box_id
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#)
+            .bind(purchase.supplier_id)
+            .bind(invoice_no)
+            .bind(purchase.invoice_date)
+            .bind(purchase.due_date)
+            .bind(total_amount)
+            .bind(discount_amount)
+            .bind(tax_amount)
+            .bind(net_amount)
+            .bind(paid_amount)
+            .bind(purchase.payment_method.as_deref().unwrap_or("cash"))
+            .bind(purchase.payment_status.as_deref().unwrap_or("unpaid"))
+            .bind(purchase.status.as_deref().unwrap_or("completed"))
+            .bind(&purchase.notes)
+            .bind(user_id)
+            .bind(purchase.money_box_id)
+            .execute(&mut *tx)
+            .await?;
 
-        let purchase_id = result.last_insert_rowid();
+            let purchase_id = result.last_insert_rowid();
 
-        // Insert purchase items
-        for item in &purchase.items {
-            let item_total = item.quantity as f64 * item.price;
-            let item_discount = item_total * ((item.discount_percent.unwrap_or(0.0)) / 100.0);
-            let item_tax = (item_total - item_discount) * ((item.tax_percent.unwrap_or(0.0)) / 100.0);
-            let item_net_total = item_total - item_discount + item_tax;
+            // Insert purchase items
+            for item in &purchase.items {
+                let item_total = item.quantity as f64 * item.price;
+                let item_discount = item_total * ((item.discount_percent.unwrap_or(0.0)) / 100.0);
+                let item_tax = (item_total - item_discount) * ((item.tax_percent.unwrap_or(0.0)) / 100.0);
+                let item_net_total = item_total - item_discount + item_tax;
 
-            sqlx::query(r#"
-                INSERT INTO purchase_items (
-                    purchase_id, product_id, stock_id, quantity, price,
-                    discount_percent, tax_percent, total, created_at
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
-            "#)
-            .bind(purchase_id)
-            .bind(item.product_id)
-            .bind(item.stock_id)
-            .bind(item.quantity)
-            .bind(item.price)
-            .bind(item.discount_percent.unwrap_or(0.0))
-            .bind(item.tax_percent.unwrap_or(0.0))
-            .bind(item_net_total)
-            .execute(&db.pool)
-            .await?;
-        }
+                sqlx::query(r#"
+                    INSERT INTO purchase_items (
+                        purchase_id, product_id, stock_id, quantity, price,
+                        discount_percent, tax_percent, total, created_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+                "#)
+                .bind(purchase_id)
+                .bind(item.product_id)
+                .bind(item.stock_id)
+                .bind(item.quantity)
+                .bind(item.price)
+                .bind(item.discount_percent.unwrap_or(0.0))
+                .bind(item.tax_percent.unwrap_or(0.0))
+                .bind(item_net_total)
+                .execute(&mut *tx)
+                .await?;
+
+                Self::receive_item_stock(&mut tx, purchase_id, invoice_no, purchase.invoice_date, item, user_id).await?;
+            }
+
+            tx.commit().await?;
+            Ok(purchase_id)
+        }).await?;
 
         // Get the created purchase
         let mut created_purchase = self.get_by_id(db, purchase_id).await?.unwrap();
@@ -521,8 +619,135 @@ impl PurchaseService {
         Ok(created_purchase)
     }
 
+    // Totals sent with the payload must match what the items add up to, and the net amount must be
+    // total - discount + tax, otherwise the generated remaining_amount would not mean what the client showed
+    fn check_payload_amounts(
+        purchase: &CreatePurchaseRequest,
+        total_amount: f64,
+        discount_amount: f64,
+        tax_amount: f64,
+        net_amount: f64,
+    ) -> Result<()> {
+        let differs = |sent: Option<f64>, expected: f64| sent.is_some_and(|sent| (sent - expected).abs() > AMOUNT_EPSILON);
+
+        if differs(purchase.total_amount, total_amount) {
+            return Err(InconsistentAmounts(format!("الإجمالي المرسل لا يطابق مجموع الأصناف ({:.2})", total_amount)).into());
+        }
+        if differs(purchase.discount_amount, discount_amount) {
+            return Err(InconsistentAmounts(format!("الخصم المرسل لا يطابق خصم الأصناف ({:.2})", discount_amount)).into());
+        }
+        if differs(purchase.tax_amount, tax_amount) {
+            return Err(InconsistentAmounts(format!("الضريبة المرسلة لا تطابق ضريبة الأصناف ({:.2})", tax_amount)).into());
+        }
+
+        let expected_net = purchase.total_amount.unwrap_or(total_amount)
+            - purchase.discount_amount.unwrap_or(discount_amount)
+            + purchase.tax_amount.unwrap_or(tax_amount);
+        if differs(purchase.net_amount, expected_net) || (expected_net - net_amount).abs() > AMOUNT_EPSILON {
+            return Err(InconsistentAmounts(format!(
+                "الصافي يجب أن يساوي الإجمالي - الخصم + الضريبة ({:.2})",
+                net_amount
+            )).into());
+        }
+
+        Ok(())
+    }
+
+    // Adds a received line to the product's stock, records the movement into the line's warehouse and
+    // blends the line's price into average_cost. Products never costed fall back to purchase_price as
+    // the prior average, like the inventory valuation does.
+    async fn receive_item_stock(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        purchase_id: i64,
+        invoice_no: &str,
+        invoice_date: NaiveDate,
+        item: &CreatePurchaseItemRequest,
+        user_id: Option<i64>,
+    ) -> Result<()> {
+        let product = sqlx::query(
+            "SELECT current_stock, COALESCE(NULLIF(average_cost, 0), purchase_price, 0) as average_cost FROM products WHERE id = ?"
+        )
+        .bind(item.product_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("المنتج رقم {} غير موجود", item.product_id))?;
+
+        let average_cost = weighted_average_cost(
+            product.get("current_stock"),
+            product.get("average_cost"),
+            item.quantity,
+            item.price,
+        );
+
+        sqlx::query(r#"
+            UPDATE products
+            SET current_stock = current_stock + ?,
+                total_purchased = total_purchased + ?,
+                average_cost = ?,
+                last_purchase_price = ?,
+                last_purchase_date = ?,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+        "#)
+        .bind(item.quantity)
+        .bind(item.quantity)
+        .bind(average_cost)
+        .bind(item.price)
+        .bind(invoice_date)
+        .bind(item.product_id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(r#"
+            INSERT INTO stock_movements (
+                movement_type, to_stock_id, product_id, quantity, unit_cost, total_value,
+                reference_type, reference_id, reference_number, notes, created_by
+            ) VALUES ('purchase', ?, ?, ?, ?, ?, 'purchase', ?, ?, ?, ?)
+        "#)
+        .bind(item.stock_id)
+        .bind(item.product_id)
+        .bind(item.quantity)
+        .bind(item.price)
+        .bind(item.quantity as f64 * item.price)
+        .bind(purchase_id)
+        .bind(invoice_no)
+        .bind(format!("استلام مشتريات: {}", invoice_no))
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // Takes back out the stock a purchase's items still account for (returns already removed theirs).
+    // average_cost is left as is; the blend can't be unwound once later purchases built on it.
+    async fn release_purchase_stock(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, purchase_id: i64) -> Result<()> {
+        sqlx::query(r#"
+            UPDATE products
+            SET current_stock = current_stock - (
+                    SELECT SUM(pi.quantity - COALESCE(pi.returned_quantity, 0))
+                    FROM purchase_items pi WHERE pi.purchase_id = ? AND pi.product_id = products.id
+                ),
+                total_purchased = MAX(total_purchased - (
+                    SELECT SUM(pi.quantity - COALESCE(pi.returned_quantity, 0))
+                    FROM purchase_items pi WHERE pi.purchase_id = ? AND pi.product_id = products.id
+                ), 0),
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id IN (SELECT product_id FROM purchase_items WHERE purchase_id = ?)
+        "#)
+        .bind(purchase_id)
+        .bind(purchase_id)
+        .bind(purchase_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
     // Update purchase
     pub async fn update(&self, db: &Database, id: i64, purchase: UpdatePurchaseRequest, user_id: Option<i64>) -> Result<Option<PurchaseWithDetails>> {
+        PURCHASE_ITEMS_LIMIT.check(purchase.items.len())?;
+
         // Validate that purchase exists
         let existing_purchase = self.get_by_id(db, id).await?;
         if existing_purchase.is_none() {
@@ -536,6 +761,17 @@ impl PurchaseService {
             return Err(anyhow::anyhow!("Cannot update purchase with status: {}", existing.status));
         }
 
+        // Replacing the lines would drop what was already sent back and bring the returned units back into stock
+        let returned: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(returned_quantity), 0) FROM purchase_items WHERE purchase_id = ?"
+        )
+        .bind(id)
+        .fetch_one(&db.pool)
+        .await?;
+        if returned > 0 {
+            return Err(anyhow::anyhow!("لا يمكن تعديل فاتورة شراء تم إرجاع جزء منها"));
+        }
+
         // Check for duplicate invoice number if invoice_no is being changed
         if let Some(invoice_no) = &purchase.invoice_no {
             if !invoice_no.trim().is_empty() && invoice_no.trim() != existing.invoice_no {
@@ -584,7 +820,8 @@ impl PurchaseService {
             .unwrap_or(&existing.invoice_no)
             .to_string();
 
-        // Update purchase
+        // Update purchase, its items and the stock they brought in together
+        let mut tx = db.pool.begin().await?;
         let changes = sqlx::query(r#"
             UPDATE purchases 
             SET supplier_id = ?,
@@ -617,17 +854,20 @@ impl PurchaseService {
         .bind(purchase.status.as_deref().unwrap_or(&existing.status))
         .bind(purchase.money_box_id.or(existing.money_box_id))
         .bind(id)
-        .execute(&db.pool)
+        .execute(&mut *tx)
         .await?;
 
         if changes.rows_affected() == 0 {
             return Err(anyhow::anyhow!("Purchase not found or no changes made"));
         }
 
+        // Take the old items' stock back out before replacing them
+        Self::release_purchase_stock(&mut tx, id).await?;
+
         // Delete existing purchase items
         sqlx::query("DELETE FROM purchase_items WHERE purchase_id = ?")
             .bind(id)
-            .execute(&db.pool)
+            .execute(&mut *tx)
             .await?;
 
         // Insert new purchase items
@@ -651,10 +891,15 @@ impl PurchaseService {
             .bind(item.discount_percent.unwrap_or(0.0))
             .bind(item.tax_percent.unwrap_or(0.0))
             .bind(item_net_total)
-            .execute(&db.pool)
+            .execute(&mut *tx)
             .await?;
+
+            let invoice_date = purchase.invoice_date.unwrap_or(existing.invoice_date);
+            Self::receive_item_stock(&mut tx, id, &final_invoice_no, invoice_date, item, user_id).await?;
         }
 
+        tx.commit().await?;
+
         info!("Purchase {} updated successfully with {} items", id, purchase.items.len());
         self.get_by_id(db, id).await
     }
@@ -692,59 +937,109 @@ impl PurchaseService {
             return Err(anyhow::anyhow!("Cannot delete purchase with paid amount of {}. Please process refunds first or use force deletion.", existing.paid_amount));
         }
 
+        let mut tx = db.pool.begin().await?;
+
+        // Stock the purchase brought in (less what was already returned) leaves with it
+        Self::release_purchase_stock(&mut tx, id).await?;
+
         // Delete purchase items first (foreign key constraint)
         sqlx::query("DELETE FROM purchase_items WHERE purchase_id = ?")
             .bind(id)
-            .execute(&db.pool)
+            .execute(&mut *tx)
             .await?;
 
         // Delete purchase
         let changes = sqlx::query("DELETE FROM purchases WHERE id = ?")
             .bind(id)
-            .execute(&db.pool)
+            .execute(&mut *tx)
             .await?;
 
         if changes.rows_affected() == 0 {
             return Err(anyhow::anyhow!("Purchase not found"));
         }
 
+        tx.commit().await?;
+
         info!("Purchase {} deleted successfully", id);
         Ok(true)
     }
 
-    // Process purchase return
-    pub async fn process_purchase_return(&self, db: &Database, purchase_id: i64, return_items: Vec<crate::models::PurchaseReturnItemRequest>, reason: String, user_id: Option<i64>) -> Result<PurchaseReturnResponse> {
-        let purchase = self.get_by_id(db, purchase_id).await?;
-        if purchase.is_none() {
-            return Err(anyhow::anyhow!("Purchase not found"));
+    // Create a purchase return: stock goes back out, the amount owed to the supplier drops and
+    // any cash refund is posted to the purchase's money box. Everything runs in one transaction.
+    pub async fn create_return(
+        &self,
+        db: &Database,
+        purchase_id: i64,
+        return_items: Vec<crate::models::PurchaseReturnItemRequest>,
+        reason: String,
+        refund_method: String,
+        user_id: Option<i64>,
+    ) -> Result<PurchaseReturnResponse> {
+        if return_items.is_empty() {
+            return Err(anyhow::anyhow!("يجب تحديد المنتجات المراد إرجاعها"));
+        }
+        if !matches!(refund_method.as_str(), "cash" | "card" | "bank_transfer" | "check") {
+            return Err(anyhow::anyhow!("طريقة الاسترداد غير صالحة: {}", refund_method));
         }
 
-        let purchase = purchase.unwrap();
-        if purchase.status != "completed" {
+        let mut tx = db.pool.begin().await?;
+
+        let purchase = sqlx::query(
+            "SELECT id, invoice_no, status, remaining_amount, money_box_id FROM purchases WHERE id = ?"
+        )
+        .bind(purchase_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Purchase not found"))?;
+
+        let status: String = purchase.get("status");
+        if status != "completed" && status != "partially_returned" {
             return Err(anyhow::anyhow!("Only completed purchases can be returned"));
         }
+        let invoice_no: String = purchase.get("invoice_no");
+        let remaining_amount: f64 = purchase.get("remaining_amount");
+        let money_box_id: Option<i64> = purchase.get("money_box_id");
 
-        // Validate return items
-        let purchase_items = self.get_purchase_items(db, purchase_id).await?;
-        let item_map: HashMap<i64, &crate::models::PurchaseItemWithDetails> = purchase_items.iter().map(|item| (item.id, item)).collect();
+        let item_rows = sqlx::query(
+            "SELECT id, product_id, stock_id, quantity, total, COALESCE(returned_quantity, 0) as returned_quantity FROM purchase_items WHERE purchase_id = ?"
+        )
+        .bind(purchase_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let items: HashMap<i64, &sqlx::sqlite::SqliteRow> = item_rows.iter()
+            .map(|row| (row.get::<i64, _>("id"), row))
+            .collect();
 
-        // Calculate total return amount
+        // Validate quantities and price each line at the item's net unit cost
+        let mut lines = Vec::with_capacity(return_items.len());
         let mut total_return_amount = 0.0;
         for return_item in &return_items {
-            let original_item = item_map.get(&return_item.purchase_item_id)
+            let item = *items.get(&return_item.purchase_item_id)
                 .ok_or_else(|| anyhow::anyhow!("Invalid purchase item id: {}", return_item.purchase_item_id))?;
-            
-            let current_returned_quantity = original_item.returned_quantity;
-            let remaining_quantity = original_item.quantity - current_returned_quantity;
-            
-            if return_item.quantity > remaining_quantity {
-                return Err(anyhow::anyhow!("Return quantity exceeds remaining quantity for item {}", return_item.purchase_item_id));
+            let quantity: i64 = item.get("quantity");
+            let returned_quantity: i64 = item.get("returned_quantity");
+
+            if return_item.quantity <= 0 {
+                return Err(anyhow::anyhow!("كمية الإرجاع يجب أن تكون أكبر من صفر"));
             }
-            
-            total_return_amount += return_item.quantity as f64 * original_item.price;
+            if return_item.quantity > quantity - returned_quantity {
+                return Err(anyhow::anyhow!(
+                    "كمية الإرجاع ({}) تتجاوز الكمية المتبقية ({}) للعنصر {}",
+                    return_item.quantity, quantity - returned_quantity, return_item.purchase_item_id
+                ));
+            }
+
+            let unit_cost = if quantity > 0 { item.get::<f64, _>("total") / quantity as f64 } else { 0.0 };
+            let line_total = unit_cost * return_item.quantity as f64;
+            total_return_amount += line_total;
+            lines.push((return_item, item, unit_cost, line_total));
         }
 
-        // Create return record
+        // Whatever is still owed on the invoice is cancelled first (the supplier balance is the
+        // sum of purchases.remaining_amount); anything already paid comes back via the refund method
+        let credit_amount = total_return_amount.min(remaining_amount.max(0.0));
+        let refund_amount = total_return_amount - credit_amount;
+
         let return_id = sqlx::query(r#"
             INSERT INTO purchase_returns (
                 purchase_id, return_date, reason, status, refund_method, total_amount, created_by
@@ -752,82 +1047,145 @@ impl PurchaseService {
         "#)
         .bind(purchase_id)
         .bind(&reason)
-        .bind("cash") // Default refund method
+        .bind(&refund_method)
         .bind(total_return_amount)
         .bind(user_id)
-        .execute(&db.pool)
+        .execute(&mut *tx)
         .await?
         .last_insert_rowid();
 
-        // Process each return item
-        for item in &return_items {
-            let original_item = item_map.get(&item.purchase_item_id).unwrap();
-            
-            // Insert return item record
+        for (return_item, item, unit_cost, line_total) in &lines {
+            let product_id: i64 = item.get("product_id");
+            let stock_id: Option<i64> = item.get("stock_id");
+
             sqlx::query(r#"
                 INSERT INTO purchase_return_items (
                     return_id, purchase_item_id, quantity, price, total
                 ) VALUES (?, ?, ?, ?, ?)
             "#)
             .bind(return_id)
-            .bind(item.purchase_item_id)
-            .bind(item.quantity)
-            .bind(original_item.price)
-            .bind(item.quantity as f64 * original_item.price)
-            .execute(&db.pool)
+            .bind(return_item.purchase_item_id)
+            .bind(return_item.quantity)
+            .bind(unit_cost)
+            .bind(line_total)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(r#"
+                UPDATE purchase_items
+                SET returned_quantity = COALESCE(returned_quantity, 0) + ?, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+            "#)
+            .bind(return_item.quantity)
+            .bind(return_item.purchase_item_id)
+            .execute(&mut *tx)
             .await?;
 
-            // Update purchase item with returned quantity
-            let new_returned_quantity = original_item.returned_quantity + item.quantity;
             sqlx::query(r#"
-                UPDATE purchase_items 
-                SET returned_quantity = ?, updated_at = CURRENT_TIMESTAMP
+                UPDATE products
+                SET current_stock = current_stock - ?, updated_at = CURRENT_TIMESTAMP
                 WHERE id = ?
             "#)
-            .bind(new_returned_quantity)
-            .bind(item.purchase_item_id)
-            .execute(&db.pool)
+            .bind(return_item.quantity)
+            .bind(product_id)
+            .execute(&mut *tx)
             .await?;
+
+            if let Some(stock_id) = stock_id {
+                sqlx::query(r#"
+                    INSERT INTO stock_movements (
+                        movement_type, from_stock_id, product_id, quantity, unit_cost, total_value,
+                        reference_type, reference_id, reference_number, notes, created_by
+                    ) VALUES ('return', ?, ?, ?, ?, ?, 'return', ?, ?, ?, ?)
+                "#)
+                .bind(stock_id)
+                .bind(product_id)
+                .bind(return_item.quantity)
+                .bind(unit_cost)
+                .bind(line_total)
+                .bind(return_id)
+                .bind(&invoice_no)
+                .bind(format!("إرجاع مشتريات: {}", reason))
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            }
         }
 
-        // Get updated purchase items to check if all items are returned
-        let updated_purchase_items = self.get_purchase_items(db, purchase_id).await?;
-        let all_items_returned = updated_purchase_items.iter().all(|item| 
-            item.returned_quantity >= item.quantity
-        );
+        if credit_amount > 0.0 {
+            sqlx::query(r#"
+                UPDATE purchases
+                SET net_amount = net_amount - ?,
+                    payment_status = CASE
+                        WHEN net_amount - ? <= paid_amount THEN 'paid'
+                        WHEN paid_amount > 0 THEN 'partial'
+                        ELSE 'unpaid'
+                    END,
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+            "#)
+            .bind(credit_amount)
+            .bind(credit_amount)
+            .bind(purchase_id)
+            .execute(&mut *tx)
+            .await?;
+        }
 
-        // Calculate total returned amount including this return
-        let total_returned_amount = updated_purchase_items.iter().map(|item| {
-            item.returned_quantity as f64 * item.price
-        }).sum::<f64>();
-
-        // Check if total returned amount equals or exceeds the purchase net amount
-        let is_fully_returned_monetarily = total_returned_amount >= purchase.net_amount;
-
-        // Determine new status based on both item quantity and monetary amount
-        let new_status = if all_items_returned && is_fully_returned_monetarily {
-            "returned"
-        } else if is_fully_returned_monetarily {
-            "returned" // Even if not all items returned, if monetary amount is fully returned
-        } else if all_items_returned {
-            "returned" // All items returned
-        } else {
-            "partially_returned"
-        };
-        
-        // Update purchase status
-        sqlx::query(r#"
-            UPDATE purchases 
-            SET status = ?, updated_at = CURRENT_TIMESTAMP
-            WHERE id = ?
-        "#)
-        .bind(new_status)
+        // Cash refunds go back into the box the purchase was paid from
+        if refund_amount > 0.0 && refund_method == "cash" {
+            match money_box_id {
+                Some(box_id) => {
+                    let balance: f64 = sqlx::query("SELECT amount FROM money_boxes WHERE id = ?")
+                        .bind(box_id)
+                        .fetch_optional(&mut *tx)
+                        .await?
+                        .map(|row| row.get("amount"))
+                        .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+
+                    sqlx::query("UPDATE money_boxes SET amount = amount + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                        .bind(refund_amount)
+                        .bind(box_id)
+                        .execute(&mut *tx)
+                        .await?;
+
+                    sqlx::query(r#"
+                        INSERT INTO money_box_transactions
+                        (box_id, type, amount, balance_after, notes, created_by, created_at)
+                        VALUES (?, 'purchase_return', ?, ?, ?, ?, CURRENT_TIMESTAMP)
+                    "#)
+                    .bind(box_id)
+                    .bind(refund_amount)
+                    .bind(balance + refund_amount)
+                    .bind(format!("إرجاع مشتريات فاتورة {}", invoice_no))
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                None => warn!("Purchase {} has no money box, cash refund of {} not posted", purchase_id, refund_amount),
+            }
+        }
+
+        // Fully returned once every line has been sent back
+        let pending: i64 = sqlx::query(
+            "SELECT COUNT(*) as pending FROM purchase_items WHERE purchase_id = ? AND COALESCE(returned_quantity, 0) < quantity"
+        )
         .bind(purchase_id)
-        .execute(&db.pool)
-        .await?;
+        .fetch_one(&mut *tx)
+        .await?
+        .get("pending");
+        let new_status = if pending == 0 { "returned" } else { "partially_returned" };
+
+        sqlx::query("UPDATE purchases SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(new_status)
+            .bind(purchase_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        info!("Purchase return {} created for purchase {}: total={}, credited={}, refunded={}",
+            return_id, purchase_id, total_return_amount, credit_amount, refund_amount);
 
-        info!("Purchase return {} created successfully for purchase {}", return_id, purchase_id);
-        
         Ok(PurchaseReturnResponse {
             return_id,
             total_amount: total_return_amount,
@@ -989,3 +1347,278 @@ impl PurchaseService {
         }))
     }
 }
+
+// ((stock * average) + (qty * price)) / (stock + qty). Without positive stock on hand the old average
+// has nothing left to weigh, so the new price becomes the average.
+fn weighted_average_cost(current_stock: i64, average_cost: f64, quantity: i64, unit_price: f64) -> f64 {
+    if current_stock <= 0 || current_stock + quantity <= 0 {
+        return unit_price;
+    }
+    (current_stock as f64 * average_cost + quantity as f64 * unit_price) / (current_stock + quantity) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::models::PurchaseReturnItemRequest;
+
+    // Supplier plus two stocked-at-zero products, returned as (supplier_id, [product ids])
+    async fn seed_catalog(db: &Database) -> (i64, [i64; 2]) {
+        let supplier_id = sqlx::query("INSERT INTO suppliers (name, contact_person) VALUES ('شركة الرافدين', 'Ali')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        let mut products = [0; 2];
+        for (slot, sku) in products.iter_mut().zip(["RICE-5KG", "OIL-1L"]) {
+            *slot = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price) VALUES (?, ?, 4, 6, 5)")
+                .bind(sku)
+                .bind(sku)
+                .execute(&db.pool).await.unwrap()
+                .last_insert_rowid();
+        }
+        (supplier_id, products)
+    }
+
+    async fn receive(db: &Database, supplier_id: i64, products: [i64; 2], paid: f64) -> (i64, Vec<i64>) {
+        let request: CreatePurchaseRequest = serde_json::from_value(serde_json::json!({
+            "supplier_id": supplier_id,
+            "invoice_date": "2026-03-01",
+            "paid_amount": paid,
+            "money_box_id": 1,
+            "items": [
+                { "product_id": products[0], "stock_id": 1, "quantity": 10, "price": 4.0 },
+                { "product_id": products[1], "stock_id": 1, "quantity": 5, "price": 8.0 }
+            ]
+        })).unwrap();
+        let purchase = PurchaseService::new().create(db, request, Some(1)).await.unwrap();
+        let item_ids = sqlx::query_scalar("SELECT id FROM purchase_items WHERE purchase_id = ? ORDER BY id")
+            .bind(purchase.id)
+            .fetch_all(&db.pool).await.unwrap();
+        (purchase.id, item_ids)
+    }
+
+    async fn current_stock(db: &Database, product_id: i64) -> i64 {
+        sqlx::query_scalar("SELECT current_stock FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_one(&db.pool).await.unwrap()
+    }
+
+    fn lines(pairs: &[(i64, i64)]) -> Vec<PurchaseReturnItemRequest> {
+        pairs.iter().map(|&(purchase_item_id, quantity)| PurchaseReturnItemRequest { purchase_item_id, quantity }).collect()
+    }
+
+    #[tokio::test]
+    async fn partial_return_on_credit_reduces_stock_and_what_is_owed() {
+        let db = TestDatabase::new().await;
+        let service = PurchaseService::new();
+        let (supplier_id, products) = seed_catalog(&db).await;
+        let (purchase_id, items) = receive(&db, supplier_id, products, 0.0).await;
+        assert_eq!(current_stock(&db, products[0]).await, 10);
+
+        let response = service
+            .create_return(&db, purchase_id, lines(&[(items[0], 3)]), "تالف".into(), "cash".into(), Some(1))
+            .await.unwrap();
+        assert_eq!(response.total_amount, 12.0);
+        assert_eq!(response.new_purchase_status, "partially_returned");
+        assert_eq!(current_stock(&db, products[0]).await, 7);
+
+        let (remaining, status): (f64, String) = sqlx::query_as("SELECT remaining_amount, status FROM purchases WHERE id = ?")
+            .bind(purchase_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(remaining, 80.0 - 12.0);
+        assert_eq!(status, "partially_returned");
+
+        // Nothing was paid, so no cash leaves or enters a box
+        let refunds: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM money_box_transactions WHERE type = 'purchase_return'")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(refunds, 0);
+
+        // Only 7 of the first line are left to send back
+        let err = service
+            .create_return(&db, purchase_id, lines(&[(items[0], 8)]), "تالف".into(), "cash".into(), Some(1))
+            .await.unwrap_err();
+        assert!(err.to_string().contains("تتجاوز"), "{err}");
+        assert_eq!(current_stock(&db, products[0]).await, 7);
+    }
+
+    #[tokio::test]
+    async fn full_return_of_a_paid_purchase_refunds_cash_into_its_box() {
+        let db = TestDatabase::new().await;
+        let service = PurchaseService::new();
+        let (supplier_id, products) = seed_catalog(&db).await;
+        let (purchase_id, items) = receive(&db, supplier_id, products, 80.0).await;
+        let box_before: f64 = sqlx::query_scalar("SELECT amount FROM money_boxes WHERE id = 1")
+            .fetch_one(&db.pool).await.unwrap();
+
+        let response = service
+            .create_return(&db, purchase_id, lines(&[(items[0], 10), (items[1], 5)]), "إلغاء الطلبية".into(), "cash".into(), None)
+            .await.unwrap();
+        assert_eq!(response.total_amount, 80.0);
+        assert_eq!(response.new_purchase_status, "returned");
+        assert_eq!(current_stock(&db, products[0]).await, 0);
+        assert_eq!(current_stock(&db, products[1]).await, 0);
+
+        let box_after: f64 = sqlx::query_scalar("SELECT amount FROM money_boxes WHERE id = 1")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(box_after - box_before, 80.0);
+        let (amount, balance_after): (f64, f64) = sqlx::query_as(
+            "SELECT amount, balance_after FROM money_box_transactions WHERE box_id = 1 AND type = 'purchase_return'"
+        )
+        .fetch_one(&db.pool).await.unwrap();
+        assert_eq!((amount, balance_after), (80.0, box_after));
+
+        let returned: Vec<i64> = sqlx::query_scalar("SELECT returned_quantity FROM purchase_items WHERE purchase_id = ? ORDER BY id")
+            .bind(purchase_id)
+            .fetch_all(&db.pool).await.unwrap();
+        assert_eq!(returned, vec![10, 5]);
+
+        // A returned purchase can't be returned again
+        assert!(service
+            .create_return(&db, purchase_id, lines(&[(items[0], 1)]), "x".into(), "cash".into(), None)
+            .await.is_err());
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_supplier_and_invoice_dates() {
+        let db = TestDatabase::new().await;
+        let service = PurchaseService::new();
+        let (rafidain, products) = seed_catalog(&db).await;
+        let dijla = sqlx::query("INSERT INTO suppliers (name, contact_person) VALUES ('مخازن دجلة', 'Omar')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        for (supplier_id, date) in [(rafidain, "2026-01-15"), (rafidain, "2026-02-10"), (dijla, "2026-02-20"), (rafidain, "2026-03-05")] {
+            let request: CreatePurchaseRequest = serde_json::from_value(serde_json::json!({
+                "supplier_id": supplier_id,
+                "invoice_date": date,
+                "paid_amount": 0.0,
+                "items": [
+                    { "product_id": products[0], "stock_id": 1, "quantity": 2, "price": 4.0 },
+                    { "product_id": products[1], "stock_id": 1, "quantity": 1, "price": 8.0 }
+                ]
+            })).unwrap();
+            service.create(&db, request, Some(1)).await.unwrap();
+        }
+        let list = |filters: serde_json::Value| {
+            let query: PurchaseQuery = serde_json::from_value(filters).unwrap();
+            let service = &service;
+            let db = &db;
+            async move { service.get_all(db, &query).await.unwrap() }
+        };
+
+        let by_supplier = list(serde_json::json!({ "supplier_id": rafidain })).await;
+        assert_eq!(by_supplier.total, 3);
+        assert!(by_supplier.purchases.iter().all(|purchase| purchase.supplier_name.as_deref() == Some("شركة الرافدين")));
+        assert!(by_supplier.purchases.iter().all(|purchase| purchase.items.len() == 2));
+
+        let february = list(serde_json::json!({ "start_date": "2026-02-01", "end_date": "2026-02-28" })).await;
+        assert_eq!(february.total, 2);
+        let mut dates: Vec<String> = february.purchases.iter().map(|purchase| purchase.invoice_date.to_string()).collect();
+        dates.sort();
+        assert_eq!(dates, ["2026-02-10", "2026-02-20"]);
+
+        // The count and the page share one WHERE clause
+        let paged = list(serde_json::json!({ "supplier_id": rafidain, "start_date": "2026-02-01", "limit": 1 })).await;
+        assert_eq!((paged.total, paged.total_pages, paged.purchases.len()), (2, 2, 1));
+        assert_eq!(paged.purchases[0].supplier_id, rafidain);
+    }
+
+    #[tokio::test]
+    async fn sent_totals_must_add_up_before_anything_is_stored() {
+        let db = TestDatabase::new().await;
+        let service = PurchaseService::new();
+        let (supplier_id, products) = seed_catalog(&db).await;
+        // Items come to 10 x 4 + 5 x 8 = 80
+        let payload = |totals: serde_json::Value| -> CreatePurchaseRequest {
+            let mut body = serde_json::json!({
+                "supplier_id": supplier_id,
+                "invoice_date": "2026-03-01",
+                "items": [
+                    { "product_id": products[0], "stock_id": 1, "quantity": 10, "price": 4.0 },
+                    { "product_id": products[1], "stock_id": 1, "quantity": 5, "price": 8.0 }
+                ]
+            });
+            body.as_object_mut().unwrap().extend(totals.as_object().unwrap().clone());
+            serde_json::from_value(body).unwrap()
+        };
+
+        let purchase = service
+            .create(&db, payload(serde_json::json!({ "total_amount": 80.0, "net_amount": 80.0, "paid_amount": 30.0 })), Some(1))
+            .await.unwrap();
+        let (net, paid, remaining): (f64, f64, f64) = sqlx::query_as(
+            "SELECT CAST(net_amount AS REAL), CAST(paid_amount AS REAL), CAST(remaining_amount AS REAL) FROM purchases WHERE id = ?"
+        )
+        .bind(purchase.id)
+        .fetch_one(&db.pool).await.unwrap();
+        assert_eq!((net, paid, remaining), (80.0, 30.0, 50.0));
+
+        for totals in [
+            serde_json::json!({ "total_amount": 80.0, "net_amount": 95.0 }),
+            serde_json::json!({ "total_amount": 75.0 }),
+            serde_json::json!({ "paid_amount": 120.0 }),
+        ] {
+            let err = service.create(&db, payload(totals.clone()), Some(1)).await.unwrap_err();
+            assert!(err.downcast_ref::<InconsistentAmounts>().is_some(), "{totals}: {err}");
+        }
+        let purchases: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM purchases").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(purchases, 1);
+        assert_eq!(current_stock(&db, products[0]).await, 10);
+    }
+
+    #[tokio::test]
+    async fn receiving_at_a_new_price_blends_the_average_cost() {
+        let db = TestDatabase::new().await;
+        let service = PurchaseService::new();
+        let (supplier_id, products) = seed_catalog(&db).await;
+        let buy = |date: &str, quantity: i64, price: f64| -> CreatePurchaseRequest {
+            serde_json::from_value(serde_json::json!({
+                "supplier_id": supplier_id,
+                "invoice_date": date,
+                "items": [{ "product_id": products[0], "stock_id": 1, "quantity": quantity, "price": price }]
+            })).unwrap()
+        };
+        let costing = || async {
+            sqlx::query_as::<_, (i64, f64, f64, String)>(
+                "SELECT current_stock, CAST(average_cost AS REAL), CAST(last_purchase_price AS REAL), last_purchase_date FROM products WHERE id = ?"
+            )
+            .bind(products[0])
+            .fetch_one(&db.pool).await.unwrap()
+        };
+
+        service.create(&db, buy("2026-03-01", 10, 4.0), Some(1)).await.unwrap();
+        assert_eq!(costing().await, (10, 4.0, 4.0, "2026-03-01".to_string()));
+
+        // (10 x 4 + 30 x 6) / 40
+        service.create(&db, buy("2026-03-09", 30, 6.0), Some(1)).await.unwrap();
+        assert_eq!(costing().await, (40, 5.5, 6.0, "2026-03-09".to_string()));
+
+        // Oversold stock leaves nothing to weigh, so the new price becomes the average
+        sqlx::query("UPDATE products SET current_stock = -3 WHERE id = ?").bind(products[0]).execute(&db.pool).await.unwrap();
+        service.create(&db, buy("2026-03-20", 5, 7.0), Some(1)).await.unwrap();
+        assert_eq!(costing().await, (2, 7.0, 7.0, "2026-03-20".to_string()));
+    }
+
+    #[tokio::test]
+    async fn a_partly_returned_purchase_cannot_be_edited() {
+        let db = TestDatabase::new().await;
+        let service = PurchaseService::new();
+        let (supplier_id, products) = seed_catalog(&db).await;
+        let (purchase_id, items) = receive(&db, supplier_id, products, 0.0).await;
+        service
+            .create_return(&db, purchase_id, lines(&[(items[0], 3)]), "تالف".into(), "cash".into(), Some(1))
+            .await.unwrap();
+
+        let edit: UpdatePurchaseRequest = serde_json::from_value(serde_json::json!({
+            "items": [
+                { "product_id": products[0], "stock_id": 1, "quantity": 10, "price": 4.0 },
+                { "product_id": products[1], "stock_id": 1, "quantity": 5, "price": 8.0 }
+            ]
+        })).unwrap();
+        let err = service.update(&db, purchase_id, edit, Some(1)).await.unwrap_err();
+        assert!(err.to_string().contains("إرجاع"), "{err}");
+        assert_eq!(current_stock(&db, products[0]).await, 7);
+        let returned: i64 = sqlx::query_scalar("SELECT returned_quantity FROM purchase_items WHERE id = ?")
+            .bind(items[0])
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(returned, 3);
+    }
+}
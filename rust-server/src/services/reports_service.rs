@@ -1,6 +1,8 @@
 use anyhow::Result;
 use crate::database::Database;
 use crate::models::report::*;
+use crate::models::inventory::CostMethod;
+use crate::services::InventoryService;
 use sqlx::{Row, SqlitePool};
 use tracing::{info, warn, error};
 use chrono::{Utc, DateTime, NaiveDate, NaiveDateTime, Datelike};
@@ -667,6 +669,424 @@ impl ReportsService {
     }
 
     // Helper function to calculate period dates
+    pub const SNAPSHOT_TYPES: &'static [&'static str] = &["daily", "weekly", "monthly", "quarterly", "yearly", "custom"];
+    pub const SNAPSHOT_FREQUENCIES: &'static [&'static str] = &["off", "monthly", "daily"];
+    // How often the scheduler looks for newly closed periods
+    const SNAPSHOT_CHECK_INTERVAL_SECS: u64 = 3600;
+    // Missed periods are backfilled this far back (e.g. the app was closed at month end)
+    const SNAPSHOT_BACKFILL_MONTHS: u32 = 12;
+    const SNAPSHOT_BACKFILL_DAYS: i64 = 7;
+
+    // Recalculate period totals and store them as the snapshot for (report_type, start, end)
+    pub async fn snapshot_period(&self, db: &Database, report_type: &str, start: NaiveDate, end: NaiveDate) -> Result<ReportSnapshot> {
+        if !Self::SNAPSHOT_TYPES.contains(&report_type) {
+            return Err(anyhow::anyhow!("نوع التقرير غير صحيح"));
+        }
+        if start > end {
+            return Err(anyhow::anyhow!("تاريخ البداية يجب أن يكون قبل تاريخ النهاية"));
+        }
+
+        let totals = self.period_totals(db, start, end).await?;
+
+        let counts = sqlx::query(r#"
+            SELECT
+                (SELECT COUNT(*) FROM customers WHERE id != 999) as total_customers,
+                (SELECT COUNT(*) FROM suppliers) as total_suppliers,
+                (SELECT COUNT(*) FROM products WHERE is_active = 1) as total_products,
+                (SELECT COUNT(*) FROM products WHERE is_active = 1 AND current_stock > 0 AND current_stock <= min_stock) as low_stock_products,
+                (SELECT COUNT(*) FROM products WHERE is_active = 1 AND current_stock <= 0) as out_of_stock_products
+        "#)
+        .fetch_one(&db.pool)
+        .await?;
+
+        sqlx::query(r#"
+            INSERT INTO reports (
+                report_type, period_start, period_end, total_sales, total_purchases, total_expenses, net_profit,
+                total_customers, total_suppliers, total_products, low_stock_products, out_of_stock_products,
+                created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            ON CONFLICT(report_type, period_start, period_end) DO UPDATE SET
+                total_sales = excluded.total_sales,
+                total_purchases = excluded.total_purchases,
+                total_expenses = excluded.total_expenses,
+                net_profit = excluded.net_profit,
+                total_customers = excluded.total_customers,
+                total_suppliers = excluded.total_suppliers,
+                total_products = excluded.total_products,
+                low_stock_products = excluded.low_stock_products,
+                out_of_stock_products = excluded.out_of_stock_products,
+                updated_at = CURRENT_TIMESTAMP
+        "#)
+        .bind(report_type)
+        .bind(start)
+        .bind(end)
+        .bind(totals.total_sales)
+        .bind(totals.total_purchases)
+        .bind(totals.total_expenses)
+        .bind(totals.net_profit)
+        .bind(counts.get::<i64, _>("total_customers"))
+        .bind(counts.get::<i64, _>("total_suppliers"))
+        .bind(counts.get::<i64, _>("total_products"))
+        .bind(counts.get::<i64, _>("low_stock_products"))
+        .bind(counts.get::<i64, _>("out_of_stock_products"))
+        .execute(&db.pool)
+        .await?;
+
+        let snapshot = sqlx::query_as::<_, ReportSnapshot>(
+            "SELECT * FROM reports WHERE report_type = ? AND period_start = ? AND period_end = ?"
+        )
+        .bind(report_type)
+        .bind(start)
+        .bind(end)
+        .fetch_one(&db.pool)
+        .await?;
+
+        info!("Report snapshot {} saved for {} - {}", report_type, start, end);
+        Ok(snapshot)
+    }
+
+    // Sales, cost of goods, purchases and expenses for a date range (by invoice/expense date)
+    async fn period_totals(&self, db: &Database, start: NaiveDate, end: NaiveDate) -> Result<PeriodTotals> {
+        // Sales and cost of goods net of returned quantities
+        let sales = sqlx::query(r#"
+            SELECT
+                COALESCE(SUM((si.quantity - COALESCE(si.returned_quantity, 0)) * si.price), 0.0) as total_sales,
+                COALESCE(SUM((si.quantity - COALESCE(si.returned_quantity, 0)) * COALESCE(p.purchase_price, 0)), 0.0) as cost_of_goods
+            FROM sales s
+            JOIN sale_items si ON s.id = si.sale_id
+            LEFT JOIN products p ON si.product_id = p.id
+            WHERE s.invoice_date BETWEEN ? AND ?
+              AND s.status NOT IN ('cancelled', 'returned')
+        "#)
+        .bind(start)
+        .bind(end)
+        .fetch_one(&db.pool)
+        .await?;
+        let total_sales: f64 = sales.get("total_sales");
+        let cost_of_goods: f64 = sales.get("cost_of_goods");
+
+        let total_purchases: f64 = sqlx::query(r#"
+            SELECT COALESCE(SUM(net_amount), 0.0) as total FROM purchases
+            WHERE invoice_date BETWEEN ? AND ? AND status != 'cancelled'
+        "#)
+        .bind(start)
+        .bind(end)
+        .fetch_one(&db.pool)
+        .await?
+        .get("total");
+
+        let total_expenses: f64 = sqlx::query("SELECT COALESCE(SUM(amount), 0.0) as total FROM expenses WHERE date BETWEEN ? AND ?")
+            .bind(start)
+            .bind(end)
+            .fetch_one(&db.pool)
+            .await?
+            .get("total");
+
+        Ok(PeriodTotals {
+            start,
+            end,
+            total_sales,
+            cost_of_goods,
+            gross_profit: total_sales - cost_of_goods,
+            total_purchases,
+            total_expenses,
+            net_profit: total_sales - cost_of_goods - total_expenses,
+        })
+    }
+
+    // Revenue net of returns against average-cost COGS and expenses.
+    // Returned and partially returned sales stay in gross sales; their returns are subtracted below.
+    pub async fn profit_and_loss(&self, db: &Database, start_date: NaiveDate, end_date: NaiveDate) -> Result<ProfitAndLoss> {
+        if start_date > end_date {
+            return Err(anyhow::anyhow!("تاريخ البداية يجب أن يكون قبل تاريخ النهاية"));
+        }
+
+        let gross_sales: f64 = sqlx::query(r#"
+            SELECT COALESCE(SUM(net_amount), 0.0) as total FROM sales
+            WHERE invoice_date BETWEEN ? AND ?
+              AND status IN ('completed', 'returned', 'partially_returned')
+        "#)
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_one(&db.pool)
+        .await?
+        .get("total");
+
+        let returns: f64 = sqlx::query(r#"
+            SELECT COALESCE(SUM(sr.total_amount), 0.0) as total
+            FROM sale_returns sr
+            JOIN sales s ON sr.sale_id = s.id
+            WHERE s.invoice_date BETWEEN ? AND ?
+              AND s.status IN ('completed', 'returned', 'partially_returned')
+              AND sr.status = 'completed'
+        "#)
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_one(&db.pool)
+        .await?
+        .get("total");
+
+        let cost_of_goods: f64 = sqlx::query(r#"
+            SELECT COALESCE(SUM((si.quantity - COALESCE(si.returned_quantity, 0)) * COALESCE(p.average_cost, 0)), 0.0) as total
+            FROM sale_items si
+            JOIN sales s ON si.sale_id = s.id
+            LEFT JOIN products p ON si.product_id = p.id
+            WHERE s.invoice_date BETWEEN ? AND ?
+              AND s.status IN ('completed', 'returned', 'partially_returned')
+        "#)
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_one(&db.pool)
+        .await?
+        .get("total");
+
+        let total_expenses: f64 = sqlx::query("SELECT COALESCE(SUM(amount), 0.0) as total FROM expenses WHERE date BETWEEN ? AND ?")
+            .bind(start_date)
+            .bind(end_date)
+            .fetch_one(&db.pool)
+            .await?
+            .get("total");
+
+        let revenue = gross_sales - returns;
+        let gross_profit = revenue - cost_of_goods;
+
+        Ok(ProfitAndLoss {
+            start_date,
+            end_date,
+            gross_sales,
+            returns,
+            revenue,
+            cost_of_goods,
+            gross_profit,
+            total_expenses,
+            net_profit: gross_profit - total_expenses,
+        })
+    }
+
+    // Cash + inventory + receivables - payables as of a date (today when omitted)
+    pub async fn balance_snapshot(&self, db: &Database, as_of: NaiveDate) -> Result<BalanceSnapshot> {
+        let today = chrono::Local::now().date_naive();
+
+        // Past dates take each box's last recorded balance on or before the day
+        let box_rows = if as_of >= today {
+            sqlx::query("SELECT id, name, CAST(amount AS REAL) as balance FROM money_boxes ORDER BY id")
+                .fetch_all(&db.pool)
+                .await?
+        } else {
+            sqlx::query(r#"
+                SELECT mb.id, mb.name, COALESCE((
+                    SELECT CAST(t.balance_after AS REAL) FROM money_box_transactions t
+                    WHERE t.box_id = mb.id AND t.balance_after IS NOT NULL AND DATE(t.created_at) <= ?
+                    ORDER BY t.created_at DESC, t.id DESC LIMIT 1
+                ), 0.0) as balance
+                FROM money_boxes mb
+                ORDER BY mb.id
+            "#)
+            .bind(as_of)
+            .fetch_all(&db.pool)
+            .await?
+        };
+        let money_boxes: Vec<MoneyBoxBalance> = box_rows.into_iter()
+            .map(|row| MoneyBoxBalance {
+                id: row.get("id"),
+                name: row.get("name"),
+                balance: row.get("balance"),
+            })
+            .collect();
+        let cash = money_boxes.iter().fold(0.0, |total, b| total + b.balance);
+
+        let inventory_value = InventoryService::new()
+            .valuation(db, CostMethod::AverageCost)
+            .await?
+            .total_value;
+
+        let receivables: f64 = sqlx::query(r#"
+            SELECT COALESCE(SUM(CAST(net_amount - paid_amount AS REAL)), 0.0) as total FROM sales
+            WHERE invoice_date <= ? AND net_amount > paid_amount
+              AND status IN ('completed', 'partially_returned')
+        "#)
+        .bind(as_of)
+        .fetch_one(&db.pool)
+        .await?
+        .get("total");
+
+        let payables: f64 = sqlx::query(r#"
+            SELECT COALESCE(SUM(CAST(net_amount - paid_amount AS REAL)), 0.0) as total FROM purchases
+            WHERE invoice_date <= ? AND net_amount > paid_amount
+              AND status IN ('completed', 'partially_returned')
+        "#)
+        .bind(as_of)
+        .fetch_one(&db.pool)
+        .await?
+        .get("total");
+
+        let total_assets = cash + inventory_value + receivables;
+
+        Ok(BalanceSnapshot {
+            as_of,
+            cash,
+            money_boxes,
+            inventory_value,
+            receivables,
+            payables,
+            total_assets,
+            net: total_assets - payables,
+        })
+    }
+
+    // Best sellers in a range by net quantity or net revenue; returned quantities are excluded
+    pub async fn top_products(&self, db: &Database, start_date: NaiveDate, end_date: NaiveDate, metric: TopProductsMetric, limit: i64) -> Result<Vec<TopProduct>> {
+        if start_date > end_date {
+            return Err(anyhow::anyhow!("تاريخ البداية يجب أن يكون قبل تاريخ النهاية"));
+        }
+
+        let order_by = match metric {
+            TopProductsMetric::Quantity => "total_quantity DESC, total_revenue DESC",
+            TopProductsMetric::Revenue => "total_revenue DESC, total_quantity DESC",
+        };
+        let rows = sqlx::query(&format!(r#"
+            SELECT
+                CASE WHEN si.product_id > 0 THEN si.product_id END as group_id,
+                MAX(p.name) as name,
+                MAX(p.sku) as sku,
+                COALESCE(SUM(si.quantity - COALESCE(si.returned_quantity, 0)), 0) as total_quantity,
+                COALESCE(SUM((si.quantity - COALESCE(si.returned_quantity, 0)) * si.price * (1 - COALESCE(si.discount_percent, 0) / 100.0)), 0.0) as total_revenue
+            FROM sale_items si
+            JOIN sales s ON si.sale_id = s.id
+            LEFT JOIN products p ON si.product_id = p.id
+            WHERE s.invoice_date BETWEEN ? AND ?
+              AND s.status IN ('completed', 'partially_returned')
+            GROUP BY group_id
+            HAVING total_quantity > 0
+            ORDER BY {}
+            LIMIT ?
+        "#, order_by))
+        .bind(start_date)
+        .bind(end_date)
+        .bind(limit.clamp(1, 100))
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| {
+            let product_id: Option<i64> = row.get("group_id");
+            TopProduct {
+                product_id,
+                name: match product_id {
+                    Some(_) => row.get::<Option<String>, _>("name").unwrap_or_default(),
+                    None => "مواد اخرى".to_string(),
+                },
+                sku: row.get("sku"),
+                total_quantity: row.get("total_quantity"),
+                total_revenue: row.get("total_revenue"),
+            }
+        }).collect())
+    }
+
+    // Compare sales, profit and expenses of period B against period A
+    pub async fn compare_periods(&self, db: &Database, period_a: (NaiveDate, NaiveDate), period_b: (NaiveDate, NaiveDate)) -> Result<PeriodComparison> {
+        for (start, end) in [period_a, period_b] {
+            if start > end {
+                return Err(anyhow::anyhow!("تاريخ البداية يجب أن يكون قبل تاريخ النهاية"));
+            }
+        }
+
+        let a = self.period_totals(db, period_a.0, period_a.1).await?;
+        let b = self.period_totals(db, period_b.0, period_b.1).await?;
+
+        Ok(PeriodComparison {
+            sales: MetricChange::between(a.total_sales, b.total_sales),
+            gross_profit: MetricChange::between(a.gross_profit, b.gross_profit),
+            expenses: MetricChange::between(a.total_expenses, b.total_expenses),
+            net_profit: MetricChange::between(a.net_profit, b.net_profit),
+            period_a: a,
+            period_b: b,
+        })
+    }
+
+    // Background task that snapshots every closed period according to settings.report_snapshot_frequency
+    pub fn start_snapshot_scheduler(&self, db: Database) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(Self::SNAPSHOT_CHECK_INTERVAL_SECS));
+            loop {
+                interval.tick().await;
+                let today = chrono::Local::now().date_naive();
+                match service.run_due_snapshots(&db, today).await {
+                    Ok(0) => {},
+                    Ok(created) => info!("Report scheduler created {} snapshots", created),
+                    Err(err) => error!("Report snapshot scheduler failed: {}", err),
+                }
+            }
+        });
+    }
+
+    // Snapshot closed periods (before `today`) that have no row yet; returns how many were created
+    pub async fn run_due_snapshots(&self, db: &Database, today: NaiveDate) -> Result<usize> {
+        let frequency: String = sqlx::query("SELECT COALESCE(report_snapshot_frequency, 'monthly') as frequency FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get("frequency"))
+            .unwrap_or_else(|| "monthly".to_string());
+        if frequency == "off" {
+            return Ok(0);
+        }
+
+        // Nothing to report on before the first recorded activity
+        let earliest: Option<NaiveDate> = sqlx::query(r#"
+            SELECT MIN(d) as earliest FROM (
+                SELECT MIN(invoice_date) as d FROM sales
+                UNION ALL SELECT MIN(invoice_date) FROM purchases
+                UNION ALL SELECT MIN(date) FROM expenses
+            )
+        "#)
+        .fetch_one(&db.pool)
+        .await?
+        .get("earliest");
+        let earliest = match earliest {
+            Some(date) => date,
+            None => return Ok(0),
+        };
+
+        let mut periods = Vec::new();
+        let mut month_start = today.with_day(1).unwrap_or(today);
+        for _ in 0..Self::SNAPSHOT_BACKFILL_MONTHS {
+            let month_end = match month_start.pred_opt() {
+                Some(date) => date,
+                None => break,
+            };
+            month_start = month_end.with_day(1).unwrap_or(month_end);
+            if month_end < earliest {
+                break;
+            }
+            periods.push(("monthly", month_start, month_end));
+        }
+        if frequency == "daily" {
+            for days_back in 1..=Self::SNAPSHOT_BACKFILL_DAYS {
+                let day = today - chrono::Duration::days(days_back);
+                if day < earliest {
+                    break;
+                }
+                periods.push(("daily", day, day));
+            }
+        }
+
+        let mut created = 0;
+        for (report_type, start, end) in periods {
+            let exists = sqlx::query("SELECT id FROM reports WHERE report_type = ? AND period_start = ? AND period_end = ?")
+                .bind(report_type)
+                .bind(start)
+                .bind(end)
+                .fetch_optional(&db.pool)
+                .await?;
+            if exists.is_none() {
+                self.snapshot_period(db, report_type, start, end).await?;
+                created += 1;
+            }
+        }
+
+        Ok(created)
+    }
+
     async fn calculate_period_dates(&self, start_date: Option<String>, end_date: Option<String>, period: Option<String>) -> Result<(String, String)> {
         let (first_day_of_month, last_day_of_month) = if let (Some(start), Some(end)) = (start_date, end_date) {
             (start, end)
@@ -707,3 +1127,268 @@ impl ReportsService {
         Ok((first_day_of_month, last_day_of_month))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::services::SaleService;
+
+    async fn product(db: &Database, sku: &str, current_stock: i64, min_stock: i64, is_active: bool) -> i64 {
+        sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, current_stock, min_stock, is_active) VALUES (?, ?, 30, 50, 45, ?, ?, ?)")
+            .bind(sku)
+            .bind(sku)
+            .bind(current_stock)
+            .bind(min_stock)
+            .bind(is_active)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    async fn sell(db: &Database, product_id: i64, quantity: i64, invoice_date: &str) {
+        let request = serde_json::from_value(serde_json::json!({
+            "customer_id": 999,
+            "invoice_date": invoice_date,
+            "payment_method": "cash",
+            "paid_amount": quantity as f64 * 50.0,
+            "items": [{ "product_id": product_id, "quantity": quantity, "price": 50.0 }]
+        })).unwrap();
+        SaleService::new().create(db, request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn snapshot_matches_the_period_computed_by_hand() {
+        let db = TestDatabase::new().await;
+        let lamp = product(&db, "LAMP", 10, 7, true).await;
+        product(&db, "FAN", 0, 2, true).await;
+        product(&db, "HEATER", 0, 2, false).await;
+        let supplier_id = sqlx::query("INSERT INTO suppliers (name, contact_person) VALUES ('Basra Electric', 'Omar')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        sqlx::query("INSERT INTO customers (name) VALUES ('Zainab')").execute(&db.pool).await.unwrap();
+
+        sell(&db, lamp, 4, "2026-07-14").await;
+        sell(&db, lamp, 1, "2026-08-02").await; // after the period
+        sqlx::query("INSERT INTO purchases (supplier_id, invoice_no, invoice_date, total_amount, net_amount, paid_amount) VALUES (?, 'BE-9', '2026-07-01', 600, 600, 600)")
+            .bind(supplier_id)
+            .execute(&db.pool).await.unwrap();
+        sqlx::query("INSERT INTO expenses (description, amount, category, date) VALUES ('Generator fuel', 35, 'utilities', '2026-07-20')")
+            .execute(&db.pool).await.unwrap();
+
+        let july = (NaiveDate::from_ymd_opt(2026, 7, 1).unwrap(), NaiveDate::from_ymd_opt(2026, 7, 31).unwrap());
+        let snapshot = ReportsService::new().snapshot_period(&db, "monthly", july.0, july.1).await.unwrap();
+
+        // 4 lamps at 50 cost 30 each; 35 spent on fuel
+        assert_eq!(snapshot.total_sales, 200.0);
+        assert_eq!(snapshot.total_purchases, 600.0);
+        assert_eq!(snapshot.total_expenses, 35.0);
+        assert_eq!(snapshot.net_profit, 200.0 - 120.0 - 35.0);
+        // Counts are as of now: 5 lamps left under a minimum of 7, the fan is out, the heater is inactive
+        assert_eq!((snapshot.total_customers, snapshot.total_suppliers, snapshot.total_products), (1, 1, 2));
+        assert_eq!((snapshot.low_stock_products, snapshot.out_of_stock_products), (1, 1));
+
+        // Taking the snapshot again refreshes the same row
+        sqlx::query("INSERT INTO expenses (description, amount, category, date) VALUES ('Rent', 100, 'rent', '2026-07-31')")
+            .execute(&db.pool).await.unwrap();
+        let again = ReportsService::new().snapshot_period(&db, "monthly", july.0, july.1).await.unwrap();
+        assert_eq!(again.id, snapshot.id);
+        assert_eq!(again.total_expenses, 135.0);
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM reports").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[tokio::test]
+    async fn snapshot_rejects_unknown_types_and_reversed_ranges() {
+        let db = TestDatabase::new().await;
+        let service = ReportsService::new();
+        let day = |d| NaiveDate::from_ymd_opt(2026, 7, d).unwrap();
+        assert!(service.snapshot_period(&db, "hourly", day(1), day(2)).await.is_err());
+        assert!(service.snapshot_period(&db, "monthly", day(9), day(2)).await.is_err());
+    }
+
+    async fn expense_on(db: &Database, date: &str) {
+        sqlx::query("INSERT INTO expenses (description, amount, category, date) VALUES ('Shop rent', 250, 'rent', ?)")
+            .bind(date)
+            .execute(&db.pool).await.unwrap();
+    }
+
+    async fn snapshot_periods(db: &Database, report_type: &str) -> Vec<(NaiveDate, NaiveDate)> {
+        sqlx::query_as("SELECT period_start, period_end FROM reports WHERE report_type = ? ORDER BY period_start")
+            .bind(report_type)
+            .fetch_all(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn scheduler_snapshots_each_closed_month_once() {
+        let db = TestDatabase::new().await;
+        let service = ReportsService::new();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        expense_on(&db, "2026-06-20").await;
+
+        // September is still open; nothing before June had any activity
+        assert_eq!(service.run_due_snapshots(&db, date(2026, 9, 5)).await.unwrap(), 3);
+        assert_eq!(snapshot_periods(&db, "monthly").await, vec![
+            (date(2026, 6, 1), date(2026, 6, 30)),
+            (date(2026, 7, 1), date(2026, 7, 31)),
+            (date(2026, 8, 1), date(2026, 8, 31)),
+        ]);
+        assert!(snapshot_periods(&db, "daily").await.is_empty());
+
+        // Later runs only add the months that closed since
+        assert_eq!(service.run_due_snapshots(&db, date(2026, 9, 28)).await.unwrap(), 0);
+        assert_eq!(service.run_due_snapshots(&db, date(2026, 10, 1)).await.unwrap(), 1);
+        assert_eq!(snapshot_periods(&db, "monthly").await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn scheduler_frequency_setting_adds_days_or_turns_it_off() {
+        let db = TestDatabase::new().await;
+        let service = ReportsService::new();
+        let date = |d| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        expense_on(&db, "2026-03-10").await;
+
+        sqlx::query("UPDATE settings SET report_snapshot_frequency = 'off' WHERE id = 1").execute(&db.pool).await.unwrap();
+        assert_eq!(service.run_due_snapshots(&db, date(13)).await.unwrap(), 0);
+
+        sqlx::query("UPDATE settings SET report_snapshot_frequency = 'daily' WHERE id = 1").execute(&db.pool).await.unwrap();
+        assert_eq!(service.run_due_snapshots(&db, date(13)).await.unwrap(), 3);
+        assert_eq!(snapshot_periods(&db, "daily").await, vec![(date(10), date(10)), (date(11), date(11)), (date(12), date(12))]);
+        assert_eq!(service.run_due_snapshots(&db, date(14)).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn comparison_reports_deltas_of_period_b_against_a() {
+        let db = TestDatabase::new().await;
+        let service = ReportsService::new();
+        let lamp = product(&db, "LAMP", 20, 0, true).await;
+        sell(&db, lamp, 2, "2026-01-12").await;
+        expense_on(&db, "2026-01-30").await;
+        sell(&db, lamp, 5, "2026-02-09").await;
+
+        let date = |m, d| NaiveDate::from_ymd_opt(2026, m, d).unwrap();
+        let january = (date(1, 1), date(1, 31));
+        let february = (date(2, 1), date(2, 28));
+        let comparison = service.compare_periods(&db, january, february).await.unwrap();
+
+        // January: 100 in sales, 60 cost, 250 rent. February: 250 in sales, 150 cost
+        let change = |m: &MetricChange| (m.period_a, m.period_b, m.change, m.change_percent);
+        assert_eq!(change(&comparison.sales), (100.0, 250.0, 150.0, Some(150.0)));
+        assert_eq!(change(&comparison.gross_profit), (40.0, 100.0, 60.0, Some(150.0)));
+        assert_eq!(change(&comparison.expenses), (250.0, 0.0, -250.0, Some(-100.0)));
+        // Measured against the size of January's loss
+        assert_eq!(change(&comparison.net_profit), (-210.0, 100.0, 310.0, Some(147.62)));
+
+        // An empty base period has no percentage to report
+        let march = (date(3, 1), date(3, 31));
+        let comparison = service.compare_periods(&db, march, february).await.unwrap();
+        assert_eq!(comparison.sales.change_percent, None);
+        assert!(service.compare_periods(&db, (date(2, 28), date(2, 1)), march).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn profit_and_loss_nets_out_returns_at_average_cost() {
+        let db = TestDatabase::new().await;
+        let kettle = product(&db, "KETTLE", 10, 0, true).await;
+        sqlx::query("UPDATE products SET average_cost = 28 WHERE id = ?").bind(kettle).execute(&db.pool).await.unwrap();
+
+        let request = serde_json::from_value(serde_json::json!({
+            "customer_id": 999,
+            "invoice_date": "2026-04-08",
+            "payment_method": "cash",
+            "paid_amount": 250.0,
+            "items": [{ "product_id": kettle, "quantity": 5, "price": 50.0 }]
+        })).unwrap();
+        let sale = SaleService::new().create(&db, request).await.unwrap();
+        let returned = serde_json::from_value(serde_json::json!({
+            "items": [{ "sale_item_id": sale.items[0].id, "quantity": 2, "price": 50.0, "total": 100.0 }],
+            "reason": "faulty",
+            "refund_method": "cash"
+        })).unwrap();
+        SaleService::new().process_return(&db, sale.id, returned).await.unwrap();
+        sqlx::query("INSERT INTO expenses (description, amount, category, date) VALUES ('Delivery', 40, 'transport', '2026-04-20')")
+            .execute(&db.pool).await.unwrap();
+
+        let april = (NaiveDate::from_ymd_opt(2026, 4, 1).unwrap(), NaiveDate::from_ymd_opt(2026, 4, 30).unwrap());
+        let pnl = ReportsService::new().profit_and_loss(&db, april.0, april.1).await.unwrap();
+
+        assert_eq!((pnl.gross_sales, pnl.returns, pnl.revenue), (250.0, 100.0, 150.0));
+        // Only the three kettles kept are costed
+        assert_eq!(pnl.cost_of_goods, 84.0);
+        assert_eq!(pnl.gross_profit, 66.0);
+        assert_eq!(pnl.total_expenses, 40.0);
+        assert_eq!(pnl.net_profit, 26.0);
+    }
+
+    #[tokio::test]
+    async fn top_products_order_flips_between_quantity_and_revenue() {
+        let db = TestDatabase::new().await;
+        let cable = product(&db, "USB-C", 50, 0, true).await;
+        let monitor = product(&db, "MON-24", 5, 0, true).await;
+        let request = serde_json::from_value(serde_json::json!({
+            "customer_id": 999,
+            "invoice_date": "2026-09-09",
+            "payment_method": "cash",
+            "paid_amount": 1045.0,
+            "items": [
+                { "product_id": cable, "quantity": 10, "price": 35.0 },
+                { "product_id": monitor, "quantity": 2, "price": 300.0 },
+                { "name": "توصيل", "quantity": 3, "price": 10.0 },
+                { "name": "تغليف", "quantity": 1, "price": 15.0 }
+            ]
+        })).unwrap();
+        SaleService::new().create(&db, request).await.unwrap();
+
+        let september = (NaiveDate::from_ymd_opt(2026, 9, 1).unwrap(), NaiveDate::from_ymd_opt(2026, 9, 30).unwrap());
+        let ranked = |rows: Vec<TopProduct>| rows.into_iter()
+            .map(|row| (row.name, row.total_quantity, row.total_revenue))
+            .collect::<Vec<_>>();
+
+        let by_quantity = ReportsService::new()
+            .top_products(&db, september.0, september.1, TopProductsMetric::Quantity, 10)
+            .await.unwrap();
+        assert_eq!(ranked(by_quantity), vec![
+            ("USB-C".to_string(), 10, 350.0),
+            ("مواد اخرى".to_string(), 4, 45.0),
+            ("MON-24".to_string(), 2, 600.0),
+        ]);
+
+        let by_revenue = ReportsService::new()
+            .top_products(&db, september.0, september.1, TopProductsMetric::Revenue, 2)
+            .await.unwrap();
+        assert_eq!(by_revenue[0].product_id, Some(monitor));
+        assert_eq!(by_revenue[0].sku.as_deref(), Some("MON-24"));
+        assert_eq!(ranked(by_revenue), vec![
+            ("MON-24".to_string(), 2, 600.0),
+            ("USB-C".to_string(), 10, 350.0),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn balance_components_add_up_to_the_net() {
+        let db = TestDatabase::new().await;
+        product(&db, "KETTLE", 10, 2, true).await;
+        sqlx::query("UPDATE money_boxes SET amount = 1250.5 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let supplier_id = sqlx::query("INSERT INTO suppliers (name, contact_person) VALUES ('Mosul Home Goods', 'Yasir')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        sqlx::query("INSERT INTO sales (customer_id, invoice_no, invoice_date, total_amount, net_amount, paid_amount) VALUES (999, 'BS-1', '2026-01-05', 500, 500, 200)")
+            .execute(&db.pool).await.unwrap();
+        for (invoice_no, status) in [("MH-1", "completed"), ("MH-2", "cancelled")] {
+            sqlx::query("INSERT INTO purchases (supplier_id, invoice_no, invoice_date, total_amount, net_amount, paid_amount, status) VALUES (?, ?, '2026-01-03', 800, 800, 500, ?)")
+                .bind(supplier_id)
+                .bind(invoice_no)
+                .bind(status)
+                .execute(&db.pool).await.unwrap();
+        }
+
+        let today = chrono::Local::now().date_naive();
+        let snapshot = ReportsService::new().balance_snapshot(&db, today).await.unwrap();
+        assert_eq!(snapshot.cash, 1250.5);
+        assert_eq!(snapshot.inventory_value, 300.0);
+        assert_eq!(snapshot.receivables, 300.0);
+        assert_eq!(snapshot.payables, 300.0);
+        assert_eq!(snapshot.total_assets, snapshot.cash + snapshot.inventory_value + snapshot.receivables);
+        assert_eq!(snapshot.net, snapshot.total_assets - snapshot.payables);
+        assert_eq!(snapshot.net, 1550.5);
+    }
+}
@@ -1,11 +1,60 @@
 use anyhow::Result;
 use crate::database::Database;
 use crate::models::sale::*;
-use sqlx::{Row, SqlitePool};
+use crate::services::stock_holds_service::StockHoldsService;
+use crate::services::{AuditService, BarcodeService, InventoryService, PermissionsService, ValidationService};
+use crate::services::validation_service::{PAYMENT_METHODS, PAYMENT_STATUSES};
+use crate::utils::{CsvFormat, CsvRow, PageCursor, SALE_ITEMS_LIMIT};
+use sqlx::{Acquire, Row, SqlitePool};
 use tracing::{info, warn, error};
 use chrono::{Utc, DateTime, NaiveDate, NaiveDateTime};
 use serde_json::Value;
 use std::collections::HashMap;
+use bytes::Bytes;
+use futures::stream::{self, Stream, StreamExt};
+use tokio::sync::mpsc;
+
+// Sales per chunk written to the response by the streaming export
+const EXPORT_CHUNK_ROWS: usize = 500;
+
+// How long a sale's idempotency key is remembered, and its longest accepted form
+const IDEMPOTENCY_WINDOW_HOURS: i64 = 24;
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+// Unpaid remainder would take the customer's balance past their credit limit; routes answer 400 for it
+#[derive(Debug)]
+pub struct CreditLimitExceeded {
+    pub customer_name: String,
+    pub credit_limit: f64,
+    pub current_balance: f64,
+    pub debt_amount: f64,
+}
+
+impl CreditLimitExceeded {
+    pub fn overage(&self) -> f64 {
+        self.current_balance + self.debt_amount - self.credit_limit
+    }
+}
+
+impl std::fmt::Display for CreditLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "تتجاوز الفاتورة الحد الائتماني للعميل {} ({:.2}) بمقدار {:.2}",
+            self.customer_name, self.credit_limit, self.overage()
+        )
+    }
+}
+
+impl std::error::Error for CreditLimitExceeded {}
+
+// Invoice discount past the settings threshold, approved by the caller; audited with the sale
+struct DiscountApproval {
+    user_id: i64,
+    amount: f64,
+    percent: f64,
+    threshold: f64,
+}
 
 #[derive(Clone)]
 pub struct SaleService;
@@ -15,19 +64,17 @@ impl SaleService {
         Self
     }
 
-    // Validation helpers
+    // Validation helpers; the rules themselves live in ValidationService
     pub fn validate_payment_method(method: &str) -> Result<()> {
-        let valid_methods = vec!["cash", "card", "bank_transfer"];
-        if !valid_methods.contains(&method) {
-            return Err(anyhow::anyhow!("Invalid payment method. Must be one of: {}", valid_methods.join(", ")));
+        if !PAYMENT_METHODS.contains(&method) {
+            return Err(anyhow::anyhow!("Invalid payment method. Must be one of: {}", PAYMENT_METHODS.join(", ")));
         }
         Ok(())
     }
 
     pub fn validate_payment_status(status: &str) -> Result<()> {
-        let valid_statuses = vec!["paid", "unpaid", "partial"];
-        if !valid_statuses.contains(&status) {
-            return Err(anyhow::anyhow!("Invalid payment status. Must be one of: {}", valid_statuses.join(", ")));
+        if !PAYMENT_STATUSES.contains(&status) {
+            return Err(anyhow::anyhow!("Invalid payment status. Must be one of: {}", PAYMENT_STATUSES.join(", ")));
         }
         Ok(())
     }
@@ -40,41 +87,75 @@ impl SaleService {
         Ok(())
     }
 
-    pub fn validate_sale_item(item: &CreateSaleItemRequest) -> Result<()> {
-        // Check if this is a manual item
-        let is_manual_item = item.is_manual_item();
-        
-        if is_manual_item {
-            // For manual items, provide a default name if not provided
-            if item.name.as_ref().map_or(true, |name| name.trim().is_empty()) {
-                // Default name will be set in the service
-            }
-        } else {
-            // For real products, we need a valid product_id
-            if item.product_id.is_none() || item.product_id.unwrap() <= 0 {
-                return Err(anyhow::anyhow!("Real products must have a valid positive product_id"));
-            }
+    pub fn validate_sale_item(item: &CreateSaleItemRequest, allow_zero_price: bool) -> Result<()> {
+        if let Some(error) = ValidationService::new().sale_item_errors(0, item).into_iter().next() {
+            return Err(anyhow::anyhow!(error.message));
         }
-        
-        if item.quantity <= 0 {
-            return Err(anyhow::anyhow!("Invalid quantity in sale item"));
+        if item.price == 0.0 && !allow_zero_price {
+            return Err(anyhow::anyhow!("Zero price items are not allowed"));
         }
-        if item.price <= 0.0 {
-            return Err(anyhow::anyhow!("Invalid price in sale item"));
+        Ok(())
+    }
+
+    // settings.allow_zero_price_items
+    async fn zero_price_allowed(db: &Database) -> Result<bool> {
+        let allowed: i64 = sqlx::query("SELECT COALESCE(allow_zero_price_items, 0) as allowed FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get("allowed"))
+            .unwrap_or(0);
+        Ok(allowed == 1)
+    }
+
+    // Whether the (optional) calling user holds a permission; anonymous callers never do
+    async fn caller_has_permission(db: &Database, requested_by: Option<i64>, permission: &str) -> bool {
+        match requested_by {
+            Some(user_id) => PermissionsService::new().has_permission(db, user_id, permission).await.unwrap_or(false),
+            None => false,
         }
-        if let Some(discount) = item.discount_percent {
-            if discount < 0.0 || discount > 100.0 {
-                return Err(anyhow::anyhow!("Invalid discount_percent in sale item"));
-            }
+    }
+
+    // Zero-priced lines need both the setting and the sales.zero_price permission
+    async fn validate_items(db: &Database, items: &[CreateSaleItemRequest], requested_by: Option<i64>) -> Result<()> {
+        let allow_zero_price = Self::zero_price_allowed(db).await?;
+        for item in items {
+            Self::validate_sale_item(item, allow_zero_price)?;
         }
-        if let Some(tax) = item.tax_percent {
-            if tax < 0.0 || tax > 100.0 {
-                return Err(anyhow::anyhow!("Invalid tax_percent in sale item"));
-            }
+        if items.iter().any(|item| item.price == 0.0) && !Self::caller_has_permission(db, requested_by, "sales.zero_price").await {
+            return Err(anyhow::anyhow!("Zero price items require sales.zero_price"));
         }
         Ok(())
     }
 
+    // Invoice discounts above settings.invoice_discount_threshold (% of the subtotal) need sales.discount_override;
+    // returns the approval to audit once the sale is written, None when the discount is within the threshold
+    async fn check_invoice_discount(db: &Database, subtotal: f64, total_discount: f64, requested_by: Option<i64>) -> Result<Option<DiscountApproval>> {
+        let threshold: f64 = sqlx::query("SELECT CAST(COALESCE(invoice_discount_threshold, 0) AS REAL) as threshold FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get("threshold"))
+            .unwrap_or(0.0);
+        let percent = if subtotal > 0.0 { total_discount / subtotal * 100.0 } else { 0.0 };
+        if threshold <= 0.0 || percent <= threshold {
+            return Ok(None);
+        }
+        if !Self::caller_has_permission(db, requested_by, "sales.discount_override").await {
+            return Err(anyhow::anyhow!(
+                "Invoice discount {:.2}% exceeds threshold {:.2}% and requires sales.discount_override",
+                percent, threshold
+            ));
+        }
+        Ok(requested_by.map(|user_id| DiscountApproval { user_id, amount: total_discount, percent, threshold }))
+    }
+
+    async fn record_discount_override(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, approval: &DiscountApproval, sale_id: i64) -> Result<()> {
+        AuditService::record(tx, Some(approval.user_id), "discount_override", "sale", Some(sale_id), serde_json::json!({
+            "discount_amount": approval.amount,
+            "discount_percent": approval.percent,
+            "threshold_percent": approval.threshold
+        })).await
+    }
+
     // Calculate sale totals
     pub fn calculate_sale_totals(items: &[CreateSaleItemRequest], discount_amount: f64, tax_amount: f64) -> (f64, f64, f64, f64) {
         let subtotal = items.iter().map(|item| {
@@ -90,12 +171,220 @@ impl SaleService {
         (subtotal, total_discount, total_tax, net_amount)
     }
 
+    // Dry run of create: report stale products, price drift and stock shortfalls without writing anything
+    pub async fn validate_cart(&self, db: &Database, cart: CreateSaleRequest) -> Result<CartValidation> {
+        SALE_ITEMS_LIMIT.check(cart.items.len())?;
+        let allow_zero_price = Self::zero_price_allowed(db).await?;
+        let allow_negative_stock: i64 = sqlx::query("SELECT COALESCE(allow_negative_stock, 0) as allowed FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get("allowed"))
+            .unwrap_or(0);
+
+        // Same product on several lines draws from the same stock
+        let mut requested: HashMap<i64, i64> = HashMap::new();
+        for item in cart.items.iter().filter(|item| !item.is_manual_item()) {
+            *requested.entry(item.product_id.unwrap_or_default()).or_insert(0) += item.quantity;
+        }
+
+        let mut conn = db.pool.acquire().await?;
+        let mut issues = Vec::new();
+        for (line, item) in cart.items.iter().enumerate() {
+            let issue = |issue: &str, message: String| CartIssue {
+                line,
+                product_id: item.product_id,
+                issue: issue.to_string(),
+                message,
+                requested_price: Some(item.price),
+                current_price: None,
+                available_stock: None,
+            };
+
+            if let Err(err) = Self::validate_sale_item(item, allow_zero_price) {
+                issues.push(issue("invalid_item", err.to_string()));
+                continue;
+            }
+            if item.is_manual_item() {
+                continue;
+            }
+
+            let product_id = item.product_id.unwrap_or_default();
+            let product = sqlx::query("SELECT name, selling_price, current_stock, is_active FROM products WHERE id = ?")
+                .bind(product_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+            let product = match product {
+                Some(product) => product,
+                None => {
+                    issues.push(issue("product_not_found", format!("المنتج رقم {} غير موجود", product_id)));
+                    continue;
+                }
+            };
+            let name: String = product.get("name");
+            if product.get::<Option<i64>, _>("is_active").unwrap_or(1) != 1 {
+                issues.push(issue("product_inactive", format!("المنتج {} غير مفعل", name)));
+                continue;
+            }
+
+            let current_price: f64 = product.get("selling_price");
+            if (current_price - item.price).abs() >= 0.01 {
+                issues.push(CartIssue {
+                    current_price: Some(current_price),
+                    ..issue("price_changed", format!("تغير سعر المنتج {} من {} إلى {}", name, item.price, current_price))
+                });
+            }
+
+            if allow_negative_stock == 0 {
+                let current_stock: i64 = product.get("current_stock");
+                let held = StockHoldsService::held_quantity(&mut conn, product_id, cart.hold_reference.as_deref()).await?;
+                let available = current_stock - held;
+                let wanted = requested.get(&product_id).copied().unwrap_or(item.quantity);
+                if wanted > available {
+                    issues.push(CartIssue {
+                        available_stock: Some(available.max(0)),
+                        ..issue("insufficient_stock", format!("الكمية المتاحة للمنتج {} هي {} فقط", name, available.max(0)))
+                    });
+                }
+            }
+        }
+
+        Ok(CartValidation {
+            valid: issues.is_empty(),
+            issues,
+        })
+    }
+
     // Get all sales with related data
     pub async fn get_all(&self, db: &Database, query: &SaleQuery) -> Result<SaleListResponse> {
         let page = query.page.unwrap_or(1);
         let limit = query.limit.unwrap_or(50);
         let offset = (page - 1) * limit;
+        let keyset = query.after.is_some();
+        let after = match query.after.as_deref() {
+            Some(raw) => PageCursor::decode(raw)?,
+            None => None,
+        };
 
+        let (mut where_clause, mut params) = Self::sale_filters(query);
+
+        // Get total count for pagination
+        let count_query = format!(
+            "SELECT COUNT(*) as total FROM sales s {}",
+            where_clause
+        );
+        
+        let mut count_query_builder = sqlx::query(&count_query);
+        for param in &params {
+            count_query_builder = count_query_builder.bind(param.as_str());
+        }
+        let total: i64 = count_query_builder
+            .fetch_one(&db.pool)
+            .await?
+            .get("total");
+
+        if !keyset {
+            let sales = self.fetch_with_details(db, &where_clause, &params, limit, offset).await?;
+            return Ok(SaleListResponse {
+                items: sales,
+                total,
+                page,
+                limit,
+                total_pages: (total + limit - 1) / limit,
+                next_cursor: None,
+            });
+        }
+
+        // Keyset mode: same (created_at, id) order as offset mode, one extra row tells whether more remain
+        if let Some(cursor) = after {
+            let condition = PageCursor::condition("s");
+            where_clause = if where_clause.is_empty() {
+                format!("WHERE {}", condition)
+            } else {
+                format!("{} AND {}", where_clause, condition)
+            };
+            params.push(cursor.created_at.clone());
+            params.push(cursor.created_at);
+            params.push(cursor.id.to_string());
+        }
+
+        let mut sales = self.fetch_with_details(db, &where_clause, &params, limit + 1, 0).await?;
+        let mut next_cursor = None;
+        if sales.len() as i64 > limit {
+            sales.truncate(limit.max(0) as usize);
+            if let Some(last) = sales.last() {
+                // The raw column text, so the next page compares exactly what SQLite sorted on
+                let created_at: String = sqlx::query_scalar("SELECT CAST(created_at AS TEXT) FROM sales WHERE id = ?")
+                    .bind(last.id)
+                    .fetch_one(&db.pool)
+                    .await?;
+                next_cursor = Some(PageCursor { created_at, id: last.id }.encode());
+            }
+        }
+
+        Ok(SaleListResponse {
+            items: sales,
+            total,
+            page,
+            limit,
+            total_pages: (total + limit - 1) / limit,
+            next_cursor,
+        })
+    }
+
+    // Every sale matching the list filters (page and limit are ignored) as CSV. Rows are read with a
+    // cursor and sent in chunks of EXPORT_CHUNK_ROWS, so a year of sales never sits in memory at once.
+    // A database error mid-way ends the stream with an error, which aborts the response instead of
+    // handing the client a silently truncated file.
+    pub fn export_csv_stream(&self, db: &Database, query: &SaleQuery, format: CsvFormat) -> impl Stream<Item = std::io::Result<Bytes>> {
+        let (where_clause, params) = Self::sale_filters(query);
+        let sales_query = Self::sales_with_details_query(&where_clause);
+        let pool = db.pool.clone();
+        let (sender, receiver) = mpsc::channel::<std::io::Result<Bytes>>(4);
+
+        tokio::spawn(async move {
+            let mut chunk = format.finish(format.header(&SaleWithDetails::headers()));
+            let mut pending = 0;
+
+            let mut query_builder = sqlx::query(&sales_query);
+            for param in &params {
+                query_builder = query_builder.bind(param.as_str());
+            }
+            // A negative LIMIT means no limit in SQLite
+            let mut rows = query_builder.bind(-1i64).bind(0i64).fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(row) => {
+                        let sale = Self::map_sale_row_to_with_details(&row);
+                        chunk.push_str(&format.row(&sale.to_record(&format)));
+                        pending += 1;
+                        if pending >= EXPORT_CHUNK_ROWS {
+                            pending = 0;
+                            // The client went away, stop reading
+                            if sender.send(Ok(Bytes::from(std::mem::take(&mut chunk)))).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("Sales export stopped by a database error: {}", e);
+                        let _ = sender.send(Err(std::io::Error::other(e))).await;
+                        return;
+                    }
+                }
+            }
+
+            if !chunk.is_empty() {
+                let _ = sender.send(Ok(Bytes::from(chunk))).await;
+            }
+        });
+
+        stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|item| (item, receiver))
+        })
+    }
+
+    fn sale_filters(query: &SaleQuery) -> (String, Vec<String>) {
         let mut conditions = Vec::new();
         let mut params: Vec<String> = Vec::new();
 
@@ -131,23 +420,12 @@ impl SaleService {
             format!("WHERE {}", conditions.join(" AND "))
         };
 
-        // Get total count for pagination
-        let count_query = format!(
-            "SELECT COUNT(*) as total FROM sales s {}",
-            where_clause
-        );
-        
-        let mut count_query_builder = sqlx::query(&count_query);
-        for param in &params {
-            count_query_builder = count_query_builder.bind(param.as_str());
-        }
-        let total: i64 = count_query_builder
-            .fetch_one(&db.pool)
-            .await?
-            .get("total");
+        (where_clause, params)
+    }
 
-        // Get sales with details
-        let sales_query = format!(
+    // SELECT for sales with their items aggregated per sale; binds the filter params, then LIMIT and OFFSET
+    fn sales_with_details_query(where_clause: &str) -> String {
+        format!(
             r#"
             SELECT 
                 s.*,
@@ -158,20 +436,21 @@ impl SaleService {
                 json_group_array(
                     json_object(
                         'id', si.id,
+                        'sale_id', si.sale_id,
                         'product_id', si.product_id,
                         'product_name', CASE 
                             WHEN si.product_name IS NOT NULL THEN si.product_name
-                            WHEN si.product_id IS NOT NULL THEN p.name 
+                            WHEN si.product_id IS NOT NULL THEN COALESCE(p.name, 'مواد اخرى')
                             ELSE 'مواد اخرى'
                         END,
                         'sku', CASE 
                             WHEN si.product_name IS NOT NULL THEN 'MANUAL'
-                            WHEN si.product_id IS NOT NULL THEN p.sku 
+                            WHEN si.product_id IS NOT NULL THEN COALESCE(p.sku, 'MANUAL')
                             ELSE 'MANUAL'
                         END,
                         'unit', CASE 
                             WHEN si.product_name IS NOT NULL THEN 'قطعة'
-                            WHEN si.product_id IS NOT NULL THEN p.unit 
+                            WHEN si.product_id IS NOT NULL THEN COALESCE(p.unit, 'قطعة')
                             ELSE 'قطعة'
                         END,
                         'quantity', si.quantity,
@@ -180,9 +459,11 @@ impl SaleService {
                         'discount_percent', si.discount_percent,
                         'tax_percent', si.tax_percent,
                         'total', si.total,
-                        'line_total', si.line_total
+                        'line_total', si.line_total,
+                        'created_at', strftime('%Y-%m-%dT%H:%M:%S', si.created_at),
+                        'updated_at', strftime('%Y-%m-%dT%H:%M:%S', si.updated_at)
                     )
-                ) as items
+                ) FILTER (WHERE si.id IS NOT NULL) as items
             FROM sales s
             LEFT JOIN customers c ON s.customer_id = c.id
             LEFT JOIN representatives r ON s.delegate_id = r.id
@@ -191,151 +472,39 @@ impl SaleService {
             LEFT JOIN products p ON si.product_id = p.id AND si.product_id IS NOT NULL
             {}
             GROUP BY s.id
-            ORDER BY s.created_at DESC
+            ORDER BY s.created_at DESC, s.id DESC
             LIMIT ? OFFSET ?
             "#,
             where_clause
-        );
+        )
+    }
+
+    // Sales with their items for an already built WHERE clause, newest first
+    async fn fetch_with_details(&self, db: &Database, where_clause: &str, params: &[String], limit: i64, offset: i64) -> Result<Vec<SaleWithDetails>> {
+        let sales_query = Self::sales_with_details_query(where_clause);
 
         let mut query_builder = sqlx::query(&sales_query);
-        for param in &params {
+        for param in params {
             query_builder = query_builder.bind(param.as_str());
         }
         query_builder = query_builder.bind(limit).bind(offset);
 
+        // Items are aggregated per sale in the same statement, so a page costs one query
         let sales_rows = query_builder.fetch_all(&db.pool).await?;
 
-        let mut sales = Vec::new();
-        for row in sales_rows {
-            let sale = self.map_sale_row_to_with_details(row).await?;
-            sales.push(sale);
-        }
-
-        Ok(SaleListResponse {
-            items: sales,
-            total,
-            page,
-            limit,
-            total_pages: (total + limit - 1) / limit,
-        })
+        Ok(sales_rows.iter().map(Self::map_sale_row_to_with_details).collect())
     }
 
     // Get sale by ID with related data
     pub async fn get_by_id(&self, db: &Database, id: i64) -> Result<Option<SaleWithDetails>> {
-        let sale_row = sqlx::query(r#"
-            SELECT 
-                s.*,
-                c.name as customer_name,
-                r.name as delegate_name,
-                u.name as created_by_name,
-                u.username as created_by_username,
-                json_group_array(
-                    json_object(
-                        'id', si.id,
-                        'product_id', si.product_id,
-                        'product_name', CASE 
-                            WHEN si.product_name IS NOT NULL THEN si.product_name
-                            WHEN si.product_id IS NOT NULL THEN p.name 
-                            ELSE 'مواد اخرى'
-                        END,
-                        'sku', CASE 
-                            WHEN si.product_name IS NOT NULL THEN 'MANUAL'
-                            WHEN si.product_id IS NOT NULL THEN p.sku 
-                            ELSE 'MANUAL'
-                        END,
-                        'unit', CASE 
-                            WHEN si.product_name IS NOT NULL THEN 'قطعة'
-                            WHEN si.product_id IS NOT NULL THEN p.unit 
-                            ELSE 'قطعة'
-                        END,
-                        'quantity', si.quantity,
-                        'returned_quantity', si.returned_quantity,
-                        'price', si.price,
-                        'discount_percent', si.discount_percent,
-                        'tax_percent', si.tax_percent,
-                        'total', si.total,
-                        'line_total', si.line_total
-                    )
-                ) as items
-            FROM sales s
-            LEFT JOIN customers c ON s.customer_id = c.id
-            LEFT JOIN representatives r ON s.delegate_id = r.id
-            LEFT JOIN users u ON s.created_by = u.id
-            LEFT JOIN sale_items si ON s.id = si.sale_id
-            LEFT JOIN products p ON si.product_id = p.id AND si.product_id IS NOT NULL
-            WHERE s.id = ?
-            GROUP BY s.id
-        "#)
-        .bind(id)
-        .fetch_optional(&db.pool)
-        .await?;
-
-        if let Some(row) = sale_row {
-            let sale = self.map_sale_row_to_with_details(row).await?;
-            Ok(Some(sale))
-        } else {
-            Ok(None)
-        }
+        let mut sales = self.fetch_with_details(db, "WHERE s.id = ?", &[id.to_string()], 1, 0).await?;
+        Ok(sales.pop())
     }
 
     // Get customer sales
     pub async fn get_by_customer(&self, db: &Database, customer_id: i64) -> Result<Vec<SaleWithDetails>> {
-        let sales_rows = sqlx::query(r#"
-            SELECT 
-                s.*,
-                c.name as customer_name,
-                r.name as delegate_name,
-                u.name as created_by_name,
-                u.username as created_by_username,
-                json_group_array(
-                    json_object(
-                        'id', si.id,
-                        'product_id', si.product_id,
-                        'product_name', CASE 
-                            WHEN si.product_name IS NOT NULL THEN si.product_name
-                            WHEN si.product_id IS NOT NULL THEN p.name 
-                            ELSE 'مواد اخرى'
-                        END,
-                        'sku', CASE 
-                            WHEN si.product_name IS NOT NULL THEN 'MANUAL'
-                            WHEN si.product_id IS NOT NULL THEN p.sku 
-                            ELSE 'MANUAL'
-                        END,
-                        'unit', CASE 
-                            WHEN si.product_name IS NOT NULL THEN 'قطعة'
-                            WHEN si.product_id IS NOT NULL THEN p.unit 
-                            ELSE 'قطعة'
-                        END,
-                        'quantity', si.quantity,
-                        'returned_quantity', si.returned_quantity,
-                        'price', si.price,
-                        'discount_percent', si.discount_percent,
-                        'tax_percent', si.tax_percent,
-                        'total', si.total,
-                        'line_total', si.line_total
-                    )
-                ) as items
-            FROM sales s
-            LEFT JOIN customers c ON s.customer_id = c.id
-            LEFT JOIN representatives r ON s.delegate_id = r.id
-            LEFT JOIN users u ON s.created_by = u.id
-            LEFT JOIN sale_items si ON s.id = si.sale_id
-            LEFT JOIN products p ON si.product_id = p.id AND si.product_id IS NOT NULL
-            WHERE s.customer_id = ?
-            GROUP BY s.id
-            ORDER BY s.created_at DESC
-        "#)
-        .bind(customer_id)
-        .fetch_all(&db.pool)
-        .await?;
-
-        let mut sales = Vec::new();
-        for row in sales_rows {
-            let sale = self.map_sale_row_to_with_details(row).await?;
-            sales.push(sale);
-        }
-
-        Ok(sales)
+        // LIMIT -1 means no limit in SQLite
+        self.fetch_with_details(db, "WHERE s.customer_id = ?", &[customer_id.to_string()], -1, 0).await
     }
 
     // Create new sale
@@ -349,6 +518,19 @@ impl SaleService {
         if sale_data.items.is_empty() {
             return Err(anyhow::anyhow!("Sale must have at least one item"));
         }
+        SALE_ITEMS_LIMIT.check(sale_data.items.len())?;
+
+        // A double-click or client retry carrying the same key gets the sale the first request created
+        let idempotency_key = sale_data.idempotency_key.as_deref().map(str::trim).filter(|key| !key.is_empty()).map(str::to_string);
+        if let Some(ref key) = idempotency_key {
+            if key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+                return Err(anyhow::anyhow!("Idempotency key must be at most {} characters", MAX_IDEMPOTENCY_KEY_LEN));
+            }
+            if let Some(sale) = self.sale_for_idempotency_key(db, key).await? {
+                info!("Idempotency key {} already used by sale {}, returning it", key, sale.id);
+                return Ok(sale);
+            }
+        }
 
         // Validate customer exists (if not anonymous)
         if let Some(customer_id) = sale_data.customer_id {
@@ -377,9 +559,9 @@ impl SaleService {
         }
 
         // Validate each sale item
+        Self::validate_items(db, &sale_data.items, sale_data.requested_by).await?;
         for item in &sale_data.items {
-            Self::validate_sale_item(item)?;
-            
+
             // Validate product exists (if not manual item)
             if !item.is_manual_item() {
                 if let Some(product_id) = item.product_id {
@@ -416,19 +598,36 @@ impl SaleService {
         }
 
         // Calculate totals
-        let (_subtotal, total_discount, total_tax, net_amount) = Self::calculate_sale_totals(
+        let (subtotal, total_discount, total_tax, net_amount) = Self::calculate_sale_totals(
             &sale_data.items,
             sale_data.discount_amount.unwrap_or(0.0),
             sale_data.tax_amount.unwrap_or(0.0)
         );
 
+        let discount_approval = Self::check_invoice_discount(db, subtotal, total_discount, sale_data.requested_by).await?;
+
+        // Going past the credit limit is only possible for someone allowed to approve it
+        let credit_approver = if sale_data.override_credit_limit.unwrap_or(false) {
+            if !Self::caller_has_permission(db, sale_data.requested_by, "sales.credit_limit_override").await {
+                return Err(anyhow::anyhow!("Overriding the credit limit requires sales.credit_limit_override"));
+            }
+            sale_data.requested_by
+        } else {
+            None
+        };
+
         // Generate invoice number
         let timestamp = chrono::Utc::now().timestamp_millis();
         let random_suffix = rand::random::<u32>() % 10000;
         let invoice_no = format!("INV-{}-{}", timestamp, random_suffix);
 
-        // Use database transaction
-        let mut tx = db.pool.begin().await?;
+        // Use database transaction, started over if another writer holds the database
+        let sale_data = &sale_data;
+        let invoice_no = &invoice_no;
+        let idempotency_key = &idempotency_key;
+        let discount_approval = &discount_approval;
+        let result = db.with_retry(move || async move {
+                let mut tx = db.pool.begin().await?;
                 // Double-check for duplicates within transaction
                 if let Some(ref barcode) = sale_data.barcode {
                     let duplicate_check = sqlx::query("SELECT id FROM sales WHERE barcode = ?")
@@ -444,15 +643,16 @@ impl SaleService {
                 // Create sale record
                 let sale_id = sqlx::query(r#"
                     INSERT INTO sales (
-                        customer_id, delegate_id, invoice_no, invoice_date, due_date,
+                        customer_id, delegate_id, employee_id, invoice_no, invoice_date, due_date,
                         total_amount, discount_amount, tax_amount, net_amount,
                         paid_amount, payment_method, payment_status, status,
                         notes, barcode, created_by, created_at, updated_at
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
                 "#)
                 .bind(sale_data.customer_id)
                 .bind(sale_data.delegate_id)
-                .bind(&invoice_no)
+                .bind(sale_data.employee_id)
+                .bind(invoice_no)
                 .bind(sale_data.invoice_date.unwrap_or_else(|| chrono::Utc::now().date_naive()))
                 .bind(sale_data.due_date)
                 .bind(net_amount)
@@ -464,12 +664,63 @@ impl SaleService {
                 .bind(sale_data.payment_status.as_deref().unwrap_or("unpaid"))
                 .bind("completed")
                 .bind(&sale_data.notes)
-                .bind(sale_data.barcode)
+                .bind(&sale_data.barcode)
                 .bind(Some(1i64)) // created_by - using admin user ID
                 .execute(&mut *tx)
-                .await?
+                .await
+                .map_err(|e| match sale_data.barcode {
+                    Some(ref barcode) if e.to_string().contains("UNIQUE") => {
+                        anyhow::anyhow!("Sale with barcode {} already exists", barcode)
+                    }
+                    _ => e.into(),
+                })?
                 .last_insert_rowid();
 
+                // Unique per key: of two concurrent requests with the same key only one commits
+                if let Some(key) = idempotency_key {
+                    sqlx::query("INSERT INTO idempotency_keys (scope, idempotency_key, entity_id) VALUES ('sale', ?, ?)")
+                        .bind(key)
+                        .bind(sale_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                // Derive the barcode from the sale id so concurrent sales can never collide
+                if sale_data.barcode.is_none() {
+                    let auto_generate: i64 = sqlx::query("SELECT COALESCE(auto_generate_sale_barcode, 1) as enabled FROM settings WHERE id = 1")
+                        .fetch_optional(&mut *tx)
+                        .await?
+                        .map(|row| row.get("enabled"))
+                        .unwrap_or(1);
+
+                    if auto_generate == 1 {
+                        sqlx::query("UPDATE sales SET barcode = ? WHERE id = ?")
+                            .bind(Self::sale_barcode(sale_id))
+                            .bind(sale_id)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+
+                // Quantities reserved by other drafts are not available to this sale
+                for item in sale_data.items.iter().filter(|item| !item.is_manual_item()) {
+                    let product_id = item.product_id.unwrap_or_default();
+                    let held = StockHoldsService::held_quantity(&mut tx, product_id, sale_data.hold_reference.as_deref()).await?;
+                    if held > 0 {
+                        let current_stock: i64 = sqlx::query("SELECT current_stock FROM products WHERE id = ?")
+                            .bind(product_id)
+                            .fetch_one(&mut *tx)
+                            .await?
+                            .get("current_stock");
+                        if item.quantity > current_stock - held {
+                            return Err(anyhow::anyhow!(
+                                "Insufficient available stock for product {}: {} available, {} held by draft sales",
+                                product_id, (current_stock - held).max(0), held
+                            ));
+                        }
+                    }
+                }
+
                 // Create sale items
                 for item in &sale_data.items {
                     let item_total = item.total.unwrap_or_else(|| item.quantity as f64 * item.price);
@@ -515,12 +766,54 @@ impl SaleService {
                         .bind(line_total)
                         .execute(&mut *tx)
                         .await?;
+
+                        Self::move_sale_stock(&mut tx, sale_id, item.product_id.unwrap_or_default(), item.quantity, sale_data.requested_by, "بيع").await?;
                     }
                 }
 
+                // Confirming a draft turns its holds into the actual decrement done above
+                if let Some(ref reference) = sale_data.hold_reference {
+                    StockHoldsService::confirm(&mut tx, reference, sale_id).await?;
+                }
+
+                if let Some(approval) = discount_approval {
+                    Self::record_discount_override(&mut tx, approval, sale_id).await?;
+                }
+
                 // Create debt record if payment is not fully paid
                 if sale_data.payment_status.as_deref() != Some("paid") && (sale_data.paid_amount.unwrap_or(0.0) < net_amount) {
                     let debt_amount = net_amount - sale_data.paid_amount.unwrap_or(0.0);
+
+                    // Checked against the balance as of this transaction so concurrent credit sales can't both slip under
+                    if let Some(customer_id) = sale_data.customer_id {
+                        let customer = sqlx::query("SELECT name, COALESCE(credit_limit, 0) as credit_limit, COALESCE(current_balance, 0) as current_balance FROM customers WHERE id = ?")
+                            .bind(customer_id)
+                            .fetch_optional(&mut *tx)
+                            .await?;
+                        if let Some(customer) = customer {
+                            let exceeded = CreditLimitExceeded {
+                                customer_name: customer.get("name"),
+                                credit_limit: customer.get("credit_limit"),
+                                current_balance: customer.get("current_balance"),
+                                debt_amount,
+                            };
+                            if exceeded.overage() > 0.005 {
+                                match credit_approver {
+                                    Some(user_id) => {
+                                        AuditService::record(&mut tx, Some(user_id), "credit_limit_override", "sale", Some(sale_id), serde_json::json!({
+                                            "customer_id": customer_id,
+                                            "credit_limit": exceeded.credit_limit,
+                                            "current_balance": exceeded.current_balance,
+                                            "debt_amount": debt_amount,
+                                            "overage": exceeded.overage()
+                                        })).await?;
+                                    }
+                                    None => return Err(exceeded.into()),
+                                }
+                            }
+                        }
+                    }
+
                     sqlx::query(r#"
                         INSERT INTO debts (
                             customer_id, sale_id, amount, due_date, status, notes, created_at, updated_at
@@ -534,16 +827,201 @@ impl SaleService {
                     .bind(&sale_data.notes)
                     .execute(&mut *tx)
                     .await?;
+
+                    // current_balance is what the customer owes: open debts less unallocated payments
+                    sqlx::query("UPDATE customers SET current_balance = COALESCE(current_balance, 0) + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                        .bind(debt_amount)
+                        .bind(sale_data.customer_id)
+                        .execute(&mut *tx)
+                        .await?;
                 }
 
-        tx.commit().await?;
-        let result = sale_id;
+                tx.commit().await?;
+                Ok(sale_id)
+        }).await;
+
+        // Lost the race to a concurrent request with the same key: answer with its sale
+        let result = match (result, idempotency_key) {
+            (Ok(sale_id), _) => sale_id,
+            (Err(err), Some(key)) => match self.sale_for_idempotency_key(db, key).await? {
+                Some(sale) => return Ok(sale),
+                None => return Err(err),
+            },
+            (Err(err), None) => return Err(err),
+        };
 
         // Get the created sale with details
         let sale = self.get_by_id(db, result).await?;
         sale.ok_or_else(|| anyhow::anyhow!("Failed to retrieve created sale"))
     }
 
+    // Sale recorded under an idempotency key within the window. Expired keys are dropped first so
+    // they can be used again; a key whose sale has since been deleted is dropped as well.
+    async fn sale_for_idempotency_key(&self, db: &Database, key: &str) -> Result<Option<SaleWithDetails>> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE created_at < datetime('now', '-' || ? || ' hours')")
+            .bind(IDEMPOTENCY_WINDOW_HOURS)
+            .execute(&db.pool)
+            .await?;
+
+        let sale_id: Option<i64> = sqlx::query_scalar("SELECT entity_id FROM idempotency_keys WHERE scope = 'sale' AND idempotency_key = ?")
+            .bind(key)
+            .fetch_optional(&db.pool)
+            .await?;
+        let sale_id = match sale_id {
+            Some(sale_id) => sale_id,
+            None => return Ok(None),
+        };
+
+        let sale = self.get_by_id(db, sale_id).await?;
+        if sale.is_none() {
+            sqlx::query("DELETE FROM idempotency_keys WHERE scope = 'sale' AND idempotency_key = ?")
+                .bind(key)
+                .execute(&db.pool)
+                .await?;
+        }
+        Ok(sale)
+    }
+
+    // Sale lines move stock here, inside the caller's transaction, and each move is logged to
+    // inventory_movements: a positive quantity leaves the shelf as sold (earliest-expiring batch
+    // first), a negative one goes back.
+    // Lines without a known product (manual items) have no stock to move.
+    pub async fn move_sale_stock(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        sale_id: i64,
+        product_id: i64,
+        quantity: i64,
+        created_by: Option<i64>,
+        notes: &str,
+    ) -> Result<()> {
+        if quantity == 0 {
+            return Ok(());
+        }
+        let unit_cost: Option<f64> = match sqlx::query_scalar("SELECT CAST(purchase_price AS REAL) FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_optional(&mut **tx)
+            .await?
+        {
+            Some(cost) => cost,
+            None => return Ok(()),
+        };
+
+        sqlx::query(r#"
+            UPDATE products
+            SET current_stock = current_stock - ?, total_sold = total_sold + ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+        "#)
+        .bind(quantity)
+        .bind(quantity)
+        .bind(product_id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(r#"
+            INSERT INTO inventory_movements (
+                product_id, movement_type, quantity, unit_cost, total_value,
+                reference_type, reference_id, notes, created_by
+            ) VALUES (?, ?, ?, ?, ?, 'sale', ?, ?, ?)
+        "#)
+        .bind(product_id)
+        .bind(if quantity > 0 { "out" } else { "in" })
+        .bind(quantity.abs())
+        .bind(unit_cost)
+        .bind(unit_cost.map(|cost| cost * quantity.abs() as f64))
+        .bind(sale_id)
+        .bind(notes)
+        .bind(created_by)
+        .execute(&mut **tx)
+        .await?;
+
+        if quantity > 0 {
+            InventoryService::take_from_batches(tx, sale_id, product_id, quantity).await?;
+        } else {
+            InventoryService::return_to_batches(tx, sale_id, product_id, -quantity).await?;
+        }
+
+        Ok(())
+    }
+
+    // Put back the stock of every product line of a sale, e.g. before its items are replaced or removed.
+    // Cancelled sales already had their stock returned when they were voided.
+    pub async fn restore_sale_stock(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        sale_id: i64,
+        created_by: Option<i64>,
+        notes: &str,
+    ) -> Result<()> {
+        let status: Option<String> = sqlx::query_scalar("SELECT status FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+        if status.as_deref().unwrap_or("cancelled") == "cancelled" {
+            return Ok(());
+        }
+
+        // Returned units already went back to stock when the return was processed
+        let items = sqlx::query(
+            "SELECT product_id, quantity - COALESCE(returned_quantity, 0) AS kept FROM sale_items WHERE sale_id = ? AND product_id IS NOT NULL"
+        )
+            .bind(sale_id)
+            .fetch_all(&mut **tx)
+            .await?;
+        for item in items {
+            let kept: i64 = item.get("kept");
+            if kept <= 0 {
+                continue;
+            }
+            Self::move_sale_stock(tx, sale_id, item.get("product_id"), -kept, created_by, notes).await?;
+        }
+        Ok(())
+    }
+
+    // What a sale still has open in debts, i.e. its share of the customer's current_balance
+    async fn open_debt(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, sale_id: i64) -> Result<f64> {
+        let open: f64 = sqlx::query_scalar("SELECT CAST(COALESCE(SUM(amount), 0) AS REAL) FROM debts WHERE sale_id = ? AND status != 'paid'")
+            .bind(sale_id)
+            .fetch_one(&mut **tx)
+            .await?;
+        Ok(open)
+    }
+
+    // Move the running balance of the sale's customer by `delta` (positive: owes more)
+    async fn shift_customer_balance(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, sale_id: i64, delta: f64) -> Result<()> {
+        if delta.abs() < 0.005 {
+            return Ok(());
+        }
+        sqlx::query(r#"
+            UPDATE customers
+            SET current_balance = COALESCE(current_balance, 0) + ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = (SELECT customer_id FROM sales WHERE id = ?)
+        "#)
+        .bind(delta)
+        .bind(sale_id)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    // EAN-13 for a sale id: "2" (in-store prefix) + 11-digit zero-padded id + check digit
+    pub fn sale_barcode(sale_id: i64) -> String {
+        let body = format!("2{:011}", sale_id);
+        format!("{}{}", body, BarcodeService::check_digit(&body))
+    }
+
+    // Look up a sale by its scanned barcode
+    pub async fn get_by_barcode(&self, db: &Database, barcode: &str) -> Result<Option<SaleWithDetails>> {
+        let sale_id = sqlx::query("SELECT id FROM sales WHERE barcode = ?")
+            .bind(barcode.trim())
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get::<i64, _>("id"));
+
+        match sale_id {
+            Some(id) => self.get_by_id(db, id).await,
+            None => Ok(None),
+        }
+    }
+
     // Update sale
     pub async fn update(&self, db: &Database, id: i64, sale_data: UpdateSaleRequest) -> Result<SaleWithDetails> {
         // Validate payment method and status if provided
@@ -554,11 +1032,18 @@ impl SaleService {
             Self::validate_payment_status(payment_status)?;
         }
 
-        // Validate items if provided
+        // Validate items if provided; new items also reprice the invoice discount
+        let mut totals = (0.0, 0.0, 0.0, 0.0);
+        let mut discount_approval = None;
         if let Some(ref items) = sale_data.items {
-            for item in items {
-                Self::validate_sale_item(item)?;
-            }
+            SALE_ITEMS_LIMIT.check(items.len())?;
+            Self::validate_items(db, items, sale_data.requested_by).await?;
+            totals = Self::calculate_sale_totals(
+                items,
+                sale_data.discount_amount.unwrap_or(0.0),
+                sale_data.tax_amount.unwrap_or(0.0)
+            );
+            discount_approval = Self::check_invoice_discount(db, totals.0, totals.1, sale_data.requested_by).await?;
         }
 
         let mut tx = db.pool.begin().await?;
@@ -572,24 +1057,15 @@ impl SaleService {
                     return Err(anyhow::anyhow!("Sale not found"));
                 }
 
-                // Delete existing sale items if new items are provided
+                // Delete existing sale items if new items are provided, putting their stock back first
                 if sale_data.items.is_some() {
+                    Self::restore_sale_stock(&mut tx, id, sale_data.requested_by, "تعديل فاتورة بيع").await?;
                     sqlx::query("DELETE FROM sale_items WHERE sale_id = ?")
                         .bind(id)
                         .execute(&mut *tx)
                         .await?;
                 }
 
-                // Calculate new totals if items are provided
-                let mut totals = (0.0, 0.0, 0.0, 0.0);
-                if let Some(ref items) = sale_data.items {
-                    totals = Self::calculate_sale_totals(
-                        items,
-                        sale_data.discount_amount.unwrap_or(0.0),
-                        sale_data.tax_amount.unwrap_or(0.0)
-                    );
-                }
-
                 // Update sale
                 let mut query_parts = Vec::new();
                 let mut has_updates = false;
@@ -679,6 +1155,7 @@ impl SaleService {
                 }
 
                 // Insert new sale items if provided
+                let items_changed = sale_data.items.is_some();
                 if let Some(items) = sale_data.items {
                     for item in items {
                         let item_total = item.total.unwrap_or_else(|| item.quantity as f64 * item.price);
@@ -722,10 +1199,43 @@ impl SaleService {
                             .bind(line_total)
                             .execute(&mut *tx)
                             .await?;
+
+                            Self::move_sale_stock(&mut tx, id, item.product_id.unwrap_or_default(), item.quantity, sale_data.requested_by, "تعديل فاتورة بيع").await?;
                         }
                     }
                 }
 
+                // A new net amount moves what is still owed on the sale; only an open debt carries it
+                let old_net: f64 = existing_sale.as_ref().map_or(0.0, |sale| sale.get("net_amount"));
+                let net_change = totals.3 - old_net;
+                if items_changed && net_change.abs() >= 0.005 {
+                    let open = Self::open_debt(&mut tx, id).await?;
+                    if open > 0.0 {
+                        let owed = (open + net_change).max(0.0);
+                        if owed < 0.005 {
+                            sqlx::query("DELETE FROM debts WHERE sale_id = ? AND status != 'paid'")
+                                .bind(id)
+                                .execute(&mut *tx)
+                                .await?;
+                        } else {
+                            sqlx::query("UPDATE debts SET amount = ?, updated_at = CURRENT_TIMESTAMP WHERE sale_id = ? AND status != 'paid'")
+                                .bind(owed)
+                                .bind(id)
+                                .execute(&mut *tx)
+                                .await?;
+                        }
+                        sqlx::query("UPDATE sales SET remaining_amount = MAX(net_amount - paid_amount, 0) WHERE id = ?")
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await?;
+                        Self::shift_customer_balance(&mut tx, id, owed - open).await?;
+                    }
+                }
+
+                if let Some(ref approval) = discount_approval {
+                    Self::record_discount_override(&mut tx, approval, id).await?;
+                }
+
         tx.commit().await?;
 
         // Get the updated sale with details
@@ -736,6 +1246,12 @@ impl SaleService {
     // Delete sale
     pub async fn delete(&self, db: &Database, id: i64) -> Result<bool> {
         let mut tx = db.pool.begin().await?;
+                Self::restore_sale_stock(&mut tx, id, None, "حذف فاتورة بيع").await?;
+
+                // The customer no longer owes what was left open on the sale
+                let open = Self::open_debt(&mut tx, id).await?;
+                Self::shift_customer_balance(&mut tx, id, -open).await?;
+
                 // Delete related records
                 sqlx::query("DELETE FROM debts WHERE sale_id = ?")
                     .bind(id)
@@ -760,6 +1276,235 @@ impl SaleService {
         Ok(result)
     }
 
+    // Void every sale matching `filter` as a correction: stock goes back, the sale's debts are cleared and
+    // money received into money boxes through its receipts is withdrawn again. Sales with returns are
+    // skipped. Work is committed in batches, each sale under its own savepoint so one failure does not
+    // undo the others.
+    pub async fn bulk_void(&self, db: &Database, filter: SaleFilter, reason: &str, user_id: i64) -> Result<BulkVoidResult> {
+        const BATCH_SIZE: usize = 50;
+
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(anyhow::anyhow!("سبب الإلغاء مطلوب"));
+        }
+        let has_ids = filter.sale_ids.as_ref().is_some_and(|ids| !ids.is_empty());
+        if !has_ids && (filter.start_date.is_none() || filter.end_date.is_none()) {
+            return Err(anyhow::anyhow!("يجب تحديد نطاق تاريخ أو أرقام الفواتير"));
+        }
+
+        let mut conditions = vec!["1 = 1".to_string()];
+        if filter.start_date.is_some() {
+            conditions.push("invoice_date >= ?".to_string());
+        }
+        if filter.end_date.is_some() {
+            conditions.push("invoice_date <= ?".to_string());
+        }
+        if filter.customer_id.is_some() {
+            conditions.push("customer_id = ?".to_string());
+        }
+        if let Some(ids) = filter.sale_ids.as_ref().filter(|ids| !ids.is_empty()) {
+            conditions.push(format!("id IN ({})", vec!["?"; ids.len()].join(", ")));
+        }
+        let sql = format!("SELECT id, invoice_no FROM sales WHERE {} ORDER BY id", conditions.join(" AND "));
+        let mut query_builder = sqlx::query(&sql);
+        if let Some(start_date) = filter.start_date {
+            query_builder = query_builder.bind(start_date);
+        }
+        if let Some(end_date) = filter.end_date {
+            query_builder = query_builder.bind(end_date);
+        }
+        if let Some(customer_id) = filter.customer_id {
+            query_builder = query_builder.bind(customer_id);
+        }
+        for id in filter.sale_ids.iter().flatten() {
+            query_builder = query_builder.bind(id);
+        }
+        let sales: Vec<(i64, String)> = query_builder
+            .fetch_all(&db.pool)
+            .await?
+            .into_iter()
+            .map(|row| (row.get("id"), row.get("invoice_no")))
+            .collect();
+
+        let mut outcomes = Vec::with_capacity(sales.len());
+        for batch in sales.chunks(BATCH_SIZE) {
+            let mut tx = db.pool.begin().await?;
+            for (sale_id, invoice_no) in batch {
+                let mut savepoint = tx.begin().await?;
+                let (status, message) = match Self::void_in_tx(&mut savepoint, *sale_id, invoice_no, reason, user_id).await {
+                    Ok(None) => {
+                        savepoint.commit().await?;
+                        ("voided", None)
+                    }
+                    Ok(Some(skip_reason)) => {
+                        savepoint.rollback().await?;
+                        ("skipped", Some(skip_reason))
+                    }
+                    Err(e) => {
+                        savepoint.rollback().await?;
+                        warn!("Bulk void failed for sale {}: {}", sale_id, e);
+                        ("failed", Some(e.to_string()))
+                    }
+                };
+                outcomes.push(BulkVoidOutcome {
+                    sale_id: *sale_id,
+                    invoice_no: invoice_no.clone(),
+                    status: status.to_string(),
+                    message,
+                });
+            }
+            tx.commit().await?;
+        }
+
+        let count = |status: &str| outcomes.iter().filter(|outcome| outcome.status == status).count();
+        info!("Bulk void by user {}: {} voided, {} skipped, {} failed", user_id, count("voided"), count("skipped"), count("failed"));
+        Ok(BulkVoidResult {
+            voided: count("voided"),
+            skipped: count("skipped"),
+            failed: count("failed"),
+            outcomes,
+        })
+    }
+
+    // Void a single sale, keeping the row (status 'cancelled') for history; see void_in_tx for what is undone
+    pub async fn void(&self, db: &Database, sale_id: i64, reason: &str, user_id: i64) -> Result<SaleWithDetails> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(anyhow::anyhow!("سبب الإلغاء مطلوب"));
+        }
+        let invoice_no: String = sqlx::query_scalar("SELECT invoice_no FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .fetch_optional(&db.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("الفاتورة غير موجودة"))?;
+
+        let mut tx = db.pool.begin().await?;
+        if let Some(refusal) = Self::void_in_tx(&mut tx, sale_id, &invoice_no, reason, user_id).await? {
+            return Err(anyhow::anyhow!(refusal));
+        }
+        tx.commit().await?;
+        info!("Sale {} voided by user {}: {}", invoice_no, user_id, reason);
+
+        self.get_by_id(db, sale_id).await?
+            .ok_or_else(|| anyhow::anyhow!("الفاتورة غير موجودة"))
+    }
+
+    // Void one sale inside the caller's transaction; Ok(Some(reason)) when the sale is left alone
+    async fn void_in_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        sale_id: i64,
+        invoice_no: &str,
+        reason: &str,
+        user_id: i64,
+    ) -> Result<Option<String>> {
+        let status: String = sqlx::query("SELECT status FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .fetch_one(&mut **tx)
+            .await?
+            .get("status");
+        if status == "cancelled" {
+            return Ok(Some("الفاتورة ملغاة مسبقاً".to_string()));
+        }
+        let has_returns: Option<i64> = sqlx::query_scalar("SELECT id FROM sale_returns WHERE sale_id = ? LIMIT 1")
+            .bind(sale_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+        if has_returns.is_some() || status == "returned" || status == "partially_returned" {
+            return Ok(Some("الفاتورة عليها مرتجعات".to_string()));
+        }
+
+        // Put back what the sale took off the shelf
+        Self::restore_sale_stock(tx, sale_id, Some(user_id), reason).await?;
+
+        // The customer no longer owes what was left open on the sale
+        sqlx::query(r#"
+            UPDATE customers
+            SET current_balance = COALESCE(current_balance, 0) - (
+                    SELECT COALESCE(SUM(amount), 0) FROM debts WHERE sale_id = ? AND status != 'paid'
+                ),
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = (SELECT customer_id FROM sales WHERE id = ?)
+        "#)
+        .bind(sale_id)
+        .bind(sale_id)
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query("DELETE FROM debts WHERE sale_id = ?")
+            .bind(sale_id)
+            .execute(&mut **tx)
+            .await?;
+
+        // Money that reached a money box for this sale: receipts against it and its share of multi-debt receipts
+        let deposits = sqlx::query(r#"
+            SELECT id as receipt_id, money_box_id, CAST(amount AS REAL) as amount, receipt_no, 1 as direct
+            FROM customer_receipts
+            WHERE sale_id = ? AND money_box_id IS NOT NULL
+            UNION ALL
+            SELECT cr.id as receipt_id, cr.money_box_id, CAST(a.amount AS REAL) as amount, cr.receipt_no, 0 as direct
+            FROM customer_receipt_allocations a
+            JOIN customer_receipts cr ON cr.id = a.receipt_id
+            WHERE a.sale_id = ? AND cr.money_box_id IS NOT NULL
+        "#)
+        .bind(sale_id)
+        .bind(sale_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        for deposit in deposits {
+            let box_id: i64 = deposit.get("money_box_id");
+            let amount: f64 = deposit.get("amount");
+            let receipt_no: String = deposit.get("receipt_no");
+            let balance: f64 = sqlx::query("SELECT CAST(amount AS REAL) as amount FROM money_boxes WHERE id = ?")
+                .bind(box_id)
+                .fetch_optional(&mut **tx)
+                .await?
+                .map(|row| row.get("amount"))
+                .ok_or_else(|| anyhow::anyhow!("صندوق المال غير موجود"))?;
+            if balance < amount {
+                return Err(anyhow::anyhow!("رصيد صندوق المال غير كافٍ لعكس الإيصال {}", receipt_no));
+            }
+
+            sqlx::query("UPDATE money_boxes SET amount = amount - ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(amount)
+                .bind(box_id)
+                .execute(&mut **tx)
+                .await?;
+            sqlx::query(r#"
+                INSERT INTO money_box_transactions (box_id, type, amount, balance_after, notes, created_by, created_at)
+                VALUES (?, 'withdraw', ?, ?, ?, ?, CURRENT_TIMESTAMP)
+            "#)
+            .bind(box_id)
+            .bind(amount)
+            .bind(balance - amount)
+            .bind(format!("عكس الإيصال {} لإلغاء الفاتورة {}", receipt_no, invoice_no))
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await?;
+
+            // A receipt against the sale itself stood as credit on the balance; refunded, it goes with the sale.
+            // Allocated shares already came off the debts that were just cleared.
+            if deposit.get::<i64, _>("direct") == 1 {
+                Self::shift_customer_balance(tx, sale_id, amount).await?;
+                sqlx::query("DELETE FROM customer_receipts WHERE id = ?")
+                    .bind(deposit.get::<i64, _>("receipt_id"))
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        }
+
+        sqlx::query("UPDATE sales SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(sale_id)
+            .execute(&mut **tx)
+            .await?;
+
+        AuditService::record(tx, Some(user_id), "sale_void", "sale", Some(sale_id), serde_json::json!({
+            "invoice_no": invoice_no,
+            "reason": reason,
+        })).await?;
+
+        Ok(None)
+    }
+
     // Process sale return
     pub async fn process_return(&self, db: &Database, id: i64, return_data: SaleReturnRequest) -> Result<SaleReturnResult> {
         let mut tx = db.pool.begin().await?;
@@ -858,6 +1603,10 @@ impl SaleService {
                     .bind(item.sale_item_id)
                     .execute(&mut *tx)
                     .await?;
+
+                    if let Some(product_id) = original_item.get::<Option<i64>, _>("product_id") {
+                        Self::move_sale_stock(&mut tx, id, product_id, -item.quantity, None, "مرتجع مبيعات").await?;
+                    }
                 }
 
                 // Check if all items are returned
@@ -904,7 +1653,8 @@ impl SaleService {
                 .execute(&mut *tx)
                 .await?;
 
-                // Update debt record
+                // Update debt record, moving the customer's balance by however much the open debt changes
+                let open_before = Self::open_debt(&mut tx, id).await?;
                 let existing_debt = sqlx::query("SELECT * FROM debts WHERE sale_id = ?")
                     .bind(id)
                     .fetch_optional(&mut *tx)
@@ -943,6 +1693,8 @@ impl SaleService {
                     .execute(&mut *tx)
                     .await?;
                 }
+                let open_after = Self::open_debt(&mut tx, id).await?;
+                Self::shift_customer_balance(&mut tx, id, open_after - open_before).await?;
 
         tx.commit().await?;
         let result = (return_id, new_status, new_total_amount, new_paid_amount, new_remaining_amount, new_payment_status);
@@ -1017,15 +1769,18 @@ impl SaleService {
     }
 
     // Helper method to map database row to SaleWithDetails
-    async fn map_sale_row_to_with_details(&self, row: sqlx::sqlite::SqliteRow) -> Result<SaleWithDetails> {
-        let items_json: String = row.get("items");
-        let items: Vec<SaleItemWithDetails> = if items_json == "[null]" {
-            Vec::new()
-        } else {
-            serde_json::from_str(&items_json).unwrap_or_else(|_| Vec::new())
-        };
-
-        Ok(SaleWithDetails {
+    fn map_sale_row_to_with_details(row: &sqlx::sqlite::SqliteRow) -> SaleWithDetails {
+        // The FILTER clause keeps sales without items at an empty array instead of [null]
+        let items_json: Option<String> = row.get("items");
+        let items: Vec<SaleItemWithDetails> = items_json
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .map_err(|e| warn!("Failed to parse items for sale {}: {}", row.get::<i64, _>("id"), e))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        SaleWithDetails {
             id: row.get("id"),
             customer_id: row.get("customer_id"),
             customer_name: row.get("customer_name"),
@@ -1052,6 +1807,554 @@ impl SaleService {
             updated_at: row.get("updated_at"),
             total_items: items.len() as i64,
             items,
-        })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use serde_json::json;
+    use sqlx::ConnectOptions;
+
+    fn cash_sale(extra: Value) -> CreateSaleRequest {
+        let mut body = json!({
+            "customer_id": 999,
+            "invoice_date": "2026-04-18",
+            "payment_method": "cash",
+            "paid_amount": 1000.0,
+            "items": [{ "name": "شحن رصيد", "quantity": 1, "price": 1000.0 }]
+        });
+        body.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn concurrent_sales_each_get_their_own_valid_barcode() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+
+        let sales = futures::future::join_all((0..4).map(|_| service.create(&db, cash_sale(json!({}))))).await;
+        let mut barcodes: Vec<String> = sales.into_iter()
+            .map(|sale| {
+                let sale = sale.unwrap();
+                let barcode = sale.barcode.clone().unwrap();
+                assert_eq!(barcode, SaleService::sale_barcode(sale.id));
+                barcode
+            })
+            .collect();
+        for barcode in &barcodes {
+            assert_eq!(barcode.len(), 13);
+            assert_eq!(barcode[12..].parse::<u32>().unwrap(), BarcodeService::check_digit(&barcode[..12]));
+        }
+        barcodes.sort();
+        barcodes.dedup();
+        assert_eq!(barcodes.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn supplied_barcodes_are_kept_and_generation_can_be_turned_off() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+
+        let sale = service.create(&db, cash_sale(json!({ "barcode": "INV-A-77" }))).await.unwrap();
+        assert_eq!(sale.barcode.as_deref(), Some("INV-A-77"));
+        assert!(service.create(&db, cash_sale(json!({ "barcode": "INV-A-77" }))).await.is_err());
+
+        sqlx::query("UPDATE settings SET auto_generate_sale_barcode = 0 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let sale = service.create(&db, cash_sale(json!({}))).await.unwrap();
+        assert_eq!(sale.barcode, None);
+    }
+
+    async fn staff(db: &Database, username: &str, permissions: &[&str]) -> i64 {
+        let user_id = sqlx::query("INSERT INTO users (username, password, name, role) VALUES (?, 'not-a-hash', ?, 'user')")
+            .bind(username)
+            .bind(username)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        for permission in permissions {
+            sqlx::query("INSERT INTO user_permissions (user_id, permission_id) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(permission)
+                .execute(&db.pool).await.unwrap();
+        }
+        user_id
+    }
+
+    // 1000 of goods with an invoice-level discount, rung up by `requested_by`
+    fn discounted_sale(discount_amount: f64, requested_by: i64) -> CreateSaleRequest {
+        let mut request = cash_sale(json!({
+            "paid_amount": 0.0,
+            "payment_status": "unpaid",
+            "due_date": "2026-05-18",
+            "discount_amount": discount_amount,
+            "items": [{ "name": "طقم قدور", "quantity": 4, "price": 250.0 }]
+        }));
+        request.requested_by = Some(requested_by);
+        request
+    }
+
+    async fn discount_overrides(db: &Database, sale_id: i64) -> Vec<(i64, Value)> {
+        sqlx::query_as::<_, (i64, String)>("SELECT user_id, details FROM audit_logs WHERE action = 'discount_override' AND entity_id = ?")
+            .bind(sale_id)
+            .fetch_all(&db.pool).await.unwrap()
+            .into_iter()
+            .map(|(user_id, details)| (user_id, serde_json::from_str(&details).unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn invoice_discount_over_threshold_needs_the_override_permission() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+        sqlx::query("UPDATE settings SET invoice_discount_threshold = 10 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let cashier = staff(&db, "cashier.m", &[]).await;
+        let supervisor = staff(&db, "supervisor.r", &["sales.discount_override"]).await;
+
+        // Within the threshold anyone may discount, and nothing is audited
+        let small = service.create(&db, discounted_sale(5.0, cashier)).await.unwrap();
+        assert!(discount_overrides(&db, small.id).await.is_empty());
+
+        let err = service.create(&db, discounted_sale(10.0, cashier)).await.unwrap_err();
+        assert!(err.to_string().contains("sales.discount_override"), "{err}");
+        let sales: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(sales, 1);
+
+        let approved = service.create(&db, discounted_sale(10.0, supervisor)).await.unwrap();
+        let audit = discount_overrides(&db, approved.id).await;
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].0, supervisor);
+        assert_eq!(audit[0].1["discount_amount"], approved.discount_amount);
+        assert_eq!(audit[0].1["threshold_percent"], 10.0);
+    }
+
+    #[tokio::test]
+    async fn editing_items_rechecks_the_invoice_discount() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+        sqlx::query("UPDATE settings SET invoice_discount_threshold = 10 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let cashier = staff(&db, "cashier.s", &[]).await;
+        let supervisor = staff(&db, "supervisor.k", &["sales.discount_override"]).await;
+        let sale = service.create(&db, discounted_sale(5.0, cashier)).await.unwrap();
+
+        let edit = |requested_by| {
+            let mut update: UpdateSaleRequest = serde_json::from_value(json!({
+                "discount_amount": 12.0,
+                "items": [{ "name": "طقم قدور", "quantity": 4, "price": 250.0 }]
+            })).unwrap();
+            update.requested_by = Some(requested_by);
+            update
+        };
+        assert!(service.update(&db, sale.id, edit(cashier)).await.is_err());
+        assert!(discount_overrides(&db, sale.id).await.is_empty());
+
+        service.update(&db, sale.id, edit(supervisor)).await.unwrap();
+        let audit = discount_overrides(&db, sale.id).await;
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].0, supervisor);
+    }
+
+    #[tokio::test]
+    async fn no_threshold_means_no_discount_approval() {
+        let db = TestDatabase::new().await;
+        let cashier = staff(&db, "cashier.z", &[]).await;
+        let sale = SaleService::new().create(&db, discounted_sale(40.0, cashier)).await.unwrap();
+        assert!(discount_overrides(&db, sale.id).await.is_empty());
+    }
+
+    // A paid line plus a promotional line at `promo_price`
+    fn promo_sale(promo_price: f64, requested_by: i64) -> CreateSaleRequest {
+        let mut request = cash_sale(json!({
+            "paid_amount": 1000.0,
+            "items": [
+                { "name": "مكنسة كهربائية", "quantity": 1, "price": 1000.0 },
+                { "name": "كيس هدية", "quantity": 1, "price": promo_price }
+            ]
+        }));
+        request.requested_by = Some(requested_by);
+        request
+    }
+
+    #[tokio::test]
+    async fn free_items_need_the_setting_and_the_permission() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+        let cashier = staff(&db, "cashier.h", &[]).await;
+        let manager = staff(&db, "manager.a", &["sales.zero_price"]).await;
+
+        // Off by default, whoever asks
+        assert!(service.create(&db, promo_sale(0.0, manager)).await.is_err());
+
+        sqlx::query("UPDATE settings SET allow_zero_price_items = 1 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let err = service.create(&db, promo_sale(0.0, cashier)).await.unwrap_err();
+        assert!(err.to_string().contains("sales.zero_price"), "{err}");
+
+        let sale = service.create(&db, promo_sale(0.0, manager)).await.unwrap();
+        assert_eq!(sale.items.len(), 2);
+        assert_eq!(sale.items[1].price, 0.0);
+        assert_eq!(sale.total_amount, 1000.0);
+    }
+
+    #[tokio::test]
+    async fn negative_prices_are_rejected_even_when_free_items_are_allowed() {
+        let db = TestDatabase::new().await;
+        let manager = staff(&db, "manager.b", &["sales.zero_price"]).await;
+        sqlx::query("UPDATE settings SET allow_zero_price_items = 1 WHERE id = 1").execute(&db.pool).await.unwrap();
+
+        assert!(SaleService::new().create(&db, promo_sale(-50.0, manager)).await.is_err());
+        let sales: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(sales, 0);
+    }
+
+    #[tokio::test]
+    async fn cart_check_reports_a_deleted_product_and_a_stale_price() {
+        let db = TestDatabase::new().await;
+        let mut ids = Vec::new();
+        for sku in ["CHRG-1", "CBL-1"] {
+            let id = sqlx::query(
+                "INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, current_stock) VALUES (?, ?, 3000, 4000, 3500, 10)"
+            )
+            .bind(format!("منتج {}", sku))
+            .bind(sku)
+            .execute(&db.pool)
+            .await
+            .unwrap()
+            .last_insert_rowid();
+            ids.push(id);
+        }
+        let cart = cash_sale(json!({
+            "items": [
+                { "product_id": ids[0], "quantity": 1, "price": 4000.0 },
+                { "product_id": ids[1], "quantity": 2, "price": 4000.0 }
+            ]
+        }));
+
+        // The cart was built before the first product was removed and the second repriced
+        sqlx::query("DELETE FROM products WHERE id = ?").bind(ids[0]).execute(&db.pool).await.unwrap();
+        sqlx::query("UPDATE products SET selling_price = 4500 WHERE id = ?").bind(ids[1]).execute(&db.pool).await.unwrap();
+
+        let result = SaleService::new().validate_cart(&db, cart).await.unwrap();
+        assert!(!result.valid);
+        let found: Vec<(usize, &str)> = result.issues.iter().map(|issue| (issue.line, issue.issue.as_str())).collect();
+        assert_eq!(found, vec![(0, "product_not_found"), (1, "price_changed")]);
+        assert_eq!(result.issues[1].requested_price, Some(4000.0));
+        assert_eq!(result.issues[1].current_price, Some(4500.0));
+
+        // Nothing was written by the dry run
+        let sales: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(sales, 0);
+    }
+
+    async fn stock_and_movements(db: &Database, product_id: i64) -> (i64, Vec<(String, i64)>) {
+        let stock: i64 = sqlx::query_scalar("SELECT current_stock FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_one(&db.pool).await.unwrap();
+        let movements: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT movement_type, quantity FROM inventory_movements WHERE product_id = ? AND reference_type = 'sale' ORDER BY id"
+        )
+        .bind(product_id)
+        .fetch_all(&db.pool).await.unwrap();
+        (stock, movements)
+    }
+
+    #[tokio::test]
+    async fn sale_lines_move_stock_once_and_log_every_move() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+        let product_id = sqlx::query(
+            "INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, current_stock) VALUES ('سماعة', 'HP-9', 6000, 9000, 8000, 10)"
+        )
+        .execute(&db.pool).await.unwrap()
+        .last_insert_rowid();
+        let line = |quantity: i64| json!([{ "product_id": product_id, "quantity": quantity, "price": 9000.0 }]);
+
+        let sale = service.create(&db, cash_sale(json!({ "items": line(3), "paid_amount": 27000.0 }))).await.unwrap();
+        assert_eq!(stock_and_movements(&db, product_id).await, (7, vec![("out".to_string(), 3)]));
+
+        // Editing the quantity puts the old line back before taking the new one
+        let edit: UpdateSaleRequest = serde_json::from_value(json!({ "items": line(5) })).unwrap();
+        service.update(&db, sale.id, edit).await.unwrap();
+        let (stock, movements) = stock_and_movements(&db, product_id).await;
+        assert_eq!(stock, 5);
+        assert_eq!(movements[1..], [("in".to_string(), 3), ("out".to_string(), 5)]);
+
+        assert!(service.delete(&db, sale.id).await.unwrap());
+        let (stock, movements) = stock_and_movements(&db, product_id).await;
+        assert_eq!(stock, 10);
+        assert_eq!(movements.len(), 4);
+        let total_sold: i64 = sqlx::query_scalar("SELECT total_sold FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(total_sold, 0);
+    }
+
+    #[tokio::test]
+    async fn an_oversized_cart_fails_before_touching_the_database() {
+        let db = TestDatabase::new().await;
+        let lines = SALE_ITEMS_LIMIT.max() + 1;
+        let cart = || cash_sale(json!({ "items": vec![json!({ "name": "علكة", "quantity": 1, "price": 250.0 }); lines] }));
+
+        // With the pool closed any query would fail with a database error instead
+        db.pool.close().await;
+        let service = SaleService::new();
+        for err in [service.create(&db, cart()).await.unwrap_err(), service.validate_cart(&db, cart()).await.unwrap_err()] {
+            let exceeded = err.downcast_ref::<crate::utils::ItemLimitExceeded>().expect("limit error, not a database one");
+            assert_eq!((exceeded.count, exceeded.limit), (lines, lines - 1));
+        }
+    }
+
+    #[tokio::test]
+    async fn sale_by_id_reads_its_items_through_the_sale_id_index() {
+        let db = TestDatabase::new().await;
+        let plan = sqlx::query(&format!("EXPLAIN QUERY PLAN {}", SaleService::sales_with_details_query("WHERE s.id = ?")))
+            .bind("1")
+            .bind(1_i64)
+            .bind(0_i64)
+            .fetch_all(&db.pool).await.unwrap();
+        let steps: Vec<String> = plan.iter().map(|row| row.get("detail")).collect();
+
+        assert!(
+            steps.iter().any(|step| step.starts_with("SEARCH si USING") && step.contains("INDEX idx_sale_items_sale_id")),
+            "{steps:#?}"
+        );
+        assert!(!steps.iter().any(|step| step.starts_with("SCAN si")), "{steps:#?}");
+    }
+
+    #[tokio::test]
+    async fn a_page_of_sales_costs_the_same_statements_for_one_sale_or_fifty() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+        // A one-connection pool on the same file whose statements are logged from a thread of its own
+        let counted = Database {
+            pool: sqlx::sqlite::SqlitePoolOptions::new()
+                .max_connections(1)
+                .connect_with(
+                    sqlx::sqlite::SqliteConnectOptions::new()
+                        .filename(db.file())
+                        .log_statements(log::LevelFilter::Info)
+                        .thread_name(|_| "sales-page-count".to_string()),
+                )
+                .await.unwrap(),
+        };
+        let query: SaleQuery = serde_json::from_value(json!({ "limit": 50 })).unwrap();
+        let statements_for_a_page = || async {
+            let on_this_pool = || crate::test_support::sql_events().iter().filter(|event| event.thread == "sales-page-count").count();
+            let before = on_this_pool();
+            let page = service.get_all(&counted, &query).await.unwrap();
+            (page.items.len(), on_this_pool() - before)
+        };
+
+        service.create(&db, cash_sale(json!({}))).await.unwrap();
+        let (sales, one) = statements_for_a_page().await;
+        assert_eq!(sales, 1);
+
+        for _ in 0..49 {
+            service.create(&db, cash_sale(json!({}))).await.unwrap();
+        }
+        let (sales, fifty) = statements_for_a_page().await;
+        assert_eq!(sales, 50);
+        assert!(one > 0);
+        assert_eq!(fifty, one);
+    }
+
+    #[tokio::test]
+    async fn export_stream_sends_every_filtered_sale_in_chunks() {
+        let db = TestDatabase::new().await;
+        sqlx::query(r#"
+            WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1205)
+            INSERT INTO sales (customer_id, invoice_no, invoice_date, total_amount, net_amount, paid_amount, payment_method, payment_status, status)
+            SELECT 999, 'EXP-' || i, CASE WHEN i <= 2 THEN '2025-12-31' ELSE '2026-03-01' END, 1000, 1000, 1000, 'cash', 'paid', 'completed' FROM n
+        "#)
+        .execute(&db.pool).await.unwrap();
+        let query: SaleQuery = serde_json::from_value(json!({ "start_date": "2026-01-01", "limit": 10 })).unwrap();
+
+        let chunks: Vec<Bytes> = SaleService::new()
+            .export_csv_stream(&db, &query, CsvFormat { bom: false, ..CsvFormat::default() })
+            .map(|chunk| chunk.unwrap())
+            .collect().await;
+        assert_eq!(chunks.len(), 1203usize.div_ceil(EXPORT_CHUNK_ROWS));
+
+        let csv: Vec<u8> = chunks.concat();
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+        let invoices: Vec<String> = reader.records().map(|record| record.unwrap()[1].to_string()).collect();
+        assert_eq!(invoices.len(), 1203);
+        assert!(!invoices.iter().any(|invoice| invoice == "EXP-1" || invoice == "EXP-2"));
+
+        // A database that fails under the export ends the stream with an error instead of a short file
+        db.pool.close().await;
+        let items: Vec<std::io::Result<Bytes>> = SaleService::new().export_csv_stream(&db, &query, CsvFormat::default()).collect().await;
+        assert!(items.last().unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn voiding_a_credit_sale_puts_stock_back_and_cancels_its_debt() {
+        let db = TestDatabase::new().await;
+        let pool = &db.pool;
+        let service = SaleService::new();
+        let product_id = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock) VALUES ('Rice 5kg', 'RICE-5KG', 8000, 10000, 9000, 1, 12)")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone) VALUES ('Noor Hadi', '07714445555')")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let credit_sale = |quantity: i64| cash_sale(json!({
+            "customer_id": customer_id,
+            "payment_status": "partial",
+            "paid_amount": 5000.0,
+            "due_date": "2026-05-18",
+            "items": [{ "product_id": product_id, "quantity": quantity, "price": 10000.0 }]
+        }));
+        let stock = || sqlx::query_scalar::<_, i64>("SELECT current_stock FROM products WHERE id = ?").bind(product_id).fetch_one(pool);
+        let balance = || sqlx::query_scalar::<_, f64>("SELECT CAST(current_balance AS REAL) FROM customers WHERE id = ?").bind(customer_id).fetch_one(pool);
+
+        let sale = service.create(&db, credit_sale(3)).await.unwrap();
+        assert_eq!(stock().await.unwrap(), 9);
+        assert_eq!(balance().await.unwrap(), 25000.0);
+
+        let voided = service.void(&db, sale.id, "خطأ في الإدخال", 1).await.unwrap();
+        assert_eq!(voided.status, "cancelled");
+        assert_eq!(stock().await.unwrap(), 12);
+        assert_eq!(balance().await.unwrap(), 0.0);
+        let debts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM debts WHERE sale_id = ?").bind(sale.id).fetch_one(pool).await.unwrap();
+        assert_eq!(debts, 0);
+
+        // Kept for history, and only voided once
+        let kept: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales WHERE id = ?").bind(sale.id).fetch_one(pool).await.unwrap();
+        assert_eq!(kept, 1);
+        assert!(service.void(&db, sale.id, "مرة ثانية", 1).await.is_err());
+        assert_eq!(stock().await.unwrap(), 12);
+
+        // A sale with returns against it is settled through the return instead
+        let returned = service.create(&db, credit_sale(1)).await.unwrap();
+        sqlx::query("INSERT INTO sale_returns (sale_id, reason, total_amount) VALUES (?, 'تالف', 10000)")
+            .bind(returned.id).execute(pool).await.unwrap();
+        let err = service.void(&db, returned.id, "إلغاء", 1).await.unwrap_err();
+        assert_eq!(err.to_string(), "الفاتورة عليها مرتجعات");
+    }
+
+    #[tokio::test]
+    async fn returned_units_go_back_once_and_delete_only_restores_the_rest() {
+        let db = TestDatabase::new().await;
+        let service = SaleService::new();
+        let product_id = sqlx::query(
+            "INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, current_stock) VALUES ('شاحن', 'CH-4', 4000, 6000, 5000, 10)"
+        )
+        .execute(&db.pool).await.unwrap()
+        .last_insert_rowid();
+
+        let items = json!([{ "product_id": product_id, "quantity": 4, "price": 6000.0 }]);
+        let sale = service.create(&db, cash_sale(json!({ "items": items, "paid_amount": 24000.0 }))).await.unwrap();
+        assert_eq!(stock_and_movements(&db, product_id).await.0, 6);
+
+        let sale_item_id: i64 = sqlx::query_scalar("SELECT id FROM sale_items WHERE sale_id = ?")
+            .bind(sale.id)
+            .fetch_one(&db.pool).await.unwrap();
+        let return_data: SaleReturnRequest = serde_json::from_value(json!({
+            "items": [{ "sale_item_id": sale_item_id, "quantity": 1, "price": 6000.0, "total": 6000.0 }],
+            "reason": "تالف",
+            "refund_method": "cash"
+        }))
+        .unwrap();
+        service.process_return(&db, sale.id, return_data).await.unwrap();
+        let (stock, movements) = stock_and_movements(&db, product_id).await;
+        assert_eq!(stock, 7);
+        assert_eq!(movements[1..], [("in".to_string(), 1)]);
+
+        assert!(service.delete(&db, sale.id).await.unwrap());
+        let (stock, movements) = stock_and_movements(&db, product_id).await;
+        assert_eq!(stock, 10);
+        assert_eq!(movements[2..], [("in".to_string(), 3)]);
+    }
+
+    #[tokio::test]
+    async fn returns_edits_and_deletes_keep_the_customer_balance_in_step() {
+        let db = TestDatabase::new().await;
+        let pool = &db.pool;
+        let service = SaleService::new();
+        let product_id = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, current_stock) VALUES ('Sugar 1kg', 'SUG-1', 800, 1000, 900, 20)")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone) VALUES ('Hussein Ali', '07716667777')")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let line = |quantity: i64| json!([{ "product_id": product_id, "quantity": quantity, "price": 10000.0 }]);
+        let credit_sale = |quantity: i64| cash_sale(json!({
+            "customer_id": customer_id,
+            "payment_status": "partial",
+            "paid_amount": 5000.0,
+            "due_date": "2026-05-18",
+            "items": line(quantity)
+        }));
+        let balance = || sqlx::query_scalar::<_, f64>("SELECT CAST(current_balance AS REAL) FROM customers WHERE id = ?").bind(customer_id).fetch_one(pool);
+        let debt = |sale_id: i64| sqlx::query_scalar::<_, f64>("SELECT CAST(COALESCE(SUM(amount), 0) AS REAL) FROM debts WHERE sale_id = ?").bind(sale_id).fetch_one(pool);
+
+        let sale = service.create(&db, credit_sale(3)).await.unwrap();
+        assert_eq!(balance().await.unwrap(), 25000.0);
+
+        // Returning one of three leaves 20000 on the invoice, 15000 of it unpaid
+        let sale_item_id: i64 = sqlx::query_scalar("SELECT id FROM sale_items WHERE sale_id = ?").bind(sale.id).fetch_one(pool).await.unwrap();
+        let return_data: SaleReturnRequest = serde_json::from_value(json!({
+            "items": [{ "sale_item_id": sale_item_id, "quantity": 1, "price": 10000.0, "total": 10000.0 }],
+            "reason": "تالف",
+            "refund_method": "cash"
+        }))
+        .unwrap();
+        service.process_return(&db, sale.id, return_data).await.unwrap();
+        assert_eq!(debt(sale.id).await.unwrap(), 15000.0);
+        assert_eq!(balance().await.unwrap(), 15000.0);
+
+        assert!(service.delete(&db, sale.id).await.unwrap());
+        assert_eq!(balance().await.unwrap(), 0.0);
+
+        // Raising the quantity raises the open debt and the balance with it
+        let sale = service.create(&db, credit_sale(2)).await.unwrap();
+        assert_eq!(balance().await.unwrap(), 15000.0);
+        let edit: UpdateSaleRequest = serde_json::from_value(json!({ "items": line(3) })).unwrap();
+        service.update(&db, sale.id, edit).await.unwrap();
+        assert_eq!(debt(sale.id).await.unwrap(), 25000.0);
+        assert_eq!(balance().await.unwrap(), 25000.0);
+
+        let recomputed = crate::services::CustomerService::new().recompute_balance(&db, customer_id).await.unwrap();
+        assert_eq!(recomputed.current_balance, 25000.0);
+    }
+
+    #[tokio::test]
+    async fn voiding_a_sale_refunds_its_receipt_and_drops_the_credit_it_gave() {
+        use crate::services::customer_receipts_service::{CreateCustomerReceiptRequest, CustomerReceiptsService};
+
+        let db = TestDatabase::new().await;
+        let pool = &db.pool;
+        let service = SaleService::new();
+        let customer_id = sqlx::query("INSERT INTO customers (name, phone) VALUES ('Layla Kadhim', '07718889999')")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let balance = || sqlx::query_scalar::<_, f64>("SELECT CAST(current_balance AS REAL) FROM customers WHERE id = ?").bind(customer_id).fetch_one(pool);
+        let box_amount = || sqlx::query_scalar::<_, f64>("SELECT CAST(amount AS REAL) FROM money_boxes WHERE id = 1").fetch_one(pool);
+        let box_before = box_amount().await.unwrap();
+
+        let sale = service.create(&db, cash_sale(json!({
+            "customer_id": customer_id,
+            "payment_status": "unpaid",
+            "paid_amount": 0.0,
+            "due_date": "2026-05-18",
+            "items": [{ "name": "تصليح شاشة", "quantity": 1, "price": 100.0 }]
+        }))).await.unwrap();
+        CustomerReceiptsService::new().create_receipt(&db, CreateCustomerReceiptRequest {
+            customer_id,
+            sale_id: Some(sale.id),
+            receipt_date: None,
+            amount: 40.0,
+            payment_method: "cash".to_string(),
+            reference_number: None,
+            notes: None,
+            money_box_id: Some(1),
+        }, 1).await.unwrap();
+        assert_eq!(balance().await.unwrap(), 60.0);
+        assert_eq!(box_amount().await.unwrap(), box_before + 40.0);
+
+        service.void(&db, sale.id, "خطأ في الإدخال", 1).await.unwrap();
+        assert_eq!(balance().await.unwrap(), 0.0);
+        assert_eq!(box_amount().await.unwrap(), box_before);
+        let receipts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM customer_receipts WHERE customer_id = ?").bind(customer_id).fetch_one(pool).await.unwrap();
+        assert_eq!(receipts, 0);
+
+        let recomputed = crate::services::CustomerService::new().recompute_balance(&db, customer_id).await.unwrap();
+        assert_eq!(recomputed.current_balance, 0.0);
     }
 }
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use sqlx::{Row, SqliteConnection};
+use tracing::info;
+use crate::database::Database;
+use crate::models::sequence::*;
+
+#[derive(Clone)]
+pub struct SequenceService;
+
+impl SequenceService {
+    pub const CUSTOMER_RECEIPT: &'static str = "customer_receipt";
+    pub const SUPPLIER_PAYMENT_RECEIPT: &'static str = "supplier_payment_receipt";
+
+    pub fn new() -> Self {
+        Self
+    }
+
+    // Claim the next number inside the caller's transaction; a rollback hands it back, keeping the series gap-free
+    pub async fn next(conn: &mut SqliteConnection, name: &str) -> Result<String> {
+        let row = sqlx::query(r#"
+            UPDATE number_sequences SET next_value = next_value + 1, updated_at = CURRENT_TIMESTAMP
+            WHERE name = ?
+            RETURNING prefix, padding, next_value - 1 as value
+        "#)
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Number sequence {} not found", name))?;
+
+        let prefix: String = row.get("prefix");
+        let padding: i64 = row.get("padding");
+        let value: i64 = row.get("value");
+        Ok(format!("{}{:0width$}", prefix, value, width = padding.max(1) as usize))
+    }
+
+    pub async fn get_all(&self, db: &Database) -> Result<Vec<NumberSequence>> {
+        let sequences = sqlx::query_as::<_, NumberSequence>("SELECT * FROM number_sequences ORDER BY name")
+            .fetch_all(&db.pool)
+            .await?;
+
+        Ok(sequences)
+    }
+
+    pub async fn update(&self, db: &Database, name: &str, payload: UpdateNumberSequenceRequest) -> Result<NumberSequence> {
+        let existing = sqlx::query_as::<_, NumberSequence>("SELECT * FROM number_sequences WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&db.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("تسلسل الترقيم غير موجود"))?;
+
+        let prefix = payload.prefix.map(|p| p.trim().to_string()).unwrap_or(existing.prefix);
+        let padding = payload.padding.unwrap_or(existing.padding);
+        if !(1..=12).contains(&padding) {
+            return Err(anyhow::anyhow!("عدد خانات الترقيم يجب أن يكون بين 1 و 12"));
+        }
+        // Moving the counter back would reissue numbers already printed
+        let next_value = payload.next_value.unwrap_or(existing.next_value);
+        if next_value < existing.next_value {
+            return Err(anyhow::anyhow!("لا يمكن إرجاع الترقيم إلى قيمة أقل من {}", existing.next_value));
+        }
+
+        sqlx::query(r#"
+            UPDATE number_sequences SET prefix = ?, padding = ?, next_value = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE name = ? AND next_value <= ?
+        "#)
+        .bind(&prefix)
+        .bind(padding)
+        .bind(next_value)
+        .bind(name)
+        .bind(next_value)
+        .execute(&db.pool)
+        .await?;
+
+        info!("Number sequence {} set to prefix {} padding {} next {}", name, prefix, padding, next_value);
+
+        sqlx::query_as::<_, NumberSequence>("SELECT * FROM number_sequences WHERE name = ?")
+            .bind(name)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(Into::into)
+    }
+}
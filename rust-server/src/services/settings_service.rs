@@ -1,7 +1,8 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
 use sqlx::Row;
+use sha2::{Digest, Sha256};
 
 use crate::database::Database;
 
@@ -43,11 +44,24 @@ pub struct SettingsResponse {
     pub allow_negative_stock: bool,
     pub require_customer_for_sales: bool,
     pub auto_generate_barcode: bool,
+    pub auto_generate_sale_barcode: bool,
     pub default_payment_method: String,
     pub tax_rate: f64,
     pub enable_loyalty_program: bool,
     pub loyalty_points_rate: f64,
     pub minimum_order_amount: f64,
+    pub default_import_category: Option<String>, // category name assigned to imported products
+    pub stock_hold_minutes: i32, // how long a draft sale reserves stock
+    pub report_snapshot_frequency: String, // off, monthly or daily (daily also keeps monthly)
+    pub strict_units: bool, // product units must exist in the units catalog
+    pub invoice_discount_threshold: f64, // invoice discount percent needing sales.discount_override; 0 disables
+    pub allow_zero_price_items: bool, // free lines, still gated by sales.zero_price
+
+    // CSV Export Settings (dates follow date_format)
+    pub csv_delimiter: String,
+    pub csv_decimal_separator: String,
+    pub csv_decimals: i32,
+    pub csv_include_bom: bool,
     
     // Security Settings
     pub session_timeout: i32,
@@ -167,11 +181,22 @@ impl Default for SettingsResponse {
             allow_negative_stock: false,
             require_customer_for_sales: true,
             auto_generate_barcode: true,
+            auto_generate_sale_barcode: true,
             default_payment_method: "cash".to_string(),
             tax_rate: 0.00,
             enable_loyalty_program: false,
             loyalty_points_rate: 1.00,
             minimum_order_amount: 0.0,
+            default_import_category: None,
+            stock_hold_minutes: 30,
+            report_snapshot_frequency: "monthly".to_string(),
+            strict_units: false,
+            invoice_discount_threshold: 0.0,
+            allow_zero_price_items: false,
+            csv_delimiter: ",".to_string(),
+            csv_decimal_separator: ".".to_string(),
+            csv_decimals: 2,
+            csv_include_bom: true,
             
             // Security Settings
             session_timeout: 30,
@@ -251,6 +276,54 @@ impl Default for SettingsResponse {
     }
 }
 
+// Upper bound for settings.exchange_rate; anything above is treated as a typo
+pub const MAX_EXCHANGE_RATE: f64 = 100_000.0;
+
+// Columns of the settings row that may be changed through update_partial
+pub const UPDATABLE_SETTINGS_COLUMNS: &[&str] = &[
+    "company_name", "logo_url", "mobile", "email", "address", "website", "tax_number",
+    "registration_number", "description", "currency", "language", "timezone", "date_format",
+    "number_format", "rtl_mode", "theme", "primary_color", "secondary_color", "dashboard_layout",
+    "dashboard_tile_size", "sidebar_collapsed", "enable_animations", "compact_mode",
+    "rtl_direction", "allow_negative_stock", "require_customer_for_sales", "auto_generate_barcode",
+    "default_payment_method", "tax_rate", "enable_loyalty_program", "loyalty_points_rate",
+    "minimum_order_amount", "session_timeout", "password_min_length", "require_strong_password",
+    "enable_two_factor", "allow_multiple_sessions", "login_attempts", "lockout_duration",
+    "email_notifications_enabled", "email_low_stock_notifications",
+    "email_new_order_notifications", "sms_notifications_enabled", "push_notifications_enabled",
+    "bill_template", "bill_show_logo", "bill_show_barcode", "bill_show_company_info",
+    "bill_show_qr_code", "bill_footer_text", "bill_paper_size", "bill_orientation",
+    "bill_margin_top", "bill_margin_right", "bill_margin_bottom", "bill_margin_left",
+    "bill_font_header", "bill_font_body", "bill_font_footer", "bill_color_primary",
+    "bill_color_secondary", "bill_color_text", "bill_print_mode", "email_provider", "email_host",
+    "email_port", "email_username", "email_password", "email_encryption", "email_from_name",
+    "email_from_email", "pos_barcode_scanner_enabled", "accounting_integration_enabled",
+    "analytics_integration_enabled", "auto_backup_enabled", "backup_frequency",
+    "backup_retention_days", "last_backup_date", "backup_time", "sidebar_menu_items",
+    "exchange_rate", "default_import_category", "auto_generate_sale_barcode",
+    "stock_hold_minutes", "report_snapshot_frequency", "strict_units", "invoice_discount_threshold",
+    "allow_zero_price_items", "csv_delimiter", "csv_decimal_separator", "csv_decimals", "csv_include_bom",
+];
+
+// Settings naming the business itself; an import keeps the local values unless asked to overwrite
+pub const IDENTITY_SETTINGS_COLUMNS: &[&str] = &["company_name", "tax_number", "logo_url"];
+
+// Per-install values that never travel in an export (the SMTP password stays on its machine)
+const LOCAL_SETTINGS_COLUMNS: &[&str] = &["last_backup_date", "email_password"];
+
+// Settings the UI offers as a fixed choice; imports are checked against these before anything is written
+const SETTINGS_CHOICES: &[(&str, &[&str])] = &[
+    ("default_payment_method", crate::services::validation_service::PAYMENT_METHODS),
+    ("report_snapshot_frequency", crate::services::ReportsService::SNAPSHOT_FREQUENCIES),
+    ("backup_frequency", &["daily", "weekly", "monthly"]),
+    ("bill_print_mode", &["a4", "thermal"]),
+    ("bill_orientation", &["portrait", "landscape"]),
+    ("dashboard_tile_size", &["small", "medium", "large"]),
+];
+
+// Bumped when the export layout changes in a way older servers cannot import
+pub const SETTINGS_EXPORT_VERSION: i64 = 1;
+
 #[derive(Clone)]
 pub struct SettingsService;
 
@@ -259,6 +332,22 @@ impl SettingsService {
         Self
     }
 
+    // Exchange rate must be positive and plausible; stored with 4 decimals like the DECIMAL(10,4) column
+    pub fn validate_exchange_rate(rate: f64) -> Result<f64> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(anyhow::anyhow!("سعر الصرف يجب أن يكون أكبر من صفر"));
+        }
+        if rate > MAX_EXCHANGE_RATE {
+            warn!("Rejected exchange rate {} (above {}), likely a typo", rate, MAX_EXCHANGE_RATE);
+            return Err(anyhow::anyhow!(
+                "سعر الصرف {} غير منطقي (الحد الأقصى {})، يرجى التحقق من القيمة",
+                rate, MAX_EXCHANGE_RATE
+            ));
+        }
+
+        Ok((rate * 10_000.0).round() / 10_000.0)
+    }
+
     pub async fn get_all_settings(&self, db: &Database) -> Result<SettingsResponse> {
         // Get settings from the single row (id=1) approach like Node.js
         let settings_row = sqlx::query(
@@ -288,7 +377,10 @@ impl SettingsService {
                 number_format: settings.get::<Option<String>, _>("number_format").unwrap_or_else(|| "ar-IQ".to_string()),
                 rtl_mode: settings.get::<Option<i32>, _>("rtl_mode").unwrap_or(1) == 1,
                 rtl_direction: settings.get::<Option<i32>, _>("rtl_direction").unwrap_or(1) == 1,
-                exchange_rate: settings.get::<Option<i32>, _>("exchange_rate").unwrap_or(1) as f64,
+                // DECIMAL affinity stores whole rates (e.g. 1500) as INTEGER
+                exchange_rate: settings.try_get::<Option<f64>, _>("exchange_rate").ok().flatten()
+                    .or_else(|| settings.try_get::<Option<i64>, _>("exchange_rate").ok().flatten().map(|rate| rate as f64))
+                    .unwrap_or(1.0),
                 
                 // UI and Theme Settings
                 theme: settings.get::<Option<String>, _>("theme").unwrap_or_else(|| "default".to_string()),
@@ -305,11 +397,25 @@ impl SettingsService {
                 allow_negative_stock: settings.get::<Option<i32>, _>("allow_negative_stock").unwrap_or(0) == 1,
                 require_customer_for_sales: settings.get::<Option<i32>, _>("require_customer_for_sales").unwrap_or(1) == 1,
                 auto_generate_barcode: settings.get::<Option<i32>, _>("auto_generate_barcode").unwrap_or(1) == 1,
+                auto_generate_sale_barcode: settings.get::<Option<i32>, _>("auto_generate_sale_barcode").unwrap_or(1) == 1,
                 default_payment_method: settings.get::<Option<String>, _>("default_payment_method").unwrap_or_else(|| "cash".to_string()),
                 tax_rate: settings.get::<Option<i32>, _>("tax_rate").unwrap_or(0) as f64,
                 enable_loyalty_program: settings.get::<Option<i32>, _>("enable_loyalty_program").unwrap_or(0) == 1,
                 loyalty_points_rate: settings.get::<Option<i32>, _>("loyalty_points_rate").unwrap_or(1) as f64,
                 minimum_order_amount: settings.get::<Option<i32>, _>("minimum_order_amount").unwrap_or(0) as f64,
+                default_import_category: settings.get::<Option<String>, _>("default_import_category")
+                    .filter(|name| !name.trim().is_empty()),
+                stock_hold_minutes: settings.get::<Option<i32>, _>("stock_hold_minutes").unwrap_or(30),
+                report_snapshot_frequency: settings.get::<Option<String>, _>("report_snapshot_frequency").unwrap_or_else(|| "monthly".to_string()),
+                strict_units: settings.get::<Option<i32>, _>("strict_units").unwrap_or(0) == 1,
+                invoice_discount_threshold: settings.try_get::<Option<f64>, _>("invoice_discount_threshold").ok().flatten()
+                    .or_else(|| settings.try_get::<Option<i64>, _>("invoice_discount_threshold").ok().flatten().map(|percent| percent as f64))
+                    .unwrap_or(0.0),
+                allow_zero_price_items: settings.get::<Option<i32>, _>("allow_zero_price_items").unwrap_or(0) == 1,
+                csv_delimiter: settings.get::<Option<String>, _>("csv_delimiter").unwrap_or_else(|| ",".to_string()),
+                csv_decimal_separator: settings.get::<Option<String>, _>("csv_decimal_separator").unwrap_or_else(|| ".".to_string()),
+                csv_decimals: settings.get::<Option<i32>, _>("csv_decimals").unwrap_or(2),
+                csv_include_bom: settings.get::<Option<i32>, _>("csv_include_bom").unwrap_or(1) == 1,
                 
                 // Security Settings
                 session_timeout: settings.get::<Option<i32>, _>("session_timeout").unwrap_or(30) as i32,
@@ -394,6 +500,221 @@ impl SettingsService {
 
 
 
+    // Settings plus an ETag derived from the row's updated_at and the serialized payload,
+    // so pollers can send If-None-Match and skip unchanged responses
+    pub async fn get_all_settings_with_etag(&self, db: &Database) -> Result<(SettingsResponse, String)> {
+        let settings = self.get_all_settings(db).await?;
+
+        let updated_at: Option<String> = sqlx::query("SELECT updated_at FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .and_then(|row| row.get::<Option<String>, _>("updated_at"));
+
+        let mut hasher = Sha256::new();
+        hasher.update(updated_at.unwrap_or_default().as_bytes());
+        hasher.update(serde_json::to_vec(&settings)?);
+        let etag = format!("\"{}\"", hex::encode(&hasher.finalize()[..16]));
+
+        Ok((settings, etag))
+    }
+
+    // Update only the keys present in `patch`; keys are checked against the column
+    // whitelist before being interpolated into the UPDATE statement
+    pub async fn update_partial(&self, db: &Database, mut patch: serde_json::Map<String, serde_json::Value>) -> Result<SettingsResponse> {
+        if patch.is_empty() {
+            return Err(anyhow::anyhow!("لم يتم إرسال أي إعدادات للتحديث"));
+        }
+
+        let unknown: Vec<&str> = patch.keys()
+            .map(|key| key.as_str())
+            .filter(|key| !UPDATABLE_SETTINGS_COLUMNS.contains(key))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(anyhow::anyhow!("مفاتيح إعدادات غير معروفة: {}", unknown.join(", ")));
+        }
+
+        if let Some(value) = patch.get("exchange_rate") {
+            let rate = value.as_f64()
+                .or_else(|| value.as_str().and_then(|s| s.trim().parse::<f64>().ok()))
+                .ok_or_else(|| anyhow::anyhow!("سعر الصرف يجب أن يكون رقماً"))?;
+            let rate = Self::validate_exchange_rate(rate)?;
+            patch.insert("exchange_rate".to_string(), serde_json::json!(rate));
+        }
+
+        if let Some(value) = patch.get("invoice_discount_threshold") {
+            let valid = value.as_f64().is_some_and(|percent| (0.0..=100.0).contains(&percent));
+            if !valid {
+                return Err(anyhow::anyhow!("حد خصم الفاتورة يجب أن يكون نسبة بين 0 و 100"));
+            }
+        }
+
+        if let Some(value) = patch.get("report_snapshot_frequency") {
+            let valid = value.as_str()
+                .is_some_and(|frequency| crate::services::ReportsService::SNAPSHOT_FREQUENCIES.contains(&frequency));
+            if !valid {
+                return Err(anyhow::anyhow!("تكرار لقطات التقارير يجب أن يكون off أو monthly أو daily"));
+            }
+        }
+
+        if let Some(value) = patch.get("csv_delimiter") {
+            let delimiter = value.as_str().ok_or_else(|| anyhow::anyhow!("فاصل الأعمدة يجب أن يكون نصاً"))?;
+            crate::utils::CsvFormat::parse_delimiter(delimiter)?;
+        }
+
+        if let Some(value) = patch.get("csv_decimal_separator") {
+            let separator = value.as_str().ok_or_else(|| anyhow::anyhow!("الفاصل العشري يجب أن يكون نصاً"))?;
+            crate::utils::CsvFormat::parse_decimal_separator(separator)?;
+        }
+
+        if let Some(value) = patch.get("csv_decimals") {
+            if !value.as_i64().is_some_and(|decimals| (0..=6).contains(&decimals)) {
+                return Err(anyhow::anyhow!("عدد المنازل العشرية يجب أن يكون بين 0 و 6"));
+            }
+        }
+
+        let assignments: Vec<String> = patch.keys().map(|key| format!("{} = ?", key)).collect();
+        let sql = format!(
+            "UPDATE settings SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = 1",
+            assignments.join(", ")
+        );
+
+        let mut query = sqlx::query(&sql);
+        for value in patch.values() {
+            query = match value {
+                serde_json::Value::Null => query.bind(None::<String>),
+                serde_json::Value::Bool(b) => query.bind(*b as i64),
+                serde_json::Value::Number(n) => match n.as_i64() {
+                    Some(i) => query.bind(i),
+                    None => query.bind(n.as_f64()),
+                },
+                serde_json::Value::String(s) => query.bind(s.clone()),
+                // JSON columns such as sidebar_menu_items are stored as text
+                other => query.bind(other.to_string()),
+            };
+        }
+
+        let mut tx = db.pool.begin().await?;
+        let new_rate = patch.get("exchange_rate").and_then(|rate| rate.as_f64());
+        let previous_rate: Option<f64> = match new_rate {
+            Some(_) => sqlx::query_scalar("SELECT CAST(exchange_rate AS REAL) FROM settings WHERE id = 1")
+                .fetch_optional(&mut *tx)
+                .await?
+                .flatten(),
+            None => None,
+        };
+
+        let result = query.execute(&mut *tx).await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("لم يتم العثور على الإعدادات"));
+        }
+        if let Some(rate) = new_rate {
+            Self::record_exchange_rate(&mut tx, previous_rate, rate).await?;
+        }
+        tx.commit().await?;
+
+        info!("Settings updated: {}", patch.keys().cloned().collect::<Vec<_>>().join(", "));
+        self.get_all_settings(db).await
+    }
+
+    // Keep exchange_rate_history in step with the setting so CurrencyConverter can value past dates. The
+    // first change also stores the rate it replaces, effective from the start, so earlier dates keep it;
+    // several changes on one day leave only the last.
+    async fn record_exchange_rate(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, previous_rate: Option<f64>, rate: f64) -> Result<()> {
+        if previous_rate.is_some_and(|previous| (previous - rate).abs() < 0.00005) {
+            return Ok(());
+        }
+
+        let has_history: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM exchange_rate_history)")
+            .fetch_one(&mut **tx)
+            .await?;
+        if let Some(previous) = previous_rate.filter(|previous| !has_history && *previous > 0.0) {
+            sqlx::query("INSERT INTO exchange_rate_history (rate, effective_date) VALUES (?, '1970-01-01')")
+                .bind(previous)
+                .execute(&mut **tx)
+                .await?;
+        }
+
+        let today = chrono::Local::now().date_naive();
+        sqlx::query("DELETE FROM exchange_rate_history WHERE effective_date = ?")
+            .bind(today)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("INSERT INTO exchange_rate_history (rate, effective_date) VALUES (?, ?)")
+            .bind(rate)
+            .bind(today)
+            .execute(&mut **tx)
+            .await?;
+
+        info!("Exchange rate changed to {} (was {:?})", rate, previous_rate);
+        Ok(())
+    }
+
+    // Portable copy of the settings row for setting up another branch: every updatable column except the
+    // per-install ones, with their stored values (flags stay 0/1)
+    pub async fn export(&self, db: &Database) -> Result<serde_json::Value> {
+        let row = sqlx::query("SELECT * FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("لم يتم العثور على الإعدادات"))?;
+
+        let mut settings = match crate::utils::sqlite_row_to_json(&row) {
+            serde_json::Value::Object(columns) => columns,
+            _ => serde_json::Map::new(),
+        };
+        settings.retain(|key, _| {
+            UPDATABLE_SETTINGS_COLUMNS.contains(&key.as_str()) && !LOCAL_SETTINGS_COLUMNS.contains(&key.as_str())
+        });
+
+        Ok(serde_json::json!({
+            "version": SETTINGS_EXPORT_VERSION,
+            "exported_at": chrono::Utc::now().to_rfc3339(),
+            "settings": settings,
+        }))
+    }
+
+    // Apply an export (or a bare settings object) through update_partial. Identity columns are dropped unless
+    // overwrite_identity is set, so a new branch keeps its own name, tax number and logo. Keys this server
+    // does not know are skipped rather than failing the import, so exports from newer versions still apply.
+    pub async fn import(&self, db: &Database, value: serde_json::Value, overwrite_identity: bool) -> Result<SettingsResponse> {
+        if let Some(version) = value.get("version") {
+            if version.as_i64().is_none_or(|version| version > SETTINGS_EXPORT_VERSION) {
+                return Err(anyhow::anyhow!("إصدار ملف الإعدادات غير مدعوم: {}", version));
+            }
+        }
+
+        let settings = match value.get("settings").cloned().unwrap_or(value) {
+            serde_json::Value::Object(settings) => settings,
+            _ => return Err(anyhow::anyhow!("ملف الإعدادات غير صالح")),
+        };
+
+        let mut patch = serde_json::Map::new();
+        let mut skipped = Vec::new();
+        for (key, value) in settings {
+            if !UPDATABLE_SETTINGS_COLUMNS.contains(&key.as_str()) || LOCAL_SETTINGS_COLUMNS.contains(&key.as_str()) {
+                skipped.push(key);
+            } else if overwrite_identity || !IDENTITY_SETTINGS_COLUMNS.contains(&key.as_str()) {
+                patch.insert(key, value);
+            }
+        }
+        if !skipped.is_empty() {
+            warn!("Settings import skipped keys: {}", skipped.join(", "));
+        }
+
+        let invalid: Vec<String> = SETTINGS_CHOICES.iter()
+            .filter_map(|(key, allowed)| {
+                let value = patch.get(*key)?;
+                let valid = value.as_str().is_some_and(|choice| allowed.contains(&choice));
+                (!valid).then(|| format!("{} ({})", key, allowed.join("/")))
+            })
+            .collect();
+        if !invalid.is_empty() {
+            return Err(anyhow::anyhow!("قيم غير صالحة في ملف الإعدادات: {}", invalid.join("، ")));
+        }
+
+        info!("Importing {} settings (identity {})", patch.len(), if overwrite_identity { "overwritten" } else { "kept" });
+        self.update_partial(db, patch).await
+    }
+
     pub async fn insert_default_settings(&self, db: &Database) -> Result<()> {
         // Check if settings row already exists
         let exists = sqlx::query("SELECT id FROM settings WHERE id = 1")
@@ -414,4 +735,143 @@ impl Default for SettingsService {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::utils::CurrencyConverter;
+
+    fn patch(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[tokio::test]
+    async fn partial_update_touches_only_the_sent_keys() {
+        let db = TestDatabase::new().await;
+        let service = SettingsService::new();
+        let before = service.get_all_settings(&db).await.unwrap();
+
+        let after = service.update_partial(&db, patch(serde_json::json!({
+            "mobile": "07701234567",
+            "allow_negative_stock": true
+        }))).await.unwrap();
+        assert_eq!(after.mobile, "07701234567");
+        assert!(after.allow_negative_stock);
+        assert_eq!(after.company_name, before.company_name);
+        assert_eq!(after.currency, before.currency);
+    }
+
+    #[tokio::test]
+    async fn partial_update_rejects_unknown_keys_without_writing() {
+        let db = TestDatabase::new().await;
+        let service = SettingsService::new();
+
+        let err = service.update_partial(&db, patch(serde_json::json!({
+            "mobile": "07800000000",
+            "id = 2, mobile": "x"
+        }))).await.unwrap_err();
+        assert!(err.to_string().contains("id = 2, mobile"));
+        assert_ne!(service.get_all_settings(&db).await.unwrap().mobile, "07800000000");
+
+        assert!(service.update_partial(&db, serde_json::Map::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn etag_is_stable_until_settings_change() {
+        let db = TestDatabase::new().await;
+        let service = SettingsService::new();
+        let (_, first) = service.get_all_settings_with_etag(&db).await.unwrap();
+        let (_, again) = service.get_all_settings_with_etag(&db).await.unwrap();
+        assert_eq!(first, again);
+
+        service.update_partial(&db, patch(serde_json::json!({ "company_name": "متجر النور" }))).await.unwrap();
+        let (settings, changed) = service.get_all_settings_with_etag(&db).await.unwrap();
+        assert_ne!(first, changed);
+        assert_eq!(settings.company_name, "متجر النور");
+    }
+
+    #[test]
+    fn exchange_rate_must_be_positive_plausible_and_is_rounded() {
+        assert_eq!(SettingsService::validate_exchange_rate(1465.123456).unwrap(), 1465.1235);
+        assert!(SettingsService::validate_exchange_rate(0.0).is_err());
+        assert!(SettingsService::validate_exchange_rate(-1310.0).is_err());
+        assert!(SettingsService::validate_exchange_rate(f64::NAN).is_err());
+        // A slipped keystroke (1460000 for 1460) is refused rather than stored
+        assert!(SettingsService::validate_exchange_rate(1_460_000.0).is_err());
+    }
+
+    #[tokio::test]
+    async fn exchange_rate_is_stored_normalized_from_a_string() {
+        let db = TestDatabase::new().await;
+        let service = SettingsService::new();
+
+        let settings = service.update_partial(&db, patch(serde_json::json!({ "exchange_rate": " 1482.55555 " }))).await.unwrap();
+        assert_eq!(settings.exchange_rate, 1482.5556);
+        assert!(service.update_partial(&db, patch(serde_json::json!({ "exchange_rate": "abc" }))).await.is_err());
+        assert_eq!(service.get_all_settings(&db).await.unwrap().exchange_rate, 1482.5556);
+    }
+
+    #[tokio::test]
+    async fn exchange_rate_change_is_recorded_for_past_dates() {
+        let db = TestDatabase::new().await;
+        let service = SettingsService::new();
+        service.update_partial(&db, patch(serde_json::json!({ "exchange_rate": 1310 }))).await.unwrap();
+        service.update_partial(&db, patch(serde_json::json!({ "exchange_rate": 1460 }))).await.unwrap();
+        // Same rate again is not a change
+        service.update_partial(&db, patch(serde_json::json!({ "exchange_rate": 1460 }))).await.unwrap();
+
+        let history: Vec<(String, f64)> = sqlx::query_as("SELECT effective_date, CAST(rate AS REAL) FROM exchange_rate_history ORDER BY effective_date")
+            .fetch_all(&db.pool).await.unwrap();
+        // The default rate is kept for earlier dates; both changes today leave only the last
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0], ("1970-01-01".to_string(), 1.0));
+        assert_eq!(history[1].1, 1460.0);
+
+        let converter = CurrencyConverter::load(&db.pool).await.unwrap();
+        let last_year = chrono::Local::now().date_naive() - chrono::Duration::days(365);
+        assert_eq!(converter.rate_on(Some(last_year)), 1.0);
+        assert_eq!(converter.convert(2.0, "USD", "IQD", None).unwrap(), 2920.0);
+    }
+
+    #[tokio::test]
+    async fn settings_round_trip_to_another_branch_keeping_its_identity() {
+        let main_branch = TestDatabase::new().await;
+        let new_branch = TestDatabase::new().await;
+        let service = SettingsService::new();
+        service.update_partial(&main_branch, patch(serde_json::json!({
+            "company_name": "أسواق الرافدين - المركز",
+            "tax_number": "IQ-100200",
+            "logo_url": "/uploads/logo-main.png",
+            "mobile": "07701112233",
+            "bill_print_mode": "thermal",
+            "email_password": "smtp-secret"
+        }))).await.unwrap();
+        service.update_partial(&new_branch, patch(serde_json::json!({
+            "company_name": "أسواق الرافدين - زيونة",
+            "tax_number": "IQ-100201"
+        }))).await.unwrap();
+
+        let export = service.export(&main_branch).await.unwrap();
+        assert!(export["settings"].get("email_password").is_none());
+
+        let imported = service.import(&new_branch, export.clone(), false).await.unwrap();
+        assert_eq!(imported.mobile, "07701112233");
+        assert_eq!(imported.bill_print_mode, "thermal");
+        assert_eq!(imported.company_name, "أسواق الرافدين - زيونة");
+        assert_eq!(imported.tax_number, "IQ-100201");
+        assert_ne!(imported.logo_url.as_deref(), Some("/uploads/logo-main.png"));
+        assert_ne!(imported.email_password, "smtp-secret");
+
+        let mut bad = export.clone();
+        bad["settings"]["bill_print_mode"] = serde_json::json!("poster");
+        bad["settings"]["mobile"] = serde_json::json!("07709999999");
+        assert!(service.import(&new_branch, bad, false).await.is_err());
+        assert_eq!(service.get_all_settings(&new_branch).await.unwrap().mobile, "07701112233");
+
+        let copied = service.import(&new_branch, export, true).await.unwrap();
+        assert_eq!(copied.company_name, "أسواق الرافدين - المركز");
+        assert_eq!(copied.tax_number, "IQ-100200");
+        assert_eq!(copied.logo_url.as_deref(), Some("/uploads/logo-main.png"));
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::Result;
+use sqlx::{Row, SqliteConnection};
+use tracing::info;
+use crate::database::Database;
+use crate::models::stock_hold::*;
+
+#[derive(Clone)]
+pub struct StockHoldsService;
+
+impl StockHoldsService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    // Reserve stock for a draft sale; every item must fit in the currently available quantity
+    pub async fn create(&self, db: &Database, payload: CreateStockHoldRequest) -> Result<Vec<StockHold>> {
+        if payload.items.is_empty() {
+            return Err(anyhow::anyhow!("يجب تحديد منتج واحد على الأقل للحجز"));
+        }
+
+        let reference = payload.reference
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty())
+            .unwrap_or_else(|| format!("HOLD-{}", uuid::Uuid::new_v4()));
+
+        let mut tx = db.pool.begin().await?;
+        Self::expire_stale(&mut tx).await?;
+
+        let minutes = match payload.expires_in_minutes {
+            Some(minutes) => minutes,
+            None => sqlx::query("SELECT COALESCE(stock_hold_minutes, 30) as minutes FROM settings WHERE id = 1")
+                .fetch_optional(&mut *tx)
+                .await?
+                .map(|row| row.get::<i64, _>("minutes"))
+                .unwrap_or(30),
+        };
+        if minutes <= 0 {
+            return Err(anyhow::anyhow!("مدة الحجز يجب أن تكون أكبر من صفر"));
+        }
+
+        for item in &payload.items {
+            if item.quantity <= 0 {
+                return Err(anyhow::anyhow!("الكمية يجب أن تكون أكبر من صفر"));
+            }
+
+            let product = sqlx::query("SELECT name, current_stock FROM products WHERE id = ? AND is_active = 1")
+                .bind(item.product_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("المنتج غير موجود"))?;
+            let name: String = product.get("name");
+            let current_stock: i64 = product.get("current_stock");
+
+            let held = Self::held_quantity(&mut tx, item.product_id, None).await?;
+            let available = current_stock - held;
+            if item.quantity > available {
+                return Err(anyhow::anyhow!("الكمية المتاحة للمنتج {} هي {} فقط", name, available.max(0)));
+            }
+
+            sqlx::query(r#"
+                INSERT INTO stock_holds (reference, product_id, quantity, status, expires_at, created_at, updated_at)
+                VALUES (?, ?, ?, 'active', datetime('now', ?), CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            "#)
+            .bind(&reference)
+            .bind(item.product_id)
+            .bind(item.quantity)
+            .bind(format!("+{} minutes", minutes))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        info!("Stock hold {} placed for {} items", reference, payload.items.len());
+
+        self.get_by_reference(db, &reference).await
+    }
+
+    pub async fn get_by_reference(&self, db: &Database, reference: &str) -> Result<Vec<StockHold>> {
+        let holds = sqlx::query_as::<_, StockHold>("SELECT * FROM stock_holds WHERE reference = ? ORDER BY id")
+            .bind(reference)
+            .fetch_all(&db.pool)
+            .await?;
+
+        Ok(holds)
+    }
+
+    // Cancel a draft: its active holds stop counting against available stock
+    pub async fn release(&self, db: &Database, reference: &str) -> Result<u64> {
+        let result = sqlx::query(r#"
+            UPDATE stock_holds SET status = 'released', updated_at = CURRENT_TIMESTAMP
+            WHERE reference = ? AND status = 'active'
+        "#)
+        .bind(reference)
+        .execute(&db.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn availability(&self, db: &Database, product_id: i64) -> Result<ProductAvailability> {
+        let mut conn = db.pool.acquire().await?;
+        let current_stock: i64 = sqlx::query("SELECT current_stock FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .map(|row| row.get("current_stock"))
+            .ok_or_else(|| anyhow::anyhow!("المنتج غير موجود"))?;
+        let held_quantity = Self::held_quantity(&mut conn, product_id, None).await?;
+
+        Ok(ProductAvailability {
+            product_id,
+            current_stock,
+            held_quantity,
+            available_stock: current_stock - held_quantity,
+        })
+    }
+
+    // Quantity reserved by unexpired active holds, optionally ignoring one draft's own holds
+    pub async fn held_quantity(conn: &mut SqliteConnection, product_id: i64, exclude_reference: Option<&str>) -> Result<i64> {
+        let row = sqlx::query(r#"
+            SELECT COALESCE(SUM(quantity), 0) as held FROM stock_holds
+            WHERE product_id = ? AND status = 'active' AND expires_at > datetime('now')
+              AND (? IS NULL OR reference != ?)
+        "#)
+        .bind(product_id)
+        .bind(exclude_reference)
+        .bind(exclude_reference)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(row.get("held"))
+    }
+
+    // Convert a draft's holds once its sale is written; the sale_items trigger does the physical decrement
+    pub async fn confirm(conn: &mut SqliteConnection, reference: &str, sale_id: i64) -> Result<()> {
+        let result = sqlx::query(r#"
+            UPDATE stock_holds SET status = 'confirmed', sale_id = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE reference = ? AND status = 'active' AND expires_at > datetime('now')
+        "#)
+        .bind(sale_id)
+        .bind(reference)
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Stock hold {} not found or expired", reference));
+        }
+
+        Ok(())
+    }
+
+    async fn expire_stale(conn: &mut SqliteConnection) -> Result<()> {
+        sqlx::query(r#"
+            UPDATE stock_holds SET status = 'expired', updated_at = CURRENT_TIMESTAMP
+            WHERE status = 'active' AND expires_at <= datetime('now')
+        "#)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::services::SaleService;
+    use serde_json::json;
+
+    async fn stocked_product(db: &Database, on_hand: i64) -> i64 {
+        sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, current_stock) VALUES ('Laptop bag', 'BAG-15', 20, 35, 30, ?)")
+            .bind(on_hand)
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid()
+    }
+
+    async fn hold(db: &Database, reference: &str, product_id: i64, quantity: i64) -> Result<Vec<StockHold>> {
+        StockHoldsService::new().create(db, CreateStockHoldRequest {
+            reference: Some(reference.into()),
+            items: vec![StockHoldItem { product_id, quantity }],
+            expires_in_minutes: None,
+        }).await
+    }
+
+    async fn sell(db: &Database, product_id: i64, quantity: i64, hold_reference: Option<&str>) -> Result<i64> {
+        let request = serde_json::from_value(json!({
+            "customer_id": 999,
+            "invoice_date": "2026-06-10",
+            "payment_method": "cash",
+            "paid_amount": quantity as f64 * 35.0,
+            "hold_reference": hold_reference,
+            "items": [{ "product_id": product_id, "quantity": quantity, "price": 35.0 }]
+        })).unwrap();
+        SaleService::new().create(db, request).await.map(|sale| sale.id)
+    }
+
+    #[tokio::test]
+    async fn held_quantity_is_unavailable_until_released() {
+        let db = TestDatabase::new().await;
+        let product_id = stocked_product(&db, 10).await;
+
+        hold(&db, "DRAFT-7", product_id, 8).await.unwrap();
+        let availability = StockHoldsService::new().availability(&db, product_id).await.unwrap();
+        assert_eq!((availability.current_stock, availability.held_quantity, availability.available_stock), (10, 8, 2));
+
+        // Neither another sale nor another draft can take the reserved units
+        assert!(sell(&db, product_id, 5, None).await.is_err());
+        assert!(hold(&db, "DRAFT-8", product_id, 3).await.is_err());
+        sell(&db, product_id, 2, None).await.unwrap();
+
+        assert_eq!(StockHoldsService::new().release(&db, "DRAFT-7").await.unwrap(), 1);
+        sell(&db, product_id, 5, None).await.unwrap();
+        let availability = StockHoldsService::new().availability(&db, product_id).await.unwrap();
+        assert_eq!((availability.current_stock, availability.held_quantity), (3, 0));
+    }
+
+    #[tokio::test]
+    async fn confirming_a_draft_turns_its_hold_into_the_sale() {
+        let db = TestDatabase::new().await;
+        let product_id = stocked_product(&db, 6).await;
+        hold(&db, "DRAFT-12", product_id, 6).await.unwrap();
+
+        // The draft itself may use what it reserved
+        let sale_id = sell(&db, product_id, 6, Some("DRAFT-12")).await.unwrap();
+        let holds = StockHoldsService::new().get_by_reference(&db, "DRAFT-12").await.unwrap();
+        assert_eq!(holds[0].status, "confirmed");
+        assert_eq!(holds[0].sale_id, Some(sale_id));
+
+        // Stock went down once, and nothing is still counted as held
+        let availability = StockHoldsService::new().availability(&db, product_id).await.unwrap();
+        assert_eq!((availability.current_stock, availability.held_quantity), (0, 0));
+        assert!(sell(&db, product_id, 1, Some("DRAFT-12")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn expired_holds_stop_reserving_stock() {
+        let db = TestDatabase::new().await;
+        let product_id = stocked_product(&db, 4).await;
+        hold(&db, "DRAFT-3", product_id, 4).await.unwrap();
+        sqlx::query("UPDATE stock_holds SET expires_at = datetime('now', '-1 minutes') WHERE reference = 'DRAFT-3'")
+            .execute(&db.pool).await.unwrap();
+
+        let availability = StockHoldsService::new().availability(&db, product_id).await.unwrap();
+        assert_eq!(availability.available_stock, 4);
+
+        // Confirming the lapsed draft is refused rather than silently selling unreserved stock
+        assert!(sell(&db, product_id, 1, Some("DRAFT-3")).await.is_err());
+        sell(&db, product_id, 4, None).await.unwrap();
+    }
+}
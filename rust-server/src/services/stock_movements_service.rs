@@ -300,8 +300,9 @@ impl StockMovementsService {
                     .fetch_optional(&mut *tx)
                     .await?;
 
+                // An adjustment is the outcome of counting the product, so it also dates the last stock check
                 if existing_product.is_some() {
-                    sqlx::query("UPDATE products SET current_stock = current_stock + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND stock_id = ?")
+                    sqlx::query("UPDATE products SET current_stock = current_stock + ?, last_stock_check = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND stock_id = ?")
                         .bind(movement_data.quantity)
                         .bind(movement_data.product_id)
                         .bind(stock_id)
@@ -309,7 +310,7 @@ impl StockMovementsService {
                         .await?;
                 } else {
                     // Update the product's stock_id to the target stock and set the quantity
-                    sqlx::query("UPDATE products SET current_stock = ?, stock_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    sqlx::query("UPDATE products SET current_stock = ?, stock_id = ?, last_stock_check = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
                         .bind(movement_data.quantity)
                         .bind(stock_id)
                         .bind(movement_data.product_id)
@@ -642,12 +643,90 @@ impl StockMovementsService {
         })
     }
 
-    // Additional methods for extended functionality
-    pub async fn get_product_stock_history(&self, _db: &Database, _product_id: i64, _query: &ProductStockHistoryQuery) -> Result<Value> {
-        // TODO: Implement product stock history
-        Ok(serde_json::json!([]))
+    // Chronological stock timeline for one product with a running balance.
+    // stock_movements count +qty when entering a stock and -qty when leaving one (transfers net to 0);
+    // inventory_movements count in/out, adjustments carry their own sign and transfers net to 0.
+    pub async fn product_history(&self, db: &Database, product_id: i64) -> Result<Vec<StockMovementWithContext>> {
+        let rows = sqlx::query(r#"
+            SELECT * FROM (
+                SELECT
+                    'stock_movements' as source,
+                    sm.id,
+                    sm.movement_type,
+                    (CASE WHEN sm.to_stock_id IS NOT NULL THEN sm.quantity ELSE 0 END)
+                        - (CASE WHEN sm.from_stock_id IS NOT NULL THEN sm.quantity ELSE 0 END) as quantity_change,
+                    fs.name as from_stock_name,
+                    ts.name as to_stock_name,
+                    sm.reference_type,
+                    sm.reference_id,
+                    sm.reference_number,
+                    sm.notes,
+                    u.name as created_by_name,
+                    sm.movement_date as movement_date
+                FROM stock_movements sm
+                LEFT JOIN stocks fs ON sm.from_stock_id = fs.id
+                LEFT JOIN stocks ts ON sm.to_stock_id = ts.id
+                LEFT JOIN users u ON sm.created_by = u.id
+                WHERE sm.product_id = ?
+
+                UNION ALL
+
+                SELECT
+                    'inventory_movements' as source,
+                    im.id,
+                    im.movement_type,
+                    CASE im.movement_type
+                        WHEN 'in' THEN ABS(im.quantity)
+                        WHEN 'out' THEN -ABS(im.quantity)
+                        WHEN 'adjustment' THEN im.quantity
+                        ELSE 0
+                    END as quantity_change,
+                    NULL as from_stock_name,
+                    NULL as to_stock_name,
+                    im.reference_type,
+                    im.reference_id,
+                    NULL as reference_number,
+                    im.notes,
+                    u.name as created_by_name,
+                    im.created_at as movement_date
+                FROM inventory_movements im
+                LEFT JOIN users u ON im.created_by = u.id
+                WHERE im.product_id = ?
+            )
+            ORDER BY movement_date ASC, source ASC, id ASC
+        "#)
+        .bind(product_id)
+        .bind(product_id)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let mut running_balance = 0i64;
+        let mut history = Vec::with_capacity(rows.len());
+        for row in rows {
+            let quantity_change: i64 = row.get("quantity_change");
+            running_balance += quantity_change;
+
+            history.push(StockMovementWithContext {
+                source: row.get("source"),
+                id: row.get("id"),
+                movement_type: row.get("movement_type"),
+                quantity_change,
+                running_balance,
+                from_stock_name: row.get("from_stock_name"),
+                to_stock_name: row.get("to_stock_name"),
+                reference_type: row.get("reference_type"),
+                reference_id: row.get("reference_id"),
+                reference_number: row.get("reference_number"),
+                notes: row.get("notes"),
+                created_by_name: row.get("created_by_name"),
+                movement_date: row.get("movement_date"),
+            });
+        }
+
+        Ok(history)
     }
 
+    // Additional methods for extended functionality
     pub async fn get_stock_movements_summary(&self, _db: &Database, _query: &StockMovementsSummaryQuery) -> Result<Value> {
         // TODO: Implement stock movements summary
         Ok(serde_json::json!({}))
@@ -737,4 +816,72 @@ impl StockMovementsService {
 
         Ok(movements)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    #[tokio::test]
+    async fn history_merges_both_ledgers_with_a_running_balance() {
+        let db = TestDatabase::new().await;
+        let pool = &db.pool;
+        let product_id = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price) VALUES ('Sugar 1kg', 'SUG-1', 1000, 1250, 1200)")
+            .execute(pool).await.unwrap().last_insert_rowid();
+        let branch = sqlx::query("INSERT INTO stocks (name, code, address) VALUES ('Branch', 'BR-2', 'Basra')")
+            .execute(pool).await.unwrap().last_insert_rowid();
+
+        sqlx::query("INSERT INTO stock_movements (movement_type, to_stock_id, product_id, quantity, reference_type, movement_date) VALUES ('purchase', 1, ?, 40, 'purchase', '2026-03-01 09:00:00')")
+            .bind(product_id).execute(pool).await.unwrap();
+        // A transfer between two stocks leaves the product total unchanged
+        sqlx::query("INSERT INTO stock_movements (movement_type, from_stock_id, to_stock_id, product_id, quantity, movement_date) VALUES ('transfer', 1, ?, ?, 15, '2026-03-02 09:00:00')")
+            .bind(branch).bind(product_id).execute(pool).await.unwrap();
+        sqlx::query("INSERT INTO inventory_movements (product_id, movement_type, quantity, created_at) VALUES (?, 'out', 6, '2026-03-03 12:00:00')")
+            .bind(product_id).execute(pool).await.unwrap();
+        sqlx::query("INSERT INTO inventory_movements (product_id, movement_type, quantity, created_at) VALUES (?, 'adjustment', -2, '2026-03-04 18:30:00')")
+            .bind(product_id).execute(pool).await.unwrap();
+
+        let history = StockMovementsService::new().product_history(&db, product_id).await.unwrap();
+        let changes: Vec<(i64, i64)> = history.iter().map(|m| (m.quantity_change, m.running_balance)).collect();
+        assert_eq!(changes, [(40, 40), (0, 40), (-6, 34), (-2, 32)]);
+        assert_eq!(history[1].to_stock_name.as_deref(), Some("Branch"));
+        assert_eq!(history[2].source, "inventory_movements");
+
+        assert!(StockMovementsService::new().product_history(&db, product_id + 1).await.unwrap().is_empty());
+    }
+
+    // (current_stock, counted within the last minute)
+    async fn count_state(db: &Database, product_id: i64) -> (i64, bool) {
+        sqlx::query_as("SELECT current_stock, COALESCE(last_stock_check >= datetime('now', '-1 minute'), 0) FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_one(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn adjustment_dates_the_products_stock_check() {
+        let db = TestDatabase::new().await;
+        let product_id = sqlx::query(r#"
+            INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock, last_stock_check)
+            VALUES ('Printer paper A4', 'PAPER-A4', 4000, 5500, 5000, 1, 12, '2025-11-02 08:00:00')
+        "#)
+        .execute(&db.pool).await.unwrap()
+        .last_insert_rowid();
+        assert_eq!(count_state(&db, product_id).await, (12, false));
+
+        let adjustment = CreateStockMovementRequest {
+            movement_type: "adjustment".to_string(),
+            from_stock_id: None,
+            to_stock_id: Some(1),
+            product_id,
+            quantity: 3,
+            unit_cost: None,
+            total_value: None,
+            reference_type: None,
+            reference_id: None,
+            reference_number: None,
+            notes: Some("جرد دوري".to_string()),
+        };
+        StockMovementsService::new().create(&db, adjustment).await.unwrap();
+        assert_eq!(count_state(&db, product_id).await, (15, true));
+    }
+}
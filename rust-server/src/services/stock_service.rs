@@ -814,8 +814,149 @@ impl StockService {
         Ok(serde_json::json!({}))
     }
 
-    pub async fn adjust_stock(&self, _db: &Database, _payload: AdjustStockRequest) -> Result<Value> {
-        // This would need to be implemented based on specific requirements
-        Ok(serde_json::json!({}))
+    // Set a product's quantity in a stock to what is actually there. The difference becomes one
+    // stock_movements row (into the stock when found, out of it when lost) valued at average cost.
+    pub async fn adjust(
+        &self,
+        db: &Database,
+        stock_id: i64,
+        request: AdjustStockRequest,
+        created_by: Option<i64>,
+    ) -> Result<StockAdjustment> {
+        let AdjustStockRequest { product_id, new_quantity, reason, notes } = request;
+        if new_quantity < 0 {
+            return Err(anyhow::anyhow!("الكمية الجديدة لا يمكن أن تكون سالبة"));
+        }
+
+        let mut tx = db.pool.begin().await?;
+
+        let stock_exists = sqlx::query("SELECT id FROM stocks WHERE id = ?")
+            .bind(stock_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .is_some();
+        if !stock_exists {
+            return Err(anyhow::anyhow!("المخزن غير موجود"));
+        }
+
+        let product = sqlx::query(r#"
+            SELECT current_stock, stock_id,
+                   CAST(COALESCE(NULLIF(average_cost, 0), purchase_price, 0) AS REAL) as unit_cost
+            FROM products WHERE id = ?
+        "#)
+        .bind(product_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("المنتج غير موجود"))?;
+
+        let product_stock_id: Option<i64> = product.get("stock_id");
+        if product_stock_id.is_some_and(|id| id != stock_id) {
+            return Err(anyhow::anyhow!("المنتج غير موجود في هذا المخزن"));
+        }
+
+        let previous_quantity: i64 = product.get("current_stock");
+        let delta = new_quantity - previous_quantity;
+        if delta == 0 {
+            return Err(anyhow::anyhow!("الكمية الجديدة مطابقة للكمية الحالية"));
+        }
+        if !reason.allows(delta) {
+            return Err(anyhow::anyhow!(
+                "سبب التعديل ({}) لا يسمح ب{} الكمية",
+                reason.label(),
+                if delta > 0 { "زيادة" } else { "إنقاص" }
+            ));
+        }
+
+        let unit_cost: f64 = product.get("unit_cost");
+        let total_value = (unit_cost * delta.abs() as f64 * 100.0).round() / 100.0;
+        let (from_stock_id, to_stock_id) = if delta > 0 { (None, Some(stock_id)) } else { (Some(stock_id), None) };
+        let notes = match notes.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+            Some(notes) => format!("{}: {}", reason.label(), notes),
+            None => reason.label().to_string(),
+        };
+
+        let movement_id = sqlx::query(r#"
+            INSERT INTO stock_movements (
+                movement_type, from_stock_id, to_stock_id, product_id, quantity, unit_cost, total_value,
+                reference_type, reference_number, notes, created_by
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, 'adjustment', ?, ?, ?)
+        "#)
+        .bind(reason.movement_type())
+        .bind(from_stock_id)
+        .bind(to_stock_id)
+        .bind(product_id)
+        .bind(delta.abs())
+        .bind(unit_cost)
+        .bind(total_value)
+        .bind(format!("ADJ-{}", Utc::now().timestamp_millis()))
+        .bind(&notes)
+        .bind(created_by)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        // A count also dates the product's last stock check
+        let checked = if reason == AdjustmentReason::Count { ", last_stock_check = CURRENT_TIMESTAMP" } else { "" };
+        sqlx::query(&format!("UPDATE products SET current_stock = ?{}, updated_at = CURRENT_TIMESTAMP WHERE id = ?", checked))
+            .bind(new_quantity)
+            .bind(product_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        info!("Stock of product {} in stock {} adjusted {} -> {} ({:?})", product_id, stock_id, previous_quantity, new_quantity, reason);
+
+        Ok(StockAdjustment {
+            movement_id,
+            stock_id,
+            product_id,
+            reason,
+            movement_type: reason.movement_type().to_string(),
+            previous_quantity,
+            new_quantity,
+            delta,
+            unit_cost,
+            total_value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+
+    #[tokio::test]
+    async fn found_stock_adds_and_damage_removes_at_average_cost() {
+        let db = TestDatabase::new().await;
+        let service = StockService::new();
+        let product_id = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price, stock_id, current_stock, average_cost) VALUES ('Flour 10kg', 'FLR-10', 9000, 12000, 11000, 1, 10, 9500)")
+            .execute(&db.pool).await.unwrap().last_insert_rowid();
+        let movement = |id: i64| sqlx::query_as::<_, (String, Option<i64>, Option<i64>, i64, f64, String)>(
+            "SELECT movement_type, from_stock_id, to_stock_id, quantity, CAST(total_value AS REAL), notes FROM stock_movements WHERE id = ?"
+        ).bind(id).fetch_one(&db.pool);
+        let count = |new_quantity: i64, reason: AdjustmentReason, notes: Option<&str>| AdjustStockRequest {
+            product_id,
+            new_quantity,
+            reason,
+            notes: notes.map(str::to_string),
+        };
+        let on_hand = || sqlx::query_scalar::<_, i64>("SELECT current_stock FROM products WHERE id = ?").bind(product_id).fetch_one(&db.pool);
+
+        let found = service.adjust(&db, 1, count(12, AdjustmentReason::Found, Some("خلف الرفوف")), None).await.unwrap();
+        assert_eq!(found.delta, 2);
+        assert_eq!(movement(found.movement_id).await.unwrap(), ("adjustment".to_string(), None, Some(1), 2, 19000.0, "مخزون غير مسجل: خلف الرفوف".to_string()));
+        assert_eq!(on_hand().await.unwrap(), 12);
+
+        let damaged = service.adjust(&db, 1, count(9, AdjustmentReason::Damage, None), None).await.unwrap();
+        assert_eq!(damaged.delta, -3);
+        assert_eq!(movement(damaged.movement_id).await.unwrap(), ("damage".to_string(), Some(1), None, 3, 28500.0, "تالف".to_string()));
+        assert_eq!(on_hand().await.unwrap(), 9);
+
+        // Neither a no-op nor damage that adds stock is recorded
+        assert!(service.adjust(&db, 1, count(9, AdjustmentReason::Count, None), None).await.is_err());
+        assert!(service.adjust(&db, 1, count(11, AdjustmentReason::Damage, None), None).await.is_err());
+        let movements: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_movements WHERE product_id = ?").bind(product_id).fetch_one(&db.pool).await.unwrap();
+        assert_eq!(movements, 2);
     }
 }
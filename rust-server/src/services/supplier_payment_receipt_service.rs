@@ -1,7 +1,8 @@
 use anyhow::Result;
 use crate::database::Database;
 use crate::models::supplier_payment_receipt::*;
-use sqlx::{Row, SqlitePool};
+use crate::services::SequenceService;
+use sqlx::Row;
 use tracing::{info, warn, error};
 use chrono::{Utc, DateTime, NaiveDate, NaiveDateTime};
 use serde_json::Value;
@@ -186,11 +187,13 @@ impl SupplierPaymentReceiptService {
             return Err(anyhow::anyhow!("طريقة الدفع مطلوبة"));
         }
 
+        let mut tx = db.pool.begin().await?;
+
         // Generate receipt number if not provided
         let receipt_number = if let Some(ref number) = receipt_data.receipt_number {
             number.clone()
         } else {
-            self.generate_receipt_number(&db.pool).await?
+            SequenceService::next(&mut tx, SequenceService::SUPPLIER_PAYMENT_RECEIPT).await?
         };
 
         let receipt_id = sqlx::query(r#"
@@ -207,9 +210,10 @@ impl SupplierPaymentReceiptService {
         .bind(receipt_data.reference_number)
         .bind(receipt_data.notes)
         .bind(receipt_data.money_box_id)
-        .execute(&db.pool)
+        .execute(&mut *tx)
         .await?
         .last_insert_rowid();
+        tx.commit().await?;
 
         // Get the created receipt
         let receipt = self.get_by_id(db, receipt_id).await?;
@@ -403,17 +407,6 @@ impl SupplierPaymentReceiptService {
         })
     }
 
-    // Helper method to generate receipt number
-    async fn generate_receipt_number(&self, pool: &SqlitePool) -> Result<String> {
-        let count: i64 = sqlx::query("SELECT COUNT(*) FROM supplier_payment_receipts")
-            .fetch_one(pool)
-            .await?
-            .get(0);
-
-        let receipt_number = format!("SPR{:06}", count + 1);
-        Ok(receipt_number)
-    }
-
     // Helper method to map database row to SupplierPaymentReceipt
     async fn map_receipt_row(&self, row: sqlx::sqlite::SqliteRow) -> Result<SupplierPaymentReceipt> {
         Ok(SupplierPaymentReceipt {
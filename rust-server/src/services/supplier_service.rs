@@ -1,6 +1,7 @@
 use anyhow::Result;
 use crate::database::Database;
 use crate::models::supplier::*;
+use crate::utils::CsvFormat;
 use sqlx::{Row, SqlitePool};
 use tracing::{info, warn, error};
 use chrono::{Utc, DateTime, NaiveDate, NaiveDateTime};
@@ -111,7 +112,170 @@ impl SupplierService {
         }
     }
 
+    // Running payable ledger for a supplier; entries before start_date fold into the opening balance
+    pub async fn get_statement(&self, db: &Database, supplier_id: i64, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> Result<Option<SupplierStatement>> {
+        let supplier_name: String = match sqlx::query("SELECT name FROM suppliers WHERE id = ?")
+            .bind(supplier_id)
+            .fetch_optional(&db.pool)
+            .await?
+        {
+            Some(row) => row.get("name"),
+            None => return Ok(None),
+        };
+
+        // Payments made at purchase time live on the purchase row, later ones in supplier_payment_receipts
+        let rows = sqlx::query(r#"
+            SELECT entry_date, entry_type, reference_id, reference_no, purchase_amount, payment_amount FROM (
+                SELECT invoice_date as entry_date, 'purchase' as entry_type, 0 as sort_order, id as reference_id,
+                       invoice_no as reference_no, net_amount as purchase_amount, 0.0 as payment_amount
+                FROM purchases WHERE supplier_id = ? AND status != 'cancelled'
+                UNION ALL
+                SELECT invoice_date, 'purchase_payment', 1, id, invoice_no, 0.0, paid_amount
+                FROM purchases WHERE supplier_id = ? AND status != 'cancelled' AND paid_amount > 0
+                UNION ALL
+                SELECT receipt_date, 'receipt', 2, id, receipt_number, 0.0, amount
+                FROM supplier_payment_receipts WHERE supplier_id = ?
+            )
+            WHERE (? IS NULL OR entry_date <= ?)
+            ORDER BY entry_date ASC, sort_order ASC, reference_id ASC
+        "#)
+        .bind(supplier_id)
+        .bind(supplier_id)
+        .bind(supplier_id)
+        .bind(end_date)
+        .bind(end_date)
+        .fetch_all(&db.pool)
+        .await?;
+
+        let mut opening_balance = 0.0;
+        let mut total_purchases = 0.0;
+        let mut total_payments = 0.0;
+        let mut entries = Vec::new();
+        for row in rows {
+            let entry_date: NaiveDate = row.get("entry_date");
+            let purchase_amount: f64 = row.get("purchase_amount");
+            let payment_amount: f64 = row.get("payment_amount");
+
+            if start_date.is_some_and(|start| entry_date < start) {
+                opening_balance += purchase_amount - payment_amount;
+                continue;
+            }
+
+            total_purchases += purchase_amount;
+            total_payments += payment_amount;
+            entries.push(SupplierStatementEntry {
+                entry_date,
+                entry_type: row.get("entry_type"),
+                reference_id: row.get("reference_id"),
+                reference_no: row.get("reference_no"),
+                purchase_amount,
+                payment_amount,
+                balance: opening_balance + total_purchases - total_payments,
+            });
+        }
+
+        Ok(Some(SupplierStatement {
+            supplier_id,
+            supplier_name,
+            start_date,
+            end_date,
+            opening_balance,
+            total_purchases,
+            total_payments,
+            closing_balance: opening_balance + total_purchases - total_payments,
+            entries,
+        }))
+    }
+
+    // Supplier statement as CSV for accounting imports; the opening balance is the first line
+    pub async fn export_statement_csv(&self, db: &Database, supplier_id: i64, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>, format: &CsvFormat) -> Result<Option<String>> {
+        let statement = match self.get_statement(db, supplier_id, start_date, end_date).await? {
+            Some(statement) => statement,
+            None => return Ok(None),
+        };
+
+        let mut csv_content = format.header(&["التاريخ", "النوع", "المرجع", "مشتريات", "مدفوعات", "الرصيد"]);
+        csv_content.push_str(&format.row(&[
+            start_date.map(|date| format.date(date)).unwrap_or_default(),
+            format.text("opening_balance"),
+            String::new(),
+            String::new(),
+            String::new(),
+            format.number(statement.opening_balance),
+        ]));
+        for entry in &statement.entries {
+            csv_content.push_str(&format.row(&[
+                format.date(entry.entry_date),
+                format.text(&entry.entry_type),
+                format.text(&entry.reference_no),
+                format.number(entry.purchase_amount),
+                format.number(entry.payment_amount),
+                format.number(entry.balance),
+            ]));
+        }
+
+        Ok(Some(format.finish(csv_content)))
+    }
+
     // Search suppliers
+    // Link many products to a supplier at once; products already linked keep their existing terms
+    pub async fn bulk_link_products(&self, db: &Database, supplier_id: i64, product_ids: Vec<i64>, default_price: Option<f64>, lead_time: Option<i64>) -> Result<BulkLinkProductsResult> {
+        if product_ids.is_empty() {
+            return Err(anyhow::anyhow!("يجب تحديد منتج واحد على الأقل"));
+        }
+        if default_price.is_some_and(|price| price < 0.0) {
+            return Err(anyhow::anyhow!("سعر المورد لا يمكن أن يكون سالباً"));
+        }
+        if lead_time.is_some_and(|days| days < 0) {
+            return Err(anyhow::anyhow!("مدة التوريد لا يمكن أن تكون سالبة"));
+        }
+        if self.get_by_id(db, supplier_id).await?.is_none() {
+            return Err(anyhow::anyhow!("المورد غير موجود"));
+        }
+
+        let mut product_ids = product_ids;
+        product_ids.sort_unstable();
+        product_ids.dedup();
+
+        let mut linked = Vec::new();
+        let mut already_linked = Vec::new();
+        let mut tx = db.pool.begin().await?;
+        for product_id in product_ids {
+            let exists = sqlx::query("SELECT id FROM products WHERE id = ?")
+                .bind(product_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if exists.is_none() {
+                return Err(anyhow::anyhow!("المنتج رقم {} غير موجود", product_id));
+            }
+
+            let result = sqlx::query(r#"
+                INSERT OR IGNORE INTO product_suppliers (product_id, supplier_id, supplier_price, lead_time, created_at, updated_at)
+                VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            "#)
+            .bind(product_id)
+            .bind(supplier_id)
+            .bind(default_price)
+            .bind(lead_time)
+            .execute(&mut *tx)
+            .await?;
+
+            if result.rows_affected() > 0 {
+                linked.push(product_id);
+            } else {
+                already_linked.push(product_id);
+            }
+        }
+        tx.commit().await?;
+
+        info!("Linked {} products to supplier {} ({} already linked)", linked.len(), supplier_id, already_linked.len());
+        Ok(BulkLinkProductsResult {
+            supplier_id,
+            linked,
+            already_linked,
+        })
+    }
+
     pub async fn search(&self, db: &Database, query: &str) -> Result<Vec<Supplier>> {
         if query.trim().is_empty() {
             return Err(anyhow::anyhow!("يجب إدخال مطلوب البحث"));
@@ -420,3 +584,152 @@ impl SupplierService {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::utils::{CsvDateFormat, CsvFormatQuery};
+
+    // Purchases and receipts are written directly: the statement only reads the rows
+    async fn purchase(db: &Database, supplier_id: i64, invoice_no: &str, date: &str, net: f64, paid: f64, status: &str) {
+        sqlx::query("INSERT INTO purchases (supplier_id, invoice_no, invoice_date, total_amount, net_amount, paid_amount, status) VALUES (?, ?, ?, ?, ?, ?, ?)")
+            .bind(supplier_id)
+            .bind(invoice_no)
+            .bind(date)
+            .bind(net)
+            .bind(net)
+            .bind(paid)
+            .bind(status)
+            .execute(&db.pool).await.unwrap();
+    }
+
+    async fn receipt(db: &Database, supplier_id: i64, receipt_number: &str, date: &str, amount: f64) {
+        sqlx::query("INSERT INTO supplier_payment_receipts (receipt_number, supplier_id, receipt_date, amount, payment_method) VALUES (?, ?, ?, ?, 'cash')")
+            .bind(receipt_number)
+            .bind(supplier_id)
+            .bind(date)
+            .bind(amount)
+            .execute(&db.pool).await.unwrap();
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn statement_runs_a_balance_from_the_opening_amount() {
+        let db = TestDatabase::new().await;
+        let supplier_id = sqlx::query("INSERT INTO suppliers (name, contact_person) VALUES ('مطبعة النور', 'Huda')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+
+        purchase(&db, supplier_id, "P-OLD", "2026-01-20", 300.0, 100.0, "completed").await;
+        purchase(&db, supplier_id, "P-100", "2026-02-03", 1200.0, 0.0, "completed").await;
+        purchase(&db, supplier_id, "P-101", "2026-02-05", 900.0, 0.0, "cancelled").await;
+        receipt(&db, supplier_id, "SR-1", "2026-02-10", 450.0).await;
+        receipt(&db, supplier_id, "SR-2", "2026-03-02", 50.0).await;
+
+        let statement = SupplierService::new()
+            .get_statement(&db, supplier_id, Some(date("2026-02-01")), Some(date("2026-02-28")))
+            .await.unwrap()
+            .expect("supplier exists");
+
+        // January's purchase less what was paid on it carries in; March's receipt is out of range
+        assert_eq!(statement.opening_balance, 200.0);
+        assert_eq!(statement.total_purchases, 1200.0);
+        assert_eq!(statement.total_payments, 450.0);
+        assert_eq!(statement.closing_balance, 950.0);
+
+        let ledger: Vec<(&str, f64)> = statement.entries.iter()
+            .map(|entry| (entry.entry_type.as_str(), entry.balance))
+            .collect();
+        assert_eq!(ledger, vec![("purchase", 1400.0), ("receipt", 950.0)]);
+    }
+
+    #[tokio::test]
+    async fn statement_csv_follows_the_configured_separators_and_dates() {
+        let db = TestDatabase::new().await;
+        let supplier_id = sqlx::query("INSERT INTO suppliers (name, contact_person) VALUES ('Al-Rafidain Paints', 'Kareem')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        purchase(&db, supplier_id, "RP-7", "2026-04-03", 1234.5, 0.0, "completed").await;
+        sqlx::query("UPDATE settings SET csv_delimiter = ';', csv_decimal_separator = ',', date_format = 'DD/MM/YYYY' WHERE id = 1")
+            .execute(&db.pool).await.unwrap();
+        let service = SupplierService::new();
+        let (start, end) = (Some(date("2026-04-01")), Some(date("2026-04-30")));
+
+        let format = CsvFormat::load(&db.pool, &CsvFormatQuery::default()).await.unwrap();
+        let csv = service.export_statement_csv(&db, supplier_id, start, end, &format).await.unwrap().unwrap();
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert!(lines[0].starts_with("\u{FEFF}التاريخ;النوع;"));
+        assert_eq!(lines[1], "01/04/2026;opening_balance;;;;0,00");
+        assert_eq!(lines[2], "03/04/2026;purchase;RP-7;1234,50;0,00;1234,50");
+
+        // Query parameters win over the settings
+        let overrides = CsvFormatQuery {
+            delimiter: Some("|".to_string()),
+            decimal_separator: Some(".".to_string()),
+            date_format: Some(CsvDateFormat::Iso),
+            decimals: Some(0),
+            bom: Some(false),
+        };
+        let format = CsvFormat::load(&db.pool, &overrides).await.unwrap();
+        let csv = service.export_statement_csv(&db, supplier_id, start, end, &format).await.unwrap().unwrap();
+        assert!(csv.starts_with("التاريخ|"));
+        assert!(csv.contains("\r\n2026-04-03|purchase|RP-7|1235|0|1235\r\n"));
+    }
+
+    #[tokio::test]
+    async fn unknown_supplier_has_no_statement() {
+        let db = TestDatabase::new().await;
+        assert!(SupplierService::new().get_statement(&db, 404, None, None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn bulk_link_adds_new_links_and_keeps_existing_terms() {
+        let db = TestDatabase::new().await;
+        let service = SupplierService::new();
+        let supplier_id = sqlx::query("INSERT INTO suppliers (name, contact_person) VALUES ('Dijla Paper', 'Sami')")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        let mut products = Vec::new();
+        for sku in ["A4-80G", "A3-80G", "NOTE-100"] {
+            products.push(sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price) VALUES (?, ?, 5, 7, 6)")
+                .bind(sku)
+                .bind(sku)
+                .execute(&db.pool).await.unwrap()
+                .last_insert_rowid());
+        }
+        sqlx::query("INSERT INTO product_suppliers (product_id, supplier_id, supplier_price, lead_time) VALUES (?, ?, 4.5, 3)")
+            .bind(products[0])
+            .bind(supplier_id)
+            .execute(&db.pool).await.unwrap();
+
+        // Repeated ids in the request are linked once
+        let result = service
+            .bulk_link_products(&db, supplier_id, vec![products[2], products[0], products[1], products[2]], Some(4.0), Some(7))
+            .await.unwrap();
+        assert_eq!(result.linked, vec![products[1], products[2]]);
+        assert_eq!(result.already_linked, vec![products[0]]);
+
+        let links: Vec<(i64, f64, i64)> = sqlx::query_as(
+            "SELECT product_id, CAST(supplier_price AS REAL), lead_time FROM product_suppliers WHERE supplier_id = ? ORDER BY product_id"
+        )
+        .bind(supplier_id)
+        .fetch_all(&db.pool).await.unwrap();
+        assert_eq!(links, vec![(products[0], 4.5, 3), (products[1], 4.0, 7), (products[2], 4.0, 7)]);
+
+        // An unknown product aborts the whole batch
+        let pens = sqlx::query("INSERT INTO products (name, sku, purchase_price, selling_price, wholesale_price) VALUES ('Pens', 'PEN-BLUE', 1, 2, 2)")
+            .execute(&db.pool).await.unwrap()
+            .last_insert_rowid();
+        assert!(service.bulk_link_products(&db, supplier_id, vec![pens, 9999], None, None).await.is_err());
+        let pen_links: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM product_suppliers WHERE product_id = ?")
+            .bind(pens)
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(pen_links, 0);
+        assert!(service.bulk_link_products(&db, supplier_id, vec![], None, None).await.is_err());
+        assert!(service.bulk_link_products(&db, supplier_id + 1, vec![products[1]], None, None).await.is_err());
+    }
+}
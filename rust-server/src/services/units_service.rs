@@ -0,0 +1,307 @@
+use anyhow::Result;
+use sqlx::Row;
+use tracing::info;
+use crate::database::Database;
+use crate::models::unit::*;
+
+#[derive(Clone)]
+pub struct UnitsService;
+
+impl UnitsService {
+    // Guards against cycles in base_unit_id chains
+    const MAX_UNIT_DEPTH: usize = 8;
+
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub async fn get_all(&self, db: &Database) -> Result<Vec<Unit>> {
+        let units = sqlx::query_as::<_, Unit>("SELECT * FROM units ORDER BY name")
+            .fetch_all(&db.pool)
+            .await?;
+
+        Ok(units)
+    }
+
+    pub async fn get_by_id(&self, db: &Database, id: i64) -> Result<Option<Unit>> {
+        let unit = sqlx::query_as::<_, Unit>("SELECT * FROM units WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&db.pool)
+            .await?;
+
+        Ok(unit)
+    }
+
+    async fn get_by_name(&self, db: &Database, name: &str) -> Result<Option<Unit>> {
+        let unit = sqlx::query_as::<_, Unit>("SELECT * FROM units WHERE name = ?")
+            .bind(name.trim())
+            .fetch_optional(&db.pool)
+            .await?;
+
+        Ok(unit)
+    }
+
+    pub async fn create(&self, db: &Database, payload: CreateUnitRequest) -> Result<Unit> {
+        let name = payload.name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("اسم الوحدة مطلوب"));
+        }
+        if self.get_by_name(db, name).await?.is_some() {
+            return Err(anyhow::anyhow!("الوحدة موجودة مسبقاً"));
+        }
+        let conversion_factor = payload.conversion_factor.unwrap_or(1.0);
+        Self::validate_conversion(payload.base_unit_id, conversion_factor)?;
+        if let Some(base_id) = payload.base_unit_id {
+            if self.get_by_id(db, base_id).await?.is_none() {
+                return Err(anyhow::anyhow!("الوحدة الأساسية غير موجودة"));
+            }
+        }
+
+        let id = sqlx::query(r#"
+            INSERT INTO units (name, symbol, base_unit_id, conversion_factor, is_active, created_at, updated_at)
+            VALUES (?, ?, ?, ?, 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        "#)
+        .bind(name)
+        .bind(&payload.symbol)
+        .bind(payload.base_unit_id)
+        .bind(conversion_factor)
+        .execute(&db.pool)
+        .await?
+        .last_insert_rowid();
+
+        info!("Unit created: {}", name);
+        self.get_by_id(db, id).await?.ok_or_else(|| anyhow::anyhow!("فشل في إنشاء الوحدة"))
+    }
+
+    pub async fn update(&self, db: &Database, id: i64, payload: UpdateUnitRequest) -> Result<Unit> {
+        let existing = self.get_by_id(db, id).await?
+            .ok_or_else(|| anyhow::anyhow!("الوحدة غير موجودة"))?;
+
+        let name = payload.name.as_deref().map(str::trim).unwrap_or(&existing.name).to_string();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("اسم الوحدة مطلوب"));
+        }
+        if let Some(other) = self.get_by_name(db, &name).await? {
+            if other.id != id {
+                return Err(anyhow::anyhow!("الوحدة موجودة مسبقاً"));
+            }
+        }
+
+        let base_unit_id = payload.base_unit_id.or(existing.base_unit_id);
+        let conversion_factor = payload.conversion_factor.unwrap_or(existing.conversion_factor);
+        Self::validate_conversion(base_unit_id, conversion_factor)?;
+        if let Some(base_id) = base_unit_id {
+            // The new base must not lead back to this unit
+            let chain = self.base_chain(db, base_id).await?;
+            if base_id == id || chain.iter().any(|(unit_id, _)| *unit_id == id) {
+                return Err(anyhow::anyhow!("لا يمكن أن تكون الوحدة أساساً لنفسها"));
+            }
+        }
+
+        let mut tx = db.pool.begin().await?;
+        sqlx::query(r#"
+            UPDATE units SET name = ?, symbol = ?, base_unit_id = ?, conversion_factor = ?, is_active = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+        "#)
+        .bind(&name)
+        .bind(payload.symbol.as_ref().or(existing.symbol.as_ref()))
+        .bind(base_unit_id)
+        .bind(conversion_factor)
+        .bind(payload.is_active.unwrap_or(existing.is_active))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Keep products pointing at the renamed unit
+        if name != existing.name {
+            sqlx::query("UPDATE products SET unit = ?, updated_at = CURRENT_TIMESTAMP WHERE unit = ?")
+                .bind(&name)
+                .bind(&existing.name)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        self.get_by_id(db, id).await?.ok_or_else(|| anyhow::anyhow!("الوحدة غير موجودة"))
+    }
+
+    pub async fn delete(&self, db: &Database, id: i64) -> Result<bool> {
+        let unit = match self.get_by_id(db, id).await? {
+            Some(unit) => unit,
+            None => return Ok(false),
+        };
+
+        let in_use = sqlx::query(r#"
+            SELECT
+                (SELECT COUNT(*) FROM products WHERE unit = ?) as products,
+                (SELECT COUNT(*) FROM units WHERE base_unit_id = ?) as derived
+        "#)
+        .bind(&unit.name)
+        .bind(id)
+        .fetch_one(&db.pool)
+        .await?;
+        if in_use.get::<i64, _>("products") > 0 {
+            return Err(anyhow::anyhow!("لا يمكن حذف وحدة مستخدمة في منتجات"));
+        }
+        if in_use.get::<i64, _>("derived") > 0 {
+            return Err(anyhow::anyhow!("لا يمكن حذف وحدة أساسية لوحدات أخرى"));
+        }
+
+        let result = sqlx::query("DELETE FROM units WHERE id = ?")
+            .bind(id)
+            .execute(&db.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Convert between two units that share the same root unit
+    pub async fn convert(&self, db: &Database, from: &str, to: &str, quantity: f64) -> Result<f64> {
+        let from_unit = self.get_by_name(db, from).await?
+            .ok_or_else(|| anyhow::anyhow!("الوحدة {} غير موجودة", from))?;
+        let to_unit = self.get_by_name(db, to).await?
+            .ok_or_else(|| anyhow::anyhow!("الوحدة {} غير موجودة", to))?;
+
+        let (from_root, from_factor) = self.to_root(db, &from_unit).await?;
+        let (to_root, to_factor) = self.to_root(db, &to_unit).await?;
+        if from_root != to_root {
+            return Err(anyhow::anyhow!("لا يوجد تحويل معرف بين {} و {}", from, to));
+        }
+
+        Ok(quantity * from_factor / to_factor)
+    }
+
+    // Reject units missing from the catalog when settings.strict_units is on
+    pub async fn ensure_allowed(db: &Database, unit: &str) -> Result<()> {
+        let strict: i64 = sqlx::query("SELECT COALESCE(strict_units, 0) as strict FROM settings WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await?
+            .map(|row| row.get("strict"))
+            .unwrap_or(0);
+        if strict == 0 {
+            return Ok(());
+        }
+
+        let exists = sqlx::query("SELECT id FROM units WHERE name = ? AND is_active = 1")
+            .bind(unit.trim())
+            .fetch_optional(&db.pool)
+            .await?;
+        if exists.is_none() {
+            return Err(anyhow::anyhow!("الوحدة {} غير معرفة في قائمة الوحدات", unit));
+        }
+
+        Ok(())
+    }
+
+    fn validate_conversion(base_unit_id: Option<i64>, conversion_factor: f64) -> Result<()> {
+        if !conversion_factor.is_finite() || conversion_factor <= 0.0 {
+            return Err(anyhow::anyhow!("معامل التحويل يجب أن يكون أكبر من صفر"));
+        }
+        if base_unit_id.is_none() && conversion_factor != 1.0 {
+            return Err(anyhow::anyhow!("معامل التحويل يتطلب تحديد وحدة أساسية"));
+        }
+        Ok(())
+    }
+
+    // (id, conversion_factor) of each ancestor starting at base_id
+    async fn base_chain(&self, db: &Database, base_id: i64) -> Result<Vec<(i64, f64)>> {
+        let mut chain = Vec::new();
+        let mut next = Some(base_id);
+        while let Some(id) = next {
+            if chain.len() >= Self::MAX_UNIT_DEPTH {
+                return Err(anyhow::anyhow!("سلسلة تحويل الوحدات طويلة جداً"));
+            }
+            let unit = self.get_by_id(db, id).await?
+                .ok_or_else(|| anyhow::anyhow!("الوحدة الأساسية غير موجودة"))?;
+            chain.push((unit.id, unit.conversion_factor));
+            next = unit.base_unit_id;
+        }
+        Ok(chain)
+    }
+
+    // Root unit id and how many root units one `unit` holds
+    async fn to_root(&self, db: &Database, unit: &Unit) -> Result<(i64, f64)> {
+        match unit.base_unit_id {
+            None => Ok((unit.id, 1.0)),
+            Some(base_id) => {
+                let chain = self.base_chain(db, base_id).await?;
+                let root = chain.last().map(|(id, _)| *id).unwrap_or(base_id);
+                let factor = chain.iter().fold(unit.conversion_factor, |acc, (_, factor)| acc * factor);
+                Ok((root, factor))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TestDatabase;
+    use crate::services::ProductService;
+
+    fn unit(name: &str, base_unit_id: Option<i64>, conversion_factor: Option<f64>) -> CreateUnitRequest {
+        CreateUnitRequest { name: name.into(), symbol: None, base_unit_id, conversion_factor }
+    }
+
+    async fn create_product(db: &Database, sku: &str, unit: &str) -> Result<()> {
+        let payload = serde_json::from_value(serde_json::json!({
+            "name": format!("Tea {sku}"),
+            "sku": sku,
+            "purchase_price": 1500.0,
+            "selling_price": 2000.0,
+            "wholesale_price": 1800.0,
+            "unit": unit
+        })).unwrap();
+        ProductService::new().create(db, payload).await.map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn strict_mode_rejects_products_with_undefined_units() {
+        let db = TestDatabase::new().await;
+
+        // Off by default: any text is accepted
+        create_product(&db, "TEA-1", "كيس").await.unwrap();
+
+        sqlx::query("UPDATE settings SET strict_units = 1 WHERE id = 1").execute(&db.pool).await.unwrap();
+        let err = create_product(&db, "TEA-2", "علبة").await.unwrap_err();
+        assert!(err.to_string().contains("غير معرفة"), "{err}");
+        create_product(&db, "TEA-3", "قطعة").await.unwrap();
+
+        UnitsService::new().create(&db, unit("علبة", None, None)).await.unwrap();
+        create_product(&db, "TEA-2", "علبة").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn conversions_follow_the_base_unit_chain() {
+        let db = TestDatabase::new().await;
+        let service = UnitsService::new();
+        let piece = service.get_all(&db).await.unwrap().into_iter().find(|u| u.name == "قطعة").expect("seeded");
+        let box_unit = service.create(&db, unit("علبة", Some(piece.id), Some(12.0))).await.unwrap();
+        service.create(&db, unit("كرتون", Some(box_unit.id), Some(4.0))).await.unwrap();
+        service.create(&db, unit("كغم", None, None)).await.unwrap();
+
+        assert_eq!(service.convert(&db, "كرتون", "قطعة", 2.0).await.unwrap(), 96.0);
+        assert_eq!(service.convert(&db, "قطعة", "علبة", 30.0).await.unwrap(), 2.5);
+        assert!(service.convert(&db, "كغم", "قطعة", 1.0).await.is_err());
+
+        // A unit can't become the base of its own ancestor
+        let cycle = UpdateUnitRequest { name: None, symbol: None, base_unit_id: Some(box_unit.id), conversion_factor: Some(2.0), is_active: None };
+        assert!(service.update(&db, piece.id, cycle).await.is_err());
+        assert!(service.create(&db, unit("ربطة", None, Some(6.0))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn renaming_follows_products_and_units_in_use_are_kept() {
+        let db = TestDatabase::new().await;
+        let service = UnitsService::new();
+        let jar = service.create(&db, unit("برطمان", None, None)).await.unwrap();
+        create_product(&db, "HONEY-1", "برطمان").await.unwrap();
+
+        assert!(service.delete(&db, jar.id).await.is_err());
+        let rename = UpdateUnitRequest { name: Some("قنينة".into()), symbol: None, base_unit_id: None, conversion_factor: None, is_active: None };
+        service.update(&db, jar.id, rename).await.unwrap();
+        let product_unit: String = sqlx::query_scalar("SELECT unit FROM products WHERE sku = 'HONEY-1'")
+            .fetch_one(&db.pool).await.unwrap();
+        assert_eq!(product_unit, "قنينة");
+    }
+}
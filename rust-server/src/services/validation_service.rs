@@ -1,4 +1,26 @@
-use anyhow::Result;
+use crate::models::sale::{CreateSaleItemRequest, CreateSaleRequest, UpdateSaleRequest};
+use serde::Serialize;
+
+pub const PAYMENT_METHODS: &[&str] = &["cash", "card", "bank_transfer"];
+pub const PAYMENT_STATUSES: &[&str] = &["paid", "unpaid", "partial"];
+
+// One invalid input, addressed by its JSON path (e.g. `items[2].quantity`) so the UI can highlight it
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct ValidationService;
@@ -7,4 +29,156 @@ impl ValidationService {
     pub fn new() -> Self {
         Self
     }
+
+    // Shape checks that need no database; every problem is collected rather than stopping at the first
+    pub fn validate_create_sale(&self, req: &CreateSaleRequest) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if req.invoice_date.is_none() {
+            errors.push(FieldError::new("invoice_date", "required", "تاريخ الفاتورة مطلوب"));
+        }
+        if req.customer_id.is_none() && !req.is_anonymous.unwrap_or(false) {
+            errors.push(FieldError::new("customer_id", "required", "العميل مطلوب للفواتير غير المجهولة"));
+        }
+        if req.items.is_empty() {
+            errors.push(FieldError::new("items", "required", "يجب أن تحتوي الفاتورة على صنف واحد على الأقل"));
+        }
+        for (index, item) in req.items.iter().enumerate() {
+            errors.extend(self.sale_item_errors(index, item));
+        }
+        self.check_sale_amounts(&mut errors, req.paid_amount, req.discount_amount, req.tax_amount);
+        self.check_payment_fields(&mut errors, req.payment_method.as_deref(), req.payment_status.as_deref());
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    pub fn validate_update_sale(&self, req: &UpdateSaleRequest) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if req.invoice_date.is_none() {
+            errors.push(FieldError::new("invoice_date", "required", "تاريخ الفاتورة مطلوب"));
+        }
+        if req.customer_id.is_none() {
+            errors.push(FieldError::new("customer_id", "required", "العميل مطلوب"));
+        }
+        if let Some(ref items) = req.items {
+            if items.is_empty() {
+                errors.push(FieldError::new("items", "required", "يجب أن تحتوي الفاتورة على صنف واحد على الأقل"));
+            }
+            for (index, item) in items.iter().enumerate() {
+                errors.extend(self.sale_item_errors(index, item));
+            }
+        }
+        self.check_sale_amounts(&mut errors, req.paid_amount, req.discount_amount, req.tax_amount);
+        self.check_payment_fields(&mut errors, req.payment_method.as_deref(), req.payment_status.as_deref());
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    // Quantity, price and percentage ranges for one sale line. Zero prices pass here; whether they are
+    // allowed depends on settings and permissions, which SaleService checks.
+    pub fn sale_item_errors(&self, index: usize, item: &CreateSaleItemRequest) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        let field = |name: &str| format!("items[{}].{}", index, name);
+
+        if item.quantity <= 0 {
+            errors.push(FieldError::new(field("quantity"), "out_of_range", "الكمية يجب أن تكون أكبر من صفر"));
+        }
+        if !item.price.is_finite() || item.price < 0.0 {
+            errors.push(FieldError::new(field("price"), "out_of_range", "السعر لا يمكن أن يكون سالباً"));
+        }
+        if let Some(discount) = item.discount_percent {
+            if !(0.0..=100.0).contains(&discount) {
+                errors.push(FieldError::new(field("discount_percent"), "out_of_range", "نسبة الخصم يجب أن تكون بين 0 و 100"));
+            }
+        }
+        if let Some(tax) = item.tax_percent {
+            if !(0.0..=100.0).contains(&tax) {
+                errors.push(FieldError::new(field("tax_percent"), "out_of_range", "نسبة الضريبة يجب أن تكون بين 0 و 100"));
+            }
+        }
+        errors
+    }
+
+    fn check_sale_amounts(&self, errors: &mut Vec<FieldError>, paid: Option<f64>, discount: Option<f64>, tax: Option<f64>) {
+        for (field, value) in [("paid_amount", paid), ("discount_amount", discount), ("tax_amount", tax)] {
+            if let Some(value) = value {
+                if !value.is_finite() || value < 0.0 {
+                    errors.push(FieldError::new(field, "out_of_range", "المبلغ لا يمكن أن يكون سالباً"));
+                }
+            }
+        }
+    }
+
+    fn check_payment_fields(&self, errors: &mut Vec<FieldError>, method: Option<&str>, status: Option<&str>) {
+        if let Some(method) = method {
+            if !PAYMENT_METHODS.contains(&method) {
+                errors.push(FieldError::new(
+                    "payment_method",
+                    "invalid_value",
+                    format!("طريقة الدفع غير صالحة، القيم المسموحة: {}", PAYMENT_METHODS.join(", ")),
+                ));
+            }
+        }
+        if let Some(status) = status {
+            if !PAYMENT_STATUSES.contains(&status) {
+                errors.push(FieldError::new(
+                    "payment_status",
+                    "invalid_value",
+                    format!("حالة الدفع غير صالحة، القيم المسموحة: {}", PAYMENT_STATUSES.join(", ")),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn codes(errors: &[FieldError]) -> Vec<(&str, &str)> {
+        errors.iter().map(|error| (error.field.as_str(), error.code.as_str())).collect()
+    }
+
+    #[test]
+    fn every_invalid_sale_field_is_reported_together() {
+        let req: CreateSaleRequest = serde_json::from_value(json!({
+            "items": [
+                { "product_id": 1, "quantity": 2, "price": 1500.0 },
+                { "product_id": 2, "quantity": 0, "price": -5.0, "discount_percent": 120.0 },
+                { "product_id": 3, "quantity": 1, "price": 250.0, "tax_percent": -1.0 }
+            ],
+            "paid_amount": -100.0,
+            "payment_method": "bitcoin",
+            "payment_status": "paid"
+        })).unwrap();
+
+        let errors = ValidationService::new().validate_create_sale(&req).unwrap_err();
+        assert_eq!(codes(&errors), [
+            ("invoice_date", "required"),
+            ("customer_id", "required"),
+            ("items[1].quantity", "out_of_range"),
+            ("items[1].price", "out_of_range"),
+            ("items[1].discount_percent", "out_of_range"),
+            ("items[2].tax_percent", "out_of_range"),
+            ("paid_amount", "out_of_range"),
+            ("payment_method", "invalid_value"),
+        ]);
+        assert!(errors.iter().all(|error| !error.message.is_empty()));
+    }
+
+    #[test]
+    fn an_anonymous_sale_without_items_only_misses_its_items() {
+        let req: CreateSaleRequest = serde_json::from_value(json!({
+            "invoice_date": "2026-10-15",
+            "is_anonymous": true,
+            "items": [],
+            "payment_method": "cash",
+            "payment_status": "paid"
+        })).unwrap();
+
+        let errors = ValidationService::new().validate_create_sale(&req).unwrap_err();
+        assert_eq!(codes(&errors), [("items", "required")]);
+    }
 }
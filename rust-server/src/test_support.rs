@@ -0,0 +1,124 @@
+// Drives the full router (middleware included) against a temp database, for route-level tests
+use axum::{
+    body::Body,
+    http::{header::{AUTHORIZATION, CONTENT_TYPE}, Method, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use std::sync::atomic::Ordering;
+use tower::Service;
+
+use crate::database::TestDatabase;
+use crate::{app_router, AppState};
+
+pub struct TestApp {
+    pub db: TestDatabase,
+    pub state: AppState,
+    router: Router,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        let db = TestDatabase::new().await;
+        let state = AppState::new(db.db.clone());
+        state.ready.store(true, Ordering::Release);
+        let router = app_router(state.clone());
+        Self { db, state, router }
+    }
+
+    // Active user whose password is "secret", with `permissions` granted directly. Hashed at the
+    // lowest bcrypt cost so logins stay fast.
+    pub async fn add_user(&self, username: &str, role: &str, permissions: &[&str]) -> i64 {
+        let password = bcrypt::hash("secret", 4).unwrap();
+        let user_id = sqlx::query("INSERT INTO users (username, password, name, role) VALUES (?, ?, ?, ?)")
+            .bind(username)
+            .bind(password)
+            .bind(username)
+            .bind(role)
+            .execute(&self.db.pool).await.unwrap()
+            .last_insert_rowid();
+        for permission in permissions {
+            sqlx::query("INSERT INTO user_permissions (user_id, permission_id) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(permission)
+                .execute(&self.db.pool).await.unwrap();
+        }
+        user_id
+    }
+
+    pub async fn login(&self, username: &str) -> String {
+        let (status, body) = self.request(Method::POST, "/api/auth/login", None, Some(json!({
+            "username": username,
+            "password": "secret"
+        }))).await;
+        assert_eq!(status, StatusCode::OK, "login failed: {body}");
+        body["data"]["token"].as_str().expect("token").to_string()
+    }
+
+    pub async fn request(&self, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let (status, _, bytes) = self.request_raw(method, uri, token, body).await;
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    // For responses that are not JSON (images, CSV, PDFs): status, Content-Type and the body as sent
+    pub async fn request_raw(&self, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Option<String>, Vec<u8>) {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            builder = builder.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let request = match body {
+            Some(body) => builder.header(CONTENT_TYPE, "application/json").body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }.unwrap();
+
+        let response = self.router.clone().call(request).await.unwrap();
+        let status = response.status();
+        let content_type = response.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).map(str::to_string);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, bytes.to_vec())
+    }
+}
+
+// sqlx statement events (target "sqlx::query", info and above) with the thread that ran them. Each SQLite
+// connection runs on its own worker thread, so only a process-wide subscriber sees them; tests tell their
+// own connections apart by giving them a thread name.
+#[derive(Debug, Clone)]
+pub struct SqlEvent {
+    pub thread: String,
+    pub level: tracing::Level,
+    pub fields: String,
+}
+
+static SQL_EVENTS: std::sync::OnceLock<std::sync::Arc<std::sync::Mutex<Vec<SqlEvent>>>> = std::sync::OnceLock::new();
+
+struct SqlEventLayer(std::sync::Arc<std::sync::Mutex<Vec<SqlEvent>>>);
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SqlEventLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+        struct Fields(String);
+        impl tracing::field::Visit for Fields {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0.push_str(&format!("{}={:?} ", field.name(), value));
+            }
+        }
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        self.0.lock().unwrap().push(SqlEvent {
+            thread: std::thread::current().name().unwrap_or_default().to_string(),
+            level: *event.metadata().level(),
+            fields: fields.0,
+        });
+    }
+}
+
+pub fn sql_events() -> Vec<SqlEvent> {
+    let events = SQL_EVENTS.get_or_init(|| {
+        use tracing_subscriber::{layer::SubscriberExt, Layer};
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let filter = tracing_subscriber::filter::Targets::new().with_target("sqlx::query", tracing::Level::INFO);
+        let subscriber = tracing_subscriber::registry().with(SqlEventLayer(events.clone()).with_filter(filter));
+        tracing::subscriber::set_global_default(subscriber).expect("no other global subscriber in tests");
+        events
+    });
+    events.lock().unwrap().clone()
+}
@@ -0,0 +1,225 @@
+// Arabic text for outputs without an OpenType shaper (PDF text, printers): letters are replaced by
+// their contextual presentation forms and each line is put into visual (left-to-right drawing) order.
+
+#[derive(Clone, Copy, PartialEq)]
+enum Joining {
+    Dual,  // joins on both sides (ب)
+    Right, // joins only to the preceding letter (ا، د، ر، و)
+    Causing, // tatweel
+}
+
+// (letter, joining, isolated, final, initial, medial)
+const FORMS: &[(char, Joining, u32, u32, u32, u32)] = &[
+    ('\u{0621}', Joining::Right, 0xFE80, 0, 0, 0),
+    ('\u{0622}', Joining::Right, 0xFE81, 0xFE82, 0, 0),
+    ('\u{0623}', Joining::Right, 0xFE83, 0xFE84, 0, 0),
+    ('\u{0624}', Joining::Right, 0xFE85, 0xFE86, 0, 0),
+    ('\u{0625}', Joining::Right, 0xFE87, 0xFE88, 0, 0),
+    ('\u{0626}', Joining::Dual, 0xFE89, 0xFE8A, 0xFE8B, 0xFE8C),
+    ('\u{0627}', Joining::Right, 0xFE8D, 0xFE8E, 0, 0),
+    ('\u{0628}', Joining::Dual, 0xFE8F, 0xFE90, 0xFE91, 0xFE92),
+    ('\u{0629}', Joining::Right, 0xFE93, 0xFE94, 0, 0),
+    ('\u{062A}', Joining::Dual, 0xFE95, 0xFE96, 0xFE97, 0xFE98),
+    ('\u{062B}', Joining::Dual, 0xFE99, 0xFE9A, 0xFE9B, 0xFE9C),
+    ('\u{062C}', Joining::Dual, 0xFE9D, 0xFE9E, 0xFE9F, 0xFEA0),
+    ('\u{062D}', Joining::Dual, 0xFEA1, 0xFEA2, 0xFEA3, 0xFEA4),
+    ('\u{062E}', Joining::Dual, 0xFEA5, 0xFEA6, 0xFEA7, 0xFEA8),
+    ('\u{062F}', Joining::Right, 0xFEA9, 0xFEAA, 0, 0),
+    ('\u{0630}', Joining::Right, 0xFEAB, 0xFEAC, 0, 0),
+    ('\u{0631}', Joining::Right, 0xFEAD, 0xFEAE, 0, 0),
+    ('\u{0632}', Joining::Right, 0xFEAF, 0xFEB0, 0, 0),
+    ('\u{0633}', Joining::Dual, 0xFEB1, 0xFEB2, 0xFEB3, 0xFEB4),
+    ('\u{0634}', Joining::Dual, 0xFEB5, 0xFEB6, 0xFEB7, 0xFEB8),
+    ('\u{0635}', Joining::Dual, 0xFEB9, 0xFEBA, 0xFEBB, 0xFEBC),
+    ('\u{0636}', Joining::Dual, 0xFEBD, 0xFEBE, 0xFEBF, 0xFEC0),
+    ('\u{0637}', Joining::Dual, 0xFEC1, 0xFEC2, 0xFEC3, 0xFEC4),
+    ('\u{0638}', Joining::Dual, 0xFEC5, 0xFEC6, 0xFEC7, 0xFEC8),
+    ('\u{0639}', Joining::Dual, 0xFEC9, 0xFECA, 0xFECB, 0xFECC),
+    ('\u{063A}', Joining::Dual, 0xFECD, 0xFECE, 0xFECF, 0xFED0),
+    ('\u{0640}', Joining::Causing, 0x0640, 0x0640, 0x0640, 0x0640),
+    ('\u{0641}', Joining::Dual, 0xFED1, 0xFED2, 0xFED3, 0xFED4),
+    ('\u{0642}', Joining::Dual, 0xFED5, 0xFED6, 0xFED7, 0xFED8),
+    ('\u{0643}', Joining::Dual, 0xFED9, 0xFEDA, 0xFEDB, 0xFEDC),
+    ('\u{0644}', Joining::Dual, 0xFEDD, 0xFEDE, 0xFEDF, 0xFEE0),
+    ('\u{0645}', Joining::Dual, 0xFEE1, 0xFEE2, 0xFEE3, 0xFEE4),
+    ('\u{0646}', Joining::Dual, 0xFEE5, 0xFEE6, 0xFEE7, 0xFEE8),
+    ('\u{0647}', Joining::Dual, 0xFEE9, 0xFEEA, 0xFEEB, 0xFEEC),
+    ('\u{0648}', Joining::Right, 0xFEED, 0xFEEE, 0, 0),
+    ('\u{0649}', Joining::Right, 0xFEEF, 0xFEF0, 0, 0),
+    ('\u{064A}', Joining::Dual, 0xFEF1, 0xFEF2, 0xFEF3, 0xFEF4),
+    // Persian letters common in Iraqi Arabic and Kurdish names
+    ('\u{067E}', Joining::Dual, 0xFB56, 0xFB57, 0xFB58, 0xFB59),
+    ('\u{0686}', Joining::Dual, 0xFB7A, 0xFB7B, 0xFB7C, 0xFB7D),
+    ('\u{0698}', Joining::Right, 0xFB8A, 0xFB8B, 0, 0),
+    ('\u{06A4}', Joining::Dual, 0xFB6A, 0xFB6B, 0xFB6C, 0xFB6D),
+    ('\u{06A9}', Joining::Dual, 0xFB8E, 0xFB8F, 0xFB90, 0xFB91),
+    ('\u{06AF}', Joining::Dual, 0xFB92, 0xFB93, 0xFB94, 0xFB95),
+    ('\u{06CC}', Joining::Dual, 0xFBFC, 0xFBFD, 0xFBFE, 0xFBFF),
+];
+
+// Lam followed by an alef variant becomes one ligature: (alef, isolated, final)
+const LAM_ALEF: &[(char, u32, u32)] = &[
+    ('\u{0622}', 0xFEF5, 0xFEF6),
+    ('\u{0623}', 0xFEF7, 0xFEF8),
+    ('\u{0625}', 0xFEF9, 0xFEFA),
+    ('\u{0627}', 0xFEFB, 0xFEFC),
+];
+
+const LAM: char = '\u{0644}';
+
+fn forms(c: char) -> Option<&'static (char, Joining, u32, u32, u32, u32)> {
+    FORMS.iter().find(|entry| entry.0 == c)
+}
+
+// Harakat and other marks sit on a letter without breaking the joining around it
+fn is_transparent(c: char) -> bool {
+    matches!(c, '\u{0610}'..='\u{061A}' | '\u{064B}'..='\u{065F}' | '\u{0670}' | '\u{06D6}'..='\u{06ED}')
+}
+
+fn is_rtl(c: char) -> bool {
+    matches!(c,
+        '\u{0590}'..='\u{05FF}'
+        | '\u{0600}'..='\u{065F}'
+        | '\u{066A}'..='\u{06EF}'
+        | '\u{06FA}'..='\u{08FF}'
+        | '\u{FB1D}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}'
+    )
+}
+
+// Latin letters and digits (Arabic-Indic included) keep their left-to-right order inside RTL text
+fn is_ltr(c: char) -> bool {
+    !is_rtl(c) && c.is_alphanumeric()
+}
+
+// Replace Arabic letters by the presentation form their neighbours call for; text stays in logical order
+pub fn shape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let neighbour = |from: usize, step: isize| -> Option<char> {
+        let mut i = from as isize + step;
+        while i >= 0 && (i as usize) < chars.len() {
+            let c = chars[i as usize];
+            if !is_transparent(c) {
+                return Some(c);
+            }
+            i += step;
+        }
+        None
+    };
+
+    let mut shaped = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let Some(&(_, joining, isolated, final_form, initial, medial)) = forms(c) else {
+            shaped.push(c);
+            i += 1;
+            continue;
+        };
+
+        let joins_prev = neighbour(i, -1)
+            .and_then(forms)
+            .is_some_and(|prev| matches!(prev.1, Joining::Dual | Joining::Causing));
+
+        if c == LAM {
+            let next = (i + 1..chars.len()).find(|&j| !is_transparent(chars[j]));
+            if let Some(&(_, lig_isolated, lig_final)) = next.and_then(|j| LAM_ALEF.iter().find(|entry| entry.0 == chars[j])) {
+                let ligature = if joins_prev { lig_final } else { lig_isolated };
+                shaped.push(char::from_u32(ligature).unwrap_or(c));
+                // Marks between the lam and the alef stay with the ligature
+                let j = next.unwrap_or(i);
+                shaped.extend(chars[i + 1..j].iter());
+                i = j + 1;
+                continue;
+            }
+        }
+
+        let joins_next = matches!(joining, Joining::Dual | Joining::Causing)
+            && neighbour(i, 1).and_then(forms).is_some_and(|next| next.2 != 0xFE80);
+
+        let form = match (joins_prev, joins_next) {
+            (true, true) if medial != 0 => medial,
+            (true, _) if final_form != 0 => final_form,
+            (false, true) if initial != 0 => initial,
+            _ => isolated,
+        };
+        shaped.push(char::from_u32(form).unwrap_or(c));
+        i += 1;
+    }
+    shaped
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => c,
+    }
+}
+
+// Shape and reorder one line for left-to-right drawing. Runs of Arabic are reversed, runs of
+// Latin/digits keep their order, and neutrals (spaces, punctuation) follow the text around them,
+// falling back to the paragraph direction. A simplified form of the Unicode bidi algorithm that
+// is enough for single invoice/receipt lines.
+pub fn visual(text: &str, rtl_paragraph: bool) -> String {
+    let shaped = shape(text);
+    let chars: Vec<char> = shaped.chars().collect();
+    if !chars.iter().any(|&c| is_rtl(c)) {
+        return shaped;
+    }
+
+    // Resolved direction per character: Some(true) = RTL, Some(false) = LTR
+    let strong: Vec<Option<bool>> = chars
+        .iter()
+        .map(|&c| if is_rtl(c) { Some(true) } else if is_ltr(c) { Some(false) } else { None })
+        .collect();
+    let resolved: Vec<bool> = (0..chars.len())
+        .map(|i| {
+            strong[i].unwrap_or_else(|| {
+                let before = strong[..i].iter().rev().find_map(|d| *d);
+                let after = strong[i + 1..].iter().find_map(|d| *d);
+                match (before, after) {
+                    (Some(a), Some(b)) if a == b => a,
+                    _ => rtl_paragraph,
+                }
+            })
+        })
+        .collect();
+
+    let mut runs: Vec<(bool, Vec<char>)> = Vec::new();
+    for (&c, &dir) in chars.iter().zip(&resolved) {
+        match runs.last_mut() {
+            Some((run_dir, run)) if *run_dir == dir => run.push(c),
+            _ => runs.push((dir, vec![c])),
+        }
+    }
+    if rtl_paragraph {
+        runs.reverse();
+    }
+
+    let mut line = String::with_capacity(shaped.len());
+    for (dir, run) in runs {
+        if dir {
+            // Reverse whole clusters so marks are still drawn right after the letter they sit on
+            let mut clusters: Vec<Vec<char>> = Vec::new();
+            for c in run {
+                match clusters.last_mut() {
+                    Some(cluster) if is_transparent(c) => cluster.push(c),
+                    _ => clusters.push(vec![mirror(c)]),
+                }
+            }
+            line.extend(clusters.into_iter().rev().flatten());
+        } else {
+            line.extend(run);
+        }
+    }
+    line
+}
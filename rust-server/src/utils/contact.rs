@@ -0,0 +1,101 @@
+use std::fmt;
+
+// Malformed phone or email on a customer; routes downcast to answer 400 with the message
+#[derive(Debug)]
+pub struct InvalidContact(pub String);
+
+impl fmt::Display for InvalidContact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidContact {}
+
+const IRAQ_CODE: &str = "964";
+
+// Canonical phone form: `+` and digits only, local Iraqi numbers rewritten to +964
+// ("0770 123 4567", "770-123-4567", "00964 770 123 4567" all become "+9647701234567").
+// Blank input means no phone.
+pub fn normalize_phone(raw: &str) -> Result<Option<String>, InvalidContact> {
+    let compact: String = raw.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '(' | ')' | '.' | '/'))
+        .collect();
+    if compact.is_empty() {
+        return Ok(None);
+    }
+
+    let (has_plus, digits) = match compact.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (false, compact.as_str()),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(InvalidContact(format!("رقم الهاتف غير صالح: {}", raw.trim())));
+    }
+
+    let international = if has_plus {
+        digits.to_string()
+    } else if let Some(rest) = digits.strip_prefix("00") {
+        rest.to_string()
+    } else if digits.starts_with(IRAQ_CODE) && digits.len() > 10 {
+        digits.to_string()
+    } else if let Some(rest) = digits.strip_prefix('0') {
+        format!("{}{}", IRAQ_CODE, rest)
+    } else {
+        format!("{}{}", IRAQ_CODE, digits)
+    };
+
+    if !(8..=15).contains(&international.len()) {
+        return Err(InvalidContact(format!("رقم الهاتف غير صالح: {}", raw.trim())));
+    }
+    Ok(Some(format!("+{}", international)))
+}
+
+// Trimmed, lowercased email with a basic `local@domain.tld` shape check. Blank input means no email.
+pub fn normalize_email(raw: &str) -> Result<Option<String>, InvalidContact> {
+    let email = raw.trim().to_lowercase();
+    if email.is_empty() {
+        return Ok(None);
+    }
+
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+                && domain.contains('.')
+                && domain.split('.').all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+        }
+        None => false,
+    };
+    if !valid {
+        return Err(InvalidContact(format!("البريد الإلكتروني غير صالح: {}", raw.trim())));
+    }
+    Ok(Some(email))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_and_international_spellings_of_a_number_normalize_alike() {
+        for raw in ["0770 123 4567", "770-123-4567", "00964 770 123 4567", "+964 (770) 123.4567", "9647701234567"] {
+            assert_eq!(normalize_phone(raw).unwrap().as_deref(), Some("+9647701234567"), "{raw}");
+        }
+        assert_eq!(normalize_phone("+44 20 7946 0958").unwrap().as_deref(), Some("+442079460958"));
+        assert_eq!(normalize_phone("  ").unwrap(), None);
+        assert!(normalize_phone("0770-ABC-4567").is_err());
+        assert!(normalize_phone("12").is_err());
+    }
+
+    #[test]
+    fn emails_are_lowercased_and_malformed_ones_rejected() {
+        assert_eq!(normalize_email("  Ali.Hassan@Example.IQ ").unwrap().as_deref(), Some("ali.hassan@example.iq"));
+        assert_eq!(normalize_email("").unwrap(), None);
+        for raw in ["ali.hassan", "@example.iq", "ali@example", "ali@@example.iq", "ali hassan@example.iq", "ali@-example.iq"] {
+            let err = normalize_email(raw).unwrap_err();
+            assert!(err.to_string().starts_with("البريد الإلكتروني غير صالح"), "{raw}: {err}");
+        }
+    }
+}
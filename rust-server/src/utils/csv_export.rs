@@ -0,0 +1,8 @@
+use super::csv_format::CsvFormat;
+
+// A record that can be written as one CSV line. Fields come back already rendered through the format
+// (quoted text, rounded numbers, configured dates) so every export follows the same settings.
+pub trait CsvRow {
+    fn headers() -> Vec<&'static str>;
+    fn to_record(&self, format: &CsvFormat) -> Vec<String>;
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvDateFormat {
+    Iso, // 2024-03-31
+    Dmy, // 31/03/2024
+}
+
+// Per-request overrides of the settings defaults, e.g. ?delimiter=;&decimal_separator=,&date_format=dmy
+#[derive(Debug, Default, Deserialize)]
+pub struct CsvFormatQuery {
+    pub delimiter: Option<String>,
+    pub decimal_separator: Option<String>,
+    pub date_format: Option<CsvDateFormat>,
+    pub decimals: Option<usize>,
+    pub bom: Option<bool>,
+}
+
+// How exported CSV renders separators, numbers and dates so accounting imports accept it
+#[derive(Debug, Clone)]
+pub struct CsvFormat {
+    pub delimiter: char,
+    pub decimal_separator: char,
+    pub date_format: CsvDateFormat,
+    pub decimals: usize,
+    pub bom: bool,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            decimal_separator: '.',
+            date_format: CsvDateFormat::Iso,
+            decimals: 2,
+            bom: true,
+        }
+    }
+}
+
+impl CsvFormat {
+    // Settings defaults with the request's overrides applied on top
+    pub async fn load(pool: &SqlitePool, overrides: &CsvFormatQuery) -> Result<Self> {
+        let mut format = Self::default();
+
+        let settings = sqlx::query(
+            "SELECT date_format, csv_delimiter, csv_decimal_separator, csv_decimals, csv_include_bom FROM settings WHERE id = 1"
+        )
+        .fetch_optional(pool)
+        .await?;
+        if let Some(row) = settings {
+            if let Some(delimiter) = row.get::<Option<String>, _>("csv_delimiter") {
+                format.delimiter = Self::parse_delimiter(&delimiter)?;
+            }
+            if let Some(separator) = row.get::<Option<String>, _>("csv_decimal_separator") {
+                format.decimal_separator = Self::parse_decimal_separator(&separator)?;
+            }
+            if let Some(decimals) = row.get::<Option<i64>, _>("csv_decimals") {
+                format.decimals = decimals.clamp(0, 6) as usize;
+            }
+            if let Some(bom) = row.get::<Option<i64>, _>("csv_include_bom") {
+                format.bom = bom == 1;
+            }
+            if row.get::<Option<String>, _>("date_format").as_deref() == Some("DD/MM/YYYY") {
+                format.date_format = CsvDateFormat::Dmy;
+            }
+        }
+
+        if let Some(ref delimiter) = overrides.delimiter {
+            format.delimiter = Self::parse_delimiter(delimiter)?;
+        }
+        if let Some(ref separator) = overrides.decimal_separator {
+            format.decimal_separator = Self::parse_decimal_separator(separator)?;
+        }
+        if let Some(date_format) = overrides.date_format {
+            format.date_format = date_format;
+        }
+        if let Some(decimals) = overrides.decimals {
+            if decimals > 6 {
+                return Err(anyhow::anyhow!("عدد المنازل العشرية يجب أن يكون بين 0 و 6"));
+            }
+            format.decimals = decimals;
+        }
+        if let Some(bom) = overrides.bom {
+            format.bom = bom;
+        }
+
+        if format.delimiter == format.decimal_separator {
+            return Err(anyhow::anyhow!("الفاصل العشري لا يمكن أن يكون نفس فاصل الأعمدة"));
+        }
+
+        Ok(format)
+    }
+
+    pub fn parse_delimiter(value: &str) -> Result<char> {
+        match value {
+            "," => Ok(','),
+            ";" => Ok(';'),
+            "|" => Ok('|'),
+            "\t" | "tab" => Ok('\t'),
+            _ => Err(anyhow::anyhow!("فاصل الأعمدة يجب أن يكون , أو ; أو | أو tab")),
+        }
+    }
+
+    pub fn parse_decimal_separator(value: &str) -> Result<char> {
+        match value {
+            "." => Ok('.'),
+            "," => Ok(','),
+            _ => Err(anyhow::anyhow!("الفاصل العشري يجب أن يكون . أو ,")),
+        }
+    }
+
+    // Quote fields holding the delimiter, quotes or line breaks
+    pub fn text(&self, value: &str) -> String {
+        if value.contains(self.delimiter) || value.contains('"') || value.contains('\n') || value.contains('\r') {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    // Rounded to the configured decimals, without thousands grouping
+    pub fn number(&self, value: f64) -> String {
+        let factor = 10f64.powi(self.decimals as i32);
+        let rounded = (value * factor).round() / factor;
+        let rounded = if rounded == 0.0 { 0.0 } else { rounded };
+        let formatted = format!("{:.*}", self.decimals, rounded);
+        if self.decimal_separator == '.' {
+            formatted
+        } else {
+            formatted.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+
+    pub fn date(&self, value: NaiveDate) -> String {
+        match self.date_format {
+            CsvDateFormat::Iso => value.format("%Y-%m-%d").to_string(),
+            CsvDateFormat::Dmy => value.format("%d/%m/%Y").to_string(),
+        }
+    }
+
+    pub fn datetime(&self, value: NaiveDateTime) -> String {
+        match self.date_format {
+            CsvDateFormat::Iso => value.format("%Y-%m-%d %H:%M:%S").to_string(),
+            CsvDateFormat::Dmy => value.format("%d/%m/%Y %H:%M:%S").to_string(),
+        }
+    }
+
+    // SQLite stores dates as text in a few shapes; anything unparseable is passed through
+    pub fn date_text(&self, value: &str) -> String {
+        let value = value.trim();
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f"))
+        {
+            return self.datetime(datetime);
+        }
+        match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            Ok(date) => self.date(date),
+            Err(_) => self.text(value),
+        }
+    }
+
+    pub fn row(&self, fields: &[String]) -> String {
+        let mut line = fields.join(&self.delimiter.to_string());
+        line.push_str("\r\n");
+        line
+    }
+
+    pub fn header(&self, columns: &[&str]) -> String {
+        let fields: Vec<String> = columns.iter().map(|column| self.text(column)).collect();
+        self.row(&fields)
+    }
+
+    // Excel only detects UTF-8 (and so Arabic text) when the file starts with a BOM
+    pub fn finish(&self, body: String) -> String {
+        if self.bom {
+            format!("\u{FEFF}{}", body)
+        } else {
+            body
+        }
+    }
+}
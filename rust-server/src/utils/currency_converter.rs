@@ -0,0 +1,118 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Converts between the base currency (IQD) and USD using the settings exchange rate.
+/// Rates are loaded once per converter so report loops don't hit the settings table
+/// for every row:
+/// - `on_date` in the past: the latest historical rate effective on or before that date
+/// - `on_date` today/None (or no history yet): the current settings rate
+pub struct CurrencyConverter {
+    current_rate: f64,
+    // (effective_date, rate) sorted ascending by date
+    history: Vec<(NaiveDate, f64)>,
+    resolved: Mutex<HashMap<NaiveDate, f64>>,
+}
+
+impl CurrencyConverter {
+    pub const BASE_CURRENCY: &'static str = "IQD";
+    pub const FOREIGN_CURRENCY: &'static str = "USD";
+
+    pub fn new(current_rate: f64, mut history: Vec<(NaiveDate, f64)>) -> Self {
+        history.sort_by_key(|(date, _)| *date);
+        Self {
+            current_rate,
+            history,
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn load(pool: &SqlitePool) -> Result<Self> {
+        let current_rate = sqlx::query("SELECT CAST(exchange_rate AS REAL) as exchange_rate FROM settings WHERE id = 1")
+            .fetch_optional(pool)
+            .await?
+            .and_then(|row| row.get::<Option<f64>, _>("exchange_rate"))
+            .filter(|rate| *rate > 0.0)
+            .unwrap_or(1.0);
+
+        let history = sqlx::query("SELECT effective_date, CAST(rate AS REAL) as rate FROM exchange_rate_history ORDER BY effective_date ASC")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|row| (row.get::<NaiveDate, _>("effective_date"), row.get::<f64, _>("rate")))
+            .collect();
+
+        Ok(Self::new(current_rate, history))
+    }
+
+    // Rate (IQD per USD) applicable on the given date
+    pub fn rate_on(&self, on_date: Option<NaiveDate>) -> f64 {
+        let date = match on_date {
+            Some(date) if date < chrono::Local::now().date_naive() => date,
+            _ => return self.current_rate,
+        };
+
+        let mut resolved = self.resolved.lock().unwrap();
+        *resolved.entry(date).or_insert_with(|| {
+            let idx = self.history.partition_point(|(effective, _)| *effective <= date);
+            if idx == 0 {
+                self.current_rate
+            } else {
+                self.history[idx - 1].1
+            }
+        })
+    }
+
+    pub fn convert(&self, amount: f64, from: &str, to: &str, on_date: Option<NaiveDate>) -> Result<f64> {
+        let from = from.to_uppercase();
+        let to = to.to_uppercase();
+        for currency in [&from, &to] {
+            if currency != Self::BASE_CURRENCY && currency != Self::FOREIGN_CURRENCY {
+                return Err(anyhow::anyhow!("Unsupported currency: {}", currency));
+            }
+        }
+
+        if from == to {
+            return Ok(amount);
+        }
+
+        let rate = self.rate_on(on_date);
+        if from == Self::FOREIGN_CURRENCY {
+            Ok(amount * rate)
+        } else {
+            Ok(amount / rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn past_dates_use_the_rate_effective_then() {
+        let converter = CurrencyConverter::new(1500.0, vec![(date(2025, 6, 1), 1320.0), (date(2024, 1, 1), 1310.0)]);
+
+        assert_eq!(converter.rate_on(Some(date(2024, 3, 15))), 1310.0);
+        assert_eq!(converter.rate_on(Some(date(2025, 6, 1))), 1320.0);
+        // Before the first entry there is nothing older, the current rate stands in
+        assert_eq!(converter.rate_on(Some(date(2023, 12, 31))), 1500.0);
+        assert_eq!(converter.rate_on(None), 1500.0);
+    }
+
+    #[test]
+    fn converts_both_ways_and_rejects_other_currencies() {
+        let converter = CurrencyConverter::new(1480.0, Vec::new());
+
+        assert_eq!(converter.convert(10.0, "usd", "iqd", None).unwrap(), 14800.0);
+        assert_eq!(converter.convert(2960.0, "IQD", "USD", None).unwrap(), 2.0);
+        assert_eq!(converter.convert(75.0, "IQD", "IQD", None).unwrap(), 75.0);
+        assert!(converter.convert(1.0, "EUR", "IQD", None).is_err());
+    }
+}
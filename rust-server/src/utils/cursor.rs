@@ -0,0 +1,51 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::fmt;
+
+// `after` cursor that could not be decoded; routes downcast to answer 400
+#[derive(Debug)]
+pub struct InvalidCursor;
+
+impl fmt::Display for InvalidCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("مؤشر الصفحة غير صالح")
+    }
+}
+
+impl std::error::Error for InvalidCursor {}
+
+// Position of the last row of a keyset page: its raw created_at text and id. Lists paged this way
+// sort by (created_at DESC, id DESC), so the next page is every row strictly before this key.
+// Clients only ever see the opaque encoded form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageCursor {
+    pub created_at: String,
+    pub id: i64,
+}
+
+impl PageCursor {
+    // Condition selecting the rows after this cursor; binds created_at, created_at, id in that order
+    pub fn condition(alias: &str) -> String {
+        format!(
+            "({alias}.created_at < ? OR ({alias}.created_at = ? AND {alias}.id < ?))",
+            alias = alias
+        )
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}|{}", self.id, self.created_at))
+    }
+
+    // A blank cursor means the first page
+    pub fn decode(raw: &str) -> Result<Option<Self>, InvalidCursor> {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        let bytes = URL_SAFE_NO_PAD.decode(raw).map_err(|_| InvalidCursor)?;
+        let text = String::from_utf8(bytes).map_err(|_| InvalidCursor)?;
+        let (id, created_at) = text.split_once('|').ok_or(InvalidCursor)?;
+        let id = id.parse().map_err(|_| InvalidCursor)?;
+        Ok(Some(Self { created_at: created_at.to_string(), id }))
+    }
+}
@@ -0,0 +1,285 @@
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+use anyhow::Result;
+
+use super::arabic_text;
+use super::invoice_pdf::{Align, BILL_FONT};
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+const LF: u8 = 0x0A;
+
+// Printable width of the common thermal rolls at 203 dpi
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReceiptWidth {
+    Mm58,
+    Mm80,
+}
+
+impl ReceiptWidth {
+    // "58", "58mm", "thermal-58mm" → Mm58; "80", "80mm", "thermal", "thermal-80mm" → Mm80
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().trim_start_matches("thermal").trim_start_matches('-') {
+            "58" | "58mm" => Ok(Self::Mm58),
+            "" | "80" | "80mm" => Ok(Self::Mm80),
+            _ => Err(anyhow::anyhow!("عرض الإيصال يجب أن يكون 58 أو 80 ملم")),
+        }
+    }
+
+    pub fn dots(&self) -> usize {
+        match self {
+            Self::Mm58 => 384,
+            Self::Mm80 => 576,
+        }
+    }
+
+    // Characters per line in the printer's default 12x24 font
+    pub fn columns(&self) -> usize {
+        match self {
+            Self::Mm58 => 32,
+            Self::Mm80 => 48,
+        }
+    }
+}
+
+// Collects ESC/POS commands for one receipt. Plain ASCII goes out as printer text; lines with Arabic
+// are shaped and sent as raster rows, since printer code pages cannot be relied on for joined Arabic.
+pub struct EscPosReceipt {
+    width: ReceiptWidth,
+    rtl: bool,
+    bytes: Vec<u8>,
+}
+
+impl EscPosReceipt {
+    pub fn new(width: ReceiptWidth, rtl: bool) -> Self {
+        // ESC @ resets the printer to its defaults
+        Self { width, rtl, bytes: vec![ESC, b'@'] }
+    }
+
+    pub fn line(&mut self, text: &str, align: Align, bold: bool) {
+        if text.is_ascii() {
+            self.set_align(align);
+            self.bytes.extend([ESC, b'E', bold as u8]);
+            self.bytes.extend(text.bytes().take(self.width.columns()));
+            self.bytes.push(LF);
+            self.bytes.extend([ESC, b'E', 0]);
+            self.set_align(Align::Start);
+        } else {
+            self.raster_text(&[(text, align)], 24.0, bold);
+        }
+    }
+
+    // Double width and height, for the shop name
+    pub fn title(&mut self, text: &str) {
+        if text.is_ascii() {
+            self.bytes.extend([GS, b'!', 0x11]);
+            self.set_align(Align::Center);
+            self.bytes.extend([ESC, b'E', 1]);
+            self.bytes.extend(text.bytes().take(self.width.columns() / 2));
+            self.bytes.push(LF);
+            self.bytes.extend([ESC, b'E', 0, GS, b'!', 0]);
+            self.set_align(Align::Start);
+        } else {
+            self.raster_text(&[(text, Align::Center)], 36.0, true);
+        }
+    }
+
+    // Label at the reading start, value at the reading end
+    pub fn pair(&mut self, label: &str, value: &str, bold: bool) {
+        if label.is_ascii() && value.is_ascii() {
+            let columns = self.width.columns();
+            let value: String = value.chars().take(columns).collect();
+            let label: String = label.chars().take(columns.saturating_sub(value.len() + 1)).collect();
+            let padding = " ".repeat(columns - label.len() - value.len());
+            let text = if self.rtl {
+                format!("{}{}{}", value, padding, label)
+            } else {
+                format!("{}{}{}", label, padding, value)
+            };
+            self.line(&text, Align::Start, bold);
+        } else {
+            self.raster_text(&[(label, Align::Start), (value, Align::End)], 24.0, bold);
+        }
+    }
+
+    pub fn rule(&mut self) {
+        let dashes = "-".repeat(self.width.columns());
+        self.line(&dashes, Align::Start, false);
+    }
+
+    // 8-bit grayscale image (dark < 128), centered; wider images are cropped to the paper
+    pub fn image(&mut self, width: usize, height: usize, pixels: &[u8]) {
+        let dots = self.width.dots();
+        let offset = dots.saturating_sub(width) / 2;
+        let mut row = vec![0u8; dots];
+        let mut bits = Vec::with_capacity(dots * height);
+        for y in 0..height {
+            row.fill(0);
+            for x in 0..width.min(dots) {
+                if pixels[y * width + x] < 128 {
+                    row[offset + x] = 1;
+                }
+            }
+            bits.extend_from_slice(&row);
+        }
+        self.raster(dots, height, &bits);
+    }
+
+    pub fn feed(&mut self, lines: u8) {
+        self.bytes.extend([ESC, b'd', lines]);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        // GS V 66 n: feed n lines to the cutter, then partial cut
+        self.bytes.extend([GS, b'V', 66, 3]);
+        self.bytes
+    }
+
+    fn set_align(&mut self, align: Align) {
+        let position = match (align, self.rtl) {
+            (Align::Center, _) => 1,
+            (Align::Start, false) | (Align::End, true) => 0,
+            (Align::Start, true) | (Align::End, false) => 2,
+        };
+        self.bytes.extend([ESC, b'a', position]);
+    }
+
+    // GS v 0: one bit per dot, rows of width/8 bytes, most significant bit on the left
+    fn raster(&mut self, width: usize, height: usize, bits: &[u8]) {
+        let row_bytes = width.div_ceil(8);
+        self.bytes.extend([GS, b'v', b'0', 0]);
+        self.bytes.extend([(row_bytes & 0xFF) as u8, (row_bytes >> 8) as u8]);
+        self.bytes.extend([(height & 0xFF) as u8, (height >> 8) as u8]);
+        for y in 0..height {
+            for chunk in 0..row_bytes {
+                let mut byte = 0u8;
+                for bit in 0..8 {
+                    let x = chunk * 8 + bit;
+                    if x < width && bits[y * width + x] == 1 {
+                        byte |= 0x80 >> bit;
+                    }
+                }
+                self.bytes.push(byte);
+            }
+        }
+    }
+
+    // One line of text drawn with the bundled font; each part is placed by its alignment across the full width
+    fn raster_text(&mut self, parts: &[(&str, Align)], px: f32, bold: bool) {
+        let Ok(face) = ttf_parser::Face::parse(BILL_FONT, 0) else {
+            return;
+        };
+        let dots = self.width.dots();
+        let scale = px / face.units_per_em() as f32;
+        let height = (px * 1.35).ceil() as usize;
+        let baseline = px * 1.0;
+        let mut rasterizer = Rasterizer::new(dots, height);
+
+        let glyph_width = |text: &str| -> f32 {
+            text.chars()
+                .filter_map(|c| face.glyph_index(c))
+                .filter_map(|glyph| face.glyph_hor_advance(glyph))
+                .fold(0.0, |sum, advance| sum + advance as f32 * scale)
+        };
+
+        let max_width = if parts.len() > 1 { dots as f32 / parts.len() as f32 - 8.0 } else { dots as f32 };
+        for &(text, align) in parts {
+            let text = fit(text, max_width, |candidate| glyph_width(&arabic_text::shape(candidate)));
+            let visual = arabic_text::visual(&text, self.rtl);
+            let text_width = glyph_width(&visual);
+            let x = match (align, self.rtl) {
+                (Align::Center, _) => (dots as f32 - text_width) / 2.0,
+                (Align::Start, false) | (Align::End, true) => 0.0,
+                (Align::Start, true) | (Align::End, false) => dots as f32 - text_width - 2.0,
+            };
+
+            let mut pen = x.max(0.0);
+            for c in visual.chars() {
+                let Some(glyph) = face.glyph_index(c) else {
+                    continue;
+                };
+                for offset in if bold { &[0.0, 1.0][..] } else { &[0.0][..] } {
+                    let mut outline = GlyphOutline {
+                        rasterizer: &mut rasterizer,
+                        origin: point(pen + offset, baseline),
+                        scale,
+                        start: point(0.0, 0.0),
+                        last: point(0.0, 0.0),
+                    };
+                    face.outline_glyph(glyph, &mut outline);
+                }
+                pen += face.glyph_hor_advance(glyph).unwrap_or(0) as f32 * scale;
+            }
+        }
+
+        let mut bits = vec![0u8; dots * height];
+        rasterizer.for_each_pixel(|index, coverage| {
+            if coverage >= 0.5 {
+                bits[index] = 1;
+            }
+        });
+        self.raster(dots, height, &bits);
+    }
+}
+
+// Shorten text with an ellipsis until `measure` fits `max_width`
+fn fit(text: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> String {
+    if measure(text) <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if measure(&candidate) <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+// Feeds a glyph outline (font units, y up) into the rasterizer (pixels, y down)
+struct GlyphOutline<'a> {
+    rasterizer: &'a mut Rasterizer,
+    origin: Point,
+    scale: f32,
+    start: Point,
+    last: Point,
+}
+
+impl GlyphOutline<'_> {
+    fn map(&self, x: f32, y: f32) -> Point {
+        point(self.origin.x + x * self.scale, self.origin.y - y * self.scale)
+    }
+}
+
+impl ttf_parser::OutlineBuilder for GlyphOutline<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.map(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.map(x, y);
+        self.rasterizer.draw_line(self.last, to);
+        self.last = to;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (control, to) = (self.map(x1, y1), self.map(x, y));
+        self.rasterizer.draw_quad(self.last, control, to);
+        self.last = to;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (c1, c2, to) = (self.map(x1, y1), self.map(x2, y2), self.map(x, y));
+        self.rasterizer.draw_cubic(self.last, c1, c2, to);
+        self.last = to;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.rasterizer.draw_line(self.last, self.start);
+        }
+        self.last = self.start;
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::Result;
+use printpdf::{Color, IndirectFontRef, Line, Mm, PdfDocument, Point, Rgb};
+use std::borrow::Cow;
+use std::io::Cursor;
+
+use super::arabic_text;
+
+// Bundled so bills render Arabic on every machine; DejaVu Sans carries the Arabic presentation forms
+pub const BILL_FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+
+const PT_TO_MM: f32 = 0.3528;
+const LINE_SPACING: f32 = 1.45;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontRole {
+    Header,
+    Body,
+    Footer,
+}
+
+// One column of a text row; start and width are fractions of the content width measured from the reading start
+#[derive(Debug, Clone)]
+pub struct PdfCell {
+    pub text: String,
+    pub start: f32,
+    pub width: f32,
+    pub align: Align,
+}
+
+impl PdfCell {
+    pub fn new(text: impl Into<String>, start: f32, width: f32, align: Align) -> Self {
+        Self { text: text.into(), start, width, align }
+    }
+
+    pub fn full(text: impl Into<String>, align: Align) -> Self {
+        Self::new(text, 0.0, 1.0, align)
+    }
+}
+
+enum Block {
+    Row { role: FontRole, size: f32, color: (f32, f32, f32), cells: Vec<PdfCell> },
+    Rule { color: (f32, f32, f32) },
+    Space(f32),
+}
+
+impl Block {
+    fn height(&self) -> f32 {
+        match self {
+            Block::Row { size, .. } => size * LINE_SPACING * PT_TO_MM,
+            Block::Rule { .. } => 2.0,
+            Block::Space(mm) => *mm,
+        }
+    }
+}
+
+// Paper and margins in millimetres; roll (thermal) paper has no fixed height and grows with the content
+#[derive(Debug, Clone)]
+pub struct PdfPage {
+    pub width: f32,
+    pub height: Option<f32>,
+    pub margin_top: f32,
+    pub margin_right: f32,
+    pub margin_bottom: f32,
+    pub margin_left: f32,
+}
+
+impl PdfPage {
+    // bill_paper_size / bill_orientation as stored in settings; unknown sizes fall back to A4
+    pub fn from_settings(paper_size: &str, orientation: &str, margins: [f32; 4]) -> Self {
+        let (width, height) = match paper_size.trim().to_lowercase().as_str() {
+            "a5" => (148.0, Some(210.0)),
+            "letter" => (215.9, Some(279.4)),
+            "thermal" | "thermal-80mm" | "80mm" => (80.0, None),
+            "thermal-58mm" | "58mm" => (58.0, None),
+            _ => (210.0, Some(297.0)),
+        };
+        let (width, height) = match (orientation.trim().to_lowercase().as_str(), height) {
+            ("landscape", Some(height)) => (height, Some(width)),
+            _ => (width, height),
+        };
+        let [top, right, bottom, left] = margins.map(|margin| margin.clamp(0.0, width / 4.0));
+        Self { width, height, margin_top: top, margin_right: right, margin_bottom: bottom, margin_left: left }
+    }
+
+    fn content_width(&self) -> f32 {
+        self.width - self.margin_left - self.margin_right
+    }
+}
+
+struct LoadedFont {
+    data: Cow<'static, [u8]>,
+    units_per_em: f32,
+}
+
+impl LoadedFont {
+    fn new(data: Cow<'static, [u8]>) -> Option<Self> {
+        let face = ttf_parser::Face::parse(&data, 0).ok()?;
+        // Only fonts able to draw joined Arabic and Latin digits are usable for the bill
+        if ['\u{FEB3}', '\u{FEFB}', '0', 'A'].iter().any(|&c| face.glyph_index(c).is_none()) {
+            return None;
+        }
+        let units_per_em = face.units_per_em() as f32;
+        Some(Self { data, units_per_em })
+    }
+
+    // Width in mm of already shaped text; glyphs the font lacks are skipped, as printpdf does
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let Ok(face) = ttf_parser::Face::parse(&self.data, 0) else {
+            return 0.0;
+        };
+        let units: f32 = text
+            .chars()
+            .filter_map(|c| face.glyph_index(c))
+            .filter_map(|glyph| face.glyph_hor_advance(glyph))
+            .fold(0.0, |sum, advance| sum + advance as f32);
+        units / self.units_per_em * size * PT_TO_MM
+    }
+}
+
+// Look a settings font name (e.g. "Arial", "Tahoma") up in the usual system font folders
+fn system_font(name: &str) -> Option<Vec<u8>> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\', '.']) {
+        return None;
+    }
+    let compact = name.replace(' ', "");
+    let candidates = [
+        format!("{}.ttf", compact.to_lowercase()),
+        format!("{}.ttf", compact),
+        format!("{}.ttf", name),
+        format!("{}-Regular.ttf", compact),
+    ];
+    let dirs = [
+        "C:\\Windows\\Fonts",
+        "/Library/Fonts",
+        "/System/Library/Fonts/Supplemental",
+        "/usr/share/fonts/truetype/msttcorefonts",
+        "/usr/share/fonts/truetype/dejavu",
+        "/usr/share/fonts/TTF",
+    ];
+    dirs.iter()
+        .flat_map(|dir| candidates.iter().map(move |file| std::path::Path::new(dir).join(file)))
+        .find_map(|path| std::fs::read(path).ok())
+}
+
+// Collects invoice rows top to bottom, then lays them out on pages (or one long roll) in finish()
+pub struct InvoicePdf {
+    title: String,
+    page: PdfPage,
+    rtl: bool,
+    fonts: Vec<LoadedFont>,
+    roles: [usize; 3],
+    text_color: (f32, f32, f32),
+    blocks: Vec<Block>,
+}
+
+impl InvoicePdf {
+    // Font names come from bill_font_header/body/footer; names that cannot be found or lack Arabic
+    // glyphs use the bundled font
+    pub fn new(title: &str, page: PdfPage, rtl: bool, font_names: [&str; 3], text_color: &str) -> Result<Self> {
+        let mut fonts = vec![LoadedFont::new(Cow::Borrowed(BILL_FONT))
+            .ok_or_else(|| anyhow::anyhow!("تعذر تحميل خط الفاتورة"))?];
+        let mut loaded_names: Vec<String> = Vec::new();
+        let mut roles = [0usize; 3];
+        for (role, name) in font_names.iter().enumerate() {
+            let key = name.trim().to_lowercase();
+            if let Some(index) = loaded_names.iter().position(|loaded| *loaded == key) {
+                roles[role] = index + 1;
+                continue;
+            }
+            if let Some(font) = system_font(name).and_then(|data| LoadedFont::new(Cow::Owned(data))) {
+                fonts.push(font);
+                loaded_names.push(key);
+                roles[role] = fonts.len() - 1;
+            }
+        }
+
+        Ok(Self {
+            title: title.to_string(),
+            page,
+            rtl,
+            fonts,
+            roles,
+            text_color: parse_color(text_color).unwrap_or((0.2, 0.2, 0.2)),
+            blocks: Vec::new(),
+        })
+    }
+
+    pub fn content_width(&self) -> f32 {
+        self.page.content_width()
+    }
+
+    fn font_index(&self, role: FontRole) -> usize {
+        match role {
+            FontRole::Header => self.roles[0],
+            FontRole::Body => self.roles[1],
+            FontRole::Footer => self.roles[2],
+        }
+    }
+
+    fn font(&self, role: FontRole) -> &LoadedFont {
+        &self.fonts[self.font_index(role)]
+    }
+
+    pub fn row(&mut self, role: FontRole, size: f32, cells: Vec<PdfCell>) {
+        let color = self.text_color;
+        self.row_colored(role, size, color, cells);
+    }
+
+    pub fn row_colored(&mut self, role: FontRole, size: f32, color: (f32, f32, f32), cells: Vec<PdfCell>) {
+        let cells = cells
+            .into_iter()
+            .map(|cell| {
+                let max_width = cell.width * self.content_width();
+                let text = self.fit(&cell.text, role, size, max_width);
+                PdfCell { text, ..cell }
+            })
+            .collect();
+        self.blocks.push(Block::Row { role, size, color, cells });
+    }
+
+    pub fn rule(&mut self, color: &str) {
+        self.blocks.push(Block::Rule { color: parse_color(color).unwrap_or((0.6, 0.6, 0.6)) });
+    }
+
+    pub fn space(&mut self, mm: f32) {
+        self.blocks.push(Block::Space(mm));
+    }
+
+    // Cut text that would overflow its column, ending it with an ellipsis
+    fn fit(&self, text: &str, role: FontRole, size: f32, max_width: f32) -> String {
+        let font = self.font(role);
+        if font.width(&arabic_text::shape(text), size) <= max_width {
+            return text.to_string();
+        }
+        let mut chars: Vec<char> = text.chars().collect();
+        while !chars.is_empty() {
+            chars.pop();
+            let candidate = format!("{}…", chars.iter().collect::<String>().trim_end());
+            if font.width(&arabic_text::shape(&candidate), size) <= max_width {
+                return candidate;
+            }
+        }
+        String::new()
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let content_height: f32 = self.blocks.iter().map(Block::height).sum();
+        let page_height = self
+            .page
+            .height
+            .unwrap_or(self.page.margin_top + content_height + self.page.margin_bottom);
+
+        let (doc, first_page, first_layer) =
+            PdfDocument::new(self.title.as_str(), Mm(self.page.width), Mm(page_height), "Invoice");
+        let font_refs: Vec<IndirectFontRef> = self
+            .fonts
+            .iter()
+            .map(|font| doc.add_external_font(Cursor::new(font.data.as_ref())))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| anyhow::anyhow!("تعذر تضمين خط الفاتورة: {}", e))?;
+
+        let mut layer = doc.get_page(first_page).get_layer(first_layer);
+        let mut y = page_height - self.page.margin_top;
+        let content_width = self.content_width();
+        let left = self.page.margin_left;
+
+        for block in &self.blocks {
+            let height = block.height();
+            if y - height < self.page.margin_bottom && self.page.height.is_some() {
+                let (page, page_layer) = doc.add_page(Mm(self.page.width), Mm(page_height), "Invoice");
+                layer = doc.get_page(page).get_layer(page_layer);
+                y = page_height - self.page.margin_top;
+            }
+
+            match block {
+                Block::Row { role, size, color, cells } => {
+                    let font_index = self.font_index(*role);
+                    let font = &self.fonts[font_index];
+                    let baseline = y - size * PT_TO_MM * 1.05;
+                    layer.set_fill_color(Color::Rgb(Rgb::new(color.0, color.1, color.2, None)));
+                    for cell in cells {
+                        if cell.text.is_empty() {
+                            continue;
+                        }
+                        let text = arabic_text::visual(&cell.text, self.rtl);
+                        let text_width = font.width(&text, *size);
+                        let cell_width = cell.width * content_width;
+                        // Columns run from the reading start: the right edge for RTL bills
+                        let cell_left = if self.rtl {
+                            left + (1.0 - cell.start - cell.width) * content_width
+                        } else {
+                            left + cell.start * content_width
+                        };
+                        let x = match (cell.align, self.rtl) {
+                            (Align::Center, _) => cell_left + (cell_width - text_width) / 2.0,
+                            (Align::Start, false) | (Align::End, true) => cell_left,
+                            (Align::Start, true) | (Align::End, false) => cell_left + cell_width - text_width,
+                        };
+                        layer.use_text(text, *size, Mm(x), Mm(baseline), &font_refs[font_index]);
+                    }
+                }
+                Block::Rule { color } => {
+                    let rule_y = y - height / 2.0;
+                    layer.set_outline_color(Color::Rgb(Rgb::new(color.0, color.1, color.2, None)));
+                    layer.set_outline_thickness(0.5);
+                    layer.add_line(Line {
+                        points: vec![
+                            (Point::new(Mm(left), Mm(rule_y)), false),
+                            (Point::new(Mm(left + content_width), Mm(rule_y)), false),
+                        ],
+                        is_closed: false,
+                    });
+                }
+                Block::Space(_) => {}
+            }
+            y -= height;
+        }
+
+        doc.save_to_bytes().map_err(|e| anyhow::anyhow!("تعذر إنشاء ملف PDF: {}", e))
+    }
+}
+
+// "#1f1f1f" → (r, g, b) in 0..1
+pub fn parse_color(hex: &str) -> Option<(f32, f32, f32)> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|v| v as f32 / 255.0);
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
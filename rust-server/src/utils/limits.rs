@@ -0,0 +1,61 @@
+use std::fmt;
+use std::sync::OnceLock;
+use tracing::warn;
+
+// Caps on how many lines a single request may carry, on top of the HTTP body limit: a sale or purchase
+// with hundreds of thousands of lines would otherwise hold a write transaction for minutes.
+// Each cap is read once from its environment variable and falls back to the default.
+pub struct ItemLimit {
+    what: &'static str,
+    env: &'static str,
+    default: usize,
+    value: OnceLock<usize>,
+}
+
+pub static SALE_ITEMS_LIMIT: ItemLimit = ItemLimit::new("أصناف فاتورة البيع", "MAX_SALE_ITEMS", 1000);
+pub static PURCHASE_ITEMS_LIMIT: ItemLimit = ItemLimit::new("أصناف فاتورة الشراء", "MAX_PURCHASE_ITEMS", 1000);
+pub static IMPORT_ROWS_LIMIT: ItemLimit = ItemLimit::new("صفوف ملف الاستيراد", "MAX_IMPORT_ROWS", 50_000);
+pub static BARCODE_LOOKUP_LIMIT: ItemLimit = ItemLimit::new("الباركودات المطلوبة", "MAX_BARCODE_LOOKUP", 5000);
+
+impl ItemLimit {
+    const fn new(what: &'static str, env: &'static str, default: usize) -> Self {
+        Self { what, env, default, value: OnceLock::new() }
+    }
+
+    pub fn max(&self) -> usize {
+        *self.value.get_or_init(|| match std::env::var(self.env) {
+            Ok(value) => match value.trim().parse::<usize>() {
+                Ok(max) if max > 0 => max,
+                _ => {
+                    warn!("Invalid {}={}, using {}", self.env, value, self.default);
+                    self.default
+                }
+            },
+            Err(_) => self.default,
+        })
+    }
+
+    pub fn check(&self, count: usize) -> Result<(), ItemLimitExceeded> {
+        let limit = self.max();
+        if count > limit {
+            return Err(ItemLimitExceeded { what: self.what, count, limit });
+        }
+        Ok(())
+    }
+}
+
+// Routes downcast to this to answer 400 rather than a generic failure
+#[derive(Debug)]
+pub struct ItemLimitExceeded {
+    pub what: &'static str,
+    pub count: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for ItemLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "عدد {} ({}) يتجاوز الحد المسموح ({})", self.what, self.count, self.limit)
+    }
+}
+
+impl std::error::Error for ItemLimitExceeded {}
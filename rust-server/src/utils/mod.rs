@@ -1,3 +1,24 @@
 pub mod sku_generator;
+pub mod currency_converter;
+pub mod row_json;
+pub mod csv_format;
+pub mod csv_export;
+pub mod arabic_text;
+pub mod invoice_pdf;
+pub mod escpos;
+pub mod receipt_labels;
+pub mod limits;
+pub mod contact;
+pub mod cursor;
 
 pub use sku_generator::*;
+pub use currency_converter::*;
+pub use row_json::*;
+pub use csv_format::*;
+pub use csv_export::*;
+pub use invoice_pdf::*;
+pub use escpos::*;
+pub use receipt_labels::*;
+pub use limits::*;
+pub use contact::*;
+pub use cursor::*;
@@ -0,0 +1,111 @@
+use anyhow::Result;
+
+// Language of the fixed labels on printed invoices and receipts. Arabic unless the settings or the
+// print request say otherwise; Bilingual prints "Arabic / English" side by side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReceiptLanguage {
+    Arabic,
+    English,
+    Bilingual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReceiptLabel {
+    Invoice,
+    SalesInvoice,
+    InvoiceNo,
+    Date,
+    Customer,
+    WalkInCustomer,
+    PaymentMethod,
+    Phone,
+    TaxNumber,
+    Item,
+    Quantity,
+    Price,
+    LineTotal,
+    Total,
+    Discount,
+    Tax,
+    Net,
+    Paid,
+    Remaining,
+    Notes,
+    ThankYou,
+}
+
+// (Arabic, English)
+fn translations(label: ReceiptLabel) -> (&'static str, &'static str) {
+    match label {
+        ReceiptLabel::Invoice => ("فاتورة", "Invoice"),
+        ReceiptLabel::SalesInvoice => ("فاتورة مبيعات", "Sales Invoice"),
+        ReceiptLabel::InvoiceNo => ("رقم الفاتورة", "Invoice No"),
+        ReceiptLabel::Date => ("التاريخ", "Date"),
+        ReceiptLabel::Customer => ("العميل", "Customer"),
+        ReceiptLabel::WalkInCustomer => ("زبون نقدي", "Walk-in customer"),
+        ReceiptLabel::PaymentMethod => ("طريقة الدفع", "Payment"),
+        ReceiptLabel::Phone => ("هاتف", "Phone"),
+        ReceiptLabel::TaxNumber => ("الرقم الضريبي", "Tax No"),
+        ReceiptLabel::Item => ("المادة", "Item"),
+        ReceiptLabel::Quantity => ("الكمية", "Qty"),
+        ReceiptLabel::Price => ("السعر", "Price"),
+        ReceiptLabel::LineTotal => ("المجموع", "Amount"),
+        ReceiptLabel::Total => ("الإجمالي", "Total"),
+        ReceiptLabel::Discount => ("الخصم", "Discount"),
+        ReceiptLabel::Tax => ("الضريبة", "Tax"),
+        ReceiptLabel::Net => ("الصافي", "Net"),
+        ReceiptLabel::Paid => ("المدفوع", "Paid"),
+        ReceiptLabel::Remaining => ("المتبقي", "Remaining"),
+        ReceiptLabel::Notes => ("ملاحظات", "Notes"),
+        ReceiptLabel::ThankYou => ("شكراً لتعاملكم معنا", "Thank you for your business"),
+    }
+}
+
+const PAYMENT_METHODS: &[(&str, &str, &str)] = &[
+    ("cash", "نقدي", "Cash"),
+    ("card", "بطاقة", "Card"),
+    ("credit", "آجل", "Credit"),
+    ("bank_transfer", "تحويل بنكي", "Bank transfer"),
+    ("installment", "أقساط", "Installments"),
+];
+
+impl ReceiptLanguage {
+    // "ar", "en", or "ar-en" / "bilingual" for both
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "ar" | "arabic" => Ok(Self::Arabic),
+            "en" | "english" => Ok(Self::English),
+            "ar-en" | "en-ar" | "both" | "bilingual" => Ok(Self::Bilingual),
+            _ => Err(anyhow::anyhow!("لغة الإيصال يجب أن تكون ar أو en أو ar-en")),
+        }
+    }
+
+    // English receipts always read left to right; the others follow rtl_mode
+    pub fn rtl(&self, rtl_mode: bool) -> bool {
+        match self {
+            Self::English => false,
+            Self::Arabic | Self::Bilingual => rtl_mode,
+        }
+    }
+
+    pub fn label(&self, label: ReceiptLabel) -> String {
+        let (arabic, english) = translations(label);
+        self.pick(arabic, english)
+    }
+
+    // Unknown methods are printed as stored
+    pub fn payment_method(&self, method: &str) -> String {
+        match PAYMENT_METHODS.iter().find(|(key, _, _)| *key == method) {
+            Some(&(_, arabic, english)) => self.pick(arabic, english),
+            None => method.to_string(),
+        }
+    }
+
+    fn pick(&self, arabic: &str, english: &str) -> String {
+        match self {
+            Self::Arabic => arabic.to_string(),
+            Self::English => english.to_string(),
+            Self::Bilingual => format!("{} / {}", arabic, english),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use serde_json::{Map, Value};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Column, Row, TypeInfo, ValueRef};
+
+// Convert a SQLite row into a JSON object keyed by column name, using each value's
+// runtime storage class (INTEGER/REAL/TEXT/NULL); BLOBs are skipped as null.
+pub fn sqlite_row_to_json(row: &SqliteRow) -> Value {
+    let mut object = Map::new();
+    for column in row.columns() {
+        let index = column.ordinal();
+        let value = match row.try_get_raw(index) {
+            Ok(raw) if !raw.is_null() => match raw.type_info().name() {
+                "INTEGER" => row.try_get::<i64, _>(index).map(Value::from).unwrap_or(Value::Null),
+                "REAL" => row.try_get::<f64, _>(index).map(Value::from).unwrap_or(Value::Null),
+                "TEXT" => row.try_get::<String, _>(index).map(Value::from).unwrap_or(Value::Null),
+                _ => Value::Null,
+            },
+            _ => Value::Null,
+        };
+        object.insert(column.name().to_string(), value);
+    }
+    Value::Object(object)
+}
+
+pub fn sqlite_rows_to_json(rows: &[SqliteRow]) -> Vec<Value> {
+    rows.iter().map(sqlite_row_to_json).collect()
+}
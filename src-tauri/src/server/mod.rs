@@ -110,17 +110,24 @@ pub async fn start_rust_server(app: AppHandle) -> Result<String, String> {
         }
     });
     
-    // Wait for server to be ready
-    for i in 0..10 {
+    // Wait for server to be ready: the port opens before startup (license check, schedulers) is done,
+    // so poll /api/ready rather than just the TCP connect
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .map_err(|e| e.to_string())?;
+    for i in 0..40 {
         println!("🔍 Attempt {} - Checking if server is ready...", i + 1);
-        if tokio::net::TcpStream::connect("127.0.0.1:39000").await.is_ok() {
-            println!("✅ Server is responding on port 39000");
-            return Ok("External Rust server started successfully".to_string());
+        if let Ok(response) = client.get("http://127.0.0.1:39000/api/ready").send().await {
+            if response.status().is_success() {
+                println!("✅ Server is ready on port 39000");
+                return Ok("External Rust server started successfully".to_string());
+            }
         }
         sleep(Duration::from_millis(500)).await;
     }
     
-    Err("Server started but not responding on port 39000".to_string())
+    Err("Server started but not ready on port 39000".to_string())
 }
 
 pub async fn check_server_status() -> Result<String, String> {